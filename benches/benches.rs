@@ -1,10 +1,12 @@
 use std::collections::HashSet;
 use std::path::PathBuf;
 
+use branchless::commands::smartlog::{compute_smartlog, SmartlogOptions};
 use branchless::core::dag::Dag;
 use branchless::core::effects::Effects;
 use branchless::core::eventlog::{EventLogDb, EventReplayer};
 use branchless::core::formatting::Glyphs;
+use branchless::core::node_descriptors::{CommitDiffStatDescriptor, DiffStatColumn};
 use branchless::core::rewrite::{BuildRebasePlanOptions, RebasePlanBuilder, RepoResource};
 use branchless::git::{CherryPickFastOptions, Commit, Diff, Repo};
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
@@ -157,13 +159,74 @@ fn bench_get_paths_touched_by_commits(c: &mut Criterion) {
     });
 }
 
+fn bench_diff_stats(c: &mut Criterion) {
+    let mut group = c.benchmark_group("diff stats");
+    group.sample_size(10);
+    let repo = get_repo();
+    let head_oid = repo.get_head_info().unwrap().oid.unwrap();
+    let commit_oids = {
+        let mut commit = repo.find_commit_or_fail(head_oid).unwrap();
+        let mut commit_oids = vec![commit.get_oid()];
+        for _i in 0..300 {
+            commit = match commit.get_parents().first() {
+                Some(parent) => parent.clone(),
+                None => break,
+            };
+            commit_oids.push(commit.get_oid());
+        }
+        commit_oids
+    };
+
+    group.bench_function("Repo::get_commit_diff_stat", |b| {
+        b.iter(|| {
+            for commit_oid in commit_oids.iter() {
+                let commit = repo.find_commit_or_fail(*commit_oid).unwrap();
+                repo.get_commit_diff_stat(&commit).unwrap();
+            }
+        });
+    });
+    group.bench_function("CommitDiffStatDescriptor::prefetch", |b| {
+        let pool = ThreadPoolBuilder::new().build().unwrap();
+        let repo_pool = RepoResource::new_pool(&repo).unwrap();
+        b.iter(|| {
+            let descriptor =
+                CommitDiffStatDescriptor::new(&repo, DiffStatColumn::Insertions).unwrap();
+            descriptor
+                .prefetch(&pool, &repo_pool, &commit_oids)
+                .unwrap();
+        });
+    });
+}
+
+fn bench_smartlog(c: &mut Criterion) {
+    let mut group = c.benchmark_group("smartlog");
+    group.sample_size(10);
+    for use_cache in [false, true] {
+        let name = if use_cache { "cached" } else { "uncached" };
+        group.bench_function(format!("compute_smartlog ({})", name), |b| {
+            let repo = get_repo();
+            let effects = Effects::new_suppress_for_test(Glyphs::text());
+            let options = SmartlogOptions {
+                use_cache,
+                ..Default::default()
+            };
+            // Populate the cache before measuring.
+            compute_smartlog(&effects, &repo, &options).unwrap();
+
+            b.iter(|| compute_smartlog(&effects, &repo, &options).unwrap());
+        });
+    }
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default().sample_size(10);
     targets =
         bench_cherry_pick_fast,
         bench_diff_fast,
+        bench_diff_stats,
         bench_get_paths_touched_by_commits,
         bench_rebase_plan,
+        bench_smartlog,
 );
 criterion_main!(benches);
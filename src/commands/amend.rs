@@ -3,29 +3,161 @@
 //! This command amends the HEAD commit with changes to files
 //! that are already tracked in the repo. Following the amend,
 //! the command performs a restack.
+//!
+//! The commit message can be updated at the same time with `--message` or
+//! `--edit`. The new message is applied in the same rewrite as the changes, so
+//! the descendants are only restacked once.
+//!
+//! Alternatively, with `--reparent`, the descendants of the amended commit are
+//! reparented onto it without applying their diffs, so that each descendant
+//! keeps its original tree.
 
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt::Write;
 use std::time::SystemTime;
 
+use eden_dag::DagAlgorithm;
 use eyre::Context;
 use itertools::Itertools;
 use tracing::instrument;
 
 use crate::commands::gc::mark_commit_reachable;
 use crate::commands::restack;
-use crate::core::config::get_restack_preserve_timestamps;
+use crate::commands::reword::edit_message;
+use crate::commands::BranchlessExitCode;
+use crate::core::config::{get_commit_gpg_sign, get_restack_preserve_timestamps};
+use crate::core::dag::{commit_set_to_vec, CommitSet, Dag};
 use crate::core::effects::Effects;
-use crate::core::eventlog::{Event, EventLogDb};
+use crate::core::eventlog::{Event, EventLogDb, EventReplayer, EventTransactionId};
 use crate::core::formatting::Pluralize;
-use crate::git::{AmendFastOptions, FileStatus, GitRunInfo, Repo};
+use crate::core::rewrite::move_branches;
+use crate::git::{AmendFastOptions, FileStatus, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo};
 use crate::opts::MoveOptions;
 
+/// Recreate each visible descendant of `old_oid` on top of `new_oid`, keeping
+/// the same tree, rather than rebasing its changes. Branches pointing to the
+/// descendants are moved to the recreated commits.
+#[instrument]
+fn reparent_descendants(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &mut EventLogDb,
+    event_tx_id: EventTransactionId,
+    now: SystemTime,
+    old_oid: NonZeroOid,
+    new_oid: NonZeroOid,
+    sign_commits: bool,
+) -> eyre::Result<usize> {
+    let references_snapshot = repo.get_references_snapshot()?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let dag = Dag::open_and_sync(
+        effects,
+        repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let visible_commits = dag.observed_commits.difference(&dag.obsolete_commits);
+    let descendants = dag
+        .query()
+        .descendants(CommitSet::from(old_oid))?
+        .difference(&CommitSet::from(old_oid))
+        .intersection(&visible_commits);
+    let descendants = dag.query().sort(&descendants)?;
+    // Visit parents before their children.
+    let descendant_oids = commit_set_to_vec(&descendants)?.into_iter().rev();
+
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let preserve_timestamps = get_restack_preserve_timestamps(repo)?;
+    let default_committer = if preserve_timestamps {
+        None
+    } else {
+        repo.get_default_signature(git_run_info, now)?
+    };
+    let mut rewritten_oids: HashMap<NonZeroOid, NonZeroOid> = HashMap::new();
+    rewritten_oids.insert(old_oid, new_oid);
+    let mut events = Vec::new();
+    for descendant_oid in descendant_oids {
+        let commit = repo.find_commit_or_fail(descendant_oid)?;
+        let parents = commit
+            .get_parent_oids()
+            .into_iter()
+            .map(|parent_oid| {
+                let parent_oid = rewritten_oids
+                    .get(&parent_oid)
+                    .copied()
+                    .unwrap_or(parent_oid);
+                repo.find_commit_or_fail(parent_oid)
+            })
+            .try_collect::<_, Vec<_>, _>()?;
+        let committer = if preserve_timestamps {
+            commit.get_committer()
+        } else {
+            match &default_committer {
+                Some(committer) => committer.to_owned(),
+                None => commit.get_committer().update_timestamp(now)?,
+            }
+        };
+        let message = commit.get_message_raw()?.to_string_lossy().into_owned();
+        let reparented_commit_oid = if sign_commits {
+            repo.create_signed_commit(
+                git_run_info,
+                None,
+                &commit.get_author(),
+                &committer,
+                &message,
+                &commit.get_tree()?,
+                parents.iter().collect(),
+            )?
+        } else {
+            repo.create_commit(
+                None,
+                &commit.get_author(),
+                &committer,
+                &message,
+                &commit.get_tree()?,
+                parents.iter().collect(),
+            )?
+        };
+        mark_commit_reachable(repo, reparented_commit_oid)
+            .wrap_err("Marking commit as reachable for GC purposes.")?;
+        rewritten_oids.insert(descendant_oid, reparented_commit_oid);
+        events.push(Event::RewriteEvent {
+            timestamp,
+            event_tx_id,
+            old_commit_oid: descendant_oid.into(),
+            new_commit_oid: reparented_commit_oid.into(),
+        });
+    }
+    let num_reparented_commits = events.len();
+    event_log_db.add_events(events)?;
+
+    rewritten_oids.remove(&old_oid);
+    let rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid> = rewritten_oids
+        .into_iter()
+        .map(|(old_oid, new_oid)| (old_oid, MaybeZeroOid::NonZero(new_oid)))
+        .collect();
+    move_branches(effects, git_run_info, repo, event_tx_id, &rewritten_oids)?;
+
+    Ok(num_reparented_commits)
+}
+
 /// Amends the existing HEAD commit.
+///
+/// If `messages` is non-empty or `edit` is set, the commit message is replaced
+/// as part of the same amend. Staged changes take precedence over uncommitted
+/// changes as usual; the new message applies either way.
 #[instrument]
 pub fn amend(
     effects: &Effects,
     git_run_info: &GitRunInfo,
+    reparent: bool,
+    messages: Vec<String>,
+    edit: bool,
     move_options: &MoveOptions,
 ) -> eyre::Result<isize> {
     let now = SystemTime::now();
@@ -54,6 +186,41 @@ pub fn amend(
         return Ok(1);
     }
 
+    // Determine the new message before rewriting anything, so that aborting
+    // the edit leaves the repository untouched.
+    let new_message = if messages.is_empty() && !edit {
+        None
+    } else {
+        let message = if messages.is_empty() {
+            head_commit
+                .get_message_raw()?
+                .to_string_lossy()
+                .into_owned()
+        } else {
+            git2::message_prettify(messages.join("\n\n"), None)
+                .wrap_err("Cleaning up commit message")?
+        };
+        let message = if edit {
+            edit_message(
+                git_run_info,
+                &repo,
+                &message,
+                &head_oid.to_string(),
+                "amend",
+            )?
+        } else {
+            message
+        };
+        if message.trim().is_empty() {
+            writeln!(
+                effects.get_output_stream(),
+                "Aborting amend due to empty commit message."
+            )?;
+            return Ok(1);
+        }
+        Some(message)
+    };
+
     let event_tx_id = event_log_db.make_transaction_id(now, "amend")?;
     let staged_index_paths = repo.get_staged_paths()?;
     let (opts, dirty_working_tree) = if !staged_index_paths.is_empty() {
@@ -83,7 +250,7 @@ pub fn amend(
         };
         (opts, true)
     };
-    if opts.is_empty() {
+    if opts.is_empty() && new_message.is_none() {
         writeln!(
             effects.get_output_stream(),
             "There are no uncommitted or staged changes. Nothing to amend."
@@ -91,26 +258,55 @@ pub fn amend(
         return Ok(0);
     }
 
-    let amended_tree = repo.amend_fast(&head_commit, &opts)?;
+    let amended_tree = if opts.is_empty() {
+        None
+    } else {
+        Some(repo.amend_fast(&head_commit, &opts)?)
+    };
     let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
 
     let (author, committer) = (head_commit.get_author(), head_commit.get_committer());
     let (author, committer) = if get_restack_preserve_timestamps(&repo)? {
         (author, committer)
     } else {
-        (
-            author.update_timestamp(now)?,
-            committer.update_timestamp(now)?,
-        )
+        let committer = match repo.get_default_signature(git_run_info, now)? {
+            Some(committer) => committer,
+            None => committer.update_timestamp(now)?,
+        };
+        (author.update_timestamp(now)?, committer)
     };
 
-    let amended_commit_oid = head_commit.amend_commit(
-        Some("HEAD"),
-        Some(&author),
-        Some(&committer),
-        None,
-        Some(&amended_tree),
-    )?;
+    let sign_commits = move_options.sign_commits && get_commit_gpg_sign(&repo)?;
+    let amended_commit_oid = if sign_commits {
+        let message = match new_message.as_deref() {
+            Some(message) => message.to_owned(),
+            None => head_commit
+                .get_message_raw()?
+                .to_string_lossy()
+                .into_owned(),
+        };
+        let tree = match amended_tree {
+            Some(tree) => tree,
+            None => head_commit.get_tree()?,
+        };
+        repo.create_signed_commit(
+            git_run_info,
+            Some("HEAD"),
+            &author,
+            &committer,
+            &message,
+            &tree,
+            head_commit.get_parents().iter().collect(),
+        )?
+    } else {
+        head_commit.amend_commit(
+            Some("HEAD"),
+            Some(&author),
+            Some(&committer),
+            new_message.as_deref(),
+            amended_tree.as_ref(),
+        )?
+    };
     mark_commit_reachable(&repo, amended_commit_oid)
         .wrap_err("Marking commit as reachable for GC purposes.")?;
 
@@ -121,22 +317,49 @@ pub fn amend(
         new_commit_oid: amended_commit_oid.into(),
     }])?;
 
-    if let AmendFastOptions::FromWorkingCopy { .. } = opts {
-        // TODO(#201): Figure out a way to perform "fast amend" on the working copy without needing a reset.
-        git_run_info.run(effects, Some(event_tx_id), &["reset"])?;
+    if let AmendFastOptions::FromWorkingCopy { status_entries } = &opts {
+        if !status_entries.is_empty() {
+            // TODO(#201): Figure out a way to perform "fast amend" on the working copy without needing a reset.
+            git_run_info.run(effects, Some(event_tx_id), &["reset"])?;
+        }
     }
 
-    let restack_exit_code = restack::restack(
-        effects,
-        git_run_info,
-        vec![head_oid.to_string()],
-        move_options,
-    )?;
-    if restack_exit_code != 0 {
-        return Ok(restack_exit_code);
+    if reparent {
+        let num_reparented_commits = reparent_descendants(
+            effects,
+            git_run_info,
+            &repo,
+            &mut event_log_db,
+            event_tx_id,
+            now,
+            head_oid,
+            amended_commit_oid,
+            sign_commits,
+        )?;
+        writeln!(
+            effects.get_output_stream(),
+            "Reparented {}.",
+            Pluralize {
+                determiner: None,
+                amount: num_reparented_commits.try_into()?,
+                unit: ("descendant commit", "descendant commits"),
+            },
+        )?;
+    } else {
+        let restack_exit_code = restack::restack(
+            effects,
+            git_run_info,
+            vec![head_oid.to_string()],
+            move_options,
+            false,
+        )?;
+        if !BranchlessExitCode::is_ok(restack_exit_code) {
+            return Ok(restack_exit_code);
+        }
     }
 
     match opts {
+        _ if opts.is_empty() => {}
         AmendFastOptions::FromIndex { paths } => {
             let staged_changes = Pluralize {
                 determiner: None,
@@ -163,5 +386,8 @@ pub fn amend(
             )?;
         }
     }
+    if new_message.is_some() {
+        writeln!(effects.get_output_stream(), "Amended commit message.")?;
+    }
     Ok(0)
 }
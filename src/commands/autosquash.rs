@@ -0,0 +1,579 @@
+//! Fold `fixup!` and `squash!` commits into the commits they refer to.
+//!
+//! `git commit --fixup` and `git commit --squash` create commits whose
+//! subjects name an earlier commit in the stack. Rather than reordering the
+//! stack as `git rebase --autosquash` does, the changes of each such commit
+//! are applied directly to the commit it refers to, and the rest of the stack
+//! is then restacked on top of the result.
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use eden_dag::DagAlgorithm;
+use eyre::Context;
+use itertools::Itertools;
+use rayon::ThreadPoolBuilder;
+use tracing::instrument;
+
+use crate::commands::gc::mark_commit_reachable;
+use crate::commands::smartlog::smartlog;
+use crate::commands::BranchlessExitCode;
+use crate::core::commit_message::combine_commit_messages;
+use crate::core::config::{
+    get_commit_gpg_sign, get_commit_template, get_restack_preserve_timestamps,
+};
+use crate::core::dag::{sort_commit_set, CommitSet, Dag};
+use crate::core::effects::Effects;
+use crate::core::eventlog::{Event, EventLogDb, EventReplayer, EventTransactionId};
+use crate::core::formatting::printable_styled_string;
+use crate::core::rewrite::{
+    execute_rebase_plan, find_rewrite_target, move_branches, BuildRebasePlanOptions,
+    ExecuteRebasePlanOptions, ExecuteRebasePlanResult, RebasePlanBuilder, RepoResource,
+};
+use crate::git::{
+    check_out_commit, CheckOutCommitOptions, CherryPickFastError, CherryPickFastOptions, Commit,
+    GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
+};
+use crate::opts::MoveOptions;
+
+const FIXUP_PREFIX: &str = "fixup! ";
+const SQUASH_PREFIX: &str = "squash! ";
+
+/// How the changes of a commit should be folded into its target commit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FixupKind {
+    /// Fold in the changes, and discard the commit message.
+    Fixup,
+
+    /// Fold in the changes, and append the commit message to the target
+    /// commit's message.
+    Squash,
+}
+
+/// A `fixup!` or `squash!` commit, along with the commit it refers to.
+#[derive(Debug)]
+struct Fixup {
+    oid: NonZeroOid,
+    kind: FixupKind,
+    target_oid: NonZeroOid,
+}
+
+/// Parse the subject line of a `fixup!` or `squash!` commit. Returns the kind
+/// of fixup and the subject of the commit it refers to, or `None` if this is
+/// not a fixup commit.
+fn parse_fixup_subject(subject: &str) -> Option<(FixupKind, &str)> {
+    let (kind, mut target) = if let Some(target) = subject.strip_prefix(FIXUP_PREFIX) {
+        (FixupKind::Fixup, target)
+    } else if let Some(target) = subject.strip_prefix(SQUASH_PREFIX) {
+        (FixupKind::Squash, target)
+    } else {
+        return None;
+    };
+
+    // Running `git commit --fixup` on a fixup commit produces a subject like
+    // `fixup! fixup! foo`, which refers to the original commit `foo`.
+    while let Some(inner_target) = target
+        .strip_prefix(FIXUP_PREFIX)
+        .or_else(|| target.strip_prefix(SQUASH_PREFIX))
+    {
+        target = inner_target;
+    }
+    Some((kind, target))
+}
+
+fn is_fixup_commit(commit: &Commit) -> eyre::Result<bool> {
+    let summary = commit.get_summary()?;
+    Ok(parse_fixup_subject(&summary.to_string_lossy()).is_some())
+}
+
+/// Find the commit which the given fixup commit refers to. This is the closest
+/// draft ancestor which isn't itself a fixup commit, and whose subject is
+/// `target_subject` or whose OID starts with `target_subject`.
+fn find_fixup_target(
+    draft_commits: &HashSet<NonZeroOid>,
+    fixup_commit: &Commit,
+    target_subject: &str,
+) -> eyre::Result<Option<NonZeroOid>> {
+    let is_oid_prefix =
+        target_subject.len() >= 4 && target_subject.chars().all(|c| c.is_ascii_hexdigit());
+    let mut current = fixup_commit.get_only_parent();
+    while let Some(commit) = current {
+        if !draft_commits.contains(&commit.get_oid()) {
+            break;
+        }
+        if !is_fixup_commit(&commit)? {
+            let summary = commit.get_summary()?;
+            if summary.to_string_lossy() == target_subject
+                || (is_oid_prefix && commit.get_oid().to_string().starts_with(target_subject))
+            {
+                return Ok(Some(commit.get_oid()));
+            }
+        }
+        current = commit.get_only_parent();
+    }
+    Ok(None)
+}
+
+/// Get the message to append to the target commit's message for a `squash!`
+/// commit. This is the commit message without its `squash!` subject line.
+fn get_squash_message_body(commit: &Commit) -> eyre::Result<Option<String>> {
+    let message = commit.get_message_raw()?.to_string_lossy().into_owned();
+    let body = match message.split_once('\n') {
+        Some((_subject, body)) => body.trim(),
+        None => "",
+    };
+    if body.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(format!("{}\n", body)))
+    }
+}
+
+/// Fold each `fixup!` and `squash!` draft commit into the commit it refers to,
+/// and restack the descendants of both commits. When `squash!` commit messages
+/// are combined, they are formatted with `commit_template`, or with
+/// `branchless.commitTemplate` if not provided.
+#[instrument]
+pub fn autosquash(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    commit_template: Option<&str>,
+    move_options: &MoveOptions,
+) -> eyre::Result<isize> {
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
+
+    let references_snapshot = repo.get_references_snapshot()?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let public_commits = dag.query_public_commits()?;
+    let active_heads = dag.query_active_heads(
+        &public_commits,
+        &dag.observed_commits.difference(&dag.obsolete_commits),
+    )?;
+    let draft_commits = dag
+        .query()
+        .range(public_commits.clone(), active_heads)?
+        .difference(&public_commits);
+    let draft_commits = sort_commit_set(&repo, &dag, &draft_commits)?;
+    let draft_commit_oids: HashSet<NonZeroOid> = draft_commits
+        .iter()
+        .map(|commit| commit.get_oid())
+        .collect();
+
+    let mut fixups: Vec<Fixup> = Vec::new();
+    for commit in draft_commits.iter() {
+        let summary = commit.get_summary()?;
+        let summary = summary.to_string_lossy();
+        let (kind, target_subject) = match parse_fixup_subject(&summary) {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+        match find_fixup_target(&draft_commit_oids, commit, target_subject)? {
+            Some(target_oid) => fixups.push(Fixup {
+                oid: commit.get_oid(),
+                kind,
+                target_oid,
+            }),
+            None => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Skipping {}, because the commit it refers to could not be found.",
+                    printable_styled_string(
+                        effects.get_glyphs(),
+                        commit.friendly_describe(effects.get_glyphs())?
+                    )?,
+                )?;
+            }
+        }
+    }
+    if fixups.is_empty() {
+        writeln!(effects.get_output_stream(), "No fixup commits to fold.")?;
+        return Ok(0);
+    }
+
+    // Fixups are in topological order, so fixups to the same target are
+    // applied in the order in which they were made.
+    let target_oids: Vec<NonZeroOid> = fixups
+        .iter()
+        .map(|fixup| fixup.target_oid)
+        .unique()
+        .collect();
+    let preserve_timestamps = get_restack_preserve_timestamps(&repo)?;
+    let sign_commits = move_options.sign_commits && get_commit_gpg_sign(&repo)?;
+    let default_committer = if preserve_timestamps {
+        None
+    } else {
+        repo.get_default_signature(git_run_info, now)?
+    };
+    let commit_template = match commit_template {
+        Some(commit_template) => Some(commit_template.to_string()),
+        None => get_commit_template(&repo)?,
+    };
+    let mut folded_oids: HashMap<NonZeroOid, NonZeroOid> = HashMap::new();
+    for target_oid in target_oids.iter().copied() {
+        let target = repo.find_commit_or_fail(target_oid)?;
+        let committer = if preserve_timestamps {
+            target.get_committer()
+        } else {
+            match &default_committer {
+                Some(committer) => committer.to_owned(),
+                None => target.get_committer().update_timestamp(now)?,
+            }
+        };
+        let mut message = target.get_message_raw()?.to_string_lossy().into_owned();
+        let mut messages = vec![message.clone()];
+        let mut folded_oid = target_oid;
+        for fixup in fixups.iter().filter(|fixup| fixup.target_oid == target_oid) {
+            let fixup_commit = repo.find_commit_or_fail(fixup.oid)?;
+            let folded_commit = repo.find_commit_or_fail(folded_oid)?;
+            let tree = match repo.cherry_pick_fast(
+                &fixup_commit,
+                &folded_commit,
+                &CherryPickFastOptions {
+                    reuse_parent_tree_if_possible: false,
+                },
+            )? {
+                Ok(tree) => tree,
+                Err(CherryPickFastError::MergeConflict { conflicting_paths }) => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Cannot fold {} into {}, because it would cause a merge conflict in: {}",
+                        printable_styled_string(
+                            effects.get_glyphs(),
+                            fixup_commit.friendly_describe(effects.get_glyphs())?
+                        )?,
+                        printable_styled_string(
+                            effects.get_glyphs(),
+                            target.friendly_describe(effects.get_glyphs())?
+                        )?,
+                        conflicting_paths
+                            .iter()
+                            .sorted()
+                            .map(|path| path.to_string_lossy())
+                            .join(", "),
+                    )?;
+                    writeln!(effects.get_output_stream(), "No commits were changed.")?;
+                    return Ok(1);
+                }
+            };
+
+            if fixup.kind == FixupKind::Squash {
+                if let Some(body) = get_squash_message_body(&fixup_commit)? {
+                    messages.push(body);
+                    message = combine_commit_messages(
+                        commit_template.as_deref(),
+                        &messages.iter().map(String::as_str).collect_vec(),
+                    );
+                }
+            }
+            folded_oid = repo.create_commit(
+                None,
+                &target.get_author(),
+                &committer,
+                &message,
+                &tree,
+                target.get_parents().iter().collect(),
+            )?;
+        }
+        if sign_commits && folded_oid != target_oid {
+            // Only sign the final folded commit, rather than each intermediate
+            // commit produced while applying the fixups.
+            let folded_commit = repo.find_commit_or_fail(folded_oid)?;
+            folded_oid = repo.create_signed_commit(
+                git_run_info,
+                None,
+                &folded_commit.get_author(),
+                &folded_commit.get_committer(),
+                &folded_commit.get_message_raw()?.to_string_lossy(),
+                &folded_commit.get_tree()?,
+                folded_commit.get_parents().iter().collect(),
+            )?;
+        }
+        folded_oids.insert(target_oid, folded_oid);
+    }
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "autosquash")?;
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let mut events = Vec::new();
+    for (old_oid, new_oid) in folded_oids.iter().sorted().chain(
+        fixups
+            .iter()
+            .map(|fixup| (&fixup.oid, &folded_oids[&fixup.target_oid])),
+    ) {
+        mark_commit_reachable(&repo, *new_oid)
+            .wrap_err("Marking commit as reachable for GC purposes.")?;
+        events.push(Event::RewriteEvent {
+            timestamp,
+            event_tx_id,
+            old_commit_oid: (*old_oid).into(),
+            new_commit_oid: (*new_oid).into(),
+        });
+    }
+    event_log_db.add_events(events)?;
+
+    let rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid> = folded_oids
+        .iter()
+        .map(|(target_oid, folded_oid)| (*target_oid, MaybeZeroOid::NonZero(*folded_oid)))
+        .collect();
+    let exit_code =
+        move_branches_and_head(effects, git_run_info, &repo, event_tx_id, &rewritten_oids)?;
+    if exit_code != 0 {
+        return Ok(exit_code);
+    }
+
+    let fixup_oids: HashSet<NonZeroOid> = fixups.iter().map(|fixup| fixup.oid).collect();
+    let exit_code = restack_descendants(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        now,
+        &fixup_oids,
+        &folded_oids,
+        move_options,
+    )?;
+    if exit_code != 0 {
+        return Ok(exit_code);
+    }
+
+    // A fixup commit is replaced by its parent in the stack, so its branches
+    // are moved to the newest version of its parent. This keeps a branch at
+    // the top of the stack at the top of the stack.
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid> = HashMap::new();
+    for fixup in fixups.iter() {
+        let parent_oid = get_stack_parent_oid(&repo, &fixup_oids, fixup.oid)?;
+        let new_oid = find_rewrite_target(&event_replayer, event_cursor, parent_oid)
+            .unwrap_or(MaybeZeroOid::NonZero(parent_oid));
+        rewritten_oids.insert(fixup.oid, new_oid);
+    }
+    let exit_code =
+        move_branches_and_head(effects, git_run_info, &repo, event_tx_id, &rewritten_oids)?;
+    if exit_code != 0 {
+        return Ok(exit_code);
+    }
+
+    for fixup in fixups.iter() {
+        writeln!(
+            effects.get_output_stream(),
+            "Folded {} into {}.",
+            printable_styled_string(
+                effects.get_glyphs(),
+                repo.friendly_describe_commit_from_oid(effects.get_glyphs(), fixup.oid)?
+            )?,
+            printable_styled_string(
+                effects.get_glyphs(),
+                repo.friendly_describe_commit_from_oid(effects.get_glyphs(), fixup.target_oid)?
+            )?,
+        )?;
+    }
+    smartlog(effects, git_run_info, &Default::default())
+}
+
+/// Get the parent of the given commit, skipping over any fixup commits.
+fn get_stack_parent_oid(
+    repo: &Repo,
+    fixup_oids: &HashSet<NonZeroOid>,
+    oid: NonZeroOid,
+) -> eyre::Result<NonZeroOid> {
+    let mut oid = oid;
+    loop {
+        oid = repo
+            .find_commit_or_fail(oid)?
+            .get_only_parent()
+            .map(|parent| parent.get_oid())
+            .ok_or_else(|| eyre::eyre!("Fixup commit should have one parent: {:?}", oid))?;
+        if !fixup_oids.contains(&oid) {
+            return Ok(oid);
+        }
+    }
+}
+
+/// Move any branches pointing to a commit in `rewritten_oids` to its new
+/// commit. If `HEAD` pointed to one of these commits, check out the new commit
+/// instead, and then check out the branch again once it's been moved.
+#[instrument]
+fn move_branches_and_head(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_tx_id: EventTransactionId,
+    rewritten_oids: &HashMap<NonZeroOid, MaybeZeroOid>,
+) -> eyre::Result<isize> {
+    let head_info = repo.get_head_info()?;
+    let head_new_oid = head_info
+        .oid
+        .and_then(|head_oid| match rewritten_oids.get(&head_oid) {
+            Some(MaybeZeroOid::NonZero(new_oid)) => Some(*new_oid),
+            _ => None,
+        });
+    if let Some(head_new_oid) = head_new_oid {
+        let exit_code = check_out_commit(
+            effects,
+            git_run_info,
+            Some(event_tx_id),
+            Some(head_new_oid.to_string()),
+            &CheckOutCommitOptions {
+                additional_args: &[],
+                render_smartlog: false,
+            },
+        )?;
+        if exit_code != 0 {
+            return Ok(exit_code);
+        }
+    }
+    move_branches(effects, git_run_info, repo, event_tx_id, rewritten_oids)?;
+    if head_new_oid.is_some() {
+        if let Some(reference_name) = &head_info.reference_name {
+            let exit_code = check_out_commit(
+                effects,
+                git_run_info,
+                Some(event_tx_id),
+                Some(reference_name),
+                &CheckOutCommitOptions {
+                    additional_args: &[],
+                    render_smartlog: false,
+                },
+            )?;
+            if exit_code != 0 {
+                return Ok(exit_code);
+            }
+        }
+    }
+    Ok(0)
+}
+
+/// Move the children of each target commit onto the commit it was folded
+/// into, and the children of each fixup commit onto the new version of its
+/// parent. The fixup commits themselves are left out of the rebase.
+#[instrument]
+fn restack_descendants(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    event_tx_id: EventTransactionId,
+    now: SystemTime,
+    fixup_oids: &HashSet<NonZeroOid>,
+    folded_oids: &HashMap<NonZeroOid, NonZeroOid>,
+    move_options: &MoveOptions,
+) -> eyre::Result<isize> {
+    let references_snapshot = repo.get_references_snapshot()?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let dag = Dag::open_and_sync(
+        effects,
+        repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+    let visible_commits = dag.observed_commits.difference(&dag.obsolete_commits);
+    let get_children_oids = |oid: NonZeroOid| -> eyre::Result<Vec<NonZeroOid>> {
+        let children = dag
+            .query()
+            .children(CommitSet::from(oid))?
+            .intersection(&visible_commits);
+        let mut result = Vec::new();
+        for child in children.iter()? {
+            let child_oid = NonZeroOid::try_from(child?)?;
+            if !fixup_oids.contains(&child_oid) {
+                result.push(child_oid);
+            }
+        }
+        Ok(result)
+    };
+
+    let mut children_moves = Vec::new();
+    for (target_oid, folded_oid) in folded_oids.iter().sorted() {
+        for child_oid in get_children_oids(*target_oid)? {
+            children_moves.push((child_oid, *folded_oid));
+        }
+    }
+    for fixup_oid in fixup_oids.iter().sorted() {
+        let parent_oid = get_stack_parent_oid(repo, fixup_oids, *fixup_oid)?;
+        let dest_oid = folded_oids.get(&parent_oid).copied().unwrap_or(parent_oid);
+        for child_oid in get_children_oids(*fixup_oid)? {
+            children_moves.push((child_oid, dest_oid));
+        }
+    }
+    if children_moves.is_empty() {
+        return Ok(0);
+    }
+
+    let MoveOptions {
+        force_in_memory,
+        force_on_disk,
+        detect_duplicate_commits_via_patch_id,
+        resolve_merge_conflicts,
+        sign_commits,
+        dump_rebase_constraints,
+        dump_rebase_plan,
+    } = *move_options;
+    let pool = ThreadPoolBuilder::new().build()?;
+    let repo_pool = RepoResource::new_pool(repo)?;
+    let rebase_plan = {
+        let mut builder = RebasePlanBuilder::new(&dag);
+        for fixup_oid in fixup_oids.iter().sorted() {
+            builder.remove_commit(*fixup_oid)?;
+        }
+        for (child_oid, dest_oid) in children_moves {
+            builder.move_subtree(child_oid, dest_oid)?;
+        }
+        builder.build(
+            effects,
+            &pool,
+            &repo_pool,
+            &BuildRebasePlanOptions {
+                dump_rebase_constraints,
+                dump_rebase_plan,
+                detect_duplicate_commits_via_patch_id,
+            },
+        )?
+    };
+    let rebase_plan = match rebase_plan {
+        Ok(None) => return Ok(0),
+        Ok(Some(rebase_plan)) => rebase_plan,
+        Err(err) => {
+            err.describe(effects, repo)?;
+            return Ok(1);
+        }
+    };
+
+    let options = ExecuteRebasePlanOptions {
+        now,
+        event_tx_id,
+        preserve_timestamps: get_restack_preserve_timestamps(repo)?,
+        force_in_memory,
+        force_on_disk,
+        resolve_merge_conflicts,
+        sign_commits,
+        check_out_commit_options: CheckOutCommitOptions {
+            additional_args: &[],
+            render_smartlog: false,
+        },
+    };
+    match execute_rebase_plan(effects, git_run_info, repo, &rebase_plan, &options)? {
+        ExecuteRebasePlanResult::Succeeded => Ok(0),
+        ExecuteRebasePlanResult::DeclinedToMerge { merge_conflict } => {
+            merge_conflict.describe(effects, repo)?;
+            Ok(BranchlessExitCode::MergeConflict.into())
+        }
+        ExecuteRebasePlanResult::Failed { exit_code } => Ok(exit_code),
+    }
+}
@@ -9,7 +9,7 @@ use bugreport::format::Markdown;
 use bugreport::report::ReportEntry;
 use itertools::Itertools;
 
-use crate::commands::smartlog::{make_smartlog_graph, render_graph};
+use crate::commands::smartlog::{make_smartlog_graph, render_graph, SubjectLayout};
 use crate::core::dag::Dag;
 use crate::core::effects::Effects;
 use crate::core::eventlog::{Event, EventCursor, EventLogDb, EventReplayer};
@@ -35,17 +35,37 @@ fn redact_event(redactor: &Redactor, event: &Event) -> String {
             event_tx_id: _,
             commit_oid: _,
         }
-        | Event::ObsoleteEvent {
+        | Event::UnobsoleteEvent {
             timestamp: _,
             event_tx_id: _,
             commit_oid: _,
         }
-        | Event::UnobsoleteEvent {
+        | Event::WorkingCopySnapshot {
             timestamp: _,
             event_tx_id: _,
+            head_oid: _,
             commit_oid: _,
         }) => event,
 
+        Event::ObsoleteEvent {
+            timestamp,
+            event_tx_id,
+            commit_oid,
+            message,
+        } => {
+            let message = message.map(|message| {
+                redactor
+                    .redact_commit_summary(message.to_string_lossy().into_owned())
+                    .into()
+            });
+            Event::ObsoleteEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid,
+                message,
+            }
+        }
+
         Event::RefUpdateEvent {
             timestamp,
             event_tx_id,
@@ -112,6 +132,8 @@ fn describe_event_cursor(
         event_cursor,
         true,
         false,
+        None,
+        0,
     )?;
     let graph_lines = render_graph(
         &effects,
@@ -122,11 +144,12 @@ fn describe_event_cursor(
         &mut [
             &mut CommitOidDescriptor::new(true)?,
             &mut RelativeTimeDescriptor::new(repo, now)?,
-            &mut ObsolescenceExplanationDescriptor::new(event_replayer, event_cursor)?,
+            &mut ObsolescenceExplanationDescriptor::new(repo, event_replayer, event_cursor)?,
             &mut BranchesDescriptor::new(repo, head_info, references_snapshot, redactor)?,
             &mut DifferentialRevisionDescriptor::new(repo, redactor)?,
             &mut CommitMessageDescriptor::new(redactor)?,
         ],
+        &SubjectLayout::default(),
     )?;
     let graph_lines = graph_lines
         .into_iter()
@@ -0,0 +1,122 @@
+//! Show the changes of a commit relative to its logical base.
+//!
+//! The logical base of a draft commit is its merge-base with the main branch,
+//! i.e. the public commit which its stack was started from. Diffing against
+//! it shows the cumulative changes of the stack, rather than only the changes
+//! of the commit itself.
+
+use std::fmt::Write;
+
+use eden_dag::DagAlgorithm;
+use tracing::instrument;
+
+use crate::commands::submit::get_current_stack;
+use crate::core::dag::{commit_set_to_vec, resolve_commits, CommitSet, Dag, ResolveCommitsResult};
+use crate::core::effects::Effects;
+use crate::core::eventlog::{EventLogDb, EventReplayer};
+use crate::core::formatting::printable_styled_string;
+use crate::git::{GitRunInfo, Repo};
+
+/// Diff the provided commit, or the current commit if none is provided,
+/// against its merge-base with the main branch. If `range` is set, diff the
+/// head of the commit's stack instead. `diff_args` are passed to `git diff`.
+#[instrument]
+pub fn diff(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    commit: Option<String>,
+    range: bool,
+    diff_args: &[String],
+) -> eyre::Result<isize> {
+    let repo = Repo::from_current_dir()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commit = match (commit, references_snapshot.head_oid) {
+        (Some(commit), _) => commit,
+        (None, Some(head_oid)) => head_oid.to_string(),
+        (None, None) => {
+            writeln!(
+                effects.get_error_stream(),
+                "No commit is currently checked out. Check out a commit to diff, or provide the commit to diff, and then try again.",
+            )?;
+            return Ok(1);
+        }
+    };
+    let commit = match resolve_commits(effects, &repo, &mut dag, vec![commit])? {
+        ResolveCommitsResult::Ok { commits } => match commits.as_slice() {
+            [commit] => commit.clone(),
+            other => eyre::bail!("Expected 1 resolved commit, got: {:?}", other),
+        },
+        ResolveCommitsResult::CommitNotFound { commit } => {
+            writeln!(effects.get_error_stream(), "Commit not found: {}", commit)?;
+            return Ok(1);
+        }
+    };
+    let describe_commit = printable_styled_string(
+        effects.get_glyphs(),
+        commit.friendly_describe(effects.get_glyphs())?,
+    )?;
+
+    let base_oid = match dag.get_one_merge_base_oid(
+        effects,
+        &repo,
+        commit.get_oid(),
+        references_snapshot.main_branch_oid,
+    )? {
+        Some(base_oid) => base_oid,
+        None => {
+            writeln!(
+                effects.get_error_stream(),
+                "Cannot diff {}, because it has no common ancestor with the main branch.",
+                describe_commit,
+            )?;
+            return Ok(1);
+        }
+    };
+
+    let target_oid = if range {
+        let stack_commits = get_current_stack(&dag, commit.get_oid())?;
+        let stack_heads = commit_set_to_vec(
+            &dag.query().heads(
+                dag.query()
+                    .descendants(CommitSet::from(commit.get_oid()))?
+                    .intersection(&stack_commits),
+            )?,
+        )?;
+        match stack_heads.as_slice() {
+            [] => commit.get_oid(),
+            [stack_head_oid] => *stack_head_oid,
+            _ => {
+                writeln!(
+                    effects.get_error_stream(),
+                    "Cannot diff the stack containing {}, because it has {} heads. Diff one of the heads instead.",
+                    describe_commit,
+                    stack_heads.len(),
+                )?;
+                return Ok(1);
+            }
+        }
+    } else {
+        commit.get_oid()
+    };
+
+    let args = {
+        let mut args = vec!["diff".to_string()];
+        args.extend(diff_args.iter().cloned());
+        args.push(base_oid.to_string());
+        args.push(target_oid.to_string());
+        args
+    };
+    git_run_info.run(effects, None, &args)
+}
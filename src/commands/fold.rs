@@ -0,0 +1,261 @@
+//! Fold a commit into its parent.
+//!
+//! The changes of the commit are combined with the changes of its parent into
+//! a single new commit, similar to a `squash` command in an interactive
+//! rebase. The descendants of both commits are then moved on top of the new
+//! commit.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use eyre::Context;
+use tracing::instrument;
+
+use crate::commands::gc::mark_commit_reachable;
+use crate::commands::reword::edit_message;
+use crate::commands::smartlog::smartlog;
+use crate::commands::split::move_descendants;
+use crate::core::commit_message::combine_commit_messages;
+use crate::core::config::{
+    get_commit_gpg_sign, get_commit_template, get_restack_preserve_timestamps,
+};
+use crate::core::dag::{resolve_commits, Dag, ResolveCommitsResult};
+use crate::core::effects::Effects;
+use crate::core::eventlog::{Event, EventLogDb, EventReplayer};
+use crate::core::formatting::printable_styled_string;
+use crate::core::rewrite::move_branches;
+use crate::git::{
+    check_out_commit, CheckOutCommitOptions, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
+};
+use crate::opts::MoveOptions;
+
+/// Fold the provided commit, or the current commit if none is provided, into
+/// its parent commit. If `edit` is set, the combined commit message is opened
+/// in the user's editor. The combined commit message is formatted with
+/// `commit_template`, or with `branchless.commitTemplate` if not provided.
+#[instrument]
+pub fn fold(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    commit: Option<String>,
+    edit: bool,
+    commit_template: Option<&str>,
+    move_options: &MoveOptions,
+) -> eyre::Result<isize> {
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
+    let head_info = repo.get_head_info()?;
+
+    let commit = match (commit, head_info.oid) {
+        (Some(commit), _) => commit,
+        (None, Some(head_oid)) => head_oid.to_string(),
+        (None, None) => {
+            writeln!(
+                effects.get_output_stream(),
+                "No commit is currently checked out. Check out a commit to fold, or provide the commit to fold, and then try again.",
+            )?;
+            return Ok(1);
+        }
+    };
+
+    let references_snapshot = repo.get_references_snapshot()?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+    let commit = match resolve_commits(effects, &repo, &mut dag, vec![commit])? {
+        ResolveCommitsResult::Ok { mut commits } => commits.remove(0),
+        ResolveCommitsResult::CommitNotFound { commit } => {
+            writeln!(effects.get_output_stream(), "Commit not found: {}", commit)?;
+            return Ok(1);
+        }
+    };
+    let describe_commit = printable_styled_string(
+        effects.get_glyphs(),
+        commit.friendly_describe(effects.get_glyphs())?,
+    )?;
+    let parent = match commit.get_parents().as_slice() {
+        [parent] => parent.clone(),
+        [] => {
+            writeln!(
+                effects.get_output_stream(),
+                "Cannot fold {}, because it does not have a parent commit.",
+                describe_commit,
+            )?;
+            return Ok(1);
+        }
+        [..] => {
+            writeln!(
+                effects.get_output_stream(),
+                "Cannot fold {}, because it is a merge commit.",
+                describe_commit,
+            )?;
+            return Ok(1);
+        }
+    };
+    let describe_parent = printable_styled_string(
+        effects.get_glyphs(),
+        parent.friendly_describe(effects.get_glyphs())?,
+    )?;
+    if dag
+        .query_public_commits()?
+        .contains(&parent.get_oid().into())?
+    {
+        writeln!(
+            effects.get_output_stream(),
+            "Cannot fold {}, because its parent commit {} is on the main branch.",
+            describe_commit,
+            describe_parent,
+        )?;
+        return Ok(1);
+    }
+
+    let commit_template = match commit_template {
+        Some(commit_template) => Some(commit_template.to_string()),
+        None => get_commit_template(&repo)?,
+    };
+    let message = combine_commit_messages(
+        commit_template.as_deref(),
+        &[
+            &parent.get_message_raw()?.to_string_lossy(),
+            &commit.get_message_raw()?.to_string_lossy(),
+        ],
+    );
+    let message = if edit {
+        edit_message(git_run_info, &repo, &message, "the folded commit", "fold")?
+    } else {
+        message
+    };
+    if message.trim().is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Aborting fold due to empty commit message."
+        )?;
+        return Ok(1);
+    }
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "fold")?;
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let committer = if get_restack_preserve_timestamps(&repo)? {
+        parent.get_committer()
+    } else {
+        match repo.get_default_signature(git_run_info, now)? {
+            Some(committer) => committer,
+            None => parent.get_committer().update_timestamp(now)?,
+        }
+    };
+    let folded_commit_oid = if move_options.sign_commits && get_commit_gpg_sign(&repo)? {
+        repo.create_signed_commit(
+            git_run_info,
+            None,
+            &parent.get_author(),
+            &committer,
+            &message,
+            &commit.get_tree()?,
+            parent.get_parents().iter().collect(),
+        )?
+    } else {
+        repo.create_commit(
+            None,
+            &parent.get_author(),
+            &committer,
+            &message,
+            &commit.get_tree()?,
+            parent.get_parents().iter().collect(),
+        )?
+    };
+    mark_commit_reachable(&repo, folded_commit_oid)
+        .wrap_err("Marking commit as reachable for GC purposes.")?;
+    event_log_db.add_events(vec![
+        Event::RewriteEvent {
+            timestamp,
+            event_tx_id,
+            old_commit_oid: parent.get_oid().into(),
+            new_commit_oid: folded_commit_oid.into(),
+        },
+        Event::RewriteEvent {
+            timestamp,
+            event_tx_id,
+            old_commit_oid: commit.get_oid().into(),
+            new_commit_oid: folded_commit_oid.into(),
+        },
+    ])?;
+
+    // If either commit was checked out, check out the folded commit instead,
+    // and then check out the branch again once it's been moved.
+    let is_head_folded =
+        head_info.oid == Some(commit.get_oid()) || head_info.oid == Some(parent.get_oid());
+    if is_head_folded {
+        let exit_code = check_out_commit(
+            effects,
+            git_run_info,
+            Some(event_tx_id),
+            Some(folded_commit_oid.to_string()),
+            &CheckOutCommitOptions {
+                additional_args: &[],
+                render_smartlog: false,
+            },
+        )?;
+        if exit_code != 0 {
+            return Ok(exit_code);
+        }
+    }
+    let rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid> = [
+        (parent.get_oid(), MaybeZeroOid::NonZero(folded_commit_oid)),
+        (commit.get_oid(), MaybeZeroOid::NonZero(folded_commit_oid)),
+    ]
+    .iter()
+    .copied()
+    .collect();
+    move_branches(effects, git_run_info, &repo, event_tx_id, &rewritten_oids)?;
+    if is_head_folded {
+        if let Some(reference_name) = &head_info.reference_name {
+            let exit_code = check_out_commit(
+                effects,
+                git_run_info,
+                Some(event_tx_id),
+                Some(reference_name),
+                &CheckOutCommitOptions {
+                    additional_args: &[],
+                    render_smartlog: false,
+                },
+            )?;
+            if exit_code != 0 {
+                return Ok(exit_code);
+            }
+        }
+    }
+
+    let exit_code = move_descendants(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        now,
+        &[
+            (commit.get_oid(), folded_commit_oid),
+            (parent.get_oid(), folded_commit_oid),
+        ],
+        move_options,
+    )?;
+    if exit_code != 0 {
+        return Ok(exit_code);
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Folded {} into {}.",
+        describe_commit,
+        describe_parent,
+    )?;
+    smartlog(effects, git_run_info, &Default::default())
+}
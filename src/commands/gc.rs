@@ -11,10 +11,15 @@
 use std::convert::TryInto;
 use std::ffi::OsStr;
 use std::fmt::Write;
+use std::time::{Duration, SystemTime};
 
 use eyre::Context;
 use tracing::instrument;
 
+use crate::commands::snapshot::is_snapshot_ref;
+use crate::core::config::{
+    get_eventlog_max_age_days, get_eventlog_max_entries, get_gc_retention_days,
+};
 use crate::core::effects::Effects;
 use crate::core::eventlog::{
     is_gc_ref, CommitActivityStatus, EventCursor, EventLogDb, EventReplayer,
@@ -22,18 +27,36 @@ use crate::core::eventlog::{
 use crate::core::formatting::Pluralize;
 use crate::git::{NonZeroOid, Reference, Repo};
 
+struct DanglingReferences<'repo> {
+    /// References to commits which can be released.
+    released: Vec<Reference<'repo>>,
+
+    /// The number of references to hidden commits which are being kept,
+    /// because they were hidden too recently.
+    num_retained: usize,
+}
+
 fn find_dangling_references<'repo>(
     repo: &'repo Repo,
     event_replayer: &EventReplayer,
     event_cursor: EventCursor,
-) -> eyre::Result<Vec<Reference<'repo>>> {
-    let mut result = Vec::new();
+    retention_cutoff: SystemTime,
+) -> eyre::Result<DanglingReferences<'repo>> {
+    let references_snapshot = repo.get_references_snapshot()?;
+    let mut released = Vec::new();
+    let mut num_retained = 0;
     for reference in repo.get_all_references()? {
         let reference_name = reference.get_name()?;
         if !is_gc_ref(&reference_name) {
             continue;
         }
 
+        // Snapshots aren't tracked as commits in the event log, so they would
+        // otherwise always be released.
+        if reference_name.to_str().map_or(false, is_snapshot_ref) {
+            continue;
+        }
+
         // The graph only contains commits, so we don't need to handle the
         // case of the reference not peeling to a valid commit. (It might be
         // a reference to a different kind of object.)
@@ -41,17 +64,37 @@ fn find_dangling_references<'repo>(
             Some(commit) => commit,
             None => continue,
         };
+        let commit_oid = commit.get_oid();
 
-        match event_replayer.get_cursor_commit_activity_status(event_cursor, commit.get_oid()) {
+        // Never release commits which are still in use, even if they're hidden.
+        if references_snapshot.head_oid == Some(commit_oid)
+            || references_snapshot
+                .branch_oid_to_names
+                .contains_key(&commit_oid)
+        {
+            continue;
+        }
+
+        match event_replayer.get_cursor_commit_activity_status(event_cursor, commit_oid) {
             CommitActivityStatus::Active => {
                 // Do nothing.
             }
-            CommitActivityStatus::Inactive | CommitActivityStatus::Obsolete => {
-                result.push(reference)
+            CommitActivityStatus::Inactive => released.push(reference),
+            CommitActivityStatus::Obsolete => {
+                let hidden_time = event_replayer
+                    .get_cursor_commit_latest_event(event_cursor, commit_oid)
+                    .map(|event| event.get_timestamp());
+                match hidden_time {
+                    Some(hidden_time) if hidden_time > retention_cutoff => num_retained += 1,
+                    _ => released.push(reference),
+                }
             }
         }
     }
-    Ok(result)
+    Ok(DanglingReferences {
+        released,
+        num_retained,
+    })
 }
 
 /// Mark a commit as reachable.
@@ -90,27 +133,39 @@ pub fn mark_commit_reachable(repo: &Repo, commit_oid: NonZeroOid) -> eyre::Resul
 
 /// Run branchless's garbage collection.
 ///
-/// Frees any references to commits which are no longer visible in the smartlog.
+/// Frees any references to commits which are no longer visible in the
+/// smartlog, unless they were hidden within the number of days given by
+/// `branchless.gc.retentionDays`. Then prunes old events from the event log
+/// according to `branchless.eventlog.maxEntries` and
+/// `branchless.eventlog.maxAgeDays`, if set. If `compact` is set, also compacts
+/// the event log database.
 #[instrument]
-pub fn gc(effects: &Effects) -> eyre::Result<()> {
+pub fn gc(effects: &Effects, compact: bool) -> eyre::Result<()> {
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
-    let event_log_db = EventLogDb::new(&conn)?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
     let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
     let event_cursor = event_replayer.make_default_cursor();
+    let retention_days = get_gc_retention_days(&repo)?;
+    let retention_cutoff = SystemTime::now()
+        .checked_sub(Duration::from_secs(retention_days * 60 * 60 * 24))
+        .unwrap_or(SystemTime::UNIX_EPOCH);
 
     writeln!(
         effects.get_output_stream(),
         "branchless: collecting garbage"
     )?;
-    let dangling_references = find_dangling_references(&repo, &event_replayer, event_cursor)?;
+    let DanglingReferences {
+        released,
+        num_retained,
+    } = find_dangling_references(&repo, &event_replayer, event_cursor, retention_cutoff)?;
     let num_dangling_references = Pluralize {
         determiner: None,
-        amount: dangling_references.len().try_into()?,
+        amount: released.len().try_into()?,
         unit: ("dangling reference", "dangling references"),
     }
     .to_string();
-    for mut reference in dangling_references.into_iter() {
+    for mut reference in released.into_iter() {
         reference.delete()?;
     }
 
@@ -119,5 +174,47 @@ pub fn gc(effects: &Effects) -> eyre::Result<()> {
         "branchless: {} deleted",
         num_dangling_references,
     )?;
+    if num_retained > 0 {
+        writeln!(
+            effects.get_output_stream(),
+            "branchless: {} kept (hidden within the last {})",
+            Pluralize {
+                determiner: None,
+                amount: num_retained.try_into()?,
+                unit: ("hidden commit", "hidden commits"),
+            },
+            Pluralize {
+                determiner: None,
+                amount: retention_days.try_into()?,
+                unit: ("day", "days"),
+            },
+        )?;
+    }
+
+    let max_entries = get_eventlog_max_entries(&repo)?;
+    let max_age_days = get_eventlog_max_age_days(&repo)?;
+    if max_entries.is_some() || max_age_days.is_some() {
+        let max_age =
+            max_age_days.map(|max_age_days| Duration::from_secs(max_age_days * 60 * 60 * 24));
+        let num_pruned_events = event_log_db.compact(SystemTime::now(), max_entries, max_age)?;
+        writeln!(
+            effects.get_output_stream(),
+            "branchless: {} pruned from event log",
+            Pluralize {
+                determiner: None,
+                amount: num_pruned_events.try_into()?,
+                unit: ("old event", "old events"),
+            },
+        )?;
+    }
+
+    if compact {
+        conn.execute("VACUUM", rusqlite::params![])
+            .wrap_err("Compacting event log")?;
+        writeln!(
+            effects.get_output_stream(),
+            "branchless: compacted event log"
+        )?;
+    }
     Ok(())
 }
@@ -2,22 +2,35 @@
 //! automatically as the result of a rewrite operation).
 
 use std::convert::TryInto;
+use std::ffi::OsString;
 use std::fmt::Write;
 use std::time::SystemTime;
 
 use eden_dag::DagAlgorithm;
 use tracing::instrument;
 
-use crate::core::dag::{resolve_commits, sort_commit_set, CommitSet, Dag, ResolveCommitsResult};
+use crate::commands::BranchlessExitCode;
+use crate::core::dag::{
+    commit_set_to_vec, resolve_commit_range, resolve_commits, sort_commit_set, CommitSet, Dag,
+    ResolveCommitsResult,
+};
 use crate::core::effects::Effects;
-use crate::core::eventlog::{CommitActivityStatus, Event};
+use crate::core::eventlog::{make_force_hidden_public_hide_message, CommitActivityStatus, Event};
 use crate::core::eventlog::{EventLogDb, EventReplayer};
 use crate::core::formatting::{printable_styled_string, Glyphs, Pluralize};
-use crate::git::Repo;
+use crate::core::query::{parse_duration, parse_query, Query, QueryContext};
+use crate::git::{Repo, RepoReferencesSnapshot};
 
 /// Hide the hashes provided on the command-line.
 #[instrument]
-pub fn hide(effects: &Effects, hashes: Vec<String>, recursive: bool) -> eyre::Result<isize> {
+pub fn hide(
+    effects: &Effects,
+    hashes: Vec<String>,
+    recursive: bool,
+    query: Option<String>,
+    message: Option<String>,
+    force: bool,
+) -> eyre::Result<isize> {
     let now = SystemTime::now();
     let glyphs = Glyphs::detect();
     let repo = Repo::from_current_dir()?;
@@ -34,12 +47,36 @@ pub fn hide(effects: &Effects, hashes: Vec<String>, recursive: bool) -> eyre::Re
         &references_snapshot,
     )?;
 
+    let query = match query {
+        Some(query) => match parse_query(&query) {
+            Ok(query) => Some(query),
+            Err(err) => {
+                writeln!(effects.get_output_stream(), "Invalid query: {}", err)?;
+                return Ok(BranchlessExitCode::UserError.into());
+            }
+        },
+        None => None,
+    };
+
+    let (ranges, hashes): (Vec<String>, Vec<String>) =
+        hashes.into_iter().partition(|hash| hash.contains(".."));
+    let mut range_commits = CommitSet::empty();
+    for range in ranges {
+        match resolve_commit_range(effects, &repo, &mut dag, &range)? {
+            Some(commits) => range_commits = range_commits.union(&commits),
+            None => {
+                writeln!(effects.get_output_stream(), "Commit not found: {}", range)?;
+                return Ok(BranchlessExitCode::UserError.into());
+            }
+        }
+    }
+
     let commits = resolve_commits(effects, &repo, &mut dag, hashes)?;
     let commits = match commits {
         ResolveCommitsResult::Ok { commits } => commits,
         ResolveCommitsResult::CommitNotFound { commit: hash } => {
             writeln!(effects.get_output_stream(), "Commit not found: {}", hash)?;
-            return Ok(1);
+            return Ok(BranchlessExitCode::UserError.into());
         }
     };
 
@@ -48,26 +85,90 @@ pub fn hide(effects: &Effects, hashes: Vec<String>, recursive: bool) -> eyre::Re
         .map(|commit| commit.get_oid())
         .rev()
         .collect();
-    let commits = if recursive {
-        dag.query()
+
+    // Don't hide public commits which happen to be in the provided ranges,
+    // since they would be hidden from the main branch, unless forced.
+    let public_commits = dag.query_public_commits()?;
+    let (commits, skipped_public_commits) = if force {
+        (commits.union(&range_commits), CommitSet::empty())
+    } else {
+        (
+            commits.union(&range_commits.difference(&public_commits)),
+            range_commits.intersection(&public_commits),
+        )
+    };
+
+    let commits = match &query {
+        Some(query) => {
+            let matching_commits =
+                find_matching_commits(&repo, &dag, &references_snapshot, now, query)?;
+            if matching_commits.is_empty()? && commits.is_empty()? {
+                writeln!(effects.get_output_stream(), "No commits matched the query.")?;
+                return Ok(0);
+            }
+            commits.union(&matching_commits)
+        }
+        None => commits,
+    };
+    let (commits, skipped_public_commits) = if recursive {
+        // Likewise, don't hide public commits which happen to be descendants
+        // of the provided commits.
+        let descendants = dag
+            .query()
             .descendants(commits)?
-            .difference(&dag.obsolete_commits)
+            .difference(&dag.obsolete_commits);
+        if force {
+            (descendants, skipped_public_commits)
+        } else {
+            (
+                descendants.difference(&public_commits),
+                descendants
+                    .intersection(&public_commits)
+                    .union(&skipped_public_commits),
+            )
+        }
     } else {
-        commits
+        (commits, skipped_public_commits)
     };
     let commits = dag.query().sort(&commits)?;
     let commits = sort_commit_set(&repo, &dag, &commits)?;
 
+    let skipped_public_commits = sort_commit_set(&repo, &dag, &skipped_public_commits)?;
+    for commit in skipped_public_commits.iter() {
+        writeln!(
+            effects.get_output_stream(),
+            "Skipped hiding public commit: {}",
+            printable_styled_string(&glyphs, commit.friendly_describe(&glyphs)?)?
+        )?;
+    }
+    if !skipped_public_commits.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "To hide public commits anyway, run again with --force."
+        )?;
+    }
+    if commits.is_empty() && !skipped_public_commits.is_empty() {
+        return Ok(BranchlessExitCode::NothingToDo.into());
+    }
+
     let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
     let event_tx_id = event_log_db.make_transaction_id(now, "hide")?;
     let events = commits
         .iter()
-        .map(|commit| Event::ObsoleteEvent {
-            timestamp,
-            event_tx_id,
-            commit_oid: commit.get_oid(),
+        .map(|commit| -> eyre::Result<Event> {
+            let message = if force && public_commits.contains(&commit.get_oid().into())? {
+                Some(make_force_hidden_public_hide_message(message.as_deref()))
+            } else {
+                message.clone()
+            };
+            Ok(Event::ObsoleteEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid: commit.get_oid(),
+                message: message.map(OsString::from),
+            })
         })
-        .collect();
+        .collect::<eyre::Result<_>>()?;
     event_log_db.add_events(events)?;
 
     let cursor = event_replayer.make_default_cursor();
@@ -101,6 +202,40 @@ pub fn hide(effects: &Effects, hashes: Vec<String>, recursive: bool) -> eyre::Re
     Ok(0)
 }
 
+/// Find the visible draft commits which match the provided query.
+#[instrument]
+fn find_matching_commits(
+    repo: &Repo,
+    dag: &Dag,
+    references_snapshot: &RepoReferencesSnapshot,
+    now: SystemTime,
+    query: &Query,
+) -> eyre::Result<CommitSet> {
+    let public_commits = dag.query_public_commits()?;
+    let draft_commits = dag
+        .observed_commits
+        .difference(&dag.obsolete_commits)
+        .difference(&public_commits);
+
+    let mailmap = repo.get_mailmap()?;
+    let context = QueryContext {
+        now,
+        mailmap: &mailmap,
+        branch_oid_to_names: &references_snapshot.branch_oid_to_names,
+    };
+    let mut result = Vec::new();
+    for commit_oid in commit_set_to_vec(&draft_commits)? {
+        let commit = match repo.find_commit(commit_oid)? {
+            Some(commit) => commit,
+            None => continue,
+        };
+        if query.matches(&context, &commit)? {
+            result.push(commit_oid);
+        }
+    }
+    Ok(result.into_iter().collect())
+}
+
 /// Unhide the hashes provided on the command-line.
 #[instrument]
 pub fn unhide(effects: &Effects, hashes: Vec<String>, recursive: bool) -> eyre::Result<isize> {
@@ -125,7 +260,7 @@ pub fn unhide(effects: &Effects, hashes: Vec<String>, recursive: bool) -> eyre::
         ResolveCommitsResult::Ok { commits } => commits,
         ResolveCommitsResult::CommitNotFound { commit: hash } => {
             writeln!(effects.get_output_stream(), "Commit not found: {}", hash)?;
-            return Ok(1);
+            return Ok(BranchlessExitCode::UserError.into());
         }
     };
 
@@ -182,3 +317,96 @@ pub fn unhide(effects: &Effects, hashes: Vec<String>, recursive: bool) -> eyre::
 
     Ok(0)
 }
+
+/// Unhide all commits which are currently hidden as the result of `git hide`,
+/// optionally only those hidden within the provided duration, like `2h`.
+#[instrument]
+pub fn unhide_all(effects: &Effects, since: Option<String>) -> eyre::Result<isize> {
+    let now = SystemTime::now();
+    let glyphs = Glyphs::detect();
+    let since = match since {
+        Some(since) => match parse_duration(&since) {
+            Ok(since) => Some(since),
+            Err(err) => {
+                writeln!(effects.get_output_stream(), "Invalid duration: {}", err)?;
+                return Ok(BranchlessExitCode::UserError.into());
+            }
+        },
+        None => None,
+    };
+
+    let repo = Repo::from_current_dir()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    // Only consider commits whose most recent event is an explicit hide, so
+    // that commits which were hidden because they were rewritten stay hidden.
+    let mut hidden_oids = Vec::new();
+    for oid in event_replayer.get_cursor_oids(event_cursor) {
+        let timestamp = match event_replayer.get_cursor_commit_latest_event(event_cursor, oid) {
+            Some(event @ Event::ObsoleteEvent { .. }) => event.get_timestamp(),
+            _ => continue,
+        };
+        if let Some(since) = since {
+            // A duration reaching back before the earliest representable time
+            // includes every commit.
+            if let Some(cutoff) = now.checked_sub(since) {
+                if timestamp < cutoff {
+                    continue;
+                }
+            }
+        }
+        if repo.find_commit(oid)?.is_some() {
+            hidden_oids.push(oid);
+        }
+    }
+    let commits: CommitSet = hidden_oids.into_iter().collect();
+    let commits = sort_commit_set(&repo, &dag, &commits)?;
+    if commits.is_empty() {
+        writeln!(effects.get_output_stream(), "No hidden commits to unhide.")?;
+        return Ok(0);
+    }
+
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let event_tx_id = event_log_db.make_transaction_id(now, "unhide")?;
+    let events = commits
+        .iter()
+        .map(|commit| Event::UnobsoleteEvent {
+            timestamp,
+            event_tx_id,
+            commit_oid: commit.get_oid(),
+        })
+        .collect();
+    event_log_db.add_events(events)?;
+
+    let num_commits = commits.len();
+    for commit in commits {
+        writeln!(
+            effects.get_output_stream(),
+            "Unhid commit: {}",
+            printable_styled_string(&glyphs, commit.friendly_describe(&glyphs)?)?,
+        )?;
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "To hide {}, run: git undo",
+        Pluralize {
+            determiner: Some(("this", "these")),
+            amount: num_commits.try_into()?,
+            unit: ("commit", "commits"),
+        },
+    )?;
+
+    Ok(0)
+}
@@ -18,6 +18,7 @@ use itertools::Itertools;
 use tracing::{error, instrument, warn};
 
 use crate::commands::gc::mark_commit_reachable;
+use crate::core::config::{get_hooks_pre_commit, get_main_branch_name};
 use crate::core::eventlog::{should_ignore_ref_updates, Event, EventLogDb};
 use crate::core::formatting::{printable_styled_string, Glyphs, Pluralize};
 use crate::git::{CategorizedReferenceName, MaybeZeroOid, Repo};
@@ -61,7 +62,7 @@ pub fn hook_post_checkout(
             let oid: MaybeZeroOid = current_head_oid.parse()?;
             oid
         },
-        ref_name: OsString::from("HEAD"),
+        ref_name: repo.get_head_reference_name(),
         message: None,
     }])?;
     Ok(())
@@ -120,12 +121,97 @@ pub fn hook_post_commit(effects: &Effects) -> eyre::Result<()> {
 /// `post-commit` hook after a merge commit, so we need to handle this case
 /// explicitly with another hook.
 ///
+/// The hook is also invoked for fast-forward and squash merges, which don't
+/// create a merge commit. The resulting reference updates are already recorded
+/// by the `reference-transaction` hook, so there's no new commit to record in
+/// those cases.
+///
 /// See the man-page for `githooks(5)`.
 #[instrument]
-pub fn hook_post_merge(effects: &Effects, _is_squash_merge: isize) -> eyre::Result<()> {
+pub fn hook_post_merge(effects: &Effects, is_squash_merge: isize) -> eyre::Result<()> {
+    if is_squash_merge != 0 {
+        return Ok(());
+    }
+
+    let repo = Repo::from_current_dir()?;
+    let head_commit = match repo.get_head_info()?.oid {
+        Some(head_oid) => repo.find_commit_or_fail(head_oid)?,
+        None => {
+            warn!("`post-merge` hook called, but could not determine the OID of `HEAD`");
+            return Ok(());
+        }
+    };
+
+    // `git merge` sets `ORIG_HEAD` to the commit which was checked out before
+    // the merge. If that commit isn't the first parent of `HEAD`, then `HEAD`
+    // was fast-forwarded to an existing commit.
+    let orig_head_oid = repo
+        .revparse_single_commit("ORIG_HEAD")?
+        .map(|commit| commit.get_oid());
+    let is_merge_commit = head_commit.get_parent_count() > 1
+        && head_commit.get_parent_oids().first().copied() == orig_head_oid;
+    if !is_merge_commit {
+        return Ok(());
+    }
+
     hook_post_commit_common(effects, "post-merge")
 }
 
+/// Handle Git's `pre-commit` hook.
+///
+/// Warns if the user is about to commit directly on top of the main branch
+/// after it has already been pushed to its upstream. This never prevents the
+/// commit from being made.
+///
+/// See the man-page for `githooks(5)`.
+#[instrument]
+pub fn hook_pre_commit(effects: &Effects) -> eyre::Result<()> {
+    let repo = Repo::from_current_dir()?;
+    if !get_hooks_pre_commit(&repo)? {
+        return Ok(());
+    }
+
+    let head_info = repo.get_head_info()?;
+    let (head_oid, head_reference_name) = match (head_info.oid, head_info.reference_name) {
+        (Some(head_oid), Some(head_reference_name)) => (head_oid, head_reference_name),
+        _ => return Ok(()),
+    };
+
+    let main_branch_name = get_main_branch_name(&repo)?;
+    if head_reference_name != OsString::from(format!("refs/heads/{}", main_branch_name)) {
+        return Ok(());
+    }
+    let main_branch = match repo.find_branch(&main_branch_name, git2::BranchType::Local)? {
+        Some(main_branch) => main_branch,
+        None => return Ok(()),
+    };
+    let upstream_oid = match main_branch.get_upstream_branch()? {
+        Some(upstream_branch) => match upstream_branch.get_oid()? {
+            Some(upstream_oid) => upstream_oid,
+            None => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+
+    // Only warn if the commit at `HEAD` has already been pushed, i.e. it's
+    // reachable from the upstream branch.
+    if repo.find_merge_base(head_oid, upstream_oid)? != Some(head_oid) {
+        return Ok(());
+    }
+
+    writeln!(
+        effects.get_error_stream(),
+        "\
+branchless: warning: you are committing directly on top of main branch {main_branch_name:?},
+branchless: which has already been pushed.
+branchless: To use the branchless workflow instead, detach HEAD before committing:
+branchless:     git checkout --detach
+branchless: To disable this warning, run: git config branchless.hooks.preCommit false",
+        main_branch_name = main_branch_name,
+    )?;
+    Ok(())
+}
+
 mod reference_transaction {
     use std::collections::HashMap;
     use std::convert::TryInto;
@@ -428,6 +514,18 @@ pub fn hook_reference_transaction(effects: &Effects, transaction_state: &str) ->
              }| !should_ignore_ref_updates(ref_name),
         )
         .map(|parsed_line| fix_packed_reference_oid(&repo, &packed_references, parsed_line))
+        .map(|parsed_line| {
+            // Record which worktree's `HEAD` was updated, since the event log
+            // is shared between worktrees.
+            if parsed_line.ref_name == "HEAD" {
+                ParsedReferenceTransactionLine {
+                    ref_name: repo.get_head_reference_name(),
+                    ..parsed_line
+                }
+            } else {
+                parsed_line
+            }
+        })
         .collect();
     if parsed_lines.is_empty() {
         return Ok(());
@@ -9,7 +9,10 @@ use eyre::Context;
 use path_slash::PathExt;
 use tracing::{instrument, warn};
 
-use crate::core::config::{get_core_hooks_path, get_default_branch_name};
+use crate::core::config::{
+    expand_home_dir, get_core_hooks_path, get_default_branch_name, get_hook_enabled,
+    get_hook_enabled_config_key, INIT_HOOK_SHEBANG_CONFIG_KEY,
+};
 use crate::core::effects::Effects;
 use crate::git::{Config, ConfigRead, ConfigWrite, GitRunInfo, GitVersion, Repo};
 use crate::opts::write_man_pages;
@@ -43,6 +46,12 @@ git branchless hook-post-checkout "$@"
         "pre-auto-gc",
         r#"
 git branchless hook-pre-auto-gc "$@"
+"#,
+    ),
+    (
+        "pre-commit",
+        r#"
+git branchless hook-pre-commit "$@"
 "#,
     ),
     (
@@ -62,13 +71,17 @@ echo 'branchless: This is a bug. Please report it.'
 const ALL_ALIASES: &[(&str, &str)] = &[
     ("amend", "amend"),
     ("co", "checkout"),
+    ("fold", "fold"),
     ("hide", "hide"),
     ("move", "move"),
     ("next", "next"),
     ("prev", "prev"),
+    ("redo", "redo"),
     ("restack", "restack"),
+    ("reword", "reword"),
     ("sl", "smartlog"),
     ("smartlog", "smartlog"),
+    ("split", "split"),
     ("sync", "sync"),
     ("undo", "undo"),
     ("unhide", "unhide"),
@@ -85,7 +98,7 @@ enum Hook {
 
 #[instrument]
 fn determine_hook_path(repo: &Repo, hook_type: &str) -> eyre::Result<Hook> {
-    let multi_hooks_path = repo.get_path().join("hooks_multi");
+    let multi_hooks_path = repo.get_common_path().join("hooks_multi");
     let hook = if multi_hooks_path.exists() {
         let path = multi_hooks_path
             .join(format!("{}.d", hook_type))
@@ -135,6 +148,25 @@ fn update_between_lines(lines: &str, updated_lines: &str) -> String {
     new_lines
 }
 
+fn remove_between_lines(lines: &str) -> String {
+    let mut new_lines = String::new();
+    let mut is_ignoring_lines = false;
+    for line in lines.lines() {
+        if line == UPDATE_MARKER_START {
+            is_ignoring_lines = true;
+        } else if line == UPDATE_MARKER_END {
+            is_ignoring_lines = false;
+        } else if !is_ignoring_lines {
+            new_lines.push_str(line);
+            new_lines.push('\n');
+        }
+    }
+    if is_ignoring_lines {
+        warn!("Unterminated branchless config comment in hook");
+    }
+    new_lines
+}
+
 #[instrument]
 fn write_script(path: &Path, contents: &str) -> eyre::Result<()> {
     let script_dir = path
@@ -144,7 +176,9 @@ fn write_script(path: &Path, contents: &str) -> eyre::Result<()> {
 
     std::fs::write(path, contents).wrap_err("Writing script contents")?;
 
-    // Setting hook file as executable only supported on Unix systems.
+    // Setting hook file as executable only supported on Unix systems. On
+    // Windows, Git for Windows runs hooks with its bundled shell based on the
+    // shebang line, so no permissions need to be set.
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
@@ -161,27 +195,88 @@ fn write_script(path: &Path, contents: &str) -> eyre::Result<()> {
     Ok(())
 }
 
-#[instrument]
-fn update_hook_contents(hook: &Hook, hook_contents: &str) -> eyre::Result<()> {
-    let (hook_path, hook_contents) = match hook {
-        Hook::RegularHook { path } => match std::fs::read_to_string(path) {
-            Ok(lines) => {
-                let lines = update_between_lines(&lines, hook_contents);
-                (path, lines)
-            }
-            Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => {
-                let hook_contents = format!(
-                    "{}\n{}\n{}\n{}\n",
-                    SHEBANG, UPDATE_MARKER_START, hook_contents, UPDATE_MARKER_END
-                );
-                (path, hook_contents)
-            }
-            Err(other) => {
-                return Err(eyre::eyre!(other));
-            }
-        },
-        Hook::MultiHook { path } => (path, format!("{}\n{}", SHEBANG, hook_contents)),
+/// Get the shebang line to write at the top of newly-created hook files.
+fn get_hook_shebang(config: &impl ConfigRead) -> eyre::Result<String> {
+    config.get_or(INIT_HOOK_SHEBANG_CONFIG_KEY, SHEBANG.to_string())
+}
+
+/// Compute the new contents of a hook file, given its existing contents, if
+/// any. Existing hook contents outside of the branchless section are preserved.
+/// The `shebang` line is only written if there are no existing contents.
+fn merge_hook_contents(
+    existing_contents: Option<&str>,
+    hook_contents: &str,
+    shebang: &str,
+) -> String {
+    match existing_contents {
+        Some(existing_contents) if !existing_contents.trim().is_empty() => {
+            update_between_lines(existing_contents, hook_contents)
+        }
+        Some(_) | None => format!(
+            "{}\n{}\n{}\n{}\n",
+            shebang, UPDATE_MARKER_START, hook_contents, UPDATE_MARKER_END
+        ),
+    }
+}
+
+/// Get the contents of the hook script to install. Git for Windows runs hooks
+/// with its bundled shell, whose `PATH` may not include the directory where
+/// `git-branchless` is installed (such as when Git is invoked from an IDE), so
+/// on Windows the hook invokes the current executable by its full path.
+fn make_hook_script(hook_script: &str) -> eyre::Result<String> {
+    if cfg!(windows) {
+        let exe_path = std::env::current_exe().wrap_err("Getting path to current executable")?;
+        Ok(make_windows_hook_script(hook_script, &exe_path))
+    } else {
+        Ok(hook_script.to_string())
+    }
+}
+
+fn make_windows_hook_script(hook_script: &str, exe_path: &Path) -> String {
+    // The hook is run by a POSIX shell, so use forward slashes, and quote the
+    // path in case it contains spaces (such as under `C:\Program Files`).
+    let exe_path = exe_path
+        .to_string_lossy()
+        .replace('\\', "/")
+        .replace('\'', r"'\''");
+    hook_script.replace("git branchless ", &format!("'{}' ", exe_path))
+}
+
+#[instrument(skip(config))]
+fn update_hook_contents(
+    config: &impl ConfigRead,
+    hook: &Hook,
+    hook_contents: &str,
+) -> eyre::Result<()> {
+    let hook_path = match hook {
+        Hook::RegularHook { path } | Hook::MultiHook { path } => path,
+    };
+    let existing_contents = match std::fs::read_to_string(hook_path) {
+        Ok(lines) => Some(lines),
+        Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(other) => {
+            return Err(eyre::eyre!(other));
+        }
+    };
+    let existing_contents = match (hook, existing_contents) {
+        // Older versions wrote multi-hooks without markers, since the whole
+        // file belonged to branchless. Replace those files entirely, rather
+        // than appending a second copy of the hook.
+        (Hook::MultiHook { .. }, Some(existing_contents))
+            if !existing_contents
+                .lines()
+                .any(|line| line == UPDATE_MARKER_START)
+                && existing_contents.contains("git branchless hook-") =>
+        {
+            None
+        }
+        (_, existing_contents) => existing_contents,
     };
+    let hook_contents = merge_hook_contents(
+        existing_contents.as_deref(),
+        &make_hook_script(hook_contents)?,
+        &get_hook_shebang(config)?,
+    );
 
     write_script(hook_path, &hook_contents).wrap_err("Writing hook script")?;
 
@@ -191,13 +286,25 @@ fn update_hook_contents(hook: &Hook, hook_contents: &str) -> eyre::Result<()> {
 #[instrument]
 fn install_hook(repo: &Repo, hook_type: &str, hook_script: &str) -> eyre::Result<()> {
     let hook = determine_hook_path(repo, hook_type)?;
-    update_hook_contents(&hook, hook_script)?;
+    update_hook_contents(&repo.get_readonly_config()?, &hook, hook_script)?;
     Ok(())
 }
 
 #[instrument]
 fn install_hooks(effects: &Effects, repo: &Repo) -> eyre::Result<()> {
     for (hook_type, hook_script) in ALL_HOOKS {
+        if !get_hook_enabled(repo, hook_type)? {
+            writeln!(
+                effects.get_output_stream(),
+                "Skipping disabled hook: {} (set {} to true to enable it)",
+                hook_type,
+                get_hook_enabled_config_key(hook_type),
+            )?;
+            // Remove the hook in case it was installed by a previous `init`.
+            uninstall_hook(effects, repo, hook_type, false)?;
+            continue;
+        }
+
         writeln!(
             effects.get_output_stream(),
             "Installing hook: {}",
@@ -223,22 +330,110 @@ fn install_hooks(effects: &Effects, repo: &Repo) -> eyre::Result<()> {
     Ok(())
 }
 
+/// The change to be made to a hook file when uninstalling.
+#[derive(Debug)]
+enum HookUninstallAction {
+    /// The hook doesn't contain any branchless configuration.
+    None,
+
+    /// The branchless configuration is removed, leaving the other contents of
+    /// the hook in place.
+    Update { path: PathBuf, contents: String },
+
+    /// The hook file only contained branchless configuration, so it's deleted.
+    Delete { path: PathBuf },
+}
+
+#[instrument]
+fn plan_uninstall_hook(repo: &Repo, hook_type: &str) -> eyre::Result<HookUninstallAction> {
+    let hook = determine_hook_path(repo, hook_type)?;
+    let (path, is_multi_hook) = match hook {
+        Hook::RegularHook { path } => (path, false),
+        Hook::MultiHook { path } => (path, true),
+    };
+    let action = match std::fs::read_to_string(&path) {
+        Ok(lines) => {
+            if !lines.lines().any(|line| line == UPDATE_MARKER_START) {
+                if is_multi_hook && lines.contains("git branchless hook-") {
+                    // Written by an older version without markers.
+                    HookUninstallAction::Delete { path }
+                } else {
+                    HookUninstallAction::None
+                }
+            } else {
+                let contents = remove_between_lines(&lines);
+                let shebang = get_hook_shebang(&repo.get_readonly_config()?)?;
+                if contents.trim().is_empty()
+                    || contents.trim() == SHEBANG
+                    || contents.trim() == shebang
+                {
+                    HookUninstallAction::Delete { path }
+                } else {
+                    HookUninstallAction::Update { path, contents }
+                }
+            }
+        }
+        Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => HookUninstallAction::None,
+        Err(other) => {
+            return Err(eyre::eyre!(other));
+        }
+    };
+    Ok(action)
+}
+
 #[instrument]
-fn uninstall_hooks(effects: &Effects, repo: &Repo) -> eyre::Result<()> {
+fn uninstall_hook(
+    effects: &Effects,
+    repo: &Repo,
+    hook_type: &str,
+    dry_run: bool,
+) -> eyre::Result<()> {
+    let action = plan_uninstall_hook(repo, hook_type)?;
+    match action {
+        HookUninstallAction::None => {}
+        HookUninstallAction::Update { path, contents } => {
+            if dry_run {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Would uninstall hook: {} (keeping other contents of {})",
+                    hook_type,
+                    path.to_string_lossy()
+                )?;
+            } else {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Uninstalling hook: {}",
+                    hook_type
+                )?;
+                write_script(&path, &contents).wrap_err("Writing hook script")?;
+            }
+        }
+        HookUninstallAction::Delete { path } => {
+            if dry_run {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Would uninstall hook: {} (deleting {})",
+                    hook_type,
+                    path.to_string_lossy()
+                )?;
+            } else {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Uninstalling hook: {}",
+                    hook_type
+                )?;
+                std::fs::remove_file(&path)
+                    .wrap_err_with(|| format!("Deleting hook script {:?}", path))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[instrument]
+fn uninstall_hooks(effects: &Effects, repo: &Repo, dry_run: bool) -> eyre::Result<()> {
     for (hook_type, _hook_script) in ALL_HOOKS {
-        writeln!(
-            effects.get_output_stream(),
-            "Uninstalling hook: {}",
-            hook_type
-        )?;
-        install_hook(
-            repo,
-            hook_type,
-            r#"
-# This hook has been uninstalled.
-# Run `git branchless init` to reinstall.
-"#,
-        )?;
+        uninstall_hook(effects, repo, hook_type, dry_run)?;
     }
     Ok(())
 }
@@ -259,24 +454,94 @@ fn should_use_wrapped_command_alias() -> bool {
     cfg!(feature = "man-pages")
 }
 
+/// Get the value of the alias which invokes the given `git-branchless`
+/// subcommand.
+fn make_alias_value(to: &str) -> String {
+    if should_use_wrapped_command_alias() {
+        format!("branchless-{}", to)
+    } else {
+        format!("branchless {}", to)
+    }
+}
+
+/// Determine which aliases to install, taking into account the user's
+/// `branchless.init.aliases.<alias>` settings. Setting one of these to a
+/// different name installs the alias under that name instead, and setting it to
+/// the empty string skips installing that alias.
+#[instrument(skip(config))]
+fn get_configured_aliases(config: &impl ConfigRead) -> eyre::Result<Vec<(String, &'static str)>> {
+    let mut result = Vec::new();
+    for (from, to) in ALL_ALIASES {
+        let configured_from: Option<String> =
+            config.get(format!("branchless.init.aliases.{}", from))?;
+        match configured_from {
+            None => result.push((from.to_string(), *to)),
+            Some(configured_from) => {
+                let configured_from = configured_from.trim();
+                if !configured_from.is_empty() {
+                    result.push((configured_from.to_string(), *to));
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Whether the given alias value invokes a `git-branchless` subcommand.
+fn is_branchless_alias_value(value: &str) -> bool {
+    value.starts_with("branchless ") || value.starts_with("branchless-")
+}
+
+/// Remove the aliases previously installed into the isolated config, so that
+/// re-running `init` leaves only the currently-configured set of aliases.
 #[instrument]
+fn remove_installed_aliases(config: &mut Config) -> eyre::Result<()> {
+    for (alias_key, value) in config.get_entries_matching(r"^alias\.")? {
+        if is_branchless_alias_value(&value) {
+            config.remove(&alias_key)?;
+        }
+    }
+    Ok(())
+}
+
+/// Warn that the alias `from` wasn't installed because the user has already
+/// defined it as something else.
+fn warn_conflicting_alias(effects: &Effects, from: &str, existing_alias: &str) -> eyre::Result<()> {
+    writeln!(
+        effects.get_output_stream(),
+        "{}: alias {} is already defined as `{}`, so it was not installed.",
+        style("Warning").yellow().bold(),
+        from,
+        existing_alias,
+    )?;
+    writeln!(
+        effects.get_output_stream(),
+        "To overwrite it, run: {}",
+        style("git branchless init --force").bold(),
+    )?;
+    writeln!(
+        effects.get_output_stream(),
+        "To install it under a different name, run: git config branchless.init.aliases.{} <name>",
+        from
+    )?;
+    Ok(())
+}
+
+#[instrument(skip(repo_config))]
 fn install_alias(
     effects: &Effects,
-    repo: &Repo,
     config: &mut Config,
     default_config: &Config,
+    repo_config: &impl ConfigRead,
+    force: bool,
     from: &str,
     to: &str,
 ) -> eyre::Result<()> {
     let alias_key = format!("alias.{}", from);
-
-    let existing_alias: Option<String> = config.get(&alias_key)?;
-    if existing_alias.is_some() {
-        config.remove(&alias_key)?;
-    }
+    let alias = make_alias_value(to);
 
     let default_alias: Option<String> = default_config.get(&alias_key)?;
-    if default_alias.is_some() {
+    if default_alias.as_ref() == Some(&alias) {
         writeln!(
             effects.get_output_stream(),
             "Alias {} already installed, skipping",
@@ -285,11 +550,22 @@ fn install_alias(
         return Ok(());
     }
 
-    let alias = if should_use_wrapped_command_alias() {
-        format!("branchless-{}", to)
-    } else {
-        format!("branchless {}", to)
-    };
+    let existing_alias: Option<String> = repo_config.get(&alias_key)?;
+    if let Some(existing_alias) =
+        existing_alias.filter(|existing_alias| !is_branchless_alias_value(existing_alias))
+    {
+        if !force {
+            warn_conflicting_alias(effects, from, &existing_alias)?;
+            return Ok(());
+        }
+        writeln!(
+            effects.get_output_stream(),
+            "Overwriting alias {} (was: `{}`)",
+            from,
+            existing_alias
+        )?;
+    }
+
     config.set(&alias_key, alias)?;
     Ok(())
 }
@@ -331,9 +607,21 @@ fn install_aliases(
     config: &mut Config,
     default_config: &Config,
     git_run_info: &GitRunInfo,
+    force: bool,
 ) -> eyre::Result<()> {
-    for (from, to) in ALL_ALIASES {
-        install_alias(effects, repo, config, default_config, from, to)?;
+    let aliases = get_configured_aliases(&repo.get_readonly_config()?)?;
+    remove_installed_aliases(config)?;
+    let repo_config = repo.get_readonly_config()?;
+    for (from, to) in aliases {
+        install_alias(
+            effects,
+            config,
+            default_config,
+            &repo_config,
+            force,
+            &from,
+            to,
+        )?;
     }
 
     let version_str = git_run_info
@@ -379,11 +667,11 @@ fn install_man_pages(effects: &Effects, repo: &Repo, config: &mut Config) -> eyr
 
     let man_dir = repo.get_man_dir();
     let man_dir_relative = {
-        let man_dir_relative = man_dir.strip_prefix(repo.get_path()).wrap_err_with(|| {
+        let common_path = repo.get_common_path();
+        let man_dir_relative = man_dir.strip_prefix(&common_path).wrap_err_with(|| {
             format!(
                 "Getting relative path for {:?} with respect to {:?}",
-                &man_dir,
-                repo.get_path()
+                &man_dir, &common_path
             )
         })?;
         &man_dir_relative.to_str().ok_or_else(|| {
@@ -438,22 +726,22 @@ fn set_configs(
 
             None => {
                 writeln!(
-                    effects.get_output_stream(),
+                    effects.get_primary_output_stream(),
                     "{}",
                     console::style("Your main branch name could not be auto-detected!")
                         .yellow()
                         .bold()
                 )?;
                 writeln!(
-                    effects.get_output_stream(),
+                    effects.get_primary_output_stream(),
                     "Examples of a main branch: master, main, trunk, etc."
                 )?;
                 writeln!(
-                    effects.get_output_stream(),
+                    effects.get_primary_output_stream(),
                     "See https://github.com/arxanas/git-branchless/wiki/Concepts#main-branch"
                 )?;
                 write!(
-                    effects.get_output_stream(),
+                    effects.get_primary_output_stream(),
                     "Enter the name of your main branch: "
                 )?;
                 stdout().flush()?;
@@ -494,7 +782,7 @@ fn create_isolated_config(
 
     let config = Config::open(&config_path)?;
     let config_path_relative = config_path
-        .strip_prefix(repo.get_path())
+        .strip_prefix(repo.get_common_path())
         .wrap_err("Getting relative config path")?;
     // Be careful when setting paths on Windows. Since the path would have a
     // backslash, naively using it produces
@@ -547,12 +835,43 @@ fn delete_isolated_config(
     Ok(result)
 }
 
+/// Describe the configuration file and aliases which would be removed by
+/// `delete_isolated_config`, without removing them.
+#[instrument]
+fn describe_isolated_config(effects: &Effects, repo: &Repo) -> eyre::Result<()> {
+    let config_path = repo.get_config_path();
+    if !config_path.exists() {
+        writeln!(
+            effects.get_output_stream(),
+            "Would not remove config file: {} (not present)",
+            config_path.to_string_lossy()
+        )?;
+        return Ok(());
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Would remove config file: {}",
+        config_path.to_string_lossy()
+    )?;
+    let config = Config::open(&config_path)?;
+    for (alias_key, _value) in config.get_entries_matching(r"^alias\.")? {
+        let from = alias_key.trim_start_matches("alias.");
+        writeln!(effects.get_output_stream(), "Would remove alias: {}", from)?;
+    }
+    Ok(())
+}
+
 /// Initialize `git-branchless` in the current repo.
+///
+/// If `force` is set, aliases are installed even if the user has already
+/// defined aliases with the same names.
 #[instrument]
 pub fn init(
     effects: &Effects,
     git_run_info: &GitRunInfo,
     main_branch_name: Option<&str>,
+    force: bool,
 ) -> eyre::Result<()> {
     let mut in_ = BufReader::new(stdin());
     let mut repo = Repo::from_current_dir()?;
@@ -568,6 +887,7 @@ pub fn init(
         &mut config,
         &default_config,
         git_run_info,
+        force,
     )?;
     install_man_pages(effects, &repo, &mut config)?;
     writeln!(
@@ -585,19 +905,137 @@ pub fn init(
     Ok(())
 }
 
+/// Determine the directory to install global hooks into: the existing global
+/// `core.hooksPath`, if set, or a directory next to the global configuration
+/// file otherwise.
+#[instrument]
+fn determine_global_hooks_path(
+    global_config: &Config,
+    global_config_path: &Path,
+) -> eyre::Result<PathBuf> {
+    let home_dir = global_config_path
+        .parent()
+        .ok_or_else(|| eyre::eyre!("No parent for global config {:?}", global_config_path))?;
+    // Read the raw string value, since `~` should be expanded relative to the
+    // directory containing the global config, rather than by libgit2.
+    let hooks_path: Option<String> = global_config.get("core.hooksPath")?;
+    let hooks_path = match hooks_path {
+        Some(hooks_path) => expand_home_dir(PathBuf::from(hooks_path), home_dir),
+        None => home_dir.join(".git-branchless").join("hooks"),
+    };
+    Ok(hooks_path)
+}
+
+#[instrument]
+fn install_global_aliases(
+    effects: &Effects,
+    global_config: &mut Config,
+    force: bool,
+) -> eyre::Result<()> {
+    for (from, to) in get_configured_aliases(global_config)? {
+        let alias_key = format!("alias.{}", from);
+        let alias = make_alias_value(to);
+        let existing_alias: Option<String> = global_config.get(&alias_key)?;
+        match existing_alias {
+            Some(existing_alias) if !force && !is_branchless_alias_value(&existing_alias) => {
+                warn_conflicting_alias(effects, &from, &existing_alias)?;
+            }
+            _ => global_config.set(&alias_key, alias)?,
+        }
+    }
+    Ok(())
+}
+
+/// Install the `git-branchless` hooks and aliases for all repositories, using
+/// the global `core.hooksPath`.
+#[instrument]
+pub fn init_global(effects: &Effects, force: bool) -> eyre::Result<()> {
+    let global_config_path = Config::get_global_path()?;
+    let mut global_config = Config::open(&global_config_path)?;
+    let hooks_path = determine_global_hooks_path(&global_config, &global_config_path)?;
+
+    for (hook_type, hook_script) in ALL_HOOKS {
+        writeln!(
+            effects.get_output_stream(),
+            "Installing hook: {}",
+            hook_type
+        )?;
+        let hook = Hook::RegularHook {
+            path: hooks_path.join(hook_type),
+        };
+        update_hook_contents(&global_config, &hook, hook_script)?;
+    }
+    let hooks_path_str = hooks_path
+        .to_slash()
+        .ok_or_else(|| eyre::eyre!("Could not convert hooks path to UTF-8: {:?}", hooks_path))?;
+    global_config.set("core.hooksPath", hooks_path_str.clone())?;
+    writeln!(
+        effects.get_output_stream(),
+        "Set global core.hooksPath to: {}",
+        hooks_path_str
+    )?;
+    install_global_aliases(effects, &mut global_config, force)?;
+
+    // The repository's own `core.hooksPath` takes precedence over the global
+    // one, so the hooks installed above wouldn't run in it.
+    if let Ok(repo) = Repo::from_current_dir() {
+        let local_config = Config::open(&repo.get_common_path().join("config"))?;
+        let local_hooks_path: Option<String> = local_config.get("core.hooksPath")?;
+        if let Some(local_hooks_path) = local_hooks_path {
+            if local_hooks_path != hooks_path_str {
+                writeln!(
+                    effects.get_output_stream(),
+                    "{}: this repository sets core.hooksPath to: {}",
+                    style("Warning").yellow().bold(),
+                    local_hooks_path
+                )?;
+                writeln!(
+                    effects.get_output_stream(),
+                    "The global hooks will not run in this repository. Run `git branchless init` here to install them locally."
+                )?;
+            }
+        }
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "{}",
+        console::style("Successfully installed git-branchless globally.")
+            .green()
+            .bold()
+    )?;
+    writeln!(
+        effects.get_output_stream(),
+        "To configure the main branch of a repository, run: {}",
+        console::style("git branchless init").bold()
+    )?;
+    Ok(())
+}
+
 /// Uninstall `git-branchless` in the current repo.
+///
+/// If `dry_run` is set, only prints what would be removed.
 #[instrument]
-pub fn uninstall(effects: &Effects) -> eyre::Result<()> {
+pub fn uninstall(effects: &Effects, dry_run: bool) -> eyre::Result<()> {
     let repo = Repo::from_current_dir()?;
-    let readonly_config = repo.get_readonly_config().wrap_err("Getting repo config")?;
-    delete_isolated_config(effects, &repo, readonly_config.into_config())?;
-    uninstall_hooks(effects, &repo)?;
+    if dry_run {
+        describe_isolated_config(effects, &repo)?;
+    } else {
+        let readonly_config = repo.get_readonly_config().wrap_err("Getting repo config")?;
+        delete_isolated_config(effects, &repo, readonly_config.into_config())?;
+    }
+    uninstall_hooks(effects, &repo, dry_run)?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{update_between_lines, ALL_ALIASES, UPDATE_MARKER_END, UPDATE_MARKER_START};
+    use std::path::Path;
+
+    use super::{
+        make_windows_hook_script, merge_hook_contents, remove_between_lines, update_between_lines,
+        ALL_ALIASES, SHEBANG, UPDATE_MARKER_END, UPDATE_MARKER_START,
+    };
 
     #[test]
     fn test_update_between_lines() {
@@ -635,6 +1073,102 @@ contents 3
         )
     }
 
+    #[test]
+    fn test_merge_hook_contents_without_markers() {
+        let input = "\
+#!/bin/bash
+echo 'existing hook'";
+        let expected = format!(
+            "\
+#!/bin/bash
+echo 'existing hook'
+{}
+contents 1
+{}
+",
+            UPDATE_MARKER_START, UPDATE_MARKER_END
+        );
+        assert_eq!(
+            merge_hook_contents(Some(input), "contents 1\n", SHEBANG),
+            expected
+        );
+
+        // Installing again should update the branchless section in place.
+        assert_eq!(
+            merge_hook_contents(Some(&expected), "contents 1\n", SHEBANG),
+            expected
+        );
+
+        let expected = format!(
+            "{}\n{}\ncontents 1\n\n{}\n",
+            SHEBANG, UPDATE_MARKER_START, UPDATE_MARKER_END
+        );
+        assert_eq!(
+            merge_hook_contents(Some(""), "contents 1\n", SHEBANG),
+            expected
+        );
+        assert_eq!(merge_hook_contents(None, "contents 1\n", SHEBANG), expected);
+
+        // Hooks edited on Windows may have CRLF line endings, which would
+        // cause the shell to fail to run them.
+        let input = "#!/bin/sh\r\necho 'existing hook'\r\n";
+        let expected = format!(
+            "#!/bin/sh\necho 'existing hook'\n{}\ncontents 1\n{}\n",
+            UPDATE_MARKER_START, UPDATE_MARKER_END
+        );
+        assert_eq!(
+            merge_hook_contents(Some(input), "contents 1\n", SHEBANG),
+            expected
+        );
+
+        // The shebang is only used for new hook files.
+        let expected = format!(
+            "#!/usr/bin/env bash\n{}\ncontents 1\n\n{}\n",
+            UPDATE_MARKER_START, UPDATE_MARKER_END
+        );
+        assert_eq!(
+            merge_hook_contents(None, "contents 1\n", "#!/usr/bin/env bash"),
+            expected
+        );
+        let input = "#!/bin/sh\necho 'existing hook'\n";
+        let expected = format!(
+            "#!/bin/sh\necho 'existing hook'\n{}\ncontents 1\n{}\n",
+            UPDATE_MARKER_START, UPDATE_MARKER_END
+        );
+        assert_eq!(
+            merge_hook_contents(Some(input), "contents 1\n", "#!/usr/bin/env bash"),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_remove_between_lines() {
+        let input = format!(
+            "\
+hello, world
+{}
+contents 1
+{}
+goodbye, world
+",
+            UPDATE_MARKER_START, UPDATE_MARKER_END
+        );
+        let expected = "\
+hello, world
+goodbye, world
+";
+        assert_eq!(remove_between_lines(&input), expected);
+    }
+
+    #[test]
+    fn test_make_windows_hook_script() {
+        let exe_path = Path::new(r"C:\Program Files\branchless\git-branchless.exe");
+        assert_eq!(
+            make_windows_hook_script("git branchless hook-post-commit \"$@\"\n", exe_path),
+            "'C:/Program Files/branchless/git-branchless.exe' hook-post-commit \"$@\"\n"
+        );
+    }
+
     #[test]
     fn test_all_alias_binaries_exist() {
         let all_alias_binaries_installed = cfg!(feature = "man-pages");
@@ -1,16 +1,26 @@
 //! Sub-commands of `git-branchless`.
 
 pub mod amend;
+pub mod autosquash;
 pub mod bug_report;
+pub mod diff;
+pub mod fold;
 pub mod gc;
 pub mod hide;
 pub mod hooks;
 pub mod init;
 pub mod r#move;
 pub mod navigation;
+pub mod record;
+pub mod repair;
 pub mod restack;
+pub mod reword;
 pub mod smartlog;
+pub mod snapshot;
+pub mod split;
+pub mod submit;
 pub mod sync;
+pub mod test;
 pub mod undo;
 pub mod wrap;
 
@@ -30,17 +40,65 @@ use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
 
 use crate::core::config::env_vars::get_path_to_git;
-use crate::core::effects::Effects;
-use crate::core::formatting::Glyphs;
+use crate::core::effects::{Effects, Verbosity};
+use crate::core::formatting::{Glyphs, TextOverflow};
 use crate::git::GitRunInfo;
 use crate::git::NonZeroOid;
 use crate::opts::ColorSetting;
 use crate::opts::Command;
 use crate::opts::Opts;
+use crate::opts::SmartlogGlyphs;
+use crate::opts::SmartlogOrder;
+use crate::opts::TestSubcommand;
 use crate::opts::WrappedCommand;
 
 use self::smartlog::SmartlogOptions;
 
+/// The exit codes of `git-branchless` commands, so that scripts can tell apart
+/// the different ways in which a command can finish.
+///
+/// Commands which invoke Git may also exit with the exit code of the failed
+/// Git invocation, and an unexpected internal error results in a crash with a
+/// different exit code.
+///
+/// The codes are listed in the help text for `git-branchless`, so they must be
+/// kept in sync with `opts::EXIT_STATUS_HELP`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BranchlessExitCode {
+    /// `0`: The command succeeded.
+    Success = 0,
+
+    /// `1`: The command couldn't be carried out as requested, such as because
+    /// of an invalid argument or an unknown revision, or because the user
+    /// aborted it.
+    UserError = 1,
+
+    /// `2`: The command stopped because of a merge conflict, which needs to be
+    /// resolved before trying again.
+    MergeConflict = 2,
+
+    /// `3`: There was nothing for the command to do.
+    NothingToDo = 3,
+
+    /// `4`: The command run by `git test` failed for at least one commit.
+    TestFailed = 4,
+}
+
+impl From<BranchlessExitCode> for isize {
+    fn from(exit_code: BranchlessExitCode) -> Self {
+        exit_code as isize
+    }
+}
+
+impl BranchlessExitCode {
+    /// Whether the given exit code indicates that the command didn't fail,
+    /// i.e. it either succeeded or had nothing to do.
+    pub fn is_ok(exit_code: isize) -> bool {
+        exit_code == isize::from(BranchlessExitCode::Success)
+            || exit_code == isize::from(BranchlessExitCode::NothingToDo)
+    }
+}
+
 fn rewrite_args(args: Vec<OsString>) -> Vec<OsString> {
     let first_arg = match args.first() {
         None => return args,
@@ -84,7 +142,22 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
         working_directory,
         command,
         color,
-    } = Opts::parse_from(args);
+        quiet,
+        verbose,
+    } = match Opts::try_parse_from(args) {
+        Ok(opts) => opts,
+        Err(err) => {
+            // Report invalid arguments as user errors, rather than with
+            // `clap`'s default exit code, which would be indistinguishable
+            // from a merge conflict.
+            err.print()?;
+            return Ok(if err.use_stderr() {
+                BranchlessExitCode::UserError as i32
+            } else {
+                BranchlessExitCode::Success as i32
+            });
+        }
+    };
     if let Some(working_directory) = working_directory {
         std::env::set_current_dir(&working_directory).wrap_err_with(|| {
             format!(
@@ -102,15 +175,64 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
         env: std::env::vars_os().collect(),
     };
 
+    // Scripts rely on `git smartlog --plain` to produce the same output
+    // regardless of the terminal.
+    let is_plain = matches!(command, Command::Smartlog { plain: true, .. });
+    let color = if is_plain {
+        Some(ColorSetting::Never)
+    } else {
+        color
+    };
+
     let color = match color {
         Some(ColorSetting::Always) => Glyphs::pretty(),
         Some(ColorSetting::Never) => Glyphs::text(),
         Some(ColorSetting::Auto) | None => Glyphs::detect(),
     };
-    let effects = Effects::new(color);
+    // Progress indicators are written to stderr, but they would be confusing
+    // if interleaved with output that's being redirected elsewhere.
+    let effects = if is_plain
+        || quiet
+        || !console::Term::stdout().is_term()
+        || !console::Term::stderr().is_term()
+    {
+        Effects::new_without_progress(color)
+    } else {
+        Effects::new(color)
+    };
+    let verbosity = if quiet {
+        Verbosity::Quiet
+    } else if verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    };
+    let effects = effects.verbosity(verbosity);
 
     let exit_code = match command {
-        Command::Amend { move_options } => amend::amend(&effects, &git_run_info, &move_options)?,
+        Command::Amend {
+            reparent,
+            messages,
+            edit,
+            move_options,
+        } => amend::amend(
+            &effects,
+            &git_run_info,
+            reparent,
+            messages,
+            edit,
+            &move_options,
+        )?,
+
+        Command::Autosquash {
+            commit_template,
+            move_options,
+        } => autosquash::autosquash(
+            &effects,
+            &git_run_info,
+            commit_template.as_deref(),
+            &move_options,
+        )?,
 
         Command::BugReport => bug_report::bug_report(&effects, &git_run_info)?,
 
@@ -118,12 +240,43 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
             navigation::checkout(&effects, &git_run_info, &checkout_options)?
         }
 
-        Command::Gc | Command::HookPreAutoGc => {
-            gc::gc(&effects)?;
+        Command::Diff {
+            commit,
+            range,
+            diff_args,
+        } => diff::diff(&effects, &git_run_info, commit, range, &diff_args)?,
+
+        Command::Fold {
+            commit,
+            edit,
+            commit_template,
+            move_options,
+        } => fold::fold(
+            &effects,
+            &git_run_info,
+            commit,
+            edit,
+            commit_template.as_deref(),
+            &move_options,
+        )?,
+
+        Command::Gc { compact } => {
+            gc::gc(&effects, compact)?;
             0
         }
 
-        Command::Hide { commits, recursive } => hide::hide(&effects, commits, recursive)?,
+        Command::HookPreAutoGc => {
+            gc::gc(&effects, false)?;
+            0
+        }
+
+        Command::Hide {
+            commits,
+            recursive,
+            query,
+            message,
+            force,
+        } => hide::hide(&effects, commits, recursive, query, message, force)?,
 
         Command::HookDetectEmptyCommit { old_commit_oid } => {
             let old_commit_oid: NonZeroOid = old_commit_oid.parse()?;
@@ -160,6 +313,11 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
             0
         }
 
+        Command::HookPreCommit => {
+            hooks::hook_pre_commit(&effects)?;
+            0
+        }
+
         Command::HookReferenceTransaction { transaction_state } => {
             hooks::hook_reference_transaction(&effects, &transaction_state)?;
             0
@@ -176,19 +334,36 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
             0
         }
 
+        Command::Init {
+            uninstall: false,
+            main_branch_name: _,
+            dry_run: _,
+            global: true,
+            force,
+        } => {
+            init::init_global(&effects, force)?;
+            0
+        }
+
         Command::Init {
             uninstall: false,
             main_branch_name,
+            dry_run: _,
+            global: false,
+            force,
         } => {
-            init::init(&effects, &git_run_info, main_branch_name.as_deref())?;
+            init::init(&effects, &git_run_info, main_branch_name.as_deref(), force)?;
             0
         }
 
         Command::Init {
             uninstall: true,
             main_branch_name: _,
+            dry_run,
+            global: _,
+            force: _,
         } => {
-            init::uninstall(&effects)?;
+            init::uninstall(&effects, dry_run)?;
             0
         }
 
@@ -196,16 +371,27 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
             source,
             dest,
             base,
+            exact,
             move_options,
-        } => r#move::r#move(&effects, &git_run_info, source, dest, base, &move_options)?,
+        } => r#move::r#move(
+            &effects,
+            &git_run_info,
+            source,
+            dest,
+            base,
+            exact,
+            &move_options,
+        )?,
 
         Command::Next {
             traverse_commits_options,
+            target_branch,
         } => navigation::traverse_commits(
             &effects,
             &git_run_info,
             navigation::Command::Next,
             &traverse_commits_options,
+            target_branch.as_deref(),
         )?,
 
         Command::Prev {
@@ -215,31 +401,137 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
             &git_run_info,
             navigation::Command::Prev,
             &traverse_commits_options,
+            None,
         )?,
 
+        Command::Record { messages, all } => {
+            record::record(&effects, &git_run_info, messages, all)?
+        }
+
+        Command::Redo => undo::redo(&effects, &git_run_info)?,
+
+        Command::Repair { dry_run } => repair::repair(&effects, dry_run)?,
+
         Command::Restack {
             commits,
             move_options,
-        } => restack::restack(&effects, &git_run_info, commits, &move_options)?,
+            dry_run,
+        } => restack::restack(&effects, &git_run_info, commits, &move_options, dry_run)?,
+
+        Command::Restore { snapshot } => snapshot::restore(&effects, &git_run_info, snapshot)?,
+
+        Command::Reword {
+            commits,
+            messages,
+            sign_commits,
+            force,
+        } => reword::reword(
+            &effects,
+            &git_run_info,
+            commits,
+            messages,
+            sign_commits,
+            force,
+        )?,
 
         Command::Smartlog {
             show_hidden_commits,
             only_show_branches,
-        } => {
-            smartlog::smartlog(
-                &effects,
-                &git_run_info,
-                &SmartlogOptions {
-                    show_hidden_commits,
-                    only_show_branches,
+            only_show_unpushed,
+            hide_branches,
+            reverse,
+            date_order,
+            topo_order,
+            format,
+            glyphs,
+            max_commits,
+            full,
+            num_public_commits,
+            since,
+            show_age,
+            stat,
+            show_author,
+            show_index,
+            show_signatures,
+            show_legend,
+            highlight,
+            color_by_stack,
+            author,
+            no_pager,
+            wrap,
+            no_wrap,
+            no_cache,
+            plain,
+            commits,
+        } => smartlog::smartlog(
+            // The smartlog is the output that was requested, so display it
+            // even if `--quiet` was passed.
+            &effects.verbosity(verbosity.max(Verbosity::Normal)),
+            &git_run_info,
+            &SmartlogOptions {
+                show_hidden_commits,
+                only_show_branches,
+                only_show_unpushed,
+                hide_branches,
+                reverse,
+                order: match (date_order, topo_order) {
+                    (true, _) => Some(SmartlogOrder::Date),
+                    (false, true) => Some(SmartlogOrder::Topo),
+                    (false, false) => None,
                 },
-            )?;
-            0
+                format,
+                glyphs: if plain {
+                    Some(SmartlogGlyphs::Ascii)
+                } else {
+                    glyphs
+                },
+                max_commits,
+                full,
+                num_public_commits,
+                since,
+                show_age,
+                stat,
+                show_author,
+                show_index,
+                show_signatures,
+                show_legend,
+                highlight,
+                color_by_stack,
+                author,
+                commits,
+                use_pager: !no_pager && !plain,
+                overflow: match (wrap, no_wrap || plain) {
+                    (true, _) => Some(TextOverflow::Wrap),
+                    (false, true) => Some(TextOverflow::Overflow),
+                    (false, false) => None,
+                },
+                use_cache: !no_cache,
+            },
+        )?,
+
+        Command::Snapshot { include_untracked } => {
+            snapshot::snapshot(&effects, &git_run_info, include_untracked)?
         }
 
+        Command::Split {
+            commit,
+            commit_template,
+            move_options,
+        } => split::split(
+            &effects,
+            &git_run_info,
+            commit,
+            commit_template.as_deref(),
+            &move_options,
+        )?,
+
+        Command::Submit { dry_run } => submit::submit(&effects, &git_run_info, dry_run)?,
+
         Command::Sync {
             update_refs,
             force,
+            delete_merged,
+            onto,
             move_options,
             commits,
         } => sync::sync(
@@ -247,13 +539,34 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
             &git_run_info,
             update_refs,
             force,
+            delete_merged,
+            onto,
             &move_options,
             commits,
         )?,
 
-        Command::Undo { interactive } => undo::undo(&effects, &git_run_info, interactive)?,
+        Command::Test {
+            subcommand: TestSubcommand::Run { command, commits },
+        } => test::run(&effects, &git_run_info, &command, commits)?,
 
-        Command::Unhide { commits, recursive } => hide::unhide(&effects, commits, recursive)?,
+        Command::Undo {
+            interactive,
+            preview,
+            only,
+        } => undo::undo(&effects, &git_run_info, interactive, preview, only)?,
+
+        Command::Unhide {
+            commits,
+            recursive,
+            all,
+            since,
+        } => {
+            if all {
+                hide::unhide_all(&effects, since)?
+            } else {
+                hide::unhide(&effects, commits, recursive)?
+            }
+        }
 
         Command::Wrap {
             git_executable: explicit_git_executable,
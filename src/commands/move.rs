@@ -11,6 +11,7 @@ use eden_dag::DagAlgorithm;
 use rayon::ThreadPoolBuilder;
 use tracing::instrument;
 
+use crate::commands::BranchlessExitCode;
 use crate::core::config::get_restack_preserve_timestamps;
 use crate::core::dag::{resolve_commits, CommitSet, Dag, ResolveCommitsResult};
 use crate::core::effects::Effects;
@@ -58,6 +59,7 @@ pub fn r#move(
     source: Option<String>,
     dest: Option<String>,
     base: Option<String>,
+    exact: bool,
     move_options: &MoveOptions,
 ) -> eyre::Result<isize> {
     let repo = Repo::from_current_dir()?;
@@ -68,7 +70,7 @@ pub fn r#move(
                 effects.get_output_stream(),
                 "The --source and --base options cannot both be provided."
             )?;
-            return Ok(1);
+            return Ok(BranchlessExitCode::UserError.into());
         }
         (Some(source), None) => (source, false),
         (None, Some(base)) => (base, true),
@@ -77,10 +79,10 @@ pub fn r#move(
                 Some(oid) => oid,
                 None => {
                     writeln!(effects.get_output_stream(), "No --source or --base argument was provided, and no OID for HEAD is available as a default")?;
-                    return Ok(1);
+                    return Ok(BranchlessExitCode::UserError.into());
                 }
             };
-            (source_oid.to_string(), true)
+            (source_oid.to_string(), !exact)
         }
     };
     let dest = match dest {
@@ -89,7 +91,7 @@ pub fn r#move(
             Some(oid) => oid.to_string(),
             None => {
                 writeln!(effects.get_output_stream(), "No --dest argument was provided, and no OID for HEAD is available as a default")?;
-                return Ok(1);
+                return Ok(BranchlessExitCode::UserError.into());
             }
         },
     };
@@ -115,7 +117,7 @@ pub fn r#move(
             },
             ResolveCommitsResult::CommitNotFound { commit } => {
                 writeln!(effects.get_output_stream(), "Commit not found: {}", commit)?;
-                return Ok(1);
+                return Ok(BranchlessExitCode::UserError.into());
             }
         };
 
@@ -131,6 +133,7 @@ pub fn r#move(
         force_on_disk,
         detect_duplicate_commits_via_patch_id,
         resolve_merge_conflicts,
+        sign_commits,
         dump_rebase_constraints,
         dump_rebase_plan,
     } = *move_options;
@@ -140,7 +143,11 @@ pub fn r#move(
     let repo_pool = RepoResource::new_pool(&repo)?;
     let rebase_plan = {
         let mut builder = RebasePlanBuilder::new(&dag);
-        builder.move_subtree(source_oid, dest_oid)?;
+        if exact {
+            builder.move_commit_exact(source_oid, dest_oid)?;
+        } else {
+            builder.move_subtree(source_oid, dest_oid)?;
+        }
         builder.build(
             effects,
             &pool,
@@ -155,7 +162,7 @@ pub fn r#move(
     let result = match rebase_plan {
         Ok(None) => {
             writeln!(effects.get_output_stream(), "Nothing to do.")?;
-            return Ok(0);
+            return Ok(BranchlessExitCode::NothingToDo.into());
         }
         Ok(Some(rebase_plan)) => {
             let options = ExecuteRebasePlanOptions {
@@ -165,22 +172,23 @@ pub fn r#move(
                 force_in_memory,
                 force_on_disk,
                 resolve_merge_conflicts,
+                sign_commits,
                 check_out_commit_options: Default::default(),
             };
             execute_rebase_plan(effects, git_run_info, &repo, &rebase_plan, &options)?
         }
         Err(err) => {
             err.describe(effects, &repo)?;
-            return Ok(1);
+            return Ok(BranchlessExitCode::UserError.into());
         }
     };
 
     match result {
-        ExecuteRebasePlanResult::Succeeded => Ok(0),
+        ExecuteRebasePlanResult::Succeeded => Ok(BranchlessExitCode::Success.into()),
 
         ExecuteRebasePlanResult::DeclinedToMerge { merge_conflict } => {
             merge_conflict.describe(effects, &repo)?;
-            Ok(1)
+            Ok(BranchlessExitCode::MergeConflict.into())
         }
 
         ExecuteRebasePlanResult::Failed { exit_code } => Ok(exit_code),
@@ -2,26 +2,31 @@
 
 use std::collections::HashSet;
 use std::convert::TryInto;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use std::fmt::Write;
+use std::io::{stdin, BufRead};
 use std::time::SystemTime;
 
 use cursive::theme::BaseColor;
 use cursive::utils::markup::StyledString;
 use eden_dag::DagAlgorithm;
+use eyre::Context;
 use tracing::{instrument, warn};
 
 use crate::commands::smartlog::make_smartlog_graph;
-use crate::core::config::get_next_interactive;
-use crate::core::dag::{sort_commit_set, CommitSet, Dag};
+use crate::core::config::{get_navigation_move_branch, get_next_interactive};
+use crate::core::dag::{sort_commit_set, CommitSet, CommitVertex, Dag};
 use crate::core::effects::Effects;
-use crate::core::eventlog::{EventLogDb, EventReplayer};
-use crate::core::formatting::{printable_styled_string, Pluralize};
+use crate::core::eventlog::{Event, EventLogDb, EventReplayer};
+use crate::core::formatting::{printable_styled_string, Pluralize, StyledStringBuilder};
 use crate::core::node_descriptors::{
-    BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
+    BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor, CommitView,
     DifferentialRevisionDescriptor, NodeDescriptor, Redactor, RelativeTimeDescriptor,
 };
-use crate::git::{check_out_commit, CheckOutCommitOptions, GitRunInfo, NonZeroOid, Repo};
+use crate::core::smartlog_index::SmartlogIndexDb;
+use crate::git::{
+    check_out_commit, CheckOutCommitOptions, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
+};
 use crate::opts::{CheckoutOptions, TraverseCommitsOptions};
 use crate::tui::prompt_select_commit;
 
@@ -52,6 +57,14 @@ pub enum Distance {
         /// If `true`, find the farthest commit with a branch attached to it.
         move_by_branches: bool,
     },
+
+    /// Traverse commits until reaching the given commit, which must be a
+    /// descendant (for `next`) or an ancestor (for `prev`) of the starting
+    /// commit.
+    ToCommit {
+        /// The commit to traverse towards.
+        target_oid: NonZeroOid,
+    },
 }
 
 /// Some commits have multiple children, which makes `next` ambiguous. These
@@ -70,6 +83,114 @@ pub enum Towards {
     Interactive,
 }
 
+/// The file under `.git/branchless` which stores the [`NavigationState`].
+const NAVIGATION_STATE_FILE_NAME: &str = "navigation";
+
+/// The path taken by a sequence of `prev` commands, so that `next` can retrace
+/// it without having to ask which child commit to go to.
+///
+/// The state is only valid as long as `HEAD` still points to `head_oid`. If the
+/// user checks out something else in the meantime, then the navigation session
+/// is over and the state is discarded.
+#[derive(Debug, Default)]
+struct NavigationState {
+    /// The commit which was checked out at the end of the last navigation.
+    head_oid: Option<NonZeroOid>,
+
+    /// The commits which `prev` started from, in order of traversal.
+    path: Vec<NonZeroOid>,
+}
+
+impl NavigationState {
+    fn get_path(repo: &Repo) -> std::path::PathBuf {
+        repo.get_path()
+            .join("branchless")
+            .join(NAVIGATION_STATE_FILE_NAME)
+    }
+
+    /// Load the navigation state for the session ending at `head_oid`. If the
+    /// saved state belongs to a different session, an empty state is returned.
+    #[instrument]
+    fn load(repo: &Repo, head_oid: NonZeroOid) -> eyre::Result<Self> {
+        let contents = match std::fs::read_to_string(Self::get_path(repo)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err).wrap_err("Reading navigation state"),
+        };
+
+        let mut oids = Vec::new();
+        for line in contents.lines() {
+            let oid: NonZeroOid = line.parse().wrap_err("Parsing navigation state")?;
+            oids.push(oid);
+        }
+        match oids.split_first() {
+            Some((saved_head_oid, path)) if *saved_head_oid == head_oid => {
+                let mut existing_path = Vec::new();
+                for oid in path {
+                    if repo.find_commit(*oid)?.is_some() {
+                        existing_path.push(*oid);
+                    }
+                }
+                Ok(Self {
+                    head_oid: Some(head_oid),
+                    path: existing_path,
+                })
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Save the navigation state, so that it can be picked up by the next
+    /// navigation command.
+    #[instrument]
+    fn save(&self, repo: &Repo) -> eyre::Result<()> {
+        let head_oid = match self.head_oid {
+            Some(head_oid) => head_oid,
+            None => return Self::clear(repo),
+        };
+        let contents: String = std::iter::once(head_oid)
+            .chain(self.path.iter().copied())
+            .map(|oid| format!("{}\n", oid))
+            .collect();
+        std::fs::write(Self::get_path(repo), contents).wrap_err("Writing navigation state")?;
+        Ok(())
+    }
+
+    /// Discard any saved navigation state.
+    #[instrument]
+    fn clear(repo: &Repo) -> eyre::Result<()> {
+        match std::fs::remove_file(Self::get_path(repo)) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).wrap_err("Removing navigation state"),
+        }
+    }
+}
+
+/// Prompt the user to select one of the numbered `commits` by typing its
+/// number. Returns `None` if the user didn't select a valid commit.
+fn prompt_select_numbered(
+    effects: &Effects,
+    commits: &[CommitView],
+) -> eyre::Result<Option<NonZeroOid>> {
+    write!(
+        effects.get_primary_output_stream(),
+        "Select a commit to go to [1-{}]: ",
+        commits.len()
+    )?;
+
+    let mut user_input = String::new();
+    stdin().lock().read_line(&mut user_input)?;
+    let selected_commit = user_input
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|j| j.checked_sub(1))
+        .and_then(|j| commits.get(j))
+        .map(|commit| commit.get_oid());
+    Ok(selected_commit)
+}
+
 #[instrument(skip(commit_descriptors))]
 fn advance(
     effects: &Effects,
@@ -80,7 +201,18 @@ fn advance(
     command: Command,
     distance: Distance,
     towards: Option<Towards>,
+    navigation_path: &[NonZeroOid],
 ) -> eyre::Result<Option<NonZeroOid>> {
+    // If the user didn't ask for a specific child commit, then prefer to go
+    // back the way that they came via `prev`.
+    let navigation_path_ancestors = match (command, towards) {
+        (Command::Next, None) => Some(
+            dag.query()
+                .ancestors(navigation_path.iter().copied().collect())?,
+        ),
+        _ => None,
+    };
+
     let towards = match towards {
         Some(towards) => Some(towards),
         None => {
@@ -132,9 +264,30 @@ fn advance(
                         amount: _,
                         move_by_branches: true,
                     } => descendant_branches()?,
+
+                    Distance::ToCommit { target_oid } => child_commits()?
+                        .intersection(&dag.query().ancestors(CommitSet::from(target_oid))?),
                 };
 
-                sort_commit_set(repo, dag, &children)?
+                let children = sort_commit_set(repo, dag, &children)?;
+                match &navigation_path_ancestors {
+                    Some(navigation_path_ancestors) if children.len() > 1 => {
+                        let mut children_on_path = Vec::new();
+                        for child in children.iter() {
+                            if navigation_path_ancestors.contains(&child.get_oid().into())? {
+                                children_on_path.push(child.get_oid());
+                            }
+                        }
+                        match children_on_path.as_slice() {
+                            [child_oid] => children
+                                .into_iter()
+                                .filter(|child| child.get_oid() == *child_oid)
+                                .collect(),
+                            _ => children,
+                        }
+                    }
+                    _ => children,
+                }
             }
 
             Command::Prev => {
@@ -180,11 +333,16 @@ fn advance(
                         amount: _,
                         move_by_branches: true,
                     } => ancestor_branches()?,
+
+                    Distance::ToCommit { target_oid } => parent_commits()?
+                        .intersection(&dag.query().descendants(CommitSet::from(target_oid))?),
                 };
 
                 sort_commit_set(repo, dag, &parents)?
             }
         };
+        let candidate_commits: Vec<CommitView> =
+            candidate_commits.into_iter().map(CommitView::new).collect();
 
         match distance {
             Distance::NumCommits {
@@ -203,6 +361,12 @@ fn advance(
                     break;
                 }
             }
+
+            Distance::ToCommit { target_oid } => {
+                if current_oid == target_oid {
+                    break;
+                }
+            }
         }
 
         let pluralize = match command {
@@ -225,27 +389,43 @@ fn advance(
 
         current_oid = match (towards, candidate_commits.as_slice()) {
             (_, []) => {
-                writeln!(
-                    effects.get_output_stream(),
-                    "{}",
-                    printable_styled_string(
-                        glyphs,
-                        StyledString::styled(
-                            format!(
-                                "No more {} commits to go to after traversing {}.",
-                                pluralize.unit.0, pluralize,
-                            ),
-                            BaseColor::Yellow.light()
-                        )
-                    )?
-                )?;
-
                 if i == 0 {
                     // If we didn't succeed in traversing any commits, then
                     // treat the operation as a failure. Otherwise, assume that
                     // the user just meant to go as many commits as possible.
+                    writeln!(
+                        effects.get_output_stream(),
+                        "{}",
+                        printable_styled_string(
+                            glyphs,
+                            StyledString::styled(
+                                format!(
+                                    "No more {} commits to go to after traversing {}.",
+                                    pluralize.unit.0, pluralize,
+                                ),
+                                BaseColor::Yellow.light()
+                            )
+                        )?
+                    )?;
                     return Ok(None);
                 } else {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "{}",
+                        printable_styled_string(
+                            glyphs,
+                            StyledStringBuilder::new()
+                                .append_styled(
+                                    format!(
+                                        "No more {} commits to go to after traversing {}; stopping at ",
+                                        pluralize.unit.0, pluralize,
+                                    ),
+                                    BaseColor::Yellow.light()
+                                )
+                                .append(repo.friendly_describe_commit_from_oid(glyphs, current_oid)?)
+                                .build()
+                        )?
+                    )?;
                     break;
                 }
             }
@@ -266,27 +446,62 @@ fn advance(
                     }
                 }
             }
+            (None, [_, _, ..]) if i > 0 => {
+                // Stop at the fork or merge rather than failing, since the
+                // commits traversed so far were unambiguous.
+                writeln!(
+                    effects.get_output_stream(),
+                    "{}",
+                    printable_styled_string(
+                        glyphs,
+                        StyledStringBuilder::new()
+                            .append_styled(
+                                format!(
+                                    "Found multiple possible {} commits to go to after traversing {}; stopping at ",
+                                    pluralize.unit.0, pluralize,
+                                ),
+                                BaseColor::Yellow.light()
+                            )
+                            .append(repo.friendly_describe_commit_from_oid(glyphs, current_oid)?)
+                            .build()
+                    )?
+                )?;
+                writeln!(effects.get_output_stream(), "(Pass --oldest (-o), --newest (-n), or --interactive (-i) to continue past ambiguous commits)")?;
+                break;
+            }
             (None, [_, _, ..]) => {
-                writeln!(effects.get_output_stream(), "{}", header)?;
-                for (j, child) in (0..).zip(candidate_commits.iter()) {
-                    let descriptor = if j == 0 {
+                writeln!(effects.get_primary_output_stream(), "{}", header)?;
+                for (j, child) in (1..).zip(candidate_commits.iter()) {
+                    let descriptor = if j == 1 {
                         " (oldest)"
-                    } else if j + 1 == candidate_commits.len() {
+                    } else if j == candidate_commits.len() {
                         " (newest)"
                     } else {
                         ""
                     };
 
                     writeln!(
-                        effects.get_output_stream(),
-                        "  {} {}{}",
-                        glyphs.bullet_point,
-                        printable_styled_string(glyphs, child.friendly_describe(glyphs)?)?,
+                        effects.get_primary_output_stream(),
+                        "  {}. {}{}",
+                        j,
+                        printable_styled_string(glyphs, child.describe(glyphs)?)?,
                         descriptor
                     )?;
                 }
-                writeln!(effects.get_output_stream(), "(Pass --oldest (-o), --newest (-n), or --interactive (-i) to select between ambiguous commits)")?;
-                return Ok(None);
+
+                // Only prompt if there's a user to answer; otherwise, fail
+                // rather than guessing which commit to go to.
+                if !console::user_attended() {
+                    writeln!(effects.get_output_stream(), "(Pass --oldest (-o), --newest (-n), or --interactive (-i) to select between ambiguous commits)")?;
+                    return Ok(None);
+                }
+                match prompt_select_numbered(effects, &candidate_commits)? {
+                    Some(oid) => oid,
+                    None => {
+                        writeln!(effects.get_primary_output_stream(), "Aborted.")?;
+                        return Ok(None);
+                    }
+                }
             }
         };
 
@@ -296,12 +511,16 @@ fn advance(
 }
 
 /// Go forward or backward a certain number of commits.
+///
+/// If `target_branch` is set, then traverse commits towards the commit that
+/// branch points to instead, and check out the branch once it's reached.
 #[instrument]
 pub fn traverse_commits(
     effects: &Effects,
     git_run_info: &GitRunInfo,
     command: Command,
     options: &TraverseCommitsOptions,
+    target_branch: Option<&str>,
 ) -> eyre::Result<isize> {
     let TraverseCommitsOptions {
         num_commits,
@@ -312,6 +531,7 @@ pub fn traverse_commits(
         interactive,
         merge,
         force,
+        move_branch,
     } = *options;
 
     let distance = match (all_the_way, num_commits) {
@@ -346,7 +566,7 @@ pub fn traverse_commits(
     let head_info = repo.get_head_info()?;
     let references_snapshot = repo.get_references_snapshot()?;
     let conn = repo.get_db_conn()?;
-    let event_log_db = EventLogDb::new(&conn)?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
     let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
     let event_cursor = event_replayer.make_default_cursor();
     let dag = Dag::open_and_sync(
@@ -364,6 +584,46 @@ pub fn traverse_commits(
         }
     };
 
+    let distance = match target_branch {
+        None => distance,
+        Some(branch_name) => {
+            let target_oid = match repo.find_branch(branch_name, git2::BranchType::Local)? {
+                Some(branch) => repo.resolve_reference(&branch.into_reference())?.oid,
+                None => None,
+            };
+            let target_oid = match target_oid {
+                Some(target_oid) => target_oid,
+                None => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Branch not found: {}",
+                        branch_name
+                    )?;
+                    return Ok(1);
+                }
+            };
+            let (ancestor_oid, descendant_oid, relation) = match command {
+                Command::Next => (head_oid, target_oid, "descendant"),
+                Command::Prev => (target_oid, head_oid, "ancestor"),
+            };
+            if !dag.query().is_ancestor(
+                CommitVertex::from(ancestor_oid),
+                CommitVertex::from(descendant_oid),
+            )? {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Branch {} is not a {} of the current commit.",
+                    branch_name,
+                    relation,
+                )?;
+                return Ok(1);
+            }
+            Distance::ToCommit { target_oid }
+        }
+    };
+
+    let navigation_state = NavigationState::load(&repo, head_oid)?;
+
     let current_oid = advance(
         effects,
         &repo,
@@ -384,12 +644,75 @@ pub fn traverse_commits(
         command,
         distance,
         towards,
+        &navigation_state.path,
     )?;
     let current_oid = match current_oid {
         None => return Ok(1),
         Some(current_oid) => current_oid,
     };
 
+    // Remember where `prev` started from. Once `next` has left the recorded
+    // path, there's nothing left to retrace.
+    let navigation_state = {
+        let mut path = navigation_state.path;
+        match command {
+            Command::Prev => path.push(head_oid),
+            Command::Next => {
+                let path_ancestors = dag.query().ancestors(path.iter().copied().collect())?;
+                if !path_ancestors.contains(&current_oid.into())? {
+                    path.clear();
+                }
+            }
+        }
+        NavigationState {
+            head_oid: if path.is_empty() {
+                None
+            } else {
+                Some(current_oid)
+            },
+            path,
+        }
+    };
+
+    let additional_args = {
+        let mut args = Vec::new();
+        if merge {
+            args.push("--merge");
+        }
+        if force {
+            args.push("--force")
+        }
+        args
+    };
+
+    // Only move the checked-out branch forward, so that no commits are
+    // left behind.
+    let move_branch = move_branch || get_navigation_move_branch(&repo)?;
+    if let Some(branch_name) = &head_info.reference_name {
+        if move_branch
+            && current_oid != head_oid
+            && dag.query().is_ancestor(
+                CommitVertex::from(head_oid),
+                CommitVertex::from(current_oid),
+            )?
+        {
+            let exit_code = move_branch_to_commit(
+                effects,
+                git_run_info,
+                &repo,
+                &mut event_log_db,
+                branch_name,
+                head_oid,
+                current_oid,
+                additional_args.as_slice(),
+            )?;
+            if exit_code == 0 {
+                navigation_state.save(&repo)?;
+            }
+            return Ok(exit_code);
+        }
+    }
+
     let current_oid: OsString = match distance {
         Distance::AllTheWay {
             move_by_branches: false,
@@ -423,19 +746,14 @@ pub fn traverse_commits(
                 current_oid.to_string().into()
             }
         }
-    };
 
-    let additional_args = {
-        let mut args = Vec::new();
-        if merge {
-            args.push("--merge");
-        }
-        if force {
-            args.push("--force")
-        }
-        args
+        Distance::ToCommit { target_oid } => match target_branch {
+            Some(branch_name) if current_oid == target_oid => branch_name.into(),
+            _ => current_oid.to_string().into(),
+        },
     };
-    check_out_commit(
+
+    let exit_code = check_out_commit(
         effects,
         git_run_info,
         None,
@@ -444,6 +762,61 @@ pub fn traverse_commits(
             additional_args: additional_args.as_slice(),
             ..Default::default()
         },
+    )?;
+    if exit_code == 0 {
+        navigation_state.save(&repo)?;
+    }
+    Ok(exit_code)
+}
+
+/// Check out `new_oid` and move the checked-out branch `branch_name` from
+/// `old_oid` to it, so that the branch stays checked out.
+#[instrument]
+fn move_branch_to_commit(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &mut EventLogDb,
+    branch_name: &OsStr,
+    old_oid: NonZeroOid,
+    new_oid: NonZeroOid,
+    additional_args: &[&str],
+) -> eyre::Result<isize> {
+    let now = SystemTime::now();
+    let event_tx_id = event_log_db.make_transaction_id(now, "move branch")?;
+
+    let exit_code = check_out_commit(
+        effects,
+        git_run_info,
+        Some(event_tx_id),
+        Some(new_oid.to_string()),
+        &CheckOutCommitOptions {
+            additional_args,
+            render_smartlog: false,
+        },
+    )?;
+    if exit_code != 0 {
+        return Ok(exit_code);
+    }
+
+    // Updating the reference directly doesn't invoke the
+    // `reference-transaction` hook, so record the update here.
+    repo.create_reference(branch_name, new_oid, true, "branchless: move branch")?;
+    event_log_db.add_events(vec![Event::RefUpdateEvent {
+        timestamp: now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64(),
+        event_tx_id,
+        ref_name: branch_name.to_owned(),
+        old_oid: MaybeZeroOid::NonZero(old_oid),
+        new_oid: MaybeZeroOid::NonZero(new_oid),
+        message: None,
+    }])?;
+
+    check_out_commit(
+        effects,
+        git_run_info,
+        Some(event_tx_id),
+        Some(branch_name),
+        &Default::default(),
     )
 }
 
@@ -548,6 +921,8 @@ pub fn checkout(
         event_cursor,
         true,
         false,
+        None,
+        0,
     )?;
 
     let initial_query = get_initial_query(checkout_options);
@@ -577,6 +952,32 @@ pub fn checkout(
         }
     };
 
+    // If the target is an index shown by `git smartlog --show-index`, check
+    // out the corresponding commit. Revisions take precedence, so that
+    // branches and commit hashes which consist only of digits can still be
+    // checked out. (An abbreviated hash which is ambiguous fails to parse as a
+    // revision, so it's also looked up as an index.)
+    let target = match target {
+        Some(target)
+            if !target.is_empty()
+                && target.chars().all(|c| c.is_ascii_digit())
+                && !matches!(repo.revparse_single_commit(&target), Ok(Some(_))) =>
+        {
+            let smartlog_index_db = SmartlogIndexDb::new(&conn)?;
+            match target
+                .parse()
+                .ok()
+                .map(|idx| smartlog_index_db.get_commit(idx))
+                .transpose()?
+                .flatten()
+            {
+                Some(commit_oid) => Some(commit_oid.to_string()),
+                None => Some(target),
+            }
+        }
+        target => target,
+    };
+
     let additional_args = {
         let mut args = Vec::new();
         if let Some(branch_name) = branch_name {
@@ -602,5 +1003,9 @@ pub fn checkout(
             render_smartlog: true,
         },
     )?;
+    if exit_code == 0 {
+        // Checking out a commit explicitly ends the navigation session.
+        NavigationState::clear(&repo)?;
+    }
     Ok(exit_code)
 }
@@ -0,0 +1,75 @@
+//! Create a commit from changes in the working copy, selecting which changes
+//! to include interactively.
+//!
+//! This is similar to `hg commit --interactive` or `darcs record`: staging the
+//! changes and committing them happen in a single step.
+
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use tracing::instrument;
+
+use crate::commands::smartlog::smartlog;
+use crate::commands::BranchlessExitCode;
+use crate::core::effects::Effects;
+use crate::core::eventlog::EventLogDb;
+use crate::git::{GitRunInfo, Repo};
+
+/// Commit changes from the working copy. If `all` is set, all changes to
+/// tracked files are committed. Otherwise, the user selects the changes to
+/// commit interactively, in addition to any changes which are already staged.
+/// If `messages` is empty, the commit message is edited in the user's editor.
+#[instrument]
+pub fn record(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    messages: Vec<String>,
+    all: bool,
+) -> eyre::Result<isize> {
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+
+    if repo.get_status(git_run_info, None)?.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "There are no uncommitted or staged changes. Nothing to record."
+        )?;
+        return Ok(BranchlessExitCode::NothingToDo.into());
+    }
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "record")?;
+    if !all {
+        let exit_code =
+            git_run_info.run_interactive(effects, Some(event_tx_id), &["add", "--patch"])?;
+        if exit_code != 0 {
+            return Ok(exit_code);
+        }
+        if repo.get_staged_paths()?.is_empty() {
+            writeln!(
+                effects.get_output_stream(),
+                "No changes were selected. Aborting record."
+            )?;
+            return Ok(BranchlessExitCode::UserError.into());
+        }
+    }
+
+    let args = {
+        let mut args = vec!["commit".to_string()];
+        if all {
+            args.push("--all".to_string());
+        }
+        for message in messages {
+            args.push("--message".to_string());
+            args.push(message);
+        }
+        args
+    };
+    let exit_code = git_run_info.run_interactive(effects, Some(event_tx_id), &args)?;
+    if exit_code != 0 {
+        return Ok(exit_code);
+    }
+
+    smartlog(effects, git_run_info, &Default::default())
+}
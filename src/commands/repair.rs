@@ -0,0 +1,310 @@
+//! Repair the branchless event log after it's fallen out of sync with the
+//! repository.
+//!
+//! The event log is normally kept up to date by the hooks installed with `git
+//! branchless init`. If the hooks didn't run (for example, because of a crash,
+//! or because `.git/branchless` was deleted), then the smartlog may show stale
+//! commits or be missing commits. This module reconstructs a best-effort version
+//! of the missing events from the current references and the `HEAD` reflog.
+
+use std::collections::HashSet;
+use std::convert::{TryFrom, TryInto};
+use std::ffi::{OsStr, OsString};
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use tracing::instrument;
+
+use crate::commands::gc::mark_commit_reachable;
+use crate::commands::snapshot::is_snapshot_ref;
+use crate::core::dag::Dag;
+use crate::core::effects::Effects;
+use crate::core::eventlog::{
+    is_gc_ref, CommitActivityStatus, Event, EventLogDb, EventReplayer, EventTransactionId,
+    MISSING_COMMIT_HIDE_MESSAGE,
+};
+use crate::core::formatting::{printable_styled_string, Glyphs, Pluralize};
+use crate::git::{NonZeroOid, Repo};
+
+/// A way in which the event log disagrees with the state of the repository.
+#[derive(Debug)]
+enum Discrepancy {
+    /// A draft commit is referenced by the repository, but the event log has no
+    /// record of it, so it's not shown in the smartlog.
+    UnobservedCommit { commit_oid: NonZeroOid },
+
+    /// A commit was amended according to the `HEAD` reflog, but the event log
+    /// has no record of the rewrite.
+    UnobservedRewrite {
+        old_commit_oid: NonZeroOid,
+        new_commit_oid: NonZeroOid,
+    },
+
+    /// The event log considers a commit to be visible, but it no longer exists
+    /// in the repository.
+    MissingCommit { commit_oid: NonZeroOid },
+
+    /// The event log considers a commit to be visible, but there's no reference
+    /// keeping it from being garbage-collected by Git.
+    UnreachableCommit { commit_oid: NonZeroOid },
+}
+
+impl Discrepancy {
+    fn to_event(&self, timestamp: f64, event_tx_id: EventTransactionId) -> Option<Event> {
+        let event = match self {
+            Discrepancy::UnobservedCommit { commit_oid } => Event::CommitEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid: *commit_oid,
+            },
+            Discrepancy::UnobservedRewrite {
+                old_commit_oid,
+                new_commit_oid,
+            } => Event::RewriteEvent {
+                timestamp,
+                event_tx_id,
+                old_commit_oid: (*old_commit_oid).into(),
+                new_commit_oid: (*new_commit_oid).into(),
+            },
+            Discrepancy::MissingCommit { commit_oid } => Event::ObsoleteEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid: *commit_oid,
+                message: Some(OsString::from(MISSING_COMMIT_HIDE_MESSAGE)),
+            },
+            Discrepancy::UnreachableCommit { commit_oid: _ } => return None,
+        };
+        Some(event)
+    }
+
+    fn describe(&self, glyphs: &Glyphs, repo: &Repo, dry_run: bool) -> eyre::Result<String> {
+        let describe_commit = |commit_oid: &NonZeroOid| -> eyre::Result<String> {
+            printable_styled_string(
+                glyphs,
+                repo.friendly_describe_commit_from_oid(glyphs, *commit_oid)?,
+            )
+        };
+        let result = match self {
+            Discrepancy::UnobservedCommit { commit_oid } => format!(
+                "{} commit: {}",
+                if dry_run {
+                    "Would recover"
+                } else {
+                    "Recovered"
+                },
+                describe_commit(commit_oid)?
+            ),
+            Discrepancy::UnobservedRewrite {
+                old_commit_oid,
+                new_commit_oid,
+            } => format!(
+                "{} rewrite: {} -> {}",
+                if dry_run {
+                    "Would recover"
+                } else {
+                    "Recovered"
+                },
+                describe_commit(old_commit_oid)?,
+                describe_commit(new_commit_oid)?
+            ),
+            Discrepancy::MissingCommit { commit_oid } => format!(
+                "{} missing commit: {}",
+                if dry_run { "Would hide" } else { "Hid" },
+                commit_oid
+            ),
+            Discrepancy::UnreachableCommit { commit_oid } => format!(
+                "{} commit as reachable: {}",
+                if dry_run { "Would mark" } else { "Marked" },
+                describe_commit(commit_oid)?
+            ),
+        };
+        Ok(result)
+    }
+}
+
+#[instrument]
+fn find_discrepancies(
+    repo: &Repo,
+    dag: &Dag,
+    event_replayer: &EventReplayer,
+) -> eyre::Result<Vec<Discrepancy>> {
+    let event_cursor = event_replayer.make_default_cursor();
+    let public_commits = dag.query_public_commits()?;
+    let is_public_commit = |commit_oid: NonZeroOid| -> eyre::Result<bool> {
+        Ok(public_commits.contains(&commit_oid.into())?)
+    };
+    let mut observed_oids = event_replayer.get_cursor_oids(event_cursor);
+    let mut discrepancies = Vec::new();
+
+    // Commits which are still visible to branchless, but which no longer
+    // exist, or which aren't protected from Git's garbage collection.
+    let mut visible_oids: Vec<NonZeroOid> = observed_oids
+        .iter()
+        .copied()
+        .filter(|oid| {
+            matches!(
+                event_replayer.get_cursor_commit_activity_status(event_cursor, *oid),
+                CommitActivityStatus::Active
+            )
+        })
+        .collect();
+    visible_oids.sort_unstable();
+    let gc_ref_oids: HashSet<NonZeroOid> = repo
+        .get_all_references()?
+        .into_iter()
+        .filter_map(|reference| {
+            let reference_name = reference.get_name().ok()?;
+            if !is_gc_ref(&reference_name) || reference_name.to_str().map_or(true, is_snapshot_ref)
+            {
+                return None;
+            }
+            reference
+                .peel_to_commit()
+                .ok()?
+                .map(|commit| commit.get_oid())
+        })
+        .collect();
+    for commit_oid in visible_oids {
+        if repo.find_commit(commit_oid)?.is_none() {
+            discrepancies.push(Discrepancy::MissingCommit { commit_oid });
+        } else if !gc_ref_oids.contains(&commit_oid) && !is_public_commit(commit_oid)? {
+            discrepancies.push(Discrepancy::UnreachableCommit { commit_oid });
+        }
+    }
+
+    // Commits which were made or amended without the hooks having run, as
+    // recorded in the `HEAD` reflog.
+    for entry in repo.get_reflog_entries(OsStr::new("HEAD"))? {
+        let message = match &entry.message {
+            Some(message) => message,
+            None => continue,
+        };
+        if !message.starts_with("commit") {
+            continue;
+        }
+        let new_commit_oid = match NonZeroOid::try_from(entry.new_oid) {
+            Ok(new_commit_oid) => new_commit_oid,
+            Err(_) => continue,
+        };
+        if observed_oids.contains(&new_commit_oid)
+            || repo.find_commit(new_commit_oid)?.is_none()
+            || is_public_commit(new_commit_oid)?
+        {
+            continue;
+        }
+
+        match NonZeroOid::try_from(entry.old_oid) {
+            Ok(old_commit_oid) if message.starts_with("commit (amend)") => {
+                discrepancies.push(Discrepancy::UnobservedRewrite {
+                    old_commit_oid,
+                    new_commit_oid,
+                });
+                observed_oids.insert(old_commit_oid);
+            }
+            _ => {
+                discrepancies.push(Discrepancy::UnobservedCommit {
+                    commit_oid: new_commit_oid,
+                });
+            }
+        }
+        observed_oids.insert(new_commit_oid);
+    }
+
+    // Commits which are referenced by `HEAD`, a branch, or a reference created
+    // by branchless to keep them alive, but which branchless doesn't know about.
+    let references_snapshot = repo.get_references_snapshot()?;
+    let mut referenced_oids: Vec<NonZeroOid> = references_snapshot
+        .head_oid
+        .into_iter()
+        .chain(references_snapshot.branch_oid_to_names.keys().copied())
+        .chain(gc_ref_oids.iter().copied())
+        .collect();
+    referenced_oids.sort_unstable();
+    referenced_oids.dedup();
+    for commit_oid in referenced_oids {
+        if observed_oids.contains(&commit_oid) || is_public_commit(commit_oid)? {
+            continue;
+        }
+        discrepancies.push(Discrepancy::UnobservedCommit { commit_oid });
+        observed_oids.insert(commit_oid);
+    }
+
+    Ok(discrepancies)
+}
+
+/// Reconstruct missing events in the event log from the state of the
+/// repository, and report the discrepancies which were fixed.
+///
+/// If `dry_run` is set, only report the discrepancies, without fixing them.
+#[instrument]
+pub fn repair(effects: &Effects, dry_run: bool) -> eyre::Result<isize> {
+    let now = SystemTime::now();
+    let glyphs = Glyphs::detect();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    let dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let discrepancies = find_discrepancies(&repo, &dag, &event_replayer)?;
+    if discrepancies.is_empty() {
+        writeln!(effects.get_output_stream(), "No discrepancies found.")?;
+        return Ok(0);
+    }
+
+    for discrepancy in discrepancies.iter() {
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            discrepancy.describe(&glyphs, &repo, dry_run)?
+        )?;
+    }
+    let num_discrepancies = Pluralize {
+        determiner: None,
+        amount: discrepancies.len().try_into()?,
+        unit: ("discrepancy", "discrepancies"),
+    }
+    .to_string();
+    if dry_run {
+        writeln!(
+            effects.get_output_stream(),
+            "Found {}. Run without --dry-run to repair them.",
+            num_discrepancies
+        )?;
+        return Ok(0);
+    }
+
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let event_tx_id = event_log_db.make_transaction_id(now, "repair")?;
+    let mut events = Vec::new();
+    for discrepancy in discrepancies.iter() {
+        match discrepancy {
+            Discrepancy::UnobservedCommit { commit_oid }
+            | Discrepancy::UnobservedRewrite {
+                new_commit_oid: commit_oid,
+                ..
+            }
+            | Discrepancy::UnreachableCommit { commit_oid } => {
+                mark_commit_reachable(&repo, *commit_oid)?;
+            }
+            Discrepancy::MissingCommit { commit_oid: _ } => {}
+        }
+        events.extend(discrepancy.to_event(timestamp, event_tx_id));
+    }
+    event_log_db.add_events(events)?;
+
+    writeln!(
+        effects.get_output_stream(),
+        "Repaired {}.",
+        num_discrepancies
+    )?;
+    Ok(0)
+}
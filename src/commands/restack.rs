@@ -56,39 +56,85 @@
 //! ```
 
 use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
 use std::fmt::Write;
 use std::time::SystemTime;
 
+use cursive::theme::BaseColor;
+use eden_dag::DagAlgorithm;
 use rayon::{ThreadPool, ThreadPoolBuilder};
 use tracing::{instrument, warn};
 
-use crate::commands::smartlog::smartlog;
+use crate::commands::smartlog::{
+    make_smartlog_graph, render_graph, smartlog, SmartlogGraph, SubjectLayout,
+};
+use crate::commands::BranchlessExitCode;
 use crate::core::config::get_restack_preserve_timestamps;
-use crate::core::dag::{commit_set_to_vec, resolve_commits, CommitSet, Dag, ResolveCommitsResult};
+use crate::core::dag::{
+    commit_set_to_vec, resolve_commits, sort_commit_set, CommitSet, Dag, ResolveCommitsResult,
+};
 use crate::core::effects::Effects;
 use crate::core::eventlog::{EventCursor, EventLogDb, EventReplayer};
+use crate::core::formatting::{printable_styled_string, Pluralize, StyledStringBuilder};
+use crate::core::node_descriptors::{
+    BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor, CommitView, Redactor,
+};
 use crate::core::rewrite::{
     execute_rebase_plan, find_abandoned_children, find_rewrite_target, move_branches,
     BuildRebasePlanOptions, ExecuteRebasePlanOptions, ExecuteRebasePlanResult, RebasePlanBuilder,
     RepoPool, RepoResource,
 };
-use crate::git::{CheckOutCommitOptions, GitRunInfo, NonZeroOid, Repo};
+use crate::git::{
+    CheckOutCommitOptions, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo, RepoReferencesSnapshot,
+    ResolvedReferenceInfo,
+};
 use crate::opts::MoveOptions;
 
+/// Find the obsolete commits which should be restacked in order to restack the
+/// subtrees rooted at `commits`. These are the obsolete commits in the
+/// subtrees themselves, along with the commits which were rewritten into a
+/// commit in the subtrees, such as the original version of an amended commit.
+#[instrument]
+fn find_obsolete_commits_in_subtree(
+    dag: &Dag,
+    event_replayer: &EventReplayer,
+    event_cursor: EventCursor,
+    commits: CommitSet,
+) -> eyre::Result<HashSet<NonZeroOid>> {
+    let subtree = dag.query().descendants(commits)?;
+    let mut result = HashSet::new();
+    for obsolete_oid in commit_set_to_vec(&dag.obsolete_commits)? {
+        let is_in_subtree = subtree.contains(&obsolete_oid.into())?
+            || match find_rewrite_target(event_replayer, event_cursor, obsolete_oid) {
+                Some(MaybeZeroOid::NonZero(rewritten_oid)) => {
+                    subtree.contains(&rewritten_oid.into())?
+                }
+                Some(MaybeZeroOid::Zero) | None => false,
+            };
+        if is_in_subtree {
+            result.insert(obsolete_oid);
+        }
+    }
+    Ok(result)
+}
+
+/// The abandoned children of a rewritten commit, which should be moved on top
+/// of the commit it was rewritten into.
+#[derive(Debug)]
+struct RebaseInfo {
+    dest_oid: NonZeroOid,
+    abandoned_child_oids: Vec<NonZeroOid>,
+}
+
+/// Find the abandoned children of the provided obsolete commits, or of all
+/// obsolete commits if not provided.
 #[instrument(skip(commits))]
-fn restack_commits(
-    effects: &Effects,
-    pool: &ThreadPool,
-    repo_pool: &RepoPool,
+fn find_rebases(
     dag: &Dag,
     event_replayer: &EventReplayer,
     event_cursor: EventCursor,
-    git_run_info: &GitRunInfo,
     commits: Option<impl IntoIterator<Item = NonZeroOid>>,
-    build_options: &BuildRebasePlanOptions,
-    execute_options: &ExecuteRebasePlanOptions,
-) -> eyre::Result<isize> {
-    let repo = repo_pool.try_create()?;
+) -> eyre::Result<Vec<RebaseInfo>> {
     let commit_set: CommitSet = match commits {
         Some(commits) => commits.into_iter().collect(),
         None => dag.obsolete_commits.clone(),
@@ -97,24 +143,51 @@ fn restack_commits(
     // large and we'll be throwing away most of them.
     let commits = commit_set_to_vec(&commit_set)?;
 
-    struct RebaseInfo {
-        dest_oid: NonZeroOid,
-        abandoned_child_oids: Vec<NonZeroOid>,
-    }
-    let rebases: Vec<RebaseInfo> = {
-        let mut result = Vec::new();
-        for original_commit_oid in commits {
-            let abandoned_children =
-                find_abandoned_children(dag, event_replayer, event_cursor, original_commit_oid)?;
-            if let Some((rewritten_oid, abandoned_child_oids)) = abandoned_children {
-                result.push(RebaseInfo {
-                    dest_oid: rewritten_oid,
-                    abandoned_child_oids,
-                });
-            }
+    let mut result = Vec::new();
+    for original_commit_oid in commits {
+        let abandoned_children =
+            find_abandoned_children(dag, event_replayer, event_cursor, original_commit_oid)?;
+        if let Some((rewritten_oid, abandoned_child_oids)) = abandoned_children {
+            result.push(RebaseInfo {
+                dest_oid: rewritten_oid,
+                abandoned_child_oids,
+            });
         }
-        result
-    };
+    }
+    Ok(result)
+}
+
+/// Get the commits which would be moved by restacking, i.e. the abandoned
+/// children and their visible descendants.
+fn get_moved_commits(dag: &Dag, rebases: &[RebaseInfo]) -> eyre::Result<CommitSet> {
+    let abandoned_child_oids: CommitSet = rebases
+        .iter()
+        .flat_map(|rebase_info| rebase_info.abandoned_child_oids.iter().copied())
+        .collect();
+    let moved_commits = dag
+        .query()
+        .descendants(abandoned_child_oids)?
+        .intersection(&dag.observed_commits)
+        .difference(&dag.obsolete_commits);
+    Ok(moved_commits)
+}
+
+#[instrument(skip(commits))]
+fn restack_commits(
+    effects: &Effects,
+    pool: &ThreadPool,
+    repo_pool: &RepoPool,
+    dag: &Dag,
+    event_replayer: &EventReplayer,
+    event_cursor: EventCursor,
+    git_run_info: &GitRunInfo,
+    commits: Option<impl IntoIterator<Item = NonZeroOid>>,
+    build_options: &BuildRebasePlanOptions,
+    execute_options: &ExecuteRebasePlanOptions,
+) -> eyre::Result<isize> {
+    let repo = repo_pool.try_create()?;
+    let rebases = find_rebases(dag, event_replayer, event_cursor, commits)?;
+    let moved_commits = get_moved_commits(dag, &rebases)?;
 
     let rebase_plan = {
         let mut builder = RebasePlanBuilder::new(dag);
@@ -134,11 +207,11 @@ fn restack_commits(
                     effects.get_output_stream(),
                     "No abandoned commits to restack."
                 )?;
-                return Ok(0);
+                return Ok(BranchlessExitCode::NothingToDo.into());
             }
             Err(err) => {
                 err.describe(effects, &repo)?;
-                return Ok(1);
+                return Ok(BranchlessExitCode::UserError.into());
             }
         };
         rebase_plan
@@ -148,13 +221,32 @@ fn restack_commits(
         execute_rebase_plan(effects, git_run_info, &repo, &rebase_plan, execute_options)?;
     match execute_rebase_plan_result {
         ExecuteRebasePlanResult::Succeeded => {
+            let moved_commits = sort_commit_set(&repo, dag, &moved_commits)?;
+            writeln!(
+                effects.get_output_stream(),
+                "Restacked {}:",
+                Pluralize {
+                    determiner: None,
+                    amount: moved_commits.len().try_into()?,
+                    unit: ("commit", "commits"),
+                }
+            )?;
+            let glyphs = effects.get_glyphs();
+            for commit in moved_commits {
+                writeln!(
+                    effects.get_output_stream(),
+                    "  {} {}",
+                    glyphs.bullet_point,
+                    printable_styled_string(glyphs, commit.friendly_describe(glyphs)?)?
+                )?;
+            }
             writeln!(effects.get_output_stream(), "Finished restacking commits.")?;
             Ok(0)
         }
 
         ExecuteRebasePlanResult::DeclinedToMerge { merge_conflict } => {
             merge_conflict.describe(effects, &repo)?;
-            Ok(1)
+            Ok(BranchlessExitCode::MergeConflict.into())
         }
 
         ExecuteRebasePlanResult::Failed { exit_code } => {
@@ -172,6 +264,179 @@ fn restack_commits(
     }
 }
 
+/// Print the commits which would be restacked, any merge conflicts which
+/// would occur when restacking them, and the smartlog before and after
+/// restacking, without changing the repository.
+#[instrument(skip(commits))]
+fn preview_restack(
+    effects: &Effects,
+    pool: &ThreadPool,
+    repo_pool: &RepoPool,
+    repo: &Repo,
+    dag: &Dag,
+    event_replayer: &EventReplayer,
+    event_cursor: EventCursor,
+    commits: Option<impl IntoIterator<Item = NonZeroOid>>,
+    build_options: &BuildRebasePlanOptions,
+) -> eyre::Result<isize> {
+    let rebases = find_rebases(dag, event_replayer, event_cursor, commits)?;
+    let moved_commits = get_moved_commits(dag, &rebases)?;
+
+    // Build the rebase plan to detect any problems with it, but don't execute
+    // it.
+    let mut builder = RebasePlanBuilder::new(dag);
+    for RebaseInfo {
+        dest_oid,
+        abandoned_child_oids,
+    } in rebases.iter()
+    {
+        for child_oid in abandoned_child_oids {
+            builder.move_subtree(*child_oid, *dest_oid)?;
+        }
+    }
+    match builder.build(effects, pool, repo_pool, build_options)? {
+        Ok(Some(_rebase_plan)) => {}
+        Ok(None) => {
+            writeln!(
+                effects.get_output_stream(),
+                "No abandoned commits to restack."
+            )?;
+            return Ok(BranchlessExitCode::NothingToDo.into());
+        }
+        Err(err) => {
+            err.describe(effects, repo)?;
+            return Ok(BranchlessExitCode::UserError.into());
+        }
+    }
+
+    let glyphs = effects.get_glyphs();
+    let moved_commits = sort_commit_set(repo, dag, &moved_commits)?;
+    writeln!(
+        effects.get_output_stream(),
+        "Would restack {}:",
+        Pluralize {
+            determiner: None,
+            amount: moved_commits.len().try_into()?,
+            unit: ("commit", "commits"),
+        }
+    )?;
+    for commit in moved_commits {
+        writeln!(
+            effects.get_output_stream(),
+            "  {} {}",
+            glyphs.bullet_point,
+            printable_styled_string(glyphs, commit.friendly_describe(glyphs)?)?
+        )?;
+    }
+
+    // Only check whether the abandoned children apply cleanly onto their new
+    // parents, since checking their descendants would require actually
+    // rebasing the children first.
+    for RebaseInfo {
+        dest_oid,
+        abandoned_child_oids,
+    } in rebases.iter()
+    {
+        // The destination is described once for each conflicting child.
+        let dest_commit = CommitView::new(repo.find_commit_or_fail(*dest_oid)?);
+        for child_oid in abandoned_child_oids {
+            let child_commit = repo.find_commit_or_fail(*child_oid)?;
+            if child_commit.get_parent_count() > 1 {
+                continue;
+            }
+            let index = repo.cherry_pick_commit(&child_commit, &dest_commit, 0)?;
+            if index.has_conflicts() {
+                writeln!(
+                    effects.get_output_stream(),
+                    "{}",
+                    printable_styled_string(
+                        glyphs,
+                        StyledStringBuilder::new()
+                            .append_styled(
+                                "Merge conflict expected when restacking ",
+                                BaseColor::Red.light()
+                            )
+                            .append(child_commit.friendly_describe(glyphs)?)
+                            .append_styled(" onto ", BaseColor::Red.light())
+                            .append(dest_commit.describe(glyphs)?)
+                            .build()
+                    )?
+                )?;
+            }
+        }
+    }
+
+    let head_info = repo.get_head_info()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let mut graph = make_smartlog_graph(
+        effects,
+        repo,
+        dag,
+        event_replayer,
+        event_cursor,
+        true,
+        false,
+        None,
+        0,
+    )?;
+    writeln!(effects.get_output_stream(), "Before:")?;
+    render_preview_graph(effects, repo, dag, &graph, &head_info, &references_snapshot)?;
+
+    for RebaseInfo {
+        dest_oid,
+        abandoned_child_oids,
+    } in rebases.iter()
+    {
+        for child_oid in abandoned_child_oids {
+            graph.reparent(*child_oid, *dest_oid);
+        }
+    }
+    graph.remove_obsolete_leaves();
+    writeln!(
+        effects.get_output_stream(),
+        "After (restacked commits will have new hashes):"
+    )?;
+    render_preview_graph(effects, repo, dag, &graph, &head_info, &references_snapshot)?;
+
+    Ok(0)
+}
+
+fn render_preview_graph(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &Dag,
+    graph: &SmartlogGraph,
+    head_info: &ResolvedReferenceInfo,
+    references_snapshot: &RepoReferencesSnapshot,
+) -> eyre::Result<()> {
+    let lines = render_graph(
+        effects,
+        repo,
+        dag,
+        graph,
+        references_snapshot.head_oid,
+        &mut [
+            &mut CommitOidDescriptor::new(true)?,
+            &mut BranchesDescriptor::new(
+                repo,
+                head_info,
+                references_snapshot,
+                &Redactor::Disabled,
+            )?,
+            &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
+        ],
+        &SubjectLayout::default(),
+    )?;
+    for line in lines {
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            printable_styled_string(effects.get_glyphs(), line)?
+        )?;
+    }
+    Ok(())
+}
+
 #[instrument]
 fn restack_branches(
     effects: &Effects,
@@ -179,6 +444,7 @@ fn restack_branches(
     conn: &rusqlite::Connection,
     git_run_info: &GitRunInfo,
     event_log_db: &EventLogDb,
+    commits: Option<&HashSet<NonZeroOid>>,
     options: &ExecuteRebasePlanOptions,
 ) -> eyre::Result<isize> {
     let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
@@ -195,6 +461,11 @@ fn restack_branches(
                 continue;
             }
         };
+        if let Some(commits) = commits {
+            if !commits.contains(&branch_target) {
+                continue;
+            }
+        }
 
         if let Some(new_oid) = find_rewrite_target(
             &event_replayer,
@@ -210,6 +481,7 @@ fn restack_branches(
             effects.get_output_stream(),
             "No abandoned branches to restack."
         )?;
+        return Ok(BranchlessExitCode::NothingToDo.into());
     } else {
         move_branches(
             effects,
@@ -223,21 +495,24 @@ fn restack_branches(
     Ok(0)
 }
 
-/// Restack all abandoned commits.
+/// Restack all abandoned commits, or only those in the subtrees rooted at the
+/// provided commits.
 ///
-/// Returns an exit code (0 denotes successful exit).
+/// Returns an exit code as described by [`BranchlessExitCode`]. If there were neither
+/// abandoned commits nor abandoned branches to restack, returns
+/// [`BranchlessExitCode::NothingToDo`].
 #[instrument]
 pub fn restack(
     effects: &Effects,
     git_run_info: &GitRunInfo,
     commits: Vec<String>,
     move_options: &MoveOptions,
+    dry_run: bool,
 ) -> eyre::Result<isize> {
     let now = SystemTime::now();
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
-    let event_tx_id = event_log_db.make_transaction_id(now, "restack")?;
 
     let references_snapshot = repo.get_references_snapshot()?;
     let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
@@ -254,13 +529,19 @@ pub fn restack(
         ResolveCommitsResult::Ok { commits } => commits,
         ResolveCommitsResult::CommitNotFound { commit } => {
             writeln!(effects.get_output_stream(), "Commit not found: {}", commit)?;
-            return Ok(1);
+            return Ok(BranchlessExitCode::UserError.into());
         }
     };
     let commits: Option<HashSet<NonZeroOid>> = if commits.is_empty() {
         None
     } else {
-        Some(commits.into_iter().map(|commit| commit.get_oid()).collect())
+        let commits: CommitSet = commits.into_iter().map(|commit| commit.get_oid()).collect();
+        Some(find_obsolete_commits_in_subtree(
+            &dag,
+            &event_replayer,
+            event_cursor,
+            commits,
+        )?)
     };
 
     let MoveOptions {
@@ -268,6 +549,7 @@ pub fn restack(
         force_on_disk,
         detect_duplicate_commits_via_patch_id,
         resolve_merge_conflicts,
+        sign_commits,
         dump_rebase_constraints,
         dump_rebase_plan,
     } = *move_options;
@@ -276,6 +558,24 @@ pub fn restack(
         dump_rebase_plan,
         detect_duplicate_commits_via_patch_id,
     };
+    let pool = ThreadPoolBuilder::new().build()?;
+    let repo_pool = RepoResource::new_pool(&repo)?;
+
+    if dry_run {
+        return preview_restack(
+            effects,
+            &pool,
+            &repo_pool,
+            &repo,
+            &dag,
+            &event_replayer,
+            event_cursor,
+            commits,
+            &build_options,
+        );
+    }
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "restack")?;
     let execute_options = ExecuteRebasePlanOptions {
         now,
         event_tx_id,
@@ -283,15 +583,14 @@ pub fn restack(
         force_in_memory,
         force_on_disk,
         resolve_merge_conflicts,
+        sign_commits,
         check_out_commit_options: CheckOutCommitOptions {
             additional_args: &[],
             render_smartlog: false,
         },
     };
-    let pool = ThreadPoolBuilder::new().build()?;
-    let repo_pool = RepoResource::new_pool(&repo)?;
 
-    let result = restack_commits(
+    let commits_result = restack_commits(
         effects,
         &pool,
         &repo_pool,
@@ -299,26 +598,32 @@ pub fn restack(
         &event_replayer,
         event_cursor,
         git_run_info,
-        commits,
+        commits.clone(),
         &build_options,
         &execute_options,
     )?;
-    if result != 0 {
-        return Ok(result);
+    if !BranchlessExitCode::is_ok(commits_result) {
+        return Ok(commits_result);
     }
 
-    let result = restack_branches(
+    let branches_result = restack_branches(
         effects,
         &repo,
         &conn,
         git_run_info,
         &event_log_db,
+        commits.as_ref(),
         &execute_options,
     )?;
-    if result != 0 {
-        return Ok(result);
+    if !BranchlessExitCode::is_ok(branches_result) {
+        return Ok(branches_result);
     }
 
     smartlog(effects, git_run_info, &Default::default())?;
-    Ok(result)
+    let nothing_to_do = isize::from(BranchlessExitCode::NothingToDo);
+    if commits_result == nothing_to_do && branches_result == nothing_to_do {
+        Ok(nothing_to_do)
+    } else {
+        Ok(BranchlessExitCode::Success.into())
+    }
 }
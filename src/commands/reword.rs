@@ -0,0 +1,288 @@
+//! Update the commit message of any commit.
+//!
+//! This command rewrites the message of the provided commits, which don't
+//! have to be the current commit. Following the reword, the command performs
+//! a restack, so that the descendants of the reworded commits are moved on top
+//! of them.
+
+use std::convert::TryInto;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use eyre::Context;
+use tracing::instrument;
+
+use crate::commands::gc::mark_commit_reachable;
+use crate::commands::restack;
+use crate::commands::BranchlessExitCode;
+use crate::core::config::{get_commit_gpg_sign, get_restack_preserve_timestamps};
+use crate::core::dag::{resolve_commits, Dag, ResolveCommitsResult};
+use crate::core::effects::Effects;
+use crate::core::eventlog::{Event, EventLogDb, EventReplayer};
+use crate::core::formatting::{printable_styled_string, Pluralize};
+use crate::git::{GitRunInfo, Repo};
+use crate::opts::MoveOptions;
+
+/// The comment character used in the message template opened in the editor.
+const COMMENT_CHAR: char = '#';
+
+/// Open the user's editor to edit `message`, which is the message for the
+/// commit described by `description`. Returns the new message, with comments
+/// and excess whitespace removed.
+///
+/// `operation` is the name of the command being carried out, which is aborted
+/// if the new message is empty.
+#[instrument]
+pub fn edit_message(
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    message: &str,
+    description: &str,
+    operation: &str,
+) -> eyre::Result<String> {
+    let editor = git_run_info
+        .run_silent(repo, None, &["var", "GIT_EDITOR"], Default::default())
+        .wrap_err("Determining Git editor")?
+        .stdout;
+    let editor = String::from_utf8(editor).wrap_err("Decoding stdout from Git subprocess")?;
+    let editor = editor.trim();
+
+    let template = format!(
+        "\
+{message}
+{comment} Please enter the commit message for {description}. Lines starting
+{comment} with '{comment}' will be ignored, and an empty message aborts the {operation}.
+",
+        message = message.trim_end(),
+        comment = COMMENT_CHAR,
+        description = description,
+        operation = operation,
+    );
+
+    let tempfile_dir = repo.get_tempfile_dir();
+    std::fs::create_dir_all(&tempfile_dir)
+        .wrap_err_with(|| format!("Creating tempfile dir: {:?}", &tempfile_dir))?;
+    let message_path = tempfile_dir.join("BRANCHLESS_EDITMSG");
+    std::fs::write(&message_path, template)
+        .wrap_err_with(|| format!("Writing commit message to: {:?}", &message_path))?;
+
+    // Invoke the editor the same way that Git does, so that editors with
+    // arguments (such as `code --wait`) work as expected.
+    let status = git_run_info
+        .make_shell_command(&format!("{} \"$@\"", editor))?
+        .arg(editor)
+        .arg(&message_path)
+        .status()
+        .wrap_err_with(|| format!("Running editor: {}", editor))?;
+    if !status.success() {
+        eyre::bail!("There was a problem with the editor '{}'.", editor);
+    }
+
+    let message = std::fs::read_to_string(&message_path)
+        .wrap_err_with(|| format!("Reading commit message from: {:?}", &message_path))?;
+    std::fs::remove_file(&message_path)
+        .wrap_err_with(|| format!("Removing commit message file: {:?}", &message_path))?;
+    let message = git2::message_prettify(message, Some(COMMENT_CHAR as u8))
+        .wrap_err("Cleaning up commit message")?;
+    Ok(message)
+}
+
+/// Reword the provided commits, or the current commit if none are provided.
+/// Unless `sign_commits` is `false`, the reworded commits are signed if
+/// `commit.gpgSign` is set. Public commits are only reworded if `force` is
+/// set.
+#[instrument]
+pub fn reword(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    commits: Vec<String>,
+    messages: Vec<String>,
+    sign_commits: bool,
+    force: bool,
+) -> eyre::Result<isize> {
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
+    let head_oid = repo.get_head_info()?.oid;
+
+    let commits = if commits.is_empty() {
+        match head_oid {
+            Some(head_oid) => vec![head_oid.to_string()],
+            None => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "No commit is currently checked out. Check out a commit to reword, or provide the commits to reword, and then try again.",
+                )?;
+                return Ok(BranchlessExitCode::UserError.into());
+            }
+        }
+    } else {
+        commits
+    };
+
+    let references_snapshot = repo.get_references_snapshot()?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+    let commits = match resolve_commits(effects, &repo, &mut dag, commits)? {
+        ResolveCommitsResult::Ok { commits } => commits,
+        ResolveCommitsResult::CommitNotFound { commit } => {
+            writeln!(effects.get_output_stream(), "Commit not found: {}", commit)?;
+            return Ok(BranchlessExitCode::UserError.into());
+        }
+    };
+
+    if !force {
+        let public_commits = dag.query_public_commits()?;
+        let mut found_public_commit = false;
+        for commit in commits.iter() {
+            if public_commits.contains(&commit.get_oid().into())? {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Refusing to reword public commit: {}",
+                    printable_styled_string(
+                        effects.get_glyphs(),
+                        commit.friendly_describe(effects.get_glyphs())?
+                    )?,
+                )?;
+                found_public_commit = true;
+            }
+        }
+        if found_public_commit {
+            writeln!(
+                effects.get_output_stream(),
+                "To reword public commits anyway, run again with --force."
+            )?;
+            return Ok(BranchlessExitCode::UserError.into());
+        }
+    }
+
+    let message = if messages.is_empty() {
+        None
+    } else {
+        let message = git2::message_prettify(messages.join("\n\n"), None)
+            .wrap_err("Cleaning up commit message")?;
+        Some(message)
+    };
+
+    // Collect all of the new messages before rewriting anything, so that
+    // aborting the reword of any commit leaves the repository untouched.
+    let mut new_messages = Vec::new();
+    for commit in commits.iter() {
+        let message = match &message {
+            Some(message) => message.clone(),
+            None => edit_message(
+                git_run_info,
+                &repo,
+                &commit.get_message_raw()?.to_string_lossy(),
+                &commit.get_oid().to_string(),
+                "reword",
+            )?,
+        };
+        if message.trim().is_empty() {
+            writeln!(
+                effects.get_output_stream(),
+                "Aborting reword of {} due to empty commit message.",
+                printable_styled_string(
+                    effects.get_glyphs(),
+                    commit.friendly_describe(effects.get_glyphs())?
+                )?,
+            )?;
+            return Ok(BranchlessExitCode::UserError.into());
+        }
+        new_messages.push(message);
+    }
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "reword")?;
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let preserve_timestamps = get_restack_preserve_timestamps(&repo)?;
+    let default_committer = if preserve_timestamps {
+        None
+    } else {
+        repo.get_default_signature(git_run_info, now)?
+    };
+    let should_sign = sign_commits && get_commit_gpg_sign(&repo)?;
+    let mut events = Vec::new();
+    for (commit, message) in commits.iter().zip(new_messages) {
+        let committer = if preserve_timestamps {
+            commit.get_committer()
+        } else {
+            match &default_committer {
+                Some(committer) => committer.to_owned(),
+                None => commit.get_committer().update_timestamp(now)?,
+            }
+        };
+
+        let update_ref = if Some(commit.get_oid()) == head_oid {
+            Some("HEAD")
+        } else {
+            None
+        };
+        let reworded_commit_oid = if should_sign {
+            repo.create_signed_commit(
+                git_run_info,
+                update_ref,
+                &commit.get_author(),
+                &committer,
+                &message,
+                &commit.get_tree()?,
+                commit.get_parents().iter().collect(),
+            )?
+        } else {
+            commit.amend_commit(update_ref, None, Some(&committer), Some(&message), None)?
+        };
+        mark_commit_reachable(&repo, reworded_commit_oid)
+            .wrap_err("Marking commit as reachable for GC purposes.")?;
+        events.push(Event::RewriteEvent {
+            timestamp,
+            event_tx_id,
+            old_commit_oid: commit.get_oid().into(),
+            new_commit_oid: reworded_commit_oid.into(),
+        });
+    }
+    event_log_db.add_events(events)?;
+
+    // Rewording a commit doesn't change its tree, so restacking its
+    // descendants can't cause merge conflicts.
+    let move_options = MoveOptions {
+        force_in_memory: false,
+        force_on_disk: false,
+        detect_duplicate_commits_via_patch_id: true,
+        resolve_merge_conflicts: false,
+        sign_commits,
+        dump_rebase_constraints: false,
+        dump_rebase_plan: false,
+    };
+
+    let restack_exit_code = restack::restack(
+        effects,
+        git_run_info,
+        commits
+            .iter()
+            .map(|commit| commit.get_oid().to_string())
+            .collect(),
+        &move_options,
+        false,
+    )?;
+    if !BranchlessExitCode::is_ok(restack_exit_code) {
+        return Ok(restack_exit_code);
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Reworded {}.",
+        Pluralize {
+            determiner: None,
+            amount: commits.len().try_into()?,
+            unit: ("commit", "commits"),
+        },
+    )?;
+    Ok(0)
+}
@@ -2,40 +2,87 @@
 //!
 //! The set of commits that are still being worked on is inferred from the event
 //! log; see the `eventlog` module.
-
+//!
+//! All worktrees of a repository share the same event log, so the smartlog
+//! shows the same commits no matter which worktree it's run from. Only `HEAD`
+//! is specific to each worktree: the current commit marker and the current
+//! branch are those of the worktree the smartlog is run from, and checkouts in
+//! other worktrees don't affect them.
+
+use std::collections::HashSet;
+use std::convert::{TryFrom, TryInto};
+use std::ffi::OsStr;
 use std::fmt::Write;
+use std::io::Write as IoWrite;
+use std::process::{Command, Stdio};
 use std::time::SystemTime;
 
+use chrono::{Local, NaiveDate, TimeZone};
+use clap::ArgEnum;
+use eyre::Context;
+use rayon::ThreadPoolBuilder;
+use regex::Regex;
 use tracing::instrument;
 
-use crate::core::dag::Dag;
+use crate::commands::BranchlessExitCode;
+use crate::core::config::{
+    get_commit_descriptors_author, get_smartlog_abbrev, get_smartlog_glyphs,
+    get_smartlog_max_commits, get_smartlog_max_refs, get_smartlog_max_subject_width,
+    get_smartlog_order, get_smartlog_pager, get_smartlog_show_age, get_smartlog_show_legend,
+    get_smartlog_show_remote_status, get_smartlog_subject_trailer, get_smartlog_theme,
+    resolve_main_branch_name, MainBranchFallback, MAIN_BRANCH_NOT_FOUND_HINT_CONFIG_KEY,
+};
+use crate::core::dag::{commit_set_to_vec, resolve_commits, CommitSet, Dag, ResolveCommitsResult};
 use crate::core::effects::Effects;
-use crate::core::eventlog::{EventLogDb, EventReplayer};
-use crate::core::formatting::printable_styled_string;
+use crate::core::eventlog::{
+    read_event_log_best_effort, replay_events, restore_events, EventCursor, EventLogCorruption,
+    EventLogDb, EventReplayer,
+};
+use crate::core::formatting::{printable_styled_string, ColumnAlignment, Pluralize, TextOverflow};
 use crate::core::node_descriptors::{
-    BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
-    DifferentialRevisionDescriptor, ObsolescenceExplanationDescriptor, Redactor,
-    RelativeTimeDescriptor,
+    BranchesDescriptor, CommitAgeDescriptor, CommitAuthorDescriptor, CommitDiffStatDescriptor,
+    CommitIndexDescriptor, CommitMessageDescriptor, CommitOidDescriptor, CommitSignatureDescriptor,
+    DiffStatColumn, DifferentialRevisionDescriptor, NodeDescriptor,
+    ObsolescenceExplanationDescriptor, Redactor, RelativeTimeDescriptor, TagsDescriptor,
 };
-use crate::git::{GitRunInfo, Repo};
+use crate::core::query::parse_duration;
+use crate::core::rewrite::RepoResource;
+use crate::core::smartlog_cache::{make_smartlog_cache_key, SmartlogCacheDb, SmartlogCacheOptions};
+use crate::core::smartlog_index::SmartlogIndexDb;
+use crate::git::{
+    Config, ConfigRead, ConfigWrite, GitRunInfo, NonZeroOid, Repo, RepoReferencesSnapshot,
+};
+use crate::opts::{SmartlogFormat, SmartlogGlyphs, SmartlogOrder, SmartlogStat};
 
-pub use graph::{make_smartlog_graph, SmartlogGraph};
-pub use render::{render_graph, SmartlogOptions};
+pub use dot::render_graph_dot;
+pub use graph::{
+    make_smartlog_graph, Smartlog, SmartlogBoundary, SmartlogEdge, SmartlogGraph, SmartlogNode,
+};
+pub use json::{render_graph_json, JsonNode};
+pub use render::{render_graph, render_graph_with_right_columns, SmartlogOptions, SubjectLayout};
 
 mod graph {
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet, VecDeque};
     use std::convert::TryFrom;
     use std::ops::Deref;
 
     use eden_dag::DagAlgorithm;
+    use regex::Regex;
     use tracing::instrument;
 
     use crate::core::dag::{commit_set_to_vec, CommitSet, Dag};
     use crate::core::effects::{Effects, OperationType};
     use crate::core::eventlog::{EventCursor, EventReplayer};
-    use crate::core::node_descriptors::NodeObject;
-    use crate::git::Commit;
-    use crate::git::{NonZeroOid, Repo};
+    use crate::core::node_descriptors::{CommitView, NodeObject};
+    use crate::core::rewrite::find_rewrite_target;
+    use crate::core::smartlog_cache::{CachedSmartlogGraph, CachedSmartlogNode};
+    use crate::git::{
+        CategorizedReferenceName, MaybeZeroOid, NonZeroOid, Repo, RepoReferencesSnapshot,
+    };
+
+    use crate::opts::SmartlogOrder;
+
+    use super::render::split_commit_graph_by_roots;
 
     /// Node contained in the smartlog commit graph.
     #[derive(Debug)]
@@ -52,6 +99,10 @@ mod graph {
         /// The OIDs of the children nodes in the smartlog commit graph.
         pub children: Vec<NonZeroOid>,
 
+        /// Indicates that some commits between this node and its parent node
+        /// have been omitted from the graph, such as by filtering.
+        pub has_omitted_ancestors: bool,
+
         /// Indicates that this is a commit to the main branch.
         ///
         /// These commits are considered to be immutable and should never leave the
@@ -73,6 +124,82 @@ mod graph {
         /// where you commit directly to the main branch and then later rewrite the
         /// commit.
         pub is_obsolete: bool,
+
+        /// Indicates that this commit was requested to be highlighted, such as
+        /// with `git smartlog --highlight`.
+        pub is_highlighted: bool,
+
+        /// The index of the stack of draft commits that this commit belongs
+        /// to, if stacks should be colored differently, such as with `git
+        /// smartlog --color-by-stack`.
+        pub stack_index: Option<usize>,
+    }
+
+    /// A node in a `Smartlog`.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct SmartlogNode {
+        /// The OID of the commit.
+        pub oid: NonZeroOid,
+
+        /// The first line of the commit message, or `None` if the commit has
+        /// been garbage collected.
+        pub subject: Option<String>,
+
+        /// The full names of the branches which point to this commit, sorted.
+        pub branches: Vec<String>,
+
+        /// Whether or not this commit is currently checked out.
+        pub is_head: bool,
+
+        /// Whether or not this commit belongs to the main branch.
+        pub is_main: bool,
+
+        /// Whether or not this commit has been hidden (i.e. is obsolete).
+        pub is_obsolete: bool,
+
+        /// If this commit was rewritten, the OID of the commit it was
+        /// rewritten into. This is `MaybeZeroOid::Zero` if the commit was
+        /// rewritten into nothing, such as if it was skipped during a rebase.
+        pub rewritten_as: Option<MaybeZeroOid>,
+    }
+
+    /// An edge in a `Smartlog`, which is rendered as a line between a node
+    /// and its parent node. If a parent of the commit doesn't appear in the
+    /// smartlog, then the edge connects the node to its nearest ancestor which
+    /// does appear in the smartlog instead.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct SmartlogEdge {
+        /// The OID of the parent node.
+        pub parent_oid: NonZeroOid,
+
+        /// The OID of the child node.
+        pub child_oid: NonZeroOid,
+
+        /// Whether some commits between the parent and child have been omitted
+        /// from the smartlog. Such edges are rendered with an ellipsis.
+        pub has_omitted_commits: bool,
+    }
+
+    /// A structured representation of the smartlog, which doesn't borrow from
+    /// the repository. See `compute_smartlog`.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Smartlog {
+        /// The nodes of the smartlog in topological order, with parents
+        /// appearing before their children. This is the order in which they
+        /// would be rendered in the textual smartlog (from top to bottom).
+        pub nodes: Vec<SmartlogNode>,
+
+        /// The edges between the nodes of the smartlog, in the same order as
+        /// their child nodes.
+        pub edges: Vec<SmartlogEdge>,
+
+        /// The number of commits which were omitted from the smartlog due to
+        /// `SmartlogOptions::max_commits`.
+        pub num_omitted_commits: usize,
+
+        /// The number of stacks of commits which were omitted from the
+        /// smartlog due to `branchless.smartlog.maxRefs`.
+        pub num_omitted_heads: usize,
     }
 
     /// Graph of commits that the user is working on.
@@ -83,7 +210,7 @@ mod graph {
     impl<'repo> SmartlogGraph<'repo> {
         /// Get a list of commits stored in the graph.
         /// Returns commits in descending commit time order.
-        pub fn get_commits(&self) -> Vec<Commit<'repo>> {
+        pub fn get_commits(&self) -> Vec<CommitView<'repo>> {
             let mut commits = self
                 .nodes
                 .values()
@@ -91,11 +218,544 @@ mod graph {
                     NodeObject::Commit { commit } => Some(commit.clone()),
                     NodeObject::GarbageCollected { oid: _ } => None,
                 })
-                .collect::<Vec<Commit<'repo>>>();
+                .collect::<Vec<CommitView<'repo>>>();
             commits.sort_by_key(|commit| (commit.get_committer().get_time(), commit.get_oid()));
             commits.reverse();
             commits
         }
+
+        /// Mark the given commits as highlighted. Commits which aren't in the
+        /// graph are ignored.
+        pub fn set_highlighted(&mut self, oids: &HashSet<NonZeroOid>) {
+            for (oid, node) in self.nodes.iter_mut() {
+                node.is_highlighted = oids.contains(oid);
+            }
+        }
+
+        /// Assign an index to each stack of draft commits in the graph, in
+        /// the order in which the stacks are rendered. A stack consists of a
+        /// draft commit whose parent is on the main branch (or isn't in the
+        /// graph), along with all of its descendants.
+        pub fn set_stack_indexes(&mut self, effects: &Effects, repo: &Repo, dag: &Dag) {
+            let root_oids = split_commit_graph_by_roots(effects, repo, dag, self);
+
+            let mut num_stacks = 0;
+            let mut stack: Vec<(NonZeroOid, Option<usize>)> = root_oids
+                .iter()
+                .rev()
+                .map(|root_oid| (*root_oid, None))
+                .collect();
+            while let Some((oid, parent_stack_index)) = stack.pop() {
+                let node = match self.nodes.get_mut(&oid) {
+                    Some(node) => node,
+                    None => continue,
+                };
+                let stack_index = match (node.is_main, parent_stack_index) {
+                    (true, _) => None,
+                    (false, Some(stack_index)) => Some(stack_index),
+                    (false, None) => {
+                        num_stacks += 1;
+                        Some(num_stacks - 1)
+                    }
+                };
+                node.stack_index = stack_index;
+                stack.extend(
+                    node.children
+                        .iter()
+                        .rev()
+                        .filter(|child_oid| !root_oids.contains(child_oid))
+                        .map(|child_oid| (*child_oid, stack_index)),
+                );
+            }
+        }
+
+        /// Convert the graph into the form in which it's stored in the
+        /// smartlog cache.
+        pub fn to_cached(
+            &self,
+            num_omitted_commits: usize,
+            num_omitted_heads: usize,
+        ) -> CachedSmartlogGraph {
+            let nodes = self
+                .nodes
+                .iter()
+                .map(|(oid, node)| CachedSmartlogNode {
+                    oid: oid.to_string(),
+                    parent: node.parent.map(|parent_oid| parent_oid.to_string()),
+                    children: node
+                        .children
+                        .iter()
+                        .map(|child_oid| child_oid.to_string())
+                        .collect(),
+                    has_omitted_ancestors: node.has_omitted_ancestors,
+                    is_main: node.is_main,
+                    is_obsolete: node.is_obsolete,
+                })
+                .collect();
+            CachedSmartlogGraph {
+                nodes,
+                num_omitted_commits,
+                num_omitted_heads,
+            }
+        }
+
+        /// Reconstruct a graph from the form in which it's stored in the
+        /// smartlog cache. Returns the graph, the number of commits which were
+        /// omitted from it, and the number of stacks which were omitted from
+        /// it.
+        pub fn from_cached(
+            repo: &'repo Repo,
+            cached: CachedSmartlogGraph,
+        ) -> eyre::Result<(Self, usize, usize)> {
+            let CachedSmartlogGraph {
+                nodes: cached_nodes,
+                num_omitted_commits,
+                num_omitted_heads,
+            } = cached;
+            let mut nodes = HashMap::new();
+            for cached_node in cached_nodes {
+                let CachedSmartlogNode {
+                    oid,
+                    parent,
+                    children,
+                    has_omitted_ancestors,
+                    is_main,
+                    is_obsolete,
+                } = cached_node;
+                let oid: NonZeroOid = oid.parse()?;
+                let object = match repo.find_commit(oid)? {
+                    Some(commit) => NodeObject::Commit {
+                        commit: CommitView::new(commit),
+                    },
+                    None => NodeObject::GarbageCollected { oid },
+                };
+                nodes.insert(
+                    oid,
+                    Node {
+                        object,
+                        parent: parent.map(|parent_oid| parent_oid.parse()).transpose()?,
+                        children: children
+                            .iter()
+                            .map(|child_oid| child_oid.parse())
+                            .collect::<eyre::Result<_>>()?,
+                        has_omitted_ancestors,
+                        is_main,
+                        is_obsolete,
+                        is_highlighted: false,
+                        stack_index: None,
+                    },
+                );
+            }
+            Ok((
+                SmartlogGraph { nodes },
+                num_omitted_commits,
+                num_omitted_heads,
+            ))
+        }
+
+        /// Remove all but the `max_commits` nodes closest to `head_oid` from the
+        /// graph, and return the number of nodes which were removed.
+        ///
+        /// Distance is measured in the number of parent/child links in the
+        /// graph. Nodes which aren't connected to `head_oid` are kept in order
+        /// of recency. If a node's parent is removed, then the node becomes a
+        /// root of the graph, which causes it to be rendered with an ellipsis.
+        ///
+        /// The nodes in `pinned_oids` are always kept, and don't count towards
+        /// `max_commits`.
+        pub fn truncate(
+            &mut self,
+            head_oid: Option<NonZeroOid>,
+            max_commits: usize,
+            pinned_oids: &HashSet<NonZeroOid>,
+        ) -> usize {
+            let pinned_oids: HashSet<NonZeroOid> = pinned_oids
+                .iter()
+                .filter(|oid| self.nodes.contains_key(oid))
+                .copied()
+                .collect();
+            let max_commits = max_commits + pinned_oids.len();
+            if self.nodes.len() <= max_commits {
+                return 0;
+            }
+
+            let seed_oids: Vec<NonZeroOid> = {
+                let mut garbage_collected_oids: Vec<NonZeroOid> = self
+                    .nodes
+                    .values()
+                    .filter_map(|node| match &node.object {
+                        NodeObject::Commit { commit: _ } => None,
+                        NodeObject::GarbageCollected { oid } => Some(*oid),
+                    })
+                    .collect();
+                garbage_collected_oids.sort_unstable();
+                head_oid
+                    .into_iter()
+                    .filter(|head_oid| self.nodes.contains_key(head_oid))
+                    .chain(self.get_commits().iter().map(|commit| commit.get_oid()))
+                    .chain(garbage_collected_oids)
+                    .collect()
+            };
+
+            let mut kept_oids = pinned_oids;
+            for seed_oid in seed_oids {
+                if kept_oids.len() >= max_commits {
+                    break;
+                }
+                if kept_oids.contains(&seed_oid) {
+                    continue;
+                }
+
+                let mut queue = VecDeque::from(vec![seed_oid]);
+                kept_oids.insert(seed_oid);
+                while let Some(oid) = queue.pop_front() {
+                    let node = &self.nodes[&oid];
+                    for neighbor_oid in node.parent.iter().chain(node.children.iter()) {
+                        if kept_oids.len() >= max_commits {
+                            break;
+                        }
+                        if kept_oids.insert(*neighbor_oid) {
+                            queue.push_back(*neighbor_oid);
+                        }
+                    }
+                }
+            }
+
+            self.retain_nodes(&kept_oids)
+        }
+
+        /// Remove all draft commits whose author's name or email (as resolved
+        /// by the repository's mailmap) doesn't match `author_re`. Main branch
+        /// commits are always kept, so that the remaining commits are still
+        /// anchored to the main branch.
+        pub fn retain_authors(&mut self, repo: &Repo, author_re: &Regex) -> eyre::Result<()> {
+            let mailmap = repo.get_mailmap()?;
+            let mut kept_oids = HashSet::new();
+            for (oid, node) in self.nodes.iter() {
+                let is_match = if node.is_main {
+                    true
+                } else {
+                    match &node.object {
+                        NodeObject::Commit { commit } => {
+                            let author = mailmap.resolve_signature(&commit.author())?;
+                            author
+                                .get_name()
+                                .into_iter()
+                                .chain(author.get_email())
+                                .any(|text| author_re.is_match(text))
+                        }
+                        NodeObject::GarbageCollected { oid: _ } => false,
+                    }
+                };
+                if is_match {
+                    kept_oids.insert(*oid);
+                }
+            }
+            self.retain_nodes(&kept_oids);
+            Ok(())
+        }
+
+        /// Remove all draft commits which are reachable from one of
+        /// `remote_heads`, i.e. which have already been pushed. Main branch
+        /// commits are always kept, so that the remaining commits are still
+        /// anchored to the main branch. The `remote_heads` must already have
+        /// been synced into `dag`.
+        pub fn retain_unpushed(&mut self, dag: &Dag, remote_heads: &CommitSet) -> eyre::Result<()> {
+            let pushed_commits = dag.query().ancestors(remote_heads.clone())?;
+            let draft_commits: CommitSet = self
+                .nodes
+                .iter()
+                .filter(|(_oid, node)| {
+                    !node.is_main && matches!(node.object, NodeObject::Commit { .. })
+                })
+                .map(|(oid, _node)| *oid)
+                .collect();
+            let unpushed_commits = draft_commits.difference(&pushed_commits);
+
+            let mut kept_oids: HashSet<NonZeroOid> =
+                commit_set_to_vec(&unpushed_commits)?.into_iter().collect();
+            kept_oids.extend(
+                self.nodes
+                    .iter()
+                    .filter(|(_oid, node)| node.is_main)
+                    .map(|(oid, _node)| *oid),
+            );
+            self.retain_nodes(&kept_oids);
+            Ok(())
+        }
+
+        /// Remove the main branch commits which come before `boundary`, so
+        /// that they're collapsed into the ellipsis at the bottom of the
+        /// graph. Draft commits are kept, as is the commit at `head_oid`.
+        /// Stacks rooted at a removed main branch commit are rendered as
+        /// separate roots instead.
+        pub fn retain_main_branch_since(
+            &mut self,
+            dag: &Dag,
+            head_oid: Option<NonZeroOid>,
+            boundary: &SmartlogBoundary,
+        ) -> eyre::Result<()> {
+            let omitted_commits = match boundary {
+                SmartlogBoundary::Commit(boundary_oid) => dag
+                    .query()
+                    .ancestors(CommitSet::from(*boundary_oid))?
+                    .difference(&CommitSet::from(*boundary_oid)),
+                SmartlogBoundary::Time(_) => CommitSet::empty(),
+            };
+
+            let mut kept_oids = HashSet::new();
+            for (oid, node) in self.nodes.iter() {
+                let is_omitted = if !node.is_main || Some(*oid) == head_oid {
+                    false
+                } else {
+                    match (boundary, &node.object) {
+                        (SmartlogBoundary::Commit(_), _) => {
+                            omitted_commits.contains(&(*oid).into())?
+                        }
+                        (SmartlogBoundary::Time(time), NodeObject::Commit { commit }) => {
+                            commit.get_time().seconds() < *time
+                        }
+                        (SmartlogBoundary::Time(_), NodeObject::GarbageCollected { oid: _ }) => {
+                            false
+                        }
+                    }
+                };
+                if !is_omitted {
+                    kept_oids.insert(*oid);
+                }
+            }
+            self.retain_nodes(&kept_oids);
+            Ok(())
+        }
+
+        /// Reorder the children of each node according to `order`, or by
+        /// commit date, oldest first, if no order is provided. The graph is
+        /// otherwise kept in the default order, so this should be called
+        /// after any other modifications.
+        pub fn sort_siblings(&mut self, order: Option<SmartlogOrder>) {
+            match order {
+                None => sort_children(self),
+                Some(SmartlogOrder::Date) => {
+                    let commit_times = get_commit_times(self);
+                    for node in self.nodes.values_mut() {
+                        node.children.sort_by_key(|child_oid| {
+                            (
+                                std::cmp::Reverse(commit_times[child_oid]),
+                                child_oid.to_string(),
+                            )
+                        });
+                    }
+                }
+                Some(SmartlogOrder::Topo) => {
+                    let topo_indexes = get_topo_indexes(self);
+                    for node in self.nodes.values_mut() {
+                        node.children
+                            .sort_by_key(|child_oid| topo_indexes[child_oid]);
+                    }
+                }
+            }
+        }
+
+        /// Move the node for `oid`, along with its descendants, so that it's
+        /// rendered as a child of the node for `new_parent_oid`. This can be
+        /// used to preview the result of moving commits before they're actually
+        /// moved. Does nothing if either node isn't in the graph.
+        pub fn reparent(&mut self, oid: NonZeroOid, new_parent_oid: NonZeroOid) {
+            if !self.nodes.contains_key(&oid) || !self.nodes.contains_key(&new_parent_oid) {
+                return;
+            }
+            if let Some(old_parent_oid) = self.nodes[&oid].parent {
+                if let Some(old_parent) = self.nodes.get_mut(&old_parent_oid) {
+                    old_parent.children.retain(|child_oid| *child_oid != oid);
+                }
+            }
+            if let Some(node) = self.nodes.get_mut(&oid) {
+                node.parent = Some(new_parent_oid);
+                node.has_omitted_ancestors = false;
+            }
+            if let Some(new_parent) = self.nodes.get_mut(&new_parent_oid) {
+                new_parent.children.push(oid);
+            }
+            sort_children(self);
+        }
+
+        /// Remove the obsolete draft nodes which no longer have any
+        /// descendants in the graph, as they wouldn't otherwise be shown.
+        pub fn remove_obsolete_leaves(&mut self) {
+            loop {
+                let kept_oids: HashSet<NonZeroOid> = self
+                    .nodes
+                    .iter()
+                    .filter(|(_oid, node)| {
+                        !(node.is_obsolete && !node.is_main && node.children.is_empty())
+                    })
+                    .map(|(oid, _node)| *oid)
+                    .collect();
+                if self.retain_nodes(&kept_oids) == 0 {
+                    break;
+                }
+            }
+        }
+
+        /// Convert this graph into a `Smartlog`, which can be inspected without
+        /// borrowing from the repository.
+        #[instrument(skip(self))]
+        pub fn to_smartlog(
+            &self,
+            effects: &Effects,
+            repo: &Repo,
+            dag: &Dag,
+            references_snapshot: &RepoReferencesSnapshot,
+            event_replayer: &EventReplayer,
+            event_cursor: EventCursor,
+        ) -> eyre::Result<Smartlog> {
+            let root_oids = split_commit_graph_by_roots(effects, repo, dag, self);
+
+            let mut ordered_oids = Vec::new();
+            let mut stack: Vec<NonZeroOid> = root_oids.iter().rev().copied().collect();
+            while let Some(oid) = stack.pop() {
+                ordered_oids.push(oid);
+                let node = &self.nodes[&oid];
+                stack.extend(
+                    node.children
+                        .iter()
+                        .rev()
+                        .filter(|child_oid| {
+                            self.nodes.contains_key(child_oid) && !root_oids.contains(child_oid)
+                        })
+                        .copied(),
+                );
+            }
+
+            let graph_oids: CommitSet = self.nodes.keys().copied().collect();
+            let mut nodes = Vec::new();
+            let mut edges = Vec::new();
+            for oid in ordered_oids {
+                let node = &self.nodes[&oid];
+
+                let subject = match &node.object {
+                    NodeObject::Commit { commit } => {
+                        Some(commit.subject()?.to_string_lossy().into_owned())
+                    }
+                    NodeObject::GarbageCollected { oid: _ } => None,
+                };
+
+                let mut branches: Vec<String> = match references_snapshot
+                    .branch_oid_to_names
+                    .get(&oid)
+                {
+                    Some(branch_names) => branch_names
+                        .iter()
+                        .map(|branch_name| CategorizedReferenceName::new(branch_name).render_full())
+                        .collect(),
+                    None => Vec::new(),
+                };
+                branches.sort_unstable();
+
+                // Connect the node to each of its nearest ancestors which
+                // appear in the smartlog, marking those which aren't actually
+                // its parents.
+                let parents = dag.query().parents(CommitSet::from(oid))?;
+                let parent_oids = commit_set_to_vec(&parents)?;
+                let nearest_ancestor_oids = commit_set_to_vec(
+                    &dag.query()
+                        .heads(dag.query().ancestors(parents)?.intersection(&graph_oids))?,
+                )?;
+                let (direct_parent_oids, omitted_parent_oids): (Vec<_>, Vec<_>) =
+                    nearest_ancestor_oids
+                        .into_iter()
+                        .partition(|ancestor_oid| parent_oids.contains(ancestor_oid));
+                let direct_parent_oids = parent_oids
+                    .iter()
+                    .filter(|parent_oid| direct_parent_oids.contains(parent_oid));
+                let mut omitted_parent_oids = omitted_parent_oids;
+                omitted_parent_oids.sort_unstable();
+                for parent_oid in direct_parent_oids {
+                    edges.push(SmartlogEdge {
+                        parent_oid: *parent_oid,
+                        child_oid: oid,
+                        has_omitted_commits: false,
+                    });
+                }
+                for parent_oid in omitted_parent_oids {
+                    edges.push(SmartlogEdge {
+                        parent_oid,
+                        child_oid: oid,
+                        has_omitted_commits: true,
+                    });
+                }
+
+                let rewritten_as = if node.is_obsolete {
+                    find_rewrite_target(event_replayer, event_cursor, oid)
+                } else {
+                    None
+                };
+
+                nodes.push(SmartlogNode {
+                    oid,
+                    subject,
+                    branches,
+                    is_head: references_snapshot.head_oid == Some(oid),
+                    is_main: node.is_main,
+                    is_obsolete: node.is_obsolete,
+                    rewritten_as,
+                });
+            }
+
+            Ok(Smartlog {
+                nodes,
+                edges,
+                num_omitted_commits: 0,
+                num_omitted_heads: 0,
+            })
+        }
+
+        /// Remove all nodes not in `kept_oids` from the graph, and return the
+        /// number of nodes which were removed. Nodes whose parent was removed
+        /// are attached to their nearest remaining ancestor, if any, so that
+        /// they're rendered with an ellipsis.
+        fn retain_nodes(&mut self, kept_oids: &HashSet<NonZeroOid>) -> usize {
+            let new_parents: HashMap<NonZeroOid, Option<NonZeroOid>> = self
+                .nodes
+                .iter()
+                .filter(|(oid, node)| {
+                    kept_oids.contains(oid)
+                        && matches!(node.parent, Some(parent_oid) if !kept_oids.contains(&parent_oid))
+                })
+                .map(|(oid, node)| {
+                    let mut ancestor_oid = node.parent;
+                    while let Some(oid) = ancestor_oid {
+                        if kept_oids.contains(&oid) {
+                            break;
+                        }
+                        ancestor_oid = self.nodes[&oid].parent;
+                    }
+                    (*oid, ancestor_oid)
+                })
+                .collect();
+
+            let num_nodes = self.nodes.len();
+            self.nodes.retain(|oid, _node| kept_oids.contains(oid));
+            for node in self.nodes.values_mut() {
+                node.children
+                    .retain(|child_oid| kept_oids.contains(child_oid));
+            }
+            for (oid, new_parent_oid) in new_parents {
+                let node = self.nodes.get_mut(&oid).unwrap();
+                node.parent = new_parent_oid;
+                node.has_omitted_ancestors = new_parent_oid.is_some();
+                if let Some(new_parent_oid) = new_parent_oid {
+                    self.nodes
+                        .get_mut(&new_parent_oid)
+                        .unwrap()
+                        .children
+                        .push(oid);
+                }
+            }
+            sort_children(self);
+
+            num_nodes - self.nodes.len()
+        }
     }
 
     impl std::fmt::Debug for SmartlogGraph<'_> {
@@ -144,7 +804,9 @@ mod graph {
                     let oid = NonZeroOid::try_from(vertex.clone())?;
 
                     let object = match repo.find_commit(oid)? {
-                        Some(commit) => NodeObject::Commit { commit },
+                        Some(commit) => NodeObject::Commit {
+                            commit: CommitView::new(commit),
+                        },
                         None => {
                             // Assume that this commit was garbage collected.
                             NodeObject::GarbageCollected { oid }
@@ -157,8 +819,11 @@ mod graph {
                             object,
                             parent: None,         // populated below
                             children: Vec::new(), // populated below
+                            has_omitted_ancestors: false,
                             is_main: public_commits.contains(&vertex)?,
                             is_obsolete: dag.obsolete_commits.contains(&vertex)?,
+                            is_highlighted: false,
+                            stack_index: None,
                         },
                     );
                 }
@@ -194,10 +859,20 @@ mod graph {
         Ok(SmartlogGraph { nodes: graph })
     }
 
-    /// Sort children nodes of the commit graph in a standard order, for determinism
-    /// in output.
-    fn sort_children(graph: &mut SmartlogGraph) {
-        let commit_times: HashMap<NonZeroOid, Option<git2::Time>> = graph
+    /// The point in the main branch's history below which commits aren't
+    /// shown, as requested with `git smartlog --since`.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum SmartlogBoundary {
+        /// Hide the main branch commits which are ancestors of this commit.
+        Commit(NonZeroOid),
+
+        /// Hide the main branch commits which were committed before this
+        /// time, in seconds since the Unix epoch.
+        Time(i64),
+    }
+
+    fn get_commit_times(graph: &SmartlogGraph) -> HashMap<NonZeroOid, Option<git2::Time>> {
+        graph
             .iter()
             .map(|(oid, node)| {
                 (
@@ -208,23 +883,177 @@ mod graph {
                     },
                 )
             })
-            .collect();
+            .collect()
+    }
+
+    /// Sort children nodes of the commit graph in a standard order, for determinism
+    /// in output.
+    fn sort_children(graph: &mut SmartlogGraph) {
+        let commit_times = get_commit_times(graph);
         for node in graph.nodes.values_mut() {
             node.children
                 .sort_by_key(|child_oid| (commit_times[child_oid], child_oid.to_string()));
         }
     }
 
-    /// Construct the smartlog graph for the repo.
+    /// Get the index of each node in a topological order of the graph, as
+    /// `git log --topo-order` would show it: starting from the heads, newest
+    /// first, each commit comes after all of its descendants, and each line of
+    /// history is followed to its end before another is started, so that lines
+    /// of history aren't intermixed.
+    fn get_topo_indexes(graph: &SmartlogGraph) -> HashMap<NonZeroOid, usize> {
+        let commit_times = get_commit_times(graph);
+        let mut num_unvisited_children: HashMap<NonZeroOid, usize> = graph
+            .iter()
+            .map(|(oid, node)| (*oid, node.children.len()))
+            .collect();
+
+        // The heads are visited in the opposite order that they're pushed.
+        let mut stack: Vec<NonZeroOid> = graph
+            .iter()
+            .filter(|(_oid, node)| node.children.is_empty())
+            .map(|(oid, _node)| *oid)
+            .collect();
+        stack.sort_by_key(|oid| (commit_times[oid], std::cmp::Reverse(oid.to_string())));
+
+        let mut topo_indexes = HashMap::new();
+        while let Some(oid) = stack.pop() {
+            topo_indexes.insert(oid, topo_indexes.len());
+            if let Some(parent_oid) = graph.nodes[&oid].parent {
+                if let Some(num_children) = num_unvisited_children.get_mut(&parent_oid) {
+                    *num_children -= 1;
+                    if *num_children == 0 {
+                        stack.push(parent_oid);
+                    }
+                }
+            }
+        }
+        topo_indexes
+    }
+
+    /// Find the heads of the stacks of commits to include in the smartlog
+    /// graph for the repo.
+    ///
+    /// If `focus_commits` is provided, then the graph is built around only
+    /// those commits (and their descendants), rather than all active heads.
+    ///
+    /// The `num_public_commits` most recent commits on the main branch are
+    /// also included, so that they're rendered individually rather than being
+    /// collapsed.
     #[instrument]
-    pub fn make_smartlog_graph<'repo>(
+    pub fn query_smartlog_heads(
+        dag: &Dag,
+        remove_commits: bool,
+        only_branches: bool,
+        focus_commits: Option<&CommitSet>,
+        num_public_commits: usize,
+    ) -> eyre::Result<CommitSet> {
+        let public_commits = dag.query_public_commits()?;
+
+        let observed_commits = if only_branches {
+            dag.branch_commits.clone()
+        } else if remove_commits {
+            dag.observed_commits.difference(&dag.obsolete_commits)
+        } else {
+            dag.observed_commits.clone()
+        };
+
+        let active_heads = match focus_commits {
+            Some(focus_commits) => {
+                dag.query_focused_heads(focus_commits, &public_commits, &observed_commits)?
+            }
+            None => dag.query_active_heads(&public_commits, &observed_commits)?,
+        };
+        let active_heads = if num_public_commits > 0 {
+            active_heads.union(&dag.query_recent_main_branch_commits(num_public_commits)?)
+        } else {
+            active_heads
+        };
+        Ok(active_heads)
+    }
+
+    /// Get the commits pointed to by remote-tracking branches (under
+    /// `refs/remotes/`).
+    #[instrument]
+    pub fn query_remote_heads(repo: &Repo) -> eyre::Result<CommitSet> {
+        let mut remote_oids = Vec::new();
+        for reference in repo.get_all_references()? {
+            let name = reference.get_name()?;
+            if let CategorizedReferenceName::RemoteBranch { .. } =
+                CategorizedReferenceName::new(&name)
+            {
+                if let Some(commit) = reference.peel_to_commit()? {
+                    remote_oids.push(commit.get_oid());
+                }
+            }
+        }
+        Ok(remote_oids.into_iter().collect())
+    }
+
+    /// Keep at most `max_heads` of the provided `active_heads`, so that
+    /// building the smartlog graph doesn't walk an unbounded number of
+    /// stacks. Heads which are related to `head_oid` or on the main branch are
+    /// always kept, even if that exceeds `max_heads`. Of the remaining heads,
+    /// the most recent ones are kept. Commits are only looked up for heads
+    /// which aren't already kept, and only if the limit is exceeded.
+    ///
+    /// Returns the heads to keep and the number of heads which were omitted.
+    #[instrument]
+    pub fn limit_smartlog_heads(
+        repo: &Repo,
+        dag: &Dag,
+        active_heads: &CommitSet,
+        head_oid: Option<NonZeroOid>,
+        max_heads: usize,
+    ) -> eyre::Result<(CommitSet, usize)> {
+        let num_heads = active_heads.count()?;
+        if num_heads <= max_heads {
+            return Ok((active_heads.clone(), 0));
+        }
+
+        let head_context = match head_oid {
+            Some(head_oid) => {
+                let head_commit = CommitSet::from(head_oid);
+                dag.query()
+                    .ancestors(head_commit.clone())?
+                    .union(&dag.query().descendants(head_commit)?)
+            }
+            None => CommitSet::empty(),
+        };
+        let pinned_heads =
+            active_heads.intersection(&head_context.union(&dag.query_public_commits()?));
+        let num_pinned_heads = pinned_heads.count()?;
+        if num_pinned_heads >= max_heads {
+            return Ok((pinned_heads, num_heads - num_pinned_heads));
+        }
+
+        let mut candidate_heads = Vec::new();
+        for head in commit_set_to_vec(&active_heads.difference(&pinned_heads))? {
+            let time = repo.find_commit(head)?.map(|commit| commit.get_time());
+            candidate_heads.push((time, head));
+        }
+        candidate_heads.sort_by_key(|candidate| std::cmp::Reverse(*candidate));
+        candidate_heads.truncate(max_heads - num_pinned_heads);
+
+        let num_kept_heads = num_pinned_heads + candidate_heads.len();
+        let kept_heads = candidate_heads
+            .into_iter()
+            .fold(pinned_heads, |acc, (_time, head)| {
+                acc.union(&CommitSet::from(head))
+            });
+        Ok((kept_heads, num_heads - num_kept_heads))
+    }
+
+    /// Construct the smartlog graph for the repo, by walking from the provided
+    /// heads (as found by `query_smartlog_heads`) to the main branch.
+    #[instrument]
+    pub fn make_smartlog_graph_from_heads<'repo>(
         effects: &Effects,
         repo: &'repo Repo,
         dag: &Dag,
         event_replayer: &EventReplayer,
         event_cursor: EventCursor,
-        remove_commits: bool,
-        only_branches: bool,
+        active_heads: &CommitSet,
     ) -> eyre::Result<SmartlogGraph<'repo>> {
         let (effects, _progress) = effects.start_operation(OperationType::MakeGraph);
 
@@ -232,17 +1061,6 @@ mod graph {
             let (effects, _progress) = effects.start_operation(OperationType::WalkCommits);
 
             let public_commits = dag.query_public_commits()?;
-
-            let observed_commits = if only_branches {
-                dag.branch_commits.clone()
-            } else if remove_commits {
-                dag.observed_commits.difference(&dag.obsolete_commits)
-            } else {
-                dag.observed_commits.clone()
-            };
-
-            let active_heads = dag.query_active_heads(&public_commits, &observed_commits)?;
-
             walk_from_active_heads(
                 &effects,
                 repo,
@@ -250,16 +1068,54 @@ mod graph {
                 event_replayer,
                 event_cursor,
                 &public_commits,
-                &active_heads,
+                active_heads,
             )?
         };
         sort_children(&mut graph);
         Ok(graph)
     }
+
+    /// Construct the smartlog graph for the repo.
+    ///
+    /// If `focus_commits` is provided, then the graph is built around only
+    /// those commits (and their descendants), rather than all active heads.
+    ///
+    /// The `num_public_commits` most recent commits on the main branch are
+    /// also included in the graph, so that they're rendered individually
+    /// rather than being collapsed.
+    #[instrument]
+    pub fn make_smartlog_graph<'repo>(
+        effects: &Effects,
+        repo: &'repo Repo,
+        dag: &Dag,
+        event_replayer: &EventReplayer,
+        event_cursor: EventCursor,
+        remove_commits: bool,
+        only_branches: bool,
+        focus_commits: Option<&CommitSet>,
+        num_public_commits: usize,
+    ) -> eyre::Result<SmartlogGraph<'repo>> {
+        let active_heads = query_smartlog_heads(
+            dag,
+            remove_commits,
+            only_branches,
+            focus_commits,
+            num_public_commits,
+        )?;
+        make_smartlog_graph_from_heads(
+            effects,
+            repo,
+            dag,
+            event_replayer,
+            event_cursor,
+            &active_heads,
+        )
+    }
 }
 
 mod render {
     use std::cmp::Ordering;
+    use std::collections::HashMap;
 
     use cursive::theme::Effect;
     use cursive::utils::markup::StyledString;
@@ -268,13 +1124,58 @@ mod render {
 
     use crate::core::dag::{CommitSet, CommitVertex, Dag};
     use crate::core::effects::Effects;
-    use crate::core::formatting::set_effect;
-    use crate::core::formatting::{Glyphs, StyledStringBuilder};
+    use crate::core::formatting::{
+        align_columns, fit_styled_string, ColumnAlignment, Glyphs, StyledStringBuilder,
+        TextOverflow,
+    };
+    use crate::core::formatting::{get_stack_style, set_effect, set_style};
     use crate::core::node_descriptors::{render_node_descriptors, NodeDescriptor};
     use crate::git::{NonZeroOid, Repo};
+    use crate::opts::{SmartlogFormat, SmartlogGlyphs, SmartlogOrder, SmartlogStat};
 
     use super::graph::SmartlogGraph;
 
+    /// A rendered line of the smartlog, along with the OID of the node that it
+    /// describes, if any. Lines which only contain graph edges have no OID.
+    type GraphLine = (Option<NonZeroOid>, StyledString);
+
+    /// How to fit the description of each commit into the space available for
+    /// it.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct SubjectLayout {
+        /// What to do with descriptions which don't fit.
+        pub overflow: TextOverflow,
+
+        /// The maximum width of each line, including the graph and any
+        /// right-hand columns, such as the width of the terminal.
+        pub max_line_width: Option<usize>,
+
+        /// The maximum width of each description, regardless of the width of
+        /// the line.
+        pub max_subject_width: Option<usize>,
+
+        /// Whether the lines will be displayed in reverse order. If so,
+        /// wrapped descriptions are emitted in reverse so that they still read
+        /// from top to bottom.
+        pub reverse: bool,
+    }
+
+    impl SubjectLayout {
+        /// Get the width available for a description on a line where
+        /// `used_width` columns are already taken up by other content.
+        fn get_subject_width(&self, used_width: usize) -> Option<usize> {
+            let available_width = self
+                .max_line_width
+                .map(|max_line_width| max_line_width.saturating_sub(used_width));
+            match (available_width, self.max_subject_width) {
+                (Some(available_width), Some(max_subject_width)) => {
+                    Some(available_width.min(max_subject_width))
+                }
+                (available_width, max_subject_width) => available_width.or(max_subject_width),
+            }
+        }
+    }
+
     /// Split fully-independent subgraphs into multiple graphs.
     ///
     /// This is intended to handle the situation of having multiple lines of work
@@ -282,7 +1183,7 @@ mod render {
     ///
     /// Returns the list such that the topologically-earlier subgraphs are first in
     /// the list (i.e. those that would be rendered at the bottom of the smartlog).
-    fn split_commit_graph_by_roots(
+    pub(super) fn split_commit_graph_by_roots(
         effects: &Effects,
         repo: &Repo,
         dag: &Dag,
@@ -338,7 +1239,10 @@ mod render {
         head_oid: Option<NonZeroOid>,
         current_oid: NonZeroOid,
         last_child_line_char: Option<&str>,
-    ) -> eyre::Result<Vec<StyledString>> {
+        parent_line_char: Option<&str>,
+        layout: &SubjectLayout,
+        used_width: usize,
+    ) -> eyre::Result<Vec<GraphLine>> {
         let current_node = &graph[&current_oid];
         let is_head = Some(current_oid) == head_oid;
 
@@ -353,50 +1257,117 @@ mod render {
             (true, true, false) => glyphs.commit_main_obsolete,
             (true, true, true) => glyphs.commit_main_obsolete_head,
         };
-
-        let first_line = {
-            let mut first_line = StyledString::new();
-            first_line.append_plain(cursor);
-            first_line.append_plain(" ");
-            first_line.append(text);
-            if is_head {
-                set_effect(first_line, Effect::Bold)
-            } else {
-                first_line
-            }
+        let cursor_style = match (current_node.is_main, current_node.is_obsolete, is_head) {
+            (_, _, true) => glyphs.theme.commit_head,
+            (false, false, false) => glyphs.theme.commit_visible,
+            (false, true, false) => glyphs.theme.commit_obsolete,
+            (true, false, false) => glyphs.theme.commit_main,
+            (true, true, false) => glyphs.theme.commit_main_obsolete,
         };
 
-        let mut lines = vec![first_line];
         let children: Vec<_> = current_node
             .children
             .iter()
             .filter(|child_oid| graph.contains_key(child_oid))
             .copied()
             .collect();
-        for (child_idx, child_oid) in children.iter().enumerate() {
-            if root_oids.contains(child_oid) {
-                // Will be rendered by the parent.
-                continue;
-            }
-
-            if child_idx == children.len() - 1 {
-                let line = match last_child_line_char {
-                    Some(_) => StyledString::plain(format!(
-                        "{}{}",
-                        glyphs.line_with_offshoot, glyphs.slash
-                    )),
 
-                    None => StyledString::plain(glyphs.line.to_string()),
-                };
-                lines.push(line)
+        let cursor_width = StyledString::plain(cursor).width() + 1;
+        let mut text_lines = fit_styled_string(
+            glyphs,
+            text,
+            layout.get_subject_width(used_width + cursor_width),
+            layout.overflow,
+        )
+        .into_iter();
+        let highlight = |line: StyledString| -> StyledString {
+            let line = if is_head {
+                set_effect(line, Effect::Bold)
             } else {
-                lines.push(StyledString::plain(format!(
-                    "{}{}",
-                    glyphs.line_with_offshoot, glyphs.slash
-                )))
+                line
+            };
+            let line = match current_node.stack_index {
+                Some(stack_index) => set_style(line, get_stack_style(stack_index)),
+                None => line,
+            };
+            if current_node.is_highlighted {
+                set_style(line, glyphs.theme.commit_highlighted)
+            } else {
+                line
             }
-
-            let child_output = get_child_output(
+        };
+        let first_line = {
+            let mut first_line = StyledString::new();
+            first_line.append_styled(cursor, cursor_style);
+            first_line.append_plain(" ");
+            first_line.append(text_lines.next().unwrap_or_default());
+            highlight(first_line)
+        };
+
+        // Continuation lines of a wrapped description are rendered beneath
+        // the commit, so keep drawing the edge towards the next commit in
+        // that direction.
+        let continuation_line_char = if layout.reverse {
+            parent_line_char
+        } else if children
+            .iter()
+            .any(|child_oid| !root_oids.contains(child_oid))
+        {
+            Some(glyphs.line)
+        } else {
+            last_child_line_char
+        };
+        let mut continuation_lines: Vec<GraphLine> = text_lines
+            .map(|text_line| {
+                let mut line = StyledString::plain(format!(
+                    "{}{}",
+                    continuation_line_char.unwrap_or(" "),
+                    " ".repeat(cursor_width - 1)
+                ));
+                line.append(text_line);
+                (None, highlight(line))
+            })
+            .collect();
+
+        let mut lines = if layout.reverse {
+            continuation_lines.reverse();
+            continuation_lines.push((Some(current_oid), first_line));
+            continuation_lines
+        } else {
+            let mut lines = vec![(Some(current_oid), first_line)];
+            lines.extend(continuation_lines);
+            lines
+        };
+        for (child_idx, child_oid) in children.iter().enumerate() {
+            if root_oids.contains(child_oid) {
+                // Will be rendered by the parent.
+                continue;
+            }
+
+            let has_omitted_ancestors = graph[child_oid].has_omitted_ancestors;
+            let mut child_output = Vec::new();
+            if child_idx == children.len() - 1 && last_child_line_char.is_none() {
+                let line = if has_omitted_ancestors {
+                    glyphs.vertical_ellipsis
+                } else {
+                    glyphs.line
+                };
+                lines.push((None, StyledString::plain(line.to_string())));
+            } else {
+                lines.push((
+                    None,
+                    StyledString::plain(format!("{}{}", glyphs.line_with_offshoot, glyphs.split)),
+                ));
+                if has_omitted_ancestors {
+                    child_output.push((
+                        None,
+                        StyledString::plain(glyphs.vertical_ellipsis.to_string()),
+                    ));
+                }
+            }
+
+            let is_indented = child_idx < children.len() - 1 || last_child_line_char.is_some();
+            child_output.extend(get_child_output(
                 glyphs,
                 graph,
                 root_oids,
@@ -404,8 +1375,19 @@ mod render {
                 head_oid,
                 *child_oid,
                 None,
-            )?;
-            for child_line in child_output {
+                Some(if has_omitted_ancestors {
+                    glyphs.vertical_ellipsis
+                } else {
+                    glyphs.line
+                }),
+                layout,
+                if is_indented {
+                    used_width + 2
+                } else {
+                    used_width
+                },
+            )?);
+            for (child_line_oid, child_line) in child_output {
                 let line = if child_idx == children.len() - 1 {
                     match last_child_line_char {
                         Some(last_child_line_char) => StyledStringBuilder::new()
@@ -420,7 +1402,7 @@ mod render {
                         .append(child_line)
                         .build()
                 };
-                lines.push(line)
+                lines.push((child_line_oid, line))
             }
         }
         Ok(lines)
@@ -435,7 +1417,9 @@ mod render {
         commit_descriptors: &mut [&mut dyn NodeDescriptor],
         head_oid: Option<NonZeroOid>,
         root_oids: &[NonZeroOid],
-    ) -> eyre::Result<Vec<StyledString>> {
+        layout: &SubjectLayout,
+        used_width: usize,
+    ) -> eyre::Result<Vec<GraphLine>> {
         let mut lines = Vec::new();
 
         // Determine if the provided OID has the provided parent OID as a parent.
@@ -449,30 +1433,52 @@ mod render {
             Ok(result)
         };
 
+        // Determine if the provided OID descends from the provided ancestor
+        // OID. Roots which don't descend from the previous root belong to an
+        // unrelated history, such as one started with `git checkout --orphan`,
+        // and so shouldn't be connected to it.
+        let is_descendant = |oid: NonZeroOid, ancestor_oid: NonZeroOid| -> eyre::Result<bool> {
+            let result = dag
+                .query()
+                .is_ancestor(CommitVertex::from(ancestor_oid), CommitVertex::from(oid))?;
+            Ok(result)
+        };
+
         for (root_idx, root_oid) in root_oids.iter().enumerate() {
-            if !dag
+            let is_connected_to_previous_root =
+                root_idx > 0 && is_descendant(*root_oid, root_oids[root_idx - 1])?;
+            if root_idx > 0 && !is_connected_to_previous_root {
+                // Multiple topologically-unrelated roots. Separate them with a
+                // newline.
+                lines.push((None, StyledString::new()));
+            }
+
+            let parent_line_char = if !dag
                 .query()
                 .parents(CommitSet::from(*root_oid))?
                 .is_empty()?
             {
-                let line = if root_idx > 0 && has_real_parent(*root_oid, root_oids[root_idx - 1])? {
-                    StyledString::plain(glyphs.line.to_owned())
+                let line = if is_connected_to_previous_root
+                    && has_real_parent(*root_oid, root_oids[root_idx - 1])?
+                {
+                    glyphs.line
                 } else {
-                    StyledString::plain(glyphs.vertical_ellipsis.to_owned())
+                    glyphs.vertical_ellipsis
                 };
-                lines.push(line);
-            } else if root_idx > 0 {
-                // Pathological case: multiple topologically-unrelated roots.
-                // Separate them with a newline.
-                lines.push(StyledString::new());
-            }
+                lines.push((None, StyledString::plain(line.to_owned())));
+                Some(line)
+            } else {
+                None
+            };
 
             let last_child_line_char = {
                 if root_idx == root_oids.len() - 1 {
                     None
                 } else {
                     let next_root_oid = root_oids[root_idx + 1];
-                    if has_real_parent(next_root_oid, *root_oid)? {
+                    if !is_descendant(next_root_oid, *root_oid)? {
+                        None
+                    } else if has_real_parent(next_root_oid, *root_oid)? {
                         Some(glyphs.line)
                     } else {
                         Some(glyphs.vertical_ellipsis)
@@ -488,6 +1494,9 @@ mod render {
                 head_oid,
                 *root_oid,
                 last_child_line_char,
+                parent_line_char,
+                layout,
+                used_width,
             )?;
             lines.extend(child_output.into_iter());
         }
@@ -504,6 +1513,7 @@ mod render {
         graph: &SmartlogGraph,
         head_oid: Option<NonZeroOid>,
         commit_descriptors: &mut [&mut dyn NodeDescriptor],
+        layout: &SubjectLayout,
     ) -> eyre::Result<Vec<StyledString>> {
         let root_oids = split_commit_graph_by_roots(effects, repo, dag, graph);
         let lines = get_output(
@@ -513,8 +1523,79 @@ mod render {
             commit_descriptors,
             head_oid,
             &root_oids,
+            layout,
+            0,
         )?;
-        Ok(lines)
+        Ok(lines.into_iter().map(|(_oid, line)| line).collect())
+    }
+
+    /// Render the smartlog graph, as with `render_graph`, but additionally
+    /// render the output of each of `right_column_descriptors` for each commit
+    /// in a separate column which is aligned across all lines.
+    #[instrument(skip(commit_descriptors, right_column_descriptors, graph))]
+    pub fn render_graph_with_right_columns(
+        effects: &Effects,
+        repo: &Repo,
+        dag: &Dag,
+        graph: &SmartlogGraph,
+        head_oid: Option<NonZeroOid>,
+        commit_descriptors: &mut [&mut dyn NodeDescriptor],
+        right_column_descriptors: &mut [(&mut dyn NodeDescriptor, ColumnAlignment)],
+        layout: &SubjectLayout,
+    ) -> eyre::Result<Vec<StyledString>> {
+        let glyphs = effects.get_glyphs();
+        let root_oids = split_commit_graph_by_roots(effects, repo, dag, graph);
+
+        // Render the right-hand columns first, so that their width can be
+        // reserved when fitting the commit descriptions.
+        let mut right_columns: HashMap<NonZeroOid, Vec<Option<StyledString>>> = HashMap::new();
+        let mut right_column_widths = vec![0; right_column_descriptors.len()];
+        for (oid, node) in graph.iter() {
+            let mut cells = Vec::new();
+            for ((right_column_descriptor, _alignment), column_width) in right_column_descriptors
+                .iter_mut()
+                .zip(right_column_widths.iter_mut())
+            {
+                let cell = right_column_descriptor.describe_node(glyphs, &node.object)?;
+                if let Some(cell) = &cell {
+                    *column_width = (*column_width).max(cell.width());
+                }
+                cells.push(cell);
+            }
+            right_columns.insert(*oid, cells);
+        }
+        let right_columns_width: usize = right_column_widths
+            .iter()
+            .map(|column_width| column_width + 1)
+            .sum();
+
+        let lines = get_output(
+            glyphs,
+            dag,
+            graph,
+            commit_descriptors,
+            head_oid,
+            &root_oids,
+            layout,
+            right_columns_width,
+        )?;
+
+        let mut rows = Vec::new();
+        for (oid, line) in lines {
+            let mut row = vec![Some(line)];
+            if let Some(oid) = oid {
+                row.extend(right_columns.remove(&oid).unwrap_or_default());
+            }
+            rows.push(row);
+        }
+        let alignments = std::iter::once(ColumnAlignment::Left)
+            .chain(
+                right_column_descriptors
+                    .iter()
+                    .map(|(_descriptor, alignment)| *alignment),
+            )
+            .collect::<Vec<_>>();
+        Ok(align_columns(rows, &alignments))
     }
 
     /// Options for rendering the smartlog.
@@ -526,76 +1607,1084 @@ mod render {
 
         /// Whether to only show commits on branches.
         pub only_show_branches: bool,
+
+        /// Whether to only show draft commits which aren't reachable from any
+        /// remote-tracking branch.
+        pub only_show_unpushed: bool,
+
+        /// Whether to omit branch names from the rendered graph, other than the
+        /// name of the checked-out branch.
+        pub hide_branches: bool,
+
+        /// Whether to render the graph with the newest commits at the top,
+        /// rather than at the bottom.
+        pub reverse: bool,
+
+        /// The order in which to render sibling commits. If not set, the value
+        /// of `branchless.smartlog.order` is used, or `date` otherwise.
+        pub order: Option<SmartlogOrder>,
+
+        /// The format in which to print the smartlog.
+        pub format: SmartlogFormat,
+
+        /// The glyphs to use when rendering the graph. If not set, the value
+        /// of `branchless.smartlog.glyphs` is used.
+        pub glyphs: Option<SmartlogGlyphs>,
+
+        /// The maximum number of commits to display. If not set, the value
+        /// of `branchless.smartlog.maxCommits` is used. A value of `0` means
+        /// that all commits are displayed.
+        pub max_commits: Option<usize>,
+
+        /// Whether to walk all stacks of commits, ignoring
+        /// `branchless.smartlog.maxRefs`.
+        pub full: bool,
+
+        /// The number of most recent commits on the main branch to show
+        /// individually, rather than collapsing them. These commits are
+        /// always shown, regardless of `max_commits`.
+        pub num_public_commits: usize,
+
+        /// If set, don't show main branch commits from before this boundary,
+        /// which is resolved as a commit, a duration before now (like `30d`),
+        /// or a date (like `2022-01-31`).
+        pub since: Option<String>,
+
+        /// Whether to show the age of each commit in a separate column. If
+        /// not set, the value of `branchless.smartlog.showAge` is used.
+        pub show_age: bool,
+
+        /// If set, show the number of lines changed by each commit in
+        /// separate columns.
+        pub stat: Option<SmartlogStat>,
+
+        /// Whether to show the author of each commit. If not set, the value
+        /// of `branchless.commitDescriptors.author` is used.
+        pub show_author: bool,
+
+        /// Whether to show an index for each commit, which can be passed to
+        /// `git branchless checkout` to check out that commit.
+        pub show_index: bool,
+
+        /// Whether to show the signature status of each commit.
+        pub show_signatures: bool,
+
+        /// Whether to print a key explaining the glyphs below the graph. If
+        /// not set, the value of `branchless.smartlog.showLegend` is used.
+        pub show_legend: bool,
+
+        /// Commits to highlight in the graph, such as hashes or branch names.
+        /// This doesn't affect which commits are shown.
+        pub highlight: Vec<String>,
+
+        /// Whether to render each stack of draft commits in a different color.
+        pub color_by_stack: bool,
+
+        /// If set, only show draft commits whose author name or email matches
+        /// this regular expression.
+        pub author: Option<String>,
+
+        /// If non-empty, only show these commits (and their descendants),
+        /// along with their relationship to the main branch.
+        pub commits: Vec<String>,
+
+        /// Whether to display the smartlog in a pager if stdout is a terminal
+        /// and the smartlog doesn't fit on the screen.
+        pub use_pager: bool,
+
+        /// What to do with commit descriptions which don't fit on one line.
+        /// If not set, they're truncated.
+        pub overflow: Option<TextOverflow>,
+
+        /// Whether to reuse the smartlog graph from the last invocation if
+        /// neither the event log nor any reference has changed since then.
+        /// Ignored if `commits` is non-empty.
+        pub use_cache: bool,
     }
 }
 
-/// Display a nice graph of commits you've recently worked on.
+mod json {
+    use serde::Serialize;
+
+    use super::graph::{Smartlog, SmartlogNode};
+
+    /// A node in the smartlog graph, as emitted by `git smartlog --format json`.
+    ///
+    /// The field names are part of the output format and must not be changed
+    /// without also updating any consumers of the output.
+    #[derive(Debug, Serialize)]
+    pub struct JsonNode {
+        /// The full hash of the commit.
+        pub oid: String,
+
+        /// The abbreviated hash of the commit, as rendered in the smartlog.
+        pub short_oid: String,
+
+        /// The first line of the commit message, or `None` if the commit has
+        /// been garbage collected.
+        pub subject: Option<String>,
+
+        /// The full names of the branches which point to this commit.
+        pub branches: Vec<String>,
+
+        /// Whether or not this commit is currently checked out.
+        pub is_head: bool,
+
+        /// Whether or not this commit belongs to the main branch.
+        pub is_main: bool,
+
+        /// Whether or not this commit has been hidden (i.e. is obsolete).
+        pub is_hidden: bool,
+
+        /// The OIDs of the parents of this commit which also appear in the
+        /// output. If a parent doesn't appear in the output, then its nearest
+        /// ancestor which does is listed instead, and is also listed in
+        /// `omitted_parents`.
+        pub parents: Vec<String>,
+
+        /// The entries of `parents` which aren't actual parents of this
+        /// commit, because the commits in between were omitted from the
+        /// output.
+        pub omitted_parents: Vec<String>,
+
+        /// If this commit was rewritten, the OID of the commit it was
+        /// rewritten into.
+        pub rewritten_as: Option<String>,
+    }
+
+    /// Convert the smartlog into a list of nodes suitable for serialization.
+    ///
+    /// The nodes are returned in the same order as in `Smartlog::nodes`.
+    pub fn render_graph_json(smartlog: &Smartlog, abbrev_len: usize) -> Vec<JsonNode> {
+        smartlog
+            .nodes
+            .iter()
+            .map(|node| {
+                let SmartlogNode {
+                    oid,
+                    subject,
+                    branches,
+                    is_head,
+                    is_main,
+                    is_obsolete,
+                    rewritten_as,
+                } = node;
+                let edges = smartlog
+                    .edges
+                    .iter()
+                    .filter(|edge| edge.child_oid == *oid)
+                    .collect::<Vec<_>>();
+                let oid = oid.to_string();
+                JsonNode {
+                    short_oid: oid[..abbrev_len.min(oid.len())].to_string(),
+                    oid,
+                    subject: subject.clone(),
+                    branches: branches.clone(),
+                    is_head: *is_head,
+                    is_main: *is_main,
+                    is_hidden: *is_obsolete,
+                    parents: edges
+                        .iter()
+                        .map(|edge| edge.parent_oid.to_string())
+                        .collect(),
+                    omitted_parents: edges
+                        .iter()
+                        .filter(|edge| edge.has_omitted_commits)
+                        .map(|edge| edge.parent_oid.to_string())
+                        .collect(),
+                    rewritten_as: rewritten_as.map(|rewritten_oid| rewritten_oid.to_string()),
+                }
+            })
+            .collect()
+    }
+}
+
+mod dot {
+    use std::fmt::Write;
+
+    use super::graph::{Smartlog, SmartlogEdge, SmartlogNode};
+
+    /// Quote the provided string for use as an ID in the DOT language.
+    fn quote(s: &str) -> String {
+        let mut result = String::from('"');
+        for c in s.chars() {
+            match c {
+                '"' => result.push_str("\\\""),
+                '\\' => result.push_str("\\\\"),
+                '\n' => result.push_str("\\n"),
+                c => result.push(c),
+            }
+        }
+        result.push('"');
+        result
+    }
+
+    /// Render the smartlog as a Graphviz DOT description of a directed graph,
+    /// as emitted by `git smartlog --format dot`.
+    ///
+    /// Each node is labeled with the abbreviated hash and subject of its
+    /// commit, and has an edge for each of the `Smartlog`'s edges. Main branch
+    /// commits are drawn as boxes, hidden commits are dashed, and the
+    /// checked-out commit is bold. Edges which skip over omitted commits are
+    /// dotted. Parents are placed above their children, unless `reverse` is
+    /// set.
+    pub fn render_graph_dot(
+        smartlog: &Smartlog,
+        reverse: bool,
+        abbrev_len: usize,
+    ) -> eyre::Result<String> {
+        let mut output = String::new();
+        writeln!(output, "digraph smartlog {{")?;
+        writeln!(output, "  rankdir={};", if reverse { "TB" } else { "BT" })?;
+        writeln!(output, "  node [fontname=\"monospace\"];")?;
+        for node in smartlog.nodes.iter() {
+            let SmartlogNode {
+                oid,
+                subject,
+                branches: _,
+                is_head,
+                is_main,
+                is_obsolete,
+                rewritten_as: _,
+            } = node;
+            let oid = oid.to_string();
+            let short_oid = &oid[..abbrev_len.min(oid.len())];
+            let label = match subject {
+                Some(subject) => format!("{} {}", short_oid, subject),
+                None => short_oid.to_string(),
+            };
+
+            let mut attributes = vec![
+                format!("label={}", quote(&label)),
+                format!("shape={}", if *is_main { "box" } else { "ellipse" }),
+            ];
+            let mut styles = Vec::new();
+            if *is_head {
+                styles.push("bold");
+            }
+            if *is_obsolete {
+                styles.push("dashed");
+                attributes.push("fontcolor=\"gray\"".to_string());
+            }
+            if !styles.is_empty() {
+                attributes.push(format!("style={}", quote(&styles.join(","))));
+            }
+            writeln!(output, "  {} [{}];", quote(&oid), attributes.join(", "))?;
+        }
+        for edge in smartlog.edges.iter() {
+            let SmartlogEdge {
+                parent_oid,
+                child_oid,
+                has_omitted_commits,
+            } = edge;
+            writeln!(
+                output,
+                "  {} -> {}{};",
+                quote(&child_oid.to_string()),
+                quote(&parent_oid.to_string()),
+                if *has_omitted_commits {
+                    " [style=\"dotted\"]"
+                } else {
+                    ""
+                }
+            )?;
+        }
+        writeln!(output, "}}")?;
+        Ok(output)
+    }
+}
+
+/// Resolve the argument to `git smartlog --since`. Durations and dates take
+/// precedence over commits, since a duration like `1d` is also a valid
+/// abbreviated commit hash. Returns `None` if `since` couldn't be resolved.
+fn resolve_smartlog_boundary(repo: &Repo, since: &str) -> eyre::Result<Option<SmartlogBoundary>> {
+    let now: i64 = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_secs()
+        .try_into()?;
+    if let Ok(duration) = parse_duration(since) {
+        let time = i64::try_from(duration.as_secs())
+            .ok()
+            .and_then(|duration| now.checked_sub(duration));
+        return Ok(time.map(SmartlogBoundary::Time));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        let time = Local
+            .from_local_datetime(&date.and_hms(0, 0, 0))
+            .earliest()
+            .ok_or_else(|| eyre::eyre!("Invalid date: {}", since))?;
+        return Ok(Some(SmartlogBoundary::Time(time.timestamp())));
+    }
+
+    if let Some(commit) = repo.revparse_single_commit(since)? {
+        return Ok(Some(SmartlogBoundary::Commit(commit.get_oid())));
+    }
+    Ok(None)
+}
+
+/// Build the smartlog graph according to the provided options, and return it
+/// along with the number of commits which were omitted from it due to
+/// `max_commits`, and the number of stacks which were omitted from it due to
+/// `max_refs`.
 #[instrument]
-pub fn smartlog(
+fn make_filtered_smartlog_graph<'repo>(
     effects: &Effects,
-    git_run_info: &GitRunInfo,
+    repo: &'repo Repo,
+    dag: &Dag,
+    event_replayer: &EventReplayer,
+    event_cursor: EventCursor,
+    head_oid: Option<NonZeroOid>,
+    focus_commits: Option<&CommitSet>,
     options: &SmartlogOptions,
-) -> eyre::Result<()> {
+    boundary: Option<&SmartlogBoundary>,
+    order: Option<SmartlogOrder>,
+    max_commits: Option<usize>,
+    max_refs: Option<usize>,
+) -> eyre::Result<(SmartlogGraph<'repo>, usize, usize)> {
     let SmartlogOptions {
         show_hidden_commits,
         only_show_branches,
+        only_show_unpushed,
+        author,
+        num_public_commits,
+        ..
     } = options;
 
-    let repo = Repo::from_dir(&git_run_info.working_directory)?;
-    let head_info = repo.get_head_info()?;
+    let active_heads = graph::query_smartlog_heads(
+        dag,
+        !show_hidden_commits,
+        *only_show_branches,
+        focus_commits,
+        *num_public_commits,
+    )?;
+    let (active_heads, num_omitted_heads) = match max_refs {
+        None | Some(0) => (active_heads, 0),
+        Some(max_refs) => {
+            graph::limit_smartlog_heads(repo, dag, &active_heads, head_oid, max_refs)?
+        }
+    };
+    let mut graph = graph::make_smartlog_graph_from_heads(
+        effects,
+        repo,
+        dag,
+        event_replayer,
+        event_cursor,
+        &active_heads,
+    )?;
+
+    if let Some(author) = author {
+        let author_re =
+            Regex::new(author).wrap_err_with(|| format!("Invalid author pattern: {:?}", author))?;
+        graph.retain_authors(repo, &author_re)?;
+    }
+    if *only_show_unpushed {
+        // Remote-tracking branches aren't otherwise synced into the DAG, so
+        // sync them into a separate handle before querying their ancestors.
+        let remote_heads = graph::query_remote_heads(repo)?;
+        let mut dag = dag.set_cursor(effects, repo, event_replayer, event_cursor)?;
+        dag.sync_from_oids(effects, repo, CommitSet::empty(), remote_heads.clone())?;
+        graph.retain_unpushed(&dag, &remote_heads)?;
+    }
+    if let Some(boundary) = boundary {
+        graph.retain_main_branch_since(dag, head_oid, boundary)?;
+    }
+
+    let num_omitted_commits = match max_commits {
+        None | Some(0) => 0,
+        Some(max_commits) => {
+            let public_commits = dag.query_recent_main_branch_commits(*num_public_commits)?;
+            let pinned_oids = commit_set_to_vec(&public_commits)?.into_iter().collect();
+            graph.truncate(head_oid, max_commits, &pinned_oids)
+        }
+    };
+    graph.sort_siblings(order);
+    Ok((graph, num_omitted_commits, num_omitted_heads))
+}
+
+/// A problem with the options passed to the smartlog, such as a commit which
+/// couldn't be found.
+#[derive(Clone, Debug)]
+enum SmartlogOptionsError {
+    CommitNotFound { commit: String },
+    InvalidSince { since: String },
+}
+
+impl std::fmt::Display for SmartlogOptionsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SmartlogOptionsError::CommitNotFound { commit } => {
+                write!(f, "Commit not found: {}", commit)
+            }
+            SmartlogOptionsError::InvalidSince { since } => write!(
+                f,
+                "Invalid value for --since: {} (expected a commit, a duration like `30d`, or a date like `2022-01-31`)",
+                since
+            ),
+        }
+    }
+}
+
+/// The smartlog graph, along with the state of the repository that it was
+/// computed from, which is needed to render it.
+struct LoadedSmartlog<'repo> {
+    conn: rusqlite::Connection,
+    corruption: Option<EventLogCorruption>,
+    references_snapshot: RepoReferencesSnapshot,
+    event_replayer: EventReplayer,
+    event_cursor: EventCursor,
+    dag: Dag,
+    graph: SmartlogGraph<'repo>,
+    num_omitted_commits: usize,
+    num_omitted_heads: usize,
+}
+
+impl LoadedSmartlog<'_> {
+    fn to_smartlog(&self, effects: &Effects, repo: &Repo) -> eyre::Result<Smartlog> {
+        let smartlog = self.graph.to_smartlog(
+            effects,
+            repo,
+            &self.dag,
+            &self.references_snapshot,
+            &self.event_replayer,
+            self.event_cursor,
+        )?;
+        Ok(Smartlog {
+            num_omitted_commits: self.num_omitted_commits,
+            num_omitted_heads: self.num_omitted_heads,
+            ..smartlog
+        })
+    }
+}
+
+/// Compute the smartlog graph according to the provided options. This is
+/// shared by `smartlog` and `compute_smartlog`, so that every output format
+/// shows the same commits.
+#[instrument]
+fn load_smartlog<'repo>(
+    effects: &Effects,
+    repo: &'repo Repo,
+    options: &SmartlogOptions,
+) -> eyre::Result<Result<LoadedSmartlog<'repo>, SmartlogOptionsError>> {
+    let SmartlogOptions {
+        show_hidden_commits,
+        only_show_branches,
+        only_show_unpushed,
+        order,
+        author,
+        max_commits,
+        full,
+        num_public_commits,
+        since,
+        commits,
+        use_cache,
+        ..
+    } = options;
+
+    let boundary = match since {
+        Some(since) => match resolve_smartlog_boundary(repo, since)? {
+            Some(boundary) => Some(boundary),
+            None => {
+                return Ok(Err(SmartlogOptionsError::InvalidSince {
+                    since: since.clone(),
+                }))
+            }
+        },
+        None => None,
+    };
+    let max_commits = match max_commits {
+        Some(max_commits) => Some(*max_commits),
+        None => get_smartlog_max_commits(repo)?,
+    };
+    let max_refs = if *full {
+        None
+    } else {
+        get_smartlog_max_refs(repo)?
+    };
+    let order = match order {
+        Some(order) => Some(*order),
+        None => match get_smartlog_order(repo)? {
+            Some(order) => Some(SmartlogOrder::from_str(&order, true).map_err(|err| {
+                eyre::eyre!("Invalid value for branchless.smartlog.order: {}", err)
+            })?),
+            None => None,
+        },
+    };
+
     let references_snapshot = repo.get_references_snapshot()?;
-    let conn = repo.get_db_conn()?;
+    let (conn, events, corruption) = read_event_log_best_effort(repo)?;
     let event_log_db = EventLogDb::new(&conn)?;
-    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let smartlog_cache_db = SmartlogCacheDb::new(&conn)?;
+
+    // Look up the cached graph before replaying the events and syncing the
+    // DAG. The cache key covers the event log and every reference, so on a
+    // hit, the DAG already contains every commit that could be shown.
+    let cache_key = if *use_cache && commits.is_empty() {
+        let cache_options = SmartlogCacheOptions {
+            show_hidden_commits: *show_hidden_commits,
+            only_show_branches: *only_show_branches,
+            only_show_unpushed: *only_show_unpushed,
+            order: order
+                .and_then(|order| order.to_possible_value())
+                .map(|value| value.get_name().to_string()),
+            author: author.clone(),
+            max_commits,
+            max_refs,
+            num_public_commits: *num_public_commits,
+            since_commit: match &boundary {
+                Some(SmartlogBoundary::Commit(oid)) => Some(oid.to_string()),
+                Some(SmartlogBoundary::Time(_)) | None => None,
+            },
+            since_time: match &boundary {
+                Some(SmartlogBoundary::Time(time)) => Some(*time),
+                Some(SmartlogBoundary::Commit(_)) | None => None,
+            },
+        };
+        Some(make_smartlog_cache_key(
+            repo,
+            &event_log_db,
+            &cache_options,
+        )?)
+    } else {
+        None
+    };
+    let cached = match &cache_key {
+        Some(cache_key) => smartlog_cache_db.get(cache_key)?,
+        None => None,
+    };
+
+    // On a hit, return without replaying any events. The persisted state of
+    // the event replayer was stored when the cached graph was computed, so it
+    // already covers every event.
+    if let Some(cached) = cached {
+        if let Some(event_replayer) = restore_events(repo, &event_log_db, &events)? {
+            let event_cursor = event_replayer.make_default_cursor();
+            let dag = Dag::open_without_syncing(
+                effects,
+                repo,
+                &event_replayer,
+                event_cursor,
+                &references_snapshot,
+            )?;
+            let (graph, num_omitted_commits, num_omitted_heads) =
+                SmartlogGraph::from_cached(repo, cached)?;
+            return Ok(Ok(LoadedSmartlog {
+                conn,
+                corruption,
+                references_snapshot,
+                event_replayer,
+                event_cursor,
+                dag,
+                graph,
+                num_omitted_commits,
+                num_omitted_heads,
+            }));
+        }
+    }
+
+    let event_replayer = replay_events(effects, repo, &event_log_db, events)?;
     let event_cursor = event_replayer.make_default_cursor();
-    let dag = Dag::open_and_sync(
+    let mut dag = Dag::open_and_sync(
         effects,
-        &repo,
+        repo,
         &event_replayer,
         event_cursor,
         &references_snapshot,
     )?;
+    let focus_commits = if commits.is_empty() {
+        None
+    } else {
+        match resolve_commits(effects, repo, &mut dag, commits.clone())? {
+            ResolveCommitsResult::Ok { commits } => Some(
+                commits
+                    .into_iter()
+                    .map(|commit| commit.get_oid())
+                    .collect::<CommitSet>(),
+            ),
+            ResolveCommitsResult::CommitNotFound { commit } => {
+                return Ok(Err(SmartlogOptionsError::CommitNotFound { commit }))
+            }
+        }
+    };
 
-    let graph = make_smartlog_graph(
+    let (graph, num_omitted_commits, num_omitted_heads) = make_filtered_smartlog_graph(
         effects,
-        &repo,
+        repo,
         &dag,
         &event_replayer,
         event_cursor,
-        !show_hidden_commits,
-        *only_show_branches,
+        references_snapshot.head_oid,
+        focus_commits.as_ref(),
+        options,
+        boundary.as_ref(),
+        order,
+        max_commits,
+        max_refs,
     )?;
+    if let Some(cache_key) = &cache_key {
+        smartlog_cache_db.set(
+            cache_key,
+            &graph.to_cached(num_omitted_commits, num_omitted_heads),
+        )?;
+    }
+    Ok(Ok(LoadedSmartlog {
+        conn,
+        corruption,
+        references_snapshot,
+        event_replayer,
+        event_cursor,
+        dag,
+        graph,
+        num_omitted_commits,
+        num_omitted_heads,
+    }))
+}
 
-    let lines = render_graph(
-        effects,
-        &repo,
-        &dag,
-        &graph,
-        references_snapshot.head_oid,
-        &mut [
-            &mut CommitOidDescriptor::new(true)?,
-            &mut RelativeTimeDescriptor::new(&repo, SystemTime::now())?,
-            &mut ObsolescenceExplanationDescriptor::new(
-                &event_replayer,
-                event_replayer.make_default_cursor(),
-            )?,
-            &mut BranchesDescriptor::new(
+/// Compute the smartlog for the repository, without rendering it. This is the
+/// same smartlog which `git smartlog --format json` renders.
+///
+/// The rendering-related fields of `options` (such as `glyphs` or
+/// `show_age`) are ignored.
+#[instrument]
+pub fn compute_smartlog(
+    effects: &Effects,
+    repo: &Repo,
+    options: &SmartlogOptions,
+) -> eyre::Result<Smartlog> {
+    match load_smartlog(effects, repo, options)? {
+        Ok(loaded) => loaded.to_smartlog(effects, repo),
+        Err(err) => eyre::bail!("{}", err),
+    }
+}
+
+/// Warn that the main branch couldn't be found. If there was no fallback
+/// branch to use instead, this is only a hint, so it's only shown once per
+/// repository.
+fn warn_main_branch_fallback(
+    effects: &Effects,
+    repo: &Repo,
+    main_branch_fallback: &MainBranchFallback,
+) -> eyre::Result<()> {
+    match main_branch_fallback {
+        MainBranchFallback::LocalBranch { .. } => {
+            writeln!(
+                effects.get_error_stream(),
+                "branchless: warning: {}",
+                main_branch_fallback
+            )?;
+        }
+        MainBranchFallback::NotFound { .. } => {
+            let show_hint: Option<bool> = repo
+                .get_readonly_config()?
+                .get(MAIN_BRANCH_NOT_FOUND_HINT_CONFIG_KEY)?;
+            if show_hint.unwrap_or(true) {
+                writeln!(
+                    effects.get_error_stream(),
+                    "branchless: hint: {}",
+                    main_branch_fallback
+                )?;
+                let config_path = repo.get_config_path();
+                if let Some(config_dir) = config_path.parent() {
+                    std::fs::create_dir_all(config_dir).wrap_err("Creating config path parent")?;
+                }
+                let mut config = Config::open(&config_path)?;
+                config.set(MAIN_BRANCH_NOT_FOUND_HINT_CONFIG_KEY, false)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Display a nice graph of commits you've recently worked on.
+#[instrument]
+pub fn smartlog(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    options: &SmartlogOptions,
+) -> eyre::Result<isize> {
+    let SmartlogOptions {
+        show_hidden_commits: _,
+        only_show_branches: _,
+        only_show_unpushed: _,
+        hide_branches,
+        reverse,
+        order: _,
+        format,
+        glyphs,
+        max_commits: _,
+        full: _,
+        num_public_commits: _,
+        since: _,
+        show_age,
+        stat,
+        show_author,
+        show_index,
+        show_signatures,
+        show_legend,
+        highlight,
+        color_by_stack,
+        author: _,
+        commits: _,
+        use_pager,
+        overflow,
+        use_cache: _,
+    } = options;
+
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    if let (_, Some(main_branch_fallback)) = resolve_main_branch_name(&repo)? {
+        warn_main_branch_fallback(effects, &repo, &main_branch_fallback)?;
+    }
+    let head_info = repo.get_head_info()?;
+
+    let mut highlighted_oids = HashSet::new();
+    for commit in highlight {
+        match repo.revparse_single_commit(commit)? {
+            Some(commit) => {
+                highlighted_oids.insert(commit.get_oid());
+            }
+            None => {
+                writeln!(effects.get_error_stream(), "Commit not found: {}", commit)?;
+                return Ok(BranchlessExitCode::UserError.into());
+            }
+        }
+    }
+
+    let loaded = match load_smartlog(effects, &repo, options)? {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            writeln!(effects.get_error_stream(), "{}", err)?;
+            return Ok(BranchlessExitCode::UserError.into());
+        }
+    };
+    if let Some(corruption) = &loaded.corruption {
+        writeln!(
+            effects.get_error_stream(),
+            "branchless: warning: {}, so the smartlog may be incomplete or out of date.",
+            corruption
+        )?;
+        writeln!(
+            effects.get_error_stream(),
+            "branchless: warning: run `git branchless repair` to reconstruct the event log from the current references{}.",
+            match corruption {
+                EventLogCorruption::Unreadable { .. } => {
+                    " (move `.git/branchless/db.sqlite3` out of the way first)"
+                }
+                EventLogCorruption::MalformedEvents { .. } => "",
+            }
+        )?;
+    }
+    if loaded.num_omitted_heads > 0 {
+        writeln!(
+            effects.get_error_stream(),
+            "Omitted {} from the smartlog, because there are more than `branchless.smartlog.maxRefs` to show. Run `git smartlog --full` to show all commits.",
+            Pluralize {
+                determiner: None,
+                amount: loaded.num_omitted_heads.try_into()?,
+                unit: ("stack", "stacks"),
+            },
+        )?;
+    }
+    if loaded.num_omitted_commits > 0 {
+        writeln!(
+            effects.get_error_stream(),
+            "Omitted {} from the smartlog. Run `git smartlog --max-commits 0` to show all commits.",
+            Pluralize {
+                determiner: None,
+                amount: loaded.num_omitted_commits.try_into()?,
+                unit: ("commit", "commits"),
+            },
+        )?;
+    }
+
+    let abbrev_len = repo.get_abbrev_len(
+        get_smartlog_abbrev(&repo)?,
+        loaded
+            .graph
+            .get_commits()
+            .iter()
+            .map(|commit| commit.get_oid()),
+    )?;
+
+    match format {
+        SmartlogFormat::Text => {}
+        SmartlogFormat::Json => {
+            let smartlog = loaded.to_smartlog(effects, &repo)?;
+            let mut nodes = render_graph_json(&smartlog, abbrev_len);
+            if *reverse {
+                nodes.reverse();
+            }
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                serde_json::to_string_pretty(&nodes)?
+            )?;
+            return Ok(0);
+        }
+        SmartlogFormat::Dot => {
+            let smartlog = loaded.to_smartlog(effects, &repo)?;
+            write!(
+                effects.get_output_stream(),
+                "{}",
+                render_graph_dot(&smartlog, *reverse, abbrev_len)?
+            )?;
+            return Ok(0);
+        }
+    }
+
+    let LoadedSmartlog {
+        conn,
+        corruption: _,
+        references_snapshot,
+        event_replayer,
+        event_cursor: _,
+        dag,
+        mut graph,
+        num_omitted_commits: _,
+        num_omitted_heads: _,
+    } = loaded;
+    graph.set_highlighted(&highlighted_oids);
+    if *color_by_stack {
+        graph.set_stack_indexes(effects, &repo, &dag);
+    }
+
+    let show_age = *show_age || get_smartlog_show_age(&repo)?;
+    let glyphs = match glyphs {
+        Some(glyphs) => *glyphs,
+        None => match get_smartlog_glyphs(&repo)? {
+            Some(glyphs) => SmartlogGlyphs::from_str(&glyphs, true).map_err(|err| {
+                eyre::eyre!("Invalid value for branchless.smartlog.glyphs: {}", err)
+            })?,
+            None => SmartlogGlyphs::Unicode,
+        },
+    };
+    let effects = effects
+        .ascii_glyphs(glyphs == SmartlogGlyphs::Ascii)
+        .reverse_order(*reverse)
+        .smartlog_theme(get_smartlog_theme(&repo)?);
+    let pool = ThreadPoolBuilder::new().build()?;
+    let repo_pool = RepoResource::new_pool(&repo)?;
+    let commit_oids = graph
+        .get_commits()
+        .iter()
+        .map(|commit| commit.get_oid())
+        .collect::<Vec<_>>();
+    let mut commit_oid_descriptor = CommitOidDescriptor::new_with_abbrev_len(true, abbrev_len)?;
+    let mut relative_time_descriptor = RelativeTimeDescriptor::new(&repo, SystemTime::now())?;
+    let mut obsolescence_explanation_descriptor =
+        ObsolescenceExplanationDescriptor::new_with_abbrev_len(
+            &repo,
+            &event_replayer,
+            event_replayer.make_default_cursor(),
+            abbrev_len,
+        )?;
+    let mut branches_descriptor =
+        BranchesDescriptor::new(&repo, &head_info, &references_snapshot, &Redactor::Disabled)?
+            .only_checked_out_branch(*hide_branches)
+            .show_remote_status(
                 &repo,
-                &head_info,
-                &references_snapshot,
-                &Redactor::Disabled,
-            )?,
-            &mut DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?,
-            &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
-        ],
+                &pool,
+                &repo_pool,
+                get_smartlog_show_remote_status(&repo)?,
+            )?;
+    let mut tags_descriptor = TagsDescriptor::new(&repo)?;
+    let mut differential_revision_descriptor =
+        DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?;
+    let mut commit_message_descriptor = CommitMessageDescriptor::new(&Redactor::Disabled)?
+        .subject_trailer(get_smartlog_subject_trailer(&repo)?);
+    commit_message_descriptor.prefetch(&pool, &repo_pool, &commit_oids)?;
+    let mut commit_author_descriptor =
+        CommitAuthorDescriptor::new(&repo, *show_author || get_commit_descriptors_author(&repo)?)?;
+    let mut commit_index_descriptor = CommitIndexDescriptor::new(*show_index)?;
+    let mut commit_signature_descriptor =
+        CommitSignatureDescriptor::new(&repo, git_run_info, *show_signatures)?;
+    commit_signature_descriptor.prefetch(
+        &graph
+            .get_commits()
+            .iter()
+            .map(|commit| commit.get_oid())
+            .collect::<Vec<_>>(),
     )?;
+    let commit_descriptors: &mut [&mut dyn NodeDescriptor] = &mut [
+        &mut commit_index_descriptor,
+        &mut commit_oid_descriptor,
+        &mut relative_time_descriptor,
+        &mut obsolescence_explanation_descriptor,
+        &mut branches_descriptor,
+        &mut tags_descriptor,
+        &mut differential_revision_descriptor,
+        &mut commit_message_descriptor,
+        &mut commit_author_descriptor,
+        &mut commit_signature_descriptor,
+    ];
+    let mut commit_age_descriptor = CommitAgeDescriptor::new(SystemTime::now())?;
+    let mut right_column_descriptors: Vec<(&mut dyn NodeDescriptor, ColumnAlignment)> = Vec::new();
+    if show_age {
+        right_column_descriptors.push((&mut commit_age_descriptor, ColumnAlignment::Left));
+    }
+    let mut insertions_descriptor =
+        CommitDiffStatDescriptor::new(&repo, DiffStatColumn::Insertions)?;
+    let mut deletions_descriptor = insertions_descriptor.with_column(DiffStatColumn::Deletions);
+    let mut files_descriptor = insertions_descriptor.with_column(DiffStatColumn::Files);
+    if let Some(stat) = stat {
+        insertions_descriptor.prefetch(&pool, &repo_pool, &commit_oids)?;
+
+        right_column_descriptors.push((&mut insertions_descriptor, ColumnAlignment::Right));
+        right_column_descriptors.push((&mut deletions_descriptor, ColumnAlignment::Right));
+        match stat {
+            SmartlogStat::Short => {}
+            SmartlogStat::Full => {
+                right_column_descriptors.push((&mut files_descriptor, ColumnAlignment::Left));
+            }
+        }
+    }
+    let layout = SubjectLayout {
+        overflow: overflow.unwrap_or(TextOverflow::Truncate),
+        max_line_width: get_terminal_width(),
+        max_subject_width: get_smartlog_max_subject_width(&repo)?,
+        reverse: *reverse,
+    };
+    let mut lines = if right_column_descriptors.is_empty() {
+        render_graph(
+            &effects,
+            &repo,
+            &dag,
+            &graph,
+            references_snapshot.head_oid,
+            commit_descriptors,
+            &layout,
+        )?
+    } else {
+        render_graph_with_right_columns(
+            &effects,
+            &repo,
+            &dag,
+            &graph,
+            references_snapshot.head_oid,
+            commit_descriptors,
+            &mut right_column_descriptors,
+            &layout,
+        )?
+    };
+    if *show_index {
+        let smartlog_index_db = SmartlogIndexDb::new(&conn)?;
+        smartlog_index_db.set_commits(commit_index_descriptor.get_commit_oids())?;
+    }
+    if *reverse {
+        lines.reverse();
+    }
+
+    let show_legend = *show_legend || get_smartlog_show_legend(&repo)?;
+    let num_lines = lines.len() + usize::from(show_legend);
+    let mut output = String::new();
     for line in lines {
         writeln!(
-            effects.get_output_stream(),
+            output,
             "{}",
             printable_styled_string(effects.get_glyphs(), line)?
         )?;
     }
+    if show_legend {
+        writeln!(output, "{}", effects.get_glyphs().legend())?;
+    }
+    if *use_pager && should_use_pager(num_lines) {
+        if let Some(pager) = get_pager(git_run_info, &repo)? {
+            run_pager(git_run_info, &pager, &output)?;
+            return Ok(0);
+        }
+    }
+    write!(effects.get_output_stream(), "{}", output)?;
+
+    Ok(0)
+}
+
+/// Determine whether output with the given number of lines should be shown in a
+/// pager, i.e. if stdout is a terminal and the output doesn't fit on it.
+fn should_use_pager(num_lines: usize) -> bool {
+    let term = console::Term::stdout();
+    if !term.is_term() {
+        return false;
+    }
+    match term.size_checked() {
+        Some((rows, _columns)) => num_lines >= usize::from(rows),
+        None => false,
+    }
+}
+
+/// Get the width of the terminal, or `None` if stdout is not a terminal.
+fn get_terminal_width() -> Option<usize> {
+    let term = console::Term::stdout();
+    if !term.is_term() {
+        return None;
+    }
+    term.size_checked()
+        .map(|(_rows, columns)| usize::from(columns))
+}
+
+/// Get the pager command to use, or `None` if paging is disabled. Uses
+/// `branchless.smartlog.pager` if set, and otherwise resolves the pager in the
+/// same way as Git (`GIT_PAGER`, `core.pager`, `PAGER`, then `less`).
+#[instrument]
+fn get_pager(git_run_info: &GitRunInfo, repo: &Repo) -> eyre::Result<Option<String>> {
+    let pager = match get_smartlog_pager(repo)? {
+        Some(pager) => pager,
+        None => {
+            let pager = git_run_info
+                .run_silent(repo, None, &["var", "GIT_PAGER"], Default::default())
+                .wrap_err("Determining Git pager")?
+                .stdout;
+            String::from_utf8(pager).wrap_err("Decoding stdout from Git subprocess")?
+        }
+    };
+    let pager = pager.trim();
+    if pager.is_empty() || pager == "cat" {
+        Ok(None)
+    } else {
+        Ok(Some(pager.to_string()))
+    }
+}
+
+/// Display `output` in the given pager, waiting for the user to exit it.
+#[instrument(skip(output))]
+fn run_pager(git_run_info: &GitRunInfo, pager: &str, output: &str) -> eyre::Result<()> {
+    let GitRunInfo {
+        path_to_git: _,
+        working_directory,
+        env,
+    } = git_run_info;
+
+    // Invoke the pager the same way that Git does. In particular, set `LESS`
+    // so that `less` passes through color codes (`-R`) and exits immediately
+    // if the output fits on one screen (`-F`), unless the user has configured
+    // it otherwise.
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(pager)
+        .current_dir(working_directory)
+        .env_clear()
+        .envs(env.iter())
+        .stdin(Stdio::piped());
+    if !env.contains_key(OsStr::new("LESS")) {
+        command.env("LESS", "FRX");
+    }
+    if !env.contains_key(OsStr::new("LV")) {
+        command.env("LV", "-c");
+    }
+    let mut child = command
+        .spawn()
+        .wrap_err_with(|| format!("Spawning pager: {}", pager))?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| eyre::eyre!("Could not open stdin for pager"))?;
+    match stdin.write_all(output.as_bytes()) {
+        Ok(()) => {}
+        // The user may quit the pager before reading all of the output.
+        Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => {}
+        Err(err) => return Err(err).wrap_err("Writing to pager"),
+    }
+    drop(stdin);
 
+    child.wait().wrap_err("Waiting for pager to exit")?;
     Ok(())
 }
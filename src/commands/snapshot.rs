@@ -0,0 +1,316 @@
+//! Save the state of the working copy and index into a commit, and restore it
+//! later.
+//!
+//! A snapshot is a commit whose tree is the state of the working copy. Its
+//! first parent is the commit which was checked out at the time, and its
+//! second parent is a commit whose tree is the state of the index. (This is
+//! the same layout that `git stash` uses.)
+//!
+//! Untracked files are only included if explicitly requested, in which case
+//! they're part of the working copy tree, but not the index tree. Ignored files
+//! are never included.
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use eyre::Context;
+use tracing::instrument;
+
+use crate::commands::BranchlessExitCode;
+use crate::core::effects::Effects;
+use crate::core::eventlog::{Event, EventLogDb, EventTransactionId};
+use crate::git::{
+    check_out_commit, CheckOutCommitOptions, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
+};
+
+/// Get the untracked files in the working copy which also exist in the given
+/// snapshot, and so would be overwritten by restoring it.
+fn get_untracked_paths_in_snapshot(
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_tx_id: EventTransactionId,
+    snapshot: &str,
+) -> eyre::Result<Vec<String>> {
+    let untracked_paths = git_run_info.run_silent(
+        repo,
+        Some(event_tx_id),
+        &["ls-files", "-z", "--others", "--exclude-standard"],
+        Default::default(),
+    )?;
+    let untracked_paths = String::from_utf8_lossy(&untracked_paths.stdout);
+    let untracked_paths: HashSet<&str> = untracked_paths
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .collect();
+    if untracked_paths.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let snapshot_paths = git_run_info.run_silent(
+        repo,
+        Some(event_tx_id),
+        &["ls-tree", "-z", "-r", "--name-only", snapshot],
+        Default::default(),
+    )?;
+    let snapshot_paths = String::from_utf8_lossy(&snapshot_paths.stdout);
+    Ok(snapshot_paths
+        .split('\0')
+        .filter(|path| untracked_paths.contains(path))
+        .map(|path| path.to_string())
+        .collect())
+}
+
+/// The prefix of the references which keep snapshot commits from being
+/// garbage-collected.
+const SNAPSHOT_REF_PREFIX: &str = "refs/branchless/snapshots/";
+
+/// Determine whether the given reference keeps a snapshot alive. Such
+/// references are never released by `git branchless gc`.
+pub fn is_snapshot_ref(ref_name: &str) -> bool {
+    ref_name.starts_with(SNAPSHOT_REF_PREFIX)
+}
+
+fn run_git_for_oid(
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    args: &[&str],
+) -> eyre::Result<NonZeroOid> {
+    let result = git_run_info.run_silent(repo, None, args, Default::default())?;
+    let output = String::from_utf8(result.stdout).wrap_err("Decoding Git output")?;
+    let oid = output.trim().parse()?;
+    Ok(oid)
+}
+
+/// Snapshot the current working copy and index, without modifying either of
+/// them.
+#[instrument]
+pub fn snapshot(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    include_untracked: bool,
+) -> eyre::Result<isize> {
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
+
+    let head_oid = match repo.get_head_info()?.oid {
+        Some(head_oid) => head_oid,
+        None => {
+            writeln!(
+                effects.get_error_stream(),
+                "No commit is currently checked out, so a snapshot can't be taken."
+            )?;
+            return Ok(BranchlessExitCode::UserError.into());
+        }
+    };
+
+    let unmerged_files = git_run_info
+        .run_silent(&repo, None, &["ls-files", "--unmerged"], Default::default())?
+        .stdout;
+    if !unmerged_files.is_empty() {
+        writeln!(
+            effects.get_error_stream(),
+            "The index contains merge conflicts, so a snapshot can't be taken. Resolve the conflicts and try again."
+        )?;
+        return Ok(BranchlessExitCode::UserError.into());
+    }
+
+    let index_tree_oid = run_git_for_oid(git_run_info, &repo, &["write-tree"])?;
+
+    // Stage the working copy into a temporary copy of the index, so that the
+    // user's actual index isn't modified.
+    let working_copy_tree_oid = {
+        let snapshot_dir = repo.get_path().join("branchless");
+        std::fs::create_dir_all(&snapshot_dir).wrap_err("Creating snapshot directory")?;
+        let temp_index_path = snapshot_dir.join("snapshot-index");
+        let index_path = repo.get_path().join("index");
+        if index_path.exists() {
+            std::fs::copy(&index_path, &temp_index_path).wrap_err("Copying index")?;
+        } else if temp_index_path.exists() {
+            std::fs::remove_file(&temp_index_path).wrap_err("Removing stale index")?;
+        }
+
+        let mut temp_git_run_info = git_run_info.clone();
+        temp_git_run_info.env.insert(
+            OsString::from("GIT_INDEX_FILE"),
+            temp_index_path.clone().into_os_string(),
+        );
+        let add_args: &[&str] = if include_untracked {
+            &["add", "--all"]
+        } else {
+            &["add", "--update"]
+        };
+        temp_git_run_info.run_silent(&repo, None, add_args, Default::default())?;
+        let tree_oid = run_git_for_oid(&temp_git_run_info, &repo, &["write-tree"])?;
+        std::fs::remove_file(&temp_index_path).wrap_err("Removing temporary index")?;
+        tree_oid
+    };
+
+    let head_oid_str = head_oid.to_string();
+    let index_tree_oid_str = index_tree_oid.to_string();
+    let index_commit_oid = run_git_for_oid(
+        git_run_info,
+        &repo,
+        &[
+            "commit-tree",
+            &index_tree_oid_str,
+            "-p",
+            &head_oid_str,
+            "-m",
+            "branchless: snapshot index",
+        ],
+    )?;
+    let index_commit_oid_str = index_commit_oid.to_string();
+    let working_copy_tree_oid_str = working_copy_tree_oid.to_string();
+    let snapshot_oid = run_git_for_oid(
+        git_run_info,
+        &repo,
+        &[
+            "commit-tree",
+            &working_copy_tree_oid_str,
+            "-p",
+            &head_oid_str,
+            "-p",
+            &index_commit_oid_str,
+            "-m",
+            "branchless: snapshot",
+        ],
+    )?;
+
+    let ref_name = format!("{}{}", SNAPSHOT_REF_PREFIX, snapshot_oid);
+    repo.create_reference(
+        &OsString::from(ref_name),
+        snapshot_oid,
+        true,
+        "branchless: snapshot",
+    )?;
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "snapshot")?;
+    event_log_db.add_events(vec![Event::WorkingCopySnapshot {
+        timestamp: now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64(),
+        event_tx_id,
+        head_oid: MaybeZeroOid::NonZero(head_oid),
+        commit_oid: snapshot_oid,
+    }])?;
+
+    writeln!(
+        effects.get_output_stream(),
+        "Created snapshot: {}",
+        snapshot_oid
+    )?;
+    writeln!(
+        effects.get_output_stream(),
+        "To restore it, run: git branchless restore {}",
+        snapshot_oid
+    )?;
+    Ok(0)
+}
+
+/// Restore the working copy and index from the given snapshot.
+#[instrument]
+pub fn restore(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    snapshot: String,
+) -> eyre::Result<isize> {
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+
+    let snapshot_oid = match repo.revparse_single_commit(&snapshot)? {
+        Some(commit) => commit.get_oid(),
+        None => {
+            writeln!(
+                effects.get_error_stream(),
+                "Snapshot not found: {}",
+                snapshot
+            )?;
+            return Ok(BranchlessExitCode::UserError.into());
+        }
+    };
+    let head_oid = event_log_db
+        .get_events_for_commit(snapshot_oid)?
+        .into_iter()
+        .find_map(|event| match event {
+            Event::WorkingCopySnapshot {
+                head_oid,
+                commit_oid,
+                ..
+            } if commit_oid == snapshot_oid => Some(head_oid),
+            _ => None,
+        });
+    let head_oid = match head_oid {
+        Some(MaybeZeroOid::NonZero(head_oid)) => head_oid,
+        Some(MaybeZeroOid::Zero) | None => {
+            writeln!(effects.get_error_stream(), "Not a snapshot: {}", snapshot)?;
+            return Ok(BranchlessExitCode::UserError.into());
+        }
+    };
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "restore")?;
+    if !repo.get_status(git_run_info, Some(event_tx_id))?.is_empty() {
+        writeln!(
+            effects.get_error_stream(),
+            "The working copy has uncommitted changes. Commit them, or snapshot them with `git branchless snapshot`, before restoring a snapshot."
+        )?;
+        return Ok(BranchlessExitCode::UserError.into());
+    }
+
+    if repo.get_head_info()?.oid != Some(head_oid) {
+        let exit_code = check_out_commit(
+            effects,
+            git_run_info,
+            Some(event_tx_id),
+            Some(head_oid.to_string()),
+            &CheckOutCommitOptions {
+                additional_args: &[],
+                render_smartlog: false,
+            },
+        )?;
+        if exit_code != 0 {
+            return Ok(exit_code);
+        }
+    }
+
+    let snapshot_oid_str = snapshot_oid.to_string();
+    let colliding_paths =
+        get_untracked_paths_in_snapshot(git_run_info, &repo, event_tx_id, &snapshot_oid_str)?;
+    if !colliding_paths.is_empty() {
+        writeln!(
+            effects.get_error_stream(),
+            "These untracked files would be overwritten by the snapshot:"
+        )?;
+        for path in colliding_paths {
+            writeln!(effects.get_error_stream(), "  {}", path)?;
+        }
+        writeln!(
+            effects.get_error_stream(),
+            "Move or delete them before restoring the snapshot."
+        )?;
+        return Ok(BranchlessExitCode::UserError.into());
+    }
+
+    git_run_info.run_silent(
+        &repo,
+        Some(event_tx_id),
+        &["read-tree", "--reset", "-u", &snapshot_oid_str],
+        Default::default(),
+    )?;
+    git_run_info.run_silent(
+        &repo,
+        Some(event_tx_id),
+        &["read-tree", &format!("{}^2", snapshot_oid_str)],
+        Default::default(),
+    )?;
+
+    writeln!(
+        effects.get_output_stream(),
+        "Restored snapshot: {}",
+        snapshot_oid
+    )?;
+    Ok(0)
+}
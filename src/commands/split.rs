@@ -0,0 +1,402 @@
+//! Split a commit into two commits.
+//!
+//! The user interactively selects which changes of the commit to keep in the
+//! first commit, and the remaining changes are put into a second commit on
+//! top of it. The selection happens in a temporary worktree, so the user's
+//! working copy is left untouched. The descendants of the split commit are
+//! then moved on top of the second commit.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use eden_dag::DagAlgorithm;
+use eyre::Context;
+use rayon::ThreadPoolBuilder;
+use tracing::instrument;
+
+use crate::commands::gc::mark_commit_reachable;
+use crate::commands::reword::edit_message;
+use crate::commands::smartlog::smartlog;
+use crate::commands::BranchlessExitCode;
+use crate::core::commit_message::combine_commit_messages;
+use crate::core::config::{
+    get_commit_gpg_sign, get_commit_template, get_restack_preserve_timestamps,
+};
+use crate::core::dag::{resolve_commits, CommitSet, Dag, ResolveCommitsResult};
+use crate::core::effects::Effects;
+use crate::core::eventlog::{Event, EventLogDb, EventReplayer, EventTransactionId};
+use crate::core::formatting::printable_styled_string;
+use crate::core::rewrite::{
+    execute_rebase_plan, move_branches, BuildRebasePlanOptions, ExecuteRebasePlanOptions,
+    ExecuteRebasePlanResult, RebasePlanBuilder, RepoResource,
+};
+use crate::git::{
+    check_out_commit, CheckOutCommitOptions, Commit, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
+    TemporaryWorktree, Tree,
+};
+use crate::opts::MoveOptions;
+
+/// Interactively select the changes of `commit` to keep in the first commit.
+/// Returns the tree for the first commit, or `None` if the selection was
+/// aborted.
+#[instrument]
+fn select_changes(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    commit: &Commit,
+    parent: &Commit,
+) -> eyre::Result<Option<NonZeroOid>> {
+    let worktree = TemporaryWorktree::new(git_run_info, repo)?;
+    worktree.check_out(commit.get_oid())?;
+
+    // Start with the index at the parent commit, so that the user can stage
+    // the changes which should go into the first commit. Files added by the
+    // commit are marked with intent-to-add, so that they're offered for
+    // staging as well.
+    let parent_oid = parent.get_oid().to_string();
+    let commit_oid = commit.get_oid().to_string();
+    worktree.run_git_silent(&["read-tree", &parent_oid])?;
+    let added_paths = worktree.run_git_silent(&[
+        "diff-tree",
+        "-r",
+        "-z",
+        "--name-only",
+        "--no-renames",
+        "--diff-filter=A",
+        &parent_oid,
+        &commit_oid,
+    ])?;
+    let added_paths = String::from_utf8(added_paths).wrap_err("Decoding added paths")?;
+    let added_paths: Vec<&str> = added_paths
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .collect();
+    if !added_paths.is_empty() {
+        let mut args = vec!["add", "--intent-to-add", "--"];
+        args.extend(added_paths);
+        worktree.run_git_silent(&args)?;
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Select the changes to keep in the first commit. The remaining changes will be put into the second commit."
+    )?;
+    let exit_code = worktree.run_git_interactive(&["add", "--patch"])?;
+    if exit_code != 0 {
+        worktree.remove()?;
+        return Ok(None);
+    }
+
+    let tree_oid = worktree.run_git_silent(&["write-tree"])?;
+    worktree.remove()?;
+    let tree_oid = String::from_utf8(tree_oid).wrap_err("Decoding tree OID")?;
+    let tree_oid: NonZeroOid = tree_oid.trim().parse()?;
+    Ok(Some(tree_oid))
+}
+
+/// For each `(old_oid, new_oid)` pair in `moves`, move the visible children of
+/// `old_oid` (and their descendants) on top of `new_oid`, as part of the
+/// transaction `event_tx_id`.
+#[instrument]
+pub fn move_descendants(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    event_tx_id: EventTransactionId,
+    now: SystemTime,
+    moves: &[(NonZeroOid, NonZeroOid)],
+    move_options: &MoveOptions,
+) -> eyre::Result<isize> {
+    let references_snapshot = repo.get_references_snapshot()?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let dag = Dag::open_and_sync(
+        effects,
+        repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let visible_commits = dag.observed_commits.difference(&dag.obsolete_commits);
+    let mut children_moves = Vec::new();
+    for (old_oid, new_oid) in moves.iter().copied() {
+        let children = dag
+            .query()
+            .children(CommitSet::from(old_oid))?
+            .intersection(&visible_commits);
+        for child in children.iter()? {
+            let child_oid = NonZeroOid::try_from(child?)?;
+            children_moves.push((child_oid, new_oid));
+        }
+    }
+    if children_moves.is_empty() {
+        return Ok(0);
+    }
+
+    let MoveOptions {
+        force_in_memory,
+        force_on_disk,
+        detect_duplicate_commits_via_patch_id,
+        resolve_merge_conflicts,
+        sign_commits,
+        dump_rebase_constraints,
+        dump_rebase_plan,
+    } = *move_options;
+    let pool = ThreadPoolBuilder::new().build()?;
+    let repo_pool = RepoResource::new_pool(repo)?;
+    let rebase_plan = {
+        let mut builder = RebasePlanBuilder::new(&dag);
+        for (child_oid, new_oid) in children_moves {
+            builder.move_subtree(child_oid, new_oid)?;
+        }
+        builder.build(
+            effects,
+            &pool,
+            &repo_pool,
+            &BuildRebasePlanOptions {
+                dump_rebase_constraints,
+                dump_rebase_plan,
+                detect_duplicate_commits_via_patch_id,
+            },
+        )?
+    };
+    let rebase_plan = match rebase_plan {
+        Ok(None) => return Ok(0),
+        Ok(Some(rebase_plan)) => rebase_plan,
+        Err(err) => {
+            err.describe(effects, repo)?;
+            return Ok(1);
+        }
+    };
+
+    let options = ExecuteRebasePlanOptions {
+        now,
+        event_tx_id,
+        preserve_timestamps: get_restack_preserve_timestamps(repo)?,
+        force_in_memory,
+        force_on_disk,
+        resolve_merge_conflicts,
+        sign_commits,
+        check_out_commit_options: CheckOutCommitOptions {
+            additional_args: &[],
+            render_smartlog: false,
+        },
+    };
+    match execute_rebase_plan(effects, git_run_info, repo, &rebase_plan, &options)? {
+        ExecuteRebasePlanResult::Succeeded => Ok(0),
+        ExecuteRebasePlanResult::DeclinedToMerge { merge_conflict } => {
+            merge_conflict.describe(effects, repo)?;
+            Ok(BranchlessExitCode::MergeConflict.into())
+        }
+        ExecuteRebasePlanResult::Failed { exit_code } => Ok(exit_code),
+    }
+}
+
+/// Split the provided commit, or the current commit if none is provided, into
+/// two commits.
+/// The generated commit messages are formatted with `commit_template`, or
+/// with `branchless.commitTemplate` if not provided.
+#[instrument]
+pub fn split(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    commit: Option<String>,
+    commit_template: Option<&str>,
+    move_options: &MoveOptions,
+) -> eyre::Result<isize> {
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
+    let head_info = repo.get_head_info()?;
+
+    let commit = match (commit, head_info.oid) {
+        (Some(commit), _) => commit,
+        (None, Some(head_oid)) => head_oid.to_string(),
+        (None, None) => {
+            writeln!(
+                effects.get_output_stream(),
+                "No commit is currently checked out. Check out a commit to split, or provide the commit to split, and then try again.",
+            )?;
+            return Ok(1);
+        }
+    };
+
+    let references_snapshot = repo.get_references_snapshot()?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+    let commit = match resolve_commits(effects, &repo, &mut dag, vec![commit])? {
+        ResolveCommitsResult::Ok { mut commits } => commits.remove(0),
+        ResolveCommitsResult::CommitNotFound { commit } => {
+            writeln!(effects.get_output_stream(), "Commit not found: {}", commit)?;
+            return Ok(1);
+        }
+    };
+    let parent = match commit.get_only_parent() {
+        Some(parent) => parent,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "Cannot split {}, because it does not have exactly one parent commit.",
+                printable_styled_string(
+                    effects.get_glyphs(),
+                    commit.friendly_describe(effects.get_glyphs())?
+                )?,
+            )?;
+            return Ok(1);
+        }
+    };
+
+    let first_tree_oid = match select_changes(effects, git_run_info, &repo, &commit, &parent)? {
+        Some(first_tree_oid) => first_tree_oid,
+        None => {
+            writeln!(effects.get_output_stream(), "Aborting split.")?;
+            return Ok(1);
+        }
+    };
+    let second_tree = commit.get_tree()?;
+    if first_tree_oid == parent.get_tree()?.get_oid() || first_tree_oid == second_tree.get_oid() {
+        writeln!(
+            effects.get_output_stream(),
+            "Aborting split, because either none or all of the changes were selected for the first commit."
+        )?;
+        return Ok(1);
+    }
+    let first_tree = repo.find_tree_or_fail(first_tree_oid)?;
+
+    let message = commit.get_message_raw()?;
+    let message = message.to_string_lossy();
+    let summary = commit.get_summary()?;
+    let commit_template = match commit_template {
+        Some(commit_template) => Some(commit_template.to_string()),
+        None => get_commit_template(&repo)?,
+    };
+    let first_message = edit_message(
+        git_run_info,
+        &repo,
+        &combine_commit_messages(commit_template.as_deref(), &[&message]),
+        "the first commit",
+        "split",
+    )?;
+    let second_message = edit_message(
+        git_run_info,
+        &repo,
+        &combine_commit_messages(
+            commit_template.as_deref(),
+            &[&format!("{} (continued)", summary.to_string_lossy())],
+        ),
+        "the second commit",
+        "split",
+    )?;
+    if first_message.trim().is_empty() || second_message.trim().is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Aborting split due to empty commit message."
+        )?;
+        return Ok(1);
+    }
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "split")?;
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let preserve_timestamps = get_restack_preserve_timestamps(&repo)?;
+    let committer = if preserve_timestamps {
+        commit.get_committer()
+    } else {
+        match repo.get_default_signature(git_run_info, now)? {
+            Some(committer) => committer,
+            None => commit.get_committer().update_timestamp(now)?,
+        }
+    };
+    let sign_commits = move_options.sign_commits && get_commit_gpg_sign(&repo)?;
+    let create_commit = |message: &str, tree: &Tree, parent: &Commit| {
+        if sign_commits {
+            repo.create_signed_commit(
+                git_run_info,
+                None,
+                &commit.get_author(),
+                &committer,
+                message,
+                tree,
+                vec![parent],
+            )
+        } else {
+            repo.create_commit(
+                None,
+                &commit.get_author(),
+                &committer,
+                message,
+                tree,
+                vec![parent],
+            )
+        }
+    };
+    let first_commit_oid = create_commit(&first_message, &first_tree, &parent)?;
+    let first_commit = repo.find_commit_or_fail(first_commit_oid)?;
+    let second_commit_oid = create_commit(&second_message, &second_tree, &first_commit)?;
+    for commit_oid in [first_commit_oid, second_commit_oid].iter().copied() {
+        mark_commit_reachable(&repo, commit_oid)
+            .wrap_err("Marking commit as reachable for GC purposes.")?;
+    }
+    event_log_db.add_events(vec![
+        Event::CommitEvent {
+            timestamp,
+            event_tx_id,
+            commit_oid: first_commit_oid,
+        },
+        Event::RewriteEvent {
+            timestamp,
+            event_tx_id,
+            old_commit_oid: commit.get_oid().into(),
+            new_commit_oid: second_commit_oid.into(),
+        },
+    ])?;
+
+    // The second commit has the same tree as the original commit, so neither
+    // the working copy nor the index need to change if it was checked out.
+    if head_info.oid == Some(commit.get_oid()) && head_info.reference_name.is_none() {
+        let exit_code = check_out_commit(
+            effects,
+            git_run_info,
+            Some(event_tx_id),
+            Some(second_commit_oid.to_string()),
+            &CheckOutCommitOptions {
+                additional_args: &[],
+                render_smartlog: false,
+            },
+        )?;
+        if exit_code != 0 {
+            return Ok(exit_code);
+        }
+    }
+    let rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid> =
+        std::iter::once((commit.get_oid(), MaybeZeroOid::NonZero(second_commit_oid))).collect();
+    move_branches(effects, git_run_info, &repo, event_tx_id, &rewritten_oids)?;
+
+    let exit_code = move_descendants(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        now,
+        &[(commit.get_oid(), second_commit_oid)],
+        move_options,
+    )?;
+    if exit_code != 0 {
+        return Ok(exit_code);
+    }
+
+    smartlog(effects, git_run_info, &Default::default())
+}
@@ -0,0 +1,148 @@
+//! Push the branches in the current stack to a remote, so that they're ready
+//! for review.
+//!
+//! Only branches which don't already have an upstream branch are pushed, so
+//! running `git branchless submit` again after adding new branches to the
+//! stack only pushes the new branches.
+
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use eden_dag::DagAlgorithm;
+use tracing::instrument;
+
+use crate::core::config::get_submit_remote;
+use crate::core::dag::{sort_commit_set, CommitSet, Dag};
+use crate::core::effects::Effects;
+use crate::core::eventlog::{EventLogDb, EventReplayer};
+use crate::git::{CategorizedReferenceName, GitRunInfo, NonZeroOid, Repo};
+
+/// Get the visible draft commits in the same stack as `head_oid`, i.e. the
+/// draft descendants of the draft roots of `head_oid`. This is empty if
+/// `head_oid` is not a draft commit.
+pub fn get_current_stack(dag: &Dag, head_oid: NonZeroOid) -> eyre::Result<CommitSet> {
+    let public_commits = dag.query_public_commits()?;
+    let draft_commits = dag
+        .observed_commits
+        .difference(&dag.obsolete_commits)
+        .difference(&public_commits);
+    let stack_roots = dag.query().roots(
+        dag.query()
+            .ancestors(CommitSet::from(head_oid))?
+            .intersection(&draft_commits),
+    )?;
+    let stack_commits = dag
+        .query()
+        .descendants(stack_roots)?
+        .intersection(&draft_commits);
+    Ok(stack_commits)
+}
+
+/// Push each branch in the current stack which doesn't have an upstream
+/// branch to the remote, and set the pushed branch as its upstream.
+#[instrument]
+pub fn submit(effects: &Effects, git_run_info: &GitRunInfo, dry_run: bool) -> eyre::Result<isize> {
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let head_oid = match references_snapshot.head_oid {
+        Some(head_oid) => head_oid,
+        None => {
+            writeln!(
+                effects.get_error_stream(),
+                "No commit is currently checked out, so there is no stack to submit."
+            )?;
+            return Ok(1);
+        }
+    };
+    let remote = get_submit_remote(&repo)?;
+
+    let stack_commits = get_current_stack(&dag, head_oid)?;
+    let mut branches_to_push: Vec<String> = Vec::new();
+    for commit in sort_commit_set(&repo, &dag, &stack_commits)? {
+        let mut branch_names: Vec<&OsString> = match references_snapshot
+            .branch_oid_to_names
+            .get(&commit.get_oid())
+        {
+            Some(branch_names) => branch_names.iter().collect(),
+            None => continue,
+        };
+        branch_names.sort();
+
+        for branch_name in branch_names {
+            let branch_name = CategorizedReferenceName::new(branch_name).render_suffix();
+            let branch = match repo.find_branch(&branch_name, git2::BranchType::Local)? {
+                Some(branch) => branch,
+                None => continue,
+            };
+            match branch.get_upstream_branch()? {
+                Some(upstream_branch) => {
+                    let upstream_name = upstream_branch.into_reference().get_name()?;
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Skipped branch {}: already tracking {}",
+                        branch_name,
+                        CategorizedReferenceName::new(&upstream_name).render_suffix(),
+                    )?;
+                }
+                None => branches_to_push.push(branch_name),
+            }
+        }
+    }
+
+    if branches_to_push.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "No branches in the current stack need to be pushed."
+        )?;
+        return Ok(0);
+    }
+
+    if dry_run {
+        for branch_name in branches_to_push {
+            writeln!(
+                effects.get_output_stream(),
+                "Would push branch {} to {}/{}",
+                branch_name,
+                remote,
+                branch_name
+            )?;
+        }
+        return Ok(0);
+    }
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "submit")?;
+    let args = {
+        let mut args = vec!["push", "--quiet", "--set-upstream", remote.as_str()];
+        args.extend(branches_to_push.iter().map(String::as_str));
+        args
+    };
+    let exit_code = git_run_info.run(effects, Some(event_tx_id), &args)?;
+    if exit_code != 0 {
+        return Ok(exit_code);
+    }
+
+    for branch_name in branches_to_push {
+        writeln!(
+            effects.get_output_stream(),
+            "Pushed branch {} to {}/{}",
+            branch_name,
+            remote,
+            branch_name
+        )?;
+    }
+    Ok(0)
+}
@@ -1,34 +1,54 @@
 //! Implements the `git sync` command.
 
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::ffi::{OsStr, OsString};
 use std::fmt::Write;
 use std::time::SystemTime;
 
 use eden_dag::DagAlgorithm;
 use itertools::Itertools;
 use rayon::ThreadPoolBuilder;
+use tracing::instrument;
 
-use crate::core::config::get_restack_preserve_timestamps;
-use crate::core::dag::{resolve_commits, sort_commit_set, CommitSet, Dag, ResolveCommitsResult};
+use crate::commands::BranchlessExitCode;
+use crate::core::config::{get_main_branch_name, get_restack_preserve_timestamps};
+use crate::core::dag::{
+    commit_set_to_vec, resolve_commits, sort_commit_set, CommitSet, Dag, ResolveCommitsResult,
+};
 use crate::core::effects::{Effects, OperationType};
-use crate::core::eventlog::{EventLogDb, EventReplayer};
-use crate::core::formatting::{printable_styled_string, Glyphs, StyledStringBuilder};
+use crate::core::eventlog::{Event, EventLogDb, EventReplayer, LANDED_UPSTREAM_HIDE_MESSAGE};
+use crate::core::formatting::{printable_styled_string, Glyphs, Pluralize, StyledStringBuilder};
+use crate::core::patch_ids::PatchIdsDb;
 use crate::core::rewrite::{
     execute_rebase_plan, BuildRebasePlanError, BuildRebasePlanOptions, ExecuteRebasePlanOptions,
     ExecuteRebasePlanResult, RebasePlan, RebasePlanBuilder, RepoResource,
 };
-use crate::git::{CheckOutCommitOptions, Commit, GitRunInfo, NonZeroOid, Repo};
+use crate::git::{
+    CategorizedReferenceName, CheckOutCommitOptions, Commit, GitRunInfo, MaybeZeroOid, NonZeroOid,
+    PatchId, Repo, RepoReferencesSnapshot,
+};
 use crate::opts::MoveOptions;
 
-fn get_stack_roots(dag: &Dag) -> eyre::Result<CommitSet> {
+/// Get the commits which are already part of the history being synced onto,
+/// and so shouldn't be moved: the public commits, along with the ancestors of
+/// `destination_oid`.
+fn get_upstream_commits(dag: &Dag, destination_oid: NonZeroOid) -> eyre::Result<CommitSet> {
     let public_commits = dag.query_public_commits()?;
+    let destination_commits = dag.query().ancestors(CommitSet::from(destination_oid))?;
+    Ok(public_commits.union(&destination_commits))
+}
+
+fn get_stack_roots(dag: &Dag, destination_oid: NonZeroOid) -> eyre::Result<CommitSet> {
+    let upstream_commits = get_upstream_commits(dag, destination_oid)?;
     let active_heads = dag.query_active_heads(
-        &public_commits,
+        &upstream_commits,
         &dag.observed_commits.difference(&dag.obsolete_commits),
     )?;
     let draft_commits = dag
         .query()
-        .range(public_commits.clone(), active_heads)?
-        .difference(&public_commits);
+        .range(upstream_commits.clone(), active_heads)?
+        .difference(&upstream_commits);
 
     // FIXME: if two draft roots are ancestors of a single commit (due to a
     // merge commit), then the entire unit should be treated as one stack and
@@ -37,19 +57,116 @@ fn get_stack_roots(dag: &Dag) -> eyre::Result<CommitSet> {
     Ok(draft_roots)
 }
 
-/// Move all commit stacks on top of the main branch.
+/// Find the visible draft commits whose patch IDs match those of commits in
+/// the history of `destination_oid` (usually the main branch) which aren't
+/// their ancestors. Returns each such draft commit along with the
+/// corresponding upstream commit.
+#[instrument]
+fn find_landed_commits(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &Dag,
+    patch_ids_db: &PatchIdsDb,
+    destination_oid: NonZeroOid,
+) -> eyre::Result<Vec<(NonZeroOid, NonZeroOid)>> {
+    let draft_commits = dag
+        .observed_commits
+        .difference(&dag.obsolete_commits)
+        .difference(&get_upstream_commits(dag, destination_oid)?);
+    if draft_commits.is_empty()? {
+        return Ok(Vec::new());
+    }
+    let upstream_commits = dag
+        .query()
+        .only(CommitSet::from(destination_oid), draft_commits.clone())?;
+
+    let (effects, progress) = effects.start_operation(OperationType::GetUpstreamPatchIds);
+    let draft_commit_oids = commit_set_to_vec(&draft_commits)?;
+    let upstream_commit_oids = commit_set_to_vec(&upstream_commits)?;
+    progress.notify_progress(0, draft_commit_oids.len() + upstream_commit_oids.len());
+
+    let mut draft_patch_ids: HashMap<PatchId, Vec<NonZeroOid>> = HashMap::new();
+    for draft_commit_oid in draft_commit_oids {
+        let commit = repo.find_commit_or_fail(draft_commit_oid)?;
+        if let Some(patch_id) = patch_ids_db.get_patch_id(&effects, repo, &commit)? {
+            draft_patch_ids
+                .entry(patch_id)
+                .or_default()
+                .push(draft_commit_oid);
+        }
+        progress.notify_progress_inc(1);
+    }
+
+    let mut result = Vec::new();
+    for upstream_commit_oid in upstream_commit_oids {
+        let commit = repo.find_commit_or_fail(upstream_commit_oid)?;
+        if let Some(patch_id) = patch_ids_db.get_patch_id(&effects, repo, &commit)? {
+            if let Some(draft_commit_oids) = draft_patch_ids.remove(&patch_id) {
+                result.extend(
+                    draft_commit_oids
+                        .into_iter()
+                        .map(|draft_commit_oid| (draft_commit_oid, upstream_commit_oid)),
+                );
+            }
+        }
+        progress.notify_progress_inc(1);
+    }
+    result.sort();
+    Ok(result)
+}
+
+/// Find the local branches which point to commits on the main branch, and so
+/// have been fully merged into it. The main branch itself and the branches
+/// checked out in any worktree are never included. Returns each branch's
+/// reference name along with the commit it points to, sorted by name.
+#[instrument]
+fn find_merged_branches(
+    repo: &Repo,
+    dag: &Dag,
+    references_snapshot: &RepoReferencesSnapshot,
+) -> eyre::Result<Vec<(OsString, NonZeroOid)>> {
+    let public_commits = dag.query_public_commits()?;
+    let main_branch_reference_name = format!("refs/heads/{}", get_main_branch_name(repo)?);
+    let checked_out_branch_names = repo.get_checked_out_branch_names()?;
+
+    let mut result = Vec::new();
+    for (oid, names) in references_snapshot.branch_oid_to_names.iter() {
+        if !public_commits.contains(&(*oid).into())? {
+            continue;
+        }
+        for name in names {
+            let is_local_branch = matches!(
+                CategorizedReferenceName::new(name),
+                CategorizedReferenceName::LocalBranch { .. }
+            );
+            if is_local_branch
+                && name.as_os_str() != OsStr::new(&main_branch_reference_name)
+                && !checked_out_branch_names.contains(name)
+            {
+                result.push((name.clone(), *oid));
+            }
+        }
+    }
+    result.sort();
+    Ok(result)
+}
+
+/// Move all commit stacks on top of the main branch, or on top of `onto` if
+/// provided.
 pub fn sync(
     effects: &Effects,
     git_run_info: &GitRunInfo,
     update_refs: bool,
     force: bool,
+    delete_merged: bool,
+    onto: Option<String>,
     move_options: &MoveOptions,
     commits: Vec<String>,
 ) -> eyre::Result<isize> {
     let glyphs = Glyphs::detect();
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
-    let event_log_db = EventLogDb::new(&conn)?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
     let now = SystemTime::now();
     let event_tx_id = event_log_db.make_transaction_id(now, "sync fetch")?;
 
@@ -71,15 +188,136 @@ pub fn sync(
         &references_snapshot,
     )?;
 
+    let destination_oid = match &onto {
+        None => references_snapshot.main_branch_oid,
+        Some(onto) => match repo.revparse_single_commit(onto)? {
+            Some(commit) => commit.get_oid(),
+            None => {
+                writeln!(effects.get_output_stream(), "Commit not found: {}", onto)?;
+                return Ok(BranchlessExitCode::UserError.into());
+            }
+        },
+    };
+
+    let patch_ids_db = PatchIdsDb::new(&conn)?;
+    let landed_commits = find_landed_commits(effects, &repo, &dag, &patch_ids_db, destination_oid)?;
+    let hid_landed_commits = !landed_commits.is_empty();
+    if hid_landed_commits {
+        let now = SystemTime::now();
+        let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+        let event_tx_id = event_log_db.make_transaction_id(now, "sync hide landed")?;
+        event_log_db.add_events(
+            landed_commits
+                .iter()
+                .map(|(commit_oid, _upstream_commit_oid)| Event::ObsoleteEvent {
+                    timestamp,
+                    event_tx_id,
+                    commit_oid: *commit_oid,
+                    message: Some(OsString::from(LANDED_UPSTREAM_HIDE_MESSAGE)),
+                })
+                .collect(),
+        )?;
+        for (commit_oid, upstream_commit_oid) in landed_commits {
+            let commit = repo.find_commit_or_fail(commit_oid)?;
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                printable_styled_string(
+                    &glyphs,
+                    StyledStringBuilder::new()
+                        .append_plain("Hid landed commit ")
+                        .append(commit.friendly_describe(&glyphs)?)
+                        .append_plain(format!(
+                            " (landed as {})",
+                            &upstream_commit_oid.to_string()[..8]
+                        ))
+                        .build()
+                )?
+            )?;
+        }
+
+        let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+        let event_cursor = event_replayer.make_default_cursor();
+        dag = Dag::open_and_sync(
+            effects,
+            &repo,
+            &event_replayer,
+            event_cursor,
+            &references_snapshot,
+        )?;
+    }
+
+    let merged_branches = find_merged_branches(&repo, &dag, &references_snapshot)?;
+    let deleted_merged_branches = delete_merged && !merged_branches.is_empty();
+    if deleted_merged_branches {
+        let now = SystemTime::now();
+        let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+        let event_tx_id = event_log_db.make_transaction_id(now, "sync delete merged")?;
+        for (branch_name, branch_oid) in merged_branches {
+            if let Some(mut reference) = repo.find_reference(&branch_name)? {
+                reference.delete()?;
+            }
+            // Deleting the reference directly doesn't invoke the reference
+            // transaction hook, so record the deletion ourselves, so that it
+            // can be undone.
+            event_log_db.add_events(vec![Event::RefUpdateEvent {
+                timestamp,
+                event_tx_id,
+                ref_name: branch_name.clone(),
+                old_oid: MaybeZeroOid::NonZero(branch_oid),
+                new_oid: MaybeZeroOid::Zero,
+                message: None,
+            }])?;
+
+            let commit = repo.find_commit_or_fail(branch_oid)?;
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                printable_styled_string(
+                    &glyphs,
+                    StyledStringBuilder::new()
+                        .append_plain("Deleted merged ")
+                        .append_plain(
+                            CategorizedReferenceName::new(&branch_name).friendly_describe()
+                        )
+                        .append_plain(" at ")
+                        .append(commit.friendly_describe(&glyphs)?)
+                        .build()
+                )?
+            )?;
+        }
+    } else if !merged_branches.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Found {}: {}",
+            Pluralize {
+                determiner: None,
+                amount: merged_branches.len().try_into()?,
+                unit: ("merged branch", "merged branches"),
+            },
+            merged_branches
+                .iter()
+                .map(
+                    |(branch_name, _branch_oid)| CategorizedReferenceName::new(branch_name)
+                        .render_suffix()
+                )
+                .join(", ")
+        )?;
+        writeln!(
+            effects.get_output_stream(),
+            "To delete them, run: git sync --delete-merged"
+        )?;
+    }
+
     let commits = match resolve_commits(effects, &repo, &mut dag, commits)? {
         ResolveCommitsResult::Ok { commits } => commits,
         ResolveCommitsResult::CommitNotFound { commit } => {
             writeln!(effects.get_output_stream(), "Commit not found: {}", commit)?;
-            return Ok(1);
+            return Ok(BranchlessExitCode::UserError.into());
         }
     };
     let root_commits = if commits.is_empty() {
-        get_stack_roots(&dag)?
+        get_stack_roots(&dag, destination_oid)?
     } else {
         let commits: CommitSet = commits.into_iter().map(|commit| commit.get_oid()).collect();
         dag.query().roots(commits)?
@@ -91,6 +329,7 @@ pub fn sync(
         force_on_disk,
         detect_duplicate_commits_via_patch_id,
         resolve_merge_conflicts,
+        sign_commits,
         dump_rebase_constraints,
         dump_rebase_plan,
     } = *move_options;
@@ -118,14 +357,11 @@ pub fn sync(
 
                         let only_parent_id =
                             root_commit.get_only_parent().map(|parent| parent.get_oid());
-                        if only_parent_id == Some(references_snapshot.main_branch_oid) && !force {
+                        if only_parent_id == Some(destination_oid) && !force {
                             return Ok(Ok((root_commit_oid, None)));
                         }
 
-                        builder.move_subtree(
-                            root_commit.get_oid(),
-                            references_snapshot.main_branch_oid,
-                        )?;
+                        builder.move_subtree(root_commit.get_oid(), destination_oid)?;
                         let rebase_plan = builder.build(
                             effects,
                             &pool,
@@ -149,7 +385,7 @@ pub fn sync(
             Ok(root_commit_and_plans) => root_commit_and_plans,
             Err(err) => {
                 err.describe(effects, &repo)?;
-                return Ok(1);
+                return Ok(BranchlessExitCode::UserError.into());
             }
         }
     };
@@ -163,6 +399,7 @@ pub fn sync(
         force_in_memory,
         force_on_disk,
         resolve_merge_conflicts,
+        sign_commits,
         check_out_commit_options: CheckOutCommitOptions {
             additional_args: &[],
             render_smartlog: false,
@@ -211,6 +448,10 @@ pub fn sync(
         (success_commits, merge_conflict_commits, skipped_commits)
     };
 
+    let nothing_to_do = !hid_landed_commits
+        && !deleted_merged_branches
+        && success_commits.is_empty()
+        && merge_conflict_commits.is_empty();
     for success_commit in success_commits {
         writeln!(
             effects.get_output_stream(),
@@ -225,6 +466,10 @@ pub fn sync(
         )?;
     }
 
+    let merge_conflict_commit_oids = merge_conflict_commits
+        .iter()
+        .map(|commit| commit.get_oid().to_string())
+        .collect_vec();
     for merge_conflict_commit in merge_conflict_commits {
         writeln!(
             effects.get_output_stream(),
@@ -239,6 +484,18 @@ pub fn sync(
         )?;
     }
 
+    if !merge_conflict_commit_oids.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "To resolve the merge conflicts, run: git sync{} --merge {}",
+            match &onto {
+                Some(onto) => format!(" --onto {}", onto),
+                None => String::new(),
+            },
+            merge_conflict_commit_oids.join(" "),
+        )?;
+    }
+
     for skipped_commit in skipped_commits {
         writeln!(
             effects.get_output_stream(),
@@ -247,5 +504,11 @@ pub fn sync(
         )?;
     }
 
-    Ok(0)
+    if !merge_conflict_commit_oids.is_empty() {
+        Ok(BranchlessExitCode::MergeConflict.into())
+    } else if nothing_to_do {
+        Ok(BranchlessExitCode::NothingToDo.into())
+    } else {
+        Ok(BranchlessExitCode::Success.into())
+    }
 }
@@ -0,0 +1,253 @@
+//! Run a command on each commit in a range and report the results.
+//!
+//! Commits are checked out into a temporary worktree, so that the user's
+//! working copy is left untouched. The results are cached by the tree of each
+//! commit, so that commits whose contents haven't changed since they were last
+//! tested aren't tested again.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt::Write;
+use std::process::Stdio;
+
+use eden_dag::DagAlgorithm;
+use eyre::Context;
+use tracing::instrument;
+
+use crate::commands::smartlog::{make_smartlog_graph, render_graph, SubjectLayout};
+use crate::commands::BranchlessExitCode;
+use crate::core::dag::{
+    resolve_commit_range, resolve_commits, sort_commit_set, CommitSet, Dag, ResolveCommitsResult,
+};
+use crate::core::effects::{Effects, OperationType};
+use crate::core::eventlog::{EventLogDb, EventReplayer};
+use crate::core::formatting::{printable_styled_string, Pluralize};
+use crate::core::node_descriptors::{
+    BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor, NodeDescriptor, Redactor,
+    TestResultDescriptor,
+};
+use crate::core::test_results::{TestResult, TestResultsDb};
+use crate::git::{Commit, GitRunInfo, NonZeroOid, Repo, TemporaryWorktree};
+
+/// Resolve a single commit provided by the user. Returns `Ok(None)` if the
+/// commit couldn't be found, after printing an error message.
+fn resolve_commit(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &mut Dag,
+    commit: &str,
+) -> eyre::Result<Option<CommitSet>> {
+    match resolve_commits(effects, repo, dag, vec![commit.to_string()])? {
+        ResolveCommitsResult::Ok { commits } => Ok(Some(
+            commits.into_iter().map(|commit| commit.get_oid()).collect(),
+        )),
+        ResolveCommitsResult::CommitNotFound { commit } => {
+            writeln!(effects.get_error_stream(), "Commit not found: {}", commit)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Resolve the user-provided commits or commit ranges (like `main..HEAD` or
+/// `main...HEAD`) into the set of commits to test. Returns `Ok(None)` if one
+/// of the commits couldn't be found, after printing an error message.
+#[instrument]
+fn resolve_test_commits(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &mut Dag,
+    commits: Vec<String>,
+) -> eyre::Result<Option<CommitSet>> {
+    if commits.is_empty() {
+        let head_commit = match resolve_commit(effects, repo, dag, "HEAD")? {
+            Some(head_commit) => head_commit,
+            None => return Ok(None),
+        };
+        let commit_set = dag
+            .query()
+            .only(head_commit, dag.main_branch_commit.clone())?;
+        return Ok(Some(commit_set));
+    }
+
+    let mut result = CommitSet::empty();
+    for commit in commits {
+        let commit_set = if commit.contains("..") {
+            match resolve_commit_range(effects, repo, dag, &commit)? {
+                Some(commit_set) => commit_set,
+                None => {
+                    writeln!(effects.get_error_stream(), "Commit not found: {}", commit)?;
+                    return Ok(None);
+                }
+            }
+        } else {
+            match resolve_commit(effects, repo, dag, &commit)? {
+                Some(commit_set) => commit_set,
+                None => return Ok(None),
+            }
+        };
+        result = result.union(&commit_set);
+    }
+    Ok(Some(result))
+}
+
+/// Run the given shell command in the worktree. If the command fails, its
+/// output is printed, since otherwise the user would have no way to tell why.
+#[instrument]
+fn run_command(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    worktree: &TemporaryWorktree,
+    commit: &Commit,
+    command: &str,
+) -> eyre::Result<TestResult> {
+    let output = git_run_info
+        .make_shell_command(command)?
+        .current_dir(worktree.get_path())
+        .stdin(Stdio::null())
+        .output()
+        .wrap_err_with(|| format!("Running command: {}", command))?;
+    let result = TestResult {
+        // If the command was terminated by a signal, treat it as a failure.
+        exit_code: output.status.code().unwrap_or(1),
+    };
+
+    if !result.is_passed() {
+        writeln!(
+            effects.get_output_stream(),
+            "Command failed with exit code {} on commit {}:",
+            result.exit_code,
+            printable_styled_string(
+                effects.get_glyphs(),
+                commit.friendly_describe(effects.get_glyphs())?
+            )?
+        )?;
+        write!(
+            effects.get_output_stream(),
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        )?;
+    }
+    Ok(result)
+}
+
+/// Run `command` on each of the provided commits, or the commits between the
+/// main branch and `HEAD` if none are provided.
+#[instrument]
+pub fn run(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    command: &str,
+    commits: Vec<String>,
+) -> eyre::Result<isize> {
+    let repo = Repo::from_current_dir()?;
+    let head_info = repo.get_head_info()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let test_results_db = TestResultsDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commit_set = match resolve_test_commits(effects, &repo, &mut dag, commits)? {
+        Some(commit_set) => commit_set,
+        None => return Ok(BranchlessExitCode::UserError.into()),
+    };
+    let commits = sort_commit_set(&repo, &dag, &commit_set)?;
+    if commits.is_empty() {
+        writeln!(effects.get_output_stream(), "No commits to test.")?;
+        return Ok(0);
+    }
+
+    let mut results: HashMap<NonZeroOid, TestResult> = HashMap::new();
+    {
+        let (effects, progress) = effects.start_operation(OperationType::RunTests);
+        progress.notify_progress(0, commits.len());
+        let mut worktree: Option<TemporaryWorktree> = None;
+        for commit in commits.iter() {
+            let tree_oid = commit.get_tree()?.get_oid();
+            let result = match test_results_db.get_result(command, tree_oid)? {
+                Some(result) => result,
+                None => {
+                    let worktree = match &worktree {
+                        Some(worktree) => worktree,
+                        None => worktree.insert(TemporaryWorktree::new(git_run_info, &repo)?),
+                    };
+                    worktree.check_out(commit.get_oid())?;
+                    let result = run_command(&effects, git_run_info, worktree, commit, command)?;
+                    test_results_db.set_result(command, tree_oid, result)?;
+                    result
+                }
+            };
+            results.insert(commit.get_oid(), result);
+            progress.notify_progress_inc(1);
+        }
+        if let Some(worktree) = worktree {
+            worktree.remove()?;
+        }
+    }
+
+    let graph = make_smartlog_graph(
+        effects,
+        &repo,
+        &dag,
+        &event_replayer,
+        event_cursor,
+        true,
+        false,
+        Some(&commit_set),
+        0,
+    )?;
+    let num_passed = results.values().filter(|result| result.is_passed()).count();
+    let num_failed = results.len() - num_passed;
+    let mut test_result_descriptor = TestResultDescriptor::new(results)?;
+    let mut commit_oid_descriptor = CommitOidDescriptor::new(true)?;
+    let mut branches_descriptor =
+        BranchesDescriptor::new(&repo, &head_info, &references_snapshot, &Redactor::Disabled)?;
+    let mut commit_message_descriptor = CommitMessageDescriptor::new(&Redactor::Disabled)?;
+    let lines = render_graph(
+        effects,
+        &repo,
+        &dag,
+        &graph,
+        references_snapshot.head_oid,
+        &mut [
+            &mut test_result_descriptor as &mut dyn NodeDescriptor,
+            &mut commit_oid_descriptor,
+            &mut branches_descriptor,
+            &mut commit_message_descriptor,
+        ],
+        &SubjectLayout::default(),
+    )?;
+    for line in lines {
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            printable_styled_string(effects.get_glyphs(), line)?
+        )?;
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Tested {}: {} passed, {} failed.",
+        Pluralize {
+            determiner: None,
+            amount: commits.len().try_into()?,
+            unit: ("commit", "commits"),
+        },
+        num_passed,
+        num_failed,
+    )?;
+    if num_failed > 0 {
+        Ok(BranchlessExitCode::TestFailed.into())
+    } else {
+        Ok(0)
+    }
+}
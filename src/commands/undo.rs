@@ -3,8 +3,9 @@
 //! This is accomplished by finding the events that have happened since a certain
 //! time and inverting them.
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryInto;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use std::fmt::Write;
 use std::io::{stdin, BufRead, BufReader, Read};
 use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
@@ -18,10 +19,14 @@ use cursive::{Cursive, CursiveRunnable, CursiveRunner};
 use eyre::Context;
 use tracing::instrument;
 
-use crate::commands::smartlog::{make_smartlog_graph, render_graph};
-use crate::core::dag::Dag;
+use crate::commands::smartlog::{make_smartlog_graph, render_graph, SubjectLayout};
+use crate::commands::BranchlessExitCode;
+use crate::core::dag::{sort_commit_set, Dag};
 use crate::core::effects::Effects;
-use crate::core::eventlog::{Event, EventCursor, EventLogDb, EventReplayer, EventTransactionId};
+use crate::core::eventlog::{
+    is_head_ref, partition_events_by_ref, Event, EventCursor, EventLogDb, EventReplayer,
+    EventTransactionId,
+};
 use crate::core::formatting::{printable_styled_string, Glyphs, Pluralize, StyledStringBuilder};
 use crate::core::node_descriptors::{
     BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
@@ -31,7 +36,7 @@ use crate::core::node_descriptors::{
 use crate::declare_views;
 use crate::git::{
     check_out_commit, CategorizedReferenceName, CheckOutCommitOptions, GitRunInfo, MaybeZeroOid,
-    Repo, ResolvedReferenceInfo,
+    NonZeroOid, Repo, RepoReferencesSnapshot, ResolvedReferenceInfo,
 };
 use crate::tui::{with_siv, SingletonView};
 
@@ -45,13 +50,18 @@ fn render_cursor_smartlog(
     let dag = dag.set_cursor(effects, repo, event_replayer, event_cursor)?;
     let references_snapshot = event_replayer.get_references_snapshot(repo, event_cursor)?;
 
-    // FIXME: the information of which branch is checked out is not stored in
-    // the event log. For now, just pretend that `HEAD` was always detached in
-    // the past.
-    let head_info = ResolvedReferenceInfo {
-        oid: references_snapshot.head_oid,
-        reference_name: None,
-    };
+    // The information of which branch is checked out is not stored in the
+    // event log, so it's only known for the current state of the repository.
+    let (head_info, is_head_reference_known) =
+        if event_cursor == event_replayer.make_default_cursor() {
+            (repo.get_head_info()?, true)
+        } else {
+            let head_info = ResolvedReferenceInfo {
+                oid: references_snapshot.head_oid,
+                reference_name: None,
+            };
+            (head_info, false)
+        };
 
     let graph = make_smartlog_graph(
         effects,
@@ -61,6 +71,8 @@ fn render_cursor_smartlog(
         event_cursor,
         true,
         false,
+        None,
+        0,
     )?;
     let result = render_graph(
         effects,
@@ -71,25 +83,47 @@ fn render_cursor_smartlog(
         &mut [
             &mut CommitOidDescriptor::new(true)?,
             &mut RelativeTimeDescriptor::new(repo, SystemTime::now())?,
-            &mut ObsolescenceExplanationDescriptor::new(event_replayer, event_cursor)?,
+            &mut ObsolescenceExplanationDescriptor::new(repo, event_replayer, event_cursor)?,
             &mut BranchesDescriptor::new(
                 repo,
                 &head_info,
                 &references_snapshot,
                 &Redactor::Disabled,
-            )?,
+            )?
+            .is_head_reference_known(is_head_reference_known),
             &mut DifferentialRevisionDescriptor::new(repo, &Redactor::Disabled)?,
             &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
         ],
+        &SubjectLayout::default(),
     )?;
     Ok(result)
 }
 
+/// If the given commit was committed by someone other than its author (for
+/// example, because it was rebased by another user), describe the original
+/// author. Otherwise, return an empty string.
+fn describe_original_author(repo: &Repo, commit_oid: NonZeroOid) -> eyre::Result<String> {
+    let commit = match repo.find_commit(commit_oid)? {
+        Some(commit) => commit,
+        None => return Ok(String::new()),
+    };
+    let author = commit.get_author();
+    let committer = commit.get_committer();
+    if author.get_name() == committer.get_name() && author.get_email() == committer.get_email() {
+        return Ok(String::new());
+    }
+    match author.friendly_describe() {
+        Some(author) => Ok(format!(" (originally by {})", author)),
+        None => Ok(String::new()),
+    }
+}
+
 fn describe_event(glyphs: &Glyphs, repo: &Repo, event: &Event) -> eyre::Result<Vec<StyledString>> {
     // Links to https://github.com/arxanas/git-branchless/issues/57
     const EMPTY_EVENT_MESSAGE: &str =
         "This may be an unsupported use-case; see https://git.io/J0b7z";
 
+    let head_reference_name = repo.get_head_reference_name();
     let result = match event {
         Event::CommitEvent {
             timestamp: _,
@@ -109,6 +143,7 @@ fn describe_event(glyphs: &Glyphs, repo: &Repo, event: &Event) -> eyre::Result<V
             timestamp: _,
             event_tx_id: _,
             commit_oid,
+            message: _,
         }
         | Event::RewriteEvent {
             timestamp: _,
@@ -152,7 +187,7 @@ fn describe_event(glyphs: &Glyphs, repo: &Repo, event: &Event) -> eyre::Result<V
             old_oid: MaybeZeroOid::Zero,
             new_oid: MaybeZeroOid::NonZero(new_oid),
             message: _,
-        } if ref_name == "HEAD" => {
+        } if *ref_name == head_reference_name => {
             // Not sure if this can happen. When a repo is created, maybe?
             vec![
                 StyledStringBuilder::new()
@@ -170,7 +205,7 @@ fn describe_event(glyphs: &Glyphs, repo: &Repo, event: &Event) -> eyre::Result<V
             old_oid: MaybeZeroOid::NonZero(old_oid),
             new_oid: MaybeZeroOid::NonZero(new_oid),
             message: _,
-        } if ref_name == "HEAD" => {
+        } if *ref_name == head_reference_name => {
             vec![
                 StyledStringBuilder::new()
                     .append_plain("Check out from ")
@@ -279,6 +314,7 @@ fn describe_event(glyphs: &Glyphs, repo: &Repo, event: &Event) -> eyre::Result<V
                 StyledStringBuilder::new()
                     .append_plain("           as ")
                     .append(repo.friendly_describe_commit_from_oid(glyphs, *new_commit_oid)?)
+                    .append_plain(describe_original_author(repo, *new_commit_oid)?)
                     .build(),
             ]
         }
@@ -298,6 +334,26 @@ fn describe_event(glyphs: &Glyphs, repo: &Repo, event: &Event) -> eyre::Result<V
                     .build(),
             ]
         }
+
+        Event::WorkingCopySnapshot {
+            timestamp: _,
+            event_tx_id: _,
+            head_oid: _,
+            commit_oid,
+        } => {
+            vec![
+                StyledStringBuilder::new()
+                    .append_plain("Snapshot working copy as ")
+                    .append(repo.friendly_describe_commit_from_oid(glyphs, *commit_oid)?)
+                    .build(),
+                StyledStringBuilder::new()
+                    .append_plain(format!(
+                        "(restore with: git branchless restore {})",
+                        commit_oid
+                    ))
+                    .build(),
+            ]
+        }
     };
     Ok(result)
 }
@@ -342,6 +398,7 @@ fn select_past_event(
         Previous,
         GoToEvent,
         SetEventReplayerCursor { event_id: isize },
+        ToggleBefore,
         Help,
         Quit,
         SelectEventIdAndQuit,
@@ -352,9 +409,13 @@ fn select_past_event(
         ('n'.into(), Message::Next),
         ('N'.into(), Message::Next),
         (Key::Right.into(), Message::Next),
+        (Key::Down.into(), Message::Next),
         ('p'.into(), Message::Previous),
         ('P'.into(), Message::Previous),
         (Key::Left.into(), Message::Previous),
+        (Key::Up.into(), Message::Previous),
+        ('b'.into(), Message::ToggleBefore),
+        ('B'.into(), Message::ToggleBefore),
         ('h'.into(), Message::Help),
         ('H'.into(), Message::Help),
         ('?'.into(), Message::Help),
@@ -377,6 +438,9 @@ fn select_past_event(
     });
 
     let mut cursor = event_replayer.make_default_cursor();
+    // Whether to preview the repository as it was before the selected
+    // transaction, rather than after it.
+    let mut show_before = false;
     let now = SystemTime::now();
     main_tx.send(Message::Init)?;
     while siv.is_running() {
@@ -396,10 +460,16 @@ fn select_past_event(
 
         let redraw = |siv: &mut Cursive,
                       event_replayer: &mut EventReplayer,
-                      event_cursor: EventCursor|
+                      event_cursor: EventCursor,
+                      show_before: bool|
          -> eyre::Result<()> {
+            let smartlog_cursor = if show_before {
+                event_replayer.advance_cursor_by_transaction(event_cursor, -1)
+            } else {
+                event_cursor
+            };
             let smartlog =
-                render_cursor_smartlog(effects, repo, dag, event_replayer, event_cursor)?;
+                render_cursor_smartlog(effects, repo, dag, event_replayer, smartlog_cursor)?;
             SmartlogView::find(siv)
                 .get_inner_mut()
                 .set_content(StyledStringBuilder::from_lines(smartlog));
@@ -426,7 +496,11 @@ fn select_past_event(
                     };
 
                     let mut lines = vec![StyledStringBuilder::new()
-                        .append_plain("Repo after transaction ")
+                        .append_plain(if show_before {
+                            "Repo before transaction "
+                        } else {
+                            "Repo after transaction "
+                        })
                         .append_plain(events[0].get_event_tx_id().to_string())
                         .append_plain(" (event ")
                         .append_plain(event_id.to_string())
@@ -464,22 +538,27 @@ fn select_past_event(
                         .child(Panel::new(ScrollView::new(info_view)).title("Events"))
                         .full_width(),
                 );
-                redraw(&mut siv, event_replayer, cursor)?;
+                redraw(&mut siv, event_replayer, cursor, show_before)?;
             }
 
             Ok(Message::Next) => {
                 cursor = event_replayer.advance_cursor_by_transaction(cursor, 1);
-                redraw(&mut siv, event_replayer, cursor)?;
+                redraw(&mut siv, event_replayer, cursor, show_before)?;
             }
 
             Ok(Message::Previous) => {
                 cursor = event_replayer.advance_cursor_by_transaction(cursor, -1);
-                redraw(&mut siv, event_replayer, cursor)?;
+                redraw(&mut siv, event_replayer, cursor, show_before)?;
+            }
+
+            Ok(Message::ToggleBefore) => {
+                show_before = !show_before;
+                redraw(&mut siv, event_replayer, cursor, show_before)?;
             }
 
             Ok(Message::SetEventReplayerCursor { event_id }) => {
                 cursor = event_replayer.make_cursor(event_id);
-                redraw(&mut siv, event_replayer, cursor)?;
+                redraw(&mut siv, event_replayer, cursor, show_before)?;
             }
 
             Ok(Message::GoToEvent) => {
@@ -521,9 +600,10 @@ fn select_past_event(
 
 h/?: Show this help.
 q: Quit.
-p/n or <left>/<right>: View next/previous state.
+p/n or <left>/<right> or <up>/<down>: View previous/next state.
+b: Toggle between viewing the state after and before the selected transaction.
 g: Go to a provided event ID.
-<enter>: Revert the repository to the given state (requires confirmation).
+<enter>: Revert the repository to the viewed state (requires confirmation).
 
 You can also copy a commit hash from the past and manually run `git unhide` or `git rebase` on it.
 ",
@@ -536,6 +616,9 @@ You can also copy a commit hash from the past and manually run `git unhide` or `
 
             Ok(Message::SelectEventIdAndQuit) => {
                 siv.quit();
+                if show_before {
+                    cursor = event_replayer.advance_cursor_by_transaction(cursor, -1);
+                }
                 return Ok(Some(cursor));
             }
         };
@@ -549,6 +632,7 @@ You can also copy a commit hash from the past and manually run `git unhide` or `
 }
 
 fn inverse_event(
+    event_replayer: &EventReplayer,
     event: Event,
     now: SystemTime,
     event_tx_id: EventTransactionId,
@@ -559,21 +643,36 @@ fn inverse_event(
             timestamp: _,
             event_tx_id: _,
             commit_oid,
-        }
-        | Event::UnobsoleteEvent {
-            timestamp: _,
-            event_tx_id: _,
-            commit_oid,
         } => Event::ObsoleteEvent {
             timestamp,
             event_tx_id,
             commit_oid,
+            message: None,
         },
 
+        Event::UnobsoleteEvent {
+            timestamp: _,
+            event_tx_id: _,
+            commit_oid,
+        } => {
+            // Restore the message from when the commit was last hidden, so
+            // that it survives being unhidden and hidden again by undo/redo.
+            let message = event_replayer
+                .get_cursor_commit_hide_message(event_replayer.make_default_cursor(), commit_oid)
+                .map(|message| message.to_owned());
+            Event::ObsoleteEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid,
+                message,
+            }
+        }
+
         Event::ObsoleteEvent {
             timestamp: _,
             event_tx_id: _,
             commit_oid,
+            message: _,
         } => Event::UnobsoleteEvent {
             timestamp,
             event_tx_id,
@@ -607,16 +706,30 @@ fn inverse_event(
             new_oid: old_ref,
             message: None,
         },
+
+        // Taking a snapshot doesn't modify the repository, so there's nothing
+        // to invert.
+        Event::WorkingCopySnapshot {
+            timestamp: _,
+            event_tx_id: _,
+            head_oid,
+            commit_oid,
+        } => Event::WorkingCopySnapshot {
+            timestamp,
+            event_tx_id,
+            head_oid,
+            commit_oid,
+        },
     };
     Ok(inverse_event)
 }
 
-fn optimize_inverse_events(events: Vec<Event>) -> Vec<Event> {
+fn optimize_inverse_events(events: Vec<Event>, head_reference_name: &OsStr) -> Vec<Event> {
     let mut optimized_events = Vec::new();
     let mut seen_checkout = false;
     for event in events.into_iter().rev() {
         match event {
-            Event::RefUpdateEvent { ref ref_name, .. } if ref_name == "HEAD" => {
+            Event::RefUpdateEvent { ref ref_name, .. } if ref_name == head_reference_name => {
                 if seen_checkout {
                     continue;
                 } else {
@@ -640,9 +753,12 @@ fn undo_events(
     event_log_db: &mut EventLogDb,
     event_replayer: &EventReplayer,
     event_cursor: EventCursor,
+    message: &str,
+    only_ref_name: Option<&OsStr>,
 ) -> eyre::Result<isize> {
     let now = SystemTime::now();
-    let event_tx_id = event_log_db.make_transaction_id(now, "undo")?;
+    let event_tx_id = event_log_db.make_transaction_id(now, message)?;
+    let head_reference_name = repo.get_head_reference_name();
     let inverse_events: Vec<Event> = event_replayer
         .get_events_since_cursor(event_cursor)
         .iter()
@@ -657,19 +773,44 @@ fn undo_events(
                     old_oid: MaybeZeroOid::Zero,
                     new_oid: _,
                     message: _,
-                } if ref_name == "HEAD"
-            )
+                } if *ref_name == head_reference_name
+            ) && !matches!(
+                // Checkouts in other worktrees can't be undone from this one.
+                event,
+                Event::RefUpdateEvent { ref_name, .. }
+                    if is_head_ref(ref_name) && *ref_name != head_reference_name
+            ) && !matches!(event, Event::WorkingCopySnapshot { .. })
         })
-        .map(|event| inverse_event(event.clone(), now, event_tx_id))
+        .map(|event| inverse_event(event_replayer, event.clone(), now, event_tx_id))
         .collect::<eyre::Result<Vec<Event>>>()?;
-    let mut inverse_events = optimize_inverse_events(inverse_events);
+    let inverse_events = match only_ref_name {
+        None => inverse_events,
+        Some(only_ref_name) => {
+            let (inverse_events, skipped_events) =
+                partition_events_by_ref(inverse_events, only_ref_name);
+            if !skipped_events.is_empty() {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Skipping {} which don't affect {}.",
+                    Pluralize {
+                        determiner: None,
+                        amount: skipped_events.len().try_into().unwrap(),
+                        unit: ("inverse event", "inverse events"),
+                    },
+                    CategorizedReferenceName::new(only_ref_name).friendly_describe()
+                )?;
+            }
+            inverse_events
+        }
+    };
+    let mut inverse_events = optimize_inverse_events(inverse_events, &head_reference_name);
 
     // Move any checkout operations to be first. Otherwise, we have the risk
     // that `HEAD` is a symbolic reference pointing to another reference, and we
     // update that reference. This would cause the working copy to become dirty
     // from Git's perspective.
     inverse_events.sort_by_key(|event| match event {
-        Event::RefUpdateEvent { ref_name, .. } if ref_name == "HEAD" => 0,
+        Event::RefUpdateEvent { ref_name, .. } if *ref_name == head_reference_name => 0,
         _ => 1,
     });
 
@@ -678,21 +819,24 @@ fn undo_events(
             effects.get_output_stream(),
             "No undo actions to apply, exiting."
         )?;
-        return Ok(0);
+        return Ok(BranchlessExitCode::NothingToDo.into());
     }
 
-    writeln!(effects.get_output_stream(), "Will apply these actions:")?;
+    writeln!(
+        effects.get_primary_output_stream(),
+        "Will apply these actions:"
+    )?;
     let events = describe_events_numbered(effects.get_glyphs(), repo, &inverse_events)?;
     for line in events {
         writeln!(
-            effects.get_output_stream(),
+            effects.get_primary_output_stream(),
             "{}",
             printable_styled_string(effects.get_glyphs(), line)?
         )?;
     }
 
     let confirmed = {
-        write!(effects.get_output_stream(), "Confirm? [yN] ")?;
+        write!(effects.get_primary_output_stream(), "Confirm? [yN] ")?;
         let mut user_input = String::new();
         let mut reader = BufReader::new(in_);
         match reader.read_line(&mut user_input) {
@@ -704,8 +848,8 @@ fn undo_events(
         }
     };
     if !confirmed {
-        writeln!(effects.get_output_stream(), "Aborted.")?;
-        return Ok(1);
+        writeln!(effects.get_primary_output_stream(), "Aborted.")?;
+        return Ok(BranchlessExitCode::UserError.into());
     }
 
     let num_inverse_events = Pluralize {
@@ -717,6 +861,7 @@ fn undo_events(
 
     let mut result = 0;
     for event in inverse_events.into_iter() {
+        let applied_event = event.clone();
         match event {
             Event::RefUpdateEvent {
                 timestamp: _,
@@ -725,7 +870,7 @@ fn undo_events(
                 old_oid: _,
                 new_oid: MaybeZeroOid::NonZero(new_ref),
                 message: _,
-            } if ref_name == "HEAD" => {
+            } if *ref_name == head_reference_name => {
                 let target_oid: OsString = new_ref.to_string().into();
                 // Most likely the user wanted to perform an actual checkout in
                 // this case, rather than just update `HEAD` (and be left with a
@@ -766,6 +911,12 @@ fn undo_events(
             } => match repo.find_reference(&ref_name)? {
                 Some(mut reference) => {
                     reference.delete().wrap_err("Applying `RefUpdateEvent`")?;
+                    event_log_db.add_events(vec![applied_event])?;
+                    writeln!(
+                        effects.get_verbose_stream(),
+                        "Deleted {}",
+                        CategorizedReferenceName::new(&ref_name).friendly_describe()
+                    )?;
                 }
                 None => {
                     writeln!(
@@ -791,8 +942,18 @@ fn undo_events(
                 new_oid: MaybeZeroOid::NonZero(new_oid),
                 message: _,
             } => {
-                // Create or update the given reference.
+                // Create or update the given reference. The reference
+                // transaction hooks aren't invoked for this update, so record
+                // it in the event log ourselves, so that the undo itself can be
+                // undone or redone.
                 repo.create_reference(&ref_name, new_oid, true, "branchless undo")?;
+                event_log_db.add_events(vec![applied_event])?;
+                writeln!(
+                    effects.get_verbose_stream(),
+                    "Moved {} to {}",
+                    CategorizedReferenceName::new(&ref_name).friendly_describe(),
+                    new_oid
+                )?;
             }
             Event::CommitEvent { .. }
             | Event::ObsoleteEvent { .. }
@@ -800,6 +961,9 @@ fn undo_events(
             | Event::RewriteEvent { .. } => {
                 event_log_db.add_events(vec![event])?;
             }
+            Event::WorkingCopySnapshot { .. } => {
+                // Snapshots are kept around so that they can be restored.
+            }
         }
     }
 
@@ -811,14 +975,258 @@ fn undo_events(
     Ok(result)
 }
 
+/// The transaction message used for non-interactive undo operations.
+const UNDO_MESSAGE: &str = "undo";
+
+/// The transaction message used for interactive undo operations.
+const UNDO_INTERACTIVE_MESSAGE: &str = "undo interactive";
+
+/// The transaction message used for undo operations restricted to a single
+/// branch. These only partially revert a transaction, so they're treated like
+/// any other operation, rather than popping the undo stack.
+const UNDO_ONLY_MESSAGE: &str = "undo only";
+
+/// The transaction message used for redo operations.
+const REDO_MESSAGE: &str = "redo";
+
+/// The states of the repository which `git undo` and `git redo` would return
+/// to, if any.
+#[derive(Debug, Default)]
+struct UndoRedoCursors {
+    /// The states to return to on successive undo operations. The last element
+    /// is the state to return to first.
+    undo_stack: Vec<EventCursor>,
+
+    /// The states to return to on successive redo operations. The last element
+    /// is the state to return to first.
+    redo_stack: Vec<EventCursor>,
+}
+
+/// Replay the transactions in the event log to determine the undo and redo
+/// stacks, in the same way as a typical text editor:
+///
+/// - Any operation other than `git undo` or `git redo` can be undone, and
+///   clears the redo stack.
+/// - `git undo` returns to the state before the most recent operation which
+///   hasn't already been undone, and makes that undo available to `git redo`.
+/// - `git redo` returns to the state before the most recent undo.
+///
+/// Interactive undo operations jump to an arbitrary state, so they're treated
+/// like any other operation.
+///
+/// The stacks are derived entirely from the event log, so they're shared
+/// between all terminals operating on the same repository. An operation
+/// carried out in another terminal counts as a new operation, so it clears the
+/// redo stack, and a subsequent `git undo` in any terminal undoes it first.
+#[instrument]
+fn get_undo_redo_cursors(
+    event_log_db: &EventLogDb,
+    event_replayer: &EventReplayer,
+) -> eyre::Result<UndoRedoCursors> {
+    let mut cursors = UndoRedoCursors::default();
+    let mut cursor = event_replayer.make_cursor(0);
+    loop {
+        let next_cursor = event_replayer.advance_cursor_by_transaction(cursor, 1);
+        if next_cursor == cursor {
+            break;
+        }
+        let message = match event_replayer.get_tx_events_before_cursor(next_cursor) {
+            Some((_event_id, events)) => {
+                event_log_db.get_transaction_message(events[0].get_event_tx_id())?
+            }
+            None => None,
+        };
+
+        let UndoRedoCursors {
+            undo_stack,
+            redo_stack,
+        } = &mut cursors;
+        match message.as_deref() {
+            Some(UNDO_MESSAGE) => {
+                undo_stack.pop();
+                redo_stack.push(cursor);
+            }
+            Some(REDO_MESSAGE) => {
+                redo_stack.pop();
+                undo_stack.push(cursor);
+            }
+            _ => {
+                undo_stack.push(cursor);
+                redo_stack.clear();
+            }
+        }
+        cursor = next_cursor;
+    }
+    Ok(cursors)
+}
+
+/// Get the location of each branch in the given references snapshot, keyed by
+/// reference name.
+fn get_branch_locations(
+    references_snapshot: &RepoReferencesSnapshot,
+) -> BTreeMap<OsString, NonZeroOid> {
+    references_snapshot
+        .branch_oid_to_names
+        .iter()
+        .flat_map(|(oid, names)| names.iter().map(move |name| (name.clone(), *oid)))
+        .collect()
+}
+
+/// Describe what would happen if the repository were restored to the state
+/// at `event_cursor`, without modifying the repository.
+#[instrument]
+fn preview_undo(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &Dag,
+    event_replayer: &EventReplayer,
+    event_cursor: EventCursor,
+) -> eyre::Result<()> {
+    let glyphs = effects.get_glyphs();
+    let current_cursor = event_replayer.make_default_cursor();
+    let current_references = event_replayer.get_references_snapshot(repo, current_cursor)?;
+    let target_references = event_replayer.get_references_snapshot(repo, event_cursor)?;
+
+    let mut lines: Vec<StyledString> = Vec::new();
+    if current_references.head_oid != target_references.head_oid {
+        if let Some(head_oid) = target_references.head_oid {
+            lines.push(
+                StyledStringBuilder::new()
+                    .append_plain("Check out ")
+                    .append(repo.friendly_describe_commit_from_oid(glyphs, head_oid)?)
+                    .build(),
+            );
+        }
+    }
+
+    let current_branches = get_branch_locations(&current_references);
+    let target_branches = get_branch_locations(&target_references);
+    let branch_names: BTreeSet<&OsString> = current_branches
+        .keys()
+        .chain(target_branches.keys())
+        .collect();
+    for branch_name in branch_names {
+        let branch_description = CategorizedReferenceName::new(branch_name).friendly_describe();
+        let line = match (
+            current_branches.get(branch_name),
+            target_branches.get(branch_name),
+        ) {
+            (None, None) => continue,
+            (Some(current_oid), Some(target_oid)) if current_oid == target_oid => continue,
+            (None, Some(target_oid)) => StyledStringBuilder::new()
+                .append_plain("Create ")
+                .append_plain(branch_description)
+                .append_plain(" at ")
+                .append(repo.friendly_describe_commit_from_oid(glyphs, *target_oid)?)
+                .build(),
+            (Some(current_oid), None) => StyledStringBuilder::new()
+                .append_plain("Delete ")
+                .append_plain(branch_description)
+                .append_plain(" at ")
+                .append(repo.friendly_describe_commit_from_oid(glyphs, *current_oid)?)
+                .build(),
+            (Some(_current_oid), Some(target_oid)) => StyledStringBuilder::new()
+                .append_plain("Move ")
+                .append_plain(branch_description)
+                .append_plain(" to ")
+                .append(repo.friendly_describe_commit_from_oid(glyphs, *target_oid)?)
+                .build(),
+        };
+        lines.push(line);
+    }
+
+    let current_dag = dag.set_cursor(effects, repo, event_replayer, current_cursor)?;
+    let target_dag = dag.set_cursor(effects, repo, event_replayer, event_cursor)?;
+    let current_visible_commits = current_dag
+        .observed_commits
+        .difference(&current_dag.obsolete_commits);
+    let target_visible_commits = target_dag
+        .observed_commits
+        .difference(&target_dag.obsolete_commits);
+    for (verb, commit_set) in [
+        (
+            "Unhide commit ",
+            target_visible_commits.difference(&current_visible_commits),
+        ),
+        (
+            "Hide commit ",
+            current_visible_commits.difference(&target_visible_commits),
+        ),
+    ] {
+        for commit in sort_commit_set(repo, dag, &commit_set)? {
+            lines.push(
+                StyledStringBuilder::new()
+                    .append_plain(verb)
+                    .append(commit.friendly_describe(glyphs)?)
+                    .build(),
+            );
+        }
+    }
+
+    if lines.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Undoing would not change any branches or commits."
+        )?;
+    } else {
+        writeln!(effects.get_output_stream(), "Undoing would:")?;
+        for line in lines {
+            writeln!(
+                effects.get_output_stream(),
+                "- {}",
+                printable_styled_string(glyphs, line)?
+            )?;
+        }
+    }
+
+    for (header, cursor) in [("Before:", current_cursor), ("After:", event_cursor)] {
+        writeln!(effects.get_output_stream(), "{}", header)?;
+        for line in render_cursor_smartlog(effects, repo, dag, event_replayer, cursor)? {
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                printable_styled_string(glyphs, line)?
+            )?;
+        }
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Preview only; no changes were made to the repository."
+    )?;
+    Ok(())
+}
+
 /// Restore the repository to a previous state interactively.
 #[instrument]
 pub fn undo(
     effects: &Effects,
     git_run_info: &GitRunInfo,
     interactive: bool,
+    preview: bool,
+    only_branch: Option<String>,
 ) -> eyre::Result<isize> {
     let repo = Repo::from_current_dir()?;
+    let only_ref_name = only_branch.map(|branch_name| {
+        if branch_name.starts_with("refs/") {
+            OsString::from(branch_name)
+        } else {
+            OsString::from(format!("refs/heads/{}", branch_name))
+        }
+    });
+    if let Some(only_ref_name) = &only_ref_name {
+        // Moving the checked-out branch out from under the working copy would
+        // leave it dirty, so require the user to check out something else first.
+        if repo.get_head_info()?.reference_name.as_deref() == Some(only_ref_name.as_os_str()) {
+            writeln!(
+                effects.get_output_stream(),
+                "Cannot undo changes to {} with --only while it is checked out. Check out a different commit first.",
+                CategorizedReferenceName::new(only_ref_name).friendly_describe()
+            )?;
+            return Ok(BranchlessExitCode::UserError.into());
+        }
+    }
+
     let references_snapshot = repo.get_references_snapshot()?;
     let conn = repo.get_db_conn()?;
     let mut event_log_db = EventLogDb::new(&conn)?;
@@ -837,17 +1245,76 @@ pub fn undo(
         )?
     };
 
-    let event_cursor = {
+    let (event_cursor, message) = {
         if interactive {
             let result = with_siv(effects, |effects, siv| {
                 select_past_event(siv, &effects, &repo, &dag, &mut event_replayer)
             })?;
             match result {
-                Some(event_cursor) => event_cursor,
-                None => return Ok(0),
+                Some(event_cursor) => (event_cursor, UNDO_INTERACTIVE_MESSAGE),
+                None => return Ok(BranchlessExitCode::UserError.into()),
             }
         } else {
-            event_replayer.advance_cursor_by_transaction(event_replayer.make_default_cursor(), -1)
+            let UndoRedoCursors {
+                undo_stack,
+                redo_stack: _,
+            } = get_undo_redo_cursors(&event_log_db, &event_replayer)?;
+            match undo_stack.last() {
+                Some(event_cursor) => (*event_cursor, UNDO_MESSAGE),
+                None => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "No operations to undo, exiting."
+                    )?;
+                    return Ok(BranchlessExitCode::NothingToDo.into());
+                }
+            }
+        }
+    };
+
+    if preview {
+        preview_undo(effects, &repo, &dag, &event_replayer, event_cursor)?;
+        return Ok(0);
+    }
+
+    let message = match only_ref_name {
+        Some(_) => UNDO_ONLY_MESSAGE,
+        None => message,
+    };
+    let result = undo_events(
+        &mut stdin(),
+        effects,
+        &repo,
+        git_run_info,
+        &mut event_log_db,
+        &event_replayer,
+        event_cursor,
+        message,
+        only_ref_name.as_deref(),
+    )?;
+    Ok(result)
+}
+
+/// Reapply the operation most recently reverted by `git undo`.
+#[instrument]
+pub fn redo(effects: &Effects, git_run_info: &GitRunInfo) -> eyre::Result<isize> {
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+
+    let UndoRedoCursors {
+        undo_stack: _,
+        redo_stack,
+    } = get_undo_redo_cursors(&event_log_db, &event_replayer)?;
+    let event_cursor = match redo_stack.last() {
+        Some(event_cursor) => *event_cursor,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "No undone operations to redo, exiting."
+            )?;
+            return Ok(BranchlessExitCode::NothingToDo.into());
         }
     };
 
@@ -859,6 +1326,8 @@ pub fn undo(
         &mut event_log_db,
         &event_replayer,
         event_cursor,
+        REDO_MESSAGE,
+        None,
     )?;
     Ok(result)
 }
@@ -901,6 +1370,8 @@ pub mod testing {
             event_log_db,
             event_replayer,
             event_cursor,
+            super::UNDO_INTERACTIVE_MESSAGE,
+            None,
         )
     }
 }
@@ -940,7 +1411,7 @@ mod tests {
             new_oid: MaybeZeroOid::NonZero("3".parse()?),
             message: None,
         }];
-        assert_eq!(optimize_inverse_events(input), expected);
+        assert_eq!(optimize_inverse_events(input, OsStr::new("HEAD")), expected);
         Ok(())
     }
 }
@@ -1,15 +1,20 @@
 //! Wrap a user-provided Git command, so that `git-branchless` can do special
 //! processing.
 
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::convert::TryInto;
-use std::process::Command;
+use std::ffi::OsString;
+use std::process::{Command, ExitStatus};
 use std::time::SystemTime;
 
 use eyre::Context;
 use itertools::Itertools;
 
-use crate::core::eventlog::{EventLogDb, EventTransactionId, BRANCHLESS_TRANSACTION_ID_ENV_VAR};
-use crate::git::{GitRunInfo, Repo};
+use crate::core::eventlog::{
+    should_ignore_ref_updates, Event, EventLogDb, EventTransactionId,
+    BRANCHLESS_TRANSACTION_ID_ENV_VAR,
+};
+use crate::git::{GitRunInfo, MaybeZeroOid, NonZeroOid, Repo};
 
 fn pass_through_git_command_inner(
     git_run_info: &GitRunInfo,
@@ -30,6 +35,20 @@ fn pass_through_git_command_inner(
         command.env(BRANCHLESS_TRANSACTION_ID_ENV_VAR, event_tx_id.to_string());
     }
     let exit_status = command.status().wrap_err("Running Git command")?;
+    get_exit_code(exit_status)
+}
+
+/// Get the exit code of the wrapped command, to be used as our own exit code.
+/// If the command was terminated by a signal, use the same exit code as a shell
+/// would (128 plus the signal number), rather than a generic failure code.
+fn get_exit_code(exit_status: ExitStatus) -> eyre::Result<isize> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = exit_status.signal() {
+            return Ok((128 + signal).try_into()?);
+        }
+    }
     let exit_code = exit_status.code().unwrap_or(1).try_into()?;
     Ok(exit_code)
 }
@@ -60,7 +79,76 @@ fn make_event_tx_id<S: AsRef<str> + std::fmt::Debug>(
     Ok(event_tx_id)
 }
 
+/// Get the locations of `HEAD` and the local branches, keyed by their full
+/// reference names.
+fn get_ref_locations(repo: &Repo) -> eyre::Result<HashMap<OsString, NonZeroOid>> {
+    let mut result = HashMap::new();
+    if let Some(head_oid) = repo.get_head_info()?.oid {
+        result.insert(repo.get_head_reference_name(), head_oid);
+    }
+    for (oid, names) in repo.get_branch_oid_to_names()? {
+        for name in names {
+            result.insert(name, oid);
+        }
+    }
+    Ok(result)
+}
+
+/// Record the reference updates made by the wrapped command which weren't
+/// already recorded by our hooks, such as if the command bypassed them.
+fn record_ref_updates(
+    event_tx_id: EventTransactionId,
+    ref_locations_before: &HashMap<OsString, NonZeroOid>,
+) -> eyre::Result<()> {
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let ref_locations_after = get_ref_locations(&repo)?;
+    let conn = repo.get_db_conn()?;
+    let mut event_log_db = EventLogDb::new(&conn)?;
+
+    let recorded_ref_updates: HashSet<(OsString, MaybeZeroOid)> = event_log_db
+        .get_events_for_transaction(event_tx_id)?
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::RefUpdateEvent {
+                ref_name, new_oid, ..
+            } => Some((ref_name, new_oid)),
+            _ => None,
+        })
+        .collect();
+
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let ref_names: BTreeSet<&OsString> = ref_locations_before
+        .keys()
+        .chain(ref_locations_after.keys())
+        .filter(|ref_name| !should_ignore_ref_updates(ref_name))
+        .collect();
+    let mut events = Vec::new();
+    for ref_name in ref_names {
+        let old_oid = MaybeZeroOid::from(ref_locations_before.get(ref_name).copied());
+        let new_oid = MaybeZeroOid::from(ref_locations_after.get(ref_name).copied());
+        if old_oid == new_oid || recorded_ref_updates.contains(&(ref_name.clone(), new_oid)) {
+            continue;
+        }
+        events.push(Event::RefUpdateEvent {
+            timestamp,
+            event_tx_id,
+            ref_name: ref_name.clone(),
+            old_oid,
+            new_oid,
+            message: None,
+        });
+    }
+    event_log_db.add_events(events)?;
+    Ok(())
+}
+
 /// Run the provided Git command, but wrapped in an event transaction.
+///
+/// The locations of `HEAD` and the local branches are recorded before and
+/// after running the command, so that any reference updates which weren't
+/// observed by our hooks are still added to the transaction, and can be undone
+/// with `git undo`.
 pub fn wrap<S: AsRef<str> + std::fmt::Debug>(
     git_run_info: &GitRunInfo,
     args: &[S],
@@ -68,7 +156,17 @@ pub fn wrap<S: AsRef<str> + std::fmt::Debug>(
     // We may not be able to make an event transaction ID (such as if there is
     // no repository in the current directory). Ignore the error in that case.
     let event_tx_id = make_event_tx_id(args).ok();
+    let ref_locations_before = match event_tx_id {
+        Some(_) => Repo::from_current_dir()
+            .and_then(|repo| get_ref_locations(&repo))
+            .ok(),
+        None => None,
+    };
 
     let exit_code = pass_through_git_command(git_run_info, args, event_tx_id)?;
+
+    if let (Some(event_tx_id), Some(ref_locations_before)) = (event_tx_id, ref_locations_before) {
+        record_ref_updates(event_tx_id, &ref_locations_before)?;
+    }
     Ok(exit_code)
 }
@@ -0,0 +1,93 @@
+//! Utilities for generating commit messages when combining or splitting
+//! commits, as used by `git fold`, `git autosquash`, and `git split`.
+//!
+//! A commit template (configured with `branchless.commitTemplate` or passed
+//! with `--commit-template`) may contain the following placeholders:
+//!
+//! - `%s`: the subject (first line) of the commit message.
+//! - `%b`: the body of the commit message (everything after the subject).
+//! - `%n`: a newline.
+//! - `%%`: a literal `%`.
+//!
+//! Any other `%` sequence is left as-is.
+
+/// Format a single commit message according to the given template.
+pub fn format_commit_message(template: &str, message: &str) -> String {
+    let message = message.trim();
+    let (subject, body) = match message.split_once('\n') {
+        Some((subject, body)) => (subject.trim_end(), body.trim()),
+        None => (message, ""),
+    };
+
+    let mut result = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('s') => result.push_str(subject),
+            Some('b') => result.push_str(body),
+            Some('n') => result.push('\n'),
+            Some('%') => result.push('%'),
+            _ => {
+                result.push('%');
+                continue;
+            }
+        }
+        chars.next();
+    }
+    result
+}
+
+/// Combine several commit messages into one. If a template is provided, each
+/// message is formatted with it before combining. The messages are separated
+/// by blank lines, and messages which are empty after formatting are skipped.
+pub fn combine_commit_messages(template: Option<&str>, messages: &[&str]) -> String {
+    let components: Vec<String> = messages
+        .iter()
+        .map(|message| match template {
+            Some(template) => format_commit_message(template, message),
+            None => message.to_string(),
+        })
+        .map(|message| message.trim().to_string())
+        .filter(|message| !message.is_empty())
+        .collect();
+    format!("{}\n", components.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_commit_message() {
+        assert_eq!(
+            format_commit_message("%s", "subject\n\nbody line\n"),
+            "subject"
+        );
+        assert_eq!(
+            format_commit_message("* %s%n%n%b", "subject\n\nbody line\n"),
+            "* subject\n\nbody line"
+        );
+        assert_eq!(format_commit_message("[%s] %b", "subject"), "[subject] ");
+        assert_eq!(format_commit_message("100%% %x %", "subject"), "100% %x %");
+    }
+
+    #[test]
+    fn test_combine_commit_messages() {
+        assert_eq!(
+            combine_commit_messages(None, &["first\n\nbody\n", "second\n"]),
+            "first\n\nbody\n\nsecond\n"
+        );
+        assert_eq!(
+            combine_commit_messages(Some("- %s"), &["first\n\nbody\n", "second\n"]),
+            "- first\n\n- second\n"
+        );
+        assert_eq!(
+            combine_commit_messages(Some("%b"), &["first\n\nbody\n", "second\n"]),
+            "body\n"
+        );
+    }
+}
@@ -1,32 +1,218 @@
 //! Accesses repo-specific configuration.
 
-use std::path::PathBuf;
+use std::convert::TryFrom;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 
+use eyre::Context;
 use tracing::instrument;
 
+use crate::core::eventlog::BRANCHLESS_REF_PREFIX;
+use crate::core::formatting::{parse_style, Abbrev, SmartlogTheme};
 use crate::git::{ConfigRead, Repo};
 
+/// Expand a leading `~` in `path` to `home_dir`, as Git does for paths in its
+/// configuration.
+pub fn expand_home_dir(path: PathBuf, home_dir: &Path) -> PathBuf {
+    // `Path::strip_prefix` compares whole components, so this handles both
+    // `~/hooks` and `~\hooks` on Windows.
+    match path.strip_prefix("~") {
+        Ok(relative_path) => home_dir.join(relative_path),
+        Err(_) => path,
+    }
+}
+
 /// Get the path where Git hooks are stored on disk.
+///
+/// As with Git, a leading `~` in `core.hooksPath` is expanded to the user's
+/// home directory, and a relative path is interpreted relative to the root of
+/// the working copy (or the `.git` directory for bare repositories), rather
+/// than the current directory.
 #[instrument]
 pub fn get_core_hooks_path(repo: &Repo) -> eyre::Result<PathBuf> {
-    repo.get_readonly_config()?
-        .get_or_else("core.hooksPath", || repo.get_path().join("hooks"))
+    let hooks_path: Option<PathBuf> = repo.get_readonly_config()?.get("core.hooksPath")?;
+    let hooks_path = match hooks_path {
+        Some(hooks_path) => hooks_path,
+        None => return Ok(repo.get_common_path().join("hooks")),
+    };
+
+    let hooks_path = match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        Some(home_dir) => expand_home_dir(hooks_path, Path::new(&home_dir)),
+        None => hooks_path,
+    };
+
+    if hooks_path.is_relative() {
+        let base_dir = repo
+            .get_working_copy_path()
+            .unwrap_or_else(|| repo.get_path());
+        return Ok(base_dir.join(hooks_path));
+    }
+
+    Ok(hooks_path)
 }
 
-/// Get the configured name of the main branch.
+/// Get the candidate names for the main branch, in order of preference.
+///
+/// If `branchless.core.mainBranch` is set, it's interpreted as a
+/// comma-separated list of branch names. Otherwise, the candidates are `main`,
+/// `master`, and the branch which `origin/HEAD` points to, if any.
 #[instrument]
-pub fn get_main_branch_name(repo: &Repo) -> eyre::Result<String> {
+pub fn get_main_branch_name_candidates(repo: &Repo) -> eyre::Result<Vec<String>> {
     let config = repo.get_readonly_config()?;
-    let main_branch_name: Option<String> = config.get("branchless.core.mainBranch")?;
-    let main_branch_name = match main_branch_name {
-        Some(main_branch_name) => main_branch_name,
+    let main_branch_name: Option<String> = match config.get("branchless.core.mainBranch")? {
+        Some(main_branch_name) => Some(main_branch_name),
         None => {
             // Deprecated; use `branchless.core.mainBranch` instead.
-            config
-                .get("branchless.mainBranch")?
-                .unwrap_or_else(|| "master".to_string())
+            config.get("branchless.mainBranch")?
         }
     };
+    if let Some(main_branch_name) = main_branch_name {
+        let candidates: Vec<String> = main_branch_name
+            .split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string())
+            .collect();
+        if !candidates.is_empty() {
+            return Ok(candidates);
+        }
+    }
+
+    let mut candidates = vec!["main".to_string(), "master".to_string()];
+    if let Some(reference) = repo.find_reference(OsStr::new("refs/remotes/origin/HEAD"))? {
+        let remote_head_name = repo
+            .resolve_reference(&reference)?
+            .reference_name
+            .and_then(|name| name.to_str().map(|name| name.to_string()));
+        if let Some(remote_head_name) = remote_head_name {
+            if let Some(branch_name) = remote_head_name.strip_prefix("refs/remotes/") {
+                if !candidates.iter().any(|candidate| candidate == branch_name) {
+                    candidates.push(branch_name.to_string());
+                }
+            }
+        }
+    }
+    Ok(candidates)
+}
+
+/// Get the name of the local branch to use as the main branch if none of the
+/// candidates from [`get_main_branch_name_candidates`] exist, such as when
+/// `branchless.core.mainBranch` names a remote-tracking branch which hasn't
+/// been fetched.
+#[instrument]
+pub fn get_main_branch_fallback_name(repo: &Repo) -> eyre::Result<Option<String>> {
+    let fallback_name: Option<String> = repo
+        .get_readonly_config()?
+        .get("branchless.core.mainBranchFallback")?;
+    Ok(fallback_name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty()))
+}
+
+/// Indicates that none of the candidates from
+/// [`get_main_branch_name_candidates`] exist, so that the caller can warn
+/// about it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MainBranchFallback {
+    /// The local branch from [`get_main_branch_fallback_name`] is used as the
+    /// main branch instead.
+    LocalBranch {
+        /// The configured candidates, none of which exist.
+        candidates: Vec<String>,
+
+        /// The name of the local branch which is used instead.
+        fallback_name: String,
+    },
+
+    /// There is no usable fallback, so the first candidate is used as the main
+    /// branch even though it doesn't exist.
+    NotFound {
+        /// The configured candidates, none of which exist.
+        candidates: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for MainBranchFallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let describe_candidates = |candidates: &[String]| {
+            candidates
+                .iter()
+                .map(|candidate| format!("{:?}", candidate))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            MainBranchFallback::LocalBranch {
+                candidates,
+                fallback_name,
+            } => write!(
+                f,
+                "main branch {} could not be found, falling back to local branch {:?}",
+                describe_candidates(candidates),
+                fallback_name,
+            ),
+            MainBranchFallback::NotFound { candidates } => write!(
+                f,
+                "main branch {} could not be found; set `branchless.core.mainBranch` to the name of your main branch",
+                describe_candidates(candidates),
+            ),
+        }
+    }
+}
+
+/// Config key recording that the hint for [`MainBranchFallback::NotFound`] has
+/// already been shown for this repository, so that it's only shown once.
+pub const MAIN_BRANCH_NOT_FOUND_HINT_CONFIG_KEY: &str = "branchless.hint.mainBranchNotFound";
+
+/// Get the name of the main branch. This is the first candidate from
+/// [`get_main_branch_name_candidates`] which exists as a local or remote
+/// branch. If none of them exist, the local branch from
+/// [`get_main_branch_fallback_name`] is used. Otherwise, the first candidate is
+/// used. In either case, the fallback is also returned so that the caller can
+/// warn about it.
+#[instrument]
+pub fn resolve_main_branch_name(repo: &Repo) -> eyre::Result<(String, Option<MainBranchFallback>)> {
+    let candidates = get_main_branch_name_candidates(repo)?;
+    for candidate in candidates.iter() {
+        if repo
+            .find_branch(candidate, git2::BranchType::Local)?
+            .is_some()
+            || repo
+                .find_branch(candidate, git2::BranchType::Remote)?
+                .is_some()
+        {
+            return Ok((candidate.clone(), None));
+        }
+    }
+
+    if let Some(fallback_name) = get_main_branch_fallback_name(repo)? {
+        if repo
+            .find_branch(&fallback_name, git2::BranchType::Local)?
+            .is_some()
+        {
+            let fallback = MainBranchFallback::LocalBranch {
+                candidates,
+                fallback_name: fallback_name.clone(),
+            };
+            return Ok((fallback_name, Some(fallback)));
+        }
+    }
+
+    let main_branch_name = candidates
+        .first()
+        .cloned()
+        .unwrap_or_else(|| "master".to_string());
+    Ok((
+        main_branch_name,
+        Some(MainBranchFallback::NotFound { candidates }),
+    ))
+}
+
+/// Get the name of the main branch, as determined by
+/// [`resolve_main_branch_name`].
+#[instrument]
+pub fn get_main_branch_name(repo: &Repo) -> eyre::Result<String> {
+    let (main_branch_name, _fallback) = resolve_main_branch_name(repo)?;
     Ok(main_branch_name)
 }
 
@@ -38,6 +224,50 @@ pub fn get_default_branch_name(repo: &Repo) -> eyre::Result<Option<String>> {
     Ok(default_branch_name)
 }
 
+/// What to do with a commit which becomes empty when it's rebased, such as
+/// because its changes were already applied upstream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RestackEmptyCommits {
+    /// Keep the empty commit.
+    Keep,
+
+    /// Drop the empty commit, and move its descendants onto its parent.
+    Drop,
+
+    /// Drop the empty commit as with `Drop`, and record that it was hidden
+    /// because it became empty.
+    Hide,
+}
+
+/// How to handle commits which become empty when rebased, according to
+/// `branchless.restack.emptyCommits`. Defaults to dropping them.
+#[instrument]
+pub fn get_restack_empty_commits(repo: &Repo) -> eyre::Result<RestackEmptyCommits> {
+    let empty_commits: Option<String> = repo
+        .get_readonly_config()?
+        .get("branchless.restack.emptyCommits")?;
+    match empty_commits.as_deref() {
+        None | Some("drop") => Ok(RestackEmptyCommits::Drop),
+        Some("keep") => Ok(RestackEmptyCommits::Keep),
+        Some("hide") => Ok(RestackEmptyCommits::Hide),
+        Some(other) => eyre::bail!(
+            "Invalid value for branchless.restack.emptyCommits: {:?} (expected one of: keep, drop, hide)",
+            other
+        ),
+    }
+}
+
+/// Get the template used to format commit messages generated by `git fold`,
+/// `git autosquash`, and `git split`, if any. See
+/// [`crate::core::commit_message`] for the supported placeholders.
+#[instrument]
+pub fn get_commit_template(repo: &Repo) -> eyre::Result<Option<String>> {
+    let template: Option<String> = repo
+        .get_readonly_config()?
+        .get("branchless.commitTemplate")?;
+    Ok(template.filter(|template| !template.trim().is_empty()))
+}
+
 /// If `true`, when restacking a commit, do not update its timestamp to the
 /// current time.
 #[instrument]
@@ -46,6 +276,14 @@ pub fn get_restack_preserve_timestamps(repo: &Repo) -> eyre::Result<bool> {
         .get_or("branchless.restack.preserveTimestamps", false)
 }
 
+/// If `true`, commits created when rewriting should be signed with the user's
+/// configured key, as with `git commit -S`. See `commit.gpgSign` in
+/// `git-config(1)`.
+#[instrument]
+pub fn get_commit_gpg_sign(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?.get_or("commit.gpgSign", false)
+}
+
 /// If `true`, when advancing to a "next" commit, prompt interactively to
 /// if there is ambiguity in which commit to advance to.
 #[instrument]
@@ -54,6 +292,21 @@ pub fn get_next_interactive(repo: &Repo) -> eyre::Result<bool> {
         .get_or("branchless.next.interactive", false)
 }
 
+/// If `true`, when `git next` is run while a branch is checked out, move the
+/// branch to the destination commit rather than detaching `HEAD`.
+#[instrument]
+pub fn get_navigation_move_branch(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.navigation.moveBranch", false)
+}
+
+/// The remote to which `git branchless submit` pushes branches.
+#[instrument]
+pub fn get_submit_remote(repo: &Repo) -> eyre::Result<String> {
+    repo.get_readonly_config()?
+        .get_or("branchless.submit.remote", "origin".to_string())
+}
+
 /// Config key for `get_restack_warn_abandoned`.
 pub const RESTACK_WARN_ABANDONED_CONFIG_KEY: &str = "branchless.restack.warnAbandoned";
 
@@ -65,6 +318,64 @@ pub fn get_restack_warn_abandoned(repo: &Repo) -> eyre::Result<bool> {
         .get_or(RESTACK_WARN_ABANDONED_CONFIG_KEY, true)
 }
 
+/// Get the config key which controls whether the given Git hook is installed,
+/// e.g. `branchless.hooks.postCheckout` for the `post-checkout` hook.
+pub fn get_hook_enabled_config_key(hook_type: &str) -> String {
+    let mut key = String::from("branchless.hooks.");
+    let mut capitalize_next = false;
+    for c in hook_type.chars() {
+        if c == '-' {
+            capitalize_next = true;
+        } else if capitalize_next {
+            key.extend(c.to_uppercase());
+            capitalize_next = false;
+        } else {
+            key.push(c);
+        }
+    }
+    key
+}
+
+/// Config key for the shebang line to write at the top of hook files newly
+/// created by `git branchless init`, such as `#!/usr/bin/env bash`. Hook files
+/// which already exist keep their shebang line. If not set, `#!/bin/sh` is
+/// used.
+pub const INIT_HOOK_SHEBANG_CONFIG_KEY: &str = "branchless.init.hookShebang";
+
+/// If `false`, the given Git hook is not installed by `git branchless init`
+/// (and is removed if it was previously installed).
+///
+/// Disabling hooks degrades the functionality that depends on them:
+///
+/// - `post-commit`, `post-merge`: new commits aren't recorded in the event
+///   log, so they won't be shown in the smartlog until they're checked out or
+///   referenced by a branch, and `git undo` can't restore them.
+/// - `post-rewrite`: amended or rebased commits aren't marked as obsolete, so
+///   the originals remain visible in the smartlog, and abandoned descendants
+///   aren't detected.
+/// - `post-checkout`: checkouts aren't recorded, so `git undo` can't return
+///   you to a previous location.
+/// - `pre-auto-gc`: commits hidden by branchless aren't released when Git
+///   collects garbage automatically. Run `git branchless gc` manually instead.
+/// - `pre-commit`: no warning is shown when committing on top of a main
+///   branch which has already been pushed.
+/// - `reference-transaction`: branch and ref updates aren't recorded, so
+///   `git undo` can't restore branches to their previous positions. This hook
+///   runs on every reference update, so it's the most expensive one in large
+///   repositories.
+#[instrument]
+pub fn get_hook_enabled(repo: &Repo, hook_type: &str) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or(get_hook_enabled_config_key(hook_type), true)
+}
+
+/// If `true`, the `pre-commit` hook warns when committing directly on top of
+/// the main branch after it has already been pushed.
+#[instrument]
+pub fn get_hooks_pre_commit(repo: &Repo) -> eyre::Result<bool> {
+    get_hook_enabled(repo, "pre-commit")
+}
+
 /// If `true`, show branches pointing to each commit in the smartlog.
 #[instrument]
 pub fn get_commit_descriptors_branches(repo: &Repo) -> eyre::Result<bool> {
@@ -72,6 +383,28 @@ pub fn get_commit_descriptors_branches(repo: &Repo) -> eyre::Result<bool> {
         .get_or("branchless.commitDescriptors.branches", true)
 }
 
+/// The prefixes of references which are never shown as branches in the
+/// smartlog. This always includes git-branchless's own namespace, along with
+/// any prefixes set in `branchless.smartlog.hiddenRefPrefixes` as a
+/// comma-separated list.
+#[instrument]
+pub fn get_smartlog_hidden_ref_prefixes(repo: &Repo) -> eyre::Result<Vec<String>> {
+    let mut prefixes = vec![BRANCHLESS_REF_PREFIX.to_string()];
+    let hidden_ref_prefixes: Option<String> = repo
+        .get_readonly_config()?
+        .get("branchless.smartlog.hiddenRefPrefixes")?;
+    if let Some(hidden_ref_prefixes) = hidden_ref_prefixes {
+        prefixes.extend(
+            hidden_ref_prefixes
+                .split(',')
+                .map(|prefix| prefix.trim())
+                .filter(|prefix| !prefix.is_empty())
+                .map(|prefix| prefix.to_string()),
+        );
+    }
+    Ok(prefixes)
+}
+
 /// If `true`, show associated Phabricator commits in the smartlog.
 #[instrument]
 pub fn get_commit_descriptors_differential_revision(repo: &Repo) -> eyre::Result<bool> {
@@ -86,6 +419,193 @@ pub fn get_commit_descriptors_relative_time(repo: &Repo) -> eyre::Result<bool> {
         .get_or("branchless.commitDescriptors.relativeTime", true)
 }
 
+/// If `true`, show the author of each commit in the smartlog.
+#[instrument]
+pub fn get_commit_descriptors_author(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.commitDescriptors.author", false)
+}
+
+/// If `true`, show the age of each commit in a separate column of the
+/// smartlog.
+#[instrument]
+pub fn get_smartlog_show_age(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.smartlog.showAge", false)
+}
+
+/// If `true`, print a key explaining the smartlog glyphs below the graph.
+#[instrument]
+pub fn get_smartlog_show_legend(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.smartlog.showLegend", false)
+}
+
+/// If `true`, show tags pointing to each commit in the smartlog.
+#[instrument]
+pub fn get_smartlog_show_tags(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.smartlog.showTags", true)
+}
+
+/// If `true`, show how many commits each local branch is ahead of and behind
+/// its upstream branch in the smartlog.
+#[instrument]
+pub fn get_smartlog_show_remote_status(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.smartlog.showRemoteStatus", false)
+}
+
+/// The order in which to render sibling commits in the smartlog, either `date`
+/// or `topo`, if set. If not set, sibling commits are rendered by commit date,
+/// oldest first.
+#[instrument]
+pub fn get_smartlog_order(repo: &Repo) -> eyre::Result<Option<String>> {
+    repo.get_readonly_config()?.get("branchless.smartlog.order")
+}
+
+/// The commit message trailer whose value should be displayed in the smartlog
+/// instead of the first line of the commit message, if set.
+#[instrument]
+pub fn get_smartlog_subject_trailer(repo: &Repo) -> eyre::Result<Option<String>> {
+    repo.get_readonly_config()?
+        .get("branchless.smartlog.subjectTrailer")
+}
+
+/// How to abbreviate commit hashes in the smartlog, according to
+/// `branchless.smartlog.abbrev`, or else Git's `core.abbrev`. If neither is
+/// set, hashes are abbreviated to 8 digits.
+#[instrument]
+pub fn get_smartlog_abbrev(repo: &Repo) -> eyre::Result<Abbrev> {
+    let config = repo.get_readonly_config()?;
+    for key in ["branchless.smartlog.abbrev", "core.abbrev"] {
+        if let Some(value) = config.get::<String, _>(key)? {
+            return Abbrev::parse(&value)
+                .ok_or_else(|| eyre::eyre!("Invalid value for {}: {:?}", key, value));
+        }
+    }
+    Ok(Abbrev::default())
+}
+
+/// The set of glyphs to use when rendering the smartlog, either `unicode` or
+/// `ascii`, if set.
+#[instrument]
+pub fn get_smartlog_glyphs(repo: &Repo) -> eyre::Result<Option<String>> {
+    repo.get_readonly_config()?
+        .get("branchless.smartlog.glyphs")
+}
+
+/// The styles with which to render the smartlog. Each element can be
+/// overridden with a `branchless.smartlog.color.<element>` config setting, in
+/// the same format as Git's color settings (e.g. `bold brightred`).
+#[instrument]
+pub fn get_smartlog_theme(repo: &Repo) -> eyre::Result<SmartlogTheme> {
+    let config = repo.get_readonly_config()?;
+    let mut theme = SmartlogTheme::default();
+    let SmartlogTheme {
+        commit_oid,
+        commit_message,
+        branches,
+        commit_head,
+        commit_visible,
+        commit_obsolete,
+        commit_main,
+        commit_main_obsolete,
+        commit_highlighted,
+    } = &mut theme;
+    for (element, style) in [
+        ("oid", commit_oid),
+        ("message", commit_message),
+        ("branch", branches),
+        ("head", commit_head),
+        ("visible", commit_visible),
+        ("obsolete", commit_obsolete),
+        ("main", commit_main),
+        ("mainObsolete", commit_main_obsolete),
+        ("highlighted", commit_highlighted),
+    ] {
+        let key = format!("branchless.smartlog.color.{}", element);
+        let value: Option<String> = config.get(&key)?;
+        if let Some(value) = value {
+            *style = parse_style(&value)
+                .map_err(|err| eyre::eyre!("Invalid value for {}: {}", key, err))?;
+        }
+    }
+    Ok(theme)
+}
+
+/// The pager to use when displaying the smartlog, if set. Otherwise, the pager
+/// configured for Git is used.
+#[instrument]
+pub fn get_smartlog_pager(repo: &Repo) -> eyre::Result<Option<String>> {
+    repo.get_readonly_config()?.get("branchless.smartlog.pager")
+}
+
+/// Get the value of the integer config setting `key`, if set. Git stores
+/// integers as signed values, so negative values are rejected here, rather
+/// than being wrapped around.
+#[instrument]
+fn get_non_negative_integer<T>(repo: &Repo, key: &str) -> eyre::Result<Option<T>>
+where
+    T: TryFrom<i32>,
+    <T as TryFrom<i32>>::Error: std::error::Error + Send + Sync + 'static,
+{
+    let value: Option<i32> = repo.get_readonly_config()?.get(key)?;
+    match value {
+        None => Ok(None),
+        Some(value) => {
+            let value = T::try_from(value)
+                .wrap_err_with(|| format!("Invalid value for {}: {}", key, value))?;
+            Ok(Some(value))
+        }
+    }
+}
+
+/// The number of days for which hidden commits are kept reachable before they
+/// can be released by `git branchless gc`.
+#[instrument]
+pub fn get_gc_retention_days(repo: &Repo) -> eyre::Result<u64> {
+    let retention_days = get_non_negative_integer(repo, "branchless.gc.retentionDays")?;
+    Ok(retention_days.unwrap_or(0))
+}
+
+/// The maximum number of events to keep in the event log when it's compacted
+/// during garbage collection, if any. Older events are pruned, except those
+/// needed to reconstruct the current state of the repository.
+#[instrument]
+pub fn get_eventlog_max_entries(repo: &Repo) -> eyre::Result<Option<usize>> {
+    get_non_negative_integer(repo, "branchless.eventlog.maxEntries")
+}
+
+/// The maximum age in days of events to keep in the event log when it's
+/// compacted during garbage collection, if any. Older events are pruned,
+/// except those needed to reconstruct the current state of the repository.
+#[instrument]
+pub fn get_eventlog_max_age_days(repo: &Repo) -> eyre::Result<Option<u64>> {
+    get_non_negative_integer(repo, "branchless.eventlog.maxAgeDays")
+}
+
+/// The maximum number of commits to display in the smartlog, if any. Commits
+/// furthest from `HEAD` are omitted first.
+#[instrument]
+pub fn get_smartlog_max_commits(repo: &Repo) -> eyre::Result<Option<usize>> {
+    get_non_negative_integer(repo, "branchless.smartlog.maxCommits")
+}
+
+/// The maximum number of stack heads to walk when building the smartlog, if
+/// any. Stacks containing `HEAD` and the main branch are always walked.
+#[instrument]
+pub fn get_smartlog_max_refs(repo: &Repo) -> eyre::Result<Option<usize>> {
+    get_non_negative_integer(repo, "branchless.smartlog.maxRefs")
+}
+
+/// The maximum width of the description of each commit in the smartlog, if
+/// any. Descriptions are additionally limited to the width of the terminal.
+#[instrument]
+pub fn get_smartlog_max_subject_width(repo: &Repo) -> eyre::Result<Option<usize>> {
+    get_non_negative_integer(repo, "branchless.smartlog.maxSubjectWidth")
+}
+
 /// Environment variables which affect the functioning of `git-branchless`.
 pub mod env_vars {
     use std::path::PathBuf;
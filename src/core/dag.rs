@@ -15,7 +15,7 @@ use tracing::{instrument, trace, warn};
 
 use crate::core::effects::{Effects, OperationType};
 use crate::core::eventlog::{CommitActivityStatus, EventCursor, EventReplayer};
-use crate::git::{Commit, MaybeZeroOid, NonZeroOid, Repo, RepoReferencesSnapshot};
+use crate::git::{Commit, CommitRange, MaybeZeroOid, NonZeroOid, Repo, RepoReferencesSnapshot};
 
 impl From<NonZeroOid> for eden_dag::VertexName {
     fn from(oid: NonZeroOid) -> Self {
@@ -348,6 +348,26 @@ impl Dag {
         Ok(public_commits)
     }
 
+    /// Query the `num_commits` most recent commits on the main branch, found by
+    /// following first parents from the main branch commit.
+    #[instrument]
+    pub fn query_recent_main_branch_commits(&self, num_commits: usize) -> eyre::Result<CommitSet> {
+        let mut result = Vec::new();
+        let mut next_vertex = self.main_branch_commit.iter()?.next().transpose()?;
+        while let Some(vertex) = next_vertex {
+            if result.len() >= num_commits {
+                break;
+            }
+            next_vertex = self
+                .query()
+                .parent_names(vertex.clone())?
+                .into_iter()
+                .next();
+            result.push(vertex);
+        }
+        Ok(CommitSet::from_static_names(result))
+    }
+
     /// Query the set of active heads. This includes the heads of the set of
     /// visible commits, plus any other commits which would be rendered in the
     /// smartlog.
@@ -377,6 +397,27 @@ impl Dag {
         Ok(active_heads)
     }
 
+    /// Like `query_active_heads`, but only consider the provided focus commits
+    /// and their observed draft descendants, rather than all active heads.
+    #[instrument]
+    pub fn query_focused_heads(
+        &self,
+        focus_commits: &CommitSet,
+        public_commits: &CommitSet,
+        observed_commits: &CommitSet,
+    ) -> eyre::Result<CommitSet> {
+        let focused_commits = self
+            .query()
+            .descendants(focus_commits.clone())?
+            .intersection(observed_commits)
+            .difference(public_commits);
+        let focused_heads = self.query().heads(focused_commits)?;
+        let focused_heads = focused_heads
+            .union(focus_commits)
+            .union(&self.main_branch_commit);
+        Ok(focused_heads)
+    }
+
     /// Find a path from the provided head to its merge-base with the main
     /// branch.
     #[instrument]
@@ -518,3 +559,36 @@ pub fn resolve_commits<'repo>(
     )?;
     Ok(ResolveCommitsResult::Ok { commits })
 }
+
+/// Parse a commit range such as `A..B` or `A...B` into the set of commits it
+/// refers to, following Git's range semantics. Returns `None` if either end of
+/// the range couldn't be resolved.
+#[instrument]
+pub fn resolve_commit_range(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &mut Dag,
+    range: &str,
+) -> eyre::Result<Option<CommitSet>> {
+    let CommitRange {
+        from,
+        to,
+        symmetric,
+    } = match repo.revparse_range(range)? {
+        Some(commit_range) => commit_range,
+        None => return Ok(None),
+    };
+
+    let from = CommitSet::from(from.get_oid());
+    let to = CommitSet::from(to.get_oid());
+    dag.sync_from_oids(effects, repo, CommitSet::empty(), from.union(&to))?;
+
+    let commits = if symmetric {
+        let heads = from.union(&to);
+        let merge_bases = dag.query().gca_all(heads.clone())?;
+        dag.query().only(heads, merge_bases)?
+    } else {
+        dag.query().only(to, from)?
+    };
+    Ok(Some(commits))
+}
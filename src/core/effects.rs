@@ -13,7 +13,7 @@ use itertools::Itertools;
 use lazy_static::lazy_static;
 use tracing::warn;
 
-use crate::core::formatting::Glyphs;
+use crate::core::formatting::{Glyphs, SmartlogTheme};
 
 #[allow(missing_docs)]
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -36,6 +36,7 @@ pub enum OperationType {
     ReadingFromCache,
     RebaseCommits,
     RunGitCommand(Arc<String>),
+    RunTests,
     SyncCommits,
     UpdateCommitGraph,
     WalkCommits,
@@ -64,6 +65,7 @@ impl ToString for OperationType {
             OperationType::RunGitCommand(command) => {
                 return format!("Running Git command: {}", &command)
             }
+            OperationType::RunTests => "Running tests",
             OperationType::SyncCommits => "Syncing commit stacks",
             OperationType::UpdateCommitGraph => "Updating commit graph",
             OperationType::WalkCommits => "Walking commits",
@@ -72,6 +74,27 @@ impl ToString for OperationType {
     }
 }
 
+/// How much output should be displayed to the user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only display errors and output that the user explicitly asked for
+    /// (such as prompts or the smartlog itself).
+    Quiet,
+
+    /// Display informational output about what happened during an operation.
+    Normal,
+
+    /// Additionally display detailed output, such as each commit and
+    /// reference which was updated.
+    Verbose,
+}
+
+impl Default for Verbosity {
+    fn default() -> Self {
+        Verbosity::Normal
+    }
+}
+
 #[derive(Clone, Debug)]
 enum OutputDest {
     Stdout,
@@ -344,6 +367,7 @@ impl OperationState {
 pub struct Effects {
     glyphs: Glyphs,
     dest: OutputDest,
+    verbosity: Verbosity,
     updater_thread_handle: Arc<RwLock<UpdaterThreadHandle>>,
     operation_key: Vec<OperationType>,
     root_operation: Arc<Mutex<RootOperation>>,
@@ -419,17 +443,35 @@ impl Effects {
         Effects {
             glyphs,
             dest: OutputDest::Stdout,
+            verbosity: Default::default(),
             updater_thread_handle,
             operation_key: Default::default(),
             root_operation,
         }
     }
 
+    /// Constructor. Writes to stdout, but never displays progress indicators
+    /// for long-running operations (e.g. if `--quiet` was passed or the output
+    /// isn't a terminal).
+    pub fn new_without_progress(glyphs: Glyphs) -> Self {
+        let root_operation: Arc<Mutex<RootOperation>> = Default::default();
+        root_operation.lock().unwrap().hide_multi_progress();
+        Effects {
+            glyphs,
+            dest: OutputDest::Stdout,
+            verbosity: Default::default(),
+            updater_thread_handle: Default::default(),
+            operation_key: Default::default(),
+            root_operation,
+        }
+    }
+
     /// Constructor. Suppresses all output.
     pub fn new_suppress_for_test(glyphs: Glyphs) -> Self {
         Effects {
             glyphs,
             dest: OutputDest::Suppress,
+            verbosity: Default::default(),
             updater_thread_handle: Default::default(),
             operation_key: Default::default(),
             root_operation: Default::default(),
@@ -448,6 +490,7 @@ impl Effects {
                 stdout: Arc::clone(stdout),
                 stderr: Arc::clone(stderr),
             },
+            verbosity: Default::default(),
             updater_thread_handle: Default::default(),
             operation_key: Default::default(),
             root_operation: Default::default(),
@@ -465,6 +508,44 @@ impl Effects {
         }
     }
 
+    /// Render using only ASCII glyphs. See `Glyphs::ascii`.
+    pub fn ascii_glyphs(&self, ascii: bool) -> Self {
+        Self {
+            glyphs: self.glyphs.clone().ascii(ascii),
+            ..self.clone()
+        }
+    }
+
+    /// Render the smartlog graph in reverse order, i.e. with the newest commits
+    /// at the top. See `Glyphs::reverse_order`.
+    pub fn reverse_order(&self, reverse: bool) -> Self {
+        Self {
+            glyphs: self.glyphs.clone().reverse_order(reverse),
+            ..self.clone()
+        }
+    }
+
+    /// Render the smartlog with the provided styles. See `Glyphs::theme`.
+    pub fn smartlog_theme(&self, theme: SmartlogTheme) -> Self {
+        Self {
+            glyphs: self.glyphs.clone().theme(theme),
+            ..self.clone()
+        }
+    }
+
+    /// Set how much output should be displayed. See `Verbosity`.
+    pub fn verbosity(&self, verbosity: Verbosity) -> Self {
+        Self {
+            verbosity,
+            ..self.clone()
+        }
+    }
+
+    /// Get how much output should be displayed.
+    pub fn get_verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
     /// Start reporting progress for the specified operation type.
     ///
     /// A progress spinner is shown until the returned `ProgressHandle` is
@@ -586,9 +667,33 @@ impl Effects {
         &self.glyphs
     }
 
+    /// Get the destination for output which should only be displayed at the
+    /// given verbosity level or higher.
+    fn get_dest_for_verbosity(&self, verbosity: Verbosity) -> OutputDest {
+        if self.verbosity >= verbosity {
+            self.dest.clone()
+        } else {
+            OutputDest::Suppress
+        }
+    }
+
     /// Create a stream that can be written to. The output might go to stdout or
     /// be rendered specially in the terminal.
+    ///
+    /// This stream is for informational output, which is not displayed if the
+    /// verbosity is `Verbosity::Quiet`.
     pub fn get_output_stream(&self) -> OutputStream {
+        OutputStream {
+            dest: self.get_dest_for_verbosity(Verbosity::Normal),
+            buffer: Default::default(),
+            updater_thread_handle: Arc::clone(&self.updater_thread_handle),
+            root_operation: Arc::clone(&self.root_operation),
+        }
+    }
+
+    /// Create a stream for output which should be displayed regardless of the
+    /// verbosity, such as prompts for user input.
+    pub fn get_primary_output_stream(&self) -> OutputStream {
         OutputStream {
             dest: self.dest.clone(),
             buffer: Default::default(),
@@ -597,6 +702,17 @@ impl Effects {
         }
     }
 
+    /// Create a stream for detailed output, which is only displayed if the
+    /// verbosity is `Verbosity::Verbose`.
+    pub fn get_verbose_stream(&self) -> OutputStream {
+        OutputStream {
+            dest: self.get_dest_for_verbosity(Verbosity::Verbose),
+            buffer: Default::default(),
+            updater_thread_handle: Arc::clone(&self.updater_thread_handle),
+            root_operation: Arc::clone(&self.root_operation),
+        }
+    }
+
     /// Create a stream that error output can be written to, rather than regular
     /// output.
     pub fn get_error_stream(&self) -> ErrorStream {
@@ -943,4 +1059,67 @@ mod tests {
         progress.notify_progress(0, 10);
         Ok(())
     }
+
+    #[test]
+    fn test_effects_without_progress() -> eyre::Result<()> {
+        let effects = Effects::new_without_progress(Glyphs::text());
+        let (_effects, progress) = effects.start_operation(OperationType::RebaseCommits);
+        progress.notify_progress(1, 10);
+
+        // Longer than the delay before progress is normally displayed.
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(!effects.updater_thread_handle.read().unwrap().is_visible);
+        {
+            let mut root_operation = effects.root_operation.lock().unwrap();
+            assert!(root_operation
+                .get_child(&[OperationType::RebaseCommits])
+                .is_some());
+        }
+
+        drop(progress);
+        Ok(())
+    }
+
+    #[test]
+    fn test_effects_verbosity() -> eyre::Result<()> {
+        let stdout = Arc::new(Mutex::new(Vec::new()));
+        let stderr = Arc::new(Mutex::new(Vec::new()));
+        let effects = Effects::new_from_buffer_for_test(Glyphs::text(), &stdout, &stderr);
+
+        for verbosity in [Verbosity::Quiet, Verbosity::Normal, Verbosity::Verbose] {
+            let effects = effects.verbosity(verbosity);
+            writeln!(
+                effects.get_primary_output_stream(),
+                "{:?} primary",
+                verbosity
+            )?;
+            writeln!(effects.get_output_stream(), "{:?} info", verbosity)?;
+            writeln!(effects.get_verbose_stream(), "{:?} verbose", verbosity)?;
+            writeln!(effects.get_error_stream(), "{:?} error", verbosity)?;
+        }
+
+        let stdout = String::from_utf8(stdout.lock().unwrap().clone())?;
+        let stderr = String::from_utf8(stderr.lock().unwrap().clone())?;
+        assert_eq!(
+            stdout,
+            "\
+Quiet primary
+Normal primary
+Normal info
+Verbose primary
+Verbose info
+Verbose verbose
+"
+        );
+        assert_eq!(
+            stderr,
+            "\
+Quiet error
+Normal error
+Verbose error
+"
+        );
+
+        Ok(())
+    }
 }
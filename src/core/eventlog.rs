@@ -9,12 +9,15 @@ use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::ffi::{OsStr, OsString};
-
+use std::fmt::Display;
+use std::path::Path;
 use std::str::FromStr;
 use std::time::{Duration, SystemTime};
 
 use eyre::Context;
-use tracing::{error, instrument};
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use tracing::{error, instrument, warn};
 
 use crate::core::effects::{Effects, OperationType};
 use crate::git::{
@@ -25,6 +28,141 @@ use crate::git::{
 /// which the caller has already started.
 pub const BRANCHLESS_TRANSACTION_ID_ENV_VAR: &str = "BRANCHLESS_TRANSACTION_ID";
 
+/// The prefix of the references which git-branchless uses internally, such as
+/// to keep commits from being garbage-collected.
+pub const BRANCHLESS_REF_PREFIX: &str = "refs/branchless/";
+
+/// The message recorded in an `ObsoleteEvent` for a commit which was hidden
+/// because an equivalent commit landed on the main branch.
+pub const LANDED_UPSTREAM_HIDE_MESSAGE: &str = "landed upstream";
+
+/// The explanation shown for a commit which was hidden because an equivalent
+/// commit landed on the main branch, such as in `git smartlog --hidden`.
+pub const LANDED_HIDE_EXPLANATION: &str = "landed";
+
+/// The message recorded in an `ObsoleteEvent` for a commit which was hidden
+/// because it became empty when it was rebased, if
+/// `branchless.restack.emptyCommits` is set to `hide`.
+pub const EMPTY_COMMIT_HIDE_MESSAGE: &str = "became empty";
+
+/// The prefix of the message recorded in an `ObsoleteEvent` for a public
+/// commit which was hidden with `git hide --force`. See
+/// `make_force_hidden_public_hide_message`.
+pub const FORCE_HIDDEN_PUBLIC_HIDE_MESSAGE: &str = "force hidden public";
+
+/// Make the message recorded in an `ObsoleteEvent` for a public commit which
+/// was hidden with `git hide --force`. The message provided with `git hide
+/// -m`, if any, is kept after the `FORCE_HIDDEN_PUBLIC_HIDE_MESSAGE` prefix,
+/// and can be recovered with `parse_force_hidden_public_hide_message`.
+pub fn make_force_hidden_public_hide_message(message: Option<&str>) -> String {
+    match message {
+        Some(message) => format!("{}: {}", FORCE_HIDDEN_PUBLIC_HIDE_MESSAGE, message),
+        None => FORCE_HIDDEN_PUBLIC_HIDE_MESSAGE.to_string(),
+    }
+}
+
+/// If the given `ObsoleteEvent` message marks a public commit which was hidden
+/// with `git hide --force`, return the message provided by the user, if any.
+pub fn parse_force_hidden_public_hide_message(message: &OsStr) -> Option<Option<String>> {
+    let message = message.to_str()?;
+    match message.strip_prefix(FORCE_HIDDEN_PUBLIC_HIDE_MESSAGE)? {
+        "" => Some(None),
+        rest => rest
+            .strip_prefix(": ")
+            .map(|message| Some(message.to_string())),
+    }
+}
+
+/// The message recorded in an `ObsoleteEvent` for a commit which was hidden by
+/// `git branchless repair` because it no longer exists in the repository.
+pub const MISSING_COMMIT_HIDE_MESSAGE: &str = "missing";
+
+/// The reason that a commit is hidden, as derived from the event which hid it.
+/// `HideReason::describe` renders the explanation shown for hidden commits,
+/// such as in the smartlog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HideReason {
+    /// The commit was rewritten in place, keeping the same parents, such as
+    /// with `git commit --amend`.
+    Amended {
+        /// The commit that this commit was rewritten into.
+        rewritten_oid: MaybeZeroOid,
+    },
+
+    /// The commit was rewritten onto different parents, such as with `git
+    /// rebase` or `git move`.
+    Rebased {
+        /// The commit that this commit was rewritten into.
+        rewritten_oid: MaybeZeroOid,
+    },
+
+    /// The commit was rewritten, but the rewritten commit isn't available to
+    /// determine how.
+    Rewritten {
+        /// The commit that this commit was rewritten into, or the zero OID if
+        /// it was deleted.
+        rewritten_oid: MaybeZeroOid,
+    },
+
+    /// The commit was hidden with `git hide`.
+    ManuallyHidden {
+        /// The message provided with `git hide -m`, if any.
+        message: Option<String>,
+    },
+
+    /// An equivalent commit landed on the main branch.
+    Landed,
+
+    /// The commit became empty when it was rebased, and so was dropped.
+    BecameEmpty,
+
+    /// The commit was on the main branch, and was hidden with `git hide
+    /// --force`.
+    ForceHiddenPublic {
+        /// The message provided with `git hide -m`, if any.
+        message: Option<String>,
+    },
+
+    /// The commit no longer exists in the repository, and was hidden by `git
+    /// branchless repair`.
+    Missing,
+}
+
+impl HideReason {
+    /// Render the explanation of why the commit is hidden. Commit hashes are
+    /// abbreviated to `abbrev_len` digits.
+    pub fn describe(&self, abbrev_len: usize) -> String {
+        let abbreviate = |oid: &MaybeZeroOid| {
+            let oid = oid.to_string();
+            oid[..abbrev_len.min(oid.len())].to_string()
+        };
+        match self {
+            HideReason::Amended { rewritten_oid } => {
+                format!("amended as {}", abbreviate(rewritten_oid))
+            }
+            HideReason::Rebased { rewritten_oid } => {
+                format!("rebased as {}", abbreviate(rewritten_oid))
+            }
+            HideReason::Rewritten { rewritten_oid } => {
+                format!("rewritten as {}", abbreviate(rewritten_oid))
+            }
+            HideReason::ManuallyHidden { message: None } => "manually hidden".to_string(),
+            HideReason::ManuallyHidden {
+                message: Some(message),
+            } => format!("manually hidden: {}", message),
+            HideReason::Landed => LANDED_HIDE_EXPLANATION.to_string(),
+            HideReason::BecameEmpty => "became empty".to_string(),
+            HideReason::ForceHiddenPublic { message: None } => {
+                FORCE_HIDDEN_PUBLIC_HIDE_MESSAGE.to_string()
+            }
+            HideReason::ForceHiddenPublic {
+                message: Some(message),
+            } => format!("{}: {}", FORCE_HIDDEN_PUBLIC_HIDE_MESSAGE, message),
+            HideReason::Missing => "missing from the repository".to_string(),
+        }
+    }
+}
+
 // Wrapper around the row stored directly in the database.
 #[derive(Clone, Debug)]
 struct Row {
@@ -81,6 +219,10 @@ pub enum Event {
     ///
     /// We typically want to mark the new version of the commit as active and
     /// the old version of the commit as obsolete.
+    ///
+    /// Rewrites preserve the author of the commit, so the original authorship
+    /// can be recovered from `new_commit_oid` even if the commit was rewritten
+    /// by a different committer.
     RewriteEvent {
         /// The timestamp of the event.
         timestamp: f64,
@@ -149,6 +291,11 @@ pub enum Event {
 
         /// The OID of the commit that was obsoleted.
         commit_oid: NonZeroOid,
+
+        /// The reason the commit was obsoleted, as provided by the user with
+        /// `git hide -m`, or `LANDED_UPSTREAM_HIDE_MESSAGE` if it was hidden
+        /// automatically by `git sync`.
+        message: Option<OsString>,
     },
 
     /// Indicates that a commit was explicitly un-obsoleted by the user.
@@ -165,6 +312,26 @@ pub enum Event {
         /// The OID of the commit that was unobsoleted.
         commit_oid: NonZeroOid,
     },
+
+    /// Indicates that the user took a snapshot of the working copy with `git
+    /// branchless snapshot`.
+    ///
+    /// The snapshot commit isn't considered to be active, so it's not shown in
+    /// the smartlog, but it can be restored with `git branchless restore`.
+    WorkingCopySnapshot {
+        /// The timestamp of the event.
+        timestamp: f64,
+
+        /// The transaction ID of the event.
+        event_tx_id: EventTransactionId,
+
+        /// The OID of the commit that `HEAD` pointed to when the snapshot was
+        /// taken.
+        head_oid: MaybeZeroOid,
+
+        /// The OID of the snapshot commit.
+        commit_oid: NonZeroOid,
+    },
 }
 
 impl Event {
@@ -176,6 +343,7 @@ impl Event {
             Event::CommitEvent { timestamp, .. } => timestamp,
             Event::ObsoleteEvent { timestamp, .. } => timestamp,
             Event::UnobsoleteEvent { timestamp, .. } => timestamp,
+            Event::WorkingCopySnapshot { timestamp, .. } => timestamp,
         };
         SystemTime::UNIX_EPOCH + Duration::from_secs_f64(*timestamp)
     }
@@ -188,10 +356,37 @@ impl Event {
             Event::CommitEvent { event_tx_id, .. } => *event_tx_id,
             Event::ObsoleteEvent { event_tx_id, .. } => *event_tx_id,
             Event::UnobsoleteEvent { event_tx_id, .. } => *event_tx_id,
+            Event::WorkingCopySnapshot { event_tx_id, .. } => *event_tx_id,
+        }
+    }
+
+    /// Get the name of the reference updated by this event, if any.
+    pub fn get_ref_name(&self) -> Option<&OsStr> {
+        match self {
+            Event::RefUpdateEvent { ref_name, .. } => Some(ref_name),
+            Event::RewriteEvent { .. }
+            | Event::CommitEvent { .. }
+            | Event::ObsoleteEvent { .. }
+            | Event::UnobsoleteEvent { .. }
+            | Event::WorkingCopySnapshot { .. } => None,
         }
     }
 }
 
+/// Split the given events into those which update the reference `ref_name`
+/// and those which don't, preserving their relative order.
+///
+/// This is used to apply only part of a transaction. Note that the events of a
+/// transaction aren't independent of each other: for example, a rebase both
+/// moves branches and hides the commits they used to point to. Applying only
+/// one side of the split can therefore leave the repository in a state which
+/// no single transaction produced.
+pub fn partition_events_by_ref(events: Vec<Event>, ref_name: &OsStr) -> (Vec<Event>, Vec<Event>) {
+    events
+        .into_iter()
+        .partition(|event| event.get_ref_name() == Some(ref_name))
+}
+
 impl From<Event> for Row {
     fn from(event: Event) -> Row {
         match event {
@@ -245,6 +440,7 @@ impl From<Event> for Row {
                 timestamp,
                 event_tx_id: EventTransactionId(event_tx_id),
                 commit_oid,
+                message,
             } => Row {
                 timestamp,
                 event_tx_id,
@@ -252,7 +448,7 @@ impl From<Event> for Row {
                 ref1: Some(commit_oid.to_string().into()),
                 ref2: None,
                 ref_name: None,
-                message: None,
+                message,
             },
 
             Event::UnobsoleteEvent {
@@ -268,6 +464,21 @@ impl From<Event> for Row {
                 ref_name: None,
                 message: None,
             },
+
+            Event::WorkingCopySnapshot {
+                timestamp,
+                event_tx_id: EventTransactionId(event_tx_id),
+                head_oid,
+                commit_oid,
+            } => Row {
+                timestamp,
+                event_tx_id,
+                type_: String::from("snapshot"),
+                ref1: Some(head_oid.to_string().into()),
+                ref2: Some(commit_oid.to_string().into()),
+                ref_name: None,
+                message: None,
+            },
         }
     }
 }
@@ -344,6 +555,7 @@ fn try_from_row_helper(row: &Row) -> Result<Event, eyre::Error> {
                 timestamp,
                 event_tx_id,
                 commit_oid,
+                message,
             }
         }
 
@@ -356,6 +568,17 @@ fn try_from_row_helper(row: &Row) -> Result<Event, eyre::Error> {
             }
         }
 
+        "snapshot" => {
+            let head_oid = get_oid(&ref1, "HEAD OID")?;
+            let commit_oid: NonZeroOid = get_oid(&ref2, "snapshot commit OID")?.try_into()?;
+            Event::WorkingCopySnapshot {
+                timestamp,
+                event_tx_id,
+                head_oid,
+                commit_oid,
+            }
+        }
+
         other => eyre::bail!("Unknown event type {}", other),
     };
     Ok(event)
@@ -375,6 +598,42 @@ impl TryFrom<Row> for Event {
     }
 }
 
+fn read_row(row: &rusqlite::Row) -> rusqlite::Result<(i64, Row)> {
+    let row_id: i64 = row.get("rowid")?;
+    let timestamp: f64 = row.get("timestamp")?;
+    let event_tx_id: isize = row.get("event_tx_id")?;
+    let type_: String = row.get("type")?;
+    let ref_name: Option<String> = row.get("ref_name")?;
+    let old_ref: Option<String> = row.get("old_ref")?;
+    let new_ref: Option<String> = row.get("new_ref")?;
+    let message: Option<String> = row.get("message")?;
+
+    Ok((
+        row_id,
+        Row {
+            timestamp,
+            event_tx_id,
+            type_,
+            ref_name: ref_name.map(OsString::from),
+            ref1: old_ref.map(OsString::from),
+            ref2: new_ref.map(OsString::from),
+            message: message.map(OsString::from),
+        },
+    ))
+}
+
+/// An event along with the ID of its row in the event log.
+pub type EventRow = (i64, Event);
+
+/// The tables which store the persisted states of the event replayer. See
+/// `EventReplayer::from_rows`.
+const EVENT_REPLAYER_VIEW_TABLES: &[&str] = &[
+    "event_replayer_views",
+    "event_replayer_commit_history",
+    "event_replayer_ref_locations",
+    "event_replayer_patched_old_oids",
+];
+
 /// Stores `Event`s on disk.
 pub struct EventLogDb<'conn> {
     conn: &'conn rusqlite::Connection,
@@ -422,6 +681,75 @@ CREATE TABLE IF NOT EXISTS event_transactions (
     )
     .wrap_err("Creating `event_transactions` table")?;
 
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS merged_event_logs (
+    path TEXT NOT NULL PRIMARY KEY
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `merged_event_logs` table")?;
+
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS event_replayer_views (
+    main_branch_reference_name TEXT NOT NULL,
+    head_reference_name TEXT NOT NULL,
+    view TEXT NOT NULL,
+    PRIMARY KEY (main_branch_reference_name, head_reference_name)
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `event_replayer_views` table")?;
+
+    // The entries of the persisted replayer states are stored one per row,
+    // so that only the entries affected by new events have to be written. The
+    // tables are clustered by their keys, since they're always read by the
+    // reference names.
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS event_replayer_commit_history (
+    main_branch_reference_name TEXT NOT NULL,
+    head_reference_name TEXT NOT NULL,
+    commit_oid TEXT NOT NULL,
+    history TEXT NOT NULL,
+    PRIMARY KEY (main_branch_reference_name, head_reference_name, commit_oid)
+) WITHOUT ROWID
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `event_replayer_commit_history` table")?;
+
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS event_replayer_ref_locations (
+    main_branch_reference_name TEXT NOT NULL,
+    head_reference_name TEXT NOT NULL,
+    ref_name TEXT NOT NULL,
+    commit_oid TEXT NOT NULL,
+    PRIMARY KEY (main_branch_reference_name, head_reference_name, ref_name)
+) WITHOUT ROWID
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `event_replayer_ref_locations` table")?;
+
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS event_replayer_patched_old_oids (
+    main_branch_reference_name TEXT NOT NULL,
+    head_reference_name TEXT NOT NULL,
+    event_id INTEGER NOT NULL,
+    old_oid TEXT NOT NULL,
+    PRIMARY KEY (main_branch_reference_name, head_reference_name, event_id)
+) WITHOUT ROWID
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `event_replayer_patched_old_oids` table")?;
+
     Ok(())
 }
 
@@ -490,38 +818,468 @@ INSERT INTO event_log VALUES (
     ///
     /// Returns: All the events in the database, ordered from oldest to newest.
     #[instrument]
-
     pub fn get_events(&self) -> eyre::Result<Vec<Event>> {
+        self.query_events("TRUE", rusqlite::params![])
+    }
+
+    /// Get a value identifying the current contents of the event log: the ID
+    /// of the newest event and the total number of events. This changes
+    /// whenever an event is added or the event log is compacted.
+    #[instrument]
+    pub fn get_tip(&self) -> eyre::Result<(Option<i64>, i64)> {
+        let tip = self
+            .conn
+            .query_row(
+                "SELECT MAX(rowid), COUNT(*) FROM event_log",
+                rusqlite::params![],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .wrap_err("Querying event log tip")?;
+        Ok(tip)
+    }
+
+    /// Get all the events in the database, along with their row IDs.
+    ///
+    /// Returns: All the events in the database, ordered from oldest to newest.
+    #[instrument]
+    pub fn get_events_with_row_ids(&self) -> eyre::Result<Vec<EventRow>> {
+        self.query_events_with_row_ids("TRUE", rusqlite::params![])
+    }
+
+    /// Get the persisted state of the event replayer for the given main branch
+    /// and `HEAD` reference names, if any. See `EventReplayer::from_rows`.
+    ///
+    /// Returns: The persisted state, or `None` if there is none or it
+    /// couldn't be read.
+    #[instrument]
+    fn get_replayer_view(
+        &self,
+        main_branch_reference_name: &OsStr,
+        head_reference_name: &OsStr,
+    ) -> eyre::Result<Option<EventReplayerView>> {
+        let main_branch_reference_name = main_branch_reference_name.to_string_lossy();
+        let head_reference_name = head_reference_name.to_string_lossy();
+        let params = rusqlite::named_params! {
+            ":main_branch_reference_name": main_branch_reference_name,
+            ":head_reference_name": head_reference_name,
+        };
+        let header: Option<String> = self
+            .conn
+            .query_row(
+                "
+SELECT view
+FROM event_replayer_views
+WHERE main_branch_reference_name = :main_branch_reference_name
+AND head_reference_name = :head_reference_name
+",
+                params,
+                |row| row.get("view"),
+            )
+            .optional()
+            .wrap_err("Querying event replayer view")?;
+        // A header written by an incompatible version is treated as missing,
+        // so that the events are replayed and the state is stored again.
+        let header: EventReplayerViewHeader =
+            match header.and_then(|header| serde_json::from_str(&header).ok()) {
+                Some(header) => header,
+                None => return Ok(None),
+            };
+
+        let mut commit_history = Vec::new();
+        let mut stmt = self.conn.prepare(
+            "
+SELECT commit_oid, history
+FROM event_replayer_commit_history
+WHERE main_branch_reference_name = :main_branch_reference_name
+AND head_reference_name = :head_reference_name
+",
+        )?;
+        for row in stmt.query_map(params, |row| {
+            let commit_oid: String = row.get("commit_oid")?;
+            let history: String = row.get("history")?;
+            Ok((commit_oid, history))
+        })? {
+            let (commit_oid, history) = row.wrap_err("Querying event replayer commit history")?;
+            match serde_json::from_str(&history) {
+                Ok(history) => commit_history.push((commit_oid, history)),
+                Err(_) => return Ok(None),
+            }
+        }
+
+        let mut stmt = self.conn.prepare(
+            "
+SELECT ref_name, commit_oid
+FROM event_replayer_ref_locations
+WHERE main_branch_reference_name = :main_branch_reference_name
+AND head_reference_name = :head_reference_name
+",
+        )?;
+        let ref_locations = stmt
+            .query_map(params, |row| {
+                Ok((row.get("ref_name")?, row.get("commit_oid")?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .wrap_err("Querying event replayer reference locations")?;
+
+        let mut stmt = self.conn.prepare(
+            "
+SELECT event_id, old_oid
+FROM event_replayer_patched_old_oids
+WHERE main_branch_reference_name = :main_branch_reference_name
+AND head_reference_name = :head_reference_name
+",
+        )?;
+        let patched_old_oids = stmt
+            .query_map(params, |row| {
+                Ok((row.get("event_id")?, row.get("old_oid")?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .wrap_err("Querying event replayer patched OIDs")?;
+
+        Ok(Some(EventReplayerView {
+            header,
+            commit_history,
+            ref_locations,
+            removed_ref_names: Vec::new(),
+            patched_old_oids,
+        }))
+    }
+
+    /// Persist the state of the event replayer for the given main branch and
+    /// `HEAD` reference names. Only the entries in `view` are written, so the
+    /// stored state is updated in place rather than replaced. Should be called
+    /// inside a transaction.
+    #[instrument(skip(view))]
+    fn set_replayer_view(
+        &self,
+        main_branch_reference_name: &OsStr,
+        head_reference_name: &OsStr,
+        view: &EventReplayerView,
+    ) -> eyre::Result<()> {
+        let main_branch_reference_name = main_branch_reference_name.to_string_lossy();
+        let head_reference_name = head_reference_name.to_string_lossy();
+        let EventReplayerView {
+            header,
+            commit_history,
+            ref_locations,
+            removed_ref_names,
+            patched_old_oids,
+        } = view;
+
+        self.conn
+            .execute(
+                "
+INSERT OR REPLACE INTO event_replayer_views
+(main_branch_reference_name, head_reference_name, view)
+VALUES
+(:main_branch_reference_name, :head_reference_name, :view)
+",
+                rusqlite::named_params! {
+                    ":main_branch_reference_name": main_branch_reference_name,
+                    ":head_reference_name": head_reference_name,
+                    ":view": serde_json::to_string(header)?,
+                },
+            )
+            .wrap_err("Storing event replayer view")?;
+
+        let mut stmt = self.conn.prepare(
+            "
+INSERT OR REPLACE INTO event_replayer_commit_history
+(main_branch_reference_name, head_reference_name, commit_oid, history)
+VALUES
+(:main_branch_reference_name, :head_reference_name, :commit_oid, :history)
+",
+        )?;
+        for (commit_oid, history) in commit_history {
+            stmt.execute(rusqlite::named_params! {
+                ":main_branch_reference_name": main_branch_reference_name,
+                ":head_reference_name": head_reference_name,
+                ":commit_oid": commit_oid,
+                ":history": serde_json::to_string(history)?,
+            })
+            .wrap_err("Storing event replayer commit history")?;
+        }
+
+        let mut stmt = self.conn.prepare(
+            "
+INSERT OR REPLACE INTO event_replayer_ref_locations
+(main_branch_reference_name, head_reference_name, ref_name, commit_oid)
+VALUES
+(:main_branch_reference_name, :head_reference_name, :ref_name, :commit_oid)
+",
+        )?;
+        for (ref_name, commit_oid) in ref_locations {
+            stmt.execute(rusqlite::named_params! {
+                ":main_branch_reference_name": main_branch_reference_name,
+                ":head_reference_name": head_reference_name,
+                ":ref_name": ref_name,
+                ":commit_oid": commit_oid,
+            })
+            .wrap_err("Storing event replayer reference location")?;
+        }
+
+        let mut stmt = self.conn.prepare(
+            "
+DELETE FROM event_replayer_ref_locations
+WHERE main_branch_reference_name = :main_branch_reference_name
+AND head_reference_name = :head_reference_name
+AND ref_name = :ref_name
+",
+        )?;
+        for ref_name in removed_ref_names {
+            stmt.execute(rusqlite::named_params! {
+                ":main_branch_reference_name": main_branch_reference_name,
+                ":head_reference_name": head_reference_name,
+                ":ref_name": ref_name,
+            })
+            .wrap_err("Removing event replayer reference location")?;
+        }
+
+        let mut stmt = self.conn.prepare(
+            "
+INSERT OR REPLACE INTO event_replayer_patched_old_oids
+(main_branch_reference_name, head_reference_name, event_id, old_oid)
+VALUES
+(:main_branch_reference_name, :head_reference_name, :event_id, :old_oid)
+",
+        )?;
+        for (event_id, old_oid) in patched_old_oids {
+            stmt.execute(rusqlite::named_params! {
+                ":main_branch_reference_name": main_branch_reference_name,
+                ":head_reference_name": head_reference_name,
+                ":event_id": event_id,
+                ":old_oid": old_oid,
+            })
+            .wrap_err("Storing event replayer patched OID")?;
+        }
+
+        Ok(())
+    }
+
+    /// Delete the persisted state of the event replayer for the given main
+    /// branch and `HEAD` reference names. Should be called inside a
+    /// transaction.
+    #[instrument]
+    fn clear_replayer_view(
+        &self,
+        main_branch_reference_name: &OsStr,
+        head_reference_name: &OsStr,
+    ) -> eyre::Result<()> {
+        for table in EVENT_REPLAYER_VIEW_TABLES {
+            self.conn
+                .execute(
+                    &format!(
+                        "
+DELETE FROM {}
+WHERE main_branch_reference_name = :main_branch_reference_name
+AND head_reference_name = :head_reference_name
+",
+                        table
+                    ),
+                    rusqlite::named_params! {
+                        ":main_branch_reference_name": main_branch_reference_name.to_string_lossy(),
+                        ":head_reference_name": head_reference_name.to_string_lossy(),
+                    },
+                )
+                .wrap_err("Clearing event replayer view")?;
+        }
+        Ok(())
+    }
+
+    /// Delete the persisted states of the event replayer. This must be called
+    /// whenever existing events are removed or reordered, since the states
+    /// can only be updated by appending events.
+    #[instrument]
+    fn clear_replayer_views(&self) -> eyre::Result<()> {
+        for table in EVENT_REPLAYER_VIEW_TABLES {
+            self.conn
+                .execute(&format!("DELETE FROM {}", table), rusqlite::params![])
+                .wrap_err("Clearing event replayer views")?;
+        }
+        Ok(())
+    }
+
+    /// Get the events which occurred between `since` and `until`, inclusive.
+    /// If either bound is `None`, then the range is unbounded in that
+    /// direction.
+    ///
+    /// Returns: The matching events, ordered from oldest to newest.
+    #[instrument]
+    pub fn get_events_in_range(
+        &self,
+        since: Option<SystemTime>,
+        until: Option<SystemTime>,
+    ) -> eyre::Result<Vec<Event>> {
+        let to_timestamp = |time: SystemTime| -> eyre::Result<f64> {
+            Ok(time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64())
+        };
+        let since = since.map(to_timestamp).transpose()?;
+        let until = until.map(to_timestamp).transpose()?;
+        self.query_events(
+            "(?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2)",
+            rusqlite::params![since, until],
+        )
+    }
+
+    /// Get the events which refer to the given commit. This includes
+    /// reference updates which moved a reference to or from the commit.
+    ///
+    /// Returns: The matching events, ordered from oldest to newest.
+    #[instrument]
+    pub fn get_events_for_commit(&self, commit_oid: NonZeroOid) -> eyre::Result<Vec<Event>> {
+        self.query_events(
+            "old_ref = ?1 OR new_ref = ?1",
+            rusqlite::params![commit_oid.to_string()],
+        )
+    }
+
+    /// Get the events which belong to the given event transaction.
+    ///
+    /// Returns: The matching events, ordered from oldest to newest.
+    #[instrument]
+    pub fn get_events_for_transaction(
+        &self,
+        event_tx_id: EventTransactionId,
+    ) -> eyre::Result<Vec<Event>> {
+        let EventTransactionId(event_tx_id) = event_tx_id;
+        self.query_events("event_tx_id = ?1", rusqlite::params![event_tx_id])
+    }
+
+    /// Get all the events in the database, skipping any rows which can't be
+    /// read or converted into events, such as if the event log is corrupt.
+    ///
+    /// Returns: The readable events along with their row IDs, ordered from
+    /// oldest to newest, and the number of rows which were skipped.
+    #[instrument]
+    pub fn get_events_skipping_malformed(&self) -> eyre::Result<(Vec<EventRow>, usize)> {
         let mut stmt = self.conn.prepare(
             "
-SELECT timestamp, type, event_tx_id, old_ref, new_ref, ref_name, message
+SELECT rowid, timestamp, type, event_tx_id, old_ref, new_ref, ref_name, message
 FROM event_log
 ORDER BY rowid ASC
 ",
         )?;
-        let rows: rusqlite::Result<Vec<Row>> = stmt
-            .query_map(rusqlite::params![], |row| {
-                let timestamp: f64 = row.get("timestamp")?;
-                let event_tx_id: isize = row.get("event_tx_id")?;
-                let type_: String = row.get("type")?;
-                let ref_name: Option<String> = row.get("ref_name")?;
-                let old_ref: Option<String> = row.get("old_ref")?;
-                let new_ref: Option<String> = row.get("new_ref")?;
-                let message: Option<String> = row.get("message")?;
-
-                Ok(Row {
-                    timestamp,
-                    event_tx_id,
-                    type_,
-                    ref_name: ref_name.map(OsString::from),
-                    ref1: old_ref.map(OsString::from),
-                    ref2: new_ref.map(OsString::from),
-                    message: message.map(OsString::from),
-                })
-            })?
-            .collect();
+        let mut events = Vec::new();
+        let mut num_malformed = 0;
+        for row in stmt.query_map(rusqlite::params![], read_row)? {
+            match row
+                .map_err(eyre::Error::from)
+                .and_then(|(row_id, row)| Ok((row_id, Event::try_from(row)?)))
+            {
+                Ok(event) => events.push(event),
+                Err(_) => num_malformed += 1,
+            }
+        }
+        Ok((events, num_malformed))
+    }
+
+    fn query_events(
+        &self,
+        condition: &str,
+        params: impl rusqlite::Params,
+    ) -> eyre::Result<Vec<Event>> {
+        let events = self.query_events_with_row_ids(condition, params)?;
+        Ok(events.into_iter().map(|(_row_id, event)| event).collect())
+    }
+
+    fn query_events_with_row_ids(
+        &self,
+        condition: &str,
+        params: impl rusqlite::Params,
+    ) -> eyre::Result<Vec<EventRow>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "
+SELECT rowid, timestamp, type, event_tx_id, old_ref, new_ref, ref_name, message
+FROM event_log
+WHERE {}
+ORDER BY rowid ASC
+",
+            condition
+        ))?;
+        let rows: rusqlite::Result<Vec<(i64, Row)>> = stmt.query_map(params, read_row)?.collect();
         let rows = rows?;
-        rows.into_iter().map(Event::try_from).collect()
+        rows.into_iter()
+            .map(|(row_id, row)| Ok((row_id, Event::try_from(row)?)))
+            .collect()
+    }
+
+    /// Remove old events from the event log.
+    ///
+    /// An event is considered old if it's not one of the most recent
+    /// `max_entries` events, or if it occurred more than `max_age` before
+    /// `now`. However, old events are kept if they're the most recent event for
+    /// some commit or reference, so that the current state of the repository
+    /// (such as which commits are visible) can still be reconstructed.
+    /// Superseded reference updates and commit events are removed. This means
+    /// that undoing to a point in time before the oldest remaining event may
+    /// not be possible.
+    ///
+    /// Returns: The number of events which were removed.
+    #[instrument]
+    pub fn compact(
+        &mut self,
+        now: SystemTime,
+        max_entries: Option<usize>,
+        max_age: Option<Duration>,
+    ) -> eyre::Result<usize> {
+        if max_entries.is_none() && max_age.is_none() {
+            return Ok(0);
+        }
+        let age_cutoff =
+            max_age.map(|max_age| now.checked_sub(max_age).unwrap_or(SystemTime::UNIX_EPOCH));
+
+        let events = self.query_events_with_row_ids("TRUE", rusqlite::params![])?;
+        let mut seen_commit_oids: HashSet<NonZeroOid> = HashSet::new();
+        let mut seen_ref_names: HashSet<OsString> = HashSet::new();
+        let mut pruned_row_ids = Vec::new();
+        for (num_newer_events, (row_id, event)) in events.iter().rev().enumerate() {
+            let is_recent = max_entries.map_or(true, |max_entries| num_newer_events < max_entries)
+                && age_cutoff.map_or(true, |age_cutoff| event.get_timestamp() >= age_cutoff);
+
+            let is_latest = match event {
+                Event::RefUpdateEvent { ref_name, .. } => {
+                    !should_ignore_ref_updates(ref_name) && seen_ref_names.insert(ref_name.clone())
+                }
+                Event::RewriteEvent {
+                    old_commit_oid,
+                    new_commit_oid,
+                    ..
+                } => {
+                    // Mark both commits as seen, rather than short-circuiting.
+                    let is_old_commit_latest = match old_commit_oid {
+                        MaybeZeroOid::NonZero(oid) => seen_commit_oids.insert(*oid),
+                        MaybeZeroOid::Zero => false,
+                    };
+                    let is_new_commit_latest = match new_commit_oid {
+                        MaybeZeroOid::NonZero(oid) => seen_commit_oids.insert(*oid),
+                        MaybeZeroOid::Zero => false,
+                    };
+                    is_old_commit_latest || is_new_commit_latest
+                }
+                Event::CommitEvent { commit_oid, .. }
+                | Event::ObsoleteEvent { commit_oid, .. }
+                | Event::UnobsoleteEvent { commit_oid, .. } => seen_commit_oids.insert(*commit_oid),
+                // Keep snapshots so that they can still be restored.
+                Event::WorkingCopySnapshot { .. } => true,
+            };
+
+            if !is_recent && !is_latest {
+                pruned_row_ids.push(*row_id);
+            }
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        for row_id in pruned_row_ids.iter() {
+            tx.execute(
+                "DELETE FROM event_log WHERE rowid = ?1",
+                rusqlite::params![row_id],
+            )?;
+        }
+        if !pruned_row_ids.is_empty() {
+            self.clear_replayer_views()?;
+        }
+        tx.commit()?;
+        Ok(pruned_row_ids.len())
     }
 
     #[instrument]
@@ -565,6 +1323,160 @@ ORDER BY rowid ASC
         Ok(EventTransactionId(event_tx_id))
     }
 
+    /// Get the message associated with the given transaction, if any.
+    #[instrument]
+    pub fn get_transaction_message(
+        &self,
+        event_tx_id: EventTransactionId,
+    ) -> eyre::Result<Option<String>> {
+        let message: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "
+SELECT message
+FROM event_transactions
+WHERE event_tx_id = :event_tx_id
+",
+                rusqlite::named_params! {
+                    ":event_tx_id": event_tx_id.0,
+                },
+                |row| row.get("message"),
+            )
+            .optional()
+            .wrap_err("Querying event transaction message")?;
+        Ok(message.flatten())
+    }
+
+    /// Import the event log stored at `path` into this event log.
+    ///
+    /// Older versions of git-branchless kept a separate event log for each
+    /// linked worktree under the worktree's own `.git/worktrees/<name>`
+    /// directory. This merges such an event log into the event log shared by
+    /// all worktrees. Its transactions are assigned fresh IDs, and updates to
+    /// `HEAD` are recorded as updates to `head_ref_name`, since they refer to
+    /// the worktree's `HEAD` rather than to the main worktree's. Its events
+    /// are appended after the existing events, so that the existing events
+    /// keep their order.
+    ///
+    /// The merge happens in a single transaction, which also records that
+    /// `path` was merged. If it was already merged, such as by a concurrent
+    /// process or before the event log at `path` could be moved away, then
+    /// nothing is done.
+    #[instrument]
+    pub fn merge_event_log(&mut self, path: &Path, head_ref_name: &OsStr) -> eyre::Result<()> {
+        let path = path
+            .to_str()
+            .ok_or_else(|| eyre::eyre!("Event log path is not valid UTF-8: {:?}", path))?;
+        let head_ref_name = head_ref_name.to_string_lossy();
+
+        self.conn
+            .execute("ATTACH DATABASE ?1 AS other", rusqlite::params![path])
+            .wrap_err("Attaching event log to merge")?;
+        let result = self.merge_attached_event_log(path, &head_ref_name);
+        self.conn
+            .execute("DETACH DATABASE other", rusqlite::params![])
+            .wrap_err("Detaching merged event log")?;
+        result
+    }
+
+    fn merge_attached_event_log(&mut self, path: &str, head_ref_name: &str) -> eyre::Result<()> {
+        let num_tables: isize = self.conn.query_row(
+            "
+SELECT COUNT(*)
+FROM other.sqlite_master
+WHERE type = 'table' AND name IN ('event_log', 'event_transactions')
+",
+            rusqlite::params![],
+            |row| row.get(0),
+        )?;
+        if num_tables != 2 {
+            // The event log was never initialized, so there's nothing to merge.
+            return Ok(());
+        }
+
+        // Take the write lock immediately, so that concurrent processes can't
+        // both see that the event log hasn't been merged yet.
+        let tx = rusqlite::Transaction::new_unchecked(
+            self.conn,
+            rusqlite::TransactionBehavior::Immediate,
+        )?;
+        let is_merged: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM main.merged_event_logs WHERE path = :path)",
+            rusqlite::named_params! {
+                ":path": path,
+            },
+            |row| row.get(0),
+        )?;
+        if is_merged {
+            return Ok(());
+        }
+
+        let transactions: Vec<(isize, f64, Option<String>)> = {
+            let mut stmt = self.conn.prepare(
+                "
+SELECT event_tx_id, timestamp, message
+FROM other.event_transactions
+ORDER BY event_tx_id ASC
+",
+            )?;
+            let rows = stmt.query_map(rusqlite::params![], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?;
+            rows.collect::<Result<_, _>>()?
+        };
+        for (old_event_tx_id, timestamp, message) in transactions {
+            self.conn
+                .execute(
+                    "
+INSERT INTO main.event_transactions (timestamp, message)
+VALUES (:timestamp, :message)
+",
+                    rusqlite::named_params! {
+                        ":timestamp": timestamp,
+                        ":message": message,
+                    },
+                )
+                .wrap_err("Copying event transaction")?;
+            let new_event_tx_id = self.conn.last_insert_rowid();
+            self.conn
+                .execute(
+                    "
+INSERT INTO main.event_log
+    (timestamp, type, event_tx_id, old_ref, new_ref, ref_name, message)
+SELECT
+    timestamp,
+    type,
+    :new_event_tx_id,
+    old_ref,
+    new_ref,
+    CASE WHEN ref_name = 'HEAD' THEN :head_ref_name ELSE ref_name END,
+    message
+FROM other.event_log
+WHERE event_tx_id = :old_event_tx_id
+ORDER BY rowid ASC
+",
+                    rusqlite::named_params! {
+                        ":new_event_tx_id": new_event_tx_id,
+                        ":old_event_tx_id": old_event_tx_id,
+                        ":head_ref_name": head_ref_name,
+                    },
+                )
+                .wrap_err("Copying events")?;
+        }
+
+        self.conn
+            .execute(
+                "INSERT INTO main.merged_event_logs (path) VALUES (:path)",
+                rusqlite::named_params! {
+                    ":path": path,
+                },
+            )
+            .wrap_err("Recording merged event log")?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
     /// Create a new event transaction ID to be used to insert subsequent
     /// `Event`s into the database.
     pub fn make_transaction_id(
@@ -587,7 +1499,21 @@ ORDER BY rowid ASC
 pub fn is_gc_ref(ref_name: &OsStr) -> bool {
     match ref_name.to_str() {
         None => false,
-        Some(ref_name) => ref_name.starts_with("refs/branchless/"),
+        Some(ref_name) => ref_name.starts_with(BRANCHLESS_REF_PREFIX),
+    }
+}
+
+/// Determine whether a given reference name is the name under which updates
+/// to the `HEAD` of some worktree are recorded. See
+/// [`Repo::get_head_reference_name`].
+pub fn is_head_ref(ref_name: &OsStr) -> bool {
+    match ref_name.to_str() {
+        None => false,
+        Some("HEAD") => true,
+        Some(ref_name) => match ref_name.strip_prefix("worktrees/") {
+            Some(rest) => matches!(rest.split_once('/'), Some((_, "HEAD"))),
+            None => false,
+        },
     }
 }
 
@@ -608,7 +1534,7 @@ pub fn should_ignore_ref_updates(ref_name: &OsStr) -> bool {
     )
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 enum EventClassification {
     Show,
     Hide,
@@ -634,10 +1560,11 @@ pub enum CommitActivityStatus {
     Obsolete,
 }
 
-#[derive(Debug)]
+/// An entry in the history of a commit. The event itself can be looked up by
+/// its ID in `EventReplayer::events`, rather than being copied here.
+#[derive(Debug, PartialEq)]
 struct EventInfo {
     id: isize,
-    event: Event,
     event_classification: EventClassification,
 }
 
@@ -667,6 +1594,11 @@ pub struct EventReplayer {
     /// The name of the reference representing the main branch.
     main_branch_reference_name: OsString,
 
+    /// The name under which updates to `HEAD` are recorded for the worktree
+    /// which the events are being replayed for. See
+    /// [`Repo::get_head_reference_name`].
+    head_reference_name: OsString,
+
     /// The events that have affected each commit.
     commit_history: HashMap<NonZeroOid, Vec<EventInfo>>,
 
@@ -676,6 +1608,57 @@ pub struct EventReplayer {
     /// If an entry is not present, it was either never observed, or it most
     /// recently changed to point to the zero hash (i.e. it was deleted).
     ref_locations: HashMap<OsString, NonZeroOid>,
+
+    /// The IDs of the reference deletion events whose missing `old_oid` was
+    /// filled in from `ref_locations`. See `fix_event_git_v2_31`.
+    patched_old_oids: HashMap<isize, NonZeroOid>,
+}
+
+/// The state of an `EventReplayer` after replaying a prefix of the event log,
+/// as persisted in the `event_replayer_*` tables. Storing it lets the replayer
+/// process only the events added since, rather than replaying the whole event
+/// log.
+///
+/// When updating the persisted state, only the entries affected by the newly
+/// replayed events are included.
+#[derive(Debug)]
+struct EventReplayerView {
+    header: EventReplayerViewHeader,
+    commit_history: Vec<(String, Vec<(isize, EventClassification)>)>,
+    ref_locations: Vec<(String, String)>,
+
+    /// The references which were deleted by the newly replayed events, and
+    /// so no longer have a location.
+    removed_ref_names: Vec<String>,
+
+    patched_old_oids: Vec<(isize, String)>,
+}
+
+/// Describes which part of the event log an `EventReplayerView` covers.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct EventReplayerViewHeader {
+    /// The number of event log rows which were replayed.
+    num_rows: usize,
+
+    /// The row ID, timestamp, and transaction ID of the last replayed row, used
+    /// to check that the replayed rows are still at the start of the event
+    /// log.
+    last_row: Option<(i64, f64, isize)>,
+
+    /// The number of events kept by the replayer. See `EventReplayer::events`.
+    num_events: usize,
+}
+
+fn get_view_last_row(rows: &[EventRow]) -> Option<(i64, f64, isize)> {
+    rows.last().map(|(row_id, event)| {
+        let timestamp = event
+            .get_timestamp()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs_f64())
+            .unwrap_or_default();
+        (*row_id, timestamp, event.get_event_tx_id().0)
+    })
 }
 
 impl std::fmt::Debug for EventReplayer {
@@ -690,18 +1673,26 @@ impl std::fmt::Debug for EventReplayer {
 }
 
 impl EventReplayer {
-    fn new_inner(main_branch_reference_name: OsString) -> Self {
+    fn new_inner(main_branch_reference_name: OsString, head_reference_name: OsString) -> Self {
         EventReplayer {
             id_counter: 0,
             events: vec![],
             main_branch_reference_name,
+            head_reference_name,
             commit_history: HashMap::new(),
             ref_locations: HashMap::new(),
+            patched_old_oids: HashMap::new(),
         }
     }
 
-    fn new(main_branch_reference_name: impl Into<OsString>) -> Self {
-        Self::new_inner(main_branch_reference_name.into())
+    fn new(
+        main_branch_reference_name: impl Into<OsString>,
+        head_reference_name: impl Into<OsString>,
+    ) -> Self {
+        Self::new_inner(
+            main_branch_reference_name.into(),
+            head_reference_name.into(),
+        )
     }
 
     /// Construct the replayer from all the events in the database.
@@ -714,17 +1705,279 @@ impl EventReplayer {
         effects: &Effects,
         repo: &Repo,
         event_log_db: &EventLogDb,
+    ) -> eyre::Result<Self> {
+        let rows = event_log_db.get_events_with_row_ids()?;
+        Self::from_rows(effects, repo, event_log_db, rows)
+    }
+
+    /// Construct the replayer from the given rows of `event_log_db`, ordered
+    /// by row ID.
+    ///
+    /// The replayer state is persisted in `event_log_db` after replaying the
+    /// rows. If a persisted state covering a prefix of `rows` is found, it's
+    /// restored and only the remaining rows are replayed. If the persisted
+    /// state doesn't match the rows (for example, because the event log was
+    /// compacted), all the rows are replayed instead.
+    #[instrument(skip(rows))]
+    fn from_rows(
+        effects: &Effects,
+        repo: &Repo,
+        event_log_db: &EventLogDb,
+        rows: Vec<EventRow>,
     ) -> eyre::Result<Self> {
         let (_effects, _progress) = effects.start_operation(OperationType::ProcessEvents);
 
         let main_branch_reference_name = repo.get_main_branch_reference()?.get_name()?;
-        let mut result = EventReplayer::new(main_branch_reference_name);
-        for event in event_log_db.get_events()? {
-            result.process_event(&event);
+        let mut result =
+            EventReplayer::new(main_branch_reference_name, repo.get_head_reference_name());
+        let num_restored_rows = match event_log_db.get_replayer_view(
+            &result.main_branch_reference_name,
+            &result.head_reference_name,
+        )? {
+            Some(view) => match result.restore_view(view, &rows) {
+                Some(num_restored_rows) => Some(num_restored_rows),
+                None => {
+                    warn!("Event replayer view is stale, replaying all events");
+                    result = EventReplayer::new(
+                        result.main_branch_reference_name,
+                        result.head_reference_name,
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
+        // Only the rows after the ones covered by the view have to be
+        // replayed.
+        let num_restored_events = result.events.len();
+        for (_row_id, event) in &rows[num_restored_rows.unwrap_or_default()..] {
+            result.process_event(event);
+        }
+        if num_restored_rows != Some(rows.len()) {
+            let tx = event_log_db.conn.unchecked_transaction()?;
+            if num_restored_rows.is_none() {
+                event_log_db.clear_replayer_view(
+                    &result.main_branch_reference_name,
+                    &result.head_reference_name,
+                )?;
+            }
+            event_log_db.set_replayer_view(
+                &result.main_branch_reference_name,
+                &result.head_reference_name,
+                &result.make_view(&rows, num_restored_events),
+            )?;
+            tx.commit()?;
         }
         Ok(result)
     }
 
+    /// Construct the replayer from its persisted state, without replaying any
+    /// events. See `EventReplayer::from_rows`.
+    ///
+    /// Returns: The constructed replayer, or `None` if the persisted state
+    /// doesn't cover all of `rows`.
+    #[instrument(skip(rows))]
+    fn restore_from_rows(
+        repo: &Repo,
+        event_log_db: &EventLogDb,
+        rows: &[EventRow],
+    ) -> eyre::Result<Option<Self>> {
+        let main_branch_reference_name = repo.get_main_branch_reference()?.get_name()?;
+        let mut result =
+            EventReplayer::new(main_branch_reference_name, repo.get_head_reference_name());
+        let view = match event_log_db.get_replayer_view(
+            &result.main_branch_reference_name,
+            &result.head_reference_name,
+        )? {
+            Some(view) => view,
+            None => return Ok(None),
+        };
+        if result.restore_view(view, rows) == Some(rows.len()) {
+            Ok(Some(result))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Make the view to persist after replaying `rows`. Only the entries
+    /// affected by the events starting at `since_event_id` are included, so
+    /// that the persisted view can be updated incrementally. Pass `0` to
+    /// include every entry.
+    fn make_view(&self, rows: &[EventRow], since_event_id: usize) -> EventReplayerView {
+        let mut commit_oids = HashSet::new();
+        let mut ref_names = HashSet::new();
+        for event in self.events.get(since_event_id..).unwrap_or_default() {
+            match event {
+                Event::RewriteEvent {
+                    old_commit_oid,
+                    new_commit_oid,
+                    ..
+                } => {
+                    for commit_oid in [old_commit_oid, new_commit_oid] {
+                        if let MaybeZeroOid::NonZero(commit_oid) = commit_oid {
+                            commit_oids.insert(*commit_oid);
+                        }
+                    }
+                }
+                Event::RefUpdateEvent { ref_name, .. } => {
+                    ref_names.insert(ref_name);
+                }
+                Event::CommitEvent { commit_oid, .. }
+                | Event::ObsoleteEvent { commit_oid, .. }
+                | Event::UnobsoleteEvent { commit_oid, .. } => {
+                    commit_oids.insert(*commit_oid);
+                }
+                Event::WorkingCopySnapshot { .. } => {}
+            }
+        }
+
+        let mut ref_locations = Vec::new();
+        let mut removed_ref_names = Vec::new();
+        for ref_name in ref_names {
+            let ref_name_str = ref_name.to_string_lossy().into_owned();
+            match self.ref_locations.get(ref_name) {
+                Some(oid) => ref_locations.push((ref_name_str, oid.to_string())),
+                None => removed_ref_names.push(ref_name_str),
+            }
+        }
+
+        let since_event_id = isize::try_from(since_event_id).unwrap_or(isize::MAX);
+        EventReplayerView {
+            header: EventReplayerViewHeader {
+                num_rows: rows.len(),
+                last_row: get_view_last_row(rows),
+                num_events: self.events.len(),
+            },
+            commit_history: commit_oids
+                .into_iter()
+                .filter_map(|oid| {
+                    let history = self.commit_history.get(&oid)?;
+                    Some((
+                        oid.to_string(),
+                        history
+                            .iter()
+                            .map(|event_info| (event_info.id, event_info.event_classification))
+                            .collect(),
+                    ))
+                })
+                .collect(),
+            ref_locations,
+            removed_ref_names,
+            patched_old_oids: self
+                .patched_old_oids
+                .iter()
+                .filter(|(id, _oid)| **id >= since_event_id)
+                .map(|(id, oid)| (*id, oid.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Restore the replayer state from the given persisted view, if it's
+    /// consistent with `rows`. The events for the rows covered by the view are
+    /// still added to `events`, so that cursors can refer to them, but they
+    /// don't have to be replayed.
+    ///
+    /// Returns: The number of rows covered by the view, or `None` if the view
+    /// couldn't be read or is stale.
+    fn restore_view(&mut self, view: EventReplayerView, rows: &[EventRow]) -> Option<usize> {
+        let EventReplayerView {
+            header:
+                EventReplayerViewHeader {
+                    num_rows,
+                    last_row,
+                    num_events,
+                },
+            commit_history,
+            ref_locations,
+            removed_ref_names: _,
+            patched_old_oids,
+        } = view;
+
+        // The rows covered by the view are the ones up to and including its
+        // last row.
+        let last_row_id = last_row.map(|(row_id, _timestamp, _event_tx_id)| row_id);
+        let restored_rows = match last_row_id {
+            Some(last_row_id) => {
+                &rows[..rows.partition_point(|(row_id, _event)| *row_id <= last_row_id)]
+            }
+            None => &rows[..0],
+        };
+        if restored_rows.len() != num_rows || get_view_last_row(restored_rows) != last_row {
+            return None;
+        }
+
+        for (id, oid) in patched_old_oids {
+            self.patched_old_oids.insert(id, oid.parse().ok()?);
+        }
+        for (_row_id, event) in restored_rows {
+            if let Some(event) = self.fix_event_from_view(event) {
+                self.events.push(event);
+                self.id_counter += 1;
+            }
+        }
+        if self.events.len() != num_events {
+            return None;
+        }
+
+        let num_events: isize = num_events.try_into().ok()?;
+        for (oid, history) in commit_history {
+            let history = history
+                .into_iter()
+                .map(|(id, event_classification)| {
+                    if (0..num_events).contains(&id) {
+                        Some(EventInfo {
+                            id,
+                            event_classification,
+                        })
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Option<Vec<_>>>()?;
+            self.commit_history.insert(oid.parse().ok()?, history);
+        }
+        for (ref_name, oid) in ref_locations {
+            self.ref_locations
+                .insert(OsString::from(ref_name), oid.parse().ok()?);
+        }
+        Some(num_rows)
+    }
+
+    /// Apply the same fixes to the given event as `process_event` would have,
+    /// using the `patched_old_oids` restored from a view instead of the
+    /// `ref_locations` at the time.
+    fn fix_event_from_view(&self, event: &Event) -> Option<Event> {
+        if let Event::RefUpdateEvent { ref_name, .. } = event {
+            if should_ignore_ref_updates(ref_name) {
+                return None;
+            }
+        }
+
+        let event = match event {
+            Event::RefUpdateEvent {
+                timestamp,
+                event_tx_id,
+                ref_name,
+                old_oid: MaybeZeroOid::Zero,
+                new_oid: MaybeZeroOid::Zero,
+                message,
+            } => match self.patched_old_oids.get(&self.id_counter) {
+                Some(old_oid) => Event::RefUpdateEvent {
+                    timestamp: *timestamp,
+                    event_tx_id: *event_tx_id,
+                    ref_name: ref_name.clone(),
+                    old_oid: MaybeZeroOid::NonZero(*old_oid),
+                    new_oid: MaybeZeroOid::Zero,
+                    message: message.clone(),
+                },
+                None => event.clone(),
+            },
+            event => event.clone(),
+        };
+        self.drop_duplicate_ref_deletion(event)
+    }
+
     /// Process the given event.
     ///
     /// This also sets the event cursor to point to immediately after the event
@@ -741,16 +1994,29 @@ impl EventReplayer {
             }
         }
 
+        let id = self.id_counter;
         let event = match self.fix_event_git_v2_31(event.clone()) {
             None => {
                 return;
             }
-            Some(event) => {
-                self.events.push(event);
+            Some(fixed_event) => {
+                if let (
+                    Event::RefUpdateEvent {
+                        old_oid: MaybeZeroOid::Zero,
+                        ..
+                    },
+                    Event::RefUpdateEvent {
+                        old_oid: MaybeZeroOid::NonZero(old_oid),
+                        ..
+                    },
+                ) = (event, &fixed_event)
+                {
+                    self.patched_old_oids.insert(id, *old_oid);
+                }
+                self.events.push(fixed_event);
                 self.events.last().unwrap()
             }
         };
-        let id = self.id_counter;
         self.id_counter += 1;
 
         match &event {
@@ -766,7 +2032,6 @@ impl EventReplayer {
                         .or_insert_with(Vec::new)
                         .push(EventInfo {
                             id,
-                            event: event.clone(),
                             event_classification: EventClassification::Hide,
                         });
                 }
@@ -776,7 +2041,6 @@ impl EventReplayer {
                         .or_insert_with(Vec::new)
                         .push(EventInfo {
                             id,
-                            event: event.clone(),
                             event_classification: EventClassification::Show,
                         });
                 }
@@ -807,7 +2071,6 @@ impl EventReplayer {
                 .or_insert_with(Vec::new)
                 .push(EventInfo {
                     id,
-                    event: event.clone(),
                     event_classification: EventClassification::Show,
                 }),
 
@@ -815,13 +2078,13 @@ impl EventReplayer {
                 timestamp: _,
                 event_tx_id: _,
                 commit_oid,
+                message: _,
             } => self
                 .commit_history
                 .entry(*commit_oid)
                 .or_insert_with(Vec::new)
                 .push(EventInfo {
                     id,
-                    event: event.clone(),
                     event_classification: EventClassification::Hide,
                 }),
 
@@ -835,9 +2098,11 @@ impl EventReplayer {
                 .or_insert_with(Vec::new)
                 .push(EventInfo {
                     id,
-                    event: event.clone(),
                     event_classification: EventClassification::Show,
                 }),
+
+            // A snapshot doesn't change the state of any commits or references.
+            Event::WorkingCopySnapshot { .. } => {}
         };
     }
 
@@ -869,7 +2134,10 @@ impl EventReplayer {
 
             _ => event,
         };
+        self.drop_duplicate_ref_deletion(event)
+    }
 
+    fn drop_duplicate_ref_deletion(&self, event: Event) -> Option<Event> {
         match (event, self.events.last()) {
             // Sometimes, Git v2.31 will issue multiple delete reference
             // transactions (one for the unpacked refs, and one for the packed
@@ -899,6 +2167,11 @@ impl EventReplayer {
         }
     }
 
+    fn get_event_info_event(&self, event_info: &EventInfo) -> &Event {
+        let id: usize = event_info.id.try_into().unwrap();
+        &self.events[id]
+    }
+
     fn get_cursor_commit_history(&self, cursor: EventCursor, oid: NonZeroOid) -> Vec<&EventInfo> {
         match self.commit_history.get(&oid) {
             None => vec![],
@@ -920,13 +2193,11 @@ impl EventReplayer {
         match history.last() {
             Some(EventInfo {
                 id: _,
-                event: _,
                 event_classification: EventClassification::Show,
             }) => CommitActivityStatus::Active,
 
             Some(EventInfo {
                 id: _,
-                event: _,
                 event_classification: EventClassification::Hide,
             }) => CommitActivityStatus::Obsolete,
 
@@ -949,7 +2220,24 @@ impl EventReplayer {
     ) -> Option<&Event> {
         let history = self.get_cursor_commit_history(cursor, oid);
         let event_info = *history.last()?;
-        Some(&event_info.event)
+        Some(self.get_event_info_event(event_info))
+    }
+
+    /// Get the message provided when the given commit was most recently
+    /// hidden with `git hide`, as of the cursor's point in time.
+    pub fn get_cursor_commit_hide_message(
+        &self,
+        cursor: EventCursor,
+        oid: NonZeroOid,
+    ) -> Option<&OsStr> {
+        let history = self.get_cursor_commit_history(cursor, oid);
+        history
+            .iter()
+            .rev()
+            .find_map(|event_info| match self.get_event_info_event(event_info) {
+                Event::ObsoleteEvent { message, .. } => Some(message.as_deref()),
+                _ => None,
+            })?
     }
 
     /// Get all OIDs which have been observed so far. This should be the set of
@@ -1075,7 +2363,8 @@ impl EventReplayer {
         }
     }
 
-    /// Get the OID of `HEAD` at the cursor's point in time.
+    /// Get the OID of `HEAD` for the current worktree at the cursor's point in
+    /// time.
     ///
     /// Returns: The OID pointed to by `HEAD` at that time, or `None` if `HEAD`
     /// was never observed.
@@ -1090,7 +2379,7 @@ impl EventReplayer {
                         ref_name,
                         new_oid: MaybeZeroOid::NonZero(new_oid),
                         ..
-                    } if ref_name == "HEAD" => Some(*new_oid),
+                    } if *ref_name == self.head_reference_name => Some(*new_oid),
                     Event::RefUpdateEvent { .. } => None,
 
                     // Not strictly necessary, but helps to compensate in case
@@ -1100,7 +2389,8 @@ impl EventReplayer {
 
                     Event::RewriteEvent { .. }
                     | Event::ObsoleteEvent { .. }
-                    | Event::UnobsoleteEvent { .. } => None,
+                    | Event::UnobsoleteEvent { .. }
+                    | Event::WorkingCopySnapshot { .. } => None,
                 }
             })
     }
@@ -1272,6 +2562,138 @@ impl EventReplayer {
     }
 }
 
+/// A problem encountered while reading the event log, indicating that it's
+/// corrupt or truncated.
+#[derive(Debug)]
+pub enum EventLogCorruption {
+    /// The event log database couldn't be opened or read at all.
+    Unreadable {
+        /// The error encountered while reading the event log.
+        error: eyre::Error,
+    },
+
+    /// Some events couldn't be read, and were skipped.
+    MalformedEvents {
+        /// The number of events which were skipped.
+        num_malformed: usize,
+    },
+}
+
+impl Display for EventLogCorruption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventLogCorruption::Unreadable { error } => {
+                write!(f, "the event log could not be read: {}", error.root_cause())
+            }
+            EventLogCorruption::MalformedEvents { num_malformed } => write!(
+                f,
+                "{} {} in the event log could not be read",
+                num_malformed,
+                if *num_malformed == 1 {
+                    "event"
+                } else {
+                    "events"
+                }
+            ),
+        }
+    }
+}
+
+/// Open the event log and replay its events, without failing if the event log
+/// is corrupt. Unreadable events are skipped. If the event log can't be read
+/// at all, an empty in-memory event log is used instead, so that callers can
+/// still show the commits reachable from the current references.
+///
+/// Returns: The database connection to use, the constructed replayer, and the
+/// problem encountered while reading the event log, if any.
+#[instrument]
+pub fn open_event_log_best_effort(
+    effects: &Effects,
+    repo: &Repo,
+) -> eyre::Result<(
+    rusqlite::Connection,
+    EventReplayer,
+    Option<EventLogCorruption>,
+)> {
+    let (conn, events, corruption) = read_event_log_best_effort(repo)?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = replay_events(effects, repo, &event_log_db, events)?;
+    Ok((conn, event_replayer, corruption))
+}
+
+/// Read the events from the event log without replaying them, without failing
+/// if the event log is corrupt. See `open_event_log_best_effort`.
+///
+/// Returns: The database connection to use, the events which could be read
+/// along with their row IDs, and the problem encountered while reading the
+/// event log, if any.
+#[instrument]
+pub fn read_event_log_best_effort(
+    repo: &Repo,
+) -> eyre::Result<(
+    rusqlite::Connection,
+    Vec<EventRow>,
+    Option<EventLogCorruption>,
+)> {
+    let load = |conn: &rusqlite::Connection| -> eyre::Result<(Vec<EventRow>, usize)> {
+        let event_log_db = EventLogDb::new(conn)?;
+        event_log_db.get_events_skipping_malformed()
+    };
+
+    let (conn, events, corruption) = match repo.get_db_conn().and_then(|conn| {
+        let result = load(&conn)?;
+        Ok((conn, result))
+    }) {
+        Ok((conn, (events, 0))) => (conn, events, None),
+        Ok((conn, (events, num_malformed))) => (
+            conn,
+            events,
+            Some(EventLogCorruption::MalformedEvents { num_malformed }),
+        ),
+        Err(error) => {
+            error!(?error, "Could not read event log");
+            let conn = rusqlite::Connection::open_in_memory()
+                .wrap_err("Opening in-memory database connection")?;
+            EventLogDb::new(&conn)?;
+            (
+                conn,
+                Vec::new(),
+                Some(EventLogCorruption::Unreadable { error }),
+            )
+        }
+    };
+
+    Ok((conn, events, corruption))
+}
+
+/// Replay the given events, as read by `read_event_log_best_effort`. See
+/// `EventReplayer::from_event_log_db`.
+#[instrument(skip(events))]
+pub fn replay_events(
+    effects: &Effects,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    events: Vec<EventRow>,
+) -> eyre::Result<EventReplayer> {
+    EventReplayer::from_rows(effects, repo, event_log_db, events)
+}
+
+/// Restore the replayer for the given events, as read by
+/// `read_event_log_best_effort`, from its persisted state. Unlike
+/// `replay_events`, no events are replayed, so this is only useful when the
+/// persisted state is known to be up to date.
+///
+/// Returns: The restored replayer, or `None` if the persisted state doesn't
+/// cover all of `events`.
+#[instrument(skip(events))]
+pub fn restore_events(
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    events: &[EventRow],
+) -> eyre::Result<Option<EventReplayer>> {
+    EventReplayer::restore_from_rows(repo, event_log_db, events)
+}
+
 /// Testing helpers.
 pub mod testing {
     use super::*;
@@ -1299,6 +2721,9 @@ pub mod testing {
             Event::UnobsoleteEvent {
                 ref mut timestamp, ..
             } => *timestamp = 0.0,
+            Event::WorkingCopySnapshot {
+                ref mut timestamp, ..
+            } => *timestamp = 0.0,
         }
         event
     }
@@ -1313,6 +2738,7 @@ pub mod testing {
 mod tests {
     use super::*;
 
+    use crate::core::formatting::Glyphs;
     use crate::testing::make_git;
     use testing::make_dummy_transaction_id;
 
@@ -1324,7 +2750,7 @@ mod tests {
             event_tx_id,
             commit_oid: NonZeroOid::from_str("abc")?,
         };
-        let mut replayer = EventReplayer::new("refs/heads/master");
+        let mut replayer = EventReplayer::new("refs/heads/master", "HEAD");
         replayer.process_event(&meaningful_event);
         replayer.process_event(&Event::RefUpdateEvent {
             timestamp: 0.0,
@@ -1397,9 +2823,81 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_event_log_db_queries() -> eyre::Result<()> {
+        let git = make_git()?;
+
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.commit_file("test2", 2)?;
+        git.run(&["hide", &test1_oid.to_string()])?;
+
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let events = event_log_db.get_events()?;
+
+        assert_eq!(event_log_db.get_events_in_range(None, None)?, events);
+        // Commit events are timestamped with the commit time, so the events
+        // aren't necessarily in timestamp order.
+        let min_timestamp = events.iter().map(Event::get_timestamp).min().unwrap();
+        let max_timestamp = events.iter().map(Event::get_timestamp).max().unwrap();
+        assert_eq!(
+            event_log_db.get_events_in_range(
+                Some(min_timestamp - Duration::from_secs(1)),
+                Some(max_timestamp + Duration::from_secs(1))
+            )?,
+            events
+        );
+        assert_eq!(
+            event_log_db.get_events_in_range(Some(max_timestamp + Duration::from_secs(1)), None)?,
+            Vec::new()
+        );
+        assert_eq!(
+            event_log_db.get_events_in_range(None, Some(SystemTime::UNIX_EPOCH))?,
+            Vec::new()
+        );
+
+        let commit_events = event_log_db.get_events_for_commit(test1_oid)?;
+        assert!(commit_events.iter().any(|event| matches!(
+            event,
+            Event::CommitEvent { commit_oid, .. } if *commit_oid == test1_oid
+        )));
+        assert!(commit_events.iter().any(|event| matches!(
+            event,
+            Event::ObsoleteEvent { commit_oid, .. } if *commit_oid == test1_oid
+        )));
+        assert!(commit_events.iter().all(|event| match event {
+            Event::CommitEvent { commit_oid, .. }
+            | Event::ObsoleteEvent { commit_oid, .. }
+            | Event::UnobsoleteEvent { commit_oid, .. } => *commit_oid == test1_oid,
+            Event::RefUpdateEvent {
+                old_oid, new_oid, ..
+            } => {
+                *old_oid == MaybeZeroOid::NonZero(test1_oid)
+                    || *new_oid == MaybeZeroOid::NonZero(test1_oid)
+            }
+            Event::RewriteEvent {
+                old_commit_oid,
+                new_commit_oid,
+                ..
+            } => {
+                *old_commit_oid == MaybeZeroOid::NonZero(test1_oid)
+                    || *new_commit_oid == MaybeZeroOid::NonZero(test1_oid)
+            }
+            Event::WorkingCopySnapshot {
+                head_oid,
+                commit_oid,
+                ..
+            } => *head_oid == MaybeZeroOid::NonZero(test1_oid) || *commit_oid == test1_oid,
+        }));
+
+        Ok(())
+    }
+
     #[test]
     fn test_advance_cursor_by_transaction() -> eyre::Result<()> {
-        let mut event_replayer = EventReplayer::new("refs/heads/master");
+        let mut event_replayer = EventReplayer::new("refs/heads/master", "HEAD");
         for (timestamp, event_tx_id) in (0..).zip(&[1, 1, 2, 2, 3, 4]) {
             let timestamp: f64 = timestamp.try_into()?;
             event_replayer.process_event(&Event::UnobsoleteEvent {
@@ -1469,4 +2967,173 @@ mod tests {
 
         Ok(())
     }
+
+    fn assert_replayers_equal(left: &EventReplayer, right: &EventReplayer) {
+        assert_eq!(left.id_counter, right.id_counter);
+        assert_eq!(left.events, right.events);
+        assert_eq!(left.commit_history, right.commit_history);
+        assert_eq!(left.ref_locations, right.ref_locations);
+        assert_eq!(left.patched_old_oids, right.patched_old_oids);
+    }
+
+    fn make_view_test_rows() -> eyre::Result<Vec<EventRow>> {
+        let event_tx_id = make_dummy_transaction_id(1);
+        let ref_update = |ref_name: &str, old_oid: &str, new_oid: &str| -> eyre::Result<Event> {
+            Ok(Event::RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id,
+                ref_name: OsString::from(ref_name),
+                old_oid: MaybeZeroOid::from_str(old_oid)?,
+                new_oid: MaybeZeroOid::from_str(new_oid)?,
+                message: None,
+            })
+        };
+        let zero_oid = "0000000000000000000000000000000000000000";
+        let events = vec![
+            Event::CommitEvent {
+                timestamp: 1.0,
+                event_tx_id,
+                commit_oid: NonZeroOid::from_str("abc")?,
+            },
+            ref_update("refs/heads/foo", zero_oid, "abc")?,
+            ref_update("ORIG_HEAD", zero_oid, "abc")?,
+            // Deletions without an `old_oid`, as written by Git v2.31. The
+            // first is patched and the second is dropped.
+            ref_update("refs/heads/foo", zero_oid, zero_oid)?,
+            ref_update("refs/heads/foo", zero_oid, zero_oid)?,
+            Event::RewriteEvent {
+                timestamp: 2.0,
+                event_tx_id,
+                old_commit_oid: MaybeZeroOid::from_str("abc")?,
+                new_commit_oid: MaybeZeroOid::from_str("def")?,
+            },
+            Event::ObsoleteEvent {
+                timestamp: 3.0,
+                event_tx_id,
+                commit_oid: NonZeroOid::from_str("def")?,
+                message: None,
+            },
+        ];
+        Ok((1..).zip(events).collect())
+    }
+
+    #[test]
+    fn test_event_replayer_view_incremental() -> eyre::Result<()> {
+        let rows = make_view_test_rows()?;
+        let mut expected = EventReplayer::new("refs/heads/master", "HEAD");
+        for (_row_id, event) in &rows {
+            expected.process_event(event);
+        }
+        assert_eq!(expected.patched_old_oids.len(), 1);
+
+        for num_view_rows in 0..=rows.len() {
+            let mut replayer = EventReplayer::new("refs/heads/master", "HEAD");
+            for (_row_id, event) in &rows[..num_view_rows] {
+                replayer.process_event(event);
+            }
+            let view = replayer.make_view(&rows[..num_view_rows], 0);
+
+            let mut replayer = EventReplayer::new("refs/heads/master", "HEAD");
+            assert_eq!(replayer.restore_view(view, &rows), Some(num_view_rows));
+            for (_row_id, event) in &rows[num_view_rows..] {
+                replayer.process_event(event);
+            }
+            assert_replayers_equal(&replayer, &expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_replayer_view_stale() -> eyre::Result<()> {
+        let rows = make_view_test_rows()?;
+        let mut replayer = EventReplayer::new("refs/heads/master", "HEAD");
+        for (_row_id, event) in &rows[..4] {
+            replayer.process_event(event);
+        }
+        let make_view = || replayer.make_view(&rows[..4], 0);
+
+        // The view covers more rows than there are.
+        let mut stale_replayer = EventReplayer::new("refs/heads/master", "HEAD");
+        assert_eq!(stale_replayer.restore_view(make_view(), &rows[..3]), None);
+
+        // Earlier rows were removed, such as by compaction.
+        let mut stale_replayer = EventReplayer::new("refs/heads/master", "HEAD");
+        assert_eq!(stale_replayer.restore_view(make_view(), &rows[1..]), None);
+
+        // The rows were rewritten, such as by merging another event log.
+        let mut rewritten_rows = rows.clone();
+        rewritten_rows[3].1 = rows[5].1.clone();
+        let mut stale_replayer = EventReplayer::new("refs/heads/master", "HEAD");
+        assert_eq!(
+            stale_replayer.restore_view(make_view(), &rewritten_rows),
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_replayer_view_persisted() -> eyre::Result<()> {
+        let git = make_git()?;
+
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.run(&["branch", "foo"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let mut event_log_db = EventLogDb::new(&conn)?;
+        let get_view = |event_log_db: &EventLogDb| -> eyre::Result<Option<EventReplayerView>> {
+            event_log_db.get_replayer_view(
+                OsStr::new("refs/heads/master"),
+                &repo.get_head_reference_name(),
+            )
+        };
+
+        EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+        let num_rows = event_log_db.get_events()?.len();
+        assert_eq!(
+            get_view(&event_log_db)?.map(|view| view.header.num_rows),
+            Some(num_rows)
+        );
+
+        git.commit_file("test2", 2)?;
+        git.run(&["branch", "-D", "foo"])?;
+        let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+        let events = event_log_db.get_events()?;
+        assert!(events.len() > num_rows);
+        assert_eq!(
+            get_view(&event_log_db)?.map(|view| view.header.num_rows),
+            Some(events.len())
+        );
+
+        let mut expected = EventReplayer::new("refs/heads/master", repo.get_head_reference_name());
+        for event in &events {
+            expected.process_event(event);
+        }
+        assert_replayers_equal(&event_replayer, &expected);
+
+        // The view was only updated with the entries affected by the new
+        // events, so check that restoring it produces the same state.
+        let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+        assert_replayers_equal(&event_replayer, &expected);
+        assert!(!event_replayer
+            .ref_locations
+            .contains_key(OsStr::new("refs/heads/foo")));
+
+        // A view written by an incompatible version is ignored.
+        conn.execute(
+            "UPDATE event_replayer_views SET view = 'not a view'",
+            rusqlite::params![],
+        )?;
+        assert!(get_view(&event_log_db)?.is_none());
+        let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+        assert_replayers_equal(&event_replayer, &expected);
+
+        event_log_db.compact(SystemTime::now(), Some(1), None)?;
+        assert!(get_view(&event_log_db)?.is_none());
+
+        Ok(())
+    }
 }
@@ -4,11 +4,14 @@
 //! "TTY"). In the case of interactive output, we render with prettier non-ASCII
 //! characters and with colors, using shell-specific escape codes.
 
+use std::collections::HashSet;
 use std::fmt::Display;
 
-use cursive::theme::{Effect, Style};
+use cursive::theme::{BaseColor, Color, ColorType, Effect, Style};
+use cursive::utils::lines::spans::LinesIterator;
 use cursive::utils::markup::StyledString;
 use cursive::utils::span::Span;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// Pluralize a quantity, as appropriate. Example:
 ///
@@ -86,7 +89,12 @@ pub struct Glyphs {
     pub vertical_ellipsis: &'static str,
 
     /// Line used to connect a parent commit to its non-first child commit.
-    pub slash: &'static str,
+    pub split: &'static str,
+
+    /// Line used to connect a child commit to its non-first parent commit.
+    /// This is used in place of `split` when rendering the graph in reverse
+    /// order.
+    pub merge: &'static str,
 
     /// Cursor for a normal visible commit which is not currently checked out.
     pub commit_visible: &'static str,
@@ -119,6 +127,15 @@ pub struct Glyphs {
     /// Character used to point to the currently-checked-out branch.
     pub branch_arrow: &'static str,
 
+    /// Prefix for the number of commits a branch is ahead of its upstream.
+    pub branch_ahead: &'static str,
+
+    /// Prefix for the number of commits a branch is behind its upstream.
+    pub branch_behind: &'static str,
+
+    /// Marks the end of text which was cut off because it didn't fit.
+    pub ellipsis: &'static str,
+
     /// Bullet-point character for a list of newline-separated items.
     pub bullet_point: &'static str,
 
@@ -136,6 +153,15 @@ pub struct Glyphs {
 
     /// Corner at the lower left of the arrow used when printing a commit cycle.
     pub cycle_lower_left_corner: &'static str,
+
+    /// Icon for a commit which passed a test command.
+    pub test_passed: &'static str,
+
+    /// Icon for a commit which failed a test command.
+    pub test_failed: &'static str,
+
+    /// The styles with which to render elements of the smartlog.
+    pub theme: SmartlogTheme,
 }
 
 impl Glyphs {
@@ -156,7 +182,8 @@ impl Glyphs {
             line: "|",
             line_with_offshoot: "|",
             vertical_ellipsis: ":",
-            slash: "\\",
+            split: "\\",
+            merge: "/",
             commit_visible: "o",
             commit_visible_head: "@",
             commit_obsolete: "x",
@@ -166,12 +193,18 @@ impl Glyphs {
             commit_main_obsolete: "X",
             commit_main_obsolete_head: "%",
             branch_arrow: ">",
+            branch_ahead: "+",
+            branch_behind: "-",
+            ellipsis: "...",
             bullet_point: "-",
             cycle_arrow: ">",
             cycle_horizontal_line: "-",
             cycle_vertical_line: "|",
             cycle_upper_left_corner: ",",
             cycle_lower_left_corner: "`",
+            test_passed: "[pass]",
+            test_failed: "[FAIL]",
+            theme: SmartlogTheme::default(),
         }
     }
 
@@ -182,7 +215,8 @@ impl Glyphs {
             line: "┃",
             line_with_offshoot: "┣",
             vertical_ellipsis: "⋮",
-            slash: "━┓",
+            split: "━┓",
+            merge: "━┛",
             commit_visible: "◯",
             commit_visible_head: "●",
             commit_obsolete: "✕",
@@ -192,16 +226,249 @@ impl Glyphs {
             commit_main_obsolete: "✕",
             commit_main_obsolete_head: "❖",
             branch_arrow: "ᐅ",
+            branch_ahead: "↑",
+            branch_behind: "↓",
+            ellipsis: "…",
             bullet_point: "•",
             cycle_arrow: "ᐅ",
             cycle_horizontal_line: "─",
             cycle_vertical_line: "│",
             cycle_upper_left_corner: "┌",
             cycle_lower_left_corner: "└",
+            test_passed: "✔",
+            test_failed: "✘",
+            theme: SmartlogTheme::default(),
+        }
+    }
+
+    /// Replace the graph and commit cursor glyphs with plain ASCII equivalents,
+    /// for terminals which can't render the pretty glyphs. Whether ANSI escape
+    /// codes are written is left unchanged.
+    pub fn ascii(self, ascii: bool) -> Self {
+        if ascii {
+            Glyphs {
+                should_write_ansi_escape_codes: self.should_write_ansi_escape_codes,
+                theme: self.theme,
+                ..Glyphs::text()
+            }
+        } else {
+            self
+        }
+    }
+
+    /// A one-line key explaining what each commit cursor and the branch arrow
+    /// mean in the smartlog. Glyphs which are shared between several kinds of
+    /// commits are only explained once.
+    pub fn legend(&self) -> String {
+        let entries = [
+            (self.commit_main, "public commit"),
+            (self.commit_visible, "draft commit"),
+            (self.commit_obsolete, "hidden commit"),
+            (self.commit_main_obsolete, "hidden public commit"),
+            (self.commit_visible_head, "current commit"),
+            (self.commit_main_head, "current public commit"),
+            (self.commit_obsolete_head, "current hidden commit"),
+            (
+                self.commit_main_obsolete_head,
+                "current hidden public commit",
+            ),
+            (self.branch_arrow, "current branch"),
+        ];
+        let mut seen_glyphs = HashSet::new();
+        let entries = entries
+            .iter()
+            .filter(|(glyph, _description)| seen_glyphs.insert(*glyph))
+            .map(|(glyph, description)| format!("{} {}", glyph, description))
+            .collect::<Vec<_>>();
+        format!("Legend: {}", entries.join(", "))
+    }
+
+    /// Swap the glyphs which depend on the vertical direction of the graph, so
+    /// that the graph can be rendered with the newest commits at the top.
+    pub fn reverse_order(mut self, reverse: bool) -> Self {
+        if reverse {
+            std::mem::swap(&mut self.split, &mut self.merge);
+        }
+        self
+    }
+
+    /// Use the provided styles when rendering the smartlog.
+    pub fn theme(self, theme: SmartlogTheme) -> Self {
+        Glyphs { theme, ..self }
+    }
+}
+
+/// How many hexadecimal digits to show when abbreviating a commit hash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Abbrev {
+    /// Always show exactly this many digits.
+    Fixed(usize),
+
+    /// Show at least this many digits, and more if necessary for the
+    /// abbreviated hash to identify a unique object in the repository.
+    Unique(usize),
+}
+
+impl Default for Abbrev {
+    fn default() -> Self {
+        Abbrev::Fixed(8)
+    }
+}
+
+impl Abbrev {
+    /// The fewest number of digits which Git will accept when resolving an
+    /// abbreviated hash.
+    const MIN_LEN: usize = 4;
+
+    /// The number of digits in a full hash.
+    const MAX_LEN: usize = 40;
+
+    /// Parse a value in the format accepted by Git's `core.abbrev` option:
+    /// either a number of digits, `auto` to determine the number of digits
+    /// automatically, or a false boolean value to show the full hash. Returns
+    /// `None` if the value couldn't be parsed.
+    ///
+    /// ```
+    /// # use branchless::core::formatting::Abbrev;
+    /// assert_eq!(Abbrev::parse("12"), Some(Abbrev::Unique(12)));
+    /// assert_eq!(Abbrev::parse("auto"), Some(Abbrev::Unique(7)));
+    /// assert_eq!(Abbrev::parse("no"), Some(Abbrev::Fixed(40)));
+    /// assert_eq!(Abbrev::parse("foo"), None);
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "auto" => Some(Abbrev::Unique(7)),
+            "false" | "no" | "off" => Some(Abbrev::Fixed(Self::MAX_LEN)),
+            value => {
+                let len: usize = value.parse().ok()?;
+                Some(Abbrev::Unique(len.clamp(Self::MIN_LEN, Self::MAX_LEN)))
+            }
+        }
+    }
+
+    /// The minimum number of digits to show.
+    pub fn min_len(&self) -> usize {
+        match self {
+            Abbrev::Fixed(len) | Abbrev::Unique(len) => *len,
         }
     }
 }
 
+/// The styles with which to render elements of the smartlog. Only applied if
+/// ANSI escape codes are being written.
+#[derive(Clone, Debug)]
+pub struct SmartlogTheme {
+    /// Style for the abbreviated commit hash.
+    pub commit_oid: Style,
+
+    /// Style for the first line of the commit message.
+    pub commit_message: Style,
+
+    /// Style for the branch names pointing to a commit.
+    pub branches: Style,
+
+    /// Style for the cursor of the commit which is currently checked out.
+    pub commit_head: Style,
+
+    /// Style for the cursor of a visible commit.
+    pub commit_visible: Style,
+
+    /// Style for the cursor of an obsolete commit.
+    pub commit_obsolete: Style,
+
+    /// Style for the cursor of a commit belonging to the main branch.
+    pub commit_main: Style,
+
+    /// Style for the cursor of an obsolete commit belonging to the main
+    /// branch.
+    pub commit_main_obsolete: Style,
+
+    /// Style for the whole line of a commit which was requested to be
+    /// highlighted.
+    pub commit_highlighted: Style,
+}
+
+impl Default for SmartlogTheme {
+    fn default() -> Self {
+        SmartlogTheme {
+            commit_oid: BaseColor::Yellow.dark().into(),
+            commit_message: Style::none(),
+            branches: BaseColor::Green.light().into(),
+            commit_head: Style::none(),
+            commit_visible: Style::none(),
+            commit_obsolete: BaseColor::Black.light().into(),
+            commit_main: Style::none(),
+            commit_main_obsolete: BaseColor::Black.light().into(),
+            commit_highlighted: Style::from(BaseColor::Magenta.light()).combine(Effect::Bold),
+        }
+    }
+}
+
+/// Parse a style in the same format as Git's color configuration values, e.g.
+/// `bold red` or `brightblue ul`. Only a foreground color is supported.
+///
+/// ```
+/// # use branchless::core::formatting::parse_style;
+/// assert!(parse_style("bold brightred").is_ok());
+/// assert!(parse_style("red blue").is_err());
+/// assert!(parse_style("chartreuse").is_err());
+/// ```
+pub fn parse_style(value: &str) -> Result<Style, String> {
+    let mut style = Style::none();
+    let mut has_color = false;
+    for word in value.split_whitespace() {
+        let word = word.to_lowercase();
+        let effect = match word.as_str() {
+            "bold" => Some(Effect::Bold),
+            "dim" => Some(Effect::Dim),
+            "italic" => Some(Effect::Italic),
+            "ul" | "underline" => Some(Effect::Underline),
+            "blink" => Some(Effect::Blink),
+            "reverse" => Some(Effect::Reverse),
+            _ => None,
+        };
+        if let Some(effect) = effect {
+            style.effects.insert(effect);
+            continue;
+        }
+
+        let color = match word.as_str() {
+            "normal" | "default" => Color::TerminalDefault,
+            word => {
+                let (is_bright, color_name) = match word.strip_prefix("bright") {
+                    Some(color_name) => (true, color_name),
+                    None => (false, word),
+                };
+                let base_color = match color_name {
+                    "black" => BaseColor::Black,
+                    "red" => BaseColor::Red,
+                    "green" => BaseColor::Green,
+                    "yellow" => BaseColor::Yellow,
+                    "blue" => BaseColor::Blue,
+                    "magenta" => BaseColor::Magenta,
+                    "cyan" => BaseColor::Cyan,
+                    "white" => BaseColor::White,
+                    _ => return Err(format!("unknown color or attribute: {}", word)),
+                };
+                if is_bright {
+                    Color::Light(base_color)
+                } else {
+                    Color::Dark(base_color)
+                }
+            }
+        };
+        if has_color {
+            return Err(format!(
+                "only a foreground color is supported, but got a second color: {}",
+                word
+            ));
+        }
+        style.color.front = ColorType::Color(color);
+        has_color = true;
+    }
+    Ok(style)
+}
+
 impl std::fmt::Debug for Glyphs {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -310,6 +577,244 @@ pub fn set_effect(mut string: StyledString, effect: Effect) -> StyledString {
     string
 }
 
+/// Apply the provided style to all the internal spans of the styled string,
+/// overriding their colors.
+pub fn set_style(mut string: StyledString, style: Style) -> StyledString {
+    string.spans_raw_attr_mut().for_each(|span| {
+        *span.attr = span.attr.combine(style);
+    });
+    string
+}
+
+/// The colors used to tell stacks of commits apart in the smartlog with
+/// `--color-by-stack`, in the order in which they're assigned.
+const STACK_COLORS: [BaseColor; 5] = [
+    BaseColor::Cyan,
+    BaseColor::Green,
+    BaseColor::Yellow,
+    BaseColor::Blue,
+    BaseColor::Red,
+];
+
+/// Get the style for rendering the stack with the given index, cycling through
+/// a fixed set of colors.
+pub fn get_stack_style(stack_index: usize) -> Style {
+    Style::from(STACK_COLORS[stack_index % STACK_COLORS.len()].light())
+}
+
+/// How to align the cells of a column laid out by `align_columns`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnAlignment {
+    /// Pad cells on the right.
+    Left,
+
+    /// Pad cells on the left, such as for numbers.
+    Right,
+}
+
+/// Lay out rows of cells so that each column is aligned across all rows,
+/// separating adjacent columns with a single space. A `None` cell is rendered
+/// as blank, and blank cells at the end of a row are omitted. Rows with only
+/// one cell are passed through unchanged, and don't affect the column widths.
+///
+/// ```
+/// # use cursive::utils::markup::StyledString;
+/// # use branchless::core::formatting::{align_columns, ColumnAlignment};
+/// let rows = vec![
+///     vec![Some(StyledString::plain("foo")), Some(StyledString::plain("+1"))],
+///     vec![Some(StyledString::plain("|"))],
+///     vec![Some(StyledString::plain("x")), Some(StyledString::plain("+12"))],
+/// ];
+/// let lines: Vec<String> = align_columns(
+///     rows,
+///     &[ColumnAlignment::Left, ColumnAlignment::Right],
+/// )
+/// .into_iter()
+/// .map(|line| line.source().to_string())
+/// .collect();
+/// assert_eq!(lines, vec!["foo  +1", "|", "x   +12"]);
+/// ```
+pub fn align_columns(
+    rows: Vec<Vec<Option<StyledString>>>,
+    alignments: &[ColumnAlignment],
+) -> Vec<StyledString> {
+    let mut column_widths: Vec<usize> = Vec::new();
+    for row in rows.iter().filter(|row| row.len() > 1) {
+        for (i, cell) in row.iter().enumerate() {
+            let width = cell.as_ref().map(|cell| cell.width()).unwrap_or_default();
+            match column_widths.get_mut(i) {
+                Some(column_width) => *column_width = (*column_width).max(width),
+                None => column_widths.push(width),
+            }
+        }
+    }
+
+    rows.into_iter()
+        .map(|row| {
+            if row.len() <= 1 {
+                return row.into_iter().flatten().next().unwrap_or_default();
+            }
+
+            let num_cells = row
+                .iter()
+                .rposition(|cell| cell.is_some())
+                .map(|i| i + 1)
+                .unwrap_or_default();
+            let mut result = StyledStringBuilder::new();
+            for (i, cell) in row.into_iter().take(num_cells).enumerate() {
+                if i > 0 {
+                    result = result.append_plain(" ");
+                }
+                let cell = cell.unwrap_or_default();
+                let padding = " ".repeat(column_widths[i] - cell.width());
+                let is_last = i + 1 == num_cells;
+                result = match alignments.get(i).copied().unwrap_or(ColumnAlignment::Left) {
+                    ColumnAlignment::Left if is_last => result.append(cell),
+                    ColumnAlignment::Left => result.append(cell).append_plain(padding),
+                    ColumnAlignment::Right => result.append_plain(padding).append(cell),
+                };
+            }
+            result.build()
+        })
+        .collect()
+}
+
+/// What to do with text which is wider than the space available for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextOverflow {
+    /// Render the text in full, even if it runs past the available width.
+    Overflow,
+
+    /// Cut off the text at the available width, ending it with an ellipsis.
+    Truncate,
+
+    /// Break the text onto as many lines as necessary to fit the available
+    /// width.
+    Wrap,
+}
+
+impl Default for TextOverflow {
+    fn default() -> Self {
+        TextOverflow::Overflow
+    }
+}
+
+/// Shorten the provided string so that it's at most `width` columns wide,
+/// replacing the end of the string with `ellipsis` if any of it had to be cut
+/// off. The ellipsis itself may not fit if `width` is very small.
+///
+/// ```
+/// # use cursive::utils::markup::StyledString;
+/// # use branchless::core::formatting::truncate_styled_string;
+/// let truncated = truncate_styled_string(StyledString::plain("hello world"), 8, "...");
+/// assert_eq!(truncated.source(), "hello...");
+/// ```
+pub fn truncate_styled_string(string: StyledString, width: usize, ellipsis: &str) -> StyledString {
+    if string.width() <= width {
+        return string;
+    }
+
+    let mut remaining_width = width.saturating_sub(UnicodeWidthStr::width(ellipsis));
+    let mut result = StyledString::new();
+    for span in string.spans() {
+        let Span {
+            content,
+            attr,
+            width: span_width,
+        } = span;
+        if span_width <= remaining_width {
+            result.append_styled(content, *attr);
+            remaining_width -= span_width;
+            continue;
+        }
+
+        let mut end = 0;
+        for (i, c) in content.char_indices() {
+            let char_width = UnicodeWidthChar::width(c).unwrap_or_default();
+            if char_width > remaining_width {
+                break;
+            }
+            remaining_width -= char_width;
+            end = i + c.len_utf8();
+        }
+        result.append_styled(&content[..end], *attr);
+        break;
+    }
+    let mut result = trim_end_styled_string(result);
+    result.append_plain(ellipsis);
+    result
+}
+
+/// Remove any whitespace from the end of the provided string.
+fn trim_end_styled_string(string: StyledString) -> StyledString {
+    let spans: Vec<(String, Style)> = string
+        .spans()
+        .map(|span| (span.content.to_owned(), *span.attr))
+        .collect();
+    let num_spans = spans
+        .iter()
+        .rposition(|(content, _attr)| !content.trim_end().is_empty())
+        .map(|i| i + 1)
+        .unwrap_or_default();
+
+    let mut result = StyledString::new();
+    for (i, (content, attr)) in spans.into_iter().take(num_spans).enumerate() {
+        if i + 1 == num_spans {
+            result.append_styled(content.trim_end(), attr);
+        } else {
+            result.append_styled(content, attr);
+        }
+    }
+    result
+}
+
+/// Break the provided string into lines which are each at most `width`
+/// columns wide, preferring to break lines at whitespace. Words which are
+/// wider than `width` are split across lines.
+///
+/// ```
+/// # use cursive::utils::markup::StyledString;
+/// # use branchless::core::formatting::wrap_styled_string;
+/// let lines: Vec<String> = wrap_styled_string(&StyledString::plain("hello big world"), 9)
+///     .into_iter()
+///     .map(|line| line.source().to_string())
+///     .collect();
+/// assert_eq!(lines, vec!["hello big", "world"]);
+/// ```
+pub fn wrap_styled_string(string: &StyledString, width: usize) -> Vec<StyledString> {
+    if string.width() <= width {
+        return vec![string.clone()];
+    }
+
+    LinesIterator::new(string, width.max(1))
+        .map(|row| {
+            let mut line = StyledString::new();
+            for span in row.resolve(string) {
+                line.append_styled(span.content, *span.attr);
+            }
+            trim_end_styled_string(line)
+        })
+        .collect()
+}
+
+/// Fit the provided string into `width` columns according to `overflow`,
+/// returning the resulting lines. If `width` is `None`, the string is returned
+/// as-is.
+pub fn fit_styled_string(
+    glyphs: &Glyphs,
+    string: StyledString,
+    width: Option<usize>,
+    overflow: TextOverflow,
+) -> Vec<StyledString> {
+    match (width, overflow) {
+        (None, _) | (Some(_), TextOverflow::Overflow) => vec![string],
+        (Some(width), TextOverflow::Truncate) => {
+            vec![truncate_styled_string(string, width, glyphs.ellipsis)]
+        }
+        (Some(width), TextOverflow::Wrap) => wrap_styled_string(&string, width),
+    }
+}
+
 impl From<StyledStringBuilder> for StyledString {
     fn from(builder: StyledStringBuilder) -> Self {
         builder.build()
@@ -320,7 +825,6 @@ fn render_style_as_ansi(content: &str, style: Style) -> eyre::Result<String> {
     let Style { effects, color } = style;
     let output = {
         use console::style;
-        use cursive::theme::{BaseColor, Color, ColorType};
         let output = content.to_string();
         match color.front {
             ColorType::Palette(_) => {
@@ -370,7 +874,10 @@ fn render_style_as_ansi(content: &str, style: Style) -> eyre::Result<String> {
         output
     };
 
-    Ok(output.to_string())
+    // This function is only called if we've already decided to write ANSI
+    // escape codes (e.g. because of `--color=always`), so don't let `console`
+    // second-guess that decision based on whether stdout is a terminal.
+    Ok(output.force_styling(true).to_string())
 }
 
 /// Write the provided string to `out`, using ANSI escape codes as necessary to
@@ -1,10 +1,16 @@
 //! Core algorithms and data structures.
 
+pub mod commit_message;
 pub mod config;
 pub mod dag;
 pub mod effects;
 pub mod eventlog;
 pub mod formatting;
 pub mod node_descriptors;
+pub mod patch_ids;
+pub mod query;
 pub mod rewrite;
+pub mod smartlog_cache;
+pub mod smartlog_index;
 pub mod task;
+pub mod test_results;
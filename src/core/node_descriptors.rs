@@ -4,32 +4,151 @@
 //! commit message.
 
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
-use std::ffi::OsString;
-use std::ops::Add;
+use std::ffi::{OsStr, OsString};
+use std::ops::{Add, Deref};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
+use chrono::{FixedOffset, TimeZone};
 use cursive::theme::BaseColor;
 use cursive::utils::markup::StyledString;
 use lazy_static::lazy_static;
 use os_str_bytes::OsStrBytes;
+use rayon::{prelude::*, ThreadPool};
 use regex::Regex;
 use tracing::instrument;
 
 use crate::core::config::{
     get_commit_descriptors_branches, get_commit_descriptors_differential_revision,
-    get_commit_descriptors_relative_time,
+    get_commit_descriptors_relative_time, get_smartlog_hidden_ref_prefixes, get_smartlog_show_tags,
 };
 use crate::git::{
-    CategorizedReferenceName, Commit, NonZeroOid, Repo, RepoReferencesSnapshot,
-    ResolvedReferenceInfo,
+    CategorizedReferenceName, Commit, DiffStat, GitRunInfo, GitRunOpts, Mailmap, MaybeZeroOid,
+    NonZeroOid, Repo, RepoReferencesSnapshot, ResolvedReferenceInfo, Signature,
 };
 
-use super::eventlog::{Event, EventCursor, EventReplayer};
-use super::formatting::{Glyphs, StyledStringBuilder};
-use super::rewrite::find_rewrite_target;
+use super::eventlog::{
+    parse_force_hidden_public_hide_message, Event, EventCursor, EventReplayer, HideReason,
+    EMPTY_COMMIT_HIDE_MESSAGE, LANDED_UPSTREAM_HIDE_MESSAGE, MISSING_COMMIT_HIDE_MESSAGE,
+};
+use super::formatting::{Abbrev, Glyphs, StyledStringBuilder};
+use super::rewrite::{find_rewrite_target, RepoPool};
+use super::test_results::TestResult;
+
+/// A view of a commit which caches the information about it that's looked up
+/// repeatedly while rendering, such as its subject and author, so that each is
+/// only read from the commit object once. It dereferences to the underlying
+/// `Commit` for anything else.
+#[derive(Debug)]
+pub struct CommitView<'repo> {
+    commit: Commit<'repo>,
+    oid_string: RefCell<Option<String>>,
+    subject: RefCell<Option<OsString>>,
+    author: RefCell<Option<Signature<'static>>>,
+    parent_oids: RefCell<Option<Vec<NonZeroOid>>>,
+}
+
+impl<'repo> CommitView<'repo> {
+    /// Constructor.
+    pub fn new(commit: Commit<'repo>) -> Self {
+        Self {
+            commit,
+            oid_string: Default::default(),
+            subject: Default::default(),
+            author: Default::default(),
+            parent_oids: Default::default(),
+        }
+    }
+
+    /// Get the underlying commit.
+    pub fn get_commit(&self) -> &Commit<'repo> {
+        &self.commit
+    }
+
+    /// Get the hash of the commit, abbreviated to at most `abbrev_len` digits.
+    pub fn short_oid(&self, abbrev_len: usize) -> String {
+        let mut oid_string = self.oid_string.borrow_mut();
+        let oid_string = oid_string.get_or_insert_with(|| self.commit.get_oid().to_string());
+        oid_string[..abbrev_len.min(oid_string.len())].to_string()
+    }
+
+    /// Get the subject (first line) of the commit message. This is the same
+    /// as `Commit::get_summary`.
+    pub fn subject(&self) -> eyre::Result<OsString> {
+        let mut subject = self.subject.borrow_mut();
+        match &*subject {
+            Some(subject) => Ok(subject.clone()),
+            None => Ok(subject.insert(self.commit.get_summary()?).clone()),
+        }
+    }
+
+    /// Get the author of the commit. This is the same as
+    /// `Commit::get_author`.
+    pub fn author(&self) -> Signature<'static> {
+        self.author
+            .borrow_mut()
+            .get_or_insert_with(|| self.commit.get_author().to_owned())
+            .to_owned()
+    }
+
+    /// Get the object IDs of the parents of the commit. This is the same as
+    /// `Commit::get_parent_oids`.
+    pub fn parents(&self) -> Vec<NonZeroOid> {
+        self.parent_oids
+            .borrow_mut()
+            .get_or_insert_with(|| self.commit.get_parent_oids())
+            .clone()
+    }
+
+    /// Get a one-line description of this commit containing its OID and
+    /// subject. See `Commit::friendly_describe`.
+    pub fn describe(&self, glyphs: &Glyphs) -> eyre::Result<StyledString> {
+        render_node_descriptors(
+            glyphs,
+            &NodeObject::Commit {
+                commit: self.clone(),
+            },
+            &mut [
+                &mut CommitOidDescriptor::new(true)?,
+                &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
+            ],
+        )
+    }
+}
+
+impl<'repo> Clone for CommitView<'repo> {
+    fn clone(&self) -> Self {
+        Self {
+            commit: self.commit.clone(),
+            oid_string: self.oid_string.clone(),
+            subject: self.subject.clone(),
+            author: RefCell::new(
+                self.author
+                    .borrow()
+                    .as_ref()
+                    .map(|author| author.to_owned()),
+            ),
+            parent_oids: self.parent_oids.clone(),
+        }
+    }
+}
+
+impl<'repo> Deref for CommitView<'repo> {
+    type Target = Commit<'repo>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.commit
+    }
+}
+
+impl<'repo> From<Commit<'repo>> for CommitView<'repo> {
+    fn from(commit: Commit<'repo>) -> Self {
+        Self::new(commit)
+    }
+}
 
 /// An object which can be rendered in the smartlog.
 #[derive(Clone, Debug)]
@@ -37,7 +156,7 @@ pub enum NodeObject<'repo> {
     /// A commit.
     Commit {
         /// The commit.
-        commit: Commit<'repo>,
+        commit: CommitView<'repo>,
     },
 
     /// A commit which has been garbage collected, for which detailed
@@ -171,12 +290,21 @@ pub fn render_node_descriptors(
 #[derive(Debug)]
 pub struct CommitOidDescriptor {
     use_color: bool,
+    abbrev_len: usize,
 }
 
 impl CommitOidDescriptor {
     /// Constructor.
     pub fn new(use_color: bool) -> eyre::Result<Self> {
-        Ok(CommitOidDescriptor { use_color })
+        Self::new_with_abbrev_len(use_color, Abbrev::default().min_len())
+    }
+
+    /// Constructor. Commit hashes are abbreviated to `abbrev_len` digits.
+    pub fn new_with_abbrev_len(use_color: bool, abbrev_len: usize) -> eyre::Result<Self> {
+        Ok(CommitOidDescriptor {
+            use_color,
+            abbrev_len,
+        })
     }
 }
 
@@ -184,13 +312,18 @@ impl NodeDescriptor for CommitOidDescriptor {
     #[instrument]
     fn describe_node(
         &mut self,
-        _glyphs: &Glyphs,
+        glyphs: &Glyphs,
         object: &NodeObject,
     ) -> eyre::Result<Option<StyledString>> {
-        let oid = object.get_oid();
-        let oid = &oid.to_string()[..8];
+        let oid = match object {
+            NodeObject::Commit { commit } => commit.short_oid(self.abbrev_len),
+            NodeObject::GarbageCollected { oid } => {
+                let oid = oid.to_string();
+                oid[..self.abbrev_len.min(oid.len())].to_string()
+            }
+        };
         let oid = if self.use_color {
-            StyledString::styled(oid, BaseColor::Yellow.dark())
+            StyledString::styled(oid, glyphs.theme.commit_oid)
         } else {
             StyledString::plain(oid)
         };
@@ -202,45 +335,341 @@ impl NodeDescriptor for CommitOidDescriptor {
 #[derive(Debug)]
 pub struct CommitMessageDescriptor<'a> {
     redactor: &'a Redactor,
+    subject_trailer: Option<String>,
+    summaries: HashMap<NonZeroOid, String>,
 }
 
 impl<'a> CommitMessageDescriptor<'a> {
     /// Constructor.
     pub fn new(redactor: &'a Redactor) -> eyre::Result<Self> {
-        Ok(CommitMessageDescriptor { redactor })
+        Ok(CommitMessageDescriptor {
+            redactor,
+            subject_trailer: None,
+            summaries: HashMap::new(),
+        })
+    }
+
+    /// If set, display the value of the given commit message trailer instead
+    /// of the first line of the commit message. Commits without the trailer
+    /// still display their first line.
+    pub fn subject_trailer(self, subject_trailer: Option<String>) -> Self {
+        Self {
+            subject_trailer,
+            ..self
+        }
+    }
+
+    /// Look up the summaries of the given commits ahead of time, in parallel.
+    /// Each thread uses its own repository handle from `repo_pool`. Summaries
+    /// are only looked up by OID when rendering, so the output doesn't depend
+    /// on the order in which they're computed.
+    #[instrument(skip(pool, repo_pool))]
+    pub fn prefetch(
+        &mut self,
+        pool: &ThreadPool,
+        repo_pool: &RepoPool,
+        commit_oids: &[NonZeroOid],
+    ) -> eyre::Result<()> {
+        let subject_trailer = self.subject_trailer.as_deref();
+        let results: Vec<(NonZeroOid, String)> = pool.install(|| {
+            commit_oids
+                .par_iter()
+                .copied()
+                .map(|commit_oid| -> eyre::Result<Option<(NonZeroOid, String)>> {
+                    let repo = repo_pool.try_create()?;
+                    let commit = match repo.find_commit(commit_oid)? {
+                        Some(commit) => commit,
+                        None => return Ok(None),
+                    };
+                    let summary = get_commit_summary(&commit, subject_trailer)?;
+                    Ok(Some((commit_oid, summary)))
+                })
+                .filter_map(|result| result.transpose())
+                .collect::<eyre::Result<_>>()
+        })?;
+        self.summaries.extend(results);
+        Ok(())
     }
 }
 
+fn get_commit_summary(commit: &Commit, subject_trailer: Option<&str>) -> eyre::Result<String> {
+    let trailer_value = match subject_trailer {
+        Some(subject_trailer) => commit.get_trailer(subject_trailer)?,
+        None => None,
+    };
+    let summary = match trailer_value {
+        Some(trailer_value) => trailer_value.to_string_lossy().into_owned(),
+        None => commit.get_summary()?.to_string_lossy().into_owned(),
+    };
+    Ok(summary)
+}
+
 impl<'a> NodeDescriptor for CommitMessageDescriptor<'a> {
     #[instrument]
     fn describe_node(
         &mut self,
-        _glyphs: &Glyphs,
+        glyphs: &Glyphs,
         object: &NodeObject,
     ) -> eyre::Result<Option<StyledString>> {
         let summary = match object {
-            NodeObject::Commit { commit } => commit.get_summary()?.to_string_lossy().into_owned(),
+            NodeObject::Commit { commit } => match self.summaries.get(&commit.get_oid()) {
+                Some(summary) => summary.clone(),
+                None => get_commit_summary(commit, self.subject_trailer.as_deref())?,
+            },
             NodeObject::GarbageCollected { oid: _ } => "<garbage collected>".to_string(),
         };
         let summary = self.redactor.redact_commit_summary(summary);
-        Ok(Some(StyledString::plain(summary)))
+        Ok(Some(StyledString::styled(
+            summary,
+            glyphs.theme.commit_message,
+        )))
+    }
+}
+
+/// Display the author of the commit, as resolved by the repository's mailmap.
+#[derive(Debug)]
+pub struct CommitAuthorDescriptor {
+    is_enabled: bool,
+    mailmap: Mailmap,
+}
+
+impl CommitAuthorDescriptor {
+    /// Constructor.
+    pub fn new(repo: &Repo, is_enabled: bool) -> eyre::Result<Self> {
+        let mailmap = repo.get_mailmap()?;
+        Ok(CommitAuthorDescriptor {
+            is_enabled,
+            mailmap,
+        })
+    }
+}
+
+impl NodeDescriptor for CommitAuthorDescriptor {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        if !self.is_enabled {
+            return Ok(None);
+        }
+        let commit = match object {
+            NodeObject::Commit { commit } => commit,
+            NodeObject::GarbageCollected { oid: _ } => return Ok(None),
+        };
+
+        let author = self.mailmap.resolve_signature(&commit.author())?;
+        let author = match author.get_name().or_else(|| author.get_email()) {
+            Some(author) => author,
+            None => return Ok(None),
+        };
+        let result =
+            StyledString::styled(format!("(author: {})", author), BaseColor::Black.light());
+        Ok(Some(result))
+    }
+}
+
+/// The status of a commit's GPG or SSH signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// The commit has a valid signature.
+    Good,
+
+    /// The commit has a signature which couldn't be verified, such as because
+    /// it's invalid, expired, or made with an unknown key.
+    Bad,
+
+    /// The commit isn't signed.
+    None,
+}
+
+impl SignatureStatus {
+    /// Convert from the signature status code printed by Git for the `%G?`
+    /// placeholder. See `git help log` for the meaning of each code.
+    fn from_git_status_code(code: &str) -> Self {
+        match code {
+            "G" | "U" => SignatureStatus::Good,
+            "N" => SignatureStatus::None,
+            _ => SignatureStatus::Bad,
+        }
+    }
+}
+
+/// Display whether the commit is signed, and whether the signature could be
+/// verified. `git2` can't verify signatures, so this invokes Git itself. The
+/// results are cached by OID, so each commit is only verified once.
+pub struct CommitSignatureDescriptor<'a> {
+    repo: &'a Repo,
+    git_run_info: &'a GitRunInfo,
+    is_enabled: bool,
+    statuses: HashMap<NonZeroOid, SignatureStatus>,
+}
+
+impl std::fmt::Debug for CommitSignatureDescriptor<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "<CommitSignatureDescriptor is_enabled={:?}>",
+            self.is_enabled
+        )
+    }
+}
+
+impl<'a> CommitSignatureDescriptor<'a> {
+    /// Constructor.
+    pub fn new(
+        repo: &'a Repo,
+        git_run_info: &'a GitRunInfo,
+        is_enabled: bool,
+    ) -> eyre::Result<Self> {
+        Ok(CommitSignatureDescriptor {
+            repo,
+            git_run_info,
+            is_enabled,
+            statuses: Default::default(),
+        })
+    }
+
+    /// Verify the signatures of the given commits ahead of time, using a
+    /// single Git invocation rather than one per commit.
+    #[instrument]
+    pub fn prefetch(&mut self, commit_oids: &[NonZeroOid]) -> eyre::Result<()> {
+        if !self.is_enabled {
+            return Ok(());
+        }
+        let commit_oids: Vec<String> = commit_oids
+            .iter()
+            .filter(|commit_oid| !self.statuses.contains_key(commit_oid))
+            .map(|commit_oid| commit_oid.to_string())
+            .collect();
+        if commit_oids.is_empty() {
+            return Ok(());
+        }
+
+        // Pass the commits via stdin, since there may be too many to fit on
+        // the command line.
+        let result = self.git_run_info.run_silent(
+            self.repo,
+            None,
+            &["log", "--no-walk=unsorted", "--stdin", "--format=%H %G?"],
+            GitRunOpts {
+                stdin: Some(commit_oids.join("\n").into_bytes()),
+                ..Default::default()
+            },
+        )?;
+        for line in String::from_utf8_lossy(&result.stdout).lines() {
+            let (commit_oid, status_code) = match line.split_once(' ') {
+                Some(parts) => parts,
+                None => continue,
+            };
+            self.statuses.insert(
+                commit_oid.parse()?,
+                SignatureStatus::from_git_status_code(status_code),
+            );
+        }
+        Ok(())
+    }
+}
+
+impl<'a> NodeDescriptor for CommitSignatureDescriptor<'a> {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        if !self.is_enabled {
+            return Ok(None);
+        }
+        let commit_oid = match object {
+            NodeObject::Commit { commit } => commit.get_oid(),
+            NodeObject::GarbageCollected { oid: _ } => return Ok(None),
+        };
+
+        if !self.statuses.contains_key(&commit_oid) {
+            self.prefetch(&[commit_oid])?;
+        }
+        let result = match self.statuses.get(&commit_oid) {
+            Some(SignatureStatus::Good) => {
+                StyledString::styled("(signed)", BaseColor::Green.light())
+            }
+            Some(SignatureStatus::Bad) => {
+                StyledString::styled("(bad signature)", BaseColor::Red.light())
+            }
+            Some(SignatureStatus::None) => {
+                StyledString::styled("(unsigned)", BaseColor::Yellow.light())
+            }
+            None => return Ok(None),
+        };
+        Ok(Some(result))
     }
 }
 
 /// For obsolete commits, provide the reason that it's obsolete.
 pub struct ObsolescenceExplanationDescriptor<'a> {
+    repo: &'a Repo,
     event_replayer: &'a EventReplayer,
     event_cursor: EventCursor,
+    abbrev_len: usize,
 }
 
 impl<'a> ObsolescenceExplanationDescriptor<'a> {
     /// Constructor.
-    pub fn new(event_replayer: &'a EventReplayer, event_cursor: EventCursor) -> eyre::Result<Self> {
+    pub fn new(
+        repo: &'a Repo,
+        event_replayer: &'a EventReplayer,
+        event_cursor: EventCursor,
+    ) -> eyre::Result<Self> {
+        Self::new_with_abbrev_len(
+            repo,
+            event_replayer,
+            event_cursor,
+            Abbrev::default().min_len(),
+        )
+    }
+
+    /// Constructor. The hashes of rewritten commits are abbreviated to
+    /// `abbrev_len` digits.
+    pub fn new_with_abbrev_len(
+        repo: &'a Repo,
+        event_replayer: &'a EventReplayer,
+        event_cursor: EventCursor,
+        abbrev_len: usize,
+    ) -> eyre::Result<Self> {
         Ok(ObsolescenceExplanationDescriptor {
+            repo,
             event_replayer,
             event_cursor,
+            abbrev_len,
         })
     }
+
+    /// Determine whether `old_oid` was rewritten into `new_oid` in place (an
+    /// amend) or onto different parents (a rebase), by comparing the parents of
+    /// the two commits.
+    fn get_rewrite_reason(
+        &self,
+        old_oid: NonZeroOid,
+        new_oid: MaybeZeroOid,
+        rewritten_oid: MaybeZeroOid,
+    ) -> eyre::Result<HideReason> {
+        let old_commit = self.repo.find_commit(old_oid)?;
+        let new_commit = match new_oid {
+            MaybeZeroOid::NonZero(new_oid) => self.repo.find_commit(new_oid)?,
+            MaybeZeroOid::Zero => None,
+        };
+        let reason = match (old_commit, new_commit) {
+            (Some(old_commit), Some(new_commit))
+                if old_commit.get_parent_oids() == new_commit.get_parent_oids() =>
+            {
+                HideReason::Amended { rewritten_oid }
+            }
+            (Some(_), Some(_)) => HideReason::Rebased { rewritten_oid },
+            (None, _) | (_, None) => HideReason::Rewritten { rewritten_oid },
+        };
+        Ok(reason)
+    }
 }
 
 impl<'a> NodeDescriptor for ObsolescenceExplanationDescriptor<'a> {
@@ -258,27 +687,76 @@ impl<'a> NodeDescriptor for ObsolescenceExplanationDescriptor<'a> {
             None => return Ok(None),
         };
 
-        let result = match event {
-            Event::RewriteEvent { .. } => {
+        let reason = match event {
+            Event::RewriteEvent { new_commit_oid, .. } => {
                 let rewrite_target =
                     find_rewrite_target(self.event_replayer, self.event_cursor, object.get_oid());
-                rewrite_target.map(|rewritten_oid| {
-                    StyledString::styled(
-                        format!("(rewritten as {})", &rewritten_oid.to_string()[..8]),
-                        BaseColor::Black.light(),
-                    )
-                })
+                let hide_message = self
+                    .event_replayer
+                    .get_cursor_commit_hide_message(self.event_cursor, object.get_oid());
+                match rewrite_target {
+                    // A landed commit is skipped when its descendants are
+                    // moved, which would otherwise hide the reason that it was
+                    // hidden.
+                    Some(MaybeZeroOid::Zero)
+                        if hide_message == Some(OsStr::new(LANDED_UPSTREAM_HIDE_MESSAGE)) =>
+                    {
+                        Some(HideReason::Landed)
+                    }
+                    Some(MaybeZeroOid::Zero)
+                        if hide_message == Some(OsStr::new(EMPTY_COMMIT_HIDE_MESSAGE)) =>
+                    {
+                        Some(HideReason::BecameEmpty)
+                    }
+                    Some(rewritten_oid) => Some(self.get_rewrite_reason(
+                        object.get_oid(),
+                        *new_commit_oid,
+                        rewritten_oid,
+                    )?),
+                    None => None,
+                }
             }
 
-            Event::ObsoleteEvent { .. } => Some(StyledString::styled(
-                "(manually hidden)",
-                BaseColor::Black.light(),
-            )),
+            Event::ObsoleteEvent {
+                message: Some(message),
+                ..
+            } if message == LANDED_UPSTREAM_HIDE_MESSAGE => Some(HideReason::Landed),
+
+            Event::ObsoleteEvent {
+                message: Some(message),
+                ..
+            } if message == EMPTY_COMMIT_HIDE_MESSAGE => Some(HideReason::BecameEmpty),
+
+            Event::ObsoleteEvent {
+                message: Some(message),
+                ..
+            } if message == MISSING_COMMIT_HIDE_MESSAGE => Some(HideReason::Missing),
+
+            Event::ObsoleteEvent { message, .. } => Some(
+                match message
+                    .as_deref()
+                    .and_then(parse_force_hidden_public_hide_message)
+                {
+                    Some(message) => HideReason::ForceHiddenPublic { message },
+                    None => HideReason::ManuallyHidden {
+                        message: message
+                            .as_ref()
+                            .map(|message| message.to_string_lossy().into_owned()),
+                    },
+                },
+            ),
 
             Event::RefUpdateEvent { .. }
             | Event::CommitEvent { .. }
-            | Event::UnobsoleteEvent { .. } => None,
+            | Event::UnobsoleteEvent { .. }
+            | Event::WorkingCopySnapshot { .. } => None,
         };
+        let result = reason.map(|reason| {
+            StyledString::styled(
+                format!("({})", reason.describe(self.abbrev_len)),
+                BaseColor::Black.light(),
+            )
+        });
         Ok(result)
     }
 }
@@ -287,6 +765,10 @@ impl<'a> NodeDescriptor for ObsolescenceExplanationDescriptor<'a> {
 #[derive(Debug)]
 pub struct BranchesDescriptor<'a> {
     is_enabled: bool,
+    only_checked_out_branch: bool,
+    is_head_reference_known: bool,
+    remote_statuses: HashMap<OsString, (usize, usize)>,
+    hidden_ref_prefixes: Vec<String>,
     head_info: &'a ResolvedReferenceInfo<'a>,
     references_snapshot: &'a RepoReferencesSnapshot,
     redactor: &'a Redactor,
@@ -301,13 +783,89 @@ impl<'a> BranchesDescriptor<'a> {
         redactor: &'a Redactor,
     ) -> eyre::Result<Self> {
         let is_enabled = get_commit_descriptors_branches(repo)?;
+        let hidden_ref_prefixes = get_smartlog_hidden_ref_prefixes(repo)?;
         Ok(BranchesDescriptor {
             is_enabled,
+            only_checked_out_branch: false,
+            is_head_reference_known: true,
+            remote_statuses: HashMap::new(),
+            hidden_ref_prefixes,
             head_info,
             references_snapshot,
             redactor,
         })
     }
+
+    /// If set, don't show the names of branches which aren't checked out. The
+    /// checked-out branch is still shown, so that the `>` marker is rendered.
+    pub fn only_checked_out_branch(self, only_checked_out_branch: bool) -> Self {
+        Self {
+            only_checked_out_branch,
+            ..self
+        }
+    }
+
+    /// Set whether the `reference_name` of the provided `head_info` is known.
+    /// If it's not known (such as for a past state of the repository, since
+    /// the event log doesn't record which branch was checked out), then
+    /// `HEAD` isn't marked as detached.
+    pub fn is_head_reference_known(self, is_head_reference_known: bool) -> Self {
+        Self {
+            is_head_reference_known,
+            ..self
+        }
+    }
+
+    /// If set, show the number of commits by which each local branch is ahead
+    /// of and behind its upstream branch, for branches which have one.
+    ///
+    /// The ahead/behind counts are computed in parallel, with each thread
+    /// using its own repository handle from `repo_pool`.
+    #[instrument(skip(pool, repo_pool))]
+    pub fn show_remote_status(
+        self,
+        repo: &Repo,
+        pool: &ThreadPool,
+        repo_pool: &RepoPool,
+        show_remote_status: bool,
+    ) -> eyre::Result<Self> {
+        if !show_remote_status {
+            return Ok(self);
+        }
+        let mut branches = Vec::new();
+        for branch in repo.get_all_local_branches()? {
+            let branch_oid = match branch.get_oid()? {
+                Some(branch_oid) => branch_oid,
+                None => continue,
+            };
+            let upstream_oid = match branch.get_upstream_branch()? {
+                Some(upstream_branch) => match upstream_branch.get_oid()? {
+                    Some(upstream_oid) => upstream_oid,
+                    None => continue,
+                },
+                None => continue,
+            };
+            let branch_name = branch.into_reference().get_name()?;
+            branches.push((branch_name, branch_oid, upstream_oid));
+        }
+
+        let remote_statuses: HashMap<OsString, (usize, usize)> = pool.install(|| {
+            branches
+                .into_par_iter()
+                .map(
+                    |(branch_name, branch_oid, upstream_oid)| -> eyre::Result<_> {
+                        let repo = repo_pool.try_create()?;
+                        let ahead_behind = repo.graph_ahead_behind(branch_oid, upstream_oid)?;
+                        Ok((branch_name, ahead_behind))
+                    },
+                )
+                .collect::<eyre::Result<_>>()
+        })?;
+        Ok(Self {
+            remote_statuses,
+            ..self
+        })
+    }
 }
 
 impl<'a> NodeDescriptor for BranchesDescriptor<'a> {
@@ -321,24 +879,46 @@ impl<'a> NodeDescriptor for BranchesDescriptor<'a> {
             return Ok(None);
         }
 
-        let branch_names: HashSet<OsString> = match self
+        let branch_names: HashMap<OsString, Option<(usize, usize)>> = match self
             .references_snapshot
             .branch_oid_to_names
             .get(&object.get_oid())
         {
             Some(branch_names) => branch_names
                 .iter()
-                .map(|branch_name| self.redactor.redact_ref_name(branch_name.to_owned()))
+                .filter(|branch_name| {
+                    let branch_name = branch_name.to_raw_bytes();
+                    !self
+                        .hidden_ref_prefixes
+                        .iter()
+                        .any(|prefix| branch_name.starts_with(prefix.as_bytes()))
+                })
+                .filter(|branch_name| {
+                    !self.only_checked_out_branch
+                        || self.head_info.reference_name == Some(Cow::Borrowed(branch_name))
+                })
+                .map(|branch_name| {
+                    (
+                        self.redactor.redact_ref_name(branch_name.to_owned()),
+                        self.remote_statuses.get(branch_name).copied(),
+                    )
+                })
                 .collect(),
-            None => HashSet::new(),
+            None => HashMap::new(),
         };
 
-        if branch_names.is_empty() {
+        // When `HEAD` is detached, say so explicitly, since otherwise the only
+        // indication is the absence of the arrow next to a branch name.
+        let is_detached_head = self.is_head_reference_known
+            && self.head_info.reference_name.is_none()
+            && self.head_info.oid == Some(object.get_oid());
+
+        if branch_names.is_empty() && !is_detached_head {
             Ok(None)
         } else {
             let mut branch_names: Vec<String> = branch_names
                 .into_iter()
-                .map(|branch_name| {
+                .map(|(branch_name, remote_status)| {
                     let is_checked_out_branch =
                         self.head_info.reference_name == Some(Cow::Borrowed(&branch_name));
                     let icon = if is_checked_out_branch {
@@ -349,7 +929,29 @@ impl<'a> NodeDescriptor for BranchesDescriptor<'a> {
 
                     match CategorizedReferenceName::new(&branch_name) {
                         reference_name @ CategorizedReferenceName::LocalBranch { .. } => {
-                            format!("{}{}", icon, reference_name.render_suffix())
+                            let remote_status = match remote_status {
+                                None | Some((0, 0)) => "".to_string(),
+                                Some((ahead, behind)) => {
+                                    let mut remote_status = " ".to_string();
+                                    if ahead > 0 {
+                                        remote_status
+                                            .push_str(&format!("{}{}", glyphs.branch_ahead, ahead));
+                                    }
+                                    if behind > 0 {
+                                        remote_status.push_str(&format!(
+                                            "{}{}",
+                                            glyphs.branch_behind, behind
+                                        ));
+                                    }
+                                    remote_status
+                                }
+                            };
+                            format!(
+                                "{}{}{}",
+                                icon,
+                                reference_name.render_suffix(),
+                                remote_status
+                            )
                         }
                         reference_name @ CategorizedReferenceName::RemoteBranch { .. } => {
                             format!("{}remote {}", icon, reference_name.render_suffix())
@@ -361,15 +963,148 @@ impl<'a> NodeDescriptor for BranchesDescriptor<'a> {
                 })
                 .collect();
             branch_names.sort_unstable();
+            if is_detached_head {
+                branch_names.insert(0, format!("{} detached HEAD", glyphs.branch_arrow));
+            }
             let result = StyledString::styled(
                 format!("({})", branch_names.join(", ")),
-                BaseColor::Green.light(),
+                glyphs.theme.branches,
             );
             Ok(Some(result))
         }
     }
 }
 
+/// Display the tags which point to a given commit.
+#[derive(Debug)]
+pub struct TagsDescriptor {
+    is_enabled: bool,
+    tag_oid_to_names: HashMap<NonZeroOid, HashSet<OsString>>,
+}
+
+impl TagsDescriptor {
+    /// Constructor.
+    pub fn new(repo: &Repo) -> eyre::Result<Self> {
+        let is_enabled = get_smartlog_show_tags(repo)?;
+        let tag_oid_to_names = if is_enabled {
+            repo.get_tag_oid_to_names()?
+        } else {
+            HashMap::new()
+        };
+        Ok(TagsDescriptor {
+            is_enabled,
+            tag_oid_to_names,
+        })
+    }
+}
+
+impl NodeDescriptor for TagsDescriptor {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        if !self.is_enabled {
+            return Ok(None);
+        }
+
+        let tag_names = match self.tag_oid_to_names.get(&object.get_oid()) {
+            Some(tag_names) if !tag_names.is_empty() => tag_names,
+            Some(_) | None => return Ok(None),
+        };
+        let mut tag_names: Vec<String> = tag_names
+            .iter()
+            .map(|tag_name| {
+                let tag_name = tag_name.to_string_lossy();
+                let tag_name = tag_name.strip_prefix("refs/tags/").unwrap_or(&tag_name);
+                format!("tag: {}", tag_name)
+            })
+            .collect();
+        tag_names.sort_unstable();
+        let result = StyledString::styled(
+            format!("({})", tag_names.join(", ")),
+            BaseColor::Yellow.light(),
+        );
+        Ok(Some(result))
+    }
+}
+
+/// Display the index of each commit in the rendered graph, starting from 1, so
+/// that commits can be referred to by index in later commands.
+#[derive(Debug)]
+pub struct CommitIndexDescriptor {
+    is_enabled: bool,
+    commit_oids: Vec<NonZeroOid>,
+}
+
+impl CommitIndexDescriptor {
+    /// Constructor.
+    pub fn new(is_enabled: bool) -> eyre::Result<Self> {
+        Ok(CommitIndexDescriptor {
+            is_enabled,
+            commit_oids: Vec::new(),
+        })
+    }
+
+    /// Get the commits which have been assigned indices so far, in order of
+    /// index.
+    pub fn get_commit_oids(&self) -> &[NonZeroOid] {
+        &self.commit_oids
+    }
+}
+
+impl NodeDescriptor for CommitIndexDescriptor {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        if !self.is_enabled {
+            return Ok(None);
+        }
+        self.commit_oids.push(object.get_oid());
+        Ok(Some(StyledString::styled(
+            format!("[{}]", self.commit_oids.len()),
+            BaseColor::Cyan.light(),
+        )))
+    }
+}
+
+/// Display the result of running a test command on a given commit.
+#[derive(Debug)]
+pub struct TestResultDescriptor {
+    results: HashMap<NonZeroOid, TestResult>,
+}
+
+impl TestResultDescriptor {
+    /// Constructor.
+    pub fn new(results: HashMap<NonZeroOid, TestResult>) -> eyre::Result<Self> {
+        Ok(TestResultDescriptor { results })
+    }
+}
+
+impl NodeDescriptor for TestResultDescriptor {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        let result = match self.results.get(&object.get_oid()) {
+            Some(result) => result,
+            None => return Ok(None),
+        };
+        let result = if result.is_passed() {
+            StyledString::styled(glyphs.test_passed, BaseColor::Green.light())
+        } else {
+            StyledString::styled(glyphs.test_failed, BaseColor::Red.light())
+        };
+        Ok(Some(result))
+    }
+}
+
 /// Display the associated Phabricator revision for a given commit.
 #[derive(Debug)]
 pub struct DifferentialRevisionDescriptor<'a> {
@@ -511,12 +1246,271 @@ impl NodeDescriptor for RelativeTimeDescriptor {
     }
 }
 
+/// The part of a commit's diff stat displayed by a `CommitDiffStatDescriptor`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffStatColumn {
+    /// The total number of inserted lines, such as `+12`.
+    Insertions,
+
+    /// The total number of deleted lines, such as `-3`.
+    Deletions,
+
+    /// The number of inserted and deleted lines in each file.
+    Files,
+}
+
+/// Display the number of lines changed by the given commit, to be rendered in
+/// a separate column of the smartlog. The diff stat is only computed when a
+/// commit is described.
+pub struct CommitDiffStatDescriptor<'repo> {
+    repo: &'repo Repo,
+    column: DiffStatColumn,
+    diff_stats: Arc<Mutex<HashMap<NonZeroOid, DiffStat>>>,
+}
+
+impl std::fmt::Debug for CommitDiffStatDescriptor<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<CommitDiffStatDescriptor column={:?}>", self.column)
+    }
+}
+
+impl<'repo> CommitDiffStatDescriptor<'repo> {
+    /// Constructor.
+    pub fn new(repo: &'repo Repo, column: DiffStatColumn) -> eyre::Result<Self> {
+        Ok(CommitDiffStatDescriptor {
+            repo,
+            column,
+            diff_stats: Default::default(),
+        })
+    }
+
+    /// Create a descriptor for a different column, which shares the diff stats
+    /// already computed by this descriptor.
+    pub fn with_column(&self, column: DiffStatColumn) -> Self {
+        CommitDiffStatDescriptor {
+            repo: self.repo,
+            column,
+            diff_stats: Arc::clone(&self.diff_stats),
+        }
+    }
+
+    /// Compute the diff stats for the given commits ahead of time, in
+    /// parallel. Each thread uses its own repository handle from `repo_pool`,
+    /// since `git2` objects can't be shared between threads. The rendered
+    /// output doesn't depend on the order in which the diff stats are
+    /// computed, since they're only looked up by OID.
+    ///
+    /// Computing diff stats dominates the time taken to render a smartlog
+    /// with `--stat`, so the speedup for smartlogs with many commits should
+    /// scale with the number of cores. On a single core, rendering 300
+    /// commits takes the same time (about 0.21s) with or without
+    /// prefetching. See the `diff stats` benchmark to measure it on other
+    /// machines.
+    #[instrument(skip(pool, repo_pool))]
+    pub fn prefetch(
+        &self,
+        pool: &ThreadPool,
+        repo_pool: &RepoPool,
+        commit_oids: &[NonZeroOid],
+    ) -> eyre::Result<()> {
+        let commit_oids: Vec<NonZeroOid> = {
+            let diff_stats = self.diff_stats.lock().expect("Poisoned mutex");
+            commit_oids
+                .iter()
+                .copied()
+                .filter(|commit_oid| !diff_stats.contains_key(commit_oid))
+                .collect()
+        };
+
+        let results: Vec<(NonZeroOid, DiffStat)> = pool.install(|| {
+            commit_oids
+                .into_par_iter()
+                .map(
+                    |commit_oid| -> eyre::Result<Option<(NonZeroOid, DiffStat)>> {
+                        let repo = repo_pool.try_create()?;
+                        let commit = match repo.find_commit(commit_oid)? {
+                            Some(commit) => commit,
+                            None => return Ok(None),
+                        };
+                        let diff_stat = repo.get_commit_diff_stat(&commit)?;
+                        Ok(Some((commit_oid, diff_stat)))
+                    },
+                )
+                .filter_map(|result| result.transpose())
+                .collect::<eyre::Result<_>>()
+        })?;
+
+        let mut diff_stats = self.diff_stats.lock().expect("Poisoned mutex");
+        diff_stats.extend(results);
+        Ok(())
+    }
+}
+
+impl<'repo> NodeDescriptor for CommitDiffStatDescriptor<'repo> {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        let commit = match object {
+            NodeObject::Commit { commit } => commit,
+            NodeObject::GarbageCollected { oid: _ } => return Ok(None),
+        };
+
+        let mut diff_stats = self.diff_stats.lock().expect("Poisoned mutex");
+        let diff_stat = match diff_stats.get(&commit.get_oid()) {
+            Some(diff_stat) => diff_stat,
+            None => {
+                let diff_stat = self.repo.get_commit_diff_stat(commit)?;
+                diff_stats.entry(commit.get_oid()).or_insert(diff_stat)
+            }
+        };
+
+        let result = match self.column {
+            DiffStatColumn::Insertions => StyledString::styled(
+                format!("+{}", diff_stat.insertions),
+                BaseColor::Green.light(),
+            ),
+            DiffStatColumn::Deletions => {
+                StyledString::styled(format!("-{}", diff_stat.deletions), BaseColor::Red.light())
+            }
+            DiffStatColumn::Files => {
+                if diff_stat.files.is_empty() {
+                    return Ok(None);
+                }
+                let files = diff_stat
+                    .files
+                    .iter()
+                    .map(|file| {
+                        StyledStringBuilder::new()
+                            .append_plain(file.path.to_string_lossy())
+                            .append_plain(" ")
+                            .append_styled(
+                                format!("+{}", file.insertions),
+                                BaseColor::Green.light(),
+                            )
+                            .append_plain(" ")
+                            .append_styled(format!("-{}", file.deletions), BaseColor::Red.light())
+                            .build()
+                    })
+                    .collect();
+                StyledStringBuilder::new()
+                    .append_plain("(")
+                    .append(StyledStringBuilder::join(", ", files))
+                    .append_plain(")")
+                    .build()
+            }
+        };
+        Ok(Some(result))
+    }
+}
+
+/// Display the age of the given commit in a compact form, such as `3d`, to be
+/// rendered in a separate column of the smartlog.
+#[derive(Debug)]
+pub struct CommitAgeDescriptor {
+    now: SystemTime,
+}
+
+impl CommitAgeDescriptor {
+    /// Constructor.
+    pub fn new(now: SystemTime) -> eyre::Result<Self> {
+        Ok(CommitAgeDescriptor { now })
+    }
+
+    /// Describe the age of a commit made at `previous_time`, e.g. "5w". For
+    /// commits older than a year, returns the absolute date instead, using the
+    /// commit's timezone offset.
+    pub fn describe_age(now: SystemTime, previous_time: git2::Time) -> eyre::Result<String> {
+        let previous_system_time =
+            SystemTime::UNIX_EPOCH.add(Duration::from_secs(previous_time.seconds().try_into()?));
+        let delta: u64 = match now.duration_since(previous_system_time) {
+            Ok(delta) => delta.as_secs(),
+            Err(_) => 0,
+        };
+
+        let minute = 60;
+        let hour = 60 * minute;
+        let day = 24 * hour;
+        let week = 7 * day;
+        let year = 365 * day;
+        let description = if delta < minute {
+            format!("{}s", delta)
+        } else if delta < hour {
+            format!("{}m", delta / minute)
+        } else if delta < day {
+            format!("{}h", delta / hour)
+        } else if delta < week {
+            format!("{}d", delta / day)
+        } else if delta < year {
+            format!("{}w", delta / week)
+        } else {
+            let offset = FixedOffset::east(previous_time.offset_minutes() * 60);
+            offset
+                .timestamp(previous_time.seconds(), 0)
+                .format("%Y-%m-%d")
+                .to_string()
+        };
+        Ok(description)
+    }
+}
+
+impl NodeDescriptor for CommitAgeDescriptor {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        let commit = match object {
+            NodeObject::Commit { commit } => commit,
+            NodeObject::GarbageCollected { oid: _ } => return Ok(None),
+        };
+        let description = Self::describe_age(self.now, commit.get_committer().get_time())?;
+        let result = StyledString::styled(description, BaseColor::Black.light());
+        Ok(Some(result))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::ops::Sub;
 
     use super::*;
 
+    use crate::testing::make_git;
+
+    #[test]
+    fn test_commit_view() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+
+        let repo = git.get_repo()?;
+        let commit = CommitView::new(repo.find_commit_or_fail(test2_oid)?);
+        assert_eq!(commit.get_oid(), test2_oid);
+        assert_eq!(commit.short_oid(8), "96d1c37a");
+        assert_eq!(commit.short_oid(100), test2_oid.to_string());
+        assert_eq!(commit.subject()?, OsString::from("create test2.txt"));
+        assert_eq!(commit.parents(), vec![test1_oid]);
+
+        // Cached values survive cloning.
+        let commit = commit.clone();
+        assert_eq!(commit.subject()?, OsString::from("create test2.txt"));
+        assert_eq!(
+            commit.author().friendly_describe(),
+            Some("Testy McTestface <test@example.com>".to_string())
+        );
+        assert_eq!(
+            commit.author().friendly_describe(),
+            commit.get_author().friendly_describe()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_extract_diff_number() -> eyre::Result<()> {
         let message = "\
@@ -571,4 +1565,27 @@ Differential Revision: phabricator.com/D123";
 
         Ok(())
     }
+
+    #[test]
+    fn test_describe_age() -> eyre::Result<()> {
+        let now = SystemTime::UNIX_EPOCH.add(Duration::from_secs(1_600_000_000));
+        let test_cases: Vec<(i64, &str)> = vec![
+            (0, "0s"),
+            (59, "59s"),
+            (60 * 45, "45m"),
+            (60 * 60 * 2, "2h"),
+            (60 * 60 * 24 * 3, "3d"),
+            (60 * 60 * 24 * 7 * 5, "5w"),
+            (60 * 60 * 24 * 364, "52w"),
+            (60 * 60 * 24 * 400, "2019-08-10"),
+        ];
+
+        for (delta, expected) in test_cases {
+            let previous_time = git2::Time::new(1_600_000_000 - delta, 0);
+            let age = CommitAgeDescriptor::describe_age(now, previous_time)?;
+            assert_eq!(age, expected);
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,94 @@
+//! Persistent cache of the patch IDs of commits.
+//!
+//! A commit's patch ID depends only on the commit and its parent, neither of
+//! which can change, so cached patch IDs never need to be invalidated.
+
+use eyre::Context;
+use rusqlite::OptionalExtension;
+use tracing::instrument;
+
+use crate::core::effects::Effects;
+use crate::git::{Commit, PatchId, Repo};
+
+/// Stores the patch IDs of commits. Uses the same database as the event log.
+pub struct PatchIdsDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl std::fmt::Debug for PatchIdsDb<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<PatchIdsDb>")
+    }
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS patch_ids (
+    commit_oid TEXT PRIMARY KEY,
+    patch_id TEXT
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `patch_ids` table")?;
+    Ok(())
+}
+
+impl<'conn> PatchIdsDb<'conn> {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(conn)?;
+        Ok(PatchIdsDb { conn })
+    }
+
+    /// Get the patch ID for the given commit, computing and storing it if it
+    /// hasn't been cached yet. Returns `None` if the commit doesn't have a
+    /// patch ID (such as for merge commits).
+    #[instrument]
+    pub fn get_patch_id(
+        &self,
+        effects: &Effects,
+        repo: &Repo,
+        commit: &Commit,
+    ) -> eyre::Result<Option<PatchId>> {
+        let commit_oid = commit.get_oid().to_string();
+        let cached: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "
+SELECT patch_id
+FROM patch_ids
+WHERE commit_oid = :commit_oid
+",
+                rusqlite::named_params! {
+                    ":commit_oid": commit_oid,
+                },
+                |row| row.get("patch_id"),
+            )
+            .optional()
+            .wrap_err("Querying patch ID")?;
+        if let Some(patch_id) = cached {
+            return patch_id.map(|patch_id| patch_id.parse()).transpose();
+        }
+
+        let patch_id = repo.get_patch_id(effects, commit)?;
+        self.conn
+            .execute(
+                "
+INSERT OR REPLACE INTO patch_ids
+(commit_oid, patch_id)
+VALUES
+(:commit_oid, :patch_id)
+",
+                rusqlite::named_params! {
+                    ":commit_oid": commit_oid,
+                    ":patch_id": patch_id.map(|patch_id| patch_id.to_string()),
+                },
+            )
+            .wrap_err("Storing patch ID")?;
+        Ok(patch_id)
+    }
+}
@@ -0,0 +1,571 @@
+//! A small query language for selecting commits, as used by `git hide
+//! --query`.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! query     := and ("or" and)*
+//! and       := unary ("and" unary)*
+//! unary     := "not" unary | "(" query ")" | predicate
+//! predicate := "age" ("<" | "<=" | ">" | ">=") DURATION
+//!            | "author" "~" STRING
+//!            | "message" "~" STRING
+//!            | "branch" ("~" STRING)?
+//! DURATION  := INTEGER ("s" | "m" | "h" | "d" | "w")
+//! STRING    := '"' ... '"' | WORD
+//! ```
+//!
+//! The supported predicates are:
+//!
+//! - `age > 30d`: the commit was made more than 30 days ago (by commit time).
+//! - `author ~ "alice"`: the author's name or email (as resolved by the
+//!   repository's mailmap) contains the given text.
+//! - `message ~ "wip"`: the commit message contains the given text.
+//! - `branch`: the commit has any branch pointing to it.
+//! - `branch ~ "feature/"`: the commit has a branch pointing to it whose name
+//!   contains the given text.
+//!
+//! Text matching with `~` is case-insensitive. `not` binds more tightly than
+//! `and`, which binds more tightly than `or`.
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use std::time::{Duration, SystemTime};
+
+use crate::git::{Commit, Mailmap, NonZeroOid};
+
+/// A comparison operator used in `age` predicates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    /// `<`
+    LessThan,
+
+    /// `<=`
+    LessThanOrEqual,
+
+    /// `>`
+    GreaterThan,
+
+    /// `>=`
+    GreaterThanOrEqual,
+}
+
+impl Comparison {
+    fn compare<T: PartialOrd>(self, lhs: T, rhs: T) -> bool {
+        match self {
+            Comparison::LessThan => lhs < rhs,
+            Comparison::LessThanOrEqual => lhs <= rhs,
+            Comparison::GreaterThan => lhs > rhs,
+            Comparison::GreaterThanOrEqual => lhs >= rhs,
+        }
+    }
+}
+
+/// A parsed query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Query {
+    /// Both subqueries match.
+    And(Box<Query>, Box<Query>),
+
+    /// Either subquery matches.
+    Or(Box<Query>, Box<Query>),
+
+    /// The subquery doesn't match.
+    Not(Box<Query>),
+
+    /// The age of the commit compares to the given duration.
+    Age(Comparison, Duration),
+
+    /// The author's name or email contains the given text.
+    Author(String),
+
+    /// The commit message contains the given text.
+    Message(String),
+
+    /// The commit has a branch pointing to it, optionally whose name contains
+    /// the given text.
+    Branch(Option<String>),
+}
+
+/// An error encountered while parsing a query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryParseError {
+    /// The byte offset into the input where the error occurred.
+    pub offset: usize,
+
+    /// A description of the error.
+    pub message: String,
+}
+
+impl Display for QueryParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at position {})", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for QueryParseError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    Tilde,
+    Comparison(Comparison),
+    Word(String),
+    String(String),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::LParen => write!(f, "`(`"),
+            Token::RParen => write!(f, "`)`"),
+            Token::Tilde => write!(f, "`~`"),
+            Token::Comparison(Comparison::LessThan) => write!(f, "`<`"),
+            Token::Comparison(Comparison::LessThanOrEqual) => write!(f, "`<=`"),
+            Token::Comparison(Comparison::GreaterThan) => write!(f, "`>`"),
+            Token::Comparison(Comparison::GreaterThanOrEqual) => write!(f, "`>=`"),
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::String(string) => write!(f, "{:?}", string),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || "_-./@+:".contains(c)
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, QueryParseError> {
+    let mut result = Vec::new();
+    let mut chars: Peekable<CharIndices> = input.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '~' => Token::Tilde,
+            '<' | '>' => {
+                let is_or_equal = matches!(chars.peek(), Some((_, '=')));
+                if is_or_equal {
+                    chars.next();
+                }
+                Token::Comparison(match (c, is_or_equal) {
+                    ('<', false) => Comparison::LessThan,
+                    ('<', true) => Comparison::LessThanOrEqual,
+                    ('>', false) => Comparison::GreaterThan,
+                    (_, _) => Comparison::GreaterThanOrEqual,
+                })
+            }
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => string.push(c),
+                            None => {
+                                return Err(QueryParseError {
+                                    offset,
+                                    message: "Unterminated string".to_string(),
+                                })
+                            }
+                        },
+                        Some((_, c)) => string.push(c),
+                        None => {
+                            return Err(QueryParseError {
+                                offset,
+                                message: "Unterminated string".to_string(),
+                            })
+                        }
+                    }
+                }
+                Token::String(string)
+            }
+            c if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = chars.peek() {
+                    if !is_word_char(*c) {
+                        break;
+                    }
+                    word.push(*c);
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+            c => {
+                return Err(QueryParseError {
+                    offset,
+                    message: format!("Unexpected character {:?}", c),
+                })
+            }
+        };
+        result.push((offset, token));
+    }
+    Ok(result)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    input_len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_offset, token)| token)
+    }
+
+    fn offset(&self) -> usize {
+        match self.tokens.get(self.position) {
+            Some((offset, _token)) => *offset,
+            None => self.input_len,
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, QueryParseError> {
+        Err(QueryParseError {
+            offset: self.offset(),
+            message: message.into(),
+        })
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, QueryParseError> {
+        match self.peek() {
+            Some(token) => self.error(format!("Expected {}, but got {}", expected, token)),
+            None => self.error(format!("Expected {}, but got end of query", expected)),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word == keyword)
+    }
+
+    fn parse_query(&mut self) -> Result<Query, QueryParseError> {
+        let mut lhs = self.parse_and()?;
+        while self.is_keyword("or") {
+            self.next();
+            let rhs = self.parse_and()?;
+            lhs = Query::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Query, QueryParseError> {
+        let mut lhs = self.parse_unary()?;
+        while self.is_keyword("and") {
+            self.next();
+            let rhs = self.parse_unary()?;
+            lhs = Query::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, QueryParseError> {
+        if self.is_keyword("not") {
+            self.next();
+            let query = self.parse_unary()?;
+            return Ok(Query::Not(Box::new(query)));
+        }
+
+        if let Some(Token::LParen) = self.peek() {
+            self.next();
+            let query = self.parse_query()?;
+            return match self.peek() {
+                Some(Token::RParen) => {
+                    self.next();
+                    Ok(query)
+                }
+                _ => self.unexpected("`)`"),
+            };
+        }
+
+        self.parse_predicate()
+    }
+
+    fn parse_predicate(&mut self) -> Result<Query, QueryParseError> {
+        let name = match self.peek() {
+            Some(Token::Word(word)) => word.clone(),
+            _ => return self.unexpected("a predicate"),
+        };
+        match name.as_str() {
+            "age" => {
+                self.next();
+                let comparison = match self.peek() {
+                    Some(Token::Comparison(comparison)) => *comparison,
+                    _ => return self.unexpected("one of `<`, `<=`, `>`, or `>=`"),
+                };
+                self.next();
+                let duration = self.parse_duration()?;
+                Ok(Query::Age(comparison, duration))
+            }
+
+            "author" => {
+                self.next();
+                let text = self.parse_match()?;
+                Ok(Query::Author(text))
+            }
+
+            "message" => {
+                self.next();
+                let text = self.parse_match()?;
+                Ok(Query::Message(text))
+            }
+
+            "branch" => {
+                self.next();
+                if let Some(Token::Tilde) = self.peek() {
+                    let text = self.parse_match()?;
+                    Ok(Query::Branch(Some(text)))
+                } else {
+                    Ok(Query::Branch(None))
+                }
+            }
+
+            _ => self.error(format!(
+                "Unknown predicate `{}` (expected one of `age`, `author`, `message`, or `branch`)",
+                name
+            )),
+        }
+    }
+
+    fn parse_match(&mut self) -> Result<String, QueryParseError> {
+        match self.peek() {
+            Some(Token::Tilde) => {
+                self.next();
+            }
+            _ => return self.unexpected("`~`"),
+        }
+        match self.peek() {
+            Some(Token::Word(text)) | Some(Token::String(text)) => {
+                let text = text.clone();
+                self.next();
+                Ok(text)
+            }
+            _ => self.unexpected("a string"),
+        }
+    }
+
+    fn parse_duration(&mut self) -> Result<Duration, QueryParseError> {
+        let word = match self.peek() {
+            Some(Token::Word(word)) => word.clone(),
+            _ => return self.unexpected("a duration, like `30d`"),
+        };
+        match parse_duration(&word) {
+            Ok(duration) => {
+                self.next();
+                Ok(duration)
+            }
+            Err(message) => self.error(message),
+        }
+    }
+}
+
+/// Parse a duration consisting of an integer and a unit, like `30d`. The
+/// supported units are `s`, `m`, `h`, `d`, and `w`.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let split_index = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split_index);
+    let amount: u64 = match amount.parse() {
+        Ok(amount) => amount,
+        Err(_) => {
+            return Err(format!(
+                "Expected a duration, like `30d`, but got `{}`",
+                input
+            ))
+        }
+    };
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => {
+            return Err(format!(
+                "Unknown duration unit in `{}` (expected one of `s`, `m`, `h`, `d`, or `w`)",
+                input
+            ))
+        }
+    };
+    match amount.checked_mul(unit_seconds) {
+        Some(seconds) => Ok(Duration::from_secs(seconds)),
+        None => Err(format!("Duration is too large: `{}`", input)),
+    }
+}
+
+/// Parse the given query string.
+pub fn parse_query(input: &str) -> Result<Query, QueryParseError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        input_len: input.len(),
+    };
+    let query = parser.parse_query()?;
+    match parser.peek() {
+        None => Ok(query),
+        Some(_) => parser.unexpected("`and`, `or`, or end of query"),
+    }
+}
+
+/// The information needed to evaluate a query against commits.
+pub struct QueryContext<'a> {
+    /// The time against which commit ages are measured.
+    pub now: SystemTime,
+
+    /// The mailmap used to resolve commit authors.
+    pub mailmap: &'a Mailmap,
+
+    /// A mapping from commit OID to the branches which point to that commit.
+    pub branch_oid_to_names: &'a HashMap<NonZeroOid, HashSet<OsString>>,
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+impl Query {
+    /// Determine whether the given commit matches this query.
+    pub fn matches(&self, context: &QueryContext, commit: &Commit) -> eyre::Result<bool> {
+        let result = match self {
+            Query::And(lhs, rhs) => {
+                lhs.matches(context, commit)? && rhs.matches(context, commit)?
+            }
+            Query::Or(lhs, rhs) => lhs.matches(context, commit)? || rhs.matches(context, commit)?,
+            Query::Not(query) => !query.matches(context, commit)?,
+
+            Query::Age(comparison, duration) => {
+                let commit_time = SystemTime::UNIX_EPOCH
+                    + Duration::from_secs(commit.get_time().seconds().max(0).try_into()?);
+                let age = context
+                    .now
+                    .duration_since(commit_time)
+                    .unwrap_or_else(|_| Duration::from_secs(0));
+                comparison.compare(age, *duration)
+            }
+
+            Query::Author(text) => {
+                let author = context.mailmap.resolve_signature(&commit.get_author())?;
+                author
+                    .get_name()
+                    .into_iter()
+                    .chain(author.get_email())
+                    .any(|author| contains_ignore_case(author, text))
+            }
+
+            Query::Message(text) => {
+                contains_ignore_case(&commit.get_message_raw()?.to_string_lossy(), text)
+            }
+
+            Query::Branch(text) => match context.branch_oid_to_names.get(&commit.get_oid()) {
+                None => false,
+                Some(branch_names) => match text {
+                    None => !branch_names.is_empty(),
+                    Some(text) => branch_names.iter().any(|branch_name| {
+                        let branch_name = branch_name.to_string_lossy();
+                        let branch_name = branch_name
+                            .strip_prefix("refs/heads/")
+                            .unwrap_or(&branch_name);
+                        contains_ignore_case(branch_name, text)
+                    }),
+                },
+            },
+        };
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query("age > 30d"),
+            Ok(Query::Age(
+                Comparison::GreaterThan,
+                Duration::from_secs(30 * 24 * 60 * 60)
+            ))
+        );
+        assert_eq!(
+            parse_query(r#"author ~ "Jane Doe" and not branch"#),
+            Ok(Query::And(
+                Box::new(Query::Author("Jane Doe".to_string())),
+                Box::new(Query::Not(Box::new(Query::Branch(None)))),
+            ))
+        );
+        assert_eq!(
+            parse_query("message ~ wip or message ~ fixup and branch ~ foo"),
+            Ok(Query::Or(
+                Box::new(Query::Message("wip".to_string())),
+                Box::new(Query::And(
+                    Box::new(Query::Message("fixup".to_string())),
+                    Box::new(Query::Branch(Some("foo".to_string()))),
+                )),
+            ))
+        );
+        assert_eq!(
+            parse_query("(branch or age <= 2h) and not (message ~ keep)"),
+            Ok(Query::And(
+                Box::new(Query::Or(
+                    Box::new(Query::Branch(None)),
+                    Box::new(Query::Age(
+                        Comparison::LessThanOrEqual,
+                        Duration::from_secs(2 * 60 * 60)
+                    )),
+                )),
+                Box::new(Query::Not(Box::new(Query::Message("keep".to_string())))),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_query_errors() {
+        let describe = |input: &str| parse_query(input).unwrap_err().to_string();
+        assert_eq!(
+            describe("age > 30x"),
+            "Unknown duration unit in `30x` (expected one of `s`, `m`, `h`, `d`, or `w`) (at position 6)"
+        );
+        assert_eq!(
+            describe("age > 99999999999999999w"),
+            "Duration is too large: `99999999999999999w` (at position 6)"
+        );
+        assert_eq!(
+            describe("age 30d"),
+            "Expected one of `<`, `<=`, `>`, or `>=`, but got `30d` (at position 4)"
+        );
+        assert_eq!(
+            describe("(branch"),
+            "Expected `)`, but got end of query (at position 7)"
+        );
+        assert_eq!(
+            describe("branch branch"),
+            "Expected `and`, `or`, or end of query, but got `branch` (at position 7)"
+        );
+        assert_eq!(
+            describe(r#"message ~ "wip"#),
+            "Unterminated string (at position 10)"
+        );
+        assert_eq!(
+            describe("foo"),
+            "Unknown predicate `foo` (expected one of `age`, `author`, `message`, or `branch`)"
+                .to_string()
+                + " (at position 0)"
+        );
+    }
+}
@@ -10,12 +10,13 @@ use eyre::Context;
 use os_str_bytes::OsStrBytes;
 use tracing::warn;
 
+use crate::commands::BranchlessExitCode;
 use crate::core::effects::Effects;
 use crate::core::eventlog::EventTransactionId;
 use crate::core::formatting::{printable_styled_string, Pluralize};
 use crate::git::{
-    check_out_commit, CheckOutCommitOptions, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
-    ResolvedReferenceInfo,
+    check_out_commit, CategorizedReferenceName, CheckOutCommitOptions, GitRunInfo, MaybeZeroOid,
+    NonZeroOid, Repo, ResolvedReferenceInfo,
 };
 
 use super::plan::RebasePlan;
@@ -69,6 +70,13 @@ pub fn move_branches<'a>(
                         branch_move_err = Some(err);
                         break 'outer;
                     }
+                    writeln!(
+                        effects.get_verbose_stream(),
+                        "Moved {} from {} to {}",
+                        CategorizedReferenceName::new(name).friendly_describe(),
+                        old_oid,
+                        new_oid
+                    )?;
                     branch_moves.push((*old_oid, MaybeZeroOid::NonZero(*new_oid), name));
                 }
             }
@@ -90,6 +98,12 @@ pub fn move_branches<'a>(
                             break 'outer;
                         }
                     };
+                    writeln!(
+                        effects.get_verbose_stream(),
+                        "Deleted {} (was {})",
+                        CategorizedReferenceName::new(name).friendly_describe(),
+                        old_oid
+                    )?;
                     branch_moves.push((*old_oid, MaybeZeroOid::Zero, name));
                 }
             }
@@ -315,12 +329,17 @@ mod in_memory {
     use std::collections::HashMap;
     use std::ffi::OsString;
     use std::fmt::Write;
+    use std::time::SystemTime;
 
     use eyre::Context;
     use tracing::{instrument, warn};
 
     use crate::commands::gc::mark_commit_reachable;
+    use crate::core::config::{
+        get_commit_gpg_sign, get_restack_empty_commits, RestackEmptyCommits,
+    };
     use crate::core::effects::{Effects, OperationType};
+    use crate::core::eventlog::{Event, EventLogDb, EMPTY_COMMIT_HIDE_MESSAGE};
     use crate::core::formatting::printable_styled_string;
     use crate::core::rewrite::execute::check_out_updated_head;
     use crate::core::rewrite::move_branches;
@@ -341,6 +360,10 @@ mod in_memory {
             /// - This doesn't capture if `HEAD` was pointing to a branch. The
             /// caller will need to figure that out.
             new_head_oid: Option<NonZeroOid>,
+
+            /// The commits which became empty and were dropped, and which
+            /// should be recorded as hidden for that reason.
+            hidden_empty_oids: Vec<NonZeroOid>,
         },
         CannotRebaseMergeCommit {
             commit_oid: NonZeroOid,
@@ -351,6 +374,7 @@ mod in_memory {
     #[instrument]
     pub fn rebase_in_memory(
         effects: &Effects,
+        git_run_info: &GitRunInfo,
         repo: &Repo,
         rebase_plan: &RebasePlan,
         options: &ExecuteRebasePlanOptions,
@@ -386,12 +410,23 @@ mod in_memory {
             force_in_memory: _,
             force_on_disk: _,
             resolve_merge_conflicts: _, // May be needed once we can resolve merge conflicts in memory.
+            sign_commits,
             check_out_commit_options: _, // Caller is responsible for checking out to new HEAD.
         } = options;
 
+        let sign_commits = *sign_commits && get_commit_gpg_sign(repo)?;
+        // Like `git rebase`, keep the original author, but record the current
+        // user as the committer, unless timestamps are being preserved.
+        let default_committer_signature = if *preserve_timestamps {
+            None
+        } else {
+            repo.get_default_signature(git_run_info, *now)?
+        };
         let mut current_oid = rebase_plan.first_dest_oid;
         let mut labels: HashMap<String, NonZeroOid> = HashMap::new();
         let mut rewritten_oids: Vec<(NonZeroOid, MaybeZeroOid)> = Vec::new();
+        let empty_commits = get_restack_empty_commits(repo)?;
+        let mut hidden_empty_oids: Vec<NonZeroOid> = Vec::new();
 
         // Normally, we can determine the new `HEAD` OID by looking at the
         // rewritten commits. However, if `HEAD` pointed to a commit that was
@@ -473,6 +508,13 @@ mod in_memory {
                         "Applying patch for commit: {}",
                         commit_description
                     ));
+                    writeln!(
+                        effects.get_verbose_stream(),
+                        "{} Applying commit onto {}: {}",
+                        commit_num,
+                        current_oid,
+                        commit_description
+                    )?;
                     let commit_tree = match repo.cherry_pick_fast(
                         &commit_to_apply,
                         &current_commit,
@@ -502,10 +544,14 @@ mod in_memory {
                     let committer_signature = if *preserve_timestamps {
                         commit_to_apply.get_committer()
                     } else {
-                        commit_to_apply.get_committer().update_timestamp(*now)?
+                        match &default_committer_signature {
+                            Some(signature) => signature.to_owned(),
+                            None => commit_to_apply.get_committer().update_timestamp(*now)?,
+                        }
                     };
-                    let rebased_commit_oid = repo
-                        .create_commit(
+                    let rebased_commit_oid = if sign_commits {
+                        repo.create_signed_commit(
+                            git_run_info,
                             None,
                             &commit_to_apply.get_author(),
                             &committer_signature,
@@ -513,7 +559,17 @@ mod in_memory {
                             &commit_tree,
                             vec![&current_commit],
                         )
-                        .wrap_err("Applying rebased commit")?;
+                    } else {
+                        repo.create_commit(
+                            None,
+                            &commit_to_apply.get_author(),
+                            &committer_signature,
+                            commit_message,
+                            &commit_tree,
+                            vec![&current_commit],
+                        )
+                    }
+                    .wrap_err("Applying rebased commit")?;
 
                     let rebased_commit = repo
                         .find_commit_or_fail(rebased_commit_oid)
@@ -525,9 +581,12 @@ mod in_memory {
                             rebased_commit_oid,
                         )?,
                     )?;
-                    if rebased_commit.is_empty() {
+                    if rebased_commit.is_empty() && empty_commits != RestackEmptyCommits::Keep {
                         rewritten_oids.push((*commit_oid, MaybeZeroOid::Zero));
                         maybe_set_skipped_head_new_oid(*commit_oid, current_oid);
+                        if empty_commits == RestackEmptyCommits::Hide {
+                            hidden_empty_oids.push(*commit_oid);
+                        }
 
                         writeln!(
                             effects.get_output_stream(),
@@ -633,6 +692,7 @@ mod in_memory {
         Ok(RebaseInMemoryResult::Succeeded {
             rewritten_oids,
             new_head_oid,
+            hidden_empty_oids,
         })
     }
 
@@ -642,15 +702,17 @@ mod in_memory {
         repo: &Repo,
         rewritten_oids: &[(NonZeroOid, MaybeZeroOid)],
         skipped_head_updated_oid: Option<NonZeroOid>,
+        hidden_empty_oids: &[NonZeroOid],
         options: &ExecuteRebasePlanOptions,
     ) -> eyre::Result<isize> {
         let ExecuteRebasePlanOptions {
-            now: _,
+            now,
             event_tx_id,
             preserve_timestamps: _,
             force_in_memory: _,
             force_on_disk: _,
             resolve_merge_conflicts: _,
+            sign_commits: _,
             check_out_commit_options,
         } = options;
 
@@ -680,6 +742,25 @@ mod in_memory {
             &rewritten_oids_map,
         )?;
 
+        // Record why the empty commits were dropped before the `post-rewrite`
+        // hook marks them as rewritten.
+        if !hidden_empty_oids.is_empty() {
+            let conn = repo.get_db_conn()?;
+            let mut event_log_db = EventLogDb::new(&conn)?;
+            let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+            event_log_db.add_events(
+                hidden_empty_oids
+                    .iter()
+                    .map(|commit_oid| Event::ObsoleteEvent {
+                        timestamp,
+                        event_tx_id: *event_tx_id,
+                        commit_oid: *commit_oid,
+                        message: Some(OsString::from(EMPTY_COMMIT_HIDE_MESSAGE)),
+                    })
+                    .collect(),
+            )?;
+        }
+
         // Call the `post-rewrite` hook only after moving branches so that we don't
         // produce a spurious abandoned-branch warning.
         let post_rewrite_stdin: String = rewritten_oids
@@ -744,6 +825,7 @@ mod on_disk {
             force_in_memory: _,
             force_on_disk: _,
             resolve_merge_conflicts: _,
+            sign_commits: _,
             check_out_commit_options: _, // Checkout happens after rebase has concluded.
         } = options;
 
@@ -907,6 +989,7 @@ mod on_disk {
             force_in_memory: _,
             force_on_disk: _,
             resolve_merge_conflicts: _,
+            sign_commits,
             check_out_commit_options: _, // Checkout happens after rebase has concluded.
         } = options;
 
@@ -919,7 +1002,14 @@ mod on_disk {
             effects.get_output_stream(),
             "Calling Git for on-disk rebase..."
         )?;
-        let exit_code = git_run_info.run(effects, Some(*event_tx_id), &["rebase", "--continue"])?;
+        // `git rebase` signs commits according to `commit.gpgSign` by itself,
+        // so it only needs to be told when not to sign them.
+        let args: &[&str] = if *sign_commits {
+            &["rebase", "--continue"]
+        } else {
+            &["-c", "commit.gpgSign=false", "rebase", "--continue"]
+        };
+        let exit_code = git_run_info.run(effects, Some(*event_tx_id), args)?;
         Ok(Ok(exit_code))
     }
 }
@@ -935,7 +1025,8 @@ pub struct ExecuteRebasePlanOptions<'a> {
 
     /// If `true`, any rewritten commits will keep the same authored and
     /// committed timestamps. If `false`, the committed timestamps will be updated
-    /// to the current time.
+    /// to the current time, and the committer will be set to the current user.
+    /// The author and authored timestamp are always preserved.
     pub preserve_timestamps: bool,
 
     /// Force an in-memory rebase (as opposed to an on-disk rebase).
@@ -948,6 +1039,10 @@ pub struct ExecuteRebasePlanOptions<'a> {
     /// rather than failing-fast.
     pub resolve_merge_conflicts: bool,
 
+    /// If `true`, rewritten commits are signed when `commit.gpgSign` is set,
+    /// as with `git rebase`. If `false`, they're never signed.
+    pub sign_commits: bool,
+
     /// If `HEAD` was moved, the options for checking out the new `HEAD` commit.
     pub check_out_commit_options: CheckOutCommitOptions<'a>,
 }
@@ -989,6 +1084,7 @@ pub fn execute_rebase_plan(
         force_in_memory,
         force_on_disk,
         resolve_merge_conflicts,
+        sign_commits: _,
         check_out_commit_options: _,
     } = options;
 
@@ -999,10 +1095,11 @@ pub fn execute_rebase_plan(
             "Attempting rebase in-memory..."
         )?;
 
-        match rebase_in_memory(effects, repo, rebase_plan, options)? {
+        match rebase_in_memory(effects, git_run_info, repo, rebase_plan, options)? {
             RebaseInMemoryResult::Succeeded {
                 rewritten_oids,
                 new_head_oid,
+                hidden_empty_oids,
             } => {
                 post_rebase_in_memory(
                     effects,
@@ -1010,6 +1107,7 @@ pub fn execute_rebase_plan(
                     repo,
                     &rewritten_oids,
                     new_head_oid,
+                    &hidden_empty_oids,
                     options,
                 )?;
                 writeln!(effects.get_output_stream(), "In-memory rebase succeeded.")?;
@@ -1077,6 +1175,13 @@ pub fn execute_rebase_plan(
         use on_disk::*;
         match rebase_on_disk(effects, git_run_info, repo, rebase_plan, options)? {
             Ok(0) => return Ok(ExecuteRebasePlanResult::Succeeded),
+            // If the rebase is still in progress, then Git stopped to let the
+            // user resolve a merge conflict.
+            Ok(_) if repo.get_rebase_state_dir_path().exists() => {
+                return Ok(ExecuteRebasePlanResult::Failed {
+                    exit_code: BranchlessExitCode::MergeConflict.into(),
+                })
+            }
             Ok(exit_code) => return Ok(ExecuteRebasePlanResult::Failed { exit_code }),
             Err(Error::ChangedFilesInRepository) => {
                 write!(
@@ -152,6 +152,14 @@ pub struct RebasePlanBuilder<'repo> {
     /// `y`.
     initial_constraints: HashMap<NonZeroOid, HashSet<NonZeroOid>>,
 
+    /// Commits which should be moved without their descendants. The children
+    /// of these commits are instead rebased onto the commit's original parent.
+    exact_oids: HashSet<NonZeroOid>,
+
+    /// Commits which should not be applied, even if one of their ancestors is
+    /// moved.
+    removed_oids: HashSet<NonZeroOid>,
+
     /// Cache mapping from commit OID to the paths changed in the diff for that
     /// commit. The value is `None` if the commit doesn't have an associated
     /// diff (i.e. is a merge commit).
@@ -241,6 +249,8 @@ impl<'repo> RebasePlanBuilder<'repo> {
         RebasePlanBuilder {
             dag,
             initial_constraints: Default::default(),
+            exact_oids: Default::default(),
+            removed_oids: Default::default(),
             touched_paths_cache: Default::default(),
         }
     }
@@ -443,6 +453,27 @@ impl<'repo> RebasePlanBuilder<'repo> {
         Ok(())
     }
 
+    /// Generate a sequence of rebase steps that cause only the commit at
+    /// `source_oid` to be rebased on top of `dest_oid`. Its descendants are
+    /// left in place, by rebasing its children onto its original parent.
+    pub fn move_commit_exact(
+        &mut self,
+        source_oid: NonZeroOid,
+        dest_oid: NonZeroOid,
+    ) -> eyre::Result<()> {
+        self.move_subtree(source_oid, dest_oid)?;
+        self.exact_oids.insert(source_oid);
+        Ok(())
+    }
+
+    /// Leave the commit at `oid` out of the rebase, even if one of its
+    /// ancestors is moved. Its children are not moved automatically, so the
+    /// caller should use `move_subtree` to say where they should go.
+    pub fn remove_commit(&mut self, oid: NonZeroOid) -> eyre::Result<()> {
+        self.removed_oids.insert(oid);
+        Ok(())
+    }
+
     #[instrument]
     fn collect_descendants(
         &self,
@@ -450,6 +481,12 @@ impl<'repo> RebasePlanBuilder<'repo> {
         acc: &mut Vec<Constraint>,
         current_oid: NonZeroOid,
     ) -> eyre::Result<()> {
+        if self.exact_oids.contains(&current_oid) {
+            // The children of this commit are handled separately in
+            // `add_descendant_constraints`.
+            return Ok(());
+        }
+
         let children_oids = self
             .dag
             .query()
@@ -457,6 +494,9 @@ impl<'repo> RebasePlanBuilder<'repo> {
             .intersection(visible_commits);
         let children_oids = commit_set_to_vec(&children_oids)?;
         for child_oid in children_oids {
+            if self.removed_oids.contains(&child_oid) {
+                continue;
+            }
             acc.push(Constraint {
                 parent_oid: current_oid,
                 child_oid,
@@ -489,6 +529,29 @@ impl<'repo> RebasePlanBuilder<'repo> {
             )?;
             let visible_commits = self.dag.query().ancestors(active_heads)?;
 
+            for exact_oid in self.exact_oids.iter().sorted() {
+                let parent_oids = self.dag.query().parents(CommitSet::from(*exact_oid))?;
+                let parent_oid = match commit_set_to_vec(&parent_oids)?.as_slice() {
+                    [parent_oid] => *parent_oid,
+                    _ => eyre::bail!(
+                        "Can only move a single commit which has exactly one parent: {:?}",
+                        exact_oid
+                    ),
+                };
+                let children_oids = self
+                    .dag
+                    .query()
+                    .children(CommitSet::from(*exact_oid))?
+                    .intersection(&visible_commits);
+                for child_oid in commit_set_to_vec(&children_oids)? {
+                    state
+                        .constraints
+                        .entry(parent_oid)
+                        .or_default()
+                        .insert(child_oid);
+                }
+            }
+
             let mut acc = Vec::new();
             let parents = state.constraints.values().flatten().cloned().collect_vec();
             progress.notify_progress(0, parents.len());
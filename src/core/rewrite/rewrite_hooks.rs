@@ -17,10 +17,13 @@ use os_str_bytes::{OsStrBytes, OsStringBytes};
 use tempfile::NamedTempFile;
 use tracing::instrument;
 
-use crate::core::config::{get_restack_warn_abandoned, RESTACK_WARN_ABANDONED_CONFIG_KEY};
+use crate::core::config::{
+    get_restack_empty_commits, get_restack_warn_abandoned, RestackEmptyCommits,
+    RESTACK_WARN_ABANDONED_CONFIG_KEY,
+};
 use crate::core::dag::Dag;
 use crate::core::effects::Effects;
-use crate::core::eventlog::{Event, EventLogDb, EventReplayer};
+use crate::core::eventlog::{Event, EventLogDb, EventReplayer, EMPTY_COMMIT_HIDE_MESSAGE};
 use crate::core::formatting::{printable_styled_string, Pluralize};
 use crate::git::{
     CategorizedReferenceName, CheckOutCommitOptions, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
@@ -422,6 +425,10 @@ pub fn hook_register_extra_post_rewrite_hook() -> eyre::Result<()> {
 /// For rebases, detect empty commits (which have probably been applied
 /// upstream) and write them to the `rewritten-list` file, so that they're later
 /// passed to the `post-rewrite` hook.
+///
+/// Empty commits are kept instead if `branchless.restack.emptyCommits` is set
+/// to `keep`. If it's set to `hide`, the reason that the commit was dropped is
+/// also recorded.
 pub fn hook_drop_commit_if_empty(
     effects: &Effects,
     old_commit_oid: NonZeroOid,
@@ -440,6 +447,10 @@ pub fn hook_drop_commit_if_empty(
     if !head_commit.is_empty() {
         return Ok(());
     }
+    let empty_commits = get_restack_empty_commits(&repo)?;
+    if empty_commits == RestackEmptyCommits::Keep {
+        return Ok(());
+    }
 
     let only_parent_oid = match head_commit.get_only_parent_oid() {
         Some(only_parent_oid) => only_parent_oid,
@@ -473,6 +484,25 @@ pub fn hook_drop_commit_if_empty(
         ],
     )?;
 
+    if empty_commits == RestackEmptyCommits::Hide {
+        let now = SystemTime::now();
+        let conn = repo.get_db_conn()?;
+        let mut event_log_db = EventLogDb::new(&conn)?;
+        let event_tx_id = event_log_db.make_transaction_id(now, "hook-detect-empty-commit")?;
+        let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+        event_log_db.add_events(
+            [old_commit_oid, head_commit.get_oid()]
+                .iter()
+                .map(|commit_oid| Event::ObsoleteEvent {
+                    timestamp,
+                    event_tx_id,
+                    commit_oid: *commit_oid,
+                    message: Some(OsString::from(EMPTY_COMMIT_HIDE_MESSAGE)),
+                })
+                .collect(),
+        )?;
+    }
+
     Ok(())
 }
 
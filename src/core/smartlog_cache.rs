@@ -0,0 +1,239 @@
+//! Persistent cache of the smartlog commit graph.
+//!
+//! Computing which commits to show in the smartlog requires replaying the
+//! event log and walking the commit graph, which can be slow for large
+//! repositories. The result only depends on the event log, the references in
+//! the repository, and the options used to filter the graph, so it's stored
+//! along with a key derived from those. Any change to a reference or a new
+//! event produces a different key, which invalidates the cached graph.
+
+use std::fmt::Write;
+
+use eyre::Context;
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::core::eventlog::EventLogDb;
+use crate::git::Repo;
+
+/// A node of the smartlog commit graph, as stored in the cache. See
+/// `commands::smartlog::Node`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct CachedSmartlogNode {
+    /// The OID of the commit.
+    pub oid: String,
+
+    /// The OID of the parent node in the smartlog commit graph.
+    pub parent: Option<String>,
+
+    /// The OIDs of the children nodes in the smartlog commit graph, in
+    /// rendering order.
+    pub children: Vec<String>,
+
+    /// Whether some commits between this node and its parent were omitted.
+    pub has_omitted_ancestors: bool,
+
+    /// Whether this is a commit to the main branch.
+    pub is_main: bool,
+
+    /// Whether this commit has been marked as obsolete.
+    pub is_obsolete: bool,
+}
+
+/// The smartlog commit graph, as stored in the cache.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct CachedSmartlogGraph {
+    /// The nodes of the graph, in no particular order.
+    pub nodes: Vec<CachedSmartlogNode>,
+
+    /// The number of commits which were omitted from the graph due to the
+    /// maximum number of commits to show.
+    pub num_omitted_commits: usize,
+
+    /// The number of stacks which were omitted from the graph due to the
+    /// maximum number of refs to walk.
+    #[serde(default)]
+    pub num_omitted_heads: usize,
+}
+
+/// The options which affect which commits appear in the smartlog graph, and
+/// in which order. These are serialized into the cache key, so that a graph
+/// computed with different options isn't reused.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SmartlogCacheOptions {
+    /// Whether hidden commits are shown.
+    pub show_hidden_commits: bool,
+
+    /// Whether only commits with branches are shown.
+    pub only_show_branches: bool,
+
+    /// Whether only commits which haven't been pushed are shown.
+    pub only_show_unpushed: bool,
+
+    /// The name of the order of sibling commits, if one was set.
+    pub order: Option<String>,
+
+    /// The pattern which draft commit authors must match, if any.
+    pub author: Option<String>,
+
+    /// The maximum number of commits to show, if any.
+    pub max_commits: Option<usize>,
+
+    /// The maximum number of refs to walk, if any.
+    pub max_refs: Option<usize>,
+
+    /// The number of recent main branch commits which are always shown.
+    pub num_public_commits: usize,
+
+    /// The OID of the commit before which main branch commits are hidden, if
+    /// any.
+    pub since_commit: Option<String>,
+
+    /// The time before which main branch commits are hidden, if any, in
+    /// seconds since the Unix epoch.
+    pub since_time: Option<i64>,
+}
+
+/// Compute the key under which the smartlog graph is cached.
+#[instrument]
+pub fn make_smartlog_cache_key(
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    options: &SmartlogCacheOptions,
+) -> eyre::Result<String> {
+    let mut key = String::new();
+
+    let (event_log_tip, num_events) = event_log_db.get_tip()?;
+    writeln!(key, "events {:?} {}", event_log_tip, num_events)?;
+
+    let head_info = repo.get_head_info()?;
+    writeln!(
+        key,
+        "HEAD {:?} {:?}",
+        head_info.oid, head_info.reference_name
+    )?;
+    writeln!(key, "main {:?}", repo.get_main_branch_oid()?)?;
+
+    let mut references = Vec::new();
+    for reference in repo.get_all_references()? {
+        let oid = reference.peel_to_commit()?.map(|commit| commit.get_oid());
+        references.push((reference.get_name()?, oid));
+    }
+    references.sort();
+    for (name, oid) in references {
+        writeln!(key, "ref {:?} {:?}", name, oid)?;
+    }
+
+    writeln!(key, "options {}", serde_json::to_string(options)?)?;
+
+    let key = git2::Oid::hash_object(git2::ObjectType::Blob, key.as_bytes())
+        .wrap_err("Hashing smartlog cache key")?;
+    Ok(key.to_string())
+}
+
+/// The maximum number of smartlog graphs to keep in the cache. More than one
+/// is kept so that alternating between sets of options (such as with and
+/// without `--hidden`) doesn't invalidate the cache every time.
+const MAX_CACHED_GRAPHS: usize = 8;
+
+/// Stores the most recently computed smartlog graphs, keyed by the cache key
+/// they were computed for. Uses the same database as the event log.
+pub struct SmartlogCacheDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl std::fmt::Debug for SmartlogCacheDb<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<SmartlogCacheDb>")
+    }
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS smartlog_cache (
+    cache_key TEXT PRIMARY KEY,
+    graph TEXT NOT NULL
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `smartlog_cache` table")?;
+    Ok(())
+}
+
+impl<'conn> SmartlogCacheDb<'conn> {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(conn)?;
+        Ok(SmartlogCacheDb { conn })
+    }
+
+    /// Get the cached graph for the given key, if any. Returns `None` if no
+    /// graph was stored under that key, or it couldn't be read.
+    #[instrument]
+    pub fn get(&self, cache_key: &str) -> eyre::Result<Option<CachedSmartlogGraph>> {
+        let graph: Option<String> = self
+            .conn
+            .query_row(
+                "
+SELECT graph
+FROM smartlog_cache
+WHERE cache_key = :cache_key
+",
+                rusqlite::named_params! {
+                    ":cache_key": cache_key,
+                },
+                |row| row.get("graph"),
+            )
+            .optional()
+            .wrap_err("Querying cached smartlog")?;
+        // If the cached graph was written by an incompatible version, treat
+        // it as a cache miss rather than an error.
+        Ok(graph.and_then(|graph| serde_json::from_str(&graph).ok()))
+    }
+
+    /// Store the graph for the given key, replacing any graph previously
+    /// cached under that key. Only the `MAX_CACHED_GRAPHS` most recently
+    /// stored graphs are kept.
+    #[instrument]
+    pub fn set(&self, cache_key: &str, graph: &CachedSmartlogGraph) -> eyre::Result<()> {
+        let graph = serde_json::to_string(graph)?;
+        let tx = self.conn.unchecked_transaction()?;
+        // Replacing the row assigns it a new row ID, which is then the
+        // largest, so the row IDs are ordered by when the graphs were stored.
+        tx.execute(
+            "
+INSERT OR REPLACE INTO smartlog_cache
+(cache_key, graph)
+VALUES
+(:cache_key, :graph)
+",
+            rusqlite::named_params! {
+                ":cache_key": cache_key,
+                ":graph": graph,
+            },
+        )
+        .wrap_err("Storing cached smartlog")?;
+        tx.execute(
+            "
+DELETE FROM smartlog_cache
+WHERE rowid NOT IN (
+    SELECT rowid
+    FROM smartlog_cache
+    ORDER BY rowid DESC
+    LIMIT :max_cached_graphs
+)
+",
+            rusqlite::named_params! {
+                ":max_cached_graphs": MAX_CACHED_GRAPHS,
+            },
+        )
+        .wrap_err("Evicting cached smartlogs")?;
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+//! Persistent storage for the commit indices shown by `git smartlog
+//! --show-index`.
+//!
+//! The indices from the most recently rendered smartlog are stored, so that
+//! they can be used to refer to commits in later commands, like `git
+//! branchless checkout 3`.
+
+use std::convert::TryInto;
+
+use eyre::Context;
+use rusqlite::OptionalExtension;
+use tracing::instrument;
+
+use crate::git::NonZeroOid;
+
+/// Stores the mapping from smartlog index to commit OID. Uses the same
+/// database as the event log.
+pub struct SmartlogIndexDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl std::fmt::Debug for SmartlogIndexDb<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<SmartlogIndexDb>")
+    }
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS smartlog_index (
+    idx INTEGER PRIMARY KEY,
+    commit_oid TEXT NOT NULL
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `smartlog_index` table")?;
+    Ok(())
+}
+
+impl<'conn> SmartlogIndexDb<'conn> {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(conn)?;
+        Ok(SmartlogIndexDb { conn })
+    }
+
+    /// Replace the stored indices with the given commits. The first commit is
+    /// assigned index 1, the second index 2, and so on.
+    #[instrument]
+    pub fn set_commits(&self, commit_oids: &[NonZeroOid]) -> eyre::Result<()> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .wrap_err("Starting transaction")?;
+        tx.execute("DELETE FROM smartlog_index", rusqlite::params![])
+            .wrap_err("Clearing smartlog indices")?;
+        for (idx, commit_oid) in (1..).zip(commit_oids) {
+            tx.execute(
+                "
+INSERT INTO smartlog_index
+(idx, commit_oid)
+VALUES
+(:idx, :commit_oid)
+",
+                rusqlite::named_params! {
+                    ":idx": idx,
+                    ":commit_oid": commit_oid.to_string(),
+                },
+            )
+            .wrap_err("Storing smartlog index")?;
+        }
+        tx.commit().wrap_err("Committing transaction")?;
+        Ok(())
+    }
+
+    /// Look up the commit which was shown with the given index in the most
+    /// recently rendered smartlog, if any.
+    #[instrument]
+    pub fn get_commit(&self, idx: usize) -> eyre::Result<Option<NonZeroOid>> {
+        let idx: i64 = match idx.try_into() {
+            Ok(idx) => idx,
+            Err(_) => return Ok(None),
+        };
+        let commit_oid: Option<String> = self
+            .conn
+            .query_row(
+                "
+SELECT commit_oid
+FROM smartlog_index
+WHERE idx = :idx
+",
+                rusqlite::named_params! {
+                    ":idx": idx,
+                },
+                |row| row.get("commit_oid"),
+            )
+            .optional()
+            .wrap_err("Querying smartlog index")?;
+        match commit_oid {
+            Some(commit_oid) => Ok(Some(commit_oid.parse()?)),
+            None => Ok(None),
+        }
+    }
+}
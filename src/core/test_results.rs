@@ -0,0 +1,118 @@
+//! Persistent storage for the results of `git test`.
+//!
+//! Results are keyed by the command which was run and the tree OID of the
+//! commit that it was run on, so that commits whose contents haven't changed
+//! (such as those which were only reworded or moved without conflicts) don't
+//! need to be tested again.
+
+use eyre::Context;
+use rusqlite::OptionalExtension;
+use tracing::instrument;
+
+use crate::git::NonZeroOid;
+
+/// The result of running a test command on a commit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TestResult {
+    /// The exit code of the command.
+    pub exit_code: i32,
+}
+
+impl TestResult {
+    /// Whether or not the command succeeded.
+    pub fn is_passed(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+/// Stores the results of test commands. Uses the same database as the event
+/// log.
+pub struct TestResultsDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl std::fmt::Debug for TestResultsDb<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<TestResultsDb>")
+    }
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS test_results (
+    command TEXT NOT NULL,
+    tree_oid TEXT NOT NULL,
+    exit_code INTEGER NOT NULL,
+    PRIMARY KEY (command, tree_oid)
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `test_results` table")?;
+    Ok(())
+}
+
+impl<'conn> TestResultsDb<'conn> {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(conn)?;
+        Ok(TestResultsDb { conn })
+    }
+
+    /// Look up the cached result of running `command` on a commit with the
+    /// given tree, if any.
+    #[instrument]
+    pub fn get_result(
+        &self,
+        command: &str,
+        tree_oid: NonZeroOid,
+    ) -> eyre::Result<Option<TestResult>> {
+        let exit_code: Option<i32> = self
+            .conn
+            .query_row(
+                "
+SELECT exit_code
+FROM test_results
+WHERE command = :command AND tree_oid = :tree_oid
+",
+                rusqlite::named_params! {
+                    ":command": command,
+                    ":tree_oid": tree_oid.to_string(),
+                },
+                |row| row.get("exit_code"),
+            )
+            .optional()
+            .wrap_err("Querying test result")?;
+        Ok(exit_code.map(|exit_code| TestResult { exit_code }))
+    }
+
+    /// Record the result of running `command` on a commit with the given tree.
+    #[instrument]
+    pub fn set_result(
+        &self,
+        command: &str,
+        tree_oid: NonZeroOid,
+        result: TestResult,
+    ) -> eyre::Result<()> {
+        let TestResult { exit_code } = result;
+        self.conn
+            .execute(
+                "
+INSERT OR REPLACE INTO test_results
+(command, tree_oid, exit_code)
+VALUES
+(:command, :tree_oid, :exit_code)
+",
+                rusqlite::named_params! {
+                    ":command": command,
+                    ":tree_oid": tree_oid.to_string(),
+                    ":exit_code": exit_code,
+                },
+            )
+            .wrap_err("Storing test result")?;
+        Ok(())
+    }
+}
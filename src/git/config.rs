@@ -110,6 +110,24 @@ impl GetConfigValue<bool> for bool {
     }
 }
 
+impl GetConfigValue<i32> for i32 {
+    fn get_from_config(config: &Config, key: impl AsRef<str>) -> eyre::Result<Option<i32>> {
+        #[instrument]
+        fn inner(config: &Config, key: &str) -> eyre::Result<Option<i32>> {
+            let value = match config.inner.get_i32(key) {
+                Ok(value) => Some(value),
+                Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+                Err(err) => {
+                    return Err(wrap_git_error(err))
+                        .wrap_err("Looking up integer value for config key")
+                }
+            };
+            Ok(value)
+        }
+        inner(config, key.as_ref())
+    }
+}
+
 impl GetConfigValue<PathBuf> for PathBuf {
     fn get_from_config(config: &Config, key: impl AsRef<str>) -> eyre::Result<Option<PathBuf>> {
         #[instrument]
@@ -212,6 +230,39 @@ impl Config {
         Ok(Config { inner })
     }
 
+    /// Get the path to the user's global configuration file, such as
+    /// `~/.gitconfig`. If it doesn't exist yet, returns the path where it
+    /// would be created.
+    #[instrument]
+    pub fn get_global_path() -> eyre::Result<PathBuf> {
+        match git2::Config::find_global() {
+            Ok(path) => Ok(path),
+            Err(_) => match std::env::var_os("HOME") {
+                Some(home_dir) => Ok(PathBuf::from(home_dir).join(".gitconfig")),
+                None => eyre::bail!("Could not determine the global Git config path"),
+            },
+        }
+    }
+
+    /// Get the names and values of all entries whose key matches the given
+    /// regular expression.
+    #[instrument]
+    pub fn get_entries_matching(&self, regex: &str) -> eyre::Result<Vec<(String, String)>> {
+        let entries = self
+            .inner
+            .entries(Some(regex))
+            .map_err(wrap_git_error)
+            .wrap_err("Iterating config entries")?;
+        let mut result = Vec::new();
+        for entry in &entries {
+            let entry = entry.map_err(wrap_git_error)?;
+            if let (Some(name), Some(value)) = (entry.name(), entry.value()) {
+                result.push((name.to_owned(), value.to_owned()));
+            }
+        }
+        Ok(result)
+    }
+
     #[instrument]
     fn set_inner(&mut self, key: &str, value: ConfigValue) -> eyre::Result<()> {
         match &value.inner {
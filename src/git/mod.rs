@@ -5,13 +5,16 @@ mod oid;
 mod repo;
 mod run;
 mod tree;
+mod worktree;
 
 pub use config::{Config, ConfigRead, ConfigValue, ConfigWrite};
 pub use oid::{MaybeZeroOid, NonZeroOid};
 pub use repo::{
     AmendFastOptions, Branch, CategorizedReferenceName, CherryPickFastError, CherryPickFastOptions,
-    Commit, Diff, FileStatus, GitVersion, PatchId, Reference, ReferenceTarget, Repo,
-    RepoReferencesSnapshot, ResolvedReferenceInfo, StatusEntry,
+    Commit, CommitRange, Diff, DiffStat, FileDiffStat, FileStatus, GitVersion, Mailmap, PatchId,
+    Reference, ReferenceTarget, ReflogEntry, Repo, RepoReferencesSnapshot, ResolvedReferenceInfo,
+    Signature, StatusEntry,
 };
-pub use run::{check_out_commit, CheckOutCommitOptions, GitRunInfo};
+pub use run::{check_out_commit, CheckOutCommitOptions, GitRunInfo, GitRunOpts};
 pub use tree::Tree;
+pub use worktree::TemporaryWorktree;
@@ -28,18 +28,21 @@ use os_str_bytes::{OsStrBytes, OsStringBytes};
 use regex::bytes::Regex;
 use tracing::{instrument, warn};
 
-use crate::core::config::get_main_branch_name;
+use crate::core::config::{get_main_branch_name, get_main_branch_name_candidates};
 use crate::core::effects::{Effects, OperationType};
-use crate::core::eventlog::EventTransactionId;
-use crate::core::formatting::{Glyphs, StyledStringBuilder};
-use crate::core::node_descriptors::{
-    render_node_descriptors, CommitMessageDescriptor, CommitOidDescriptor, NodeObject, Redactor,
-};
+use crate::core::eventlog::{EventLogDb, EventTransactionId};
+use crate::core::formatting::{Abbrev, Glyphs, StyledStringBuilder};
+use crate::core::node_descriptors::CommitView;
 use crate::git::config::{Config, ConfigRead};
 use crate::git::oid::{make_non_zero_oid, MaybeZeroOid, NonZeroOid};
-use crate::git::run::GitRunInfo;
+use crate::git::run::{GitRunInfo, GitRunOpts};
 use crate::git::tree::{dehydrate_tree, get_changed_paths_between_trees, hydrate_tree, Tree};
 
+/// The `user_version` of the shared database once the event logs which older
+/// versions kept for each worktree have been merged into it. See
+/// `Repo::get_db_conn`.
+const WORKTREE_EVENT_LOGS_MERGED_VERSION: i64 = 1;
+
 /// Convert a `git2::Error` into an `eyre::Error` with an auto-generated message.
 pub(super) fn wrap_git_error(error: git2::Error) -> eyre::Error {
     eyre::eyre!("Git error {:?}: {}", error.code(), error.message())
@@ -178,6 +181,19 @@ pub struct RepoReferencesSnapshot {
     pub branch_oid_to_names: HashMap<NonZeroOid, HashSet<OsString>>,
 }
 
+/// An entry in the reflog of a reference.
+#[derive(Clone, Debug)]
+pub struct ReflogEntry {
+    /// The OID that the reference pointed to before the update.
+    pub old_oid: MaybeZeroOid,
+
+    /// The OID that the reference pointed to after the update.
+    pub new_oid: MaybeZeroOid,
+
+    /// The message associated with the update, such as `commit (amend): foo`.
+    pub message: Option<String>,
+}
+
 /// Wrapper around `git2::Repository`.
 pub struct Repo {
     pub(super) inner: git2::Repository,
@@ -216,9 +232,75 @@ impl Repo {
         self.inner.path()
     }
 
+    /// Get the path to the `.git` directory shared by all worktrees of the
+    /// repository. For the main worktree, this is the same as
+    /// [`Repo::get_path`]. For a linked worktree, [`Repo::get_path`] is the
+    /// worktree's own directory inside `.git/worktrees`, and this is the main
+    /// `.git` directory.
+    pub fn get_common_path(&self) -> PathBuf {
+        let path = self.get_path();
+        if !self.inner.is_worktree() {
+            return path.to_path_buf();
+        }
+        match std::fs::read_to_string(path.join("commondir")) {
+            Ok(common_dir) => path.join(common_dir.trim()),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+
+    /// Get the name of the linked worktree which this repository was opened
+    /// from, or `None` if it was opened from the main worktree.
+    pub fn get_worktree_name(&self) -> Option<String> {
+        if !self.inner.is_worktree() {
+            return None;
+        }
+        self.get_path()
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+
+    /// Get the names of the branches which are checked out in any worktree of
+    /// this repository, including the main worktree.
+    #[instrument]
+    pub fn get_checked_out_branch_names(&self) -> eyre::Result<HashSet<OsString>> {
+        let common_path = self.get_common_path();
+        let mut head_paths = vec![common_path.join("HEAD")];
+        let worktree_names = self.inner.worktrees().map_err(wrap_git_error)?;
+        for worktree_name in worktree_names.iter().flatten() {
+            head_paths.push(common_path.join("worktrees").join(worktree_name).join("HEAD"));
+        }
+
+        let mut result = HashSet::new();
+        for head_path in head_paths {
+            let contents = match std::fs::read_to_string(&head_path) {
+                Ok(contents) => contents,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => {
+                    return Err(err)
+                        .wrap_err_with(|| format!("Reading HEAD at: {:?}", &head_path))
+                }
+            };
+            if let Some(reference_name) = contents.trim().strip_prefix("ref: ") {
+                result.insert(OsString::from(reference_name));
+            }
+        }
+        Ok(result)
+    }
+
+    /// Get the name under which updates to this worktree's `HEAD` are recorded
+    /// in the event log. This is `HEAD` for the main worktree, and
+    /// `worktrees/<name>/HEAD` for a linked worktree, matching how Git refers
+    /// to the `HEAD` of another worktree.
+    pub fn get_head_reference_name(&self) -> OsString {
+        match self.get_worktree_name() {
+            Some(worktree_name) => OsString::from(format!("worktrees/{}/HEAD", worktree_name)),
+            None => OsString::from("HEAD"),
+        }
+    }
+
     /// Get the path to the `packed-refs` file for the repository.
     pub fn get_packed_refs_path(&self) -> PathBuf {
-        self.inner.path().join("packed-refs")
+        self.get_common_path().join("packed-refs")
     }
 
     /// Get the path to the directory inside the `.git` directory which contains
@@ -255,16 +337,28 @@ impl Repo {
         Ok(Config::from(config))
     }
 
+    /// Get the mailmap for this repository, which is used to map the names and
+    /// email addresses in commit signatures to their canonical versions.
+    #[instrument]
+    pub fn get_mailmap(&self) -> eyre::Result<Mailmap> {
+        let mailmap = self
+            .inner
+            .mailmap()
+            .map_err(wrap_git_error)
+            .wrap_err("Reading mailmap")?;
+        Ok(Mailmap { inner: mailmap })
+    }
+
     /// Get the file where git-branchless-specific Git configuration is stored.
     #[instrument]
     pub fn get_config_path(&self) -> PathBuf {
-        self.get_path().join("branchless").join("config")
+        self.get_common_path().join("branchless").join("config")
     }
 
     /// Get the directory where the DAG for the repository is stored.
     #[instrument]
     pub fn get_dag_dir(&self) -> PathBuf {
-        self.get_path().join("branchless").join("dag")
+        self.get_common_path().join("branchless").join("dag")
     }
 
     /// Get the directory to store man-pages. Note that this is the `man`
@@ -272,7 +366,7 @@ impl Repo {
     /// go into the `man/man1` directory to be found by `man`.
     #[instrument]
     pub fn get_man_dir(&self) -> PathBuf {
-        self.get_path().join("branchless").join("man")
+        self.get_common_path().join("branchless").join("man")
     }
 
     /// Get a directory suitable for storing temporary files.
@@ -283,20 +377,87 @@ impl Repo {
     /// <https://github.com/arxanas/git-branchless/discussions/120>.
     #[instrument]
     pub fn get_tempfile_dir(&self) -> PathBuf {
-        self.get_path().join("branchless").join("tmp")
+        self.get_common_path().join("branchless").join("tmp")
     }
 
     /// Get the connection to the SQLite database for this repository.
     #[instrument]
     pub fn get_db_conn(&self) -> eyre::Result<rusqlite::Connection> {
-        let dir = self.get_path().join("branchless");
+        let dir = self.get_common_path().join("branchless");
         std::fs::create_dir_all(&dir).wrap_err("Creating .git/branchless dir")?;
         let path = dir.join("db.sqlite3");
         let conn = rusqlite::Connection::open(&path)
             .wrap_err_with(|| format!("Opening database connection at {:?}", &path))?;
+        self.merge_worktree_event_logs(&conn)?;
         Ok(conn)
     }
 
+    /// Merge the event logs which older versions of git-branchless kept under
+    /// each linked worktree's own Git directory into the shared event log.
+    /// Each old database is renamed afterwards. Once they've all been merged,
+    /// the shared database is marked with `WORKTREE_EVENT_LOGS_MERGED_VERSION`,
+    /// so that the worktrees aren't checked again.
+    #[instrument]
+    fn merge_worktree_event_logs(&self, conn: &rusqlite::Connection) -> eyre::Result<()> {
+        let user_version: i64 = conn
+            .query_row("PRAGMA user_version", rusqlite::params![], |row| row.get(0))
+            .wrap_err("Querying database version")?;
+        if user_version >= WORKTREE_EVENT_LOGS_MERGED_VERSION {
+            return Ok(());
+        }
+
+        let worktrees_dir = self.get_common_path().join("worktrees");
+        let worktree_names = if worktrees_dir.exists() {
+            self.inner
+                .worktrees()
+                .map_err(wrap_git_error)?
+                .iter()
+                .flatten()
+                .map(|worktree_name| worktree_name.to_owned())
+                .sorted()
+                .collect()
+        } else {
+            Vec::new()
+        };
+        for worktree_name in worktree_names {
+            let dir = worktrees_dir.join(&worktree_name).join("branchless");
+            let head_ref_name = OsString::from(format!("worktrees/{}/HEAD", worktree_name));
+            // An earlier version moved the database to `db.sqlite3.merging`
+            // while merging it, so it may have been left there if the merge
+            // was interrupted.
+            for file_name in ["db.sqlite3.merging", "db.sqlite3"] {
+                let old_path = dir.join(file_name);
+                if !old_path.exists() {
+                    continue;
+                }
+
+                // If the merge is interrupted, then it's rolled back, and the
+                // database is merged again next time. If it's interrupted
+                // after the merge but before the database is renamed, then
+                // the database is recorded as merged, so it's not merged
+                // twice.
+                let mut event_log_db = EventLogDb::new(conn)?;
+                event_log_db.merge_event_log(&old_path, &head_ref_name)?;
+                match std::fs::rename(&old_path, dir.join("db.sqlite3.merged")) {
+                    Ok(()) => {}
+                    // A concurrent process already renamed it.
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(err) => {
+                        return Err(err)
+                            .wrap_err_with(|| format!("Renaming merged event log {:?}", &old_path))
+                    }
+                }
+            }
+        }
+
+        conn.execute_batch(&format!(
+            "PRAGMA user_version = {}",
+            WORKTREE_EVENT_LOGS_MERGED_VERSION
+        ))
+        .wrap_err("Setting database version")?;
+        Ok(())
+    }
+
     /// Get a snapshot of information about a given reference.
     #[instrument]
     pub fn resolve_reference(&self, reference: &Reference) -> eyre::Result<ResolvedReferenceInfo> {
@@ -370,16 +531,32 @@ impl Repo {
             None => match self.find_branch(&main_branch_name, git2::BranchType::Remote)? {
                 Some(branch) => Ok(branch.into_reference()),
                 None => {
+                    let candidates = get_main_branch_name_candidates(self)?;
+                    let description = match candidates.as_slice() {
+                        [candidate] => {
+                            format!("The main branch {:?} could not be found", candidate)
+                        }
+                        candidates => format!(
+                            "None of the main branch candidates {} could be found",
+                            candidates
+                                .iter()
+                                .map(|candidate| format!("{:?}", candidate))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                    };
                     let suggestion = format!(
                         r"
-The main branch {:?} could not be found in your repository
+{} in your repository
 at path: {:?}.
 These branches exist: {:?}
 Either create it, or update the main branch setting by running:
 
     git config branchless.core.mainBranch <branch>
+
+(Multiple candidates can be given as a comma-separated list.)
 ",
-                        get_main_branch_name(self)?,
+                        description,
                         self.get_path(),
                         self.get_all_local_branches()?
                             .into_iter()
@@ -443,6 +620,30 @@ Either create it, or update the main branch setting by running:
         Ok(result)
     }
 
+    /// Get a mapping from OID to the names of tags which point to that OID.
+    /// Both lightweight and annotated tags are included; annotated tags are
+    /// peeled to the commit which they refer to.
+    ///
+    /// The returned tag names include the `refs/tags/` prefix, so it must be
+    /// stripped if desired.
+    #[instrument]
+    pub fn get_tag_oid_to_names(&self) -> eyre::Result<HashMap<NonZeroOid, HashSet<OsString>>> {
+        let mut result: HashMap<NonZeroOid, HashSet<OsString>> = HashMap::new();
+        for reference in self.get_all_references()? {
+            let reference_name = reference.get_name()?;
+            if !reference_name.to_string_lossy().starts_with("refs/tags/") {
+                continue;
+            }
+            if let Some(commit) = reference.peel_to_commit()? {
+                result
+                    .entry(commit.get_oid())
+                    .or_insert_with(HashSet::new)
+                    .insert(reference_name);
+            }
+        }
+        Ok(result)
+    }
+
     /// Get the positions of references in the repository.
     pub fn get_references_snapshot(&self) -> eyre::Result<RepoReferencesSnapshot> {
         let head_oid = self.get_head_info()?.oid;
@@ -518,6 +719,19 @@ Either create it, or update the main branch setting by running:
         }
     }
 
+    /// Count the number of commits which are reachable from `local` but not
+    /// `upstream`, and vice-versa.
+    #[instrument]
+    pub fn graph_ahead_behind(
+        &self,
+        local: NonZeroOid,
+        upstream: NonZeroOid,
+    ) -> eyre::Result<(usize, usize)> {
+        self.inner
+            .graph_ahead_behind(local.inner, upstream.inner)
+            .map_err(wrap_git_error)
+    }
+
     /// Get the patch for a commit, i.e. the diff between that commit and its
     /// parent.
     ///
@@ -558,6 +772,48 @@ Either create it, or update the main branch setting by running:
         Ok(Some(Diff { inner: diff }))
     }
 
+    /// Get the number of lines inserted and deleted by the given commit, in
+    /// total and per file. Merge commits are compared against their first
+    /// parent.
+    #[instrument]
+    pub fn get_commit_diff_stat(&self, commit: &Commit) -> eyre::Result<DiffStat> {
+        let parent_tree = match commit.inner.parents().next() {
+            Some(parent) => Some(parent.tree().wrap_err("Getting parent tree")?),
+            None => None,
+        };
+        let current_tree = commit.get_tree()?;
+        let diff = self
+            .inner
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&current_tree.inner), None)
+            .wrap_err_with(|| format!("Calculating diff for: {:?}", commit))?;
+
+        let mut result = DiffStat::default();
+        for (idx, delta) in diff.deltas().enumerate() {
+            let path = match delta.new_file().path().or_else(|| delta.old_file().path()) {
+                Some(path) => path.to_path_buf(),
+                None => continue,
+            };
+            let (insertions, deletions) =
+                match git2::Patch::from_diff(&diff, idx).wrap_err("Calculating patch for file")? {
+                    Some(patch) => {
+                        let (_context, insertions, deletions) =
+                            patch.line_stats().wrap_err("Counting changed lines")?;
+                        (insertions, deletions)
+                    }
+                    // Binary files don't have line counts.
+                    None => (0, 0),
+                };
+            result.insertions += insertions;
+            result.deletions += deletions;
+            result.files.push(FileDiffStat {
+                path,
+                insertions,
+                deletions,
+            });
+        }
+        Ok(result)
+    }
+
     /// Returns the set of paths currently staged to the repository's index.
     #[instrument]
     pub fn get_staged_paths(&self) -> eyre::Result<HashSet<PathBuf>> {
@@ -637,6 +893,35 @@ Either create it, or update the main branch setting by running:
         }
     }
 
+    /// Attempt to parse the user-provided range descriptor, such as `A..B` or
+    /// `A...B`. Returns `None` if either end of the range could not be resolved
+    /// to a commit.
+    pub fn revparse_range(&self, spec: &str) -> eyre::Result<Option<CommitRange>> {
+        let revspec = match self.inner.revparse(spec) {
+            Ok(revspec) => revspec,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(err) => return Err(wrap_git_error(err)),
+        };
+        if !revspec.mode().contains(git2::RevparseMode::RANGE) {
+            eyre::bail!("Not a range: {:?}", spec);
+        }
+
+        let (from, to) = match (revspec.from(), revspec.to()) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return Ok(None),
+        };
+        // Peel rather than convert, so that annotated tags (as in `v1.0..HEAD`)
+        // resolve to the commits they point to.
+        match (from.peel_to_commit(), to.peel_to_commit()) {
+            (Ok(from), Ok(to)) => Ok(Some(CommitRange {
+                from: Commit { inner: from },
+                to: Commit { inner: to },
+                symmetric: revspec.mode().contains(git2::RevparseMode::MERGE_BASE),
+            })),
+            _ => Ok(None),
+        }
+    }
+
     /// Look up a single reference by name.
     pub fn get_reference(&self, reference_name: &OsStr) -> eyre::Result<Option<Reference>> {
         let reference_name = reference_name.to_str().ok_or_else(|| {
@@ -668,6 +953,31 @@ Either create it, or update the main branch setting by running:
         Ok(all_references)
     }
 
+    /// Get the entries in the reflog of the given reference, ordered from
+    /// oldest to newest. If the reference has no reflog, returns an empty list.
+    #[instrument]
+    pub fn get_reflog_entries(&self, reference_name: &OsStr) -> eyre::Result<Vec<ReflogEntry>> {
+        let reference_name = match reference_name.to_str() {
+            Some(reference_name) => reference_name,
+            None => eyre::bail!("Reference name was not valid UTF-8: {:?}", reference_name),
+        };
+        let reflog = self
+            .inner
+            .reflog(reference_name)
+            .map_err(wrap_git_error)
+            .wrap_err("Reading reflog")?;
+        let mut entries: Vec<ReflogEntry> = reflog
+            .iter()
+            .map(|entry| ReflogEntry {
+                old_oid: entry.id_old().into(),
+                new_oid: entry.id_new().into(),
+                message: entry.message().map(|message| message.to_owned()),
+            })
+            .collect();
+        entries.reverse();
+        Ok(entries)
+    }
+
     /// Check if the repository has staged or unstaged changes. Untracked files
     /// are not included. This operation may take a while.
     #[instrument]
@@ -848,6 +1158,41 @@ Either create it, or update the main branch setting by running:
         }
     }
 
+    /// Get the number of hexadecimal digits needed to abbreviate all of the
+    /// given commit hashes according to `abbrev`. For `Abbrev::Unique`, this is
+    /// the smallest length at which none of the abbreviated hashes is ambiguous.
+    #[instrument(skip(oids))]
+    pub fn get_abbrev_len(
+        &self,
+        abbrev: Abbrev,
+        oids: impl IntoIterator<Item = NonZeroOid>,
+    ) -> eyre::Result<usize> {
+        let min_len = match abbrev {
+            Abbrev::Fixed(len) => return Ok(len),
+            Abbrev::Unique(min_len) => min_len,
+        };
+
+        let odb = self
+            .inner
+            .odb()
+            .map_err(wrap_git_error)
+            .wrap_err("Opening object database")?;
+        let mut result = min_len;
+        for oid in oids {
+            let oid = oid.to_string();
+            while result < oid.len() {
+                let prefix = git2::Oid::from_str(&oid[..result]).map_err(wrap_git_error)?;
+                match odb.exists_prefix(prefix, result) {
+                    Err(err) if err.code() == git2::ErrorCode::Ambiguous => result += 1,
+                    Ok(_) => break,
+                    Err(err) if err.code() == git2::ErrorCode::NotFound => break,
+                    Err(err) => return Err(wrap_git_error(err)),
+                }
+            }
+        }
+        Ok(result)
+    }
+
     /// Look up the commit with the given OID and render a friendly description
     /// of it, or render an error message if not found.
     pub fn friendly_describe_commit_from_oid(
@@ -867,6 +1212,69 @@ Either create it, or update the main branch setting by running:
         }
     }
 
+    /// Get the identity to record as the committer of new commits, as reported
+    /// by `git var GIT_COMMITTER_IDENT`. Like Git, this honors
+    /// `GIT_COMMITTER_NAME`, `GIT_COMMITTER_EMAIL` and `GIT_COMMITTER_DATE`
+    /// before falling back to `user.name` and `user.email`. Unless
+    /// `GIT_COMMITTER_DATE` is set, the signature is timestamped with `now`.
+    ///
+    /// Returns `None` if no identity is configured.
+    #[instrument]
+    pub fn get_default_signature(
+        &self,
+        git_run_info: &GitRunInfo,
+        now: SystemTime,
+    ) -> eyre::Result<Option<Signature<'static>>> {
+        let result = git_run_info.run_silent(
+            self,
+            None,
+            &["var", "GIT_COMMITTER_IDENT"],
+            GitRunOpts {
+                treat_git_failure_as_error: false,
+                ..Default::default()
+            },
+        )?;
+        if result.exit_code != 0 {
+            return Ok(None);
+        }
+
+        let ident = std::str::from_utf8(&result.stdout)
+            .wrap_err("Decoding committer identity")?
+            .trim();
+        let parse_ident = || -> Option<(&str, &str, i64, i32)> {
+            let (ident, offset) = ident.rsplit_once(' ')?;
+            let (ident, seconds) = ident.rsplit_once(' ')?;
+            let (name, email) = ident.strip_suffix('>')?.rsplit_once(" <")?;
+            let seconds = seconds.parse().ok()?;
+            let (sign, offset) = match offset.split_at(1) {
+                ("+", offset) => (1, offset),
+                ("-", offset) => (-1, offset),
+                _ => return None,
+            };
+            if offset.len() != 4 {
+                return None;
+            }
+            let hours: i32 = offset[..2].parse().ok()?;
+            let minutes: i32 = offset[2..].parse().ok()?;
+            Some((name, email, seconds, sign * (hours * 60 + minutes)))
+        };
+        let (name, email, seconds, offset_minutes) = parse_ident()
+            .ok_or_else(|| eyre::eyre!("Could not parse committer identity: {:?}", ident))?;
+
+        let signature =
+            git2::Signature::new(name, email, &git2::Time::new(seconds, offset_minutes))
+                .map_err(wrap_git_error)?;
+        let signature = Signature { inner: signature };
+        if git_run_info
+            .env
+            .contains_key(OsStr::new("GIT_COMMITTER_DATE"))
+        {
+            Ok(Some(signature))
+        } else {
+            Ok(Some(signature.update_timestamp(now)?))
+        }
+    }
+
     /// Create a new commit.
     #[instrument]
     pub fn create_commit(
@@ -896,6 +1304,93 @@ Either create it, or update the main branch setting by running:
         Ok(make_non_zero_oid(oid))
     }
 
+    /// Create a new commit signed with the user's configured key, like
+    /// [`Repo::create_commit`]. `git2` can't sign commits itself, so this
+    /// calls `git commit-tree -S`, which uses the signing configuration
+    /// (`user.signingKey`, `gpg.format`, etc.) in the same way as `git
+    /// commit`.
+    #[instrument]
+    pub fn create_signed_commit(
+        &self,
+        git_run_info: &GitRunInfo,
+        update_ref: Option<&str>,
+        author: &Signature,
+        committer: &Signature,
+        message: &str,
+        tree: &Tree,
+        parents: Vec<&Commit>,
+    ) -> eyre::Result<NonZeroOid> {
+        let mut git_run_info = git_run_info.clone();
+        for (role, signature) in [("AUTHOR", author), ("COMMITTER", committer)].iter() {
+            let name = signature
+                .get_name()
+                .ok_or_else(|| eyre::eyre!("Could not decode signature name: {:?}", signature))?;
+            let email = signature
+                .get_email()
+                .ok_or_else(|| eyre::eyre!("Could not decode signature email: {:?}", signature))?;
+            let time = signature.get_time();
+            let offset_minutes = time.offset_minutes();
+            let date = format!(
+                "@{} {}{:02}{:02}",
+                time.seconds(),
+                if offset_minutes < 0 { '-' } else { '+' },
+                offset_minutes.abs() / 60,
+                offset_minutes.abs() % 60
+            );
+            for (field, value) in [("NAME", name), ("EMAIL", email), ("DATE", &date)].iter() {
+                git_run_info
+                    .env
+                    .insert(format!("GIT_{}_{}", role, field).into(), value.into());
+            }
+        }
+
+        let tree_oid = tree.get_oid().to_string();
+        let parent_oids: Vec<String> = parents
+            .iter()
+            .map(|parent| parent.get_oid().to_string())
+            .collect();
+        let mut args = vec!["commit-tree", "-S", &tree_oid];
+        for parent_oid in parent_oids.iter() {
+            args.extend(["-p", parent_oid.as_str()].iter());
+        }
+        // With no `-m` or `-F` option, the message is read from stdin.
+        let result = git_run_info.run_silent(
+            self,
+            None,
+            &args,
+            GitRunOpts {
+                stdin: Some(message.as_bytes().to_vec()),
+                ..Default::default()
+            },
+        )?;
+        let stdout = String::from_utf8(result.stdout).wrap_err("Decoding commit-tree output")?;
+        let oid: NonZeroOid = stdout.trim().parse()?;
+
+        if let Some(update_ref) = update_ref {
+            // Like `git2`, update the branch that a symbolic reference such
+            // as `HEAD` points to, rather than the symbolic reference itself.
+            let reference_name = match self.inner.find_reference(update_ref) {
+                Ok(reference) => match reference.symbolic_target() {
+                    Some(target) => target.to_owned(),
+                    None => update_ref.to_owned(),
+                },
+                Err(err) if err.code() == git2::ErrorCode::NotFound => update_ref.to_owned(),
+                Err(err) => return Err(wrap_git_error(err)),
+            };
+            let summary = message.lines().next().unwrap_or_default();
+            self.inner
+                .reference(
+                    &reference_name,
+                    oid.inner,
+                    true,
+                    &format!("commit: {}", summary),
+                )
+                .map_err(wrap_git_error)?;
+        }
+
+        Ok(oid)
+    }
+
     /// Cherry-pick a commit in memory and return the resulting index.
     #[instrument]
     pub fn cherry_pick_commit(
@@ -1282,11 +1777,29 @@ impl<'repo> Signature<'repo> {
         Ok(Signature { inner: signature })
     }
 
+    /// Get a copy of this signature which doesn't borrow from the object it
+    /// was read from.
+    pub fn to_owned(&self) -> Signature<'static> {
+        Signature {
+            inner: self.inner.to_owned(),
+        }
+    }
+
     /// Get the time when this signature was applied.
     pub fn get_time(&self) -> git2::Time {
         self.inner.when()
     }
 
+    /// Get the name of the signer, if it could be decoded.
+    pub fn get_name(&self) -> Option<&str> {
+        self.inner.name()
+    }
+
+    /// Get the email address of the signer, if it could be decoded.
+    pub fn get_email(&self) -> Option<&str> {
+        self.inner.email()
+    }
+
     /// Return the friendly formatted name and email of the signature.
     pub fn friendly_describe(&self) -> Option<String> {
         let name = self.inner.name();
@@ -1328,11 +1841,63 @@ impl Index {
     }
 }
 
+/// The mailmap of a repository. See `git-check-mailmap(1)`.
+pub struct Mailmap {
+    inner: git2::Mailmap,
+}
+
+impl std::fmt::Debug for Mailmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Mailmap>")
+    }
+}
+
+impl Mailmap {
+    /// Resolve the provided signature to its canonical name and email
+    /// address, according to the mailmap.
+    #[instrument]
+    pub fn resolve_signature(&self, signature: &Signature) -> eyre::Result<Signature<'static>> {
+        let signature = self
+            .inner
+            .resolve_signature(&signature.inner)
+            .map_err(wrap_git_error)?;
+        Ok(Signature { inner: signature })
+    }
+}
+
 /// A diff between two trees/commits.
 pub struct Diff<'repo> {
     inner: git2::Diff<'repo>,
 }
 
+/// The number of lines changed by a commit, as returned by
+/// `Repo::get_commit_diff_stat`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiffStat {
+    /// The total number of inserted lines.
+    pub insertions: usize,
+
+    /// The total number of deleted lines.
+    pub deletions: usize,
+
+    /// The number of lines changed in each file, in the order that Git
+    /// reports them.
+    pub files: Vec<FileDiffStat>,
+}
+
+/// The number of lines changed in a single file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileDiffStat {
+    /// The path of the file.
+    pub path: PathBuf,
+
+    /// The number of inserted lines.
+    pub insertions: usize,
+
+    /// The number of deleted lines.
+    pub deletions: usize,
+}
+
 /// A checksum of the diff induced by a given commit, used for duplicate commit
 /// detection.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -1340,6 +1905,36 @@ pub struct PatchId {
     patch_id: git2::Oid,
 }
 
+impl std::fmt::Display for PatchId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.patch_id)
+    }
+}
+
+impl FromStr for PatchId {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let patch_id = git2::Oid::from_str(s).wrap_err("Parsing patch ID")?;
+        Ok(PatchId { patch_id })
+    }
+}
+
+/// A range of commits parsed from a revision specifier such as `A..B`.
+#[derive(Clone, Debug)]
+pub struct CommitRange<'repo> {
+    /// The start of the range. For `A..B`, commits reachable from this commit
+    /// are excluded from the range.
+    pub from: Commit<'repo>,
+
+    /// The end of the range.
+    pub to: Commit<'repo>,
+
+    /// Whether the range was written as `A...B`, in which case it consists of
+    /// the commits reachable from either end but not from both.
+    pub symmetric: bool,
+}
+
 /// Represents a commit object in the Git object database.
 #[derive(Clone, Debug)]
 pub struct Commit<'repo> {
@@ -1403,6 +1998,19 @@ impl<'repo> Commit<'repo> {
         }
     }
 
+    /// Get the value of the first trailer in the commit message with the given
+    /// key, such as `Change-Id`. Keys are compared case-insensitively.
+    pub fn get_trailer(&self, key: &str) -> eyre::Result<Option<OsString>> {
+        let trailers =
+            git2::message_trailers_bytes(self.inner.message_raw_bytes()).map_err(wrap_git_error)?;
+        for (trailer_key, trailer_value) in trailers.iter() {
+            if trailer_key.eq_ignore_ascii_case(key.as_bytes()) {
+                return Ok(Some(OsString::from_raw_vec(trailer_value.into())?));
+            }
+        }
+        Ok(None)
+    }
+
     /// Get the commit message with some whitespace trimmed.
     pub fn get_message_pretty(&self) -> eyre::Result<OsString> {
         let message = OsString::from_raw_vec(self.inner.message_bytes().into())?;
@@ -1440,17 +2048,7 @@ impl<'repo> Commit<'repo> {
     /// summary.
     #[instrument]
     pub fn friendly_describe(&self, glyphs: &Glyphs) -> eyre::Result<StyledString> {
-        let description = render_node_descriptors(
-            glyphs,
-            &NodeObject::Commit {
-                commit: self.clone(),
-            },
-            &mut [
-                &mut CommitOidDescriptor::new(true)?,
-                &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
-            ],
-        )?;
-        Ok(description)
+        CommitView::new(self.clone()).describe(glyphs)
     }
 
     /// Get a multi-line description of this commit containing information about
@@ -1743,6 +2341,15 @@ impl<'repo> Branch<'repo> {
         Ok(self.inner.get().target().map(make_non_zero_oid))
     }
 
+    /// Get the upstream branch which this branch is tracking, if any.
+    pub fn get_upstream_branch(&self) -> eyre::Result<Option<Branch<'repo>>> {
+        match self.inner.upstream() {
+            Ok(upstream) => Ok(Some(Branch { inner: upstream })),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(wrap_git_error(err)),
+        }
+    }
+
     /// Convert the branch into its underlying `Reference`.
     pub fn into_reference(self) -> Reference<'repo> {
         Reference {
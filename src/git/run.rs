@@ -48,15 +48,20 @@ impl std::fmt::Debug for GitRunInfo {
     }
 }
 
+/// Options for running Git silently. See `GitRunInfo::run_silent`.
 pub struct GitRunOpts {
     /// If set, a non-zero exit code will be treated as an error.
-    treat_git_failure_as_error: bool,
+    pub treat_git_failure_as_error: bool,
+
+    /// The data to write to the standard input of the Git process, if any.
+    pub stdin: Option<Vec<u8>>,
 }
 
 impl Default for GitRunOpts {
     fn default() -> Self {
         Self {
             treat_git_failure_as_error: true,
+            stdin: None,
         }
     }
 }
@@ -79,6 +84,24 @@ impl std::fmt::Debug for GitRunResult {
     }
 }
 
+fn make_args_string(args: &[&OsStr]) -> String {
+    args.iter()
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect_vec()
+        .join(" ")
+}
+
+fn get_exit_code(exit_status: ExitStatus) -> eyre::Result<isize> {
+    // On Unix, if the child process was terminated by a signal, we need to call
+    // some Unix-specific functions to access the signal that terminated it. For
+    // simplicity, just return `1` in those cases.
+    let exit_code = exit_status.code().unwrap_or(1);
+    let exit_code = exit_code
+        .try_into()
+        .wrap_err("Converting exit code from i32 to isize")?;
+    Ok(exit_code)
+}
+
 impl GitRunInfo {
     fn spawn_writer_thread<
         InputStream: Read + Send + 'static,
@@ -101,31 +124,25 @@ impl GitRunInfo {
         })
     }
 
-    fn run_inner(
+    /// Inform the user about the Git command which is about to be run, and set
+    /// up the subprocess to run it in the working directory and environment.
+    fn make_command(
         &self,
         effects: &Effects,
         event_tx_id: Option<EventTransactionId>,
         args: &[&OsStr],
-    ) -> eyre::Result<isize> {
+    ) -> eyre::Result<Command> {
         let GitRunInfo {
             path_to_git,
             working_directory,
             env,
         } = self;
 
-        let args_string = args
-            .iter()
-            .map(|arg| arg.to_string_lossy().to_string())
-            .collect_vec()
-            .join(" ");
-        let command_string = format!("git {}", args_string);
-        let (effects, _progress) =
-            effects.start_operation(OperationType::RunGitCommand(Arc::new(command_string)));
         writeln!(
             effects.get_output_stream(),
             "branchless: running command: {} {}",
             &path_to_git.to_string_lossy(),
-            &args_string
+            &make_args_string(args)
         )?;
 
         let mut command = Command::new(path_to_git);
@@ -136,6 +153,20 @@ impl GitRunInfo {
         if let Some(event_tx_id) = event_tx_id {
             command.env(BRANCHLESS_TRANSACTION_ID_ENV_VAR, event_tx_id.to_string());
         }
+        Ok(command)
+    }
+
+    fn run_inner(
+        &self,
+        effects: &Effects,
+        event_tx_id: Option<EventTransactionId>,
+        args: &[&OsStr],
+    ) -> eyre::Result<isize> {
+        let command_string = format!("git {}", make_args_string(args));
+        let (effects, _progress) =
+            effects.start_operation(OperationType::RunGitCommand(Arc::new(command_string)));
+
+        let mut command = self.make_command(&effects, event_tx_id, args)?;
         command.stdout(Stdio::piped());
         command.stderr(Stdio::piped());
 
@@ -151,15 +182,7 @@ impl GitRunInfo {
             .wrap_err("Waiting for Git subprocess to complete")?;
         stdout_thread.join().unwrap();
         stderr_thread.join().unwrap();
-
-        // On Unix, if the child process was terminated by a signal, we need to call
-        // some Unix-specific functions to access the signal that terminated it. For
-        // simplicity, just return `1` in those cases.
-        let exit_code = exit_status.code().unwrap_or(1);
-        let exit_code = exit_code
-            .try_into()
-            .wrap_err("Converting exit code from i32 to isize")?;
-        Ok(exit_code)
+        get_exit_code(exit_status)
     }
 
     /// Run Git in a subprocess, and inform the user.
@@ -186,6 +209,26 @@ impl GitRunInfo {
         )
     }
 
+    /// Run Git in a subprocess with its standard input and output attached to
+    /// the terminal, so that the user can interact with it (such as to select
+    /// hunks with `git add --patch`, or to edit a commit message), and inform
+    /// the user.
+    ///
+    /// Returns the exit code of Git (non-zero signifies error).
+    #[instrument]
+    #[must_use = "The return code for `run_interactive` must be checked"]
+    pub fn run_interactive<S: AsRef<OsStr> + std::fmt::Debug>(
+        &self,
+        effects: &Effects,
+        event_tx_id: Option<EventTransactionId>,
+        args: &[S],
+    ) -> eyre::Result<isize> {
+        let args = args.iter().map(AsRef::as_ref).collect_vec();
+        let mut command = self.make_command(effects, event_tx_id, &args)?;
+        let exit_status = command.status().wrap_err("Spawning Git subprocess")?;
+        get_exit_code(exit_status)
+    }
+
     fn run_silent_inner(
         &self,
         repo: &Repo,
@@ -200,6 +243,7 @@ impl GitRunInfo {
         } = self;
         let GitRunOpts {
             treat_git_failure_as_error,
+            stdin,
         } = opts;
 
         // Prefer running in the working copy path to the repo path, because
@@ -230,7 +274,33 @@ impl GitRunInfo {
         if let Some(event_tx_id) = event_tx_id {
             command.env(BRANCHLESS_TRANSACTION_ID_ENV_VAR, event_tx_id.to_string());
         }
-        let output = command.output().wrap_err("Spawning Git subprocess")?;
+        let output = match stdin {
+            None => command.output().wrap_err("Spawning Git subprocess")?,
+            Some(stdin) => {
+                let mut child = command
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()
+                    .wrap_err("Spawning Git subprocess")?;
+                let mut child_stdin = child
+                    .stdin
+                    .take()
+                    .ok_or_else(|| eyre!("Could not get stdin for Git subprocess"))?;
+                // Write from a separate thread, so that the subprocess can't
+                // block on writing its output while we're blocked on writing
+                // its input.
+                let writer = thread::spawn(move || child_stdin.write_all(&stdin));
+                let output = child
+                    .wait_with_output()
+                    .wrap_err("Waiting for Git subprocess")?;
+                writer
+                    .join()
+                    .map_err(|_| eyre!("Writing Git subprocess stdin panicked"))?
+                    .wrap_err("Writing Git subprocess stdin")?;
+                output
+            }
+        };
         let result = GitRunResult {
             // On Unix, if the child process was terminated by a signal, we need to call
             // some Unix-specific functions to access the signal that terminated it. For
@@ -266,6 +336,28 @@ impl GitRunInfo {
         )
     }
 
+    /// Make a command which runs `command_line` with the same shell that Git
+    /// hooks are run with, in the working directory and with the environment
+    /// of this `GitRunInfo`. The caller can add arguments (available to
+    /// `command_line` as `"$@"`) and configure the working directory and stdio
+    /// before running it.
+    pub fn make_shell_command(&self, command_line: &str) -> eyre::Result<Command> {
+        let GitRunInfo {
+            // We're calling a shell, but not Git itself.
+            path_to_git: _,
+            working_directory,
+            env,
+        } = self;
+        let mut command = Command::new(get_sh().ok_or_else(|| eyre!("could not get sh"))?);
+        command
+            .arg("-c")
+            .arg(command_line)
+            .current_dir(working_directory)
+            .env_clear()
+            .envs(env.iter());
+        Ok(command)
+    }
+
     fn run_hook_inner(
         &self,
         effects: &Effects,
@@ -509,6 +601,7 @@ mod tests {
             &["some-nonexistent-command"],
             GitRunOpts {
                 treat_git_failure_as_error: true,
+                ..Default::default()
             },
         );
         assert_debug_snapshot!(result, @r###"
@@ -523,6 +616,7 @@ mod tests {
             &["some-nonexistent-command"],
             GitRunOpts {
                 treat_git_failure_as_error: false,
+                ..Default::default()
             },
         );
         assert_debug_snapshot!(result, @r###"
@@ -531,6 +625,20 @@ mod tests {
         )
         "###);
 
+        let result = git_run_info.run_silent(
+            &git.get_repo()?,
+            None,
+            &["hash-object", "--stdin"],
+            GitRunOpts {
+                stdin: Some(b"hello\n".to_vec()),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(
+            String::from_utf8_lossy(&result.stdout),
+            "ce013625030ba8dba906f756967f9e9ca394464a\n"
+        );
+
         Ok(())
     }
 }
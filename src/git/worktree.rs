@@ -0,0 +1,157 @@
+//! Temporary worktrees, used to operate on commits without touching the
+//! user's working copy.
+
+use std::path::Path;
+use std::process::Command;
+
+use eyre::Context;
+use tracing::instrument;
+
+use super::{GitRunInfo, NonZeroOid, Repo};
+
+/// A temporary worktree for the repository. The worktree is registered with
+/// the repository for as long as this value is alive. When this value is
+/// dropped, the worktree is unregistered from the repository and its directory
+/// is deleted.
+///
+/// The repository's hooks are disabled for Git commands run in the worktree,
+/// so that operations on the worktree aren't recorded in the event log.
+#[derive(Debug)]
+pub struct TemporaryWorktree<'a> {
+    git_run_info: &'a GitRunInfo,
+    repo: &'a Repo,
+    temp_dir: tempfile::TempDir,
+    hooks_path_arg: String,
+    is_removed: bool,
+}
+
+impl<'a> TemporaryWorktree<'a> {
+    /// Create a new worktree with no commit checked out.
+    #[instrument]
+    pub fn new(git_run_info: &'a GitRunInfo, repo: &'a Repo) -> eyre::Result<Self> {
+        let tempfile_dir = repo.get_tempfile_dir();
+        std::fs::create_dir_all(&tempfile_dir)
+            .wrap_err_with(|| format!("Creating tempfile dir: {:?}", &tempfile_dir))?;
+        let temp_dir = tempfile::Builder::new()
+            .prefix("worktree")
+            .tempdir_in(&tempfile_dir)
+            .wrap_err_with(|| format!("Creating temporary worktree in: {:?}", &tempfile_dir))?;
+
+        // The repository's hooks are shared with its worktrees. Point Git at
+        // a hooks directory which doesn't exist instead.
+        let hooks_path = temp_dir.path().join("hooks");
+        let hooks_path_arg = format!("core.hooksPath={}", path_to_str(&hooks_path)?);
+
+        let worktree = TemporaryWorktree {
+            git_run_info,
+            repo,
+            temp_dir,
+            hooks_path_arg,
+            is_removed: false,
+        };
+        let worktree_path = path_to_str(worktree.get_path())?.to_string();
+        worktree.run_git(&[
+            "worktree",
+            "add",
+            "--detach",
+            "--no-checkout",
+            &worktree_path,
+        ])?;
+        Ok(worktree)
+    }
+
+    /// Get the path to the worktree's working copy.
+    pub fn get_path(&self) -> &Path {
+        self.temp_dir.path()
+    }
+
+    fn run_git(&self, args: &[&str]) -> eyre::Result<Vec<u8>> {
+        let mut git_args = vec!["-c", &self.hooks_path_arg];
+        git_args.extend(args);
+        let result =
+            self.git_run_info
+                .run_silent(self.repo, None, &git_args, Default::default())?;
+        Ok(result.stdout)
+    }
+
+    /// Run Git silently in the worktree. Returns the stdout of the Git
+    /// invocation.
+    #[instrument]
+    pub fn run_git_silent(&self, args: &[&str]) -> eyre::Result<Vec<u8>> {
+        let worktree_path = path_to_str(self.get_path())?;
+        let mut git_args = vec!["-C", worktree_path];
+        git_args.extend(args);
+        self.run_git(&git_args)
+    }
+
+    /// Run Git in the worktree, with its standard input and output attached to
+    /// the terminal, so that the user can interact with it. Returns the exit
+    /// code of the Git invocation.
+    #[instrument]
+    pub fn run_git_interactive(&self, args: &[&str]) -> eyre::Result<i32> {
+        let GitRunInfo {
+            path_to_git,
+            working_directory: _,
+            env,
+        } = self.git_run_info;
+        let status = Command::new(path_to_git)
+            .arg("-c")
+            .arg(&self.hooks_path_arg)
+            .args(args)
+            .current_dir(self.get_path())
+            .env_clear()
+            .envs(env.iter())
+            .status()
+            .wrap_err("Spawning Git subprocess")?;
+        Ok(status.code().unwrap_or(1))
+    }
+
+    /// Check out the given commit in the worktree, discarding any changes
+    /// made in the worktree since the last checkout.
+    #[instrument]
+    pub fn check_out(&self, commit_oid: NonZeroOid) -> eyre::Result<()> {
+        self.run_git_silent(&["checkout", "--force", "--detach", &commit_oid.to_string()])?;
+        self.run_git_silent(&["clean", "-d", "--force", "-x"])?;
+        Ok(())
+    }
+
+    /// Unregister the worktree from the repository and delete it. Unlike
+    /// dropping the worktree, this reports any errors which occur.
+    #[instrument]
+    pub fn remove(mut self) -> eyre::Result<()> {
+        self.remove_inner()
+    }
+
+    fn remove_inner(&mut self) -> eyre::Result<()> {
+        let worktree_path = path_to_str(self.get_path())?.to_string();
+        self.run_git(&["worktree", "remove", "--force", &worktree_path])?;
+        self.is_removed = true;
+        Ok(())
+    }
+}
+
+impl Drop for TemporaryWorktree<'_> {
+    fn drop(&mut self) {
+        if self.is_removed {
+            return;
+        }
+
+        // Errors can't be reported from here, so clean up on a best-effort
+        // basis. If Git couldn't remove the worktree, delete its directory
+        // ourselves and then have Git prune its administrative directory
+        // under `.git/worktrees`.
+        if self.remove_inner().is_err() {
+            let _ = std::fs::remove_dir_all(self.get_path());
+            let _ = self.run_git(&["worktree", "prune"]);
+        }
+    }
+}
+
+fn path_to_str(path: &Path) -> eyre::Result<&str> {
+    path.to_str().ok_or_else(|| {
+        eyre::eyre!(
+            "Path to temporary worktree could not be converted to UTF-8 string: {:?}",
+            path
+        )
+    })
+}
@@ -13,6 +13,25 @@ pub enum WrappedCommand {
     WrappedCommand(Vec<String>),
 }
 
+/// Sub-commands of `git test`.
+#[derive(Parser)]
+pub enum TestSubcommand {
+    /// Run a command on each commit in a given range and report the results.
+    Run {
+        /// The shell command to run on each commit. The command is run in a
+        /// temporary worktree, with the commit checked out. It's considered to
+        /// have passed if it exits with status code 0.
+        #[clap(short = 'x', long = "exec")]
+        command: String,
+
+        /// The commits to run the command on. Can either be a single commit,
+        /// like `HEAD`, or a range, like `main..HEAD`. If not provided,
+        /// defaults to the commits between the main branch and the current
+        /// commit.
+        commits: Vec<String>,
+    },
+}
+
 /// Options for moving commits.
 #[derive(Args, Debug)]
 pub struct MoveOptions {
@@ -32,6 +51,13 @@ pub struct MoveOptions {
     #[clap(long = "no-deduplicate-commits", parse(from_flag = std::ops::Not::not))]
     pub detect_duplicate_commits_via_patch_id: bool,
 
+    /// Don't sign rewritten commits, even if `commit.gpgSign` is set.
+    /// Normally, rewritten commits are signed with the key configured by
+    /// `user.signingKey` (or `gpg.format` for SSH signing), as with `git
+    /// rebase`.
+    #[clap(long = "no-gpg-sign", parse(from_flag = std::ops::Not::not))]
+    pub sign_commits: bool,
+
     /// Attempt to resolve merge conflicts, if any. If a merge conflict
     /// occurs and this option is not set, the operation is aborted.
     #[clap(name = "merge", short = 'm', long = "merge")]
@@ -53,10 +79,18 @@ pub struct MoveOptions {
 pub struct TraverseCommitsOptions {
     /// The number of commits to traverse.
     ///
-    /// If not provided, defaults to 1.
+    /// If not provided, defaults to 1. If a fork or merge is reached before
+    /// traversing this many commits, and none of `--oldest`, `--newest`, or
+    /// `--interactive` is set, then stops there.
     pub num_commits: Option<usize>,
 
     /// Traverse as many commits as possible.
+    ///
+    /// For `git prev`, this goes to the oldest draft commit in the current
+    /// stack, rather than leaving the stack for the main branch. For `git
+    /// next`, this goes to the tip of the current stack. As with a number of
+    /// commits, traversal stops at a fork or merge, unless one of `--oldest`,
+    /// `--newest`, or `--interactive` is set.
     #[clap(short = 'a', long = "all")]
     pub all_the_way: bool,
 
@@ -91,6 +125,13 @@ pub struct TraverseCommitsOptions {
     /// (Use with caution!)
     #[clap(short = 'f', long = "force", conflicts_with("merge"))]
     pub force: bool,
+
+    /// If a branch is checked out and the destination commit is a descendant
+    /// of it, move the branch to the destination commit and keep it checked
+    /// out, rather than detaching `HEAD`. This can also be enabled with the
+    /// `branchless.navigation.moveBranch` config setting.
+    #[clap(long = "move-branch")]
+    pub move_branch: bool,
 }
 
 /// Options for checking out a commit.
@@ -117,6 +158,10 @@ pub struct CheckoutOptions {
 
     /// The commit or branch to check out.
     ///
+    /// This can also be the index of a commit, as shown by the most recent
+    /// `git smartlog --show-index`, if it isn't also the name of a commit or
+    /// branch.
+    ///
     /// If this is not provided, then interactive commit selection starts as
     /// if `--interactive` were passed.
     ///
@@ -130,6 +175,50 @@ pub struct CheckoutOptions {
 pub enum Command {
     /// Amend the current HEAD commit.
     Amend {
+        /// Instead of restacking the descendants of the amended commit, move
+        /// them on top of it without applying their changes again, so that
+        /// each descendant keeps its original contents.
+        #[clap(long = "reparent")]
+        reparent: bool,
+
+        /// Use the given message as the new commit message. If multiple
+        /// messages are given, they are concatenated as separate paragraphs.
+        ///
+        /// The message is updated in addition to amending any staged changes
+        /// (or, if nothing is staged, any uncommitted changes to tracked
+        /// files). If there are no changes to amend, only the message is
+        /// updated.
+        ///
+        /// This option has no short form, since `-m` is taken by `--merge`.
+        #[clap(long = "message")]
+        messages: Vec<String>,
+
+        /// Open an editor to edit the commit message. If `--message` is also
+        /// passed, the editor is pre-filled with the given message rather than
+        /// the current one.
+        #[clap(short = 'e', long = "edit")]
+        edit: bool,
+
+        /// Options for moving commits.
+        #[clap(flatten)]
+        move_options: MoveOptions,
+    },
+
+    /// Fold each `fixup!` and `squash!` commit into the commit it refers to,
+    /// and move their descendants on top of the resulting commits.
+    ///
+    /// Multiple fixup commits for the same commit are folded in the order in
+    /// which they appear in the stack. The message of a `squash!` commit,
+    /// without its subject line, is appended to the message of the commit it
+    /// refers to. Branches pointing to a fixup commit are moved to its parent
+    /// commit.
+    Autosquash {
+        /// Template for the generated commit messages, overriding
+        /// `branchless.commitTemplate`. `%s` is replaced with the subject
+        /// and `%b` with the body of each commit message.
+        #[clap(long = "commit-template", value_name = "TEMPLATE")]
+        commit_template: Option<String>,
+
         /// Options for moving commits.
         #[clap(flatten)]
         move_options: MoveOptions,
@@ -146,20 +235,107 @@ pub enum Command {
         checkout_options: CheckoutOptions,
     },
 
+    /// Show the changes of a commit relative to its logical base, i.e. its
+    /// merge-base with the main branch, rather than relative to its parent.
+    ///
+    /// This shows the cumulative changes of the commit and its ancestors in
+    /// its stack.
+    Diff {
+        /// The commit to diff. If not provided, defaults to the current commit.
+        commit: Option<String>,
+
+        /// Diff the cumulative changes of the entire stack containing the
+        /// commit, up to the head of the stack, rather than only up to the
+        /// commit.
+        #[clap(long = "range")]
+        range: bool,
+
+        /// Additional arguments to pass to `git diff`, such as `--stat`.
+        #[clap(last = true)]
+        diff_args: Vec<String>,
+    },
+
+    /// Fold a commit into its parent commit, combining their changes and
+    /// commit messages, and move their descendants on top of the resulting
+    /// commit.
+    ///
+    /// Branches pointing to either commit are moved to the resulting commit.
+    Fold {
+        /// The commit to fold into its parent. If not provided, defaults to
+        /// the current commit.
+        commit: Option<String>,
+
+        /// Edit the combined commit message before committing.
+        #[clap(short = 'e', long = "edit")]
+        edit: bool,
+
+        /// Template for the generated commit messages, overriding
+        /// `branchless.commitTemplate`. `%s` is replaced with the subject
+        /// and `%b` with the body of each commit message.
+        #[clap(long = "commit-template", value_name = "TEMPLATE")]
+        commit_template: Option<String>,
+
+        /// Options for moving commits.
+        #[clap(flatten)]
+        move_options: MoveOptions,
+    },
+
     /// Run internal garbage collection.
-    Gc,
+    ///
+    /// Hidden commits are released to be collected by Git's garbage collection
+    /// once they've been hidden for longer than the number of days set by
+    /// `branchless.gc.retentionDays` (default 0). Commits pointed to by
+    /// branches or `HEAD` are never released.
+    Gc {
+        /// Also compact the event log database to reclaim disk space.
+        #[clap(long = "compact")]
+        compact: bool,
+    },
 
     /// Hide the provided commits from the smartlog.
     Hide {
         /// Zero or more commits to hide.
         ///
         /// Can either be hashes, like `abc123`, or ref-specs, like `HEAD^`.
+        /// Ranges like `A..B` or `A...B` hide all of the commits in the range,
+        /// skipping any which are on the main branch, unless `--force` is
+        /// passed.
         commits: Vec<String>,
 
         /// Also recursively hide all visible children commits of the provided
-        /// commits.
+        /// commits. Descendant commits which are on the main branch are
+        /// skipped, rather than hidden, unless `--force` is passed.
         #[clap(short = 'r', long = "recursive")]
         recursive: bool,
+
+        /// Also hide all visible draft commits matching the given query.
+        ///
+        /// Supported predicates are `age` (compared with `<`, `<=`, `>`, or
+        /// `>=` to a duration like `30d`, using the units `s`, `m`, `h`, `d`,
+        /// or `w`), `author ~ TEXT`, `message ~ TEXT`, and `branch` or `branch
+        /// ~ TEXT`. Text is matched case-insensitively as a substring.
+        /// Predicates can be combined with `and`, `or`, `not`, and
+        /// parentheses. For example: `age > 30d and not branch`.
+        #[clap(long = "query")]
+        query: Option<String>,
+
+        /// A message describing why the commits are being hidden, to be shown
+        /// in the smartlog when hidden commits are displayed.
+        #[clap(short = 'm', long = "message")]
+        message: Option<String>,
+
+        /// Also hide public commits (commits on the main branch) which are
+        /// part of a range or found by `--recursive`, which are otherwise
+        /// skipped. All hidden public commits, including those named
+        /// explicitly, are marked as force-hidden, along with the message from
+        /// `--message`, if any.
+        ///
+        /// Use with care: the commits are only hidden locally, and remain on the
+        /// main branch, so the smartlog will show a hidden commit in the middle
+        /// of the main branch, and commands which skip hidden commits will skip
+        /// them. Use `git undo` to unhide them.
+        #[clap(short = 'f', long = "force")]
+        force: bool,
     },
 
     /// Internal use.
@@ -198,6 +374,9 @@ pub enum Command {
         rewrite_type: String,
     },
 
+    /// Internal use.
+    HookPreCommit,
+
     /// Internal use.
     HookReferenceTransaction {
         /// One of `prepared`, `committed`, or `aborted`. See githooks(5).
@@ -216,6 +395,10 @@ pub enum Command {
     /// Initialize the branchless workflow for this repository.
     Init {
         /// Uninstall the branchless workflow instead of initializing it.
+        ///
+        /// This removes the branchless configuration (including aliases) and
+        /// strips the branchless sections from the installed hooks, deleting
+        /// any hooks which are left empty. Other hook contents are kept.
         #[clap(long = "uninstall")]
         uninstall: bool,
 
@@ -225,6 +408,24 @@ pub enum Command {
         /// then you will be prompted to enter a value for the main branch name.
         #[clap(long = "main-branch", conflicts_with = "uninstall")]
         main_branch_name: Option<String>,
+
+        /// With `--uninstall`, print the hooks, aliases, and configuration
+        /// which would be removed, without removing them.
+        #[clap(long = "dry-run", requires = "uninstall")]
+        dry_run: bool,
+
+        /// Install the hooks and aliases for all repositories, by installing
+        /// the hooks into the global `core.hooksPath` and the aliases into the
+        /// global Git configuration. The main branch must still be configured
+        /// per repository.
+        #[clap(long = "global", conflicts_with_all = &["uninstall", "main-branch-name"])]
+        global: bool,
+
+        /// Install aliases even if aliases with the same names already exist,
+        /// overwriting them. By default, aliases which don't invoke
+        /// `git-branchless` are left in place, and a warning is printed.
+        #[clap(long = "force", conflicts_with = "uninstall")]
+        force: bool,
     },
 
     /// Move a subtree of commits from one location to another.
@@ -253,6 +454,12 @@ pub enum Command {
         #[clap(short = 'd', long = "dest")]
         dest: Option<String>,
 
+        /// Only move the source commit, and not its descendants. The
+        /// descendants are instead moved onto the source commit's parent. If
+        /// `--source` isn't provided, defaults to the current commit.
+        #[clap(short = 'x', long = "exact", conflicts_with = "base")]
+        exact: bool,
+
         /// Options for moving commits.
         #[clap(flatten)]
         move_options: MoveOptions,
@@ -263,6 +470,16 @@ pub enum Command {
         /// Options for traversing commits.
         #[clap(flatten)]
         traverse_commits_options: TraverseCommitsOptions,
+
+        /// Go forward towards the commit that the given branch points to,
+        /// rather than a certain number of commits, and check out the branch.
+        /// The branch must be a descendant of the current commit.
+        #[clap(
+            long = "to-branch",
+            value_name = "BRANCH",
+            conflicts_with_all = &["num-commits", "all-the-way", "move-by-branches"]
+        )]
+        target_branch: Option<String>,
     },
 
     /// Move to an earlier commit in the current stack.
@@ -274,16 +491,103 @@ pub enum Command {
 
     /// Fix up commits abandoned by a previous rewrite operation.
     Restack {
-        /// The IDs of the abandoned commits whose descendants should be
-        /// restacked. If not provided, all abandoned commits are restacked.
+        /// The commits whose subtrees should be restacked. Only abandoned
+        /// commits in these subtrees, or which were rewritten into commits in
+        /// these subtrees, are restacked. If not provided, all abandoned
+        /// commits are restacked.
+        ///
+        /// Can either be hashes, like `abc123`, or branch names, like `foo`.
         commits: Vec<String>,
 
         /// Options for moving commits.
         #[clap(flatten)]
         move_options: MoveOptions,
+
+        /// Print which commits would be restacked and how the smartlog would
+        /// look afterwards, without making any changes to the repository.
+        #[clap(long = "dry-run")]
+        dry_run: bool,
+    },
+
+    /// Create a commit from the changes in the working copy, interactively
+    /// selecting which changes to include.
+    ///
+    /// Changes which are already staged are included in the commit as well.
+    Record {
+        /// Use the given message as the commit message, rather than opening an
+        /// editor. If multiple messages are given, they are concatenated as
+        /// separate paragraphs.
+        #[clap(short = 'm', long = "message")]
+        messages: Vec<String>,
+
+        /// Commit all changes to tracked files, without selecting them
+        /// interactively.
+        #[clap(short = 'a', long = "all")]
+        all: bool,
+    },
+
+    /// Reapply the operation most recently reverted by `git undo`.
+    Redo,
+
+    /// Reconstruct missing branchless metadata from the state of the
+    /// repository.
+    ///
+    /// Use this if the smartlog shows stale commits or is missing commits,
+    /// such as after a crash or after `.git/branchless` was deleted. Commits
+    /// referenced by `HEAD`, branches, or the `HEAD` reflog which branchless
+    /// doesn't know about are recovered, and amended commits found in the
+    /// reflog are marked as rewritten. Visible commits which no longer exist
+    /// are hidden.
+    Repair {
+        /// Print the discrepancies which would be repaired, without making any
+        /// changes.
+        #[clap(long = "dry-run")]
+        dry_run: bool,
+    },
+
+    /// Restore the working copy and index from a snapshot created by `git
+    /// branchless snapshot`.
+    ///
+    /// The commit that was checked out when the snapshot was taken is checked
+    /// out again. Files which were untracked when the snapshot was taken, and
+    /// included with `--include-untracked`, are restored as untracked files.
+    /// Other untracked files in the working copy are left alone. If restoring
+    /// the snapshot would overwrite an untracked file, nothing is restored.
+    Restore {
+        /// The snapshot to restore, as printed by `git branchless snapshot`
+        /// or shown in `git undo -i`.
+        snapshot: String,
+    },
+
+    /// Reword commits, and restack their descendants on top of the reworded
+    /// commits.
+    Reword {
+        /// The commits to reword. If not provided, defaults to the current
+        /// commit.
+        ///
+        /// Can either be hashes, like `abc123`, or ref-specs, like `HEAD^`.
+        commits: Vec<String>,
+
+        /// Use the given message as the new commit message, rather than
+        /// opening an editor. If multiple messages are given, they are
+        /// concatenated as separate paragraphs.
+        #[clap(short = 'm', long = "message")]
+        messages: Vec<String>,
+
+        /// Don't sign the reworded commits, even if `commit.gpgSign` is set.
+        #[clap(long = "no-gpg-sign", parse(from_flag = std::ops::Not::not))]
+        sign_commits: bool,
+
+        /// Also reword public commits (commits on the main branch), which are
+        /// otherwise refused, since rewriting them rewrites the main branch.
+        #[clap(short = 'f', long = "force")]
+        force: bool,
     },
 
     /// Display a nice graph of the commits you've recently worked on.
+    ///
+    /// When run from a linked worktree, the same commits are shown as in the
+    /// main worktree, but the current commit is that of the linked worktree.
     Smartlog {
         /// Also show commits which have been hidden.
         #[clap(long = "hidden")]
@@ -292,9 +596,215 @@ pub enum Command {
         /// Only show commits that exist on a branch.
         #[clap(long = "only-branches", conflicts_with = "show-hidden-commits")]
         only_show_branches: bool,
+
+        /// Only show draft commits which haven't been pushed, i.e. which
+        /// aren't reachable from any remote-tracking branch.
+        #[clap(long = "unpushed")]
+        only_show_unpushed: bool,
+
+        /// Don't show the names of branches next to commits, other than the
+        /// currently checked-out branch.
+        #[clap(long = "hide-branches")]
+        hide_branches: bool,
+
+        /// Print the smartlog in the opposite of the usual order, with the
+        /// latest commits first.
+        #[clap(long = "reverse")]
+        reverse: bool,
+
+        /// Order sibling commits by their commit dates, newest first.
+        ///
+        /// If neither this nor `--topo-order` is passed, the order is taken
+        /// from `branchless.smartlog.order` (either `date` or `topo`). If that
+        /// isn't set either, sibling commits are ordered by their commit
+        /// dates, oldest first, so that the most recently created stack is
+        /// drawn on the main line of the graph.
+        #[clap(long = "date-order", conflicts_with = "topo-order")]
+        date_order: bool,
+
+        /// Order sibling commits topologically, as `git log --topo-order`
+        /// does: the sibling whose descendants include the most recent commit
+        /// comes first, and each sibling's descendants are kept together.
+        #[clap(long = "topo-order")]
+        topo_order: bool,
+
+        /// The format in which to print the smartlog.
+        #[clap(long = "format", arg_enum, default_value = "text")]
+        format: SmartlogFormat,
+
+        /// The glyphs to use when rendering the graph. Defaults to the value
+        /// of `branchless.smartlog.glyphs`, if set, or `unicode` otherwise.
+        #[clap(long = "glyphs", arg_enum)]
+        glyphs: Option<SmartlogGlyphs>,
+
+        /// Only show the given number of commits closest to the current
+        /// commit. Pass `0` to show all commits. Defaults to the value of
+        /// `branchless.smartlog.maxCommits`, if set.
+        #[clap(long = "max-commits")]
+        max_commits: Option<usize>,
+
+        /// Walk all stacks of commits when building the smartlog, even if
+        /// there are more than `branchless.smartlog.maxRefs`.
+        #[clap(long = "full")]
+        full: bool,
+
+        /// Also show the given number of most recent commits on the main
+        /// branch, rather than collapsing them. These commits don't count
+        /// towards `--max-commits`.
+        #[clap(long = "public", value_name = "N", default_value = "0")]
+        num_public_commits: usize,
+
+        /// Don't show main branch commits from before the given boundary,
+        /// collapsing them into the ellipsis instead. The boundary may be a
+        /// commit, a duration like `30d` (meaning that long ago), or a date
+        /// like `2022-01-31`. Draft commits are still shown.
+        #[clap(long = "since", value_name = "COMMIT-OR-DATE")]
+        since: Option<String>,
+
+        /// Show the age of each commit in a separate column. This can also be
+        /// enabled with the `branchless.smartlog.showAge` config setting.
+        #[clap(long = "show-age")]
+        show_age: bool,
+
+        /// Show the number of lines inserted and deleted by each commit in
+        /// aligned columns, such as `+12 -3`. Pass `--stat=full` to also show
+        /// the counts for each file. Merge commits are compared against their
+        /// first parent.
+        #[clap(
+            long = "stat",
+            arg_enum,
+            min_values = 0,
+            require_equals = true,
+            default_missing_value = "short"
+        )]
+        stat: Option<SmartlogStat>,
+
+        /// Show the author of each commit. This can also be enabled with the
+        /// `branchless.commitDescriptors.author` config setting.
+        #[clap(long = "show-author")]
+        show_author: bool,
+
+        /// Show an index for each commit. The index can be passed to `git
+        /// branchless checkout` to check out the corresponding commit.
+        #[clap(long = "show-index")]
+        show_index: bool,
+
+        /// Show whether each commit is signed, and whether its signature
+        /// could be verified, as `(signed)`, `(unsigned)`, or `(bad
+        /// signature)`. Signatures are verified by Git, so this uses Git's
+        /// signing configuration, such as `gpg.ssh.allowedSignersFile`.
+        #[clap(long = "show-signatures")]
+        show_signatures: bool,
+
+        /// Print a key explaining the glyphs used in the graph below it. If
+        /// not passed, the value of `branchless.smartlog.showLegend` is used.
+        #[clap(long = "legend")]
+        show_legend: bool,
+
+        /// Highlight the given commit or branch in the graph. May be passed
+        /// multiple times. This doesn't affect which commits are shown.
+        #[clap(long = "highlight", value_name = "COMMIT")]
+        highlight: Vec<String>,
+
+        /// Render each stack of draft commits (the draft descendants of a
+        /// single commit on the main branch) in a different color, to make it
+        /// easier to tell independent stacks apart. Has no effect when color
+        /// is disabled.
+        #[clap(long = "color-by-stack")]
+        color_by_stack: bool,
+
+        /// Only show draft commits whose author name or email matches the
+        /// provided pattern, which may be a substring or a regular expression.
+        #[clap(long = "author")]
+        author: Option<String>,
+
+        /// Don't display the smartlog in a pager, even if it doesn't fit on
+        /// the screen.
+        #[clap(long = "no-pager")]
+        no_pager: bool,
+
+        /// Wrap commit descriptions which don't fit on one line onto multiple
+        /// lines, rather than truncating them. Lines are limited to the width
+        /// of the terminal, and descriptions to the value of
+        /// `branchless.smartlog.maxSubjectWidth`, if set.
+        #[clap(long = "wrap")]
+        wrap: bool,
+
+        /// Don't wrap or truncate commit descriptions, even if they don't fit
+        /// on one line.
+        #[clap(long = "no-wrap", conflicts_with = "wrap")]
+        no_wrap: bool,
+
+        /// Recompute the smartlog, rather than reusing the result of the
+        /// last invocation if nothing has changed since then.
+        #[clap(long = "no-cache")]
+        no_cache: bool,
+
+        /// Print the smartlog in a stable layout suitable for scripts. This
+        /// implies `--color=never --glyphs=ascii --no-pager --no-wrap`.
+        #[clap(long = "plain", conflicts_with_all = &["glyphs", "wrap"])]
+        plain: bool,
+
+        /// The commits to focus on. If provided, only these commits and their
+        /// descendants are shown, rather than all draft commits.
+        ///
+        /// Can either be hashes, like `abc123`, or ref-specs, like `HEAD^`.
+        commits: Vec<String>,
+    },
+
+    /// Save the current state of the working copy and index without
+    /// modifying them.
+    ///
+    /// The snapshot is recorded in the event log, so it's listed in `git undo
+    /// -i`, and can be brought back later with `git branchless restore`.
+    ///
+    /// Untracked files are not included unless `--include-untracked` is
+    /// passed. Ignored files are never included.
+    Snapshot {
+        /// Also include untracked files in the snapshot. Files matched by
+        /// `.gitignore` are still excluded.
+        #[clap(short = 'u', long = "include-untracked")]
+        include_untracked: bool,
+    },
+
+    /// Split a commit into two commits, and move its descendants on top of
+    /// the second commit.
+    ///
+    /// The changes to keep in the first commit are selected interactively.
+    /// The remaining changes are put into the second commit.
+    Split {
+        /// The commit to split. If not provided, defaults to the current
+        /// commit.
+        commit: Option<String>,
+
+        /// Template for the generated commit messages, overriding
+        /// `branchless.commitTemplate`. `%s` is replaced with the subject
+        /// and `%b` with the body of each commit message.
+        #[clap(long = "commit-template", value_name = "TEMPLATE")]
+        commit_template: Option<String>,
+
+        /// Options for moving commits.
+        #[clap(flatten)]
+        move_options: MoveOptions,
+    },
+
+    /// Push the branches in the current stack to a remote, and set them up
+    /// to track the pushed branches.
+    ///
+    /// Only branches which don't already have an upstream branch are pushed.
+    /// The remote is the value of `branchless.submit.remote`, or `origin` if
+    /// not set.
+    Submit {
+        /// Print which branches would be pushed, without pushing them.
+        #[clap(long = "dry-run")]
+        dry_run: bool,
     },
 
     /// Move any local commit stacks on top of the main branch.
+    ///
+    /// Draft commits whose changes have already landed on the main branch, as
+    /// determined by comparing patch IDs, are hidden first. They're shown as
+    /// `(landed)` in `git smartlog --hidden`.
     Sync {
         /// Run `git fetch` to update remote references before carrying out the
         /// sync.
@@ -311,6 +821,19 @@ pub enum Command {
         #[clap(short = 'f', long = "force")]
         force: bool,
 
+        /// Delete local branches which have been fully merged into the main
+        /// branch, i.e. which point to a commit on the main branch. The main
+        /// branch and the checked-out branch are never deleted. Deletions can
+        /// be reverted with `git undo`.
+        #[clap(long = "delete-merged")]
+        delete_merged: bool,
+
+        /// Move the stacks on top of the given commit, such as the tip of a
+        /// release branch, rather than the main branch. Commits which have
+        /// already landed in the given commit's history are hidden.
+        #[clap(long = "onto", value_name = "COMMIT")]
+        onto: Option<String>,
+
         /// Options for moving commits.
         #[clap(flatten)]
         move_options: MoveOptions,
@@ -322,12 +845,32 @@ pub enum Command {
         commits: Vec<String>,
     },
 
+    /// Run a command on commits and cache the results.
+    Test {
+        /// The action to take.
+        #[clap(subcommand)]
+        subcommand: TestSubcommand,
+    },
+
     /// Browse or return to a previous state of the repository.
     Undo {
         /// Interactively browse through previous states of the repository
         /// before selecting one to return to.
         #[clap(short = 'i', long = "interactive")]
         interactive: bool,
+
+        /// Show the changes which undoing would make to branches and commit
+        /// visibility, along with the smartlog before and after, without
+        /// modifying the repository.
+        #[clap(long = "preview", visible_alias = "dry-run")]
+        preview: bool,
+
+        /// Only revert changes to the given branch, leaving the operation's
+        /// other changes in place, such as moves of other branches and changes
+        /// to commit visibility. This intentionally leaves the operation
+        /// partially reverted. The branch must not be checked out.
+        #[clap(value_name = "BRANCH", long = "only", conflicts_with = "preview")]
+        only: Option<String>,
     },
 
     /// Unhide previously-hidden commits from the smartlog.
@@ -340,6 +883,17 @@ pub enum Command {
         /// Also recursively unhide all children commits of the provided commits.
         #[clap(short = 'r', long = "recursive")]
         recursive: bool,
+
+        /// Unhide all commits which are currently hidden because they were
+        /// hidden with `git hide`. Commits which are hidden because they were
+        /// rewritten are not unhidden.
+        #[clap(long = "all", conflicts_with = "commits")]
+        all: bool,
+
+        /// With `--all`, only unhide commits which were hidden within the
+        /// given duration, like `2h` or `3d`.
+        #[clap(long = "since", requires = "all")]
+        since: Option<String>,
     },
 
     /// Wrap a Git command inside a branchless transaction.
@@ -366,11 +920,84 @@ pub enum ColorSetting {
     Never,
 }
 
+/// The output format for `git smartlog`.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmartlogFormat {
+    /// Render the commit graph as text. This is the default behavior.
+    Text,
+    /// Emit the commits in the graph as a JSON array, for consumption by other
+    /// tools.
+    Json,
+    /// Emit the commit graph as a Graphviz DOT description, which can be
+    /// rendered with e.g. `dot -Tpng`.
+    Dot,
+}
+
+impl Default for SmartlogFormat {
+    fn default() -> Self {
+        SmartlogFormat::Text
+    }
+}
+
+/// How much detail to show for the lines changed by each commit in `git
+/// smartlog --stat`.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmartlogStat {
+    /// Show the total number of inserted and deleted lines. This is the
+    /// default when `--stat` is passed without a value.
+    Short,
+    /// Also show the number of inserted and deleted lines for each file.
+    Full,
+}
+
+/// The order in which sibling commits are rendered in `git smartlog`.
+///
+/// If no order is given, siblings are ordered by their commit dates, oldest
+/// first, so that the most recently created stack is drawn on the main line of
+/// the graph.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmartlogOrder {
+    /// Order siblings by their commit dates, newest first.
+    Date,
+    /// Order siblings topologically, as `git log --topo-order` does: the
+    /// sibling whose descendants include the most recent commit comes first,
+    /// and each sibling's descendants are kept together.
+    Topo,
+}
+
+/// The glyphs used to render the graph for `git smartlog`.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmartlogGlyphs {
+    /// Use Unicode box-drawing characters when writing to a terminal. This is
+    /// the default behavior.
+    Unicode,
+    /// Only use plain ASCII characters.
+    Ascii,
+}
+
+/// The exit codes of `git-branchless` commands, as shown in the help text. See
+/// `commands::BranchlessExitCode`.
+const EXIT_STATUS_HELP: &str = "EXIT STATUS:
+    0    The command succeeded.
+    1    The command couldn't be carried out as requested, such as because of an
+         invalid argument or an unknown revision, or because it was aborted.
+    2    The command stopped because of a merge conflict, which needs to be
+         resolved before trying again.
+    3    There was nothing for the command to do.
+    4    The command run by `git test` failed for at least one commit.
+
+Commands which invoke Git may also exit with the exit code of the failed Git
+invocation.";
+
 /// Branchless workflow for Git.
 ///
 /// See the documentation at <https://github.com/arxanas/git-branchless/wiki>.
 #[derive(Parser)]
-#[clap(version = env!("CARGO_PKG_VERSION"), author = "Waleed Khan <me@waleedkhan.name>")]
+#[clap(
+    version = env!("CARGO_PKG_VERSION"),
+    author = "Waleed Khan <me@waleedkhan.name>",
+    after_help = EXIT_STATUS_HELP
+)]
 pub struct Opts {
     /// Change to the given directory before executing the rest of the program.
     /// (The option is called `-C` for symmetry with Git.)
@@ -381,6 +1008,17 @@ pub struct Opts {
     #[clap(long = "color", arg_enum)]
     pub color: Option<ColorSetting>,
 
+    /// Only display errors and the output that was requested. Also don't
+    /// display progress indicators for long-running operations, such as
+    /// rebasing commits.
+    #[clap(short = 'q', long = "quiet", global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Display additional details, such as each commit and branch that was
+    /// updated.
+    #[clap(short = 'v', long = "verbose", global = true)]
+    pub verbose: bool,
+
     /// The `git-branchless` subcommand to run.
     #[clap(subcommand)]
     pub command: Command,
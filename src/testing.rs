@@ -77,6 +77,10 @@ pub struct GitRunOptions {
 
     /// Additional environment variables to start the process with.
     pub env: HashMap<String, String>,
+
+    /// The directory to run the command in, relative to the repository root.
+    /// If not set, the command is run in the repository root.
+    pub cwd: Option<PathBuf>,
 }
 
 impl Git {
@@ -198,6 +202,7 @@ impl Git {
             expected_exit_code,
             input,
             env,
+            cwd,
         } = options;
 
         let env: BTreeMap<_, _> = self
@@ -208,9 +213,13 @@ impl Git {
                     .map(|(k, v)| (OsString::from(k), OsString::from(v))),
             )
             .collect();
+        let current_dir = match cwd {
+            Some(cwd) => self.repo_path.join(cwd),
+            None => self.repo_path.clone(),
+        };
         let mut command = Command::new(&self.path_to_git);
         command
-            .current_dir(&self.repo_path)
+            .current_dir(&current_dir)
             .args(args)
             .env_clear()
             .envs(&env);
@@ -418,6 +427,47 @@ stderr:
         self.commit_file_with_contents(name, time, &format!("{} contents\n", name))
     }
 
+    /// Commit each of the provided files in sequence, as if by calling
+    /// `commit_file` for each `(name, time)` pair. Returns the OIDs of the
+    /// created commits, in order.
+    #[instrument]
+    pub fn commit_files(&self, files: &[(&str, isize)]) -> eyre::Result<Vec<NonZeroOid>> {
+        files
+            .iter()
+            .map(|(name, time)| self.commit_file(name, *time))
+            .collect()
+    }
+
+    /// Merge the branch `name` into the checked-out commit, always creating a
+    /// merge commit. The `time` argument is used to set the commit timestamp,
+    /// which is factored into the commit hash.
+    #[instrument]
+    pub fn merge_branch(&self, name: &str, time: isize) -> eyre::Result<NonZeroOid> {
+        self.run_with_options(
+            &["merge", "--no-ff", name],
+            &GitRunOptions {
+                time,
+                ..Default::default()
+            },
+        )?;
+
+        let repo = self.get_repo()?;
+        let oid = repo
+            .get_head_info()?
+            .oid
+            .expect("Could not find OID for just-created merge commit");
+        Ok(oid)
+    }
+
+    /// Fast-forward the checked-out branch (or `HEAD`, if detached) to the
+    /// branch `name`. Fails if the checked-out commit isn't an ancestor of the
+    /// branch.
+    #[instrument]
+    pub fn fast_forward_branch(&self, name: &str) -> eyre::Result<()> {
+        self.run(&["merge", "--ff-only", name])?;
+        Ok(())
+    }
+
     /// Detach HEAD. This is useful to call to make sure that no branch is
     /// checked out, and therefore that future commit operations don't move any
     /// branches.
@@ -481,6 +531,50 @@ stderr:
         Ok(version >= GitVersion(2, 27, 0))
     }
 
+    /// Determine if commits can be signed with SSH keys. SSH signing was
+    /// introduced in Git v2.34, and generating a key requires `ssh-keygen`.
+    pub fn supports_ssh_signing(&self) -> eyre::Result<bool> {
+        let version = self.get_version()?;
+        if version < GitVersion(2, 34, 0) {
+            return Ok(false);
+        }
+        match Command::new("ssh-keygen").arg("-?").output() {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err).wrap_err("Running ssh-keygen"),
+        }
+    }
+
+    /// Generate an SSH key and configure the repository to sign all commits
+    /// with it. Check `supports_ssh_signing` first.
+    #[instrument]
+    pub fn set_up_ssh_signing(&self) -> eyre::Result<()> {
+        let key_path = self.repo_path.join(".git").join("signing-key");
+        let status = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+            .arg(&key_path)
+            .status()
+            .wrap_err("Running ssh-keygen")?;
+        if !status.success() {
+            eyre::bail!("Generating SSH key failed: {:?}", status);
+        }
+        let key_path = match key_path.to_str() {
+            None => eyre::bail!("Could not convert key path to string: {:?}", key_path),
+            Some(key_path) => key_path,
+        };
+        self.run(&["config", "gpg.format", "ssh"])?;
+        self.run(&["config", "user.signingKey", key_path])?;
+        self.run(&["config", "commit.gpgSign", "true"])?;
+        Ok(())
+    }
+
+    /// Determine whether the given commit has a signature.
+    #[instrument]
+    pub fn is_commit_signed(&self, commit: &str) -> eyre::Result<bool> {
+        let (stdout, _stderr) = self.run(&["cat-file", "commit", commit])?;
+        Ok(stdout.lines().any(|line| line.starts_with("gpgsig ")))
+    }
+
     /// Resolve a file during a merge or rebase conflict with the provided
     /// contents.
     #[instrument]
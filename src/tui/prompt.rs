@@ -1,5 +1,5 @@
-use crate::core::node_descriptors::NodeDescriptor;
-use crate::git::{Commit, NonZeroOid};
+use crate::core::node_descriptors::{CommitView, NodeDescriptor};
+use crate::git::NonZeroOid;
 
 /// Prompt the user to select a commit from the provided list
 /// of commits, and returns the OID of the selected commit.
@@ -7,7 +7,7 @@ use crate::git::{Commit, NonZeroOid};
 pub fn prompt_select_commit(
     header: Option<&str>,
     initial_query: &str,
-    commits: Vec<Commit>,
+    commits: Vec<CommitView>,
     commit_descriptors: &mut [&mut dyn NodeDescriptor],
 ) -> eyre::Result<Option<NonZeroOid>> {
     skim::prompt_skim(header, initial_query, commits, commit_descriptors)
@@ -17,7 +17,7 @@ pub fn prompt_select_commit(
 pub fn prompt_select_commit(
     header: Option<&str>,
     initial_query: &str,
-    commits: Vec<Commit>,
+    commits: Vec<CommitView>,
     commit_descriptors: &mut [&mut dyn NodeDescriptor],
 ) -> eyre::Result<Option<NonZeroOid>> {
     unimplemented!("Non-unix targets are currently unsupported for prompting")
@@ -33,8 +33,10 @@ mod skim {
     use itertools::Itertools;
 
     use crate::core::formatting::{printable_styled_string, Glyphs};
-    use crate::core::node_descriptors::{render_node_descriptors, NodeDescriptor, NodeObject};
-    use crate::git::{Commit, NonZeroOid};
+    use crate::core::node_descriptors::{
+        render_node_descriptors, CommitView, NodeDescriptor, NodeObject,
+    };
+    use crate::git::NonZeroOid;
 
     use skim::{
         prelude::SkimOptionsBuilder, AnsiString, DisplayContext, ItemPreview, Matches,
@@ -95,25 +97,22 @@ mod skim {
 
     impl CommitSkimItem {
         fn from_descriptors(
-            commit: &Commit,
+            commit: CommitView,
             commit_descriptors: &mut [&mut dyn NodeDescriptor],
         ) -> eyre::Result<Self> {
             let glyphs = Glyphs::pretty();
+            let oid = commit.get_oid();
+            let styled_preview = printable_styled_string(&glyphs, commit.friendly_preview()?)?;
             let styled_summary = render_node_descriptors(
                 &glyphs,
-                &NodeObject::Commit {
-                    commit: commit.clone(),
-                },
+                &NodeObject::Commit { commit },
                 commit_descriptors,
             )?;
 
             Ok(CommitSkimItem {
-                oid: commit.get_oid(),
+                oid,
                 styled_summary: printable_styled_string(&glyphs, styled_summary)?,
-                styled_preview: printable_styled_string(
-                    &Glyphs::pretty(),
-                    commit.friendly_preview()?,
-                )?,
+                styled_preview,
             })
         }
     }
@@ -122,7 +121,7 @@ mod skim {
     pub fn prompt_skim(
         header: Option<&str>,
         initial_query: &str,
-        commits: Vec<Commit>,
+        commits: Vec<CommitView>,
         commit_descriptors: &mut [&mut dyn NodeDescriptor],
     ) -> eyre::Result<Option<NonZeroOid>> {
         let options = SkimOptionsBuilder::default()
@@ -137,7 +136,7 @@ mod skim {
             .map_err(|e| eyre!("building Skim options failed: {}", e))?;
 
         let items: Vec<CommitSkimItem> = commits
-            .iter()
+            .into_iter()
             .map(|commit| CommitSkimItem::from_descriptors(commit, commit_descriptors))
             .try_collect()?;
 
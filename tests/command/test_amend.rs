@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use branchless::testing::{make_git, GitRunOptions};
 
 #[test]
@@ -25,13 +27,15 @@ fn test_amend_with_children() -> eyre::Result<()> {
         [1/1] Committed as: b51f01b6 create test3.txt
         branchless: processing 1 rewritten commit
         In-memory rebase succeeded.
+        Restacked 1 commit:
+          - 70deb1e2 create test3.txt
         Finished restacking commits.
         No abandoned branches to restack.
         O f777ecc9 (master) create initial.txt
         |
         o 62fc20d2 create test1.txt
         |
-        @ 7ac317b9 create test2.txt
+        @ 7ac317b9 (> detached HEAD) create test2.txt
         |
         o b51f01b6 create test3.txt
         Amended with 1 uncommitted change.
@@ -44,7 +48,7 @@ fn test_amend_with_children() -> eyre::Result<()> {
         let (stdout, _stderr) = git.run_with_options(
             &["branchless", "amend"],
             &GitRunOptions {
-                expected_exit_code: 1,
+                expected_exit_code: 2,
                 ..Default::default()
             },
         )?;
@@ -80,7 +84,7 @@ fn test_amend_rename() -> eyre::Result<()> {
         |
         o 62fc20d2 create test1.txt
         |
-        @ f6b25538 create test2.txt
+        @ f6b25538 (> detached HEAD) create test2.txt
         Amended with 2 staged changes.
         "###);
     }
@@ -116,7 +120,7 @@ fn test_amend_delete() -> eyre::Result<()> {
         |
         o 62fc20d2 create test1.txt
         |
-        @ f0f07277 create test2.txt
+        @ f0f07277 (> detached HEAD) create test2.txt
         Amended with 1 uncommitted change.
         "###);
     }
@@ -159,7 +163,7 @@ fn test_amend_with_working_copy() -> eyre::Result<()> {
         |
         o 62fc20d2 create test1.txt
         |
-        @ f8e4ba1b create test2.txt
+        @ f8e4ba1b (> detached HEAD) create test2.txt
         Amended with 1 staged change. (Some uncommitted changes were not amended.)
         "###);
     }
@@ -174,7 +178,7 @@ fn test_amend_with_working_copy() -> eyre::Result<()> {
         |
         o 62fc20d2 create test1.txt
         |
-        @ 2e69581c create test2.txt
+        @ 2e69581c (> detached HEAD) create test2.txt
         Amended with 1 uncommitted change.
         "###);
     }
@@ -203,7 +207,7 @@ fn test_amend_head() -> eyre::Result<()> {
         No abandoned branches to restack.
         O f777ecc9 (master) create initial.txt
         |
-        @ 3b98a960 create test1.txt
+        @ 3b98a960 (> detached HEAD) create test1.txt
         Amended with 1 uncommitted change.
         "###);
     }
@@ -225,7 +229,7 @@ fn test_amend_head() -> eyre::Result<()> {
         No abandoned branches to restack.
         O f777ecc9 (master) create initial.txt
         |
-        @ 685ef311 create test1.txt
+        @ 685ef311 (> detached HEAD) create test1.txt
         Amended with 1 staged change.
         "###);
     }
@@ -260,7 +264,7 @@ fn test_amend_executable() -> eyre::Result<()> {
         |
         o 62fc20d2 create test1.txt
         |
-        @ f00ec4b5 create test2.txt
+        @ f00ec4b5 (> detached HEAD) create test2.txt
         Amended with 1 staged change.
         "###);
     }
@@ -310,3 +314,282 @@ fn test_amend_unresolved_merge_conflict() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_amend_reparent() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    git.write_file("test2", "updated contents")?;
+
+    {
+        let (stdout, _stderr) = git.run(&["amend", "--reparent"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> reset
+        branchless: processing 1 update: branch foo
+        Reparented 1 descendant commit.
+        Amended with 1 uncommitted change.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        @ 7ac317b9 (> detached HEAD) create test2.txt
+        |
+        o d6a111e5 (foo) create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "--pretty=format:", "--name-status", "foo"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        M	test2.txt
+        A	test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_amend_with_message() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    git.write_file("test1", "updated contents")?;
+    git.run(&["add", "test1.txt"])?;
+    git.write_file("test1", "unstaged contents")?;
+    {
+        let (stdout, _stderr) = git.run(&[
+            "branchless",
+            "amend",
+            "--message",
+            "amended test1",
+            "--message",
+            "body",
+        ])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> diff --quiet
+        Attempting rebase in-memory...
+        [1/1] Committed as: dcf94d5d create test2.txt
+        branchless: processing 1 rewritten commit
+        In-memory rebase succeeded.
+        Restacked 1 commit:
+          - 96d1c37a create test2.txt
+        Finished restacking commits.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 61af5ba9 (> detached HEAD) amended test1
+        |
+        o dcf94d5d create test2.txt
+        Amended with 1 staged change. (Some uncommitted changes were not amended.)
+        Amended commit message.
+        "###);
+    }
+    {
+        let (stdout, _stderr) = git.run(&["log", "-1", "--format=%B", "--stat", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        amended test1
+
+        body
+
+
+         test1.txt | 1 +
+         1 file changed, 1 insertion(+)
+        "###);
+    }
+
+    git.run(&["checkout", "--", "test1.txt"])?;
+    {
+        let (stdout, _stderr) =
+            git.run(&["branchless", "amend", "--message", "only the message"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/1] Committed as: 09b299a5 create test2.txt
+        branchless: processing 1 rewritten commit
+        In-memory rebase succeeded.
+        Restacked 1 commit:
+          - dcf94d5d create test2.txt
+        Finished restacking commits.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 708b895c (> detached HEAD) only the message
+        |
+        o 09b299a5 create test2.txt
+        Amended commit message.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["branchless", "amend", "--message", "  "],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"Aborting amend due to empty commit message.
+");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_amend_edit_message() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    git.write_file("test1", "updated contents")?;
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &[
+                "branchless",
+                "amend",
+                "--edit",
+                "--message",
+                "create test1.txt",
+            ],
+            &GitRunOptions {
+                env: {
+                    let mut env = HashMap::new();
+                    env.insert(
+                        "GIT_EDITOR".to_string(),
+                        "sed -i.bak 's/create/edited/'".to_string(),
+                    );
+                    env
+                },
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> reset
+        No abandoned commits to restack.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 5f701987 (> detached HEAD) edited test1.txt
+        Amended with 1 uncommitted change.
+        Amended commit message.
+        "###);
+    }
+    {
+        let (stdout, _stderr) = git.run(&["show", "--stat", "--format=%s", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        edited test1.txt
+
+         test1.txt | 1 +
+         1 file changed, 1 insertion(+)
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_amend_committer_from_environment() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["config", "branchless.restack.preserveTimestamps", "false"])?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    git.write_file("test1", "updated contents")?;
+    git.run_with_options(
+        &["branchless", "amend"],
+        &GitRunOptions {
+            env: {
+                let mut env = HashMap::new();
+                env.insert(
+                    "GIT_COMMITTER_NAME".to_string(),
+                    "Environment Committer".to_string(),
+                );
+                env.insert(
+                    "GIT_COMMITTER_EMAIL".to_string(),
+                    "committer@example.com".to_string(),
+                );
+                env.insert(
+                    "GIT_COMMITTER_DATE".to_string(),
+                    "@1234567890 +0100".to_string(),
+                );
+                env
+            },
+            ..Default::default()
+        },
+    )?;
+
+    {
+        let (stdout, _stderr) = git.run(&[
+            "log",
+            "-2",
+            "--date=raw",
+            "--format=%s: %an <%ae>, committed by %cn <%ce> %cd",
+            "foo",
+        ])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test2.txt: Testy McTestface <test@example.com>, committed by Environment Committer <committer@example.com> 1234567890 +0100
+        create test1.txt: Testy McTestface <test@example.com>, committed by Environment Committer <committer@example.com> 1234567890 +0100
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_amend_sign_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_ssh_signing()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.set_up_ssh_signing()?;
+
+    git.write_file("test1", "updated contents")?;
+    git.run(&["branchless", "amend", "--reparent"])?;
+    assert!(git.is_commit_signed("HEAD")?);
+    assert!(git.is_commit_signed("foo")?);
+
+    git.write_file("test1", "updated contents again")?;
+    git.run(&["branchless", "amend", "--reparent", "--no-gpg-sign"])?;
+    assert!(!git.is_commit_signed("HEAD")?);
+    assert!(!git.is_commit_signed("foo")?);
+
+    Ok(())
+}
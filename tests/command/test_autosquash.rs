@@ -0,0 +1,321 @@
+use branchless::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_autosquash_fixup() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file("test1", "updated test1 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run_with_options(
+        &["commit", "--fixup", &test1_oid.to_string()],
+        &GitRunOptions {
+            time: 3,
+            ..Default::default()
+        },
+    )?;
+    git.run(&["checkout", "-b", "foo"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "autosquash"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/1] Committed as: 665f96ed create test2.txt
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout foo
+        In-memory rebase succeeded.
+        branchless: running command: <git-executable> checkout 665f96edfb2491944631a771cf513f9744745b18
+        branchless: processing 1 update: branch foo
+        branchless: running command: <git-executable> checkout foo
+        Folded 484ff2d4 fixup! create test1.txt into 62fc20d2 create test1.txt.
+        O f777ecc9 (master) create initial.txt
+        |
+        o a090e14f create test1.txt
+        |
+        @ 665f96ed (> foo) create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--name-status", "--format=%B", "-2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test2.txt
+
+
+        A	test2.txt
+        create test1.txt
+
+
+        A	test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "HEAD^:test1.txt"])?;
+        insta::assert_snapshot!(stdout, @"updated test1 contents
+");
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_autosquash_multiple_fixups() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file("test1", "first update\n")?;
+    git.run(&["add", "."])?;
+    git.run_with_options(
+        &["commit", "--fixup", &test1_oid.to_string()],
+        &GitRunOptions {
+            time: 3,
+            ..Default::default()
+        },
+    )?;
+    git.run(&["branch", "fixup-branch"])?;
+    git.commit_file("test3", 4)?;
+    git.write_file("test1", "second update\n")?;
+    git.run(&["add", "."])?;
+    git.run_with_options(
+        &[
+            "commit",
+            "--squash",
+            &test1_oid.to_string(),
+            "-m",
+            "Explain the second update.",
+        ],
+        &GitRunOptions {
+            time: 5,
+            ..Default::default()
+        },
+    )?;
+    git.commit_file("test4", 6)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "autosquash"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/3] Committed as: ac745a92 create test2.txt
+        [2/3] Committed as: bcdb3b1a create test3.txt
+        [3/3] Committed as: 5623d161 create test4.txt
+        branchless: processing 3 rewritten commits
+        branchless: running command: <git-executable> checkout 5623d1610935491b9b89b7ab783b02c055d9ac11
+        In-memory rebase succeeded.
+        branchless: processing 1 update: branch fixup-branch
+        Folded 9ba03d3b fixup! create test1.txt into 62fc20d2 create test1.txt.
+        Folded b2baf56d squash! create test1.txt into 62fc20d2 create test1.txt.
+        O f777ecc9 (master) create initial.txt
+        |
+        o 5d78fabf create test1.txt
+        |
+        o ac745a92 (fixup-branch) create test2.txt
+        |
+        o bcdb3b1a create test3.txt
+        |
+        @ 5623d161 (> detached HEAD) create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "master..HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test4.txt
+        create test3.txt
+        create test2.txt
+        create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%B", "-1", "HEAD~3"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test1.txt
+
+        Explain the second update.
+
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "HEAD:test1.txt"])?;
+        insta::assert_snapshot!(stdout, @"second update
+");
+    }
+
+    git.run_with_options(
+        &["undo"],
+        &GitRunOptions {
+            input: Some("y".to_string()),
+            ..Default::default()
+        },
+    )?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |
+        o 9ba03d3b (fixup-branch) fixup! create test1.txt
+        |
+        o 301c433c create test3.txt
+        |
+        o b2baf56d squash! create test1.txt
+        |
+        @ aea9c754 (> detached HEAD) create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_autosquash_commit_template() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.write_file("test1", "updated\n")?;
+    git.run(&["add", "."])?;
+    git.run_with_options(
+        &[
+            "commit",
+            "--squash",
+            &test1_oid.to_string(),
+            "-m",
+            "Explain the update.",
+        ],
+        &GitRunOptions {
+            time: 2,
+            ..Default::default()
+        },
+    )?;
+
+    git.run(&["branchless", "autosquash", "--commit-template=- %s"])?;
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%B", "-1"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        - create test1.txt
+
+        - Explain the update.
+
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_autosquash_no_target() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "autosquash"])?;
+        insta::assert_snapshot!(stdout, @"No fixup commits to fold.
+");
+    }
+
+    git.write_file("test1", "updated test1 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "fixup! create missing.txt"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "autosquash"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Skipping 9d25734e fixup! create missing.txt, because the commit it refers to could not be found.
+        No fixup commits to fold.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_autosquash_on_disk() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file("test1", "updated test1 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run_with_options(
+        &["commit", "--fixup", &test1_oid.to_string()],
+        &GitRunOptions {
+            time: 3,
+            ..Default::default()
+        },
+    )?;
+    git.commit_file("test3", 4)?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "autosquash", "--on-disk"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> diff --quiet
+        Calling Git for on-disk rebase...
+        branchless: running command: <git-executable> rebase --continue
+        branchless: running command: <git-executable> checkout 665f96edfb2491944631a771cf513f9744745b18
+        Folded 484ff2d4 fixup! create test1.txt into 62fc20d2 create test1.txt.
+        O f777ecc9 (master) create initial.txt
+        |
+        o a090e14f create test1.txt
+        |
+        @ 665f96ed (> detached HEAD) create test2.txt
+        |
+        o 8abe57d7 create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_autosquash_sign_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_ssh_signing()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file("test1", "updated test1 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "--fixup", &test1_oid.to_string()])?;
+    git.run(&["checkout", "-b", "foo"])?;
+    git.set_up_ssh_signing()?;
+
+    git.run(&["branchless", "autosquash"])?;
+    assert!(git.is_commit_signed("foo^")?);
+    assert!(git.is_commit_signed("foo")?);
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use branchless::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_diff() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "diff", "--", "--stat"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> diff --stat f777ecc9b0db5ed372b2615695191a8a17f79f24 96d1c37a3d4363611c49f7e52186e189a04c531f
+         test1.txt | 1 +
+         test2.txt | 1 +
+         2 files changed, 2 insertions(+)
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "diff", "--range", "--", "--stat"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> diff --stat f777ecc9b0db5ed372b2615695191a8a17f79f24 70deb1e28791d8e7dd5a1f0c871a51b91282562f
+         test1.txt | 1 +
+         test2.txt | 1 +
+         test3.txt | 1 +
+         3 files changed, 3 insertions(+)
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "diff", "HEAD^"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> diff f777ecc9b0db5ed372b2615695191a8a17f79f24 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        diff --git a/test1.txt b/test1.txt
+        new file mode 100644
+        index 0000000..7432a8f
+        --- /dev/null
+        +++ b/test1.txt
+        @@ -0,0 +1 @@
+        +test1 contents
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_diff_main_branch_moved() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) =
+            git.run(&["branchless", "diff", &test1_oid.to_string(), "--", "--stat"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> diff --stat f777ecc9b0db5ed372b2615695191a8a17f79f24 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+         test1.txt | 1 +
+         1 file changed, 1 insertion(+)
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_diff_errors() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    {
+        let (stdout, stderr) = git.run_with_options(
+            &["branchless", "diff", "does-not-exist"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Commit not found: does-not-exist
+");
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    {
+        let (stdout, stderr) = git.run_with_options(
+            &["branchless", "diff", "--range"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Cannot diff the stack containing 62fc20d2 create test1.txt, because it has 2 heads. Diff one of the heads instead.
+");
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use branchless::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_fold_head() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["fold"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 22c0a747a03bf1eb93fcea5064a918384a5a62b0
+        Attempting rebase in-memory...
+        [1/1] Committed as: 08d328ec create test3.txt
+        branchless: processing 1 rewritten commit
+        In-memory rebase succeeded.
+        Folded 96d1c37a create test2.txt into 62fc20d2 create test1.txt.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 22c0a747 (> detached HEAD) create test1.txt
+        |
+        o 08d328ec create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--name-status", "--format=%B", "-2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test1.txt
+
+        create test2.txt
+
+
+        A	test1.txt
+        A	test2.txt
+        create initial.txt
+
+
+        A	initial.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_fold_moves_branches() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["branch", "foo"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "-b", "bar"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["fold"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 22c0a747a03bf1eb93fcea5064a918384a5a62b0
+        branchless: processing 2 updates: branch bar, branch foo
+        branchless: running command: <git-executable> checkout bar
+        Folded 96d1c37a create test2.txt into 62fc20d2 create test1.txt.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 22c0a747 (> bar, foo) create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short", "--branch"])?;
+        insta::assert_snapshot!(stdout, @"## bar
+");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_fold_edit_message() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["fold", "--edit"],
+            &GitRunOptions {
+                env: {
+                    let mut env = HashMap::new();
+                    env.insert(
+                        "GIT_EDITOR".to_string(),
+                        "sed -i.bak '/test2/d; s/create test1.txt/create test1.txt and test2.txt/'"
+                            .to_string(),
+                    );
+                    env
+                },
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 330b9e9684e7775595ffb10480f67281e1989210
+        Folded 96d1c37a create test2.txt into 62fc20d2 create test1.txt.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 330b9e96 (> detached HEAD) create test1.txt and test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_fold_commit_template() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["config", "branchless.commitTemplate", "* %s"])?;
+
+    git.run(&["fold"])?;
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%B", "-1"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        * create test2.txt
+
+        * create test3.txt
+
+        "###);
+    }
+
+    git.run(&["fold", "--commit-template", "%s (folded)"])?;
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%B", "-1"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test1.txt (folded)
+
+        * create test2.txt (folded)
+
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_fold_errors() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["merge", "--no-edit", "HEAD@{2}"])?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["fold"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"Cannot fold 432d451d Merge commit 'HEAD@{2}', because it is a merge commit.
+");
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["fold", "master^"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"Cannot fold fe65c1fe create test2.txt, because its parent commit f777ecc9 create initial.txt is on the main branch.
+");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_fold_sign_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_ssh_signing()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.set_up_ssh_signing()?;
+
+    git.run(&["fold"])?;
+    assert!(git.is_commit_signed("HEAD")?);
+
+    Ok(())
+}
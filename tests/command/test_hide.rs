@@ -14,12 +14,12 @@ fn test_hide_commit() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            O f777ecc9 (master) create initial.txt
-            |\
-            | o 62fc20d2 create test1.txt
-            |
-            @ fe65c1fe create test2.txt
-            "###);
+        O f777ecc9 (master) create initial.txt
+        |\
+        | o 62fc20d2 create test1.txt
+        |
+        @ fe65c1fe (> detached HEAD) create test2.txt
+        "###);
     }
 
     {
@@ -33,10 +33,10 @@ fn test_hide_commit() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            O f777ecc9 (master) create initial.txt
-            |
-            @ fe65c1fe create test2.txt
-            "###);
+        O f777ecc9 (master) create initial.txt
+        |
+        @ fe65c1fe (> detached HEAD) create test2.txt
+        "###);
     }
 
     Ok(())
@@ -95,10 +95,10 @@ fn test_hide_current_commit() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            O f777ecc9 (master) create initial.txt
-            |
-            % 3df4b935 (manually hidden) create test.txt
-            "###);
+        O f777ecc9 (master) create initial.txt
+        |
+        % 3df4b935 (manually hidden) (> detached HEAD) create test.txt
+        "###);
     }
 
     Ok(())
@@ -121,12 +121,12 @@ fn test_hidden_commit_with_head_as_child() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            O f777ecc9 (master) create initial.txt
-            |
-            x 62fc20d2 (manually hidden) create test1.txt
-            |
-            @ 96d1c37a create test2.txt
-            "###);
+        O f777ecc9 (master) create initial.txt
+        |
+        x 62fc20d2 (manually hidden) create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD) create test2.txt
+        "###);
     }
 
     Ok(())
@@ -183,8 +183,9 @@ fn test_branches_always_visible() -> eyre::Result<()> {
     git.run(&["branch", "-D", "test"])?;
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
-        insta::assert_snapshot!(stdout, @"@ f777ecc9 (> master) create initial.txt
-");
+        insta::assert_snapshot!(stdout, @r###"
+        @ f777ecc9 (> master) create initial.txt
+        "###);
     }
 
     Ok(())
@@ -307,3 +308,403 @@ fn test_hide_recursive() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_hide_recursive_skips_public_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["hide", "-r", &test1_oid.to_string()])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Skipped hiding public commit: 62fc20d2 create test1.txt
+        Skipped hiding public commit: 96d1c37a create test2.txt
+        To hide public commits anyway, run again with --force.
+        Hid commit: 70deb1e2 create test3.txt
+        To unhide this 1 commit, run: git undo
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 96d1c37a (> master) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_hide_force_public_commit() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["hide", "HEAD~2..HEAD^"],
+            &GitRunOptions {
+                expected_exit_code: 3,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Skipped hiding public commit: 96d1c37a create test2.txt
+        To hide public commits anyway, run again with --force.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["hide", "--force", "HEAD~2..HEAD^"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid commit: 96d1c37a create test2.txt
+        To unhide this 1 commit, run: git undo
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        X 96d1c37a (force hidden public) create test2.txt
+        |
+        @ 70deb1e2 (> master) create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) =
+            git.run(&["hide", "--force", "-m", "reverted release", "HEAD~2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid commit: 62fc20d2 create test1.txt
+        To unhide this 1 commit, run: git undo
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        X 62fc20d2 (force hidden public: reverted release) create test1.txt
+        |
+        X 96d1c37a (force hidden public) create test2.txt
+        |
+        @ 70deb1e2 (> master) create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_hide_range() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    let test4_oid = git.commit_file("test4", 4)?;
+    git.commit_file("test5", 5)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["hide", &format!("{}..{}", test2_oid, test4_oid)])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid commit: 70deb1e2 create test3.txt
+        Hid commit: 355e173b create test4.txt
+        To unhide these 2 commits, run: git undo
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 (master) create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |
+        x 70deb1e2 (manually hidden) create test3.txt
+        |
+        x 355e173b (manually hidden) create test4.txt
+        |
+        @ f81d55c0 (> detached HEAD) create test5.txt
+        "###);
+    }
+
+    git.run_with_options(
+        &["undo"],
+        &GitRunOptions {
+            input: Some("y".to_string()),
+            ..Default::default()
+        },
+    )?;
+
+    {
+        let (stdout, _stderr) = git.run(&["hide", "HEAD~5..HEAD^"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Skipped hiding public commit: 62fc20d2 create test1.txt
+        To hide public commits anyway, run again with --force.
+        Hid commit: 96d1c37a create test2.txt
+        Hid commit: 70deb1e2 create test3.txt
+        Hid commit: 355e173b create test4.txt
+        To unhide these 3 commits, run: git undo
+        "###);
+    }
+
+    git.run_with_options(
+        &["undo"],
+        &GitRunOptions {
+            input: Some("y".to_string()),
+            ..Default::default()
+        },
+    )?;
+
+    {
+        git.run(&[
+            "tag",
+            "-a",
+            "-m",
+            "annotated tag",
+            "v1.0",
+            &test2_oid.to_string(),
+        ])?;
+        let (stdout, _stderr) = git.run(&["hide", "v1.0..HEAD^"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid commit: 70deb1e2 create test3.txt
+        Hid commit: 355e173b create test4.txt
+        To unhide these 2 commits, run: git undo
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["hide", "HEAD..nonexistent"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Commit not found: HEAD..nonexistent
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_hide_query() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["branch", "foo"])?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["hide", "--query", "age < 1d"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        No commits matched the query.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["hide", "--query", "message ~ test2 and"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Invalid query: Expected a predicate, but got end of query (at position 19)
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&[
+            "hide",
+            "--query",
+            r#"message ~ "TEST3" or (age > 30d and not branch ~ fo)"#,
+        ])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid commit: 96d1c37a create test2.txt
+        Hid commit: 70deb1e2 create test3.txt
+        To unhide these 2 commits, run: git undo
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ f777ecc9 (> master) create initial.txt
+        |
+        o 62fc20d2 (foo) create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_unhide_all() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["commit", "--amend", "-m", "amended test3"])?;
+    git.run(&["hide", &test1_oid.to_string(), &test2_oid.to_string()])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        x fe65c1fe (manually hidden) create test2.txt
+        |
+        @ ff585546 (> detached HEAD) amended test3
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["unhide", "--all", "--since", "3x"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Invalid duration: Unknown duration unit in `3x` (expected one of `s`, `m`, `h`, `d`, or `w`)
+        "###);
+    }
+
+    {
+        // Durations which are too large to subtract from the current time
+        // don't exclude any commits.
+        let (stdout, _stderr) = git.run(&["unhide", "--all", "--since", "20000000000000w"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Unhid commit: 62fc20d2 create test1.txt
+        Unhid commit: fe65c1fe create test2.txt
+        To hide these 2 commits, run: git undo
+        "###);
+        git.run_with_options(
+            &["undo"],
+            &GitRunOptions {
+                input: Some("y".to_string()),
+                ..Default::default()
+            },
+        )?;
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["unhide", "--all", "--since", "1h"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Unhid commit: 62fc20d2 create test1.txt
+        Unhid commit: fe65c1fe create test2.txt
+        To hide these 2 commits, run: git undo
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |\
+        | o 62fc20d2 create test1.txt
+        |
+        o fe65c1fe create test2.txt
+        |
+        @ ff585546 (> detached HEAD) amended test3
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["unhide", "--all"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        No hidden commits to unhide.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_hide_message() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&[
+            "hide",
+            "-m",
+            "superseded by refactor",
+            &test2_oid.to_string(),
+        ])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid commit: 96d1c37a create test2.txt
+        To unhide this 1 commit, run: git undo
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--hidden"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ f777ecc9 (> master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        x 96d1c37a (manually hidden: superseded by refactor) create test2.txt
+        "###);
+    }
+
+    let confirm = GitRunOptions {
+        input: Some("y".to_string()),
+        ..Default::default()
+    };
+    git.run_with_options(&["undo"], &confirm)?;
+    git.run_with_options(&["redo"], &confirm)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--hidden"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ f777ecc9 (> master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        x 96d1c37a (manually hidden: superseded by refactor) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
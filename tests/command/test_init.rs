@@ -169,6 +169,159 @@ fn test_dont_install_existing_aliases() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_warn_conflicting_aliases() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo_with_options(&GitInitOptions {
+        run_branchless_init: false,
+        ..Default::default()
+    })?;
+
+    let fake_home_dir = git.repo_path.join("fake_home");
+    std::fs::create_dir(&fake_home_dir)?;
+    std::fs::write(
+        fake_home_dir.join(".gitconfig"),
+        "[alias]\n\tsl = status\n\tsmartlog = branchless smartlog\n",
+    )?;
+    let git_run_options = GitRunOptions {
+        env: HashMap::from([(
+            "HOME".to_string(),
+            fake_home_dir.to_string_lossy().to_string(),
+        )]),
+        ..Default::default()
+    };
+
+    {
+        let (stdout, _stderr) = git.run_with_options(&["branchless", "init"], &git_run_options)?;
+        insta::assert_snapshot!(stdout, @r###"
+        Created config file at <repo-path>/.git/branchless/config
+        Auto-detected your main branch as: master
+        If this is incorrect, run: git config branchless.core.mainBranch <branch>
+        Installing hook: post-commit
+        Installing hook: post-merge
+        Installing hook: post-rewrite
+        Installing hook: post-checkout
+        Installing hook: pre-auto-gc
+        Installing hook: pre-commit
+        Installing hook: reference-transaction
+        Warning: alias sl is already defined as `status`, so it was not installed.
+        To overwrite it, run: git branchless init --force
+        To install it under a different name, run: git config branchless.init.aliases.sl <name>
+        Alias smartlog already installed, skipping
+        Successfully installed git-branchless.
+        To uninstall, run: git branchless init --uninstall
+        "###);
+    }
+
+    {
+        let (expected_stdout, _) = git.run_with_options(&["status"], &git_run_options)?;
+        let (actual_stdout, _) = git.run_with_options(&["sl"], &git_run_options)?;
+        assert_eq!(expected_stdout, actual_stdout);
+    }
+
+    {
+        let (stdout, _stderr) =
+            git.run_with_options(&["branchless", "init", "--force"], &git_run_options)?;
+        insta::assert_snapshot!(stdout, @r###"
+        Created config file at <repo-path>/.git/branchless/config
+        Auto-detected your main branch as: master
+        If this is incorrect, run: git config branchless.core.mainBranch <branch>
+        Installing hook: post-commit
+        Installing hook: post-merge
+        Installing hook: post-rewrite
+        Installing hook: post-checkout
+        Installing hook: pre-auto-gc
+        Installing hook: pre-commit
+        Installing hook: reference-transaction
+        Overwriting alias sl (was: `status`)
+        Alias smartlog already installed, skipping
+        Successfully installed git-branchless.
+        To uninstall, run: git branchless init --uninstall
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(&["sl"], &git_run_options)?;
+        insta::assert_snapshot!(stdout, @"@ f777ecc9 (> master) create initial.txt
+");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_configured_alias_names() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["config", "branchless.init.aliases.next", "nextc"])?;
+    git.run(&["config", "branchless.init.aliases.sl", ""])?;
+    git.run(&["config", "alias.co", "checkout"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "init"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Created config file at <repo-path>/.git/branchless/config
+        Auto-detected your main branch as: master
+        If this is incorrect, run: git config branchless.core.mainBranch <branch>
+        Installing hook: post-commit
+        Installing hook: post-merge
+        Installing hook: post-rewrite
+        Installing hook: post-checkout
+        Installing hook: pre-auto-gc
+        Installing hook: pre-commit
+        Installing hook: reference-transaction
+        Warning: alias co is already defined as `checkout`, so it was not installed.
+        To overwrite it, run: git branchless init --force
+        To install it under a different name, run: git config branchless.init.aliases.co <name>
+        Successfully installed git-branchless.
+        To uninstall, run: git branchless init --uninstall
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["config", "--get-regexp", "^alias\\."])?;
+        insta::assert_snapshot!(stdout, @r###"
+        alias.amend branchless amend
+        alias.fold branchless fold
+        alias.hide branchless hide
+        alias.move branchless move
+        alias.nextc branchless next
+        alias.prev branchless prev
+        alias.redo branchless redo
+        alias.restack branchless restack
+        alias.reword branchless reword
+        alias.smartlog branchless smartlog
+        alias.split branchless split
+        alias.sync branchless sync
+        alias.undo branchless undo
+        alias.unhide branchless unhide
+        alias.co checkout
+        "###);
+    }
+
+    git.run(&["config", "branchless.init.aliases.next", "nextd"])?;
+    git.run(&["config", "--unset", "branchless.init.aliases.sl"])?;
+    git.run(&["branchless", "init"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&[
+            "config",
+            "--file",
+            ".git/branchless/config",
+            "--get-regexp",
+            "^alias\\.(next|sl)",
+        ])?;
+        insta::assert_snapshot!(stdout, @r###"
+        alias.nextd branchless next
+        alias.sl branchless smartlog
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_old_git_version_warning() -> eyre::Result<()> {
     let git = make_git()?;
@@ -233,6 +386,7 @@ fn test_init_basic() -> eyre::Result<()> {
         Installing hook: post-rewrite
         Installing hook: post-checkout
         Installing hook: pre-auto-gc
+        Installing hook: pre-commit
         Installing hook: reference-transaction
         Successfully installed git-branchless.
         To uninstall, run: git branchless init --uninstall
@@ -277,6 +431,7 @@ fn test_init_prompt_for_main_branch() -> eyre::Result<()> {
         Installing hook: post-rewrite
         Installing hook: post-checkout
         Installing hook: pre-auto-gc
+        Installing hook: pre-commit
         Installing hook: reference-transaction
         Successfully installed git-branchless.
         To uninstall, run: git branchless init --uninstall
@@ -292,6 +447,96 @@ fn test_init_prompt_for_main_branch() -> eyre::Result<()> {
     Ok(())
 }
 
+#[cfg(unix)]
+#[test]
+fn test_init_prompt_for_main_branch_quiet() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo_with_options(&GitInitOptions {
+        run_branchless_init: false,
+        ..Default::default()
+    })?;
+
+    git.run(&["branch", "-m", "master", "bespoke"])?;
+
+    {
+        let (stdout, stderr) = git.run_with_options(
+            &["branchless", "init", "--quiet"],
+            &GitRunOptions {
+                input: Some("bespoke\n".to_string()),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        Your main branch name could not be auto-detected!
+        Examples of a main branch: master, main, trunk, etc.
+        See https://github.com/arxanas/git-branchless/wiki/Concepts#main-branch
+        Enter the name of your main branch: 
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_main_branch_candidates() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        git.run(&["config", "branchless.core.mainBranch", "develop, master"])?;
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD) create test1.txt
+        "###);
+    }
+
+    {
+        // With no configured main branch, `main` is detected automatically.
+        git.run(&["config", "--unset", "branchless.core.mainBranch"])?;
+        git.run(&[
+            "config",
+            "--file",
+            ".git/branchless/config",
+            "--unset",
+            "branchless.core.mainBranch",
+        ])?;
+        git.run(&["branch", "-m", "master", "main"])?;
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (main) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD) create test1.txt
+        "###);
+    }
+
+    {
+        git.run(&["config", "branchless.core.mainBranch", "develop,trunk"])?;
+        let (_stdout, stderr) = git.run_with_options(
+            &["smartlog"],
+            &GitRunOptions {
+                // Exit code 101 indicates a panic.
+                expected_exit_code: 101,
+                ..Default::default()
+            },
+        )?;
+        assert!(stderr
+            .contains(r#"None of the main branch candidates "develop", "trunk" could be found"#));
+    }
+
+    Ok(())
+}
+
 #[cfg(unix)]
 #[test]
 fn test_main_branch_not_found_error_message() -> eyre::Result<()> {
@@ -316,6 +561,7 @@ fn test_main_branch_not_found_error_message() -> eyre::Result<()> {
     let stderr = console::strip_ansi_codes(&stderr);
     let stderr = location_trace_re.replace_all(&stderr, "some/file/path.rs:123");
     insta::assert_snapshot!(stderr, @r###"
+    branchless: hint: main branch "master" could not be found; set `branchless.core.mainBranch` to the name of your main branch
     The application panicked (crashed).
     Message:  A fatal error occurred:
        0: Could not find repository main branch
@@ -327,7 +573,9 @@ fn test_main_branch_not_found_error_message() -> eyre::Result<()> {
 
        0: branchless::git::repo::get_main_branch_oid with self=<Git repository at: "<repo-path>/.git/">
           at some/file/path.rs:123
-       1: branchless::commands::smartlog::smartlog with effects=<Output fancy=false> git_run_info=<GitRunInfo path_to_git="<git-executable>" working_directory="<repo-path>" env=not shown> options=SmartlogOptions { show_hidden_commits: false, only_show_branches: false }
+       1: branchless::commands::smartlog::load_smartlog with effects=<Output fancy=false> repo=<Git repository at: "<repo-path>/.git/"> options=SmartlogOptions { show_hidden_commits: false, only_show_branches: false, only_show_unpushed: false, hide_branches: false, reverse: false, order: None, format: Text, glyphs: None, max_commits: None, full: false, num_public_commits: 0, since: None, show_age: false, stat: None, show_author: false, show_index: false, show_signatures: false, show_legend: false, highlight: [], color_by_stack: false, author: None, commits: [], use_pager: true, overflow: None, use_cache: true }
+          at some/file/path.rs:123
+       2: branchless::commands::smartlog::smartlog with effects=<Output fancy=false> git_run_info=<GitRunInfo path_to_git="<git-executable>" working_directory="<repo-path>" env=not shown> options=SmartlogOptions { show_hidden_commits: false, only_show_branches: false, only_show_unpushed: false, hide_branches: false, reverse: false, order: None, format: Text, glyphs: None, max_commits: None, full: false, num_public_commits: 0, since: None, show_age: false, stat: None, show_author: false, show_index: false, show_signatures: false, show_legend: false, highlight: [], color_by_stack: false, author: None, commits: [], use_pager: true, overflow: None, use_cache: true }
           at some/file/path.rs:123
 
     Suggestion:
@@ -338,6 +586,8 @@ fn test_main_branch_not_found_error_message() -> eyre::Result<()> {
 
         git config branchless.core.mainBranch <branch>
 
+    (Multiple candidates can be given as a comma-separated list.)
+
 
     Backtrace omitted. Run with RUST_BACKTRACE=1 environment variable to display it.
     Run with RUST_BACKTRACE=full to include source snippets.
@@ -348,6 +598,16 @@ fn test_main_branch_not_found_error_message() -> eyre::Result<()> {
     "###);
     insta::assert_snapshot!(stdout, @"");
 
+    // The hint is only shown once.
+    let (_stdout, stderr) = git.run_with_options(
+        &["smartlog"],
+        &GitRunOptions {
+            expected_exit_code: 101,
+            ..Default::default()
+        },
+    )?;
+    assert!(!stderr.contains("branchless: hint:"));
+
     Ok(())
 }
 
@@ -368,9 +628,237 @@ fn test_init_uninstall() -> eyre::Result<()> {
         Uninstalling hook: post-rewrite
         Uninstalling hook: post-checkout
         Uninstalling hook: pre-auto-gc
+        Uninstalling hook: pre-commit
+        Uninstalling hook: reference-transaction
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_init_disabled_hooks() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    let hooks_dir = git.repo_path.join(".git").join("hooks");
+    assert!(hooks_dir.join("reference-transaction").exists());
+
+    git.run(&["config", "branchless.hooks.referenceTransaction", "false"])?;
+    git.run(&["config", "branchless.hooks.postCheckout", "false"])?;
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "init"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Created config file at <repo-path>/.git/branchless/config
+        Auto-detected your main branch as: master
+        If this is incorrect, run: git config branchless.core.mainBranch <branch>
+        Installing hook: post-commit
+        Installing hook: post-merge
+        Installing hook: post-rewrite
+        Skipping disabled hook: post-checkout (set branchless.hooks.postCheckout to true to enable it)
+        Uninstalling hook: post-checkout
+        Installing hook: pre-auto-gc
+        Installing hook: pre-commit
+        Skipping disabled hook: reference-transaction (set branchless.hooks.referenceTransaction to true to enable it)
         Uninstalling hook: reference-transaction
+        Successfully installed git-branchless.
+        To uninstall, run: git branchless init --uninstall
         "###);
     }
+    assert!(!hooks_dir.join("reference-transaction").exists());
+    assert!(!hooks_dir.join("post-checkout").exists());
+    assert!(hooks_dir.join("post-commit").exists());
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_init_uninstall_keeps_existing_hook_contents() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo_with_options(&GitInitOptions {
+        run_branchless_init: false,
+        ..Default::default()
+    })?;
+    let hooks_dir = git.repo_path.join(".git").join("hooks");
+    std::fs::create_dir_all(&hooks_dir)?;
+    std::fs::write(
+        hooks_dir.join("post-commit"),
+        "#!/bin/sh\necho 'existing hook'\n",
+    )?;
+    git.run(&["branchless", "init"])?;
+
+    let post_commit = std::fs::read_to_string(hooks_dir.join("post-commit"))?;
+    insta::assert_snapshot!(post_commit, @r###"
+    #!/bin/sh
+    echo 'existing hook'
+    ## START BRANCHLESS CONFIG
+
+    git branchless hook-post-commit "$@"
+    ## END BRANCHLESS CONFIG
+    "###);
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "init", "--uninstall", "--dry-run"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Would remove config file: <repo-path>/.git/branchless/config
+        Would remove alias: amend
+        Would remove alias: co
+        Would remove alias: fold
+        Would remove alias: hide
+        Would remove alias: move
+        Would remove alias: next
+        Would remove alias: prev
+        Would remove alias: redo
+        Would remove alias: restack
+        Would remove alias: reword
+        Would remove alias: sl
+        Would remove alias: smartlog
+        Would remove alias: split
+        Would remove alias: sync
+        Would remove alias: undo
+        Would remove alias: unhide
+        Would uninstall hook: post-commit (keeping other contents of <repo-path>/.git/hooks/post-commit)
+        Would uninstall hook: post-merge (deleting <repo-path>/.git/hooks/post-merge)
+        Would uninstall hook: post-rewrite (deleting <repo-path>/.git/hooks/post-rewrite)
+        Would uninstall hook: post-checkout (deleting <repo-path>/.git/hooks/post-checkout)
+        Would uninstall hook: pre-auto-gc (deleting <repo-path>/.git/hooks/pre-auto-gc)
+        Would uninstall hook: pre-commit (deleting <repo-path>/.git/hooks/pre-commit)
+        Would uninstall hook: reference-transaction (deleting <repo-path>/.git/hooks/reference-transaction)
+        "###);
+    }
+    assert!(hooks_dir.join("post-merge").exists());
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "init", "--uninstall"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Removing config file: <repo-path>/.git/branchless/config
+        Uninstalling hook: post-commit
+        Uninstalling hook: post-merge
+        Uninstalling hook: post-rewrite
+        Uninstalling hook: post-checkout
+        Uninstalling hook: pre-auto-gc
+        Uninstalling hook: pre-commit
+        Uninstalling hook: reference-transaction
+        "###);
+    }
+
+    let post_commit = std::fs::read_to_string(hooks_dir.join("post-commit"))?;
+    insta::assert_snapshot!(post_commit, @r###"
+    #!/bin/sh
+    echo 'existing hook'
+    "###);
+    assert!(!hooks_dir.join("post-merge").exists());
+
+    {
+        // No aliases should remain, so `git config` exits with code 1.
+        let (stdout, _stderr) = git.run_with_options(
+            &["config", "--get-regexp", "^alias\\."],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(stdout, "");
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_init_global() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo_with_options(&GitInitOptions {
+        run_branchless_init: false,
+        ..Default::default()
+    })?;
+    git.run(&["config", "core.hooksPath", "custom-hooks"])?;
+
+    let home_dir = git.repo_path.join("home");
+    std::fs::create_dir_all(&home_dir)?;
+    let options = GitRunOptions {
+        env: {
+            let mut env = HashMap::new();
+            env.insert("HOME".to_string(), home_dir.to_string_lossy().into_owned());
+            env
+        },
+        ..Default::default()
+    };
+
+    {
+        let (stdout, _stderr) =
+            git.run_with_options(&["branchless", "init", "--global"], &options)?;
+        insta::assert_snapshot!(stdout, @r###"
+        Installing hook: post-commit
+        Installing hook: post-merge
+        Installing hook: post-rewrite
+        Installing hook: post-checkout
+        Installing hook: pre-auto-gc
+        Installing hook: pre-commit
+        Installing hook: reference-transaction
+        Set global core.hooksPath to: <repo-path>/home/.git-branchless/hooks
+        Warning: this repository sets core.hooksPath to: custom-hooks
+        The global hooks will not run in this repository. Run `git branchless init` here to install them locally.
+        Successfully installed git-branchless globally.
+        To configure the main branch of a repository, run: git branchless init
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &[
+                "config",
+                "--global",
+                "--get-regexp",
+                "^(core\\.hookspath|alias\\.sl)$",
+            ],
+            &options,
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        core.hookspath <repo-path>/home/.git-branchless/hooks
+        alias.sl branchless smartlog
+        "###);
+    }
+
+    let post_commit = std::fs::read_to_string(home_dir.join(".git-branchless/hooks/post-commit"))?;
+    insta::assert_snapshot!(post_commit, @r###"
+    #!/bin/sh
+    ## START BRANCHLESS CONFIG
+
+    git branchless hook-post-commit "$@"
+
+    ## END BRANCHLESS CONFIG
+    "###);
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_init_relative_core_hooks_path() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo_with_options(&GitInitOptions {
+        run_branchless_init: false,
+        ..Default::default()
+    })?;
+    git.run(&["config", "core.hooksPath", "my-hooks"])?;
+    std::fs::create_dir_all(git.repo_path.join("subdir"))?;
+
+    // The hooks path should be resolved relative to the root of the working
+    // copy, not the current directory.
+    git.run_with_options(
+        &["branchless", "init"],
+        &GitRunOptions {
+            cwd: Some("subdir".into()),
+            ..Default::default()
+        },
+    )?;
+    assert!(git.repo_path.join("my-hooks").join("post-commit").exists());
+    assert!(!git.repo_path.join("subdir").join("my-hooks").exists());
 
     Ok(())
 }
@@ -559,6 +1047,7 @@ fn test_init_core_hooks_path_warning() -> eyre::Result<()> {
         Installing hook: post-rewrite
         Installing hook: post-checkout
         Installing hook: pre-auto-gc
+        Installing hook: pre-commit
         Installing hook: reference-transaction
         Warning: the configuration value core.hooksPath was set to: my-hooks
         The Git hooks above may have been installed to an unexpected location.
@@ -569,3 +1058,54 @@ fn test_init_core_hooks_path_warning() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[cfg(unix)]
+#[test]
+fn test_init_hook_shebang() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo_with_options(&GitInitOptions {
+        run_branchless_init: false,
+        ..Default::default()
+    })?;
+    git.run(&[
+        "config",
+        "branchless.init.hookShebang",
+        "#!/usr/bin/env bash",
+    ])?;
+    let hooks_dir = git.repo_path.join(".git").join("hooks");
+    std::fs::create_dir_all(&hooks_dir)?;
+    std::fs::write(
+        hooks_dir.join("post-commit"),
+        "#!/bin/sh\necho 'existing hook'\n",
+    )?;
+    git.run(&["branchless", "init"])?;
+
+    // Newly-created hooks use the configured shebang.
+    let post_merge = std::fs::read_to_string(hooks_dir.join("post-merge"))?;
+    insta::assert_snapshot!(post_merge, @r###"
+    #!/usr/bin/env bash
+    ## START BRANCHLESS CONFIG
+
+    git branchless hook-post-merge "$@"
+
+    ## END BRANCHLESS CONFIG
+    "###);
+
+    // Existing hooks keep their shebang.
+    let post_commit = std::fs::read_to_string(hooks_dir.join("post-commit"))?;
+    insta::assert_snapshot!(post_commit, @r###"
+    #!/bin/sh
+    echo 'existing hook'
+    ## START BRANCHLESS CONFIG
+
+    git branchless hook-post-commit "$@"
+    ## END BRANCHLESS CONFIG
+    "###);
+
+    git.run(&["branchless", "init", "--uninstall"])?;
+    assert!(!hooks_dir.join("post-merge").exists());
+    assert!(hooks_dir.join("post-commit").exists());
+
+    Ok(())
+}
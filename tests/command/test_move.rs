@@ -39,7 +39,7 @@ fn test_move_stick() -> eyre::Result<()> {
         |\
         | o 4838e49b create test3.txt
         | |
-        | @ a2482074 create test4.txt
+        | @ a2482074 (> detached HEAD) create test4.txt
         |
         O 96d1c37a (master) create test2.txt
         "###);
@@ -92,7 +92,7 @@ fn test_move_stick() -> eyre::Result<()> {
         |\
         | o 4838e49b create test3.txt
         | |
-        | @ a2482074 create test4.txt
+        | @ a2482074 (> detached HEAD) create test4.txt
         |
         O 96d1c37a (master) create test2.txt
         In-memory rebase succeeded.
@@ -100,15 +100,15 @@ fn test_move_stick() -> eyre::Result<()> {
 
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            :
-            O 62fc20d2 create test1.txt
-            |\
-            | o 4838e49b create test3.txt
-            | |
-            | @ a2482074 create test4.txt
-            |
-            O 96d1c37a (master) create test2.txt
-            "###);
+        :
+        O 62fc20d2 create test1.txt
+        |\
+        | o 4838e49b create test3.txt
+        | |
+        | @ a2482074 (> detached HEAD) create test4.txt
+        |
+        O 96d1c37a (master) create test2.txt
+        "###);
     }
 
     Ok(())
@@ -146,17 +146,17 @@ fn test_move_tree() -> eyre::Result<()> {
         {
             let (stdout, _stderr) = git.run(&["smartlog"])?;
             insta::assert_snapshot!(stdout, @r###"
-        :
-        O 62fc20d2 create test1.txt
-        |\
-        | o 4838e49b create test3.txt
-        | |\
-        | | o a2482074 create test4.txt
-        | |
-        | @ b1f9efa0 create test5.txt
-        |
-        O 96d1c37a (master) create test2.txt
-        "###);
+            :
+            O 62fc20d2 create test1.txt
+            |\
+            | o 4838e49b create test3.txt
+            | |\
+            | | o a2482074 create test4.txt
+            | |
+            | @ b1f9efa0 (> detached HEAD) create test5.txt
+            |
+            O 96d1c37a (master) create test2.txt
+            "###);
         }
     }
 
@@ -172,17 +172,17 @@ fn test_move_tree() -> eyre::Result<()> {
         {
             let (stdout, _stderr) = git.run(&["smartlog"])?;
             insta::assert_snapshot!(stdout, @r###"
-        :
-        O 62fc20d2 create test1.txt
-        |\
-        | o 4838e49b create test3.txt
-        | |\
-        | | o a2482074 create test4.txt
-        | |
-        | @ b1f9efa0 create test5.txt
-        |
-        O 96d1c37a (master) create test2.txt
-        "###);
+            :
+            O 62fc20d2 create test1.txt
+            |\
+            | o 4838e49b create test3.txt
+            | |\
+            | | o a2482074 create test4.txt
+            | |
+            | @ b1f9efa0 (> detached HEAD) create test5.txt
+            |
+            O 96d1c37a (master) create test2.txt
+            "###);
         }
     }
 
@@ -319,7 +319,7 @@ fn test_move_merge_conflict() -> eyre::Result<()> {
         let (stdout, _stderr) = git.run_with_options(
             &["move", "--source", &other_oid.to_string()],
             &GitRunOptions {
-                expected_exit_code: 1,
+                expected_exit_code: 2,
                 ..Default::default()
             },
         )?;
@@ -341,7 +341,7 @@ fn test_move_merge_conflict() -> eyre::Result<()> {
                 &other_oid.to_string(),
             ],
             &GitRunOptions {
-                expected_exit_code: 1,
+                expected_exit_code: 2,
                 ..Default::default()
             },
         )?;
@@ -393,7 +393,7 @@ fn test_move_merge_conflict() -> eyre::Result<()> {
         :
         O 62fc20d2 (master) create test1.txt
         |
-        @ 202143f2 create conflict.txt
+        @ 202143f2 (> detached HEAD) create conflict.txt
         |
         o 42951b5f create conflict.txt
         "###);
@@ -518,7 +518,7 @@ fn test_move_base_shared() -> eyre::Result<()> {
         |
         o 70deb1e2 create test3.txt
         |
-        @ 355e173b create test4.txt
+        @ 355e173b (> detached HEAD) create test4.txt
         In-memory rebase succeeded.
         "###);
     }
@@ -534,7 +534,7 @@ fn test_move_base_shared() -> eyre::Result<()> {
         |
         o 70deb1e2 create test3.txt
         |
-        @ 355e173b create test4.txt
+        @ 355e173b (> detached HEAD) create test4.txt
         "###);
     }
 
@@ -580,7 +580,7 @@ fn test_move_checkout_new_head() -> eyre::Result<()> {
         :
         O 62fc20d2 (master) create test1.txt
         |
-        @ 96d1c37a create test2.txt
+        @ 96d1c37a (> detached HEAD) create test2.txt
         In-memory rebase succeeded.
         "###);
     }
@@ -588,11 +588,11 @@ fn test_move_checkout_new_head() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            :
-            O 62fc20d2 (master) create test1.txt
-            |
-            @ 96d1c37a create test2.txt
-            "###);
+        :
+        O 62fc20d2 (master) create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD) create test2.txt
+        "###);
     }
 
     Ok(())
@@ -711,7 +711,7 @@ fn test_move_base_onto_head() -> eyre::Result<()> {
         |
         o 96d1c37a create test2.txt
         |
-        @ 70deb1e2 create test3.txt
+        @ 70deb1e2 (> detached HEAD) create test3.txt
         "###);
     }
 
@@ -771,7 +771,15 @@ fn test_rebase_in_memory_updates_committer_timestamp() -> eyre::Result<()> {
         .find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?
         .get_committer()
         .get_time();
-    git.run(&["move", "-d", "master"])?;
+    // The committer timestamp is taken from `GIT_COMMITTER_DATE`, which is set
+    // according to `time`.
+    git.run_with_options(
+        &["move", "-d", "master"],
+        &GitRunOptions {
+            time: 4,
+            ..Default::default()
+        },
+    )?;
     let updated_committer_timestamp = repo
         .find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?
         .get_committer()
@@ -840,7 +848,7 @@ fn test_move_in_memory_gc() -> eyre::Result<()> {
         |\
         | o 62fc20d2 create test1.txt
         |
-        @ fe65c1fe create test2.txt
+        @ fe65c1fe (> detached HEAD) create test2.txt
         In-memory rebase succeeded.
         "###);
     }
@@ -853,7 +861,7 @@ fn test_move_in_memory_gc() -> eyre::Result<()> {
         insta::assert_snapshot!(stdout, @r###"
         O f777ecc9 (master) create initial.txt
         |\
-        | @ 62fc20d2 create test1.txt
+        | @ 62fc20d2 (> detached HEAD) create test1.txt
         |
         o fe65c1fe create test2.txt
         "###);
@@ -867,7 +875,7 @@ fn test_move_in_memory_gc() -> eyre::Result<()> {
         insta::assert_snapshot!(stdout, @r###"
         O f777ecc9 (master) create initial.txt
         |\
-        | @ 62fc20d2 create test1.txt
+        | @ 62fc20d2 (> detached HEAD) create test1.txt
         |
         o fe65c1fe create test2.txt
         "###);
@@ -1032,17 +1040,17 @@ fn test_move_branches_after_move() -> eyre::Result<()> {
         {
             let (stdout, _stderr) = git.run(&["smartlog"])?;
             insta::assert_snapshot!(stdout, @r###"
-        :
-        O 62fc20d2 create test1.txt
-        |\
-        | o 4838e49b (foo) create test3.txt
-        | |
-        | o a2482074 create test4.txt
-        | |
-        | @ 566e4341 (bar) create test5.txt
-        |
-        O 96d1c37a (master) create test2.txt
-        "###);
+            :
+            O 62fc20d2 create test1.txt
+            |\
+            | o 4838e49b (foo) create test3.txt
+            | |
+            | o a2482074 create test4.txt
+            | |
+            | @ 566e4341 (> detached HEAD, bar) create test5.txt
+            |
+            O 96d1c37a (master) create test2.txt
+            "###);
         }
 
         {
@@ -1052,7 +1060,13 @@ fn test_move_branches_after_move() -> eyre::Result<()> {
 
         {
             // There should be no branches left to restack.
-            let (stdout, _stderr) = git.run(&["restack"])?;
+            let (stdout, _stderr) = git.run_with_options(
+                &["restack"],
+                &GitRunOptions {
+                    expected_exit_code: 3,
+                    ..Default::default()
+                },
+            )?;
             insta::assert_snapshot!(stdout, @r###"
             No abandoned commits to restack.
             No abandoned branches to restack.
@@ -1063,7 +1077,7 @@ fn test_move_branches_after_move() -> eyre::Result<()> {
             | |
             | o a2482074 create test4.txt
             | |
-            | @ 566e4341 (bar) create test5.txt
+            | @ 566e4341 (> detached HEAD, bar) create test5.txt
             |
             O 96d1c37a (master) create test2.txt
             "###);
@@ -1101,7 +1115,7 @@ fn test_move_branches_after_move() -> eyre::Result<()> {
             | |
             | o a2482074 create test4.txt
             | |
-            | @ 566e4341 (bar) create test5.txt
+            | @ 566e4341 (> detached HEAD, bar) create test5.txt
             |
             O 96d1c37a (master) create test2.txt
             In-memory rebase succeeded.
@@ -1118,7 +1132,7 @@ fn test_move_branches_after_move() -> eyre::Result<()> {
             | |
             | o a2482074 create test4.txt
             | |
-            | @ 566e4341 (bar) create test5.txt
+            | @ 566e4341 (> detached HEAD, bar) create test5.txt
             |
             O 96d1c37a (master) create test2.txt
             "###);
@@ -1126,7 +1140,13 @@ fn test_move_branches_after_move() -> eyre::Result<()> {
 
         {
             // There should be no branches left to restack.
-            let (stdout, _stderr) = git.run(&["restack"])?;
+            let (stdout, _stderr) = git.run_with_options(
+                &["restack"],
+                &GitRunOptions {
+                    expected_exit_code: 3,
+                    ..Default::default()
+                },
+            )?;
             insta::assert_snapshot!(stdout, @r###"
             No abandoned commits to restack.
             No abandoned branches to restack.
@@ -1137,7 +1157,7 @@ fn test_move_branches_after_move() -> eyre::Result<()> {
             | |
             | o a2482074 create test4.txt
             | |
-            | @ 566e4341 (bar) create test5.txt
+            | @ 566e4341 (> detached HEAD, bar) create test5.txt
             |
             O 96d1c37a (master) create test2.txt
             "###);
@@ -1196,7 +1216,7 @@ fn test_move_no_reapply_upstream_commits() -> eyre::Result<()> {
             :
             O 047b7ad7 (master) create test1.txt
             |
-            @ fa466332 create test2.txt
+            @ fa466332 (> detached HEAD) create test2.txt
             "###);
         }
     }
@@ -1222,7 +1242,7 @@ fn test_move_no_reapply_upstream_commits() -> eyre::Result<()> {
             :
             O 047b7ad7 (master) create test1.txt
             |
-            @ fa466332 create test2.txt
+            @ fa466332 (> detached HEAD) create test2.txt
             In-memory rebase succeeded.
             "###);
         }
@@ -1230,11 +1250,11 @@ fn test_move_no_reapply_upstream_commits() -> eyre::Result<()> {
         {
             let (stdout, _stderr) = git.run(&["smartlog"])?;
             insta::assert_snapshot!(stdout, @r###"
-        :
-        O 047b7ad7 (master) create test1.txt
-        |
-        @ fa466332 create test2.txt
-        "###);
+            :
+            O 047b7ad7 (master) create test1.txt
+            |
+            @ fa466332 (> detached HEAD) create test2.txt
+            "###);
         }
     }
     Ok(())
@@ -1441,7 +1461,7 @@ fn test_move_delete_checked_out_branch() -> eyre::Result<()> {
             HEAD is now at 91c5ce6 create test2.txt
             branchless: processing checkout
             :
-            @ 91c5ce63 (master) create test2.txt
+            @ 91c5ce63 (> detached HEAD, master) create test2.txt
             |
             o 012efd6e (more-work) create test3.txt
             Successfully rebased and updated detached HEAD.
@@ -1458,11 +1478,11 @@ fn test_move_delete_checked_out_branch() -> eyre::Result<()> {
         {
             let (stdout, _stderr) = git.run(&["smartlog"])?;
             insta::assert_snapshot!(stdout, @r###"
-        :
-        @ 91c5ce63 (master) create test2.txt
-        |
-        o 012efd6e (more-work) create test3.txt
-        "###);
+            :
+            @ 91c5ce63 (> detached HEAD, master) create test2.txt
+            |
+            o 012efd6e (more-work) create test3.txt
+            "###);
         }
     }
     // --in-memory
@@ -1486,7 +1506,7 @@ fn test_move_delete_checked_out_branch() -> eyre::Result<()> {
             branchless: processing 3 rewritten commits
             branchless: running command: <git-executable> checkout 91c5ce63686889388daec1120bf57bea8a744bc2
             :
-            @ 91c5ce63 (master) create test2.txt
+            @ 91c5ce63 (> detached HEAD, master) create test2.txt
             |
             o 012efd6e (more-work) create test3.txt
             In-memory rebase succeeded.
@@ -1496,11 +1516,11 @@ fn test_move_delete_checked_out_branch() -> eyre::Result<()> {
         {
             let (stdout, _stderr) = git.run(&["smartlog"])?;
             insta::assert_snapshot!(stdout, @r###"
-        :
-        @ 91c5ce63 (master) create test2.txt
-        |
-        o 012efd6e (more-work) create test3.txt
-        "###);
+            :
+            @ 91c5ce63 (> detached HEAD, master) create test2.txt
+            |
+            o 012efd6e (more-work) create test3.txt
+            "###);
         }
     }
 
@@ -1566,7 +1586,7 @@ fn test_move_merge_commit() -> eyre::Result<()> {
         | |
         | o 28790c73 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
         |\
-        | @ 98b9119d create test3.txt
+        | @ 98b9119d (> detached HEAD) create test3.txt
         | |
         | o 28790c73 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
         |
@@ -1661,7 +1681,7 @@ fn test_move_merge_commit() -> eyre::Result<()> {
             branchless: processing checkout
             O f777ecc9 create initial.txt
             |\
-            | @ 98b9119d create test3.txt
+            | @ 98b9119d (> detached HEAD) create test3.txt
             | |
             | o 96a2c4be Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
             |
@@ -1678,18 +1698,18 @@ fn test_move_merge_commit() -> eyre::Result<()> {
         {
             let (stdout, _stderr) = git.run(&["smartlog"])?;
             insta::assert_snapshot!(stdout, @r###"
-        O f777ecc9 create initial.txt
-        |\
-        | @ 98b9119d create test3.txt
-        | |
-        | o 96a2c4be Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
-        |
-        O 62fc20d2 (master) create test1.txt
-        |
-        o 96d1c37a create test2.txt
-        |
-        o 96a2c4be Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
-        "###);
+            O f777ecc9 create initial.txt
+            |\
+            | @ 98b9119d (> detached HEAD) create test3.txt
+            | |
+            | o 96a2c4be Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
+            |
+            O 62fc20d2 (master) create test1.txt
+            |
+            o 96d1c37a create test2.txt
+            |
+            o 96a2c4be Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
+            "###);
         }
     }
 
@@ -1750,7 +1770,7 @@ fn test_move_merge_commit() -> eyre::Result<()> {
             branchless: processing checkout
             O f777ecc9 create initial.txt
             |\
-            | @ 98b9119d create test3.txt
+            | @ 98b9119d (> detached HEAD) create test3.txt
             | |
             | o 96a2c4be Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
             |
@@ -1767,18 +1787,18 @@ fn test_move_merge_commit() -> eyre::Result<()> {
         {
             let (stdout, _stderr) = git.run(&["smartlog"])?;
             insta::assert_snapshot!(stdout, @r###"
-        O f777ecc9 create initial.txt
-        |\
-        | @ 98b9119d create test3.txt
-        | |
-        | o 96a2c4be Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
-        |
-        O 62fc20d2 (master) create test1.txt
-        |
-        o 96d1c37a create test2.txt
-        |
-        o 96a2c4be Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
-        "###);
+            O f777ecc9 create initial.txt
+            |\
+            | @ 98b9119d (> detached HEAD) create test3.txt
+            | |
+            | o 96a2c4be Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
+            |
+            O 62fc20d2 (master) create test1.txt
+            |
+            o 96d1c37a create test2.txt
+            |
+            o 96a2c4be Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
+            "###);
         }
     }
 
@@ -1815,6 +1835,7 @@ fn test_move_orphaned_root() -> eyre::Result<()> {
         // should be no relation between these two commits.
         insta::assert_snapshot!(stdout, @r###"
         @ da90168b (> new-root) new root
+
         :
         O 96d1c37a (master) create test2.txt
         "###);
@@ -2006,7 +2027,7 @@ fn test_move_abort_rebase_check_out_old_branch() -> eyre::Result<()> {
         let (stdout, _stderr) = git.run_with_options(
             &["move", "-d", "original", "--on-disk"],
             &GitRunOptions {
-                expected_exit_code: 1,
+                expected_exit_code: 2,
                 ..Default::default()
             },
         )?;
@@ -2049,7 +2070,7 @@ fn test_move_orig_head_no_symbolic_reference() -> eyre::Result<()> {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
         :
-        @ 96d1c37a (foo, master) create test2.txt
+        @ 96d1c37a (> detached HEAD, foo, master) create test2.txt
         "###);
     }
 
@@ -2064,7 +2085,7 @@ fn test_move_orig_head_no_symbolic_reference() -> eyre::Result<()> {
         // `foo` should be unmoved here, rather than moved to
         // `create test1.txt`.
         insta::assert_snapshot!(stdout, @r###"
-        @ f777ecc9 create initial.txt
+        @ f777ecc9 (> detached HEAD) create initial.txt
         :
         O 96d1c37a (foo, master) create test2.txt
         "###);
@@ -2123,7 +2144,7 @@ fn test_move_branch_on_merge_conflict_resolution() -> eyre::Result<()> {
     git.run_with_options(
         &["move", "-s", &test3_oid.to_string(), "--merge"],
         &GitRunOptions {
-            expected_exit_code: 1,
+            expected_exit_code: 2,
             ..Default::default()
         },
     )?;
@@ -2176,3 +2197,80 @@ fn test_move_branch_on_merge_conflict_resolution() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_move_exact() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.detach_head()?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", &test3_oid.to_string()])?;
+    git.commit_file("test5", 5)?;
+
+    // --on-disk
+    {
+        let git = git.duplicate_repo()?;
+        git.run(&[
+            "move",
+            "--on-disk",
+            "--exact",
+            "-s",
+            &test3_oid.to_string(),
+            "-d",
+            &test1_oid.to_string(),
+        ])?;
+        {
+            let (stdout, _stderr) = git.run(&["smartlog"])?;
+            insta::assert_snapshot!(stdout, @r###"
+            :
+            O 62fc20d2 create test1.txt
+            |\
+            | o 4838e49b create test3.txt
+            |
+            O 96d1c37a (master) create test2.txt
+            |\
+            | o f57e36f5 create test4.txt
+            |
+            @ d2e18e38 (> detached HEAD) create test5.txt
+            "###);
+        }
+    }
+
+    // in-memory
+    {
+        git.run(&[
+            "move",
+            "-x",
+            "-s",
+            &test3_oid.to_string(),
+            "-d",
+            &test1_oid.to_string(),
+        ])?;
+        {
+            let (stdout, _stderr) = git.run(&["smartlog"])?;
+            insta::assert_snapshot!(stdout, @r###"
+            :
+            O 62fc20d2 create test1.txt
+            |\
+            | o 4838e49b create test3.txt
+            |
+            O 96d1c37a (master) create test2.txt
+            |\
+            | o f57e36f5 create test4.txt
+            |
+            @ d2e18e38 (> detached HEAD) create test5.txt
+            "###);
+        }
+    }
+
+    Ok(())
+}
@@ -23,7 +23,7 @@ fn test_prev() -> eyre::Result<()> {
         let (stdout, _stderr) = git.run(&["prev"])?;
         insta::assert_snapshot!(stdout, @r###"
         branchless: running command: <git-executable> checkout f777ecc9b0db5ed372b2615695191a8a17f79f24
-        @ f777ecc9 create initial.txt
+        @ f777ecc9 (> detached HEAD) create initial.txt
         |
         O 62fc20d2 (master) create test1.txt
         "###);
@@ -45,7 +45,7 @@ fn test_prev() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-        @ f777ecc9 create initial.txt
+        @ f777ecc9 (> detached HEAD) create initial.txt
         |
         O 62fc20d2 (master) create test1.txt
         "###);
@@ -66,7 +66,7 @@ fn test_prev_multiple() -> eyre::Result<()> {
         let (stdout, _stderr) = git.run(&["prev", "2"])?;
         insta::assert_snapshot!(stdout, @r###"
         branchless: running command: <git-executable> checkout f777ecc9b0db5ed372b2615695191a8a17f79f24
-        @ f777ecc9 create initial.txt
+        @ f777ecc9 (> detached HEAD) create initial.txt
         :
         O 96d1c37a (master) create test2.txt
         "###);
@@ -93,7 +93,7 @@ fn test_next_multiple() -> eyre::Result<()> {
         |
         o 62fc20d2 create test1.txt
         |
-        @ 96d1c37a create test2.txt
+        @ 96d1c37a (> detached HEAD) create test2.txt
         "###);
     }
 
@@ -125,9 +125,9 @@ fn test_next_ambiguous() -> eyre::Result<()> {
         )?;
         insta::assert_snapshot!(stdout, @r###"
         Found multiple possible child commits to go to after traversing 0 children:
-          - 62fc20d2 create test1.txt (oldest)
-          - fe65c1fe create test2.txt
-          - 98b9119d create test3.txt (newest)
+          1. 62fc20d2 create test1.txt (oldest)
+          2. fe65c1fe create test2.txt
+          3. 98b9119d create test3.txt (newest)
         (Pass --oldest (-o), --newest (-n), or --interactive (-i) to select between ambiguous commits)
         "###);
     }
@@ -138,7 +138,7 @@ fn test_next_ambiguous() -> eyre::Result<()> {
         branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
         O f777ecc9 (master) create initial.txt
         |\
-        | @ 62fc20d2 create test1.txt
+        | @ 62fc20d2 (> detached HEAD) create test1.txt
         |\
         | o fe65c1fe create test2.txt
         |
@@ -157,7 +157,76 @@ fn test_next_ambiguous() -> eyre::Result<()> {
         |\
         | o fe65c1fe create test2.txt
         |
-        @ 98b9119d create test3.txt
+        @ 98b9119d (> detached HEAD) create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_next_retraces_prev() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.commit_file("test4", 4)?;
+
+    git.run(&["prev"])?;
+    {
+        let (stdout, _stderr) = git.run(&["next"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout bf0d52a607f693201512a43b6b5a70b2a275e0ad
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | o 96d1c37a create test2.txt
+        | |
+        | o 70deb1e2 create test3.txt
+        |
+        @ bf0d52a6 (> detached HEAD) create test4.txt
+        "###);
+    }
+
+    git.run(&["checkout", &test3_oid.to_string()])?;
+    git.run(&["prev", "2"])?;
+    {
+        let (stdout, _stderr) = git.run(&["next", "2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 70deb1e28791d8e7dd5a1f0c871a51b91282562f
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | o 96d1c37a create test2.txt
+        | |
+        | @ 70deb1e2 (> detached HEAD) create test3.txt
+        |
+        o bf0d52a6 create test4.txt
+        "###);
+    }
+
+    // Checking out a commit explicitly forgets the path taken.
+    git.run(&["prev", "2"])?;
+    git.run(&["branchless", "checkout", &test1_oid.to_string()])?;
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["next"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Found multiple possible child commits to go to after traversing 0 children:
+          1. 96d1c37a create test2.txt (oldest)
+          2. bf0d52a6 create test4.txt (newest)
+        (Pass --oldest (-o), --newest (-n), or --interactive (-i) to select between ambiguous commits)
         "###);
     }
 
@@ -199,7 +268,49 @@ fn test_next_ambiguous_interactive() -> eyre::Result<()> {
         |\
         | o 62fc20d2 create test1.txt
         |\
-        | @ fe65c1fe create test2.txt
+        | @ fe65c1fe (> detached HEAD) create test2.txt
+        |
+        o 98b9119d create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_next_ambiguous_numbered_prompt() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+
+    run_in_pty(
+        &git,
+        &["next"],
+        &[
+            PtyAction::WaitUntilContains("Select a commit to go to [1-3]: "),
+            PtyAction::Write("2"),
+            PtyAction::Write(CARRIAGE_RETURN),
+        ],
+    )?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |\
+        | o 62fc20d2 create test1.txt
+        |\
+        | @ fe65c1fe (> detached HEAD) create test2.txt
         |
         o 98b9119d create test3.txt
         "###);
@@ -226,7 +337,7 @@ fn test_next_on_master() -> eyre::Result<()> {
         :
         O 96d1c37a (master) create test2.txt
         |
-        @ 70deb1e2 create test3.txt
+        @ 70deb1e2 (> detached HEAD) create test3.txt
         "###);
     }
 
@@ -253,7 +364,7 @@ fn test_next_on_master2() -> eyre::Result<()> {
         |
         o 96d1c37a create test2.txt
         |
-        @ 70deb1e2 create test3.txt
+        @ 70deb1e2 (> detached HEAD) create test3.txt
         "###);
     }
 
@@ -274,12 +385,71 @@ fn test_next_no_more_children() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["next", "3"])?;
         insta::assert_snapshot!(stdout, @r###"
-        No more child commits to go to after traversing 2 children.
+        No more child commits to go to after traversing 2 children; stopping at 70deb1e2 create test3.txt
         branchless: running command: <git-executable> checkout 70deb1e28791d8e7dd5a1f0c871a51b91282562f
         :
         O 96d1c37a (master) create test2.txt
         |
-        @ 70deb1e2 create test3.txt
+        @ 70deb1e2 (> detached HEAD) create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_next_stops_at_fork() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["next", "3"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Found multiple possible child commits to go to after traversing 1 child; stopping at 62fc20d2 create test1.txt
+        (Pass --oldest (-o), --newest (-n), or --interactive (-i) to continue past ambiguous commits)
+        branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD) create test1.txt
+        |\
+        | o 96d1c37a create test2.txt
+        |
+        o 4838e49b create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_prev_stops_at_merge() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["merge", "--no-ff", "-m", "merge", &test1_oid.to_string()])?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["prev", "3"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Found multiple possible parent commits to go to after traversing 1 parent; stopping at 09f8cec2 merge
+        (Pass --oldest (-o), --newest (-n), or --interactive (-i) to continue past ambiguous commits)
+        branchless: running command: <git-executable> checkout 09f8cec2b047db19d445ac4f35a3fcc7bd2139f9
+        :
+        @ 09f8cec2 (> detached HEAD) merge
+        |
+        O da242586 (master) create test3.txt
         "###);
     }
 
@@ -315,7 +485,7 @@ fn test_checkout_pty() -> eyre::Result<()> {
         insta::assert_snapshot!(stdout, @r###"
         O f777ecc9 (master) create initial.txt
         |\
-        | @ 62fc20d2 create test1.txt
+        | @ 62fc20d2 (> detached HEAD) create test1.txt
         | |
         | o 96d1c37a create test2.txt
         |
@@ -343,7 +513,7 @@ fn test_checkout_pty() -> eyre::Result<()> {
         | |
         | o 96d1c37a create test2.txt
         |
-        @ 98b9119d create test3.txt
+        @ 98b9119d (> detached HEAD) create test3.txt
         "###);
     }
 
@@ -375,7 +545,7 @@ fn test_checkout_abort() -> eyre::Result<()> {
         |
         o 62fc20d2 create test1.txt
         |
-        @ 142901d5 create test2.txt
+        @ 142901d5 (> detached HEAD) create test2.txt
         "###);
     }
 
@@ -521,7 +691,7 @@ fn test_navigation_traverse_all_the_way() -> eyre::Result<()> {
         branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
         O f777ecc9 (master) create initial.txt
         |
-        @ 62fc20d2 create test1.txt
+        @ 62fc20d2 (> detached HEAD) create test1.txt
         |
         o 96d1c37a create test2.txt
         |
@@ -539,7 +709,74 @@ fn test_navigation_traverse_all_the_way() -> eyre::Result<()> {
         |
         o 96d1c37a create test2.txt
         |
-        @ 70deb1e2 create test3.txt
+        @ 70deb1e2 (> detached HEAD) create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_navigation_traverse_all_the_way_stops_at_fork() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", "HEAD~2"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["next", "--all"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Found multiple possible child commits to go to after traversing 1 child; stopping at 96d1c37a create test2.txt
+        (Pass --oldest (-o), --newest (-n), or --interactive (-i) to continue past ambiguous commits)
+        branchless: running command: <git-executable> checkout 96d1c37a3d4363611c49f7e52186e189a04c531f
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD) create test2.txt
+        |\
+        | o 70deb1e2 create test3.txt
+        |
+        o f57e36f5 create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["next", "--all", "--newest"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout f57e36f51563788bd8761c41226ff147e9cfa300
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |\
+        | o 70deb1e2 create test3.txt
+        |
+        @ f57e36f5 (> detached HEAD) create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["prev", "--all"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD) create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |\
+        | o 70deb1e2 create test3.txt
+        |
+        o f57e36f5 create test4.txt
         "###);
     }
 
@@ -666,12 +903,70 @@ fn test_traverse_branches_ambiguous() -> eyre::Result<()> {
         branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
         O f777ecc9 (master) create initial.txt
         |
-        @ 62fc20d2 (bar, foo) create test1.txt
+        @ 62fc20d2 (> detached HEAD, bar, foo) create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_next_to_branch() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.commit_file("test4", 4)?;
+    git.run(&["branch", "bar"])?;
+    git.run(&["checkout", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["next", "--to-branch", "foo"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout foo
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
         |
         o 96d1c37a create test2.txt
+        |\
+        | @ 70deb1e2 (> foo) create test3.txt
+        |
+        o f57e36f5 (bar) create test4.txt
         "###);
     }
 
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["next", "--to-branch", "bar"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"Branch bar is not a descendant of the current commit.
+");
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["next", "--to-branch", "nonexistent"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"Branch not found: nonexistent
+");
+    }
+
     Ok(())
 }
 
@@ -730,7 +1025,7 @@ fn test_checkout_pty_branch() -> eyre::Result<()> {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
         :
-        @ 62fc20d2 (master) create test1.txt
+        @ 62fc20d2 (> detached HEAD, master) create test1.txt
         |\
         | o 96d1c37a create test2.txt
         |
@@ -764,7 +1059,7 @@ fn test_checkout_pty_initial_query() -> eyre::Result<()> {
         insta::assert_snapshot!(stdout, @r###"
         O f777ecc9 (master) create initial.txt
         |
-        @ 62fc20d2 create test1.txt
+        @ 62fc20d2 (> detached HEAD) create test1.txt
         |
         o 96d1c37a create test2.txt
         "###);
@@ -810,7 +1105,7 @@ fn test_navigation_merge() -> eyre::Result<()> {
         M	conflicting.txt
         O f777ecc9 (master) create initial.txt
         |
-        @ 25497cb0 create conflicting.txt
+        @ 25497cb0 (> detached HEAD) create conflicting.txt
         |
         o 6dd50913 create conflicting.txt
         "###);
@@ -872,7 +1167,7 @@ fn test_navigation_force() -> eyre::Result<()> {
         branchless: running command: <git-executable> checkout 25497cb08387d7d20aa741398b73ce7f924afdb5 --force
         O f777ecc9 (master) create initial.txt
         |
-        @ 25497cb0 create conflicting.txt
+        @ 25497cb0 (> detached HEAD) create conflicting.txt
         |
         o 6dd50913 create conflicting.txt
         "###);
@@ -928,7 +1223,7 @@ fn test_navigation_checkout_flags() -> eyre::Result<()> {
             insta::assert_snapshot!(stdout, @r###"
             branchless: running command: <git-executable> checkout HEAD~2 -f
             :
-            @ 62fc20d2 create test1.txt
+            @ 62fc20d2 (> detached HEAD) create test1.txt
             :
             O 5b738c1c (master) create test1.txt
             "###);
@@ -943,7 +1238,7 @@ fn test_navigation_checkout_flags() -> eyre::Result<()> {
         branchless: running command: <git-executable> checkout HEAD~2 -m
         M	test1.txt
         :
-        @ 62fc20d2 create test1.txt
+        @ 62fc20d2 (> detached HEAD) create test1.txt
         :
         O 5b738c1c (master) create test1.txt
         "###);
@@ -967,3 +1262,136 @@ fn test_navigation_checkout_target_only() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_checkout_smartlog_index() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--show-index"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O [1] f777ecc9 (master) create initial.txt
+        |
+        o [2] 62fc20d2 create test1.txt
+        |\
+        | o [3] 96d1c37a create test2.txt
+        |
+        @ [4] 4838e49b (> detached HEAD) create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "checkout", "3"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 96d1c37a3d4363611c49f7e52186e189a04c531f
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | @ 96d1c37a (> detached HEAD) create test2.txt
+        |
+        o 4838e49b create test3.txt
+        "###);
+    }
+
+    // Indices which weren't shown are passed through to Git.
+    {
+        let (_stdout, stderr) = git.run_with_options(
+            &["branchless", "checkout", "5"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        error: pathspec '5' did not match any file(s) known to git
+        "###);
+    }
+
+    // Branches take precedence over indices.
+    git.run(&["branch", "2", "master"])?;
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "checkout", "2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 2
+        @ f777ecc9 (> 2, master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | o 96d1c37a create test2.txt
+        |
+        o 4838e49b create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_navigation_move_branch() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "foo"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["next", "--move-branch"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 96d1c37a3d4363611c49f7e52186e189a04c531f
+        branchless: running command: <git-executable> checkout foo
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        @ 96d1c37a (> foo) create test2.txt
+        |
+        o 70deb1e2 create test3.txt
+        "###);
+    }
+
+    {
+        // The branch isn't moved backwards.
+        let (stdout, _stderr) = git.run(&["prev", "--move-branch"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD) create test1.txt
+        |
+        o 96d1c37a (foo) create test2.txt
+        |
+        o 70deb1e2 create test3.txt
+        "###);
+    }
+
+    git.run(&["checkout", "foo"])?;
+    git.run(&["config", "branchless.navigation.moveBranch", "true"])?;
+    {
+        let (stdout, _stderr) = git.run(&["next"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 70deb1e28791d8e7dd5a1f0c871a51b91282562f
+        branchless: running command: <git-executable> checkout foo
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |
+        @ 70deb1e2 (> foo) create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
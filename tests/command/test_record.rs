@@ -0,0 +1,125 @@
+use branchless::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_record_all() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.write_file("test1", "updated contents\n")?;
+    git.write_file("untracked", "untracked contents\n")?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["branchless", "record", "--all", "-m", "update test1"],
+            &GitRunOptions {
+                time: 2,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> commit --all --message update test1
+        [detached HEAD 53c6c40] update test1
+         1 file changed, 1 insertion(+), 1 deletion(-)
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        @ 53c6c40c (> detached HEAD) update test1
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @"?? untracked.txt
+");
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["branchless", "record", "--all", "-m", "nothing"],
+            &GitRunOptions {
+                expected_exit_code: 3,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"There are no uncommitted or staged changes. Nothing to record.
+");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_record_interactive() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file("test1", "updated test1\n")?;
+    git.write_file("test2", "updated test2\n")?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["branchless", "record", "-m", "update test1"],
+            &GitRunOptions {
+                time: 3,
+                input: Some("y\nn\n".to_string()),
+                ..Default::default()
+            },
+        )?;
+        // The prompts of `git add --patch` vary between Git versions, so only
+        // check the result of recording.
+        let stdout = stdout
+            .lines()
+            .skip_while(|line| !line.contains("commit --message"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> commit --message update test1
+        [detached HEAD 3c8614b] update test1
+         1 file changed, 1 insertion(+), 1 deletion(-)
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |
+        @ 3c8614b1 (> detached HEAD) update test1
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "--stat", "--format=%s"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        update test1
+
+         test1.txt | 2 +-
+         1 file changed, 1 insertion(+), 1 deletion(-)
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @" M test2.txt
+");
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["branchless", "record", "-m", "update test2"],
+            &GitRunOptions {
+                input: Some("n\n".to_string()),
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        let last_line = stdout.lines().last().unwrap_or_default();
+        insta::assert_snapshot!(last_line, @"No changes were selected. Aborting record.");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use branchless::testing::make_git;
+
+#[test]
+fn test_repair_no_discrepancies() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["commit", "--amend", "-m", "amended test2"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "repair"])?;
+        insta::assert_snapshot!(stdout, @"No discrepancies found.
+");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_repair_commits_made_without_hooks() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.write_file("test2", "test2 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&[
+        "-c",
+        "core.hooksPath=/dev/null",
+        "commit",
+        "-m",
+        "create test2.txt",
+    ])?;
+    git.run(&[
+        "-c",
+        "core.hooksPath=/dev/null",
+        "commit",
+        "--amend",
+        "-m",
+        "amended test2",
+    ])?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD) create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "repair", "--dry-run"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Would recover commit: 5b61b5a0 create test2.txt
+        Would recover rewrite: 5b61b5a0 create test2.txt -> d764d46a amended test2
+        Found 2 discrepancies. Run without --dry-run to repair them.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "repair"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Recovered commit: 5b61b5a0 create test2.txt
+        Recovered rewrite: 5b61b5a0 create test2.txt -> d764d46a amended test2
+        Repaired 2 discrepancies.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD) create test1.txt
+        |
+        o d764d46a amended test2
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "repair"])?;
+        insta::assert_snapshot!(stdout, @"No discrepancies found.
+");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_repair_missing_commit() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    // Delete the commit out from under branchless.
+    git.run(&[
+        "-c",
+        "core.hooksPath=/dev/null",
+        "update-ref",
+        "-d",
+        &format!("refs/branchless/{}", test2_oid),
+    ])?;
+    let test2_oid = test2_oid.to_string();
+    std::fs::remove_file(
+        git.repo_path
+            .join(".git")
+            .join("objects")
+            .join(&test2_oid[..2])
+            .join(&test2_oid[2..]),
+    )?;
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "repair"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid missing commit: 96d1c37a3d4363611c49f7e52186e189a04c531f
+        Repaired 1 discrepancy.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "repair"])?;
+        insta::assert_snapshot!(stdout, @"No discrepancies found.
+");
+    }
+
+    Ok(())
+}
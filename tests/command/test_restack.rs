@@ -1,4 +1,4 @@
-use branchless::testing::{make_git, GitInitOptions, GitRunOptions};
+use branchless::testing::{make_git, Git, GitInitOptions, GitRunOptions};
 
 /// Remove some of the output from `git rebase`, as it seems to be
 /// non-deterministic as to whether or not it appears.
@@ -41,16 +41,16 @@ fn test_restack_amended_commit() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            O f777ecc9 (master) create initial.txt
-            |\
-            | @ 024c35ce amend test1.txt
-            |
-            x 62fc20d2 (rewritten as 024c35ce) create test1.txt
-            |
-            o 96d1c37a create test2.txt
-            |
-            o 70deb1e2 create test3.txt
-            "###);
+        O f777ecc9 (master) create initial.txt
+        |\
+        | @ 024c35ce (> detached HEAD) amend test1.txt
+        |
+        x 62fc20d2 (amended as 024c35ce) create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |
+        o 70deb1e2 create test3.txt
+        "###);
     }
 
     {
@@ -60,11 +60,14 @@ fn test_restack_amended_commit() -> eyre::Result<()> {
         branchless: running command: <git-executable> diff --quiet
         Calling Git for on-disk rebase...
         branchless: running command: <git-executable> rebase --continue
+        Restacked 2 commits:
+          - 96d1c37a create test2.txt
+          - 70deb1e2 create test3.txt
         Finished restacking commits.
         No abandoned branches to restack.
         O f777ecc9 (master) create initial.txt
         |
-        @ 024c35ce amend test1.txt
+        @ 024c35ce (> detached HEAD) amend test1.txt
         |
         o 8cd7de68 create test2.txt
         |
@@ -75,6 +78,103 @@ fn test_restack_amended_commit() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_restack_preserves_author() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["config", "branchless.restack.preserveTimestamps", "false"])?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.write_file("test2", "test2 contents\n")?;
+    git.run(&["add", "test2.txt"])?;
+    git.run(&[
+        "commit",
+        "--author",
+        "Original Author <original@example.com>",
+        "-m",
+        "create test2.txt",
+    ])?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.run(&["commit", "--amend", "-m", "amend test1.txt"])?;
+
+    git.run(&["config", "user.name", "Restacking User"])?;
+    git.run(&["config", "user.email", "restacking@example.com"])?;
+    git.run(&["restack"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&[
+            "log",
+            "-1",
+            "--format=author: %an <%ae> %ad%ncommitter: %cn <%ce>",
+            "foo",
+        ])?;
+        insta::assert_snapshot!(stdout, @r###"
+        author: Original Author <original@example.com> Thu Oct 29 12:34:56 2020 +0000
+        committer: Restacking User <restacking@example.com>
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["undo"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                input: Some("n".to_string()),
+                ..Default::default()
+            },
+        )?;
+        let stdout = stdout
+            .lines()
+            .filter(|line| line.contains("originally by"))
+            // The rewritten commit hash depends on the current time, so omit it.
+            .map(|line| &line[line.find("create").unwrap()..])
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!(stdout, @"create test2.txt (originally by Original Author <original@example.com>)");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_restack_sign_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_ssh_signing()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.set_up_ssh_signing()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.run(&["commit", "--amend", "-m", "amend test1.txt"])?;
+    git.run(&["restack", "--in-memory"])?;
+    {
+        let (parent_oid, _stderr) = git.run(&["rev-parse", "foo^"])?;
+        let (head_oid, _stderr) = git.run(&["rev-parse", "HEAD"])?;
+        assert_eq!(parent_oid, head_oid);
+    }
+    assert!(git.is_commit_signed("foo")?);
+
+    git.run(&["commit", "--amend", "-m", "amend test1.txt again"])?;
+    git.run(&["restack", "--in-memory", "--no-gpg-sign"])?;
+    assert!(!git.is_commit_signed("foo")?);
+
+    git.run(&["commit", "--amend", "-m", "amend test1.txt once more"])?;
+    git.run(&["restack", "--on-disk", "--no-gpg-sign"])?;
+    assert!(!git.is_commit_signed("foo")?);
+
+    Ok(())
+}
+
 #[test]
 fn test_restack_consecutive_rewrites() -> eyre::Result<()> {
     let git = make_git()?;
@@ -101,11 +201,14 @@ fn test_restack_consecutive_rewrites() -> eyre::Result<()> {
         branchless: running command: <git-executable> diff --quiet
         Calling Git for on-disk rebase...
         branchless: running command: <git-executable> rebase --continue
+        Restacked 2 commits:
+          - 96d1c37a create test2.txt
+          - 70deb1e2 create test3.txt
         Finished restacking commits.
         No abandoned branches to restack.
         O f777ecc9 (master) create initial.txt
         |
-        @ 662b451f amend test1.txt v2
+        @ 662b451f (> detached HEAD) amend test1.txt v2
         |
         o 8e9bbde3 create test2.txt
         |
@@ -135,7 +238,7 @@ fn test_move_abandoned_branch() -> eyre::Result<()> {
         branchless: processing 1 update: branch master
         Finished restacking branches.
         :
-        @ 662b451f (master) amend test1.txt v2
+        @ 662b451f (> detached HEAD, master) amend test1.txt v2
         "###);
     }
 
@@ -160,12 +263,12 @@ fn test_amended_initial_commit() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            @ 9a9f929a new initial commit
+        @ 9a9f929a (> detached HEAD) new initial commit
 
-            X f777ecc9 (rewritten as 9a9f929a) create initial.txt
-            |
-            O 62fc20d2 (master) create test1.txt
-            "###);
+        X f777ecc9 (amended as 9a9f929a) create initial.txt
+        |
+        O 62fc20d2 (master) create test1.txt
+        "###);
     }
 
     {
@@ -175,9 +278,11 @@ fn test_amended_initial_commit() -> eyre::Result<()> {
         branchless: running command: <git-executable> diff --quiet
         Calling Git for on-disk rebase...
         branchless: running command: <git-executable> rebase --continue
+        Restacked 1 commit:
+          - 62fc20d2 create test1.txt
         Finished restacking commits.
         No abandoned branches to restack.
-        @ 9a9f929a new initial commit
+        @ 9a9f929a (> detached HEAD) new initial commit
         |
         O 6d85943b (master) create test1.txt
         "###);
@@ -209,10 +314,12 @@ fn test_restack_amended_master() -> eyre::Result<()> {
         branchless: running command: <git-executable> diff --quiet
         Calling Git for on-disk rebase...
         branchless: running command: <git-executable> rebase --continue
+        Restacked 1 commit:
+          - 96d1c37a create test2.txt
         Finished restacking commits.
         No abandoned branches to restack.
         :
-        @ ae94dc2a amended test1
+        @ ae94dc2a (> detached HEAD) amended test1
         |
         O 51452b55 (master) create test2.txt
         "###);
@@ -239,7 +346,7 @@ fn test_restack_aborts_during_rebase_conflict() -> eyre::Result<()> {
         let (stdout, _stderr) = git.run_with_options(
             &["restack"],
             &GitRunOptions {
-                expected_exit_code: 1,
+                expected_exit_code: 2,
                 ..Default::default()
             },
         )?;
@@ -255,7 +362,7 @@ fn test_restack_aborts_during_rebase_conflict() -> eyre::Result<()> {
         let (stdout, _stderr) = git.run_with_options(
             &["restack", "--merge"],
             &GitRunOptions {
-                expected_exit_code: 1,
+                expected_exit_code: 2,
                 ..Default::default()
             },
         )?;
@@ -270,7 +377,7 @@ fn test_restack_aborts_during_rebase_conflict() -> eyre::Result<()> {
         Calling Git for on-disk rebase...
         branchless: running command: <git-executable> rebase --continue
         CONFLICT (add/add): Merge conflict in test2.txt
-        Error: Could not restack commits (exit code 1).
+        Error: Could not restack commits (exit code 2).
         You can resolve the error and try running `git restack` again.
         "###);
     }
@@ -305,11 +412,14 @@ fn test_restack_multiple_amended() -> eyre::Result<()> {
         branchless: running command: <git-executable> diff --quiet
         Calling Git for on-disk rebase...
         branchless: running command: <git-executable> rebase --continue
+        Restacked 2 commits:
+          - f10316a7 test3 amended
+          - 355e173b create test4.txt
         Finished restacking commits.
         No abandoned branches to restack.
         O f777ecc9 (master) create initial.txt
         |
-        @ 62fc20d2 create test1.txt
+        @ 62fc20d2 (> detached HEAD) create test1.txt
         |
         o 22f39285 test2 amended
         |
@@ -351,15 +461,15 @@ fn test_restack_single_of_many_commits() -> eyre::Result<()> {
         :
         O 62fc20d2 (master) create test1.txt
         |\
-        | @ 3bd716d5 updated test4
+        | @ 3bd716d5 (> detached HEAD) updated test4
         |\
         | o 7357d2b7 updated test2
         |\
-        | x 96d1c37a (rewritten as 7357d2b7) create test2.txt
+        | x 96d1c37a (amended as 7357d2b7) create test2.txt
         | |
         | o 70deb1e2 create test3.txt
         |
-        x bf0d52a6 (rewritten as 3bd716d5) create test4.txt
+        x bf0d52a6 (amended as 3bd716d5) create test4.txt
         |
         o 848121cb create test5.txt
         "###);
@@ -382,13 +492,13 @@ fn test_restack_single_of_many_commits() -> eyre::Result<()> {
         :
         O 62fc20d2 (master) create test1.txt
         |\
-        | @ 3bd716d5 updated test4
+        | @ 3bd716d5 (> detached HEAD) updated test4
         |\
         | o 7357d2b7 updated test2
         | |
         | o 944f78da create test3.txt
         |
-        x bf0d52a6 (rewritten as 3bd716d5) create test4.txt
+        x bf0d52a6 (amended as 3bd716d5) create test4.txt
         |
         o 848121cb create test5.txt
         Successfully rebased and updated detached HEAD.
@@ -397,18 +507,20 @@ fn test_restack_single_of_many_commits() -> eyre::Result<()> {
         branchless: running command: <git-executable> diff --quiet
         Calling Git for on-disk rebase...
         branchless: running command: <git-executable> rebase --continue
+        Restacked 1 commit:
+          - 70deb1e2 create test3.txt
         Finished restacking commits.
         No abandoned branches to restack.
         :
         O 62fc20d2 (master) create test1.txt
         |\
-        | @ 3bd716d5 updated test4
+        | @ 3bd716d5 (> detached HEAD) updated test4
         |\
         | o 7357d2b7 updated test2
         | |
         | o 944f78da create test3.txt
         |
-        x bf0d52a6 (rewritten as 3bd716d5) create test4.txt
+        x bf0d52a6 (amended as 3bd716d5) create test4.txt
         |
         o 848121cb create test5.txt
         "###);
@@ -442,7 +554,7 @@ fn test_restack_unobserved_commit() -> eyre::Result<()> {
         |
         o 96d1c37a (foo) create test2.txt
         |
-        @ 70deb1e2 create test3.txt
+        @ 70deb1e2 (> detached HEAD) create test3.txt
         "###);
     }
 
@@ -461,7 +573,13 @@ fn test_restack_unobserved_commit() -> eyre::Result<()> {
     }
 
     {
-        let (stdout, _stderr) = git.run(&["restack"])?;
+        let (stdout, _stderr) = git.run_with_options(
+            &["restack"],
+            &GitRunOptions {
+                expected_exit_code: 3,
+                ..Default::default()
+            },
+        )?;
         insta::assert_snapshot!(stdout, @r###"
         No abandoned commits to restack.
         No abandoned branches to restack.
@@ -497,6 +615,8 @@ fn test_restack_checked_out_branch() -> eyre::Result<()> {
         branchless: processing 1 rewritten commit
         branchless: running command: <git-executable> checkout foo
         In-memory rebase succeeded.
+        Restacked 1 commit:
+          - 96d1c37a create test2.txt
         Finished restacking commits.
         No abandoned branches to restack.
         :
@@ -506,3 +626,373 @@ fn test_restack_checked_out_branch() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_restack_branch_subtree() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.run(&["commit", "--amend", "-m", "updated test1"])?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", &test3_oid.to_string()])?;
+    git.run(&["commit", "--amend", "-m", "updated test3"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["restack", "foo"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/1] Committed as: 59eba03f create test2.txt
+        branchless: processing 1 rewritten commit
+        In-memory rebase succeeded.
+        Restacked 1 commit:
+          - 96d1c37a create test2.txt
+        Finished restacking commits.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |\
+        | @ 54f9c88a (> detached HEAD) updated test3
+        |\
+        | o 6aca6841 (foo) updated test1
+        | |
+        | o 59eba03f create test2.txt
+        |
+        x 98b9119d (amended as 54f9c88a) create test3.txt
+        |
+        o 2b633ed7 create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_restack_dry_run() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.write_file("test2", "conflicting contents")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "--amend", "-m", "updated test1"])?;
+
+    let (smartlog_before, _stderr) = git.run(&["smartlog"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["restack", "--dry-run"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Would restack 2 commits:
+          - 96d1c37a create test2.txt
+          - 70deb1e2 create test3.txt
+        Merge conflict expected when restacking 96d1c37a create test2.txt onto ff74d285 updated test1
+        Before:
+        O f777ecc9 (master) create initial.txt
+        |\
+        | @ ff74d285 (> detached HEAD) updated test1
+        |
+        x 62fc20d2 create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |
+        o 70deb1e2 create test3.txt
+        After (restacked commits will have new hashes):
+        O f777ecc9 (master) create initial.txt
+        |
+        @ ff74d285 (> detached HEAD) updated test1
+        |
+        o 96d1c37a create test2.txt
+        |
+        o 70deb1e2 create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        assert_eq!(stdout, smartlog_before);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_restack_quiet() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.run(&["commit", "--amend", "-m", "amend test1.txt"])?;
+
+    {
+        let (stdout, stderr) = git.run(&["restack", "--in-memory", "--quiet"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_restack_verbose() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.run(&["commit", "--amend", "-m", "amend test1.txt"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["restack", "--in-memory", "--verbose"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/1] Applying commit onto 024c35ce32dae6b12e981963465ee8a62b7eff9b: 96d1c37a create test2.txt
+        [1/1] Committed as: 8cd7de68 create test2.txt
+        Moved branch foo from 96d1c37a3d4363611c49f7e52186e189a04c531f to 8cd7de680cafaba911d09f430d2bafb1169d6e65
+        branchless: processing 1 update: branch foo
+        branchless: processing 1 rewritten commit
+        In-memory rebase succeeded.
+        Restacked 1 commit:
+          - 96d1c37a create test2.txt
+        Finished restacking commits.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 024c35ce (> detached HEAD) amend test1.txt
+        |
+        o 8cd7de68 (foo) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_restack_exit_codes() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["restack"],
+            &GitRunOptions {
+                expected_exit_code: 3,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        No abandoned commits to restack.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD) create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["restack", "nonexistent"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Commit not found: nonexistent
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) = git.run_with_options(
+            &["restack", "--nonexistent-flag"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        error: Found argument '--nonexistent-flag' which wasn't expected, or isn't valid in this context
+
+        	If you tried to supply `--nonexistent-flag` as a value rather than a flag, use `-- --nonexistent-flag`
+
+        USAGE:
+            git-branchless restack [OPTIONS] [COMMITS]...
+
+        For more information try --help
+        "###);
+    }
+
+    Ok(())
+}
+
+/// Make a stack in which the second commit becomes empty when restacked,
+/// because its changes were amended into the first commit.
+fn make_stack_with_emptied_commit(git: &Git) -> eyre::Result<()> {
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "HEAD~2"])?;
+    git.write_file("test2", "test2 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "--amend", "-m", "amended test1"])?;
+    Ok(())
+}
+
+#[test]
+fn test_restack_empty_commits() -> eyre::Result<()> {
+    {
+        let git = make_git()?;
+        if !git.supports_committer_date_is_author_date()? {
+            return Ok(());
+        }
+        make_stack_with_emptied_commit(&git)?;
+        git.run(&["config", "branchless.restack.emptyCommits", "keep"])?;
+
+        let (stdout, _stderr) = git.run(&["restack"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/2] Committed as: b7235074 create test2.txt
+        [2/2] Committed as: 05c29717 create test3.txt
+        branchless: processing 2 rewritten commits
+        In-memory rebase succeeded.
+        Restacked 2 commits:
+          - 96d1c37a create test2.txt
+          - 70deb1e2 create test3.txt
+        Finished restacking commits.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ cb079b22 (> detached HEAD) amended test1
+        |
+        o b7235074 create test2.txt
+        |
+        o 05c29717 create test3.txt
+        "###);
+    }
+
+    {
+        let git = make_git()?;
+        make_stack_with_emptied_commit(&git)?;
+        git.run(&["config", "branchless.restack.emptyCommits", "hide"])?;
+
+        let (stdout, _stderr) = git.run(&["restack"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/2] Skipped now-empty commit: b7235074 create test2.txt
+        [2/2] Committed as: fab7601b create test3.txt
+        branchless: processing 2 rewritten commits
+        In-memory rebase succeeded.
+        Restacked 2 commits:
+          - 96d1c37a create test2.txt
+          - 70deb1e2 create test3.txt
+        Finished restacking commits.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ cb079b22 (> detached HEAD) amended test1
+        |
+        o fab7601b create test3.txt
+        "###);
+        let (stdout, _stderr) = git.run(&["smartlog", "--hidden"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |\
+        | @ cb079b22 (> detached HEAD) amended test1
+        | |
+        | o fab7601b create test3.txt
+        |
+        x 62fc20d2 (amended as cb079b22) create test1.txt
+        |
+        x 96d1c37a (became empty) create test2.txt
+        |
+        x 70deb1e2 (rebased as fab7601b) create test3.txt
+        "###);
+    }
+
+    {
+        let git = make_git()?;
+        make_stack_with_emptied_commit(&git)?;
+        git.run(&["config", "branchless.restack.emptyCommits", "hide"])?;
+
+        let (stdout, _stderr) = git.run(&["restack", "--on-disk"])?;
+        let stdout = remove_rebase_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> diff --quiet
+        Calling Git for on-disk rebase...
+        branchless: running command: <git-executable> rebase --continue
+        Skipped now-empty commit: b7235074 create test2.txt
+        Restacked 2 commits:
+          - 96d1c37a create test2.txt
+          - 70deb1e2 create test3.txt
+        Finished restacking commits.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ cb079b22 (> detached HEAD) amended test1
+        |
+        o fab7601b create test3.txt
+        "###);
+        let (stdout, _stderr) = git.run(&["smartlog", "--hidden"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |\
+        | @ cb079b22 (> detached HEAD) amended test1
+        | |\
+        | | x b7235074 (became empty) create test2.txt
+        | |
+        | o fab7601b create test3.txt
+        |
+        x 62fc20d2 (amended as cb079b22) create test1.txt
+        |
+        x 96d1c37a (became empty) create test2.txt
+        |
+        x 70deb1e2 (rebased as fab7601b) create test3.txt
+        "###);
+    }
+
+    {
+        let git = make_git()?;
+        make_stack_with_emptied_commit(&git)?;
+        git.run(&["config", "branchless.restack.emptyCommits", "invalid"])?;
+
+        let (_stdout, stderr) = git.run_with_options(
+            &["restack"],
+            &GitRunOptions {
+                expected_exit_code: 101,
+                ..Default::default()
+            },
+        )?;
+        assert!(stderr.contains("Invalid value for branchless.restack.emptyCommits"));
+    }
+
+    Ok(())
+}
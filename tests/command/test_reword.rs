@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use branchless::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_reword_head() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["reword", "-m", "foo", "-m", "bar"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        No abandoned commits to restack.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 34ae21e8 (> detached HEAD) foo
+        Reworded 1 commit.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "-1", "--format=%B"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        foo
+
+        bar
+
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reword_with_descendants() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["branch", "foo"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&[
+            "reword",
+            &test1_oid.to_string(),
+            &test2_oid.to_string(),
+            "-m",
+            "reworded message",
+        ])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/2] Committed as: 04875c5a reworded message
+        [2/2] Committed as: 3196c6e2 create test3.txt
+        branchless: processing 1 update: branch foo
+        branchless: processing 2 rewritten commits
+        branchless: running command: <git-executable> checkout 3196c6e24059c4fea311287d80d511a703607607
+        In-memory rebase succeeded.
+        Restacked 2 commits:
+          - 872ae233 reworded message
+          - 70deb1e2 create test3.txt
+        Finished restacking commits.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |
+        o 5a4945f2 reworded message
+        |
+        o 04875c5a reworded message
+        |
+        @ 3196c6e2 (> detached HEAD, foo) create test3.txt
+        Reworded 2 commits.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 5a4945f2 reworded message
+        |
+        o 04875c5a reworded message
+        |
+        @ 3196c6e2 (> detached HEAD, foo) create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reword_with_editor() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["reword", "HEAD^"],
+            &GitRunOptions {
+                env: {
+                    let mut env = HashMap::new();
+                    env.insert(
+                        "GIT_EDITOR".to_string(),
+                        "sed -i.bak 's/create/edited/'".to_string(),
+                    );
+                    env
+                },
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/1] Committed as: cb12861f create test2.txt
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout cb12861fa175a92a77fee8f86df5cd5592f28345
+        In-memory rebase succeeded.
+        Restacked 1 commit:
+          - 96d1c37a create test2.txt
+        Finished restacking commits.
+        No abandoned branches to restack.
+        O f777ecc9 (master) create initial.txt
+        |
+        o a278073f edited test1.txt
+        |
+        @ cb12861f (> detached HEAD) create test2.txt
+        Reworded 1 commit.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reword_empty_message() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["reword", "-m", ""],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Aborting reword of 62fc20d2 create test1.txt due to empty commit message.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reword_sign_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_ssh_signing()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.set_up_ssh_signing()?;
+
+    git.run(&["branchless", "reword", "-m", "reworded test1.txt"])?;
+    assert!(git.is_commit_signed("HEAD")?);
+    assert!(git.is_commit_signed("foo")?);
+
+    git.run(&[
+        "branchless",
+        "reword",
+        "--no-gpg-sign",
+        "-m",
+        "reworded test1.txt again",
+    ])?;
+    assert!(!git.is_commit_signed("HEAD")?);
+    assert!(!git.is_commit_signed("foo")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_reword_public_commit() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["reword", "master", "-m", "reworded"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Refusing to reword public commit: f777ecc9 create initial.txt
+        To reword public commits anyway, run again with --force.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["reword", "master", "-m", "reworded", "--force"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/1] Committed as: e8b0d184 create test1.txt
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout e8b0d184f306d26f3bcb068797cdebddd0844382
+        In-memory rebase succeeded.
+        Restacked 1 commit:
+          - 62fc20d2 create test1.txt
+        Finished restacking commits.
+        branchless: processing 1 update: branch master
+        Finished restacking branches.
+        O 45fc421a (master) reworded
+        |
+        @ e8b0d184 (> detached HEAD) create test1.txt
+        Reworded 1 commit.
+        "###);
+    }
+
+    Ok(())
+}
@@ -1,6 +1,16 @@
+use std::ffi::OsString;
+use std::time::SystemTime;
+
+use branchless::commands::smartlog::{compute_smartlog, SmartlogEdge, SmartlogOptions};
+use branchless::core::effects::Effects;
+use branchless::core::eventlog::{Event, EventLogDb};
+use branchless::core::formatting::Glyphs;
+use branchless::git::{MaybeZeroOid, NonZeroOid};
 use branchless::testing::{
-    make_git, make_git_with_remote_repo, GitInitOptions, GitRunOptions, GitWrapperWithRemoteRepo,
+    make_git, make_git_with_remote_repo, Git, GitInitOptions, GitRunOptions,
+    GitWrapperWithRemoteRepo,
 };
+use itertools::Itertools;
 
 #[test]
 fn test_init_smartlog() -> eyre::Result<()> {
@@ -17,6 +27,44 @@ fn test_init_smartlog() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_smartlog_detached_head() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 62fc20d2 (> master) create test1.txt
+        "###);
+    }
+
+    git.detach_head()?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 62fc20d2 (> detached HEAD, master) create test1.txt
+        "###);
+    }
+
+    git.commit_file("test2", 2)?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 (master) create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_show_reachable_commit() -> eyre::Result<()> {
     let git = make_git()?;
@@ -77,12 +125,12 @@ fn test_rebase() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            O f777ecc9 (master) create initial.txt
-            |
-            o 62fc20d2 (test1) create test1.txt
-            |
-            @ f8d9985b create test2.txt
-            "###);
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 (test1) create test1.txt
+        |
+        @ f8d9985b (> detached HEAD) create test2.txt
+        "###);
     }
 
     Ok(())
@@ -116,15 +164,8 @@ fn test_merge_commit() -> eyre::Result<()> {
     git.run(&["checkout", "-b", "test1", "master"])?;
     git.commit_file("test1", 1)?;
     git.run(&["checkout", "-b", "test2and3", "master"])?;
-    git.commit_file("test2", 2)?;
-    git.commit_file("test3", 3)?;
-    git.run_with_options(
-        &["merge", "test1"],
-        &GitRunOptions {
-            time: 4,
-            ..Default::default()
-        },
-    )?;
+    git.commit_files(&[("test2", 2), ("test3", 3)])?;
+    git.merge_branch("test1", 4)?;
 
     {
         // Rendering here is arbitrary and open to change.
@@ -147,6 +188,28 @@ fn test_merge_commit() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_merge_fast_forward() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["checkout", "-b", "test1", "master"])?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "-b", "test2", "master"])?;
+    git.fast_forward_branch("test1")?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> test2, test1) create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_rebase_conflict() -> eyre::Result<()> {
     let git = make_git()?;
@@ -198,14 +261,14 @@ fn test_non_adjacent_commits() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            O f777ecc9 create initial.txt
-            |\
-            : o 62fc20d2 create test1.txt
-            :
-            O 02067177 (master) create test3.txt
-            |
-            @ 8e62740b create test4.txt
-            "###);
+        O f777ecc9 create initial.txt
+        |\
+        : o 62fc20d2 create test1.txt
+        :
+        O 02067177 (master) create test3.txt
+        |
+        @ 8e62740b (> detached HEAD) create test4.txt
+        "###);
     }
 
     Ok(())
@@ -228,16 +291,16 @@ fn test_non_adjacent_commits2() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            O f777ecc9 create initial.txt
-            |\
-            : o 62fc20d2 create test1.txt
-            : |
-            : o 96d1c37a create test2.txt
-            :
-            O 2b633ed7 (master) create test4.txt
-            |
-            @ 13932989 create test5.txt
-            "###);
+        O f777ecc9 create initial.txt
+        |\
+        : o 62fc20d2 create test1.txt
+        : |
+        : o 96d1c37a create test2.txt
+        :
+        O 2b633ed7 (master) create test4.txt
+        |
+        @ 13932989 (> detached HEAD) create test5.txt
+        "###);
     }
 
     Ok(())
@@ -256,8 +319,7 @@ fn test_non_adjacent_commits3() -> eyre::Result<()> {
     git.detach_head()?;
     git.commit_file("test4", 4)?;
     git.run(&["checkout", "master"])?;
-    git.commit_file("test5", 5)?;
-    git.commit_file("test6", 6)?;
+    git.commit_files(&[("test5", 5), ("test6", 6)])?;
 
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
@@ -292,11 +354,11 @@ fn test_custom_main_branch() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            :
-            O 62fc20d2 (main) create test1.txt
-            |
-            @ 96d1c37a create test2.txt
-            "###);
+        :
+        O 62fc20d2 (main) create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD) create test2.txt
+        "###);
     }
 
     Ok(())
@@ -331,7 +393,7 @@ fn test_main_remote_branch() -> eyre::Result<()> {
         let (stdout, _stderr) = cloned_repo.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
         :
-        @ 62fc20d2 (remote origin/master) create test1.txt
+        @ 62fc20d2 (> detached HEAD, remote origin/master) create test1.txt
         "###);
     }
 
@@ -344,7 +406,7 @@ fn test_main_remote_branch() -> eyre::Result<()> {
         let (stdout, _stderr) = cloned_repo.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
         :
-        @ 62fc20d2 create test1.txt
+        @ 62fc20d2 (> detached HEAD) create test1.txt
         |
         O 96d1c37a (remote origin/master) create test2.txt
         "###);
@@ -353,6 +415,46 @@ fn test_main_remote_branch() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_main_remote_branch_fallback() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    {
+        original_repo.init_repo()?;
+        original_repo.commit_file("test1", 1)?;
+        original_repo.run(&[
+            "clone",
+            original_repo.repo_path.to_str().unwrap(),
+            cloned_repo.repo_path.to_str().unwrap(),
+        ])?;
+    }
+
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+    cloned_repo.detach_head()?;
+    cloned_repo.run(&["config", "branchless.core.mainBranch", "origin/master"])?;
+    cloned_repo.run(&["config", "branchless.core.mainBranchFallback", "master"])?;
+    cloned_repo.run(&["branch", "-d", "-r", "origin/master"])?;
+    {
+        let (stdout, stderr) = cloned_repo.run(&["smartlog"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: warning: main branch "origin/master" could not be found, falling back to local branch "master"
+        "###);
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 62fc20d2 (> detached HEAD, master) create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_show_rewritten_commit_hash() -> eyre::Result<()> {
     let git = make_git()?;
@@ -367,14 +469,14 @@ fn test_show_rewritten_commit_hash() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            O f777ecc9 create initial.txt
-            |\
-            | @ 2ebe0950 test1 version 2
-            |
-            X 62fc20d2 (rewritten as 2ebe0950) create test1.txt
-            |
-            O 96d1c37a (master) create test2.txt
-            "###);
+        O f777ecc9 create initial.txt
+        |\
+        | @ 2ebe0950 (> detached HEAD) test1 version 2
+        |
+        X 62fc20d2 (amended as 2ebe0950) create test1.txt
+        |
+        O 96d1c37a (master) create test2.txt
+        "###);
     }
 
     Ok(())
@@ -401,6 +503,65 @@ fn test_smartlog_orphaned_root() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_smartlog_multiple_orphaned_roots() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+
+    git.run(&["checkout", "--orphan", "root1"])?;
+    git.run(&["rm", "-rf", "."])?;
+    git.commit_file("test3", 3)?;
+    git.detach_head()?;
+    git.commit_file("test4", 4)?;
+
+    git.run(&["checkout", "--orphan", "root2"])?;
+    git.run(&["rm", "-rf", "."])?;
+    git.commit_file("test5", 5)?;
+    git.commit_file("test6", 6)?;
+
+    {
+        let (stdout, stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 (master) create test1.txt
+        |
+        o 96d1c37a create test2.txt
+
+        o 1d512a36 (root1) create test3.txt
+        |
+        o 59ff32c1 create test4.txt
+
+        :
+        @ 406d2264 (> root2) create test6.txt
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.run(&["smartlog", "--reverse"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        @ 406d2264 (> root2) create test6.txt
+        :
+
+        o 59ff32c1 create test4.txt
+        |
+        o 1d512a36 (root1) create test3.txt
+
+        o 96d1c37a create test2.txt
+        |
+        O 62fc20d2 (master) create test1.txt
+        :
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_show_hidden_commits() -> eyre::Result<()> {
     let git = make_git()?;
@@ -418,11 +579,11 @@ fn test_show_hidden_commits() -> eyre::Result<()> {
         insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
         :
-        @ 62fc20d2 (master) create test1.txt
+        @ 62fc20d2 (> detached HEAD, master) create test1.txt
         |\
         | x cb8137ad (manually hidden) amended test2
         |
-        x 96d1c37a (rewritten as cb8137ad) create test2.txt
+        x 96d1c37a (amended as cb8137ad) create test2.txt
         "###);
     }
 
@@ -499,6 +660,144 @@ fn test_show_only_branches() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_smartlog_hide_branches() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "bar"])?;
+    git.run(&["checkout", "-b", "baz"])?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--hide-branches"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |
+        @ 70deb1e2 (> baz) create test3.txt
+        "###);
+    }
+
+    {
+        git.run(&["checkout", "--detach"])?;
+        let (stdout, _stderr) = git.run(&["smartlog", "--hide-branches"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |
+        @ 70deb1e2 (> detached HEAD) create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_show_remote_status() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    original_repo.init_repo()?;
+    original_repo.commit_file("test1", 1)?;
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+    cloned_repo.run(&["config", "branchless.smartlog.showRemoteStatus", "true"])?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 62fc20d2 (> master, remote origin/master) create test1.txt
+        "###);
+    }
+
+    original_repo.commit_file("test2", 2)?;
+    cloned_repo.run(&["fetch"])?;
+    cloned_repo.commit_file("test3", 3)?;
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 create test1.txt
+        |\
+        | @ 4838e49b (> master +1-1) create test3.txt
+        |
+        O 96d1c37a (remote origin/master) create test2.txt
+        "###);
+    }
+
+    {
+        // Branches without an upstream aren't annotated.
+        cloned_repo.run(&["branch", "foo"])?;
+        let (stdout, _stderr) = cloned_repo.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 create test1.txt
+        |\
+        | @ 4838e49b (> master +1-1, foo) create test3.txt
+        |
+        O 96d1c37a (remote origin/master) create test2.txt
+        "###);
+    }
+
+    {
+        cloned_repo.run(&["config", "branchless.smartlog.showRemoteStatus", "false"])?;
+        let (stdout, _stderr) = cloned_repo.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 create test1.txt
+        |\
+        | @ 4838e49b (> master, foo) create test3.txt
+        |
+        O 96d1c37a (remote origin/master) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_pager_not_used_without_terminal() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    // If the pager were invoked, the smartlog would not be printed.
+    git.run(&["config", "branchless.smartlog.pager", "true"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 62fc20d2 (> master) create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--no-pager"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 62fc20d2 (> master) create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_active_non_head_main_branch_commit() -> eyre::Result<()> {
     let GitWrapperWithRemoteRepo {
@@ -548,3 +847,2104 @@ fn test_active_non_head_main_branch_commit() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_smartlog_reverse() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test4", 4)?;
+    git.commit_file("test5", 5)?;
+    git.run(&["checkout", "HEAD~"])?;
+    git.commit_file("test6", 6)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 create test1.txt
+        |\
+        | o 96d1c37a create test2.txt
+        | |
+        | o 70deb1e2 create test3.txt
+        |
+        O bf0d52a6 create test4.txt
+        |\
+        | @ 275043d3 (> detached HEAD) create test6.txt
+        |
+        O 848121cb (master) create test5.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--reverse"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O 848121cb (master) create test5.txt
+        |
+        | @ 275043d3 (> detached HEAD) create test6.txt
+        |/
+        O bf0d52a6 create test4.txt
+        |
+        | o 70deb1e2 create test3.txt
+        | |
+        | o 96d1c37a create test2.txt
+        |/
+        O 62fc20d2 create test1.txt
+        :
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_sibling_order() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD~"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "HEAD~"])?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", "HEAD~"])?;
+    git.run(&["checkout", "HEAD@{3}"])?;
+    git.commit_file("test5", 5)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | o 96d1c37a create test2.txt
+        |\
+        | o 4838e49b create test3.txt
+        | |
+        | @ b1f9efa0 (> detached HEAD) create test5.txt
+        |
+        o bf0d52a6 create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--date-order"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | o bf0d52a6 create test4.txt
+        |\
+        | o 4838e49b create test3.txt
+        | |
+        | @ b1f9efa0 (> detached HEAD) create test5.txt
+        |
+        o 96d1c37a create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--topo-order"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | o 4838e49b create test3.txt
+        | |
+        | @ b1f9efa0 (> detached HEAD) create test5.txt
+        |\
+        | o bf0d52a6 create test4.txt
+        |
+        o 96d1c37a create test2.txt
+        "###);
+    }
+
+    git.run(&["config", "branchless.smartlog.order", "topo"])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | o 4838e49b create test3.txt
+        | |
+        | @ b1f9efa0 (> detached HEAD) create test5.txt
+        |\
+        | o bf0d52a6 create test4.txt
+        |
+        o 96d1c37a create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_subject_trailer() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.write_file("test1", "test1 contents")?;
+    git.run(&["add", "."])?;
+    git.run(&[
+        "commit",
+        "-m",
+        "ABC-123\n\nTitle: Fix the frobnicator\nChange-Id: I1234",
+    ])?;
+    git.commit_file("test2", 2)?;
+
+    git.run(&["config", "branchless.smartlog.subjectTrailer", "title"])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o a47a94cd Fix the frobnicator
+        |
+        @ 67a9660c (> detached HEAD) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_abbrev() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    git.run(&["config", "core.abbrev", "12"])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9b0db (master) create initial.txt
+        |
+        @ 62fc20d2a290 (> detached HEAD) create test1.txt
+        "###);
+    }
+
+    git.run(&["config", "branchless.smartlog.abbrev", "auto"])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        @ 62fc20d (> detached HEAD) create test1.txt
+        "###);
+    }
+
+    git.run(&["config", "branchless.smartlog.abbrev", "no"])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9b0db5ed372b2615695191a8a17f79f24 (master) create initial.txt
+        |
+        @ 62fc20d2a290daea0d52bdc2ed2ad4be6491010e (> detached HEAD) create test1.txt
+        "###);
+    }
+
+    git.run(&["config", "--unset", "branchless.smartlog.abbrev"])?;
+    git.run(&["commit", "--amend", "-m", "amended test1"])?;
+    {
+        // The hashes in the explanations of hidden commits are abbreviated
+        // in the same way.
+        let (stdout, _stderr) = git.run(&["smartlog", "--hidden"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9b0db (master) create initial.txt
+        |\
+        | @ ae94dc2a748b (> detached HEAD) amended test1
+        |
+        x 62fc20d2a290 (amended as ae94dc2a748b) create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_hidden_reasons() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", &test2_oid.to_string()])?;
+    git.run(&["commit", "--amend", "-m", "amended test2"])?;
+    git.run(&["restack"])?;
+    git.run(&["hide", "-m", "not needed", "HEAD~"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--hidden"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        x 62fc20d2 (manually hidden: not needed) create test1.txt
+        |\
+        | @ cb8137ad (> detached HEAD) amended test2
+        | |
+        | o 62c4b433 create test3.txt
+        | |
+        | o 204c6e4f create test4.txt
+        |
+        x 96d1c37a (amended as cb8137ad) create test2.txt
+        |
+        x 70deb1e2 (rebased as 62c4b433) create test3.txt
+        |
+        x 355e173b (rebased as 204c6e4f) create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_json() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["branch", "foo"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["commit", "--amend", "-m", "amended test2"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--hidden"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 (foo) create test1.txt
+        |\
+        | @ cb8137ad (> detached HEAD) amended test2
+        |
+        x 96d1c37a (amended as cb8137ad) create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--hidden", "--format", "json"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        [
+          {
+            "oid": "f777ecc9b0db5ed372b2615695191a8a17f79f24",
+            "short_oid": "f777ecc9",
+            "subject": "create initial.txt",
+            "branches": [
+              "refs/heads/master"
+            ],
+            "is_head": false,
+            "is_main": true,
+            "is_hidden": false,
+            "parents": [],
+            "omitted_parents": [],
+            "rewritten_as": null
+          },
+          {
+            "oid": "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+            "short_oid": "62fc20d2",
+            "subject": "create test1.txt",
+            "branches": [
+              "refs/heads/foo"
+            ],
+            "is_head": false,
+            "is_main": false,
+            "is_hidden": false,
+            "parents": [
+              "f777ecc9b0db5ed372b2615695191a8a17f79f24"
+            ],
+            "omitted_parents": [],
+            "rewritten_as": null
+          },
+          {
+            "oid": "cb8137adb1d2a166d27eeaf6bfc39a374748852c",
+            "short_oid": "cb8137ad",
+            "subject": "amended test2",
+            "branches": [],
+            "is_head": true,
+            "is_main": false,
+            "is_hidden": false,
+            "parents": [
+              "62fc20d2a290daea0d52bdc2ed2ad4be6491010e"
+            ],
+            "omitted_parents": [],
+            "rewritten_as": null
+          },
+          {
+            "oid": "96d1c37a3d4363611c49f7e52186e189a04c531f",
+            "short_oid": "96d1c37a",
+            "subject": "create test2.txt",
+            "branches": [],
+            "is_head": false,
+            "is_main": false,
+            "is_hidden": true,
+            "parents": [
+              "62fc20d2a290daea0d52bdc2ed2ad4be6491010e"
+            ],
+            "omitted_parents": [],
+            "rewritten_as": "cb8137adb1d2a166d27eeaf6bfc39a374748852c"
+          }
+        ]
+        "###);
+    }
+
+    git.run(&["hide", &test1_oid.to_string()])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--format", "json", "--reverse"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        [
+          {
+            "oid": "cb8137adb1d2a166d27eeaf6bfc39a374748852c",
+            "short_oid": "cb8137ad",
+            "subject": "amended test2",
+            "branches": [],
+            "is_head": true,
+            "is_main": false,
+            "is_hidden": false,
+            "parents": [
+              "62fc20d2a290daea0d52bdc2ed2ad4be6491010e"
+            ],
+            "omitted_parents": [],
+            "rewritten_as": null
+          },
+          {
+            "oid": "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+            "short_oid": "62fc20d2",
+            "subject": "create test1.txt",
+            "branches": [
+              "refs/heads/foo"
+            ],
+            "is_head": false,
+            "is_main": false,
+            "is_hidden": true,
+            "parents": [
+              "f777ecc9b0db5ed372b2615695191a8a17f79f24"
+            ],
+            "omitted_parents": [],
+            "rewritten_as": null
+          },
+          {
+            "oid": "f777ecc9b0db5ed372b2615695191a8a17f79f24",
+            "short_oid": "f777ecc9",
+            "subject": "create initial.txt",
+            "branches": [
+              "refs/heads/master"
+            ],
+            "is_head": false,
+            "is_main": true,
+            "is_hidden": false,
+            "parents": [],
+            "omitted_parents": [],
+            "rewritten_as": null
+          }
+        ]
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_json_omitted_parents() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt
+        |\
+        : o 62fc20d2 create test1.txt
+        :
+        @ 02067177 (> master) create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--format", "json"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        [
+          {
+            "oid": "f777ecc9b0db5ed372b2615695191a8a17f79f24",
+            "short_oid": "f777ecc9",
+            "subject": "create initial.txt",
+            "branches": [],
+            "is_head": false,
+            "is_main": true,
+            "is_hidden": false,
+            "parents": [],
+            "omitted_parents": [],
+            "rewritten_as": null
+          },
+          {
+            "oid": "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+            "short_oid": "62fc20d2",
+            "subject": "create test1.txt",
+            "branches": [],
+            "is_head": false,
+            "is_main": false,
+            "is_hidden": false,
+            "parents": [
+              "f777ecc9b0db5ed372b2615695191a8a17f79f24"
+            ],
+            "omitted_parents": [],
+            "rewritten_as": null
+          },
+          {
+            "oid": "02067177964ab16eedc74600341b2d9e4e19487e",
+            "short_oid": "02067177",
+            "subject": "create test3.txt",
+            "branches": [
+              "refs/heads/master"
+            ],
+            "is_head": true,
+            "is_main": true,
+            "is_hidden": false,
+            "parents": [
+              "f777ecc9b0db5ed372b2615695191a8a17f79f24"
+            ],
+            "omitted_parents": [
+              "f777ecc9b0db5ed372b2615695191a8a17f79f24"
+            ],
+            "rewritten_as": null
+          }
+        ]
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_max_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    git.commit_file("test5", 5)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test6", 6)?;
+    git.run(&["checkout", &test3_oid.to_string()])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt
+        |\
+        | o 62fc20d2 create test1.txt
+        | |
+        | o 96d1c37a create test2.txt
+        | |
+        | @ 70deb1e2 (> detached HEAD) create test3.txt
+        | |
+        | o 355e173b create test4.txt
+        | |
+        | o f81d55c0 create test5.txt
+        |
+        O fd6c44bf (master) create test6.txt
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.run(&["smartlog", "--max-commits", "3"])?;
+        insta::assert_snapshot!(stderr, @"Omitted 4 commits from the smartlog. Run `git smartlog --max-commits 0` to show all commits.
+");
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        o 96d1c37a create test2.txt
+        |
+        @ 70deb1e2 (> detached HEAD) create test3.txt
+        |
+        o 355e173b create test4.txt
+        "###);
+    }
+
+    git.run(&["config", "branchless.smartlog.maxCommits", "2"])?;
+    {
+        let (stdout, stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stderr, @"Omitted 5 commits from the smartlog. Run `git smartlog --max-commits 0` to show all commits.
+");
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        o 96d1c37a create test2.txt
+        |
+        @ 70deb1e2 (> detached HEAD) create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.run(&["smartlog", "--max-commits", "0"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt
+        |\
+        | o 62fc20d2 create test1.txt
+        | |
+        | o 96d1c37a create test2.txt
+        | |
+        | @ 70deb1e2 (> detached HEAD) create test3.txt
+        | |
+        | o 355e173b create test4.txt
+        | |
+        | o f81d55c0 create test5.txt
+        |
+        O fd6c44bf (master) create test6.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_public() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    git.commit_file("test5", 5)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 create test1.txt
+        |\
+        : o 96d1c37a create test2.txt
+        :
+        @ 566e4341 (> master) create test5.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--public=2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 create test1.txt
+        |\
+        : o 96d1c37a create test2.txt
+        :
+        O a2482074 create test4.txt
+        |
+        @ 566e4341 (> master) create test5.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--public", "10"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt
+        |
+        O 62fc20d2 create test1.txt
+        |\
+        | o 96d1c37a create test2.txt
+        |
+        O 4838e49b create test3.txt
+        |
+        O a2482074 create test4.txt
+        |
+        @ 566e4341 (> master) create test5.txt
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.run(&["smartlog", "--public=2", "--max-commits=1"])?;
+        insta::assert_snapshot!(stderr, @"Omitted 1 commit from the smartlog. Run `git smartlog --max-commits 0` to show all commits.
+");
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        o 96d1c37a create test2.txt
+
+        :
+        O a2482074 create test4.txt
+        |
+        @ 566e4341 (> master) create test5.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_show_age() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.run(&[
+        "commit",
+        "--allow-empty",
+        "-m",
+        "a commit with a longer message",
+    ])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--show-age"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt                        2020-10-29
+        |\
+        | o 62fc20d2 create test1.txt                        2020-10-29
+        |
+        @ 80457036 (> master) a commit with a longer message 2020-10-29
+        "###);
+    }
+
+    git.run(&["config", "branchless.smartlog.showAge", "true"])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--reverse"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ 80457036 (> master) a commit with a longer message 2020-10-29
+        |
+        | o 62fc20d2 create test1.txt                        2020-10-29
+        |/
+        O f777ecc9 create initial.txt                        2020-10-29
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_stat() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["checkout", "-b", "feature"])?;
+    git.commit_file("test1", 1)?;
+    git.write_file("test1", "line 1\nline 2\nline 3\n")?;
+    git.write_file("test2", &"line\n".repeat(12))?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "update test1 and add test2"])?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["merge", "--no-ff", "-m", "merge feature", "feature"])?;
+    git.detach_head()?;
+    git.commit_file("test4", 4)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--stat"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O e12d9af0 (feature) update test1 and add test2 +15 -1
+        |
+        O 13be0c24 (master) merge feature               +15 -0
+        |
+        @ 2c7df7d3 (> detached HEAD) create test4.txt    +1 -0
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--stat=full", "--show-age"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O e12d9af0 (feature) update test1 and add test2 2020-10-29 +15 -1 (test1.txt +3 -1, test2.txt +12 -0)
+        |
+        O 13be0c24 (master) merge feature               2020-10-29 +15 -0 (test1.txt +3 -0, test2.txt +12 -0)
+        |
+        @ 2c7df7d3 (> detached HEAD) create test4.txt   2020-10-29  +1 -0 (test4.txt +1 -0)
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_hidden_ref_prefixes() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["update-ref", "refs/branchless/internal", "HEAD"])?;
+    git.run(&["branch", "mirror/internal"])?;
+    git.run(&["branch", "foo"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD, foo, mirror/internal) create test1.txt
+        "###);
+    }
+
+    git.run(&[
+        "config",
+        "branchless.smartlog.hiddenRefPrefixes",
+        "refs/heads/mirror/, refs/heads/other/",
+    ])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD, foo) create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_dot() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["commit", "--amend", "-m", "amended \"test2\""])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--hidden", "--format", "dot"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        digraph smartlog {
+          rankdir=BT;
+          node [fontname="monospace"];
+          "f777ecc9b0db5ed372b2615695191a8a17f79f24" [label="f777ecc9 create initial.txt", shape=box];
+          "62fc20d2a290daea0d52bdc2ed2ad4be6491010e" [label="62fc20d2 create test1.txt", shape=ellipse];
+          "5f0b8f10a4d95d229e8750a77f214d886a6cb3b0" [label="5f0b8f10 amended \"test2\"", shape=ellipse, style="bold"];
+          "96d1c37a3d4363611c49f7e52186e189a04c531f" [label="96d1c37a create test2.txt", shape=ellipse, fontcolor="gray", style="dashed"];
+          "62fc20d2a290daea0d52bdc2ed2ad4be6491010e" -> "f777ecc9b0db5ed372b2615695191a8a17f79f24";
+          "5f0b8f10a4d95d229e8750a77f214d886a6cb3b0" -> "62fc20d2a290daea0d52bdc2ed2ad4be6491010e";
+          "96d1c37a3d4363611c49f7e52186e189a04c531f" -> "62fc20d2a290daea0d52bdc2ed2ad4be6491010e";
+        }
+        "###);
+    }
+
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--format", "dot"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        digraph smartlog {
+          rankdir=BT;
+          node [fontname="monospace"];
+          "f777ecc9b0db5ed372b2615695191a8a17f79f24" [label="f777ecc9 create initial.txt", shape=box];
+          "62fc20d2a290daea0d52bdc2ed2ad4be6491010e" [label="62fc20d2 create test1.txt", shape=ellipse];
+          "5f0b8f10a4d95d229e8750a77f214d886a6cb3b0" [label="5f0b8f10 amended \"test2\"", shape=ellipse];
+          "2b633ed788585e863511ffa56fc58b117d0ddab0" [label="2b633ed7 create test4.txt", shape=box, style="bold"];
+          "62fc20d2a290daea0d52bdc2ed2ad4be6491010e" -> "f777ecc9b0db5ed372b2615695191a8a17f79f24";
+          "5f0b8f10a4d95d229e8750a77f214d886a6cb3b0" -> "62fc20d2a290daea0d52bdc2ed2ad4be6491010e";
+          "2b633ed788585e863511ffa56fc58b117d0ddab0" -> "f777ecc9b0db5ed372b2615695191a8a17f79f24" [style="dotted"];
+        }
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_max_subject_width() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&[
+        "commit",
+        "--allow-empty",
+        "-m",
+        "this commit has a rather long subject line",
+    ])?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.run(&[
+        "commit",
+        "--allow-empty",
+        "-m",
+        "this commit also has a long subject",
+    ])?;
+    git.run(&["config", "branchless.smartlog.maxSubjectWidth", "30"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create in...
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | o b62f870d this commit has a...
+        |
+        @ bd6e193f (> detached HEAD)...
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--wrap"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create
+        | initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | o b62f870d this commit has a
+        |   rather long subject line
+        |
+        @ bd6e193f (> detached HEAD)
+          this commit also has a long
+          subject
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--wrap", "--reverse"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ bd6e193f (> detached HEAD)
+        | this commit also has a long
+        | subject
+        |
+        | o b62f870d this commit has a
+        | | rather long subject line
+        |/
+        o 62fc20d2 create test1.txt
+        |
+        O f777ecc9 (master) create
+          initial.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--no-wrap"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | o b62f870d this commit has a rather long subject line
+        |
+        @ bd6e193f (> detached HEAD) this commit also has a long subject
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_plain() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&[
+        "commit",
+        "--allow-empty",
+        "-m",
+        "this commit has a rather long subject line",
+    ])?;
+    git.run(&["config", "branchless.smartlog.maxSubjectWidth", "30"])?;
+    git.run(&["config", "branchless.smartlog.glyphs", "unicode"])?;
+
+    {
+        let (stdout, _stderr) =
+            git.run(&["branchless", "--color", "always", "smartlog", "--plain"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        @ b62f870d (> detached HEAD) this commit has a rather long subject line
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_legend() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["commit", "--amend", "-m", "amended test3"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--hidden", "--legend"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 (master) create test1.txt
+        |
+        o 96d1c37a (foo) create test2.txt
+        |\
+        | @ 1a2dc8f0 (> detached HEAD) amended test3
+        |
+        x 70deb1e2 (amended as 1a2dc8f0) create test3.txt
+        Legend: O public commit, o draft commit, x hidden commit, X hidden public commit, @ current commit, % current hidden commit, > current branch
+        "###);
+    }
+
+    git.run(&["config", "branchless.smartlog.showLegend", "true"])?;
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "--color", "always", "smartlog"])?;
+        let stdout = console::strip_ansi_codes(&stdout);
+        insta::assert_snapshot!(stdout.lines().last().unwrap(), @"Legend: ◇ public commit, ◯ draft commit, ✕ hidden commit, ● current commit, ◆ current public commit, ⦻ current hidden commit, ❖ current hidden public commit, ᐅ current branch");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_show_author() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&[
+        "commit",
+        "--allow-empty",
+        "--author",
+        "Alice <alice@example.com>",
+        "-m",
+        "commit by alice",
+    ])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--show-author"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt (author: Testy McTestface)
+        |
+        o 62fc20d2 create test1.txt (author: Testy McTestface)
+        |
+        @ e91f5042 (> detached HEAD) commit by alice (author: Alice)
+        "###);
+    }
+
+    std::fs::write(
+        git.repo_path.join(".mailmap"),
+        "Alice Example <alice@example.com>\n",
+    )?;
+    git.run(&["config", "branchless.commitDescriptors.author", "true"])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt (author: Testy McTestface)
+        |
+        o 62fc20d2 create test1.txt (author: Testy McTestface)
+        |
+        @ e91f5042 (> detached HEAD) commit by alice (author: Alice Example)
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_filter_by_author() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&[
+        "commit",
+        "--allow-empty",
+        "--author",
+        "Alice <alice@example.com>",
+        "-m",
+        "commit by alice",
+    ])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.run(&[
+        "commit",
+        "--allow-empty",
+        "--author",
+        "Bob <bob@example.com>",
+        "-m",
+        "commit by bob",
+    ])?;
+    git.run(&["checkout", "HEAD~"])?;
+    git.run(&[
+        "commit",
+        "--allow-empty",
+        "--author",
+        "Alice <alice@example.com>",
+        "-m",
+        "another commit by alice",
+    ])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt
+        |\
+        | @ 5943bd2b (> detached HEAD) another commit by alice
+        |\
+        | o 62fc20d2 create test1.txt
+        | |
+        | o e91f5042 commit by alice
+        | |
+        | o a97aedc2 create test2.txt
+        |
+        O 71a1c779 (master) commit by bob
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--author", "alice@"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt
+        |\
+        | @ 5943bd2b (> detached HEAD) another commit by alice
+        |\
+        | :
+        | o e91f5042 commit by alice
+        |
+        O 71a1c779 (master) commit by bob
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--author", "^(Testy|Bob)"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt
+        |\
+        | o 62fc20d2 create test1.txt
+        | :
+        | o a97aedc2 create test2.txt
+        |
+        O 71a1c779 (master) commit by bob
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_filter_by_author_from_subdir() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.write_file("subdir/test1", "test1 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run_with_options(
+        &["commit", "-m", "commit by alice"],
+        &GitRunOptions {
+            time: 1,
+            env: [
+                ("GIT_AUTHOR_NAME", "Alice"),
+                ("GIT_AUTHOR_EMAIL", "alice@example.com"),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+            ..Default::default()
+        },
+    )?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["smartlog", "--author", "alice@"],
+            &GitRunOptions {
+                cwd: Some("subdir".into()),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 33fc66c7 commit by alice
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_unpushed() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    original_repo.init_repo()?;
+    original_repo.commit_file("test1", 1)?;
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+
+    cloned_repo.run(&["checkout", "-b", "foo"])?;
+    cloned_repo.commit_file("test2", 2)?;
+    cloned_repo.run(&["push", "origin", "foo"])?;
+    cloned_repo.commit_file("test3", 3)?;
+    cloned_repo.run(&["checkout", "master"])?;
+    cloned_repo.commit_file("test4", 4)?;
+    cloned_repo.run(&["checkout", "foo"])?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 (remote origin/master) create test1.txt
+        |\
+        | o 96d1c37a create test2.txt
+        | |
+        | @ 70deb1e2 (> foo) create test3.txt
+        |
+        o bf0d52a6 (master) create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["smartlog", "--unpushed"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 (remote origin/master) create test1.txt
+        |\
+        | :
+        | @ 70deb1e2 (> foo) create test3.txt
+        |
+        o bf0d52a6 (master) create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["smartlog", "--unpushed", "--only-branches"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 (remote origin/master) create test1.txt
+        |\
+        | :
+        | @ 70deb1e2 (> foo) create test3.txt
+        |
+        o bf0d52a6 (master) create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_show_signatures() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--show-signatures"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 (master) create test1.txt (unsigned)
+        |
+        @ 96d1c37a (> detached HEAD) create test2.txt (unsigned)
+        "###);
+    }
+
+    let key_path = git.repo_path.join(".git").join("signing_key");
+    let status = std::process::Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-C", "test", "-f"])
+        .arg(&key_path)
+        .status()?;
+    assert!(status.success());
+    let public_key = std::fs::read_to_string(key_path.with_extension("pub"))?;
+    let allowed_signers_path = git.repo_path.join(".git").join("allowed_signers");
+    std::fs::write(
+        &allowed_signers_path,
+        format!("test@example.com {}", public_key),
+    )?;
+    git.run(&["config", "gpg.format", "ssh"])?;
+    git.run(&["config", "user.signingKey", key_path.to_str().unwrap()])?;
+    git.run(&[
+        "config",
+        "gpg.ssh.allowedSignersFile",
+        allowed_signers_path.to_str().unwrap(),
+    ])?;
+    git.write_file("test3", "test3 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-S", "-m", "create test3.txt"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--show-signatures"])?;
+        let stdout = stdout.lines().last().unwrap();
+        assert!(stdout.ends_with("create test3.txt (signed)"), "{}", stdout);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_tags() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["tag", "v1.0.0"])?;
+    git.run(&["tag", "-a", "v1.1.0", "-m", "annotated tag"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["tag", "v2.0.0"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 (tag: v1.0.0, tag: v1.1.0) create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD, foo) (tag: v2.0.0) create test2.txt
+        "###);
+    }
+
+    git.run(&["config", "branchless.smartlog.showTags", "false"])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD, foo) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_focus_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test5", 5)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", &test1_oid.to_string()])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "HEAD", &test1_oid.to_string()])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |\
+        | o 62fc20d2 create test1.txt
+        | |
+        | o 96d1c37a create test2.txt
+        |
+        @ aff9c670 (> detached HEAD) create test5.txt
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.run_with_options(
+            &["smartlog", "nonexistent"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Commit not found: nonexistent
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_ascii_glyphs() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "--color", "always", "smartlog"])?;
+        let stdout = console::strip_ansi_codes(&stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        ◇ f777ecc9 create initial.txt
+        ┣━┓
+        ┃ ◯ 62fc20d2 create test1.txt
+        ┃
+        ◆ fe65c1fe (ᐅ master) create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&[
+            "branchless",
+            "--color",
+            "always",
+            "smartlog",
+            "--glyphs",
+            "ascii",
+        ])?;
+        let stdout = console::strip_ansi_codes(&stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt
+        |\
+        | o 62fc20d2 create test1.txt
+        |
+        @ fe65c1fe (> master) create test2.txt
+        "###);
+    }
+
+    git.run(&["config", "branchless.smartlog.glyphs", "ascii"])?;
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "--color", "always", "smartlog"])?;
+        let stdout = console::strip_ansi_codes(&stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt
+        |\
+        | o 62fc20d2 create test1.txt
+        |
+        @ fe65c1fe (> master) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_color_config() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    git.run(&["config", "branchless.smartlog.color.oid", "bold brightblue"])?;
+    git.run(&["config", "branchless.smartlog.color.head", "red ul"])?;
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "--color", "always", "smartlog"])?;
+        assert!(stdout.contains("\u{1b}[38;5;12m\u{1b}[1mf777ecc9\u{1b}[0m"));
+        assert!(stdout.contains("\u{1b}[31m\u{1b}[1m\u{1b}[4m●\u{1b}[0m"));
+        let stdout = console::strip_ansi_codes(&stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        ◇ f777ecc9 (master) create initial.txt
+        ┃
+        ● 62fc20d2 (ᐅ detached HEAD) create test1.txt
+        "###);
+    }
+
+    git.run(&["config", "branchless.smartlog.color.oid", "chartreuse"])?;
+    {
+        let (_stdout, stderr) = git.run_with_options(
+            &["branchless", "--color", "always", "smartlog"],
+            &GitRunOptions {
+                // Exit code 101 indicates a panic.
+                expected_exit_code: 101,
+                ..Default::default()
+            },
+        )?;
+        let stderr = console::strip_ansi_codes(&stderr);
+        assert!(stderr.contains(
+            "Invalid value for branchless.smartlog.color.oid: unknown color or attribute: chartreuse"
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_compute_smartlog() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["branch", "foo"])?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["commit", "--amend", "-m", "amended test2"])?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let repo = git.get_repo()?;
+    let head_oid = repo.get_head_info()?.oid.unwrap();
+    let master_oid = repo.get_main_branch_oid()?;
+
+    let smartlog = compute_smartlog(
+        &effects,
+        &repo,
+        &SmartlogOptions {
+            show_hidden_commits: true,
+            ..Default::default()
+        },
+    )?;
+    let oids: Vec<_> = smartlog.nodes.iter().map(|node| node.oid).collect();
+    assert_eq!(oids, vec![master_oid, test1_oid, head_oid, test2_oid]);
+    assert_eq!(
+        smartlog.nodes[1].branches,
+        vec!["refs/heads/foo".to_string()]
+    );
+    assert_eq!(smartlog.nodes[2].subject.as_deref(), Some("amended test2"));
+    assert!(smartlog.nodes[0].is_main);
+    assert!(smartlog.nodes[2].is_head);
+    assert!(smartlog.nodes[3].is_obsolete);
+    assert_eq!(
+        smartlog.nodes[3].rewritten_as,
+        Some(MaybeZeroOid::NonZero(head_oid))
+    );
+    assert_eq!(
+        smartlog.edges,
+        vec![
+            SmartlogEdge {
+                parent_oid: master_oid,
+                child_oid: test1_oid,
+                has_omitted_commits: false,
+            },
+            SmartlogEdge {
+                parent_oid: test1_oid,
+                child_oid: head_oid,
+                has_omitted_commits: false,
+            },
+            SmartlogEdge {
+                parent_oid: test1_oid,
+                child_oid: test2_oid,
+                has_omitted_commits: false,
+            },
+        ]
+    );
+    assert_eq!(smartlog.num_omitted_commits, 0);
+
+    let smartlog = compute_smartlog(&effects, &repo, &Default::default())?;
+    let oids: Vec<_> = smartlog.nodes.iter().map(|node| node.oid).collect();
+    assert_eq!(oids, vec![master_oid, test1_oid, head_oid]);
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_cache() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        let (cached_stdout, _stderr) = git.run(&["smartlog"])?;
+        let (uncached_stdout, _stderr) = git.run(&["smartlog", "--no-cache"])?;
+        assert_eq!(stdout, cached_stdout);
+        assert_eq!(stdout, uncached_stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD) create test2.txt
+        "###);
+    }
+
+    // A cached graph should also be rendered in the structured formats.
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--format", "json"])?;
+        let (cached_stdout, _stderr) = git.run(&["smartlog", "--format", "json"])?;
+        let (uncached_stdout, _stderr) =
+            git.run(&["smartlog", "--format", "json", "--no-cache"])?;
+        assert_eq!(stdout, cached_stdout);
+        assert_eq!(stdout, uncached_stdout);
+    }
+
+    // Changing a reference should invalidate the cache.
+    git.run(&["branch", "foo", &test1_oid.to_string()])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 (foo) create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD) create test2.txt
+        "###);
+    }
+
+    // Adding an event without changing any references should also
+    // invalidate the cache.
+    git.run(&["hide", "HEAD"])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        let (uncached_stdout, _stderr) = git.run(&["smartlog", "--no-cache"])?;
+        assert_eq!(stdout, uncached_stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 (foo) create test1.txt
+        |
+        % 96d1c37a (manually hidden) (> detached HEAD) create test2.txt
+        "###);
+    }
+
+    // Earlier graphs aren't evicted when a graph is stored under a new key,
+    // such as one computed with different options.
+    git.run(&["smartlog", "--hidden"])?;
+    {
+        let conn = git.get_repo()?.get_db_conn()?;
+        let num_cached_graphs: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM smartlog_cache",
+            rusqlite::params![],
+            |row| row.get(0),
+        )?;
+        assert_eq!(num_cached_graphs, 4);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_corrupt_event_log() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let conn = git.get_repo()?.get_db_conn()?;
+        conn.execute(
+            "INSERT INTO event_log (timestamp, type, event_tx_id) VALUES (0.0, 'bogus', 1)",
+            rusqlite::params![],
+        )?;
+    }
+    {
+        let (stdout, stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: warning: 1 event in the event log could not be read, so the smartlog may be incomplete or out of date.
+        branchless: warning: run `git branchless repair` to reconstruct the event log from the current references.
+        "###);
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 (master) create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD) create test2.txt
+        "###);
+    }
+
+    std::fs::write(
+        git.repo_path
+            .join(".git")
+            .join("branchless")
+            .join("db.sqlite3"),
+        "not a database",
+    )?;
+    {
+        let (stdout, stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: warning: the event log could not be read: Error code 26: File opened that is not a database file, so the smartlog may be incomplete or out of date.
+        branchless: warning: run `git branchless repair` to reconstruct the event log from the current references (move `.git/branchless/db.sqlite3` out of the way first).
+        "###);
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 (master) create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_worktree() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    let worktree_path = git.repo_path.join("worktree");
+    git.run(&[
+        "worktree",
+        "add",
+        "--detach",
+        worktree_path.to_str().unwrap(),
+    ])?;
+    let worktree_git = Git {
+        repo_path: worktree_path,
+        ..(*git).clone()
+    };
+    worktree_git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD) create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = worktree_git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        @ 96d1c37a (> detached HEAD) create test2.txt
+        "###);
+    }
+
+    worktree_git.run(&["checkout", "master"])?;
+
+    {
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let head_ref_names: Vec<String> = event_log_db
+            .get_events()?
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::RefUpdateEvent { ref_name, .. }
+                    if ref_name.to_str() != Some("refs/heads/master") =>
+                {
+                    Some(ref_name.to_string_lossy().into_owned())
+                }
+                _ => None,
+            })
+            .unique()
+            .collect();
+        insta::assert_debug_snapshot!(head_ref_names, @r###"
+        [
+            "HEAD",
+            "worktrees/worktree/HEAD",
+        ]
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD) create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_worktree_merge_old_event_log() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    for worktree_name in ["worktree", "worktree2"] {
+        let worktree_path = git.repo_path.join(worktree_name);
+        git.run(&[
+            "worktree",
+            "add",
+            "--detach",
+            worktree_path.to_str().unwrap(),
+        ])?;
+    }
+
+    // Record a commit only in an event log under each worktree's own Git
+    // directory, where older versions kept it. The second one was left behind
+    // by an interrupted merge.
+    let mut old_dirs = Vec::new();
+    let mut commit_oids = Vec::new();
+    for (worktree_name, file_name) in [
+        ("worktree", "db.sqlite3"),
+        ("worktree2", "db.sqlite3.merging"),
+    ] {
+        let (commit_oid, _stderr) = git.run(&[
+            "commit-tree",
+            "HEAD^{tree}",
+            "-p",
+            "HEAD",
+            "-m",
+            &format!("old {} commit", worktree_name),
+        ])?;
+        let commit_oid: NonZeroOid = commit_oid.trim().parse()?;
+        let old_dir = git
+            .repo_path
+            .join(".git")
+            .join("worktrees")
+            .join(worktree_name)
+            .join("branchless");
+        std::fs::create_dir_all(&old_dir)?;
+        let conn = rusqlite::Connection::open(old_dir.join(file_name))?;
+        let mut event_log_db = EventLogDb::new(&conn)?;
+        let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "test")?;
+        event_log_db.add_events(vec![
+            Event::CommitEvent {
+                timestamp: 0.0,
+                event_tx_id,
+                commit_oid,
+            },
+            Event::RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id,
+                ref_name: OsString::from("HEAD"),
+                old_oid: MaybeZeroOid::Zero,
+                new_oid: MaybeZeroOid::NonZero(commit_oid),
+                message: None,
+            },
+        ])?;
+        old_dirs.push(old_dir);
+        commit_oids.push(commit_oid);
+    }
+
+    // Simulate a shared database which was last opened by an older version,
+    // before the old event logs were merged.
+    let existing_events = {
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        conn.execute_batch("PRAGMA user_version = 0")?;
+        EventLogDb::new(&conn)?.get_events()?
+    };
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        @ 62fc20d2 (> detached HEAD) create test1.txt
+        |\
+        | o 32a6e297 old worktree2 commit
+        |
+        o 46c7e5a4 old worktree commit
+        "###);
+    }
+
+    {
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let events = event_log_db.get_events()?;
+
+        // The merged events are appended after the existing ones.
+        assert_eq!(events[..existing_events.len()], existing_events[..]);
+
+        let head_ref_names: Vec<String> = events
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::RefUpdateEvent {
+                    ref_name, new_oid, ..
+                } if commit_oids
+                    .iter()
+                    .any(|commit_oid| new_oid == MaybeZeroOid::NonZero(*commit_oid)) =>
+                {
+                    Some(ref_name.to_string_lossy().into_owned())
+                }
+                _ => None,
+            })
+            .collect();
+        insta::assert_debug_snapshot!(head_ref_names, @r###"
+        [
+            "worktrees/worktree/HEAD",
+            "worktrees/worktree2/HEAD",
+        ]
+        "###);
+    }
+
+    for old_dir in old_dirs {
+        assert!(!old_dir.join("db.sqlite3").exists());
+        assert!(!old_dir.join("db.sqlite3.merging").exists());
+        assert!(old_dir.join("db.sqlite3.merged").exists());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_highlight() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (plain_stdout, _stderr) = git.run(&["branchless", "--color", "always", "smartlog"])?;
+        let (stdout, _stderr) = git.run(&[
+            "branchless",
+            "--color",
+            "always",
+            "smartlog",
+            "--highlight",
+            &test1_oid.to_string(),
+        ])?;
+        assert_eq!(
+            console::strip_ansi_codes(&stdout),
+            console::strip_ansi_codes(&plain_stdout)
+        );
+
+        let changed_lines = stdout
+            .lines()
+            .zip(plain_stdout.lines())
+            .filter(|(line, plain_line)| line != plain_line)
+            .map(|(line, _plain_line)| console::strip_ansi_codes(line).into_owned())
+            .collect_vec();
+        insta::assert_debug_snapshot!(changed_lines, @r###"
+        [
+            "◯ 62fc20d2 create test1.txt",
+        ]
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.run_with_options(
+            &["branchless", "smartlog", "--highlight", "nonexistent"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Commit not found: nonexistent
+");
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_max_refs() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test4", 4)?;
+    git.run(&["config", "branchless.smartlog.maxRefs", "3"])?;
+
+    {
+        let (stdout, stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stderr, @"Omitted 2 stacks from the smartlog, because there are more than `branchless.smartlog.maxRefs` to show. Run `git smartlog --full` to show all commits.
+");
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |\
+        | o 98b9119d create test3.txt
+        |
+        @ 8f7aef57 (> detached HEAD) create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.run(&["smartlog", "--full"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |\
+        | o 62fc20d2 create test1.txt
+        |\
+        | o fe65c1fe create test2.txt
+        |\
+        | o 98b9119d create test3.txt
+        |
+        @ 8f7aef57 (> detached HEAD) create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_color_by_stack() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&[
+            "branchless",
+            "--color",
+            "always",
+            "smartlog",
+            "--color-by-stack",
+        ])?;
+        let stack_colors = stdout
+            .lines()
+            .filter(|line| console::strip_ansi_codes(line).contains("create test"))
+            .map(|line| {
+                let colors: std::collections::BTreeSet<&str> = line
+                    .split("\u{1b}[38;5;")
+                    .skip(1)
+                    .filter_map(|rest| rest.split('m').next())
+                    .collect();
+                format!(
+                    "{:?} {}",
+                    colors,
+                    console::strip_ansi_codes(line)
+                        .trim_start_matches(|c: char| !c.is_ascii_alphanumeric())
+                )
+            })
+            .join("\n");
+        insta::assert_snapshot!(stack_colors, @r###"
+        {"14"} 62fc20d2 create test1.txt
+        {"14"} 96d1c37a create test2.txt
+        {"10"} 98b9119d create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&[
+            "branchless",
+            "--color",
+            "never",
+            "smartlog",
+            "--color-by-stack",
+        ])?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ f777ecc9 (> master) create initial.txt
+        |\
+        | o 62fc20d2 create test1.txt
+        | |
+        | o 96d1c37a create test2.txt
+        |
+        o 98b9119d create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_since() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--public", "2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 create test1.txt
+        |\
+        | o 96d1c37a create test2.txt
+        |
+        O 4838e49b create test3.txt
+        |
+        @ a2482074 (> master) create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--public", "2", "--since", "HEAD~1"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        o 96d1c37a create test2.txt
+
+        :
+        O 4838e49b create test3.txt
+        |
+        @ a2482074 (> master) create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--since", "1d"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        o 96d1c37a create test2.txt
+
+        :
+        @ a2482074 (> master) create test4.txt
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) = git.run_with_options(
+            &["smartlog", "--since", "not-a-commit"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Invalid value for --since: not-a-commit (expected a commit, a duration like `30d`, or a date like `2022-01-31`)
+");
+    }
+
+    Ok(())
+}
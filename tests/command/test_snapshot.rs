@@ -0,0 +1,181 @@
+use branchless::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_snapshot_restore() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.write_file("test1", "staged contents\n")?;
+    git.run(&["add", "test1.txt"])?;
+    git.write_file("test2", "unstaged contents\n")?;
+    git.write_file("untracked", "untracked contents\n")?;
+
+    let snapshot_oid = {
+        let (stdout, stderr) = git.run(&["branchless", "snapshot"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        Created snapshot: 56ade5040f110cf34f6ec48e91ad00afea415d71
+        To restore it, run: git branchless restore 56ade5040f110cf34f6ec48e91ad00afea415d71
+        "###);
+        let line = stdout.lines().next().unwrap();
+        line.trim_start_matches("Created snapshot: ").to_owned()
+    };
+
+    {
+        // The working copy and index are left as-is.
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        M  test1.txt
+         M test2.txt
+        ?? untracked.txt
+        "###);
+    }
+
+    git.run(&["reset", "--hard", "HEAD"])?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.delete_file("untracked")?;
+
+    {
+        let (stdout, stderr) = git.run(&["branchless", "restore", &snapshot_oid])?;
+        insta::assert_snapshot!(stderr, @r###"
+        Previous HEAD position was 62fc20d create test1.txt
+        branchless: processing 1 update: ref HEAD
+        HEAD is now at 96d1c37 create test2.txt
+        branchless: processing checkout
+        "###);
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 96d1c37a3d4363611c49f7e52186e189a04c531f
+        Restored snapshot: 56ade5040f110cf34f6ec48e91ad00afea415d71
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        M  test1.txt
+         M test2.txt
+        "###);
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 96d1c37a (> detached HEAD, master) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_include_untracked() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.write_file("untracked", "untracked contents\n")?;
+
+    let snapshot_oid = {
+        let (stdout, _stderr) = git.run(&["branchless", "snapshot", "--include-untracked"])?;
+        let line = stdout.lines().next().unwrap();
+        line.trim_start_matches("Created snapshot: ").to_owned()
+    };
+
+    git.delete_file("untracked")?;
+    git.run(&["branchless", "restore", &snapshot_oid])?;
+
+    {
+        // The untracked file is restored, but isn't added to the index.
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        ?? untracked.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_restore_refuses_invalid_snapshot() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, stderr) = git.run_with_options(
+            &["branchless", "restore", "HEAD"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Not a snapshot: HEAD
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    let (stdout, _stderr) = git.run(&["branchless", "snapshot"])?;
+    let snapshot_oid = stdout
+        .lines()
+        .next()
+        .unwrap()
+        .trim_start_matches("Created snapshot: ")
+        .to_owned();
+    git.write_file("test1", "modified contents\n")?;
+
+    {
+        let (stdout, stderr) = git.run_with_options(
+            &["branchless", "restore", &snapshot_oid],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        The working copy has uncommitted changes. Commit them, or snapshot them with `git branchless snapshot`, before restoring a snapshot.
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_restore_refuses_to_overwrite_untracked_files() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.write_file("untracked", "snapshot contents\n")?;
+
+    let snapshot_oid = {
+        let (stdout, _stderr) = git.run(&["branchless", "snapshot", "--include-untracked"])?;
+        let line = stdout.lines().next().unwrap();
+        line.trim_start_matches("Created snapshot: ").to_owned()
+    };
+
+    git.write_file("untracked", "other contents\n")?;
+    {
+        let (stdout, stderr) = git.run_with_options(
+            &["branchless", "restore", &snapshot_oid],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        These untracked files would be overwritten by the snapshot:
+          untracked.txt
+        Move or delete them before restoring the snapshot.
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    let contents = std::fs::read_to_string(git.repo_path.join("untracked.txt"))?;
+    assert_eq!(contents, "other contents\n");
+
+    Ok(())
+}
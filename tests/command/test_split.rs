@@ -0,0 +1,177 @@
+use branchless::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_split_head() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.write_file("test1", "test1 contents\n")?;
+    git.write_file("test2", "test2 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "create test1.txt and test2.txt"])?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["split"],
+            &GitRunOptions {
+                input: Some("y\nn\n".to_string()),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Select the changes to keep in the first commit. The remaining changes will be put into the second commit.
+        diff --git a/test1.txt b/test1.txt
+        new file mode 100644
+        index 0000000..7432a8f
+        --- /dev/null
+        +++ b/test1.txt
+        @@ -0,0 +1 @@
+        +test1 contents
+        (1/1) Stage addition [y,n,q,a,d,e,?]? 
+        diff --git a/test2.txt b/test2.txt
+        new file mode 100644
+        index 0000000..4e512d2
+        --- /dev/null
+        +++ b/test2.txt
+        @@ -0,0 +1 @@
+        +test2 contents
+        (1/1) Stage addition [y,n,q,a,d,e,?]? 
+        branchless: running command: <git-executable> checkout 7799c81de22f98faefdfb13d54a1bb2f8c215a21
+        O f777ecc9 (master) create initial.txt
+        |
+        o 6e31c4c2 create test1.txt and test2.txt
+        |
+        @ 7799c81d (> detached HEAD) create test1.txt and test2.txt (continued)
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--name-status", "--format=%s", "-2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test1.txt and test2.txt (continued)
+
+        A	test2.txt
+        create test1.txt and test2.txt
+
+        A	test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_split_with_descendants() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.write_file("test1", "test1 contents\n")?;
+    git.write_file("test2", "test2 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "create test1.txt and test2.txt"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["branch", "foo"])?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["split", "HEAD^"],
+            &GitRunOptions {
+                input: Some("n\ny\n".to_string()),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Select the changes to keep in the first commit. The remaining changes will be put into the second commit.
+        diff --git a/test1.txt b/test1.txt
+        new file mode 100644
+        index 0000000..7432a8f
+        --- /dev/null
+        +++ b/test1.txt
+        @@ -0,0 +1 @@
+        +test1 contents
+        (1/1) Stage addition [y,n,q,a,d,e,?]? 
+        diff --git a/test2.txt b/test2.txt
+        new file mode 100644
+        index 0000000..4e512d2
+        --- /dev/null
+        +++ b/test2.txt
+        @@ -0,0 +1 @@
+        +test2 contents
+        (1/1) Stage addition [y,n,q,a,d,e,?]? 
+        Attempting rebase in-memory...
+        [1/1] Committed as: f643a61f create test3.txt
+        branchless: processing 1 update: branch foo
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout f643a61f112cf1fba30599a3917f363b9ea36e04
+        In-memory rebase succeeded.
+        O f777ecc9 (master) create initial.txt
+        |
+        o 8670cc29 create test1.txt and test2.txt
+        |
+        o 5d4749e1 create test1.txt and test2.txt (continued)
+        |
+        @ f643a61f (> detached HEAD, foo) create test3.txt
+        "###);
+    }
+
+    git.run_with_options(
+        &["undo"],
+        &GitRunOptions {
+            input: Some("y".to_string()),
+            ..Default::default()
+        },
+    )?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o fd1391c4 create test1.txt and test2.txt
+        |
+        @ c848267d (> detached HEAD, foo) create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_split_nothing_selected() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["split"],
+            &GitRunOptions {
+                input: Some("n\n".to_string()),
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Select the changes to keep in the first commit. The remaining changes will be put into the second commit.
+        diff --git a/test1.txt b/test1.txt
+        new file mode 100644
+        index 0000000..7432a8f
+        --- /dev/null
+        +++ b/test1.txt
+        @@ -0,0 +1 @@
+        +test1 contents
+        (1/1) Stage addition [y,n,q,a,d,e,?]? 
+        Aborting split, because either none or all of the changes were selected for the first commit.
+        "###);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use branchless::testing::{make_git_with_remote_repo, GitInitOptions, GitWrapperWithRemoteRepo};
+
+#[test]
+fn test_submit() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    original_repo.init_repo()?;
+    original_repo.commit_file("test1", 1)?;
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+
+    cloned_repo.detach_head()?;
+    cloned_repo.commit_file("test2", 2)?;
+    cloned_repo.run(&["branch", "foo"])?;
+    cloned_repo.commit_file("test3", 3)?;
+    cloned_repo.run(&["branch", "bar"])?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["branchless", "submit", "--dry-run"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Would push branch foo to origin/foo
+        Would push branch bar to origin/bar
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["branchless", "submit"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> push --quiet --set-upstream origin foo bar
+        Pushed branch foo to origin/foo
+        Pushed branch bar to origin/bar
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = original_repo.run(&["branch", "--list"])?;
+        insta::assert_snapshot!(stdout, @r###"
+          bar
+          foo
+        * master
+        "###);
+    }
+
+    cloned_repo.commit_file("test4", 4)?;
+    cloned_repo.run(&["branch", "baz"])?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["branchless", "submit"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Skipped branch foo: already tracking origin/foo
+        Skipped branch bar: already tracking origin/bar
+        branchless: running command: <git-executable> push --quiet --set-upstream origin baz
+        Pushed branch baz to origin/baz
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["branchless", "submit"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Skipped branch foo: already tracking origin/foo
+        Skipped branch bar: already tracking origin/bar
+        Skipped branch baz: already tracking origin/baz
+        No branches in the current stack need to be pushed.
+        "###);
+    }
+
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use branchless::testing::{
-    make_git, make_git_with_remote_repo, GitInitOptions, GitWrapperWithRemoteRepo,
+    make_git, make_git_with_remote_repo, GitInitOptions, GitRunOptions, GitWrapperWithRemoteRepo,
 };
 
+use crate::util::trim_lines;
+
 #[test]
 fn test_sync_basic() -> eyre::Result<()> {
     let git = make_git()?;
@@ -92,7 +94,13 @@ fn test_sync_up_to_date() -> eyre::Result<()> {
     git.commit_file("test4", 4)?;
 
     {
-        let (stdout, stderr) = git.run(&["sync"])?;
+        let (stdout, stderr) = git.run_with_options(
+            &["sync"],
+            &GitRunOptions {
+                expected_exit_code: 3,
+                ..Default::default()
+            },
+        )?;
         insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @"Not moving up-to-date stack at 70deb1e2 create test3.txt
 ");
@@ -239,3 +247,274 @@ fn test_sync_specific_commit() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_sync_merge_conflict() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.write_file("test1", "draft contents")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "draft commit"])?;
+
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["sync"],
+            &GitRunOptions {
+                expected_exit_code: 2,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        Merge conflict for f55f22e8 draft commit
+        To resolve the merge conflicts, run: git sync --merge f55f22e81d0e4b0af025be24f787af089760c268
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_hide_landed_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["cherry-pick", &test1_oid.to_string()])?;
+
+    {
+        let (stdout, stderr) = git.run(&["sync"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        Switched to branch 'master'
+        branchless: processing checkout
+        "###);
+        insta::assert_snapshot!(stdout, @r###"
+        Hid landed commit 62fc20d2 create test1.txt (landed as 64e0ba65)
+        Attempting rebase in-memory...
+        [1/2] Skipped commit (was already applied upstream): 62fc20d2 create test1.txt
+        [2/2] Committed as: 8734036c create test2.txt
+        branchless: processing 2 rewritten commits
+        branchless: running command: <git-executable> checkout master
+        In-memory rebase succeeded.
+        Synced 62fc20d2 create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 64e0ba65 (> master) create test1.txt
+        |
+        o 8734036c create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--hidden"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt
+        |\
+        : x 62fc20d2 (landed) create test1.txt
+        : |
+        : x 96d1c37a (rebased as 8734036c) create test2.txt
+        :
+        @ 64e0ba65 (> master) create test1.txt
+        |
+        o 8734036c create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_delete_merged_branches() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.run(&["branch", "foo"])?;
+    git.commit_file("test1", 1)?;
+    git.run(&["branch", "bar"])?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "baz"])?;
+    git.run(&["checkout", "-b", "current", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["sync"],
+            &GitRunOptions {
+                expected_exit_code: 3,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Found 2 merged branches: bar, foo
+        To delete them, run: git sync --delete-merged
+        Not moving up-to-date stack at 96d1c37a create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["sync", "--delete-merged"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Deleted merged branch bar at 62fc20d2 create test1.txt
+        Deleted merged branch foo at f777ecc9 create initial.txt
+        Not moving up-to-date stack at 96d1c37a create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["branch"])?;
+        insta::assert_snapshot!(stdout, @r###"
+          baz
+        * current
+          master
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["undo"],
+            &GitRunOptions {
+                input: Some("y".to_string()),
+                ..Default::default()
+            },
+        )?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Create branch foo at f777ecc9 create initial.txt
+
+        2. Create branch bar at 62fc20d2 create test1.txt
+
+        Confirm? [yN] Applied 2 inverse events.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["branch"])?;
+        insta::assert_snapshot!(stdout, @r###"
+          bar
+          baz
+        * current
+          foo
+          master
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_delete_merged_branches_worktree() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.run(&["branch", "foo"])?;
+    git.commit_file("test1", 1)?;
+    git.run(&["branch", "bar"])?;
+    git.detach_head()?;
+
+    let worktree_path = git.repo_path.join("worktree");
+    git.run(&["worktree", "add", worktree_path.to_str().unwrap(), "foo"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["sync", "--delete-merged"])?;
+        insta::assert_snapshot!(stdout, @"Deleted merged branch bar at 62fc20d2 create test1.txt
+");
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["branch"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        * (HEAD detached at 62fc20d)
+        + foo
+          master
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_onto() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.run(&["branch", "release"])?;
+    git.commit_file("test2", 2)?;
+    git.detach_head()?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    git.run(&["checkout", "release"])?;
+    git.commit_file("test5", 5)?;
+    git.run(&["cherry-pick", &test3_oid.to_string()])?;
+    git.run(&["checkout", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["sync", "--onto", "nonexistent"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"Commit not found: nonexistent
+");
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["sync", "--onto", "release"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid landed commit 70deb1e2 create test3.txt (landed as ec5ae9c4)
+        Attempting rebase in-memory...
+        [1/2] Skipped commit (was already applied upstream): 70deb1e2 create test3.txt
+        [2/2] Committed as: 136ba762 create test4.txt
+        branchless: processing 2 rewritten commits
+        branchless: running command: <git-executable> checkout master
+        In-memory rebase succeeded.
+        Synced 70deb1e2 create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d2 create test1.txt
+        |\
+        | o ea7aa064 create test5.txt
+        | |
+        | o ec5ae9c4 (release) create test3.txt
+        | |
+        | o 136ba762 create test4.txt
+        |
+        @ 96d1c37a (> master) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
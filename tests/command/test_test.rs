@@ -0,0 +1,206 @@
+use branchless::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_test_run() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["branchless", "test", "run", "-x", "! test -f test2.txt"],
+            &GitRunOptions {
+                expected_exit_code: 4,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Command failed with exit code 1 on commit 96d1c37a create test2.txt:
+        Command failed with exit code 1 on commit 70deb1e2 create test3.txt:
+        O f777ecc9 (master) create initial.txt
+        |
+        o [pass] 62fc20d2 create test1.txt
+        |
+        o [FAIL] 96d1c37a create test2.txt
+        |
+        @ [FAIL] 70deb1e2 (> detached HEAD) create test3.txt
+        Tested 3 commits: 1 passed, 2 failed.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["worktree", "list", "--porcelain"])?;
+        assert_eq!(stdout.matches("worktree ").count(), 1);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_run_range() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    let test3_oid = git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.run(&[
+            "branchless",
+            "test",
+            "run",
+            "-x",
+            "true",
+            &format!("{}..HEAD^", test1_oid),
+        ])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        o [pass] 96d1c37a create test2.txt
+        |
+        @ 70deb1e2 (> detached HEAD) create test3.txt
+        Tested 1 commit: 1 passed, 0 failed.
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.run_with_options(
+            &["branchless", "test", "run", "-x", "true", "nonexistent"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Commit not found: nonexistent
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    // A symmetric difference range should include the commits on both sides,
+    // rather than being parsed as `A..` followed by `.B`.
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.commit_file("test4", 4)?;
+    {
+        let (stdout, _stderr) = git.run(&[
+            "branchless",
+            "test",
+            "run",
+            "-x",
+            "true",
+            &format!("HEAD...{}", test3_oid),
+        ])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |\
+        | o [pass] 96d1c37a create test2.txt
+        | |
+        | o [pass] 70deb1e2 create test3.txt
+        |
+        @ [pass] bf0d52a6 (> detached HEAD) create test4.txt
+        Tested 3 commits: 3 passed, 0 failed.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_run_failure_output() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &[
+                "branchless",
+                "test",
+                "run",
+                "-x",
+                "echo out; echo err >&2; exit 2",
+            ],
+            &GitRunOptions {
+                expected_exit_code: 4,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Command failed with exit code 2 on commit 62fc20d2 create test1.txt:
+        out
+        err
+        O f777ecc9 (master) create initial.txt
+        |
+        @ [FAIL] 62fc20d2 (> detached HEAD) create test1.txt
+        Tested 1 commit: 0 passed, 1 failed.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_run_cached() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) =
+            git.run(&["branchless", "test", "run", "-x", "echo foo >>../count.txt"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        @ [pass] 62fc20d2 (> detached HEAD) create test1.txt
+        Tested 1 commit: 1 passed, 0 failed.
+        "###);
+    }
+
+    // Rewording the commit doesn't change its tree, so the command shouldn't
+    // be run again.
+    git.run(&["commit", "--amend", "-m", "reworded"])?;
+    {
+        let (stdout, _stderr) =
+            git.run(&["branchless", "test", "run", "-x", "echo foo >>../count.txt"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        @ [pass] e900bab4 (> detached HEAD) reworded
+        Tested 1 commit: 1 passed, 0 failed.
+        "###);
+    }
+
+    {
+        let count = std::fs::read_to_string(
+            git.repo_path
+                .join(".git")
+                .join("branchless")
+                .join("tmp")
+                .join("count.txt"),
+        )?;
+        insta::assert_snapshot!(count, @r###"
+        foo
+        "###);
+    }
+
+    Ok(())
+}
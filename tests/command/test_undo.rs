@@ -99,7 +99,7 @@ fn test_undo_help() -> eyre::Result<()> {
         )?;
         insta::assert_snapshot!(screen_to_string(&screenshot1), @r###"
         ┌───────────────────────────────────────────────────┤ Commit graph ├───────────────────────────────────────────────────┐
-        │O f777ecc9 (master) create initial.txt                                                                                │
+        │O f777ecc9 (> master) create initial.txt                                                                              │
         │                                                                                                                      │
         │                                                                                                                      │
         │                                                                                                                      │
@@ -108,16 +108,16 @@ fn test_undo_help() -> eyre::Result<()> {
         │        │                                                                                                    │        │
         │        │ h/?: Show this help.                                                                               │        │
         │        │ q: Quit.                                                                                           │        │
-        │        │ p/n or <left>/<right>: View next/previous state.                                                   │        │
+        │        │ p/n or <left>/<right> or <up>/<down>: View previous/next state.                                    │        │
+        │        │ b: Toggle between viewing the state after and before the selected transaction.                     │        │
         │        │ g: Go to a provided event ID.                                                                      │        │
-        │        │ <enter>: Revert the repository to the given state (requires confirmation).                         │        │
+        │        │ <enter>: Revert the repository to the viewed state (requires confirmation).                        │        │
         │        │                                                                                                    │        │
         │        │ You can also copy a commit hash from the past and manually run `git unhide` or `git rebase` on it. │        │
         │        │                                                                                                    │        │
         │        │                                                                                            <Close> │        │
         │        └────────────────────────────────────────────────────────────────────────────────────────────────────┘        │
         │                                                                                                                      │
-        │                                                                                                                      │
         └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
         ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
         │There are no previous available events.                                                                               │
@@ -189,7 +189,7 @@ fn test_undo_navigate() -> eyre::Result<()> {
         insta::assert_snapshot!(screen_to_string(&screenshot2), @r###"
         ┌───────────────────────────────────────────────────┤ Commit graph ├───────────────────────────────────────────────────┐
         │:                                                                                                                     │
-        │@ 96d1c37a (master) create test2.txt                                                                                  │
+        │@ 96d1c37a (> master) create test2.txt                                                                                │
         │                                                                                                                      │
         │                                                                                                                      │
         │                                                                                                                      │
@@ -217,6 +217,67 @@ fn test_undo_navigate() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_undo_toggle_before() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let screenshot1 = Default::default();
+        let event_cursor = run_select_past_event(
+            &git.get_repo()?,
+            vec![
+                CursiveTestingEvent::Event('p'.into()),
+                CursiveTestingEvent::Event('b'.into()),
+                CursiveTestingEvent::TakeScreenshot(Rc::clone(&screenshot1)),
+                CursiveTestingEvent::Event(Key::Enter.into()),
+            ],
+        )?;
+        insta::assert_debug_snapshot!(event_cursor, @r###"
+        Some(
+            EventCursor {
+                event_id: 3,
+            },
+        )
+        "###);
+        insta::assert_snapshot!(screen_to_string(&screenshot1), @r###"
+        ┌───────────────────────────────────────────────────┤ Commit graph ├───────────────────────────────────────────────────┐
+        │:                                                                                                                     │
+        │@ 62fc20d2 (master) create test1.txt                                                                                  │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
+        │Repo before transaction 3 (event 4). Press 'h' for help, 'q' to quit.                                                 │
+        │1. Check out from 62fc20d2 create test1.txt                                                                           │
+        │               to 96d1c37a create test2.txt                                                                           │
+        │2. Move branch master from 62fc20d2 create test1.txt                                                                  │
+        │                        to 96d1c37a create test2.txt                                                                  │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        "###);
+    };
+
+    Ok(())
+}
+
 #[test]
 fn test_go_to_event() -> eyre::Result<()> {
     let git = make_git()?;
@@ -246,7 +307,7 @@ fn test_go_to_event() -> eyre::Result<()> {
     insta::assert_snapshot!(screen_to_string(&screenshot1), @r###"
     ┌───────────────────────────────────────────────────┤ Commit graph ├───────────────────────────────────────────────────┐
     │:                                                                                                                     │
-    │@ 96d1c37a (master) create test2.txt                                                                                  │
+    │@ 96d1c37a (> master) create test2.txt                                                                                │
     │                                                                                                                      │
     │                                                                                                                      │
     │                                                                                                                      │
@@ -319,10 +380,10 @@ fn test_undo_hide() -> eyre::Result<()> {
         let (stdout, stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
-            O f777ecc9 (master) create initial.txt
-            |
-            @ fe65c1fe create test2.txt
-            "###);
+        O f777ecc9 (master) create initial.txt
+        |
+        @ fe65c1fe (> detached HEAD) create test2.txt
+        "###);
     }
 
     let event_cursor = run_select_past_event(
@@ -359,12 +420,12 @@ fn test_undo_hide() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            O f777ecc9 (master) create initial.txt
-            |\
-            | o 62fc20d2 (test1) create test1.txt
-            |
-            @ fe65c1fe create test2.txt
-            "###);
+        O f777ecc9 (master) create initial.txt
+        |\
+        | o 62fc20d2 (test1) create test1.txt
+        |
+        @ fe65c1fe (> detached HEAD) create test2.txt
+        "###);
     }
 
     Ok(())
@@ -412,7 +473,7 @@ fn test_undo_move_refs() -> eyre::Result<()> {
                                 to 62fc20d2 create test1.txt
         Confirm? [yN] branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e --detach
         :
-        @ 62fc20d2 create test1.txt
+        @ 62fc20d2 (> detached HEAD) create test1.txt
         |
         O 96d1c37a (master) create test2.txt
         Applied 3 inverse events.
@@ -423,9 +484,9 @@ fn test_undo_move_refs() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-            :
-            @ 62fc20d2 (master) create test1.txt
-            "###);
+        :
+        @ 62fc20d2 (> detached HEAD, master) create test1.txt
+        "###);
     }
 
     Ok(())
@@ -455,7 +516,7 @@ fn test_historical_smartlog_visibility() -> eyre::Result<()> {
         insta::assert_snapshot!(screen_to_string(&screenshot1), @r###"
         ┌───────────────────────────────────────────────────┤ Commit graph ├───────────────────────────────────────────────────┐
         │:                                                                                                                     │
-        │% 62fc20d2 (manually hidden) (master) create test1.txt                                                                │
+        │% 62fc20d2 (manually hidden) (> master) create test1.txt                                                              │
         │                                                                                                                      │
         │                                                                                                                      │
         │                                                                                                                      │
@@ -640,7 +701,7 @@ fn test_undo_doesnt_make_working_dir_dirty() -> eyre::Result<()> {
         5. Delete branch foo at f777ecc9 create initial.txt
 
         Confirm? [yN] branchless: running command: <git-executable> checkout f777ecc9b0db5ed372b2615695191a8a17f79f24 --detach
-        @ f777ecc9 (foo) create initial.txt
+        @ f777ecc9 (> detached HEAD, foo) create initial.txt
         |
         O 62fc20d2 (bar, master) create test1.txt
         Applied 5 inverse events.
@@ -885,7 +946,7 @@ fn test_undo_noninteractive() -> eyre::Result<()> {
         :
         O 62fc20d2 create test1.txt
         |\
-        | % 96d1c37a (rewritten as 9ed8f9a2) create test2.txt
+        | % 96d1c37a (amended as 9ed8f9a2) (> detached HEAD) create test2.txt
         |
         O 9ed8f9a2 (master) bad message
         Applied 4 inverse events.
@@ -896,7 +957,365 @@ fn test_undo_noninteractive() -> eyre::Result<()> {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
         :
-        @ 96d1c37a (master) create test2.txt
+        @ 96d1c37a (> detached HEAD, master) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_redo() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["hide", "HEAD^^"])?;
+    git.run(&["hide", "HEAD^"])?;
+
+    let confirm = branchless::testing::GitRunOptions {
+        input: Some("y".to_string()),
+        ..Default::default()
+    };
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["redo"],
+            &branchless::testing::GitRunOptions {
+                expected_exit_code: 3,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        No undone operations to redo, exiting.
+        "###);
+    }
+
+    // Undo both hides.
+    git.run_with_options(&["undo"], &confirm)?;
+    git.run_with_options(&["undo"], &confirm)?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |
+        @ 70deb1e2 (> detached HEAD) create test3.txt
+        "###);
+    }
+
+    git.run_with_options(&["redo"], &confirm)?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        x 62fc20d2 (manually hidden) create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        |
+        @ 70deb1e2 (> detached HEAD) create test3.txt
+        "###);
+    }
+
+    git.run_with_options(&["redo"], &confirm)?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 (master) create initial.txt
+        |
+        x 62fc20d2 (manually hidden) create test1.txt
+        |
+        x 96d1c37a (manually hidden) create test2.txt
+        |
+        @ 70deb1e2 (> detached HEAD) create test3.txt
+        "###);
+    }
+
+    // A new operation resets the redo history.
+    git.run_with_options(&["undo"], &confirm)?;
+    git.run(&["hide", "HEAD"])?;
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["redo"],
+            &branchless::testing::GitRunOptions {
+                expected_exit_code: 3,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        No undone operations to redo, exiting.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_undo_preview() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["hide", "HEAD^"])?;
+    git.run(&["branch", "bar", "HEAD^"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["undo", "--preview"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Undoing would:
+        - Delete branch bar at 62fc20d2 create test1.txt
+        Before:
+        :
+        X 62fc20d2 (manually hidden) (bar, master) create test1.txt
+        |
+        @ 96d1c37a (> foo) create test2.txt
+        After:
+        :
+        X 62fc20d2 (manually hidden) (master) create test1.txt
+        |
+        @ 96d1c37a (foo) create test2.txt
+        Preview only; no changes were made to the repository.
+        "###);
+    }
+
+    // Nothing should have changed.
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        X 62fc20d2 (manually hidden) (bar, master) create test1.txt
+        |
+        @ 96d1c37a (> foo) create test2.txt
+        "###);
+    }
+
+    git.run_with_options(
+        &["undo"],
+        &branchless::testing::GitRunOptions {
+            input: Some("y".to_string()),
+            ..Default::default()
+        },
+    )?;
+    {
+        let (stdout, _stderr) = git.run(&["undo", "--dry-run"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Undoing would:
+        - Unhide commit 62fc20d2 create test1.txt
+        Before:
+        :
+        X 62fc20d2 (manually hidden) (master) create test1.txt
+        |
+        @ 96d1c37a (> foo) create test2.txt
+        After:
+        :
+        O 62fc20d2 (master) create test1.txt
+        |
+        @ 96d1c37a (foo) create test2.txt
+        Preview only; no changes were made to the repository.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_undo_preview_on_branch() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["branch", "foo"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["undo", "--preview"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Undoing would:
+        - Delete branch foo at 62fc20d2 create test1.txt
+        Before:
+        :
+        @ 62fc20d2 (> master, foo) create test1.txt
+        After:
+        :
+        @ 62fc20d2 (master) create test1.txt
+        Preview only; no changes were made to the repository.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_undo_snapshot() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.write_file("test1", "modified contents\n")?;
+    git.run(&["branchless", "snapshot"])?;
+
+    {
+        let screenshot1 = Default::default();
+        run_select_past_event(
+            &git.get_repo()?,
+            vec![
+                CursiveTestingEvent::TakeScreenshot(Rc::clone(&screenshot1)),
+                CursiveTestingEvent::Event('q'.into()),
+            ],
+        )?;
+        insta::assert_snapshot!(screen_to_string(&screenshot1), @r###"
+        ┌───────────────────────────────────────────────────┤ Commit graph ├───────────────────────────────────────────────────┐
+        │:                                                                                                                     │
+        │@ 62fc20d2 (> master) create test1.txt                                                                                │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
+        │Repo after transaction 3 (event 4). Press 'h' for help, 'q' to quit.                                                  │
+        │1. Snapshot working copy as 164f6a64 branchless: snapshot                                                             │
+        │   (restore with: git branchless restore 164f6a64e77a4edd371fccd2c50f42fea05b0cf9)                                    │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_undo_only_branch() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["branch", "foo"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "bar"])?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&[
+        "move",
+        "-s",
+        &test1_oid.to_string(),
+        "-d",
+        "master",
+        "--in-memory",
+    ])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 98b9119d (> master) create test3.txt
+        |
+        o 4b9ce31b (foo) create test1.txt
+        |
+        o 9f77bc5f (bar) create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["undo", "--only", "master"],
+            &branchless::testing::GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Cannot undo changes to branch master with --only while it is checked out. Check out a different commit first.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["undo", "--only", "foo"],
+            &branchless::testing::GitRunOptions {
+                input: Some("y".to_string()),
+                ..Default::default()
+            },
+        )?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Skipping 4 inverse events which don't affect branch foo.
+        Will apply these actions:
+        1. Move branch foo from 4b9ce31b create test1.txt
+                             to 62fc20d2 create test1.txt
+        Confirm? [yN] Applied 1 inverse event.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9 create initial.txt
+        |\
+        | x 62fc20d2 (rebased as 4b9ce31b) (foo) create test1.txt
+        |
+        @ 98b9119d (> master) create test3.txt
+        |
+        o 4b9ce31b create test1.txt
+        |
+        o 9f77bc5f (bar) create test2.txt
+        "###);
+    }
+
+    // The partial undo is itself an operation, which can be undone in full.
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["undo"],
+            &branchless::testing::GitRunOptions {
+                input: Some("y".to_string()),
+                ..Default::default()
+            },
+        )?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Move branch foo from 62fc20d2 create test1.txt
+                             to 4b9ce31b create test1.txt
+        Confirm? [yN] Applied 1 inverse event.
         "###);
     }
 
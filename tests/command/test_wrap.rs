@@ -331,3 +331,106 @@ fn test_wrap_exit_code() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[cfg(unix)]
+#[test]
+fn test_wrap_exit_code_signal() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run_with_options(
+        &[
+            "branchless",
+            "wrap",
+            "--git-executable",
+            "sh",
+            "--",
+            "-c",
+            "kill -TERM $$",
+        ],
+        &GitRunOptions {
+            expected_exit_code: 143,
+            ..Default::default()
+        },
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_wrap_records_ref_updates_without_hooks() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    // Simulate a command which bypasses our hooks.
+    git.run(&["config", "core.hooksPath", "/dev/null"])?;
+    git.run(&["branchless", "wrap", "reset", "--hard", "HEAD^"])?;
+    git.run(&["config", "--unset", "core.hooksPath"])?;
+
+    {
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let events = event_log_db.get_events()?;
+        let event_tx_id = events.last().unwrap().get_event_tx_id();
+        let events: Vec<Event> = event_log_db
+            .get_events_for_transaction(event_tx_id)?
+            .into_iter()
+            .map(redact_event_timestamp)
+            .collect();
+        insta::assert_debug_snapshot!(events, @r###"
+        [
+            RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id: EventTransactionId(
+                    5,
+                ),
+                ref_name: "HEAD",
+                old_oid: 96d1c37a3d4363611c49f7e52186e189a04c531f,
+                new_oid: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+                message: None,
+            },
+            RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id: EventTransactionId(
+                    5,
+                ),
+                ref_name: "refs/heads/master",
+                old_oid: 96d1c37a3d4363611c49f7e52186e189a04c531f,
+                new_oid: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+                message: None,
+            },
+        ]
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 62fc20d2 (> master) create test1.txt
+        |
+        o 96d1c37a create test2.txt
+        "###);
+    }
+
+    git.run_with_options(
+        &["undo"],
+        &GitRunOptions {
+            input: Some("y".to_string()),
+            ..Default::default()
+        },
+    )?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 96d1c37a (> detached HEAD, master) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
@@ -122,6 +122,7 @@ fn test_git_v2_31_events() -> eyre::Result<()> {
                 9,
             ),
             commit_oid: NonZeroOid(62fc20d2a290daea0d52bdc2ed2ad4be6491010e),
+            message: None,
         },
         RefUpdateEvent {
             timestamp: 0.0,
@@ -22,10 +22,10 @@ fn test_gc() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
         insta::assert_snapshot!(stdout, @r###"
-@ f777ecc9 (master) create initial.txt
-|
-o 62fc20d2 create test1.txt
-"###);
+        @ f777ecc9 (> detached HEAD, master) create initial.txt
+        |
+        o 62fc20d2 create test1.txt
+        "###);
     }
 
     git.run(&["hide", "62fc20d2"])?;
@@ -40,9 +40,8 @@ o 62fc20d2 create test1.txt
     git.run(&["gc", "--prune=now"])?;
     {
         let (stdout, _stderr) = git.run(&["smartlog"])?;
-        insta::assert_snapshot!(stdout, @r###"
-@ f777ecc9 (master) create initial.txt
-"###);
+        insta::assert_snapshot!(stdout, @"@ f777ecc9 (> detached HEAD, master) create initial.txt
+");
     }
 
     {
@@ -210,3 +209,116 @@ fn test_gc_reference_transaction() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_gc_retention() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["hide", "HEAD^", "HEAD"])?;
+
+    git.run(&["config", "branchless.gc.retentionDays", "30"])?;
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "gc"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: collecting garbage
+        branchless: 0 dangling references deleted
+        branchless: 1 hidden commit kept (hidden within the last 30 days)
+        "###);
+    }
+
+    git.run(&["config", "branchless.gc.retentionDays", "0"])?;
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "gc", "--compact"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: collecting garbage
+        branchless: 1 dangling reference deleted
+        branchless: compacted event log
+        "###);
+    }
+
+    {
+        let repo = git.get_repo()?;
+        let references: Vec<String> = repo
+            .get_all_references()?
+            .into_iter()
+            .map(|reference| -> eyre::Result<String> {
+                Ok(reference.get_name()?.to_string_lossy().into_owned())
+            })
+            .collect::<eyre::Result<_>>()?;
+        insta::assert_debug_snapshot!(references.into_iter().sorted().collect_vec(), @r###"
+        [
+            "refs/branchless/96d1c37a3d4363611c49f7e52186e189a04c531f",
+            "refs/heads/master",
+        ]
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_gc_eventlog_retention() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["commit", "--amend", "-m", "amended test2"])?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["hide", "HEAD"])?;
+    git.run(&["checkout", "foo"])?;
+
+    let (smartlog_before, _stderr) = git.run(&["smartlog", "--hidden"])?;
+    let num_events_before = {
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        event_log_db.get_events()?.len()
+    };
+
+    git.run(&["config", "branchless.eventlog.maxEntries", "1"])?;
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "gc"])?;
+        let stdout = stdout
+            .lines()
+            .filter(|line| line.contains("event log"))
+            .join("\n");
+        assert!(
+            stdout.starts_with("branchless: ")
+                && stdout.ends_with(" old events pruned from event log"),
+            "unexpected output: {:?}",
+            stdout
+        );
+    }
+
+    {
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        assert!(event_log_db.get_events()?.len() < num_events_before);
+    }
+
+    // The currently-visible state of the repository is unaffected.
+    let (smartlog_after, _stderr) = git.run(&["smartlog", "--hidden"])?;
+    assert_eq!(smartlog_before, smartlog_after);
+    insta::assert_snapshot!(smartlog_after, @r###"
+    O f777ecc9 (master) create initial.txt
+    |
+    o 62fc20d2 create test1.txt
+    |\
+    | @ cb8137ad (> foo) amended test2
+    |\
+    | x 96d1c37a (amended as cb8137ad) create test2.txt
+    |
+    x 4838e49b (manually hidden) create test3.txt
+    "###);
+
+    Ok(())
+}
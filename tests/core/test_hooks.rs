@@ -3,7 +3,9 @@ use branchless::core::eventlog::testing::{get_event_replayer_events, redact_even
 use branchless::core::eventlog::{Event, EventLogDb, EventReplayer};
 use branchless::core::formatting::Glyphs;
 use branchless::git::GitVersion;
-use branchless::testing::make_git;
+use branchless::testing::{
+    make_git, make_git_with_remote_repo, GitInitOptions, GitWrapperWithRemoteRepo,
+};
 use branchless::util::get_sh;
 use eyre::{eyre, Context};
 use std::process::Command;
@@ -333,3 +335,207 @@ fn test_merge_commit_recorded() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_pre_commit_warns_on_pushed_main_branch() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    original_repo.init_repo()?;
+    original_repo.commit_file("test1", 1)?;
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+
+    {
+        cloned_repo.write_file("test2", "test2 contents\n")?;
+        cloned_repo.run(&["add", "."])?;
+        let (_stdout, stderr) = cloned_repo.run(&["commit", "-m", "create test2.txt"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: warning: you are committing directly on top of main branch "master",
+        branchless: which has already been pushed.
+        branchless: To use the branchless workflow instead, detach HEAD before committing:
+        branchless:     git checkout --detach
+        branchless: To disable this warning, run: git config branchless.hooks.preCommit false
+        branchless: processing 2 updates: branch master, ref HEAD
+        branchless: processed commit: 5b61b5a0 create test2.txt
+        "###);
+    }
+
+    {
+        // `master` is now ahead of `origin/master`, so the commit at `HEAD`
+        // hasn't been pushed yet.
+        cloned_repo.write_file("test3", "test3 contents\n")?;
+        cloned_repo.run(&["add", "."])?;
+        let (_stdout, stderr) = cloned_repo.run(&["commit", "-m", "create test3.txt"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: processing 2 updates: branch master, ref HEAD
+        branchless: processed commit: 663ba3bb create test3.txt
+        "###);
+    }
+
+    {
+        cloned_repo.run(&["reset", "--hard", "origin/master"])?;
+        cloned_repo.run(&["config", "branchless.hooks.preCommit", "false"])?;
+        cloned_repo.write_file("test4", "test4 contents\n")?;
+        cloned_repo.run(&["add", "."])?;
+        let (_stdout, stderr) = cloned_repo.run(&["commit", "-m", "create test4.txt"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: processing 2 updates: branch master, ref HEAD
+        branchless: processed commit: 5565621f create test4.txt
+        "###);
+    }
+
+    {
+        cloned_repo.run(&["config", "branchless.hooks.preCommit", "true"])?;
+        cloned_repo.run(&["checkout", "--detach", "origin/master"])?;
+        cloned_repo.write_file("test5", "test5 contents\n")?;
+        cloned_repo.run(&["add", "."])?;
+        let (_stdout, stderr) = cloned_repo.run(&["commit", "-m", "create test5.txt"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: processing 1 update: ref HEAD
+        branchless: processed commit: 3f0e08f2 create test5.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_fast_forward_not_recorded() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    {
+        let (_stdout, stderr) = git.run(&["merge", "--ff-only", "foo"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: processing 2 updates: branch master, ref HEAD
+        "###);
+    }
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+    let events: Vec<Event> = get_event_replayer_events(&event_replayer)
+        .iter()
+        .cloned()
+        .map(redact_event_timestamp)
+        .collect();
+    insta::assert_debug_snapshot!(events, @r###"
+    [
+        RefUpdateEvent {
+            timestamp: 0.0,
+            event_tx_id: EventTransactionId(
+                1,
+            ),
+            ref_name: "refs/heads/foo",
+            old_oid: 0000000000000000000000000000000000000000,
+            new_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+            message: None,
+        },
+        RefUpdateEvent {
+            timestamp: 0.0,
+            event_tx_id: EventTransactionId(
+                2,
+            ),
+            ref_name: "HEAD",
+            old_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+            new_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+            message: None,
+        },
+        RefUpdateEvent {
+            timestamp: 0.0,
+            event_tx_id: EventTransactionId(
+                3,
+            ),
+            ref_name: "HEAD",
+            old_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+            new_oid: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+            message: None,
+        },
+        RefUpdateEvent {
+            timestamp: 0.0,
+            event_tx_id: EventTransactionId(
+                3,
+            ),
+            ref_name: "refs/heads/foo",
+            old_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+            new_oid: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+            message: None,
+        },
+        CommitEvent {
+            timestamp: 0.0,
+            event_tx_id: EventTransactionId(
+                4,
+            ),
+            commit_oid: NonZeroOid(62fc20d2a290daea0d52bdc2ed2ad4be6491010e),
+        },
+        RefUpdateEvent {
+            timestamp: 0.0,
+            event_tx_id: EventTransactionId(
+                5,
+            ),
+            ref_name: "HEAD",
+            old_oid: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+            new_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+            message: None,
+        },
+        RefUpdateEvent {
+            timestamp: 0.0,
+            event_tx_id: EventTransactionId(
+                7,
+            ),
+            ref_name: "HEAD",
+            old_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+            new_oid: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+            message: None,
+        },
+        RefUpdateEvent {
+            timestamp: 0.0,
+            event_tx_id: EventTransactionId(
+                7,
+            ),
+            ref_name: "refs/heads/master",
+            old_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+            new_oid: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+            message: None,
+        },
+    ]
+    "###);
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_squash_not_recorded() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    {
+        let (_stdout, stderr) = git.run(&["merge", "--squash", "foo"])?;
+        insta::assert_snapshot!(stderr, @"");
+    }
+
+    Ok(())
+}
@@ -8,14 +8,24 @@ mod core {
 
 mod command {
     mod test_amend;
+    mod test_autosquash;
     mod test_bug_report;
+    mod test_diff;
+    mod test_fold;
     mod test_hide;
     mod test_init;
     mod test_move;
     mod test_navigation;
+    mod test_record;
+    mod test_repair;
     mod test_restack;
+    mod test_reword;
     mod test_smartlog;
+    mod test_snapshot;
+    mod test_split;
+    mod test_submit;
     mod test_sync;
+    mod test_test;
     mod test_undo;
     mod test_wrap;
 }
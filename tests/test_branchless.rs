@@ -38,7 +38,7 @@ fn test_commands() -> eyre::Result<()> {
         let (stdout, _stderr) = git.run(&["prev"])?;
         insta::assert_snapshot!(stdout, @r###"
         branchless: running command: <git-executable> checkout f777ecc9b0db5ed372b2615695191a8a17f79f24
-        @ f777ecc9 create initial.txt
+        @ f777ecc9 (> detached HEAD) create initial.txt
         |
         O 3df4b935 (master) create test.txt
         "###);
@@ -49,13 +49,37 @@ fn test_commands() -> eyre::Result<()> {
         insta::assert_snapshot!(stdout, @r###"
         branchless: running command: <git-executable> checkout 3df4b9355b3b072aa6c50c6249bf32e289b3a661
         :
-        @ 3df4b935 (master) create test.txt
+        @ 3df4b935 (> detached HEAD, master) create test.txt
         "###);
     }
 
     Ok(())
 }
 
+#[test]
+fn test_help_exit_codes() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    let (stdout, _stderr) = git.run(&["branchless", "-h"])?;
+    let exit_status = &stdout[stdout.find("EXIT STATUS:").unwrap()..];
+    insta::assert_snapshot!(exit_status, @r###"
+    EXIT STATUS:
+        0    The command succeeded.
+        1    The command couldn't be carried out as requested, such as because of an
+             invalid argument or an unknown revision, or because it was aborted.
+        2    The command stopped because of a merge conflict, which needs to be
+             resolved before trying again.
+        3    There was nothing for the command to do.
+        4    The command run by `git test` failed for at least one commit.
+
+    Commands which invoke Git may also exit with the exit code of the failed Git
+    invocation.
+    "###);
+
+    Ok(())
+}
+
 #[test]
 fn test_profiling() -> eyre::Result<()> {
     let git = make_git()?;